@@ -0,0 +1,16 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use hd_fpv_video_tool::osd::{dji::file::parse, file::sorted_frames::GetFramesExt};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(frames) = parse(data) {
+        // what is done with the frames before rendering them
+        frames.used_tile_indices();
+        frames.highest_video_frame_index();
+        for frame in frames.iter() {
+            frame.enumerate_tile_indices().for_each(drop);
+        }
+    }
+});
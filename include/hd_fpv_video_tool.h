@@ -0,0 +1,38 @@
+/* C interface to the OSD file parsing and overlay rendering of hd_fpv_video_tool, see src/ffi.rs */
+
+#ifndef HD_FPV_VIDEO_TOOL_H
+#define HD_FPV_VIDEO_TOOL_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct HdFpvOSDFile HdFpvOSDFile;
+typedef struct HdFpvOverlayRenderer HdFpvOverlayRenderer;
+
+/* message of the last error of the calling thread, NULL if there was none */
+const char *hd_fpv_last_error(void);
+
+/* OSD files, NULL or a negative value is returned on error */
+HdFpvOSDFile *hd_fpv_osd_file_open(const char *path);
+HdFpvOSDFile *hd_fpv_osd_file_parse(const uint8_t *data, size_t len);
+void hd_fpv_osd_file_free(HdFpvOSDFile *osd_file);
+int64_t hd_fpv_osd_file_frame_count(const HdFpvOSDFile *osd_file);
+int hd_fpv_osd_file_dimensions_tiles(const HdFpvOSDFile *osd_file, uint32_t *width, uint32_t *height);
+int64_t hd_fpv_osd_file_frame_index(const HdFpvOSDFile *osd_file, uint32_t frame_position);
+int64_t hd_fpv_osd_file_frame_tile_indices(const HdFpvOSDFile *osd_file, uint32_t frame_position, uint16_t *tile_indices, size_t capacity);
+
+/* overlay rendering, only with the overlay feature, NULL or a negative value is returned on error */
+HdFpvOverlayRenderer *hd_fpv_overlay_renderer_new(const HdFpvOSDFile *osd_file, const char *font_dir);
+void hd_fpv_overlay_renderer_free(HdFpvOverlayRenderer *renderer);
+int hd_fpv_overlay_renderer_frame_dimensions(const HdFpvOverlayRenderer *renderer, uint32_t *width, uint32_t *height);
+int hd_fpv_overlay_renderer_render(const HdFpvOverlayRenderer *renderer, uint32_t frame_position, uint8_t *buffer, size_t buffer_size);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
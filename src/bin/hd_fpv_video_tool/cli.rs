@@ -2,9 +2,13 @@ use std::path::PathBuf;
 
 use clap::{Parser, Subcommand};
 use hd_fpv_video_tool::prelude::*;
-use getset::CopyGetters;
+use getset::{CopyGetters, Getters};
+use hd_fpv_video_tool::{cli::font_options::{parse_tile_kind, FontOptions}, output_path::OutputTemplate, video::container::Container};
+#[cfg(feature = "hwaccel")]
+use hd_fpv_video_tool::video::hw_accel::Codec;
+use hd_fpv_osd_font_tool::prelude::tile;
 
-use crate::shell_autocompletion::*;
+use crate::{error_report::ErrorFormat, shell_autocompletion::*};
 
 /// hd_fpv_video_tool is a command line tool for manipulating video files and OSD files recorded with the DJI and Walksnail Avatar FPV systems
 ///
@@ -12,7 +16,7 @@ use crate::shell_autocompletion::*;
 ///
 /// Each command is aliased to the concatenation of the first letter of each word of the command{n}
 /// Example: the `generate-overlay-frames` command is aliased to `gof`
-#[derive(Parser, CopyGetters)]
+#[derive(Parser, CopyGetters, Getters)]
 #[clap(version, about, long_about)]
 pub struct Cli {
     #[clap(short, long, value_parser, default_value_t = LogLevel::Info)]
@@ -20,15 +24,159 @@ pub struct Cli {
     #[getset(get_copy = "pub")]
     log_level: LogLevel,
 
+    /// also write the logs along with the full output of the ffmpeg processes to this file, appending to it if it exists
+    ///
+    /// Only the last lines of the ffmpeg output are printed when ffmpeg fails, more of them with `--log-level debug`.
+    /// The log file keeps all of them along with the ffmpeg command lines to investigate a failed encode.
+    #[clap(long, value_parser, global = true, value_name = "path")]
+    #[getset(get = "pub")]
+    log_file: Option<PathBuf>,
+
+    /// path of the ffmpeg binary to use instead of the one found in the PATH
+    ///
+    /// It can also be set with the `ffmpeg_path` key of the configuration file `$XDG_CONFIG_HOME/hd_fpv_video_tool/config.json`
+    /// (`~/.config/hd_fpv_video_tool/config.json` by default). The binary is checked and its version logged before running the command.
+    #[clap(long, value_parser, global = true, value_name = "path")]
+    #[getset(get = "pub")]
+    ffmpeg_path: Option<PathBuf>,
+
+    /// path of the mpv binary to use instead of the one found in the PATH
+    ///
+    /// It can also be set with the `mpv_path` key of the configuration file. The binary is checked and its version logged before running the command.
+    #[clap(long, value_parser, global = true, value_name = "path")]
+    #[getset(get = "pub")]
+    mpv_path: Option<PathBuf>,
+
+    /// print the ffmpeg and mpv command lines and the planned overlay parameters instead of executing them
+    ///
+    /// No file is written in this mode. Useful to debug issues like a wrong scaling decision or to report bugs
+    /// with reproducible commands.
+    #[clap(long, value_parser, global = true)]
+    #[getset(get_copy = "pub")]
+    dry_run: bool,
+
+    /// do not use the cache of resized font tiles
+    ///
+    /// When the OSD is scaled the resized font tiles are cached in `$XDG_CACHE_HOME/hd_fpv_video_tool/resized_tiles`
+    /// (`~/.cache/hd_fpv_video_tool/resized_tiles` by default) so that the next runs with the same font and tile size skip resizing them.
+    #[clap(long, value_parser, global = true)]
+    #[getset(get_copy = "pub")]
+    no_tile_cache: bool,
+
+    /// only warn when the estimated size of an output exceeds the space available on its file system instead of refusing to start
+    ///
+    /// The sizes are estimated from the bitrate and duration of the videos or from the count and size of the overlay frames,
+    /// the actual size of a video encoded at constant quality can be much lower than the estimate.
+    #[clap(long, value_parser, global = true)]
+    #[getset(get_copy = "pub")]
+    ignore_disk_space: bool,
+
+    /// only look for the exact OSD file names in the directory of the video when auto-detecting OSD files
+    ///
+    /// By default the names are compared case-insensitively, the copy suffixes like ` (1)` or `-1` of the video file names are
+    /// ignored and the `osd` subdirectory of the video directory is searched as well.
+    #[clap(long, value_parser, global = true)]
+    #[getset(get_copy = "pub")]
+    no_osd_auto: bool,
+
+    /// limit the share of the time the spawned FFMpeg processes run to this percentage
+    ///
+    /// The processes are periodically stopped and continued so that a long encode can run alongside other work on the
+    /// machine. Independently of this option a running command can be paused by sending SIGUSR1 to its process and resumed
+    /// with SIGUSR2. Only supported on unix systems.
+    #[clap(long, value_parser = clap::value_parser!(u8).range(1..=100), global = true, value_name = "percent")]
+    #[getset(get_copy = "pub")]
+    max_cpu: Option<u8>,
+
+    /// ring the terminal bell when the command completes or fails and print a summary: duration, output files and average encoding speed
+    ///
+    /// A desktop notification is also shown when the tool is built with the `notifications` feature.
+    #[clap(long, value_parser, global = true)]
+    #[getset(get_copy = "pub")]
+    notify: bool,
+
+    /// directory in which the outputs are written when their path is not specified, instead of the directory of the input
+    ///
+    /// The directory must exist. The outputs specified on the command line are written where specified.
+    #[clap(long, value_parser, global = true, value_name = "path")]
+    #[getset(get = "pub")]
+    output_dir: Option<PathBuf>,
+
+    /// template of the file names of the outputs written when their path is not specified [default: {stem}_{suffix}.{ext}]
+    ///
+    /// `{stem}` is replaced with the file name of the input without extension, `{suffix}` with the suffix of the command
+    /// (`transcoded`, `with_osd`, `cut`, `fixed_audio`, `osd`, `calibration`) and `{ext}` with the extension of the output,
+    /// e.g. `{suffix}-{stem}.{ext}`
+    #[clap(long, value_parser, global = true, value_name = "template")]
+    #[getset(get = "pub")]
+    output_template: Option<OutputTemplate>,
+
+    /// format of the error reported when a command fails
+    ///
+    /// With the JSON format the error is printed on stderr as an object with the `kind`, `exit_code`, `message` and `context` fields.
+    /// The exit code depends on the kind of error: 1 other, 2 invalid command line, 3 invalid arguments, 4 input not found, 5 output exists,
+    /// 6 OSD file not found, 7 invalid OSD file, 8 invalid video file, 9 font error, 10 ffmpeg failed, 11 player failed, 12 I/O error
+    #[clap(long, value_parser, global = true, default_value_t = ErrorFormat::default())]
+    #[getset(get_copy = "pub")]
+    error_format: ErrorFormat,
+
     #[command(subcommand)]
     pub command: Commands,
 }
 
-#[derive(Subcommand)]
+#[derive(Subcommand, strum::IntoStaticStr)]
+#[strum(serialize_all = "kebab-case")]
 pub enum Commands {
     /// Display information about the specified OSD file
     #[clap(alias = "dofi")]
-    DisplayOSDFileInfo { osd_file: PathBuf },
+    DisplayOSDFileInfo {
+        /// also display the usage of the cells of the tile grid, the most used tile indices and the OSD items detected for the font variant
+        ///
+        /// Helps finding the regions and items to hide with --hide-regions and --hide-items and debugging layout mismatches
+        #[clap(long, value_parser)]
+        stats: bool,
+
+        osd_file: PathBuf,
+    },
+
+    /// Display information about a font: tile kind, tile dimensions and number of tiles
+    ///
+    /// The font is loaded from the font directory without falling back to other fonts, with the ident of --font-ident
+    /// or else the one of --font-variant, the generic font by default. Use --contact-sheet to check its glyphs.
+    #[clap(alias = "dfi")]
+    DisplayFontInfo {
+        #[clap(flatten)]
+        font_options: FontOptions,
+
+        /// kind of tiles to load: sd or hd
+        #[clap(long, value_parser = parse_tile_kind, default_value = "hd", value_name = "kind")]
+        tile_kind: tile::Kind,
+
+        /// write an image of all the tiles of the font labelled with their hexadecimal index to this PNG file
+        #[clap(long, value_parser, value_name = "PNG file path")]
+        contact_sheet: Option<PathBuf>,
+    },
+
+    /// List the videos found in directories along with their OSD file, OSD kind and duration
+    ///
+    /// The directories are scanned recursively for .mp4, .mov and .mkv files, hidden files and directories are skipped.
+    /// Each video is paired with the OSD file found in its directory with the names tried by the OSD file auto-detection of
+    /// `transcode-video`, compared case-insensitively: <video file stem>.osd, DJIG<number>.osd for the DJI recordings
+    /// and AvatarG<number>.osd for the Walksnail recordings.
+    #[clap(alias = "l")]
+    List {
+        /// only scan the specified directories, not their subdirectories
+        #[clap(long, value_parser)]
+        no_recursive: bool,
+
+        /// print the list as JSON
+        #[clap(long, value_parser)]
+        json: bool,
+
+        /// directories to scan
+        #[clap(default_value = ".")]
+        dirs: Vec<PathBuf>,
+    },
 
     /// Generate a transparent overlay frame sequence as PNG files from a .osd file
     ///
@@ -52,6 +200,28 @@ pub enum Commands {
         #[clap(flatten)]
         common_args: GenerateOverlayArgs,
 
+        /// how the frames repeating the previous OSD frame are written
+        #[clap(long, value_parser, default_value_t = FrameLinkMode::default())]
+        frame_link_mode: FrameLinkMode,
+
+        /// number of threads encoding and writing the frame files
+        ///
+        /// Rendering is not blocked by writing the files until this many threads are busy, increase this value when writing to slow storage (SD card, NAS)
+        #[clap(long, value_parser = clap::value_parser!(u16).range(1..=64), default_value_t = DEFAULT_FRAME_WRITER_COUNT as u16)]
+        frame_writers: u16,
+
+        /// image format of the frame files
+        ///
+        /// The 16-bit and OpenEXR formats are meant for compositing software, only PNG frames can be burnt with the --osd-frames-dir option of `transcode-video`
+        #[clap(long, value_parser, default_value_t = FrameImageFormat::default())]
+        image_format: FrameImageFormat,
+
+        /// alpha mode of the frame files [default: premultiplied for OpenEXR, straight for the other formats]
+        ///
+        /// PNG only supports straight alpha and OpenEXR only supports premultiplied alpha
+        #[clap(long, value_parser)]
+        alpha_mode: Option<FrameAlphaMode>,
+
         /// directory in which the OSD frames will be written
         output_dir: Option<PathBuf>,
     },
@@ -67,8 +237,11 @@ pub enum Commands {
     /// If neither of these options are specified no scaling will be used and the kind of tiles used will be
     /// the native kind of tiles corresponding to the kind of OSD layout read from the FPV.WTF .osd file.
     ///
-    /// VP8 or VP9 codecs can be selected with the --codec option. Files generated with the VP9 codec are smaller
+    /// VP8, VP9 or AV1 codecs can be selected with the --codec option. Files generated with the VP9 codec are smaller
     /// but also it is roughly twice as slow as encoding with the VP8 codec which is already unfortunately pretty slow.
+    /// AV1 encoders do not support transparency so with the AV1 codec the alpha channel is stored as a grayscale picture
+    /// under the OSD picture: the file is both smaller and faster to encode but it can only be played with the
+    /// `play-video-with-osd` command.
     ///
     /// Fonts are loaded either from the directory specified with the --font-dir option or
     /// from the directory found in the environment variable FONTS_DIR or
@@ -84,6 +257,23 @@ pub enum Commands {
         #[clap(short, long, default_value = "vp8")]
         codec: OverlayVideoCodec,
 
+        /// encoding quality/speed preset, sets the quality and speed options of the encoder of the codec
+        #[clap(long, value_parser)]
+        preset: Option<QualityPreset>,
+
+        /// number of threads rendering the overlay frames ahead while FFMpeg is encoding the previous ones
+        ///
+        /// 0 uses one thread per CPU, 1 renders the frames one at a time when FFMpeg requests them
+        #[clap(long, value_parser = clap::value_parser!(u16).range(0..=256), default_value_t = 0)]
+        render_threads: u16,
+
+        /// speed of the footage the overlay is made for, e.g. `0.25` for a slow motion export at 25% speed
+        ///
+        /// The OSD frame timeline is stretched to follow the footage, the OSD frames are repeated when it is slowed down.{n}
+        /// The start, end and frame shift are in the time of the original recording.
+        #[clap(long, value_parser, value_name = "factor")]
+        speed: Option<OSDSpeed>,
+
         /// path of the video file to generate
         video_file: Option<PathBuf>,
 
@@ -92,6 +282,45 @@ pub enum Commands {
         overwrite: bool,
     },
 
+    /// Generate a calibration overlay showing the OSD grid with the placement and scaling of the OSD overlay
+    ///
+    /// The tile grid of the OSD kind of the specified OSD file is rendered with the same settings as the ones used to render
+    /// its overlay: the cells are outlined and labelled with the coordinates used by --hide-regions, every tenth line is
+    /// highlighted, the edges of the grid are drawn in red with a magenta tile in each corner and its center is marked with
+    /// a green cross.
+    ///
+    /// Play the generated video over the footage, e.g. with `play-video-with-osd`, to check that the OSD would be scaled
+    /// and placed correctly before burning it. The overlay is written as a single PNG image if the output file has the .png
+    /// extension.
+    ///
+    /// The scaling options and --target-resolution/--target-video-file work the same as with `generate-overlay-video`.
+    #[clap(alias = "gco")]
+    GenerateCalibrationOverlay {
+        /// use the resolution from the specified video file to decide what kind of tiles (SD/HD) would best fit and also whether scaling should be used when in auto scaling mode
+        #[clap(short = 'v', long, group("target_resolution_group"), value_parser)]
+        target_video_file: Option<PathBuf>,
+
+        #[clap(flatten)]
+        scaling_args: ScalingArgs,
+
+        #[clap(short, long, default_value = "vp8")]
+        codec: OverlayVideoCodec,
+
+        /// duration of the calibration video
+        #[clap(short, long, value_parser = clap::value_parser!(u32).range(1..=3600), default_value_t = 10, value_name = "seconds")]
+        duration: u32,
+
+        /// OSD file of which the OSD kind is used
+        osd_file: PathBuf,
+
+        /// path of the .webm video or .png image file to generate [default: <OSD file stem>_calibration.webm]
+        output_file: Option<PathBuf>,
+
+        /// overwrite output file if it exists
+        #[clap(short = 'y', long, value_parser)]
+        overwrite: bool,
+    },
+
     /// Cut a video file without transcoding by specifying the desired start and/or end timestamp
     ///
     /// Note that without transcoding videos can only be cut at the nearest P-frame so the cuts may not
@@ -101,6 +330,28 @@ pub enum Commands {
         #[clap(flatten)]
         start_end: StartEndArgs,
 
+        /// cut the video to the part covered by the OSD recording, e.g. to drop the end of the recording after disarming
+        ///
+        /// The start and end are set to the first and last frames of the OSD file, rounded outwards to the second.
+        #[clap(long, value_parser, conflicts_with_all = ["start", "end"])]
+        trim_to_osd: bool,
+
+        /// OSD file used with --trim-to-osd, defaults to the OSD file associated with the video file
+        ///
+        /// The OSD file is searched the same way as with the --osd option of the `transcode-video` command.
+        #[clap(long, value_parser, value_name = "OSD file path", requires = "trim_to_osd")]
+        osd_file: Option<PathBuf>,
+
+        /// do not copy the metadata and the modification time of the input video to the output video
+        #[clap(long, value_parser)]
+        no_preserve_metadata: bool,
+
+        /// container format of the output video: by default the one of the input video
+        ///
+        /// With a container the output file can have another extension than the input video
+        #[clap(long, value_parser)]
+        container: Option<Container>,
+
         /// input video file path
         input_video_file: PathBuf,
 
@@ -112,6 +363,20 @@ pub enum Commands {
         overwrite: bool,
     },
 
+    /// Split a long recording into one video file per flight by detecting the arm/disarm events on the OSD
+    ///
+    /// A flight is detected between the OSD frames on which the `DISARMED` warning disappears and reappears.
+    /// Each flight is cut without transcoding into a file named after the input video file with the flight number
+    /// and duration appended, e.g. `DJIG0001_flight02_3m25s.mp4`.
+    ///
+    /// Like with the `cut-video` command the cuts are made at the nearest P-frame and the timestamps are rounded
+    /// to the second, use --padding to keep some time before arming and after disarming.
+    #[clap(alias = "as")]
+    AutoSplit {
+        #[clap(flatten)]
+        args: AutoSplitArgs,
+    },
+
     /// Fix a DJI Air Unit video's audio sync and/or volume
     ///
     /// If the output video file is not provided the output video will be written in the same directory
@@ -129,6 +394,34 @@ pub enum Commands {
         #[clap(short, long, value_parser)]
         volume: bool,
 
+        /// compute the volume gain from an analysis of the audio loudness instead of using a fixed 20x gain
+        ///
+        /// The gain brings the integrated loudness to -14 LUFS while keeping the true peak below -1 dBFS
+        #[clap(short, long, value_parser, conflicts_with = "sync")]
+        auto_gain: bool,
+
+        /// tempo factor applied to the audio to fix its sync instead of the factor of the sync model matching the video
+        ///
+        /// The DJI FPV Air Unit and Caddx Vista recordings need a 1.00148 factor
+        #[clap(long, value_parser, value_name = "factor", conflicts_with_all = ["volume", "estimate_sync_factor"])]
+        sync_factor: Option<AudioSyncFactor>,
+
+        /// estimate the sync factor from the durations of the audio and video streams, for videos of at least 1 minute
+        ///
+        /// Falls back to the factor of the sync model matching the video when the estimation fails
+        #[clap(long, value_parser, conflicts_with = "volume")]
+        estimate_sync_factor: bool,
+
+        /// do not copy the metadata and the modification time of the input video to the output video
+        #[clap(long, value_parser)]
+        no_preserve_metadata: bool,
+
+        /// container format of the output video: by default the one of the input video
+        ///
+        /// With a container the output file can have another extension than the input video
+        #[clap(long, value_parser)]
+        container: Option<Container>,
+
         /// input video file path
         input_video_file: PathBuf,
 
@@ -140,6 +433,14 @@ pub enum Commands {
         overwrite: bool,
     },
 
+    /// Analyze the audio levels of a video file: peak, mean volume, loudness (LUFS) and clipping
+    ///
+    /// Also reports the volume gain the `fix-video-audio --auto-gain` command would apply.
+    AnalyzeAudio {
+        /// video or audio file path
+        input_file: PathBuf,
+    },
+
     /// Transcode a video file, optionally burning the OSD onto it
     ///
     /// Fonts are loaded either from the directory specified with the --font-dir option or
@@ -160,11 +461,319 @@ pub enum Commands {
     ///
     /// If the <OSD_VIDEO_FILE> argument is not provided it will try to use the file with the same base name
     /// as the <VIDEO_FILE> argument with suffix `_osd` and with `webm` extension.
+    ///
+    /// The OSD can also be rendered live from the OSD file with the --osd or --osd-file options, without having to
+    /// generate an OSD overlay video first. Seeking is not possible in this mode.
     #[clap(alias = "pvwo")]
     PlayVideoWithOSD {
+        #[clap(flatten)]
+        args: PlayWithOSDArgs,
+    },
+
+    /// Generate a contact sheet image with thumbnails of frames evenly spread over a video
+    ///
+    /// The thumbnails are arranged in a grid whose size is specified with the --grid option, useful for quickly
+    /// identifying flights in a folder. The OSD can be burned onto each thumbnail with the --osd or --osd-file options.
+    #[clap(alias = "gt")]
+    GenerateThumbnails {
+        #[clap(flatten)]
+        args: GenerateThumbnailsArgs,
+    },
+
+    /// Extract specific frames or one frame every N frames of a video as PNG or JPEG images
+    ///
+    /// The frames are specified by their index with --frames or extracted at regular intervals with --every. The image files are named
+    /// after their video frame index. The OSD can be composited onto each frame with the --osd or --osd-file options, the OSD frame
+    /// being the one displayed on the video frame when transcoding the video with the OSD burned in.
+    #[clap(alias = "ef")]
+    ExtractFrames {
+        #[clap(flatten)]
+        args: ExtractFramesArgs,
+    },
+
+    /// Render two videos side by side or one above the other, e.g. to compare two quads or two encoder settings
+    ///
+    /// The videos are scaled to a common height with the side-by-side layout or to a common width with the top-bottom layout.
+    /// An OSD overlay video generated with the `generate-overlay-video` command can be overlaid onto each video
+    /// with the --first-osd-video and --second-osd-video options. The audio of the first video is kept.
+    ///
+    /// If the output video file is not provided it is written next to the first video with the base names of both videos joined by `_vs_`.
+    #[clap(alias = "cmv")]
+    CompareVideos {
+        #[clap(flatten)]
+        args: CompareVideosArgs,
+    },
+
+    /// Overlay a second video as a picture-in-picture window over a main video
+    ///
+    /// The second video (e.g. HD footage from a GoPro) is scaled to a percentage of the main video width with --pip-size
+    /// and placed in a corner of the main video with --corner and --margin, optionally with a border.
+    /// The audio of the main video is kept and the main video keeps playing once the second video ends.
+    ///
+    /// If the output video file is not provided it is written next to the main video with suffix `_pip`.
+    Pip {
+        #[clap(flatten)]
+        args: PipArgs,
+    },
+
+    /// Export which OSD frame is displayed on each video frame as CSV or JSON
+    ///
+    /// For each video frame the table contains the index of the video frame, the index of the displayed OSD frame
+    /// and the timestamp of the displayed OSD frame in seconds. The mapping is computed with the same OSD frame shift
+    /// as the one used when burning the OSD with the `transcode-video` command so that external tools
+    /// (e.g. blackbox log synchronization scripts) can align their data exactly the same way.
+    ///
+    /// If the OSD file is not provided it is searched the same way as with the --osd option of `transcode-video`.
+    #[clap(alias = "eot")]
+    ExportOSDTimestamps {
+        /// path to FPV.WTF .osd file
+        #[clap(long, value_parser, value_name = "OSD file path")]
+        osd_file: Option<PathBuf>,
+
+        /// shift frames to sync OSD with video: OSD frames (60Hz) like -37 or -37f, seconds like +1.5s or a timestamp like -00:00:02.25
+        #[clap(short = 'o', long, value_parser, allow_hyphen_values(true), value_name = "shift")]
+        osd_frame_shift: Option<OSDFrameShift>,
+
+        /// position of the start of the OSD recording in the video, like with `transcode-video`
+        ///
+        /// Either in seconds with an optional `s` suffix or in OSD frames (60Hz) with an `f` suffix, fractional values are accepted.
+        #[clap(long, value_parser, allow_negative_numbers(true), value_name = "offset", conflicts_with = "osd_frame_shift")]
+        osd_video_offset: Option<OSDVideoOffset>,
+
+        #[clap(flatten)]
+        start_end: StartEndArgs,
+
+        /// output format
+        #[clap(short, long, value_parser, default_value_t = OSDFrameMappingExportFormat::Csv)]
+        format: OSDFrameMappingExportFormat,
+
+        /// video file path
+        video_file: PathBuf,
+
+        /// output file path, the table is written to stdout if not provided
+        output_file: Option<PathBuf>,
+    },
+
+    /// Export the telemetry displayed on the OSD as CSV or as a GPX track
+    ///
+    /// The GPS coordinates, altitude and speed are decoded from the OSD tiles so this only works with the font variants
+    /// for which the location of these OSD items is known (INAV and Ardupilot) and only if they are displayed on the OSD.
+    /// Altitudes are converted to meters and speeds to meters per second.
+    ///
+    /// The CSV table contains one line per OSD frame on which at least one value could be decoded with the timestamp
+    /// of the frame in seconds from the start of the recording. The GPX track is not timestamped since the OSD file
+    /// does not contain the date of the recording.
+    #[cfg(feature = "telemetry")]
+    #[clap(alias = "et")]
+    ExportTelemetry {
+        /// output format
+        #[clap(short, long, value_parser, default_value_t = TelemetryExportFormat::Csv)]
+        format: TelemetryExportFormat,
+
+        /// path to FPV.WTF .osd file
+        osd_file: PathBuf,
+
+        /// output file path, the telemetry is written to stdout if not provided
+        output_file: Option<PathBuf>,
+    },
+
+    /// Export the frames of an OSD file as JSON or CBOR
+    ///
+    /// Each OSD frame is written with the index of the video frame at which it is displayed and the grid of the
+    /// indices of the tiles it displays, `tiles[y][x]` being the tile displayed at column `x` of row `y` and 0 meaning
+    /// no tile. The document also contains the OSD layout and font variant of the file. This allows other tools to
+    /// render or analyze the OSD without parsing the binary DJI or WSA OSD file formats.
+    #[clap(alias = "eof")]
+    ExportOSDFrames {
+        /// output format
+        #[clap(short, long, value_parser, default_value_t = OSDFramesExportFormat::Json)]
+        format: OSDFramesExportFormat,
+
+        /// path to FPV.WTF .osd file
+        osd_file: PathBuf,
+
+        /// output file path, the document is written to stdout if not provided
+        output_file: Option<PathBuf>,
+    },
+
+    /// Write a DJI OSD file from a JSON or CBOR document exported with the `export-osd-frames` command
+    ///
+    /// This allows editing the content of an OSD file, e.g. to hide the GPS coordinates or to fix corrupt frames,
+    /// and regenerating the binary OSD file. The format of the document is detected from its content.
+    /// The OSD file is written with the DJI format version 1 so the WSA OSD layout is not supported.
+    #[clap(alias = "iof")]
+    ImportOSDFile {
+        /// overwrite output file if it exists
+        #[clap(short = 'y', long, value_parser)]
+        overwrite: bool,
+
+        /// JSON or CBOR document in the format written by the `export-osd-frames` command
+        document_file: PathBuf,
+
+        /// path of the OSD file to write
+        osd_file: PathBuf,
+    },
+
+    /// Write a cleaned copy of a DJI OSD file damaged by a crash or a corrupted recording
+    ///
+    /// The incomplete frame at the end of a truncated file is discarded, the frames using tile indices which no font
+    /// can have and the frames whose index jumps too far are dropped. The frame indices are checked against the longest
+    /// run of frames following each other so that a corrupted first frame does not get the other frames dropped. The
+    /// frames whose index is out of order are dropped unless `--renumber-duplicates` is used.
+    #[clap(alias = "rof")]
+    RepairOSDFile {
+        /// give the duplicate frames the index following the index of the previous frame instead of dropping them
+        #[clap(short, long, value_parser)]
+        renumber_duplicates: bool,
+
+        /// maximum difference between the indices of consecutive frames, the frames jumping further are dropped
+        #[clap(long, value_parser, default_value_t = 3600, value_name = "frames")]
+        max_frame_gap: u32,
+
+        /// overwrite output file if it exists
+        #[clap(short = 'y', long, value_parser)]
+        overwrite: bool,
+
+        /// path to FPV.WTF .osd file
+        osd_file: PathBuf,
+
+        /// output OSD file path, defaults to <OSD_FILE basename>_repaired.osd
+        output_file: Option<PathBuf>,
+    },
+
+    /// Merge the OSD files of consecutive recordings into one OSD file matching the concatenated videos
+    ///
+    /// The frame indices of each OSD file are shifted by the duration of the videos preceding it so that the merged
+    /// file can be paired with the video spliced from the recordings. The durations are either probed from the video
+    /// files, given in the same order as the OSD files, or provided in seconds with `--durations`.
+    #[clap(alias = "mof")]
+    MergeOSDFiles {
+        /// video file recorded with each OSD file, in the same order, the last one can be omitted
+        #[clap(short, long = "video-file", value_parser, value_name = "video file", conflicts_with = "durations")]
+        video_files: Vec<PathBuf>,
+
+        /// comma separated durations in seconds of the videos recorded with each OSD file, the last one can be omitted
+        #[clap(short, long, value_parser = parse_video_duration, value_delimiter = ',', value_name = "seconds", required_unless_present = "video_files")]
+        durations: Vec<f64>,
+
+        /// overwrite output file if it exists
+        #[clap(short = 'y', long, value_parser)]
+        overwrite: bool,
+
+        /// path of the merged OSD file to write
+        #[clap(short, long, value_parser)]
+        output_file: PathBuf,
+
+        /// paths of the FPV.WTF .osd files to merge, in recording order
+        #[clap(required = true, num_args = 2..)]
+        osd_files: Vec<PathBuf>,
+    },
+
+    /// Package the videos of a flying session with their OSD overlay videos into MKV files
+    ///
+    /// Each video is muxed with its OSD overlay video into an MKV file in which the OSD overlay is a secondary
+    /// video track so that the original video is not modified. A `session.m3u` playlist listing all the flights
+    /// is written next to the MKV files for browsing the session with media center software.
+    ///
+    /// The OSD overlay video of each video file is expected to have the same base name as the video file
+    /// with suffix `_osd` and with `webm` extension, like the ones generated by the `generate-overlay-video` command.
+    #[clap(alias = "ps")]
+    PackageSession {
+        /// directory in which the MKV files and the playlist are written
+        #[clap(short, long, value_parser, default_value = ".")]
+        output_dir: PathBuf,
+
+        /// overwrite output files if they exist
+        #[clap(short = 'y', long, value_parser)]
+        overwrite: bool,
+
+        /// video files of the session
+        #[clap(required = true)]
+        video_files: Vec<PathBuf>,
+    },
+
+    /// Transcode a video into an HLS ladder for self-hosted streaming
+    ///
+    /// One H.264/AAC rendition is encoded per requested height in a single pass, each in its own sub-directory named after its
+    /// height, e.g. `720p/index.m3u8`, along with a `master.m3u8` playlist referencing them that can be served by any web server.
+    /// The OSD overlay video generated with the `generate-overlay-video` command can be overlaid onto the video at the same time.
+    #[clap(alias = "ew")]
+    ExportWeb {
+        #[clap(flatten)]
+        args: ExportWebArgs,
+    },
+
+    /// Make a short looped GIF or animated WebP clip from a part of a video
+    ///
+    /// The part of the video is selected with `--start` and `--end`. The OSD overlay video generated with the `generate-overlay-video`
+    /// command can be burned into the clip. GIF clips use a palette generated from the clip itself to get the best out of their 256 colors.
+    #[clap(alias = "mc")]
+    MakeClip {
+        #[clap(flatten)]
+        args: MakeClipArgs,
+    },
+
+    /// Check the environment: FFMpeg and MPV availability and versions, encoders and fonts
+    ///
+    /// Each problem found is printed with the steps to fix it. The command fails if a check failed,
+    /// warnings are printed for the missing components which are only needed by some commands or options.
+    #[clap(alias = "d")]
+    Doctor {
+        /// path to the directory containing font sets
+        #[clap(short, long, value_parser, value_name = "dirpath")]
+        font_dir: Option<PathBuf>,
+    },
+
+    /// Measure the encoding speed of the available video encoders and save the fastest one as the default encoder
+    ///
+    /// A generated 1080p60 test video is encoded with the software encoder and the available hardware encoders of each codec
+    /// using the default quality settings of `transcode-video`. The speed and output size of each encoder are printed and the
+    /// fastest encoder of --codec is written to the configuration file, `transcode-video` uses it when --video-encoder is not specified.
+    #[cfg(feature = "hwaccel")]
+    #[clap(alias = "b")]
+    Benchmark {
+        /// codec of the recommended encoder
+        #[clap(short, long, value_parser, default_value = "hevc")]
+        codec: Codec,
+
+        /// duration of the test video in seconds
+        #[clap(short, long, value_parser = clap::value_parser!(u32).range(1..), default_value_t = 10, value_name = "seconds")]
+        duration: u32,
+
+        /// only print the results, do not write the recommended encoder to the configuration file
+        #[clap(long, value_parser)]
+        no_save: bool,
+    },
+
+    /// Find the dead and stuck pixels of the camera and print them as a --remove-video-defects argument
+    ///
+    /// Frames evenly spread over the video are compared: the pixels standing out from their neighbors in nearly all of them
+    /// are defects. The video should be moving, e.g. a flight, for the scene not to be mistaken for defects.
+    #[clap(alias = "ad")]
+    AnalyzeDefects {
+        /// number of frames sampled from the video
+        #[clap(short, long, value_parser = clap::value_parser!(u32).range(video::defect_analysis::MIN_SAMPLE_COUNT as i64..), default_value_t = 50)]
+        samples: u32,
+
+        /// write the regions to the configuration file, `transcode-video` removes them when --remove-video-defects is not specified
+        #[clap(long, value_parser)]
+        save: bool,
+
         video_file: PathBuf,
+    },
 
-        osd_video_file: Option<PathBuf>,
+    /// Queue commands to run them one after the other later, e.g. to run several long transcodes overnight
+    ///
+    /// The queue is stored in a state file so that it survives the end of the session, by default in
+    /// `$XDG_STATE_HOME/hd_fpv_video_tool/queue` or `~/.local/state/hd_fpv_video_tool/queue`.
+    #[clap(alias = "q")]
+    Queue {
+        /// path of the queue state file
+        #[clap(long, value_parser, value_name = "file path")]
+        queue_file: Option<PathBuf>,
+
+        #[command(subcommand)]
+        command: QueueCommands,
     },
 
     #[clap(hide(true))]
@@ -176,3 +785,62 @@ pub enum Commands {
     #[clap(hide(true))]
     GenerateManPages,
 }
+
+#[derive(Subcommand)]
+pub enum QueueCommands {
+    /// Add a command to the queue, e.g. `queue add transcode-video --osd DJIG0000.mp4`
+    ///
+    /// The command is checked before being added to the queue, it is run from the current directory.
+    Add {
+        /// command with its arguments
+        #[clap(required = true, trailing_var_arg = true, allow_hyphen_values = true, value_name = "COMMAND")]
+        args: Vec<String>,
+    },
+
+    /// List the jobs of the queue with their status
+    List,
+
+    /// Run the pending jobs one after the other, also resumes a paused queue
+    Run {
+        /// number of times a failed job is retried
+        #[clap(long, value_parser, default_value_t = 0)]
+        retries: u32,
+
+        /// shell command run before each job, the job is marked as failed without being run if it fails
+        ///
+        /// Overrides the `pre_job` hook of the configuration file. The hooks are run from the working directory of the job with
+        /// the HD_FPV_JOB_ID, HD_FPV_JOB_STATUS, HD_FPV_JOB_ATTEMPTS, HD_FPV_JOB_WORKING_DIR and HD_FPV_JOB_COMMAND environment variables set.
+        #[clap(long, value_parser, value_name = "command")]
+        pre_job_hook: Option<String>,
+
+        /// shell command run after each job, HD_FPV_JOB_STATUS being `done` or `failed`
+        ///
+        /// Overrides the `post_job` hook of the configuration file, e.g. to upload the output or move the input video to an archive directory.
+        #[clap(long, value_parser, value_name = "command")]
+        post_job_hook: Option<String>,
+    },
+
+    /// Stop the running queue once the current job is done
+    Pause,
+
+    /// Remove jobs from the queue
+    Remove {
+        /// ids of the jobs to remove
+        #[clap(required = true)]
+        ids: Vec<u32>,
+    },
+
+    /// Remove the finished jobs from the queue
+    Clear {
+        /// also remove the pending jobs
+        #[clap(long, value_parser)]
+        all: bool,
+    },
+}
+
+// the durations shift the frame indices of the merged OSD files so they must be actual durations
+fn parse_video_duration(value: &str) -> Result<f64, String> {
+    let duration = value.trim().parse::<f64>().map_err(|error| error.to_string())?;
+    if ! duration.is_finite() || duration <= 0.0 { return Err("the duration must be a number of seconds greater than 0".to_owned()) }
+    Ok(duration)
+}
@@ -0,0 +1,628 @@
+use std::error::Error as StdError;
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use hd_fpv_video_tool::{
+    cli::{
+        font_options::OSDFontDirError,
+        start_end_args::StartGreaterThanEndError,
+        transcode_video_args::{OutputVideoFileError, RequestedOSDButNoFileProvidedNorFound},
+    },
+    config::ConfigError,
+    create_path::CreatePathError,
+    ffmpeg,
+    file::TouchError,
+    image::{ReadError as ImageReadError, WriteError as ImageWriteError},
+    osd::{
+        self,
+        overlay::{DrawFrameOverlayError, GenerateOverlayVideoError, SaveFramesToDirError, SendFramesToFFMpegError, scaling::ScalingArgsError,
+            frame_image_format::UnsupportedAlphaMode, calibration::GenerateCalibrationOverlayError},
+        region::InvalidRegionError,
+        frames_document::{ExportOSDFramesError, ImportOSDFileError},
+        repair::RepairOSDFileError,
+        concat::{ConcatOSDFramesError, MergeOSDFilesError},
+        dji::file::WriteError as DJIWriteError,
+        tile_indices::UnknownOSDItem,
+        font_info::FontInfoError,
+    },
+    job_queue::JobQueueError,
+    process::ProgramNotWorking,
+    cancellation::Cancelled as CancelledError,
+    removable_media::StageError,
+    disk_space::InsufficientDiskSpaceError,
+    video::{
+        self,
+        audio_analysis::AnalyzeAudioError,
+        audio_sync::AudioSyncError,
+        auto_split::AutoSplitError,
+        clip::MakeClipError,
+        continuation::JoinRecordingError,
+        osd_video::OSDVideoError,
+        defect_analysis::DefectAnalysisError,
+        discovery::DiscoveryError,
+        compare::CompareVideosError,
+        trim_to_osd::TrimToOSDError,
+        frame_extraction::ExtractFramesError,
+        pip::PipError,
+        package::PackageSessionError,
+        web_export::ExportWebError,
+        thumbnails::GenerateThumbnailsError,
+        CutVideoError, ExportOSDFrameMappingError, FixVideoFileAudioError, PlayWithOSDError, TranscodeVideoError, TranscodeWithOSDArgsError,
+    },
+};
+#[cfg(feature = "hwaccel")]
+use hd_fpv_video_tool::video::{benchmark::BenchmarkError, hw_accel::DetectionError as HwEncoderDetectionError};
+#[cfg(feature = "telemetry")]
+use hd_fpv_video_tool::osd::telemetry::ExportTelemetryError;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, strum::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum ErrorFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// Category of the error which made a command fail, each category has its own stable exit code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::Display)]
+#[strum(serialize_all = "snake_case")]
+pub enum ErrorKind {
+    Other,
+    InvalidCommandLine,
+    InvalidArguments,
+    InputNotFound,
+    OutputExists,
+    OSDFileNotFound,
+    InvalidOSDFile,
+    InvalidVideoFile,
+    Font,
+    #[strum(serialize = "ffmpeg")]
+    FFMpeg,
+    Player,
+    IO,
+    Cancelled,
+}
+
+impl ErrorKind {
+
+    /// Exit code of the process, 2 is the exit code of the command line parsing errors like with any clap program
+    pub const fn exit_code(&self) -> i32 {
+        use ErrorKind::*;
+        match self {
+            Other => 1,
+            InvalidCommandLine => 2,
+            InvalidArguments => 3,
+            InputNotFound => 4,
+            OutputExists => 5,
+            OSDFileNotFound => 6,
+            InvalidOSDFile => 7,
+            InvalidVideoFile => 8,
+            Font => 9,
+            FFMpeg => 10,
+            Player => 11,
+            IO => 12,
+            // the usual exit code of the processes interrupted with Ctrl-C
+            Cancelled => 130,
+        }
+    }
+
+    /// Category of an error, the first error of the chain which can be categorized decides
+    pub fn of(error: &anyhow::Error) -> Self {
+        error.chain().find_map(classify).unwrap_or(ErrorKind::Other)
+    }
+
+}
+
+fn classify_inner(error: &(dyn StdError + 'static)) -> ErrorKind {
+    classify(error).unwrap_or(ErrorKind::Other)
+}
+
+fn classify(error: &(dyn StdError + 'static)) -> Option<ErrorKind> {
+    use ErrorKind::*;
+
+    if let Some(error) = error.downcast_ref::<CutVideoError>() {
+        return Some(match error {
+            CutVideoError::InputVideoFileDoesNotExist => InputNotFound,
+            CutVideoError::OutputVideoFileExists => OutputExists,
+            CutVideoError::InputAndOutputFileIsTheSame | CutVideoError::InputHasNoFileName | CutVideoError::InputHasNoExtension |
+                CutVideoError::OutputHasADifferentExtensionThanInput => InvalidArguments,
+            CutVideoError::FailedToGetInputVideoDetails(error) => classify_inner(error),
+            CutVideoError::FailedSpawningFFMpegProcess(_) | CutVideoError::FFMpegExitedWithError(_) => FFMpeg,
+            CutVideoError::WriteToFileError(_) | CutVideoError::InsufficientDiskSpace(_) => IO,
+        })
+    }
+
+    if let Some(error) = error.downcast_ref::<FixVideoFileAudioError>() {
+        return Some(match error {
+            FixVideoFileAudioError::InputVideoFileDoesNotExist => InputNotFound,
+            FixVideoFileAudioError::OutputVideoFileExists => OutputExists,
+            FixVideoFileAudioError::InputAndOutputFileIsTheSame | FixVideoFileAudioError::InputHasNoFileName | FixVideoFileAudioError::InputHasNoExtension |
+                FixVideoFileAudioError::OutputHasADifferentExtensionThanInput => InvalidArguments,
+            FixVideoFileAudioError::InputVideoDoesNotHaveAnAudioStream => InvalidVideoFile,
+            FixVideoFileAudioError::FailedToGetInputVideoDetails(error) => classify_inner(error),
+            FixVideoFileAudioError::FailedSpawningFFMpegProcess(_) | FixVideoFileAudioError::FFMpegExitedWithError(_) => FFMpeg,
+            FixVideoFileAudioError::WriteToFileError(_) | FixVideoFileAudioError::InsufficientDiskSpace(_) => IO,
+            FixVideoFileAudioError::AudioAnalysisError(error) => classify_inner(error),
+        })
+    }
+
+    if let Some(error) = error.downcast_ref::<AnalyzeAudioError>() {
+        return Some(match error {
+            AnalyzeAudioError::FailedRunningFFMpeg { .. } | AnalyzeAudioError::FailedAnalyzingAudio { .. } => FFMpeg,
+            AnalyzeAudioError::NoAudioLevels(_) => InvalidVideoFile,
+        })
+    }
+
+    if let Some(error) = error.downcast_ref::<TranscodeWithOSDArgsError>() {
+        return Some(match error {
+            TranscodeWithOSDArgsError::StartGreaterThanEnd(_) => InvalidArguments,
+            TranscodeWithOSDArgsError::RequestedOSDButNoFileProvidedNorFound(_) => OSDFileNotFound,
+            TranscodeWithOSDArgsError::TrimToOSDError(error) => classify_inner(error),
+            TranscodeWithOSDArgsError::TranscodeVideoError(error) => classify_inner(error),
+        })
+    }
+
+    if let Some(error) = error.downcast_ref::<TranscodeVideoError>() {
+        return Some(match error {
+            TranscodeVideoError::InputVideoFileDoesNotExist | TranscodeVideoError::OSDFramesDirDoesNotExist(_) |
+                TranscodeVideoError::LUTFileDoesNotExist(_) | TranscodeVideoError::MusicFileDoesNotExist(_) => InputNotFound,
+            TranscodeVideoError::OutputVideoFileExists | TranscodeVideoError::ProxyOutputFileExists(_) => OutputExists,
+            TranscodeVideoError::OutputVideoFileError(_) | TranscodeVideoError::CanOnlyBurnOSDOn60FPSVideo(_) |
+                TranscodeVideoError::InputAndOutputFileIsTheSame | TranscodeVideoError::IncompatibleArguments(_) |
+                TranscodeVideoError::OSDFramesDirHasNoFrameAtStart(_) | TranscodeVideoError::UnknownOSDItem(_) |
+                TranscodeVideoError::CropError(_) | TranscodeVideoError::OSDFramesManifestReadError(_) |
+                TranscodeVideoError::AnnotationsReadError(_) => InvalidArguments,
+            TranscodeVideoError::RequestedAudioFixingButInputHasNoAudio | TranscodeVideoError::AudioTrackDoesNotExist { .. } => InvalidVideoFile,
+            TranscodeVideoError::OSDFontDirError(_) => Font,
+            TranscodeVideoError::OSDFileOpenError(error) => classify_inner(error),
+            TranscodeVideoError::ScalingArgsError(error) => classify_inner(error),
+            TranscodeVideoError::DrawFrameOverlayError(error) => classify_inner(error),
+            TranscodeVideoError::FailedToGetInputVideoDetails(error) => classify_inner(error),
+            TranscodeVideoError::AudioSyncError(error) => classify_inner(error),
+            TranscodeVideoError::JoinRecordingError(error) => classify_inner(error),
+            TranscodeVideoError::OSDFileReadError(_) => InvalidOSDFile,
+            TranscodeVideoError::FailedSpawningFFMpegProcess(_) | TranscodeVideoError::FailedSendingOSDFramesToFFMpeg(_) |
+                TranscodeVideoError::FFMpegExitedWithError(_) => FFMpeg,
+            #[cfg(feature = "hwaccel")]
+            TranscodeVideoError::HwEncoderError(_) => FFMpeg,
+            TranscodeVideoError::WriteToFileError(_) | TranscodeVideoError::StageInputError(_) | TranscodeVideoError::RemoteEncodingError(_) |
+                TranscodeVideoError::OSDFramesManifestWriteError(_) | TranscodeVideoError::ChaptersWriteError(_) |
+                TranscodeVideoError::InsufficientDiskSpace(_) => IO,
+        })
+    }
+
+    if let Some(error) = error.downcast_ref::<JoinRecordingError>() {
+        return Some(match error {
+            JoinRecordingError::VideoFileDoesNotExist(_) => InputNotFound,
+            JoinRecordingError::ContinuationOSDFileNotFound(_) => OSDFileNotFound,
+            JoinRecordingError::VideoProbingError(error) => classify_inner(error),
+            JoinRecordingError::OSDFileOpenError(error) => classify_inner(error),
+            JoinRecordingError::OSDFileReadError(_) => InvalidOSDFile,
+            JoinRecordingError::ConcatOSDFramesError(error) => classify_inner(error),
+            JoinRecordingError::CreatePathError(_) | JoinRecordingError::ConcatScriptWriteError(_) => IO,
+        })
+    }
+
+    if let Some(error) = error.downcast_ref::<ConcatOSDFramesError>() {
+        return Some(match error {
+            ConcatOSDFramesError::NoSegment | ConcatOSDFramesError::MismatchedOSDKind(..) => InvalidArguments,
+            ConcatOSDFramesError::WriteError(DJIWriteError::UnsupportedOSDKind(_)) => InvalidOSDFile,
+            ConcatOSDFramesError::WriteError(DJIWriteError::FileError(_)) | ConcatOSDFramesError::WSAWriteError(_) => IO,
+        })
+    }
+
+    if let Some(error) = error.downcast_ref::<MergeOSDFilesError>() {
+        return Some(match error {
+            MergeOSDFilesError::OpenError(error) => classify_inner(error),
+            MergeOSDFilesError::OSDFileReadError(_) => InvalidOSDFile,
+            MergeOSDFilesError::ConcatOSDFramesError(error) => classify_inner(error),
+            MergeOSDFilesError::MissingDurations(..) => InvalidArguments,
+            MergeOSDFilesError::OutputFileExists(_) => OutputExists,
+        })
+    }
+
+    if let Some(error) = error.downcast_ref::<PlayWithOSDError>() {
+        return Some(match error {
+            PlayWithOSDError::OSDVideoFileNotFound(_) => InputNotFound,
+            PlayWithOSDError::InvalidVideoFilePath(_) => InvalidArguments,
+            PlayWithOSDError::UnsupportedOSDVideoCodec => InvalidVideoFile,
+            PlayWithOSDError::VideoProbingError(error) => classify_inner(error),
+            PlayWithOSDError::FailedToStartMPV(_) | PlayWithOSDError::MPVExitedWithAnError(_) => Player,
+            PlayWithOSDError::FailedWritingMPVScript(_) => IO,
+            PlayWithOSDError::RequestedOSDButNoFileProvidedNorFound(_) => OSDFileNotFound,
+            PlayWithOSDError::OSDFontDirError(_) => Font,
+            PlayWithOSDError::OSDFileOpenError(error) => classify_inner(error),
+            PlayWithOSDError::OSDFileReadError(_) => InvalidOSDFile,
+            PlayWithOSDError::ScalingArgsError(error) => classify_inner(error),
+            PlayWithOSDError::DrawFrameOverlayError(error) => classify_inner(error),
+            PlayWithOSDError::FailedSpawningFFMpegProcess(_) => FFMpeg,
+        })
+    }
+
+    if let Some(error) = error.downcast_ref::<ExportOSDFrameMappingError>() {
+        return Some(match error {
+            ExportOSDFrameMappingError::VideoFileDoesNotExist => InputNotFound,
+            ExportOSDFrameMappingError::NoOSDFileProvidedNorFound => OSDFileNotFound,
+            ExportOSDFrameMappingError::FailedToGetVideoDetails(error) => classify_inner(error),
+            ExportOSDFrameMappingError::OSDFileOpenError(error) => classify_inner(error),
+            ExportOSDFrameMappingError::OSDFileReadError(_) => InvalidOSDFile,
+            ExportOSDFrameMappingError::WriteError(_) => IO,
+        })
+    }
+
+    #[cfg(feature = "telemetry")]
+    if let Some(error) = error.downcast_ref::<ExportTelemetryError>() {
+        return Some(match error {
+            ExportTelemetryError::OpenError(error) => classify_inner(error),
+            ExportTelemetryError::OSDFileReadError(_) | ExportTelemetryError::UnsupportedFontVariant(_) |
+                ExportTelemetryError::NoTelemetryFound => InvalidOSDFile,
+            ExportTelemetryError::WriteError(_) => IO,
+        })
+    }
+
+    if let Some(error) = error.downcast_ref::<ExportOSDFramesError>() {
+        return Some(match error {
+            ExportOSDFramesError::OpenError(error) => classify_inner(error),
+            ExportOSDFramesError::OSDFileReadError(_) => InvalidOSDFile,
+            ExportOSDFramesError::WriteError(_) | ExportOSDFramesError::EncodeError(_) => IO,
+        })
+    }
+
+    if let Some(error) = error.downcast_ref::<ImportOSDFileError>() {
+        return Some(match error {
+            ImportOSDFileError::ReadError(error) if error.kind() == std::io::ErrorKind::NotFound => InputNotFound,
+            ImportOSDFileError::ReadError(_) | ImportOSDFileError::WriteError(_) => IO,
+            ImportOSDFileError::DecodeError(_) | ImportOSDFileError::UnsupportedDocumentVersion(_) | ImportOSDFileError::UnsupportedOSDDimensions(_) |
+                ImportOSDFileError::UnknownFontVariant(_) | ImportOSDFileError::InvalidFrameTileGrid { .. } => InvalidArguments,
+            ImportOSDFileError::OutputFileExists(_) => OutputExists,
+        })
+    }
+
+    if let Some(error) = error.downcast_ref::<RepairOSDFileError>() {
+        return Some(match error {
+            RepairOSDFileError::OpenError(error) => classify_inner(error),
+            RepairOSDFileError::OSDFileReadError(_) | RepairOSDFileError::UnsupportedOSDFileFormat | RepairOSDFileError::NoValidFrame => InvalidOSDFile,
+            RepairOSDFileError::OutputFileExists(_) => OutputExists,
+            RepairOSDFileError::WriteError(_) => IO,
+        })
+    }
+
+    if let Some(error) = error.downcast_ref::<GenerateThumbnailsError>() {
+        return Some(match error {
+            GenerateThumbnailsError::InputVideoFileDoesNotExist(_) => InputNotFound,
+            GenerateThumbnailsError::InvalidVideoFilePath(_) => InvalidArguments,
+            GenerateThumbnailsError::OutputFileExists(_) => OutputExists,
+            GenerateThumbnailsError::EmptyGrid | GenerateThumbnailsError::UnknownOSDItem(_) => InvalidArguments,
+            GenerateThumbnailsError::NotEnoughFrames { .. } => InvalidVideoFile,
+            GenerateThumbnailsError::FailedToGetVideoDetails(error) => classify_inner(error),
+            GenerateThumbnailsError::RequestedOSDButNoFileProvidedNorFound(_) => OSDFileNotFound,
+            GenerateThumbnailsError::OSDFontDirError(_) => Font,
+            GenerateThumbnailsError::OSDFileOpenError(error) => classify_inner(error),
+            GenerateThumbnailsError::OSDFileReadError(_) => InvalidOSDFile,
+            GenerateThumbnailsError::ScalingArgsError(error) => classify_inner(error),
+            GenerateThumbnailsError::DrawFrameOverlayError(error) => classify_inner(error),
+            GenerateThumbnailsError::FailedSpawningFFMpegProcess(_) | GenerateThumbnailsError::FFMpegExitedWithError(_) |
+                GenerateThumbnailsError::FailedSendingOSDFramesToFFMpeg(_) => FFMpeg,
+        })
+    }
+
+    if let Some(error) = error.downcast_ref::<DefectAnalysisError>() {
+        return Some(match error {
+            DefectAnalysisError::InputVideoFileDoesNotExist(_) => InputNotFound,
+            DefectAnalysisError::FailedToGetVideoDetails(error) => classify_inner(error),
+            DefectAnalysisError::NotEnoughFrames(_) => InvalidVideoFile,
+            DefectAnalysisError::FailedSpawningFFMpegProcess(_) | DefectAnalysisError::FFMpegExitedWithError(_) |
+                DefectAnalysisError::FrameReadError(_) => FFMpeg,
+            DefectAnalysisError::ConfigError(error) => classify_inner(error),
+        })
+    }
+
+    if let Some(error) = error.downcast_ref::<DiscoveryError>() {
+        return Some(match error {
+            DiscoveryError::DirectoryDoesNotExist(_) => InputNotFound,
+            DiscoveryError::ReadDirError { .. } => IO,
+        })
+    }
+
+    if let Some(error) = error.downcast_ref::<ExtractFramesError>() {
+        return Some(match error {
+            ExtractFramesError::InputVideoFileDoesNotExist(_) => InputNotFound,
+            ExtractFramesError::InvalidVideoFilePath(_) => InvalidArguments,
+            ExtractFramesError::OutputFileExists(_) => OutputExists,
+            ExtractFramesError::FrameIndexOutOfRange { .. } | ExtractFramesError::NoFrameToExtract |
+                ExtractFramesError::StartGreaterThanEnd(_) | ExtractFramesError::UnknownOSDItem(_) => InvalidArguments,
+            ExtractFramesError::FailedToGetVideoDetails(error) => classify_inner(error),
+            ExtractFramesError::RequestedOSDButNoFileProvidedNorFound(_) => OSDFileNotFound,
+            ExtractFramesError::OSDFontDirError(_) => Font,
+            ExtractFramesError::OSDFileOpenError(error) => classify_inner(error),
+            ExtractFramesError::OSDFileReadError(_) => InvalidOSDFile,
+            ExtractFramesError::ScalingArgsError(error) => classify_inner(error),
+            ExtractFramesError::DrawFrameOverlayError(error) => classify_inner(error),
+            ExtractFramesError::CreatePathError(_) | ExtractFramesError::FailedRenamingFrameFile { .. } => IO,
+            ExtractFramesError::FailedSpawningFFMpegProcess(_) | ExtractFramesError::FFMpegExitedWithError(_) |
+                ExtractFramesError::FailedSendingOSDFramesToFFMpeg(_) => FFMpeg,
+        })
+    }
+
+    if let Some(error) = error.downcast_ref::<OSDVideoError>() {
+        return Some(match error {
+            OSDVideoError::OSDVideoFileDoesNotExist(_) => InputNotFound,
+            OSDVideoError::InvalidVideoFilePath(_) => InvalidArguments,
+            OSDVideoError::UnsupportedOSDVideoCodec(_) => InvalidVideoFile,
+            OSDVideoError::FailedToGetVideoDetails(error) => classify_inner(error),
+        })
+    }
+
+    if let Some(error) = error.downcast_ref::<CompareVideosError>() {
+        return Some(match error {
+            CompareVideosError::InputVideoFileDoesNotExist(_) => InputNotFound,
+            CompareVideosError::InvalidVideoFilePath(_) => InvalidArguments,
+            CompareVideosError::OutputVideoFileExists(_) => OutputExists,
+            CompareVideosError::OSDVideo(error) => classify_inner(error),
+            CompareVideosError::FailedToGetVideoDetails(error) => classify_inner(error),
+            CompareVideosError::TouchError(_) => IO,
+            CompareVideosError::FailedSpawningFFMpegProcess(_) | CompareVideosError::FFMpegExitedWithError(_) => FFMpeg,
+        })
+    }
+
+    if let Some(error) = error.downcast_ref::<PipError>() {
+        return Some(match error {
+            PipError::InputVideoFileDoesNotExist(_) => InputNotFound,
+            PipError::InvalidVideoFilePath(_) => InvalidArguments,
+            PipError::OutputVideoFileExists(_) => OutputExists,
+            PipError::FailedToGetVideoDetails(error) => classify_inner(error),
+            PipError::TouchError(_) => IO,
+            PipError::FailedSpawningFFMpegProcess(_) | PipError::FFMpegExitedWithError(_) => FFMpeg,
+        })
+    }
+
+    if let Some(error) = error.downcast_ref::<AutoSplitError>() {
+        return Some(match error {
+            AutoSplitError::InputVideoFileDoesNotExist(_) | AutoSplitError::OutputDirDoesNotExist(_) => InputNotFound,
+            AutoSplitError::InvalidInputVideoFileName(_) => InvalidArguments,
+            AutoSplitError::NoFlightDetected => InvalidOSDFile,
+            AutoSplitError::FailedToGetVideoDetails(error) => classify_inner(error),
+            AutoSplitError::RequestedOSDButNoFileProvidedNorFound(_) => OSDFileNotFound,
+            AutoSplitError::OSDFileOpenError(error) => classify_inner(error),
+            AutoSplitError::OSDFileReadError(_) => InvalidOSDFile,
+            AutoSplitError::FailedCuttingFlight { error, .. } => classify_inner(error),
+        })
+    }
+
+    if let Some(error) = error.downcast_ref::<TrimToOSDError>() {
+        return Some(match error {
+            TrimToOSDError::FailedToGetVideoDetails(error) => classify_inner(error),
+            TrimToOSDError::RequestedOSDButNoFileProvidedNorFound(_) => OSDFileNotFound,
+            TrimToOSDError::OSDFileOpenError(error) => classify_inner(error),
+            TrimToOSDError::OSDFileReadError(_) => InvalidOSDFile,
+            TrimToOSDError::NoOverlap(_) => InvalidArguments,
+        })
+    }
+
+    if let Some(error) = error.downcast_ref::<ExportWebError>() {
+        return Some(match error {
+            ExportWebError::InputVideoFileDoesNotExist(_) => InputNotFound,
+            ExportWebError::OutputPlaylistExists(_) => OutputExists,
+            ExportWebError::InvalidVideoFilePath(_) => InvalidArguments,
+            ExportWebError::OSDVideo(error) => classify_inner(error),
+            ExportWebError::FailedToGetVideoDetails(error) => classify_inner(error),
+            ExportWebError::FailedSpawningFFMpegProcess(_) | ExportWebError::FFMpegExitedWithError(_) => FFMpeg,
+            ExportWebError::CreatePathError(_) | ExportWebError::FailedRenamingMasterPlaylist(_) => IO,
+        })
+    }
+
+    if let Some(error) = error.downcast_ref::<MakeClipError>() {
+        return Some(match error {
+            MakeClipError::InputVideoFileDoesNotExist(_) => InputNotFound,
+            MakeClipError::OutputFileExists(_) => OutputExists,
+            MakeClipError::InvalidVideoFilePath(_) | MakeClipError::StartGreaterThanEnd(_) => InvalidArguments,
+            MakeClipError::OSDVideo(error) => classify_inner(error),
+            MakeClipError::FailedToGetVideoDetails(error) => classify_inner(error),
+            MakeClipError::TouchError(_) => IO,
+            MakeClipError::FailedSpawningFFMpegProcess(_) | MakeClipError::FFMpegExitedWithError(_) => FFMpeg,
+        })
+    }
+
+    if let Some(error) = error.downcast_ref::<PackageSessionError>() {
+        return Some(match error {
+            PackageSessionError::InputVideoFileDoesNotExist(_) | PackageSessionError::OSDVideoFileNotFound(_) => InputNotFound,
+            PackageSessionError::OutputFileExists(_) => OutputExists,
+            PackageSessionError::NoVideoFiles | PackageSessionError::InvalidVideoFilePath(_) => InvalidArguments,
+            PackageSessionError::FailedToGetVideoDetails(error) => classify_inner(error),
+            PackageSessionError::FailedSpawningFFMpegProcess(_) | PackageSessionError::FFMpegExitedWithError(_) => FFMpeg,
+            PackageSessionError::OSDFileOpenError(error) => classify_inner(error),
+            PackageSessionError::OSDFileReadError(error) => classify_inner(error),
+            PackageSessionError::CreatePathError(_) | PackageSessionError::FailedWritingChapters(_) | PackageSessionError::FailedWritingPlaylist(_) => IO,
+        })
+    }
+
+    if let Some(error) = error.downcast_ref::<JobQueueError>() {
+        return Some(match error {
+            JobQueueError::JobNotFound(_) | JobQueueError::JobIsRunning(_) | JobQueueError::NoHomeDir => InvalidArguments,
+            JobQueueError::ReadError { .. } | JobQueueError::WriteError { .. } | JobQueueError::LockError { .. } | JobQueueError::CreateStateDirError(_) |
+                JobQueueError::InvalidStateFile { .. } | JobQueueError::FailedStartingJob { .. } => IO,
+            JobQueueError::JobsFailed(_) => Other,
+        })
+    }
+
+    // arguments of a command added to the queue
+    if error.downcast_ref::<clap::Error>().is_some() {
+        return Some(InvalidArguments);
+    }
+
+    if let Some(error) = error.downcast_ref::<SaveFramesToDirError>() {
+        return Some(match error {
+            SaveFramesToDirError::TargetDirectoryExists(_) => OutputExists,
+            SaveFramesToDirError::NoFrameToWrite | SaveFramesToDirError::UnknownOSDItem(_) => InvalidArguments,
+            SaveFramesToDirError::ReadError(_) => InvalidOSDFile,
+            SaveFramesToDirError::CreatePathError(_) | SaveFramesToDirError::IOError(_) | SaveFramesToDirError::ImageWriteError(_) |
+                SaveFramesToDirError::LinkError(_) | SaveFramesToDirError::FrameWritersStopped | SaveFramesToDirError::InsufficientDiskSpace(_) => IO,
+        })
+    }
+
+    if let Some(error) = error.downcast_ref::<GenerateOverlayVideoError>() {
+        return Some(match error {
+            GenerateOverlayVideoError::TargetVideoFileExists(_) => OutputExists,
+            GenerateOverlayVideoError::OutputFileExtensionNotWebm | GenerateOverlayVideoError::UnknownOSDItem(_) => InvalidArguments,
+            GenerateOverlayVideoError::FrameReadError(_) => InvalidOSDFile,
+            GenerateOverlayVideoError::FailedSpawningFFMpegProcess(_) | GenerateOverlayVideoError::FailedSendingOSDFramesToFFMpeg(_) |
+                GenerateOverlayVideoError::FFMpegExitedWithError(_) => FFMpeg,
+            GenerateOverlayVideoError::WriteToFileError(_) | GenerateOverlayVideoError::InsufficientDiskSpace(_) => IO,
+        })
+    }
+
+    if let Some(error) = error.downcast_ref::<GenerateCalibrationOverlayError>() {
+        return Some(match error {
+            GenerateCalibrationOverlayError::DrawFrameOverlayError(_) | GenerateCalibrationOverlayError::UnsupportedOutputFileExtension |
+                GenerateCalibrationOverlayError::UnknownOSDItem(_) => InvalidArguments,
+            GenerateCalibrationOverlayError::TargetFileExists(_) => OutputExists,
+            GenerateCalibrationOverlayError::FailedSpawningFFMpegProcess(_) | GenerateCalibrationOverlayError::FailedSendingFramesToFFMpeg(_) |
+                GenerateCalibrationOverlayError::FFMpegExitedWithError(_) => FFMpeg,
+            GenerateCalibrationOverlayError::ImageWriteError(_) | GenerateCalibrationOverlayError::WriteToFileError(_) |
+                GenerateCalibrationOverlayError::InsufficientDiskSpace(_) => IO,
+        })
+    }
+
+    if let Some(error) = error.downcast_ref::<DrawFrameOverlayError>() {
+        return Some(match error {
+            DrawFrameOverlayError::OSDFileIsEmpty | DrawFrameOverlayError::ReadError(_) => InvalidOSDFile,
+            DrawFrameOverlayError::FontLoadError(_) | DrawFrameOverlayError::CustomTilesError(_) |
+                DrawFrameOverlayError::GlyphSubstitutionError(_) => Font,
+            DrawFrameOverlayError::VideoResolutionTooSmallError { .. } | DrawFrameOverlayError::InvalidRegionError(_) => InvalidArguments,
+        })
+    }
+
+    if let Some(error) = error.downcast_ref::<osd::file::OpenError>() {
+        return Some(match error {
+            osd::file::OpenError::UnrecognizedOSDFile(error) => classify_inner(error),
+            osd::file::OpenError::SourceReadError { .. } => IO,
+            #[cfg(feature = "remote-osd")]
+            osd::file::OpenError::DownloadError { .. } => IO,
+            osd::file::OpenError::URLNotSupported(_) => InvalidArguments,
+        })
+    }
+
+    if let Some(error) = error.downcast_ref::<osd::file::UnrecognizedOSDFile>() {
+        return Some(if error.path().exists() || osd::file::is_stream_source(error.path()) { InvalidOSDFile } else { OSDFileNotFound })
+    }
+
+    if let Some(error) = error.downcast_ref::<AudioSyncError>() {
+        return Some(match error {
+            AudioSyncError::FailedRunningFFMpeg { .. } | AudioSyncError::FailedExtractingAudio { .. } => FFMpeg,
+            AudioSyncError::NotEnoughAudio(_) => InvalidVideoFile,
+        })
+    }
+
+    #[cfg(feature = "hwaccel")]
+    if let Some(error) = error.downcast_ref::<BenchmarkError>() {
+        return Some(match error {
+            BenchmarkError::FailedRunningFFMpeg { .. } | BenchmarkError::NoWorkingEncoder(_) | BenchmarkError::FailedSpawningFFMpegProcess(_) => FFMpeg,
+            BenchmarkError::ConfigError(error) => classify_inner(error),
+        })
+    }
+
+    if let Some(error) = error.downcast_ref::<ConfigError>() {
+        return Some(match error {
+            ConfigError::ReadError { .. } | ConfigError::WriteError { .. } | ConfigError::NoConfigFilePath => IO,
+            ConfigError::CreatePathError(_) => IO,
+            ConfigError::InvalidConfigFile { .. } => InvalidArguments,
+        })
+    }
+
+    if error.is::<CancelledError>() { return Some(Cancelled) }
+    if error.is::<RequestedOSDButNoFileProvidedNorFound>() { return Some(OSDFileNotFound) }
+    if error.is::<UnsupportedAlphaMode>() { return Some(InvalidArguments) }
+    if error.is::<osd::file::ReadError>() || error.is::<osd::dji::file::OpenError>() || error.is::<osd::wsa::file::OpenError>() {
+        return Some(InvalidOSDFile)
+    }
+    if error.is::<video::probe::Error>() { return Some(InvalidVideoFile) }
+    if error.is::<OSDFontDirError>() { return Some(Font) }
+    if let Some(error) = error.downcast_ref::<FontInfoError>() {
+        return Some(match error {
+            FontInfoError::FontLoadError(_) | FontInfoError::PageLoadError(_) => Font,
+            FontInfoError::ContactSheetWriteError(_) => IO,
+        })
+    }
+    if error.is::<ScalingArgsError>() || error.is::<StartGreaterThanEndError>() || error.is::<OutputVideoFileError>() ||
+        error.is::<InvalidRegionError>() || error.is::<UnknownOSDItem>() || error.is::<ProgramNotWorking>() {
+        return Some(InvalidArguments)
+    }
+    if error.is::<ffmpeg::SpawnError>() || error.is::<ffmpeg::ProcessError>() || error.is::<ffmpeg::BuildCommandError>() ||
+        error.is::<SendFramesToFFMpegError>() {
+        return Some(FFMpeg)
+    }
+    #[cfg(feature = "hwaccel")]
+    if error.is::<HwEncoderDetectionError>() { return Some(FFMpeg) }
+    if error.is::<std::io::Error>() || error.is::<TouchError>() || error.is::<CreatePathError>() || error.is::<StageError>() ||
+        error.is::<ImageReadError>() || error.is::<ImageWriteError>() || error.is::<InsufficientDiskSpaceError>() {
+        return Some(IO)
+    }
+
+    None
+}
+
+// error serialized by the JSON reporter
+#[derive(Serialize)]
+struct JsonError {
+    kind: String,
+    exit_code: i32,
+    message: String,
+    context: Vec<String>,
+}
+
+impl JsonError {
+
+    fn new(kind: ErrorKind, message: String, context: Vec<String>) -> Self {
+        Self { kind: kind.to_string(), exit_code: kind.exit_code(), message, context }
+    }
+
+    fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+}
+
+/// Error serialized as a JSON object: `{"kind": ..., "exit_code": ..., "message": ..., "context": [...]}`
+///
+/// The message is the error itself and the context lists the errors which caused it, from the closest to the root cause.
+pub fn to_json(error: &anyhow::Error) -> String {
+    let context = error.chain().skip(1).map(ToString::to_string).collect();
+    JsonError::new(ErrorKind::of(error), error.to_string(), context).to_json()
+}
+
+/// Reports a command error in the requested format and returns the exit code matching its category
+pub fn report(error: &anyhow::Error, format: ErrorFormat) -> i32 {
+    match format {
+        ErrorFormat::Text => log::error!("{}", error),
+        ErrorFormat::Json => eprintln!("{}", to_json(error)),
+    }
+    ErrorKind::of(error).exit_code()
+}
+
+// the error format option cannot be read from a command line which failed to parse so it is looked up in the raw arguments
+fn requested_error_format() -> ErrorFormat {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = match arg.strip_prefix("--error-format") {
+            Some("") => args.next(),
+            Some(value) => value.strip_prefix('=').map(str::to_string),
+            None => None,
+        };
+        if let Some(format) = value.and_then(|value| ErrorFormat::from_str(&value, true).ok()) { return format }
+    }
+    ErrorFormat::default()
+}
+
+/// Reports a command line parsing error in the format requested on the command line and exits
+///
+/// The help and version requests and the errors in the text format are printed by clap as usual.
+pub fn report_command_line_error(error: clap::Error) -> ! {
+    if ! error.use_stderr() || requested_error_format() != ErrorFormat::Json { error.exit() }
+    let rendered_error = error.to_string();
+    let message = rendered_error.lines().next().unwrap_or_default();
+    let message = message.strip_prefix("error: ").unwrap_or(message).to_string();
+    eprintln!("{}", JsonError::new(ErrorKind::InvalidCommandLine, message, vec![]).to_json());
+    std::process::exit(ErrorKind::InvalidCommandLine.exit_code())
+}
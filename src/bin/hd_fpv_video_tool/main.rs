@@ -5,6 +5,7 @@ use std::{
     io::Write,
     process::exit,
     path::{Path, PathBuf},
+    ffi::OsStr,
     env::current_exe,
 };
 
@@ -15,15 +16,17 @@ use strum::IntoEnumIterator;
 use anyhow::anyhow;
 
 
-use hd_fpv_video_tool::{prelude::*, osd::file::GenericReader};
+use hd_fpv_osd_font_tool::prelude::tile;
+use hd_fpv_video_tool::{prelude::*, cli::font_options::FontOptions, osd::file::GenericReader, config::Config, ffmpeg, video::container::Container, hooks::Hooks, output_path, job_queue, log_file, process, cancellation, throttle, notification};
 mod shell_autocompletion;
 mod man_pages;
 mod cli;
+mod error_report;
 
 use {cli::*, man_pages::*, shell_autocompletion::*};
 
 
-fn display_osd_file_info_command<P: AsRef<Path>>(path: P) -> anyhow::Result<()> {
+fn display_osd_file_info_command<P: AsRef<Path>>(path: P, stats: bool) -> anyhow::Result<()> {
     let mut reader = osd::file::open(path)?;
 
     println!();
@@ -39,7 +42,9 @@ fn display_osd_file_info_command<P: AsRef<Path>>(path: P) -> anyhow::Result<()>
         },
         osd::file::Reader::WSA(reader) => {
             let header = reader.header();
+            let frame_layout = reader.frame_layout();
             println!("OSD file type: Walksnail Avatar");
+            println!("Frame records: {} bit tile indices, {} padding bytes", frame_layout.tile_index_size().bits(), frame_layout.padding());
             println!("OSD Font variant: {} ({})", header.font_variant_id(), header.font_variant());
         },
     }
@@ -54,96 +59,246 @@ fn display_osd_file_info_command<P: AsRef<Path>>(path: P) -> anyhow::Result<()>
             1 => "every frame".to_owned(),
             frames => format!("every {frames} frames")
         };
-        let refresh_freq = 60.0 / refresh_interval_frames;
+        let refresh_freq = osd::OSD_TIME_BASE_HZ / refresh_interval_frames;
         println!("OSD update rate: {refresh_percent_frames:.0}% of the video frames ({refresh_freq:.1}Hz or approximately {refresh_interval_frames_str})");
     }
+    if stats { display_osd_stats(&frames) }
+    Ok(())
+}
+
+fn display_osd_stats(frames: &osd::file::SortedUniqFrames) {
+    let stats = osd::stats::OSDStats::compute(frames);
+    println!();
+    println!("Grid cells usage ({} tiles, from `.` drawn on few frames to `@` drawn on every frame):", stats.grid_dimensions());
+    print!("{}", stats.heatmap());
+    println!();
+    println!("Most used tile indices:");
+    print!("{}", stats.histogram(frames.font_variant()));
+    println!();
+    match stats.detected_items().is_empty() {
+        true => println!("No OSD item detected for the {} font variant", frames.font_variant()),
+        false => {
+            println!("Detected OSD items:");
+            for item in stats.detected_items() {
+                println!("{:<12} {:<12} {} frames", item.name(), item.region(), item.frame_count());
+            }
+        },
+    }
+}
+
+fn display_font_info_command(font_options: &FontOptions, tile_kind: tile::Kind, contact_sheet: &Option<PathBuf>) -> anyhow::Result<()> {
+    let mut font_dir = FontDir::new(font_options.font_dir()?);
+    font_dir.set_fallback_variants(&[]);
+    let font_variant = font_options.font_variant(osd::FontVariant::Generic);
+    let ident = font_options.font_ident().unwrap_or_else(|| font_variant.font_set_ident());
+    let font_info = osd::font_info::FontInfo::load(&font_dir, tile_kind, &ident)?;
+
+    println!();
+    match ident {
+        Some(ident) => println!("Font ident: {ident}"),
+        None => println!("Font ident: none (generic font)"),
+    }
+    println!("Tile kind: {}", font_info.tile_kind());
+    let (tile_width, tile_height) = font_info.tile_dimensions();
+    println!("Tile dimensions: {tile_width}x{tile_height} px");
+    println!("Number of tiles: {} ({} pages)", font_info.tile_count(), font_info.page_count());
+    println!("Blank tiles: {}", font_info.blank_tile_count());
+
+    if let Some(contact_sheet) = contact_sheet {
+        font_info.write_contact_sheet(contact_sheet)?;
+        log::info!("contact sheet written to {}", contact_sheet.to_string_lossy());
+    }
+    Ok(())
+}
+
+fn list_command(dirs: &[PathBuf], recursive: bool, json: bool) -> anyhow::Result<()> {
+    let recordings = video::discovery::discover(dirs, recursive)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&recordings)?);
+        return Ok(());
+    }
+    println!("{:<50} {:<30} {:<10} {:>9}", "video file", "OSD file", "OSD kind", "duration");
+    for recording in &recordings {
+        let osd_file = recording.osd_file().as_ref().map_or("-".into(), |osd_file| osd_file.to_string_lossy());
+        let osd_kind = recording.osd_kind().map_or("-".to_owned(), |osd_kind| osd_kind.to_string());
+        let duration = recording.duration().map_or("-".to_owned(), |duration| {
+            let secs = duration.round() as u32;
+            format!("{}m{:02}s", secs / 60, secs % 60)
+        });
+        println!("{:<50} {:<30} {:<10} {:>9}", recording.video_file().to_string_lossy(), osd_file, osd_kind, duration);
+    }
+    let with_osd_count = recordings.iter().filter(|recording| recording.osd_file().is_some()).count();
+    println!("{} videos, {with_osd_count} with an OSD file", recordings.len());
     Ok(())
 }
 
 fn generate_overlay_prepare_generator(common_args: &GenerateOverlayArgs) -> anyhow::Result<OverlayGenerator> {
     let scaling = Scaling::try_from_scaling_args(common_args.scaling_args(), common_args.target_video_file())?;
     let mut osd_file_reader = osd::file::open(common_args.osd_file())?;
-    let font_dir = FontDir::new(common_args.font_options().font_dir()?);
-    let overlay_generator = OverlayGenerator::new(
-        osd_file_reader.frames()?,
-        osd_file_reader.font_variant(),
+    let font_dir = common_args.font_options().font_dir_with_fallback()?;
+    let font_variant = common_args.font_options().font_variant(osd_file_reader.font_variant());
+    let mut osd_file_frames = osd_file_reader.frames()?;
+    if let Some(unit_system) = common_args.units() {
+        osd_file_frames = osd::units::convert_frames(&osd_file_frames, font_variant, unit_system);
+    }
+    if common_args.interpolate_values() {
+        osd_file_frames = osd::interpolation::interpolate_frames(&osd_file_frames, font_variant, osd::interpolation::DEFAULT_MAX_GAP);
+    }
+    if ! common_args.add_text().is_empty() {
+        osd_file_frames = osd::text_injection::inject_text(&osd_file_frames, font_variant, common_args.add_text());
+    }
+    let mut overlay_generator = OverlayGenerator::new(
+        osd_file_frames,
+        font_variant,
         &font_dir,
         &common_args.font_options().font_ident(),
         scaling,
         common_args.hide_regions(),
+        common_args.strict_regions(),
         common_args.hide_items()
     )?;
+    overlay_generator.set_stick_overlay(common_args.stick_overlay_args().stick_overlay())
+        .set_enlargement(common_args.enlargement_args().enlargement());
     Ok(overlay_generator)
 }
 
 fn generate_overlay_frames_command(command: &Commands) -> anyhow::Result<()> {
-    if let Commands::GenerateOverlayFrames { common_args, output_dir } = command {
+    if let Commands::GenerateOverlayFrames { common_args, frame_link_mode, frame_writers, image_format, alpha_mode, output_dir } = command {
         common_args.check_valid()?;
+        let frame_file_format = FrameFileFormat::new(*image_format, *alpha_mode)?;
         let output_dir = match (output_dir, common_args.target_video_file()) {
             (Some(output_dir), _) => output_dir.clone(),
-            (None, Some(target_video_file)) => {
-                let target_video_file_stem = target_video_file.file_stem().ok_or_else(|| anyhow!("target video file has no file name"))?;
-                let mut output_file_stem = target_video_file_stem.to_os_string();
-                output_file_stem.push("_osd_frames");
-                PathBuf::from(output_file_stem)
-            },
-            (None, None) => {
-                let osd_file = common_args.osd_file();
-                let mut output_dir_name = Path::new(osd_file.file_stem().ok_or_else(|| anyhow!("OSD file has no file name"))?).as_os_str().to_os_string();
-                output_dir_name.push("_osd_frames");
-                osd_file.with_file_name(output_dir_name)
-            }
+            (None, Some(target_video_file)) =>
+                output_path::default_output_path(target_video_file, "osd_frames", None).ok_or_else(|| anyhow!("target video file has no file name"))?,
+            (None, None) =>
+                output_path::default_output_path(common_args.osd_file(), "osd_frames", None).ok_or_else(|| anyhow!("OSD file has no file name"))?,
         };
         let mut overlay_generator = generate_overlay_prepare_generator(common_args)?;
+        overlay_generator.set_frame_link_mode(*frame_link_mode).set_frame_writer_count(*frame_writers as usize).set_frame_file_format(frame_file_format);
         overlay_generator.save_frames_to_dir(common_args.start_end().start(), common_args.start_end().end(), output_dir, common_args.frame_shift()?)?;
     }
     Ok(())
 }
 
+fn merge_osd_files_command(osd_files: &[PathBuf], video_files: &[PathBuf], durations: &[f64], output_file: &Path, overwrite: bool) -> anyhow::Result<()> {
+    let durations = match video_files.is_empty() {
+        true => durations.to_vec(),
+        false => video_files.iter()
+            .map(|video_file| Ok(video::probe(video_file)?.duration()))
+            .collect::<anyhow::Result<Vec<_>>>()?,
+    };
+    osd::concat::merge_osd_files(osd_files, &durations, output_file, overwrite)?;
+    Ok(())
+}
+
 async fn generate_overlay_video_command(command: &Commands) -> anyhow::Result<()> {
-    if let Commands::GenerateOverlayVideo { common_args, video_file, overwrite, codec } = command {
+    if let Commands::GenerateOverlayVideo { common_args, video_file, overwrite, codec, preset, render_threads, speed } = command {
         common_args.check_valid()?;
         let output_video_path = match (video_file, common_args.target_video_file()) {
             (Some(output_video_file), _) => output_video_file.clone(),
-            (None, Some(target_video_file)) => {
-                let target_video_file_stem = target_video_file.file_stem().ok_or_else(|| anyhow!("target video file has no file name"))?;
-                let mut output_file_stem = target_video_file_stem.to_os_string();
-                output_file_stem.push("_osd");
-                Path::new(&output_file_stem).with_extension("webm")
-            },
-            (None, None) => {
-                let osd_file = common_args.osd_file();
-                let mut output_file_stem = Path::new(osd_file.file_stem().ok_or_else(|| anyhow!("OSD file has no file name"))?).as_os_str().to_os_string();
-                output_file_stem.push("_osd");
-                osd_file.with_file_name(output_file_stem).with_extension("webm")
-            }
+            (None, Some(target_video_file)) =>
+                output_path::default_output_path(target_video_file, "osd", Some(OsStr::new("webm"))).ok_or_else(|| anyhow!("target video file has no file name"))?,
+            (None, None) =>
+                output_path::default_output_path(common_args.osd_file(), "osd", Some(OsStr::new("webm"))).ok_or_else(|| anyhow!("OSD file has no file name"))?,
         };
         let mut overlay_generator = generate_overlay_prepare_generator(common_args)?;
-        overlay_generator.generate_overlay_video(*codec, common_args.start_end().start(), common_args.start_end().end(), output_video_path, common_args.frame_shift()?, *overwrite).await?;
+        overlay_generator.set_render_thread_count(*render_threads as usize);
+        overlay_generator.generate_overlay_video(*codec, *preset, common_args.start_end().start(), common_args.start_end().end(), output_video_path,
+            common_args.frame_shift()?, speed.unwrap_or(OSDSpeed::NORMAL), *overwrite).await?;
     }
     Ok(())
 }
 
-async fn transcode_video_command(command: &Commands) -> anyhow::Result<()> {
-    if let Commands::TranscodeVideo { osd_args, transcode_args } = command {
+async fn generate_calibration_overlay_command(command: &Commands) -> anyhow::Result<()> {
+    if let Commands::GenerateCalibrationOverlay { target_video_file, scaling_args, codec, duration, osd_file, output_file, overwrite } = command {
+        let output_file = match output_file {
+            Some(output_file) => output_file.clone(),
+            None => output_path::default_output_path(osd_file, "calibration", Some(OsStr::new("webm"))).ok_or_else(|| anyhow!("OSD file has no file name"))?,
+        };
+        let scaling = Scaling::try_from_scaling_args(scaling_args, target_video_file)?;
+        let osd_kind = osd::file::open(osd_file)?.frames()?.kind();
+        osd::overlay::calibration::generate_calibration_overlay(osd_kind, &scaling, output_file, *duration, *codec, *overwrite).await?;
+    }
+    Ok(())
+}
 
-        transcode_args.start_end().check_valid()?;
+async fn cut_video_command(command: &Commands) -> anyhow::Result<()> {
+    if let Commands::CutVideo { start_end, trim_to_osd, osd_file, no_preserve_metadata, container, input_video_file, output_video_file, overwrite } = command {
+        let osd_start_end = match trim_to_osd {
+            true => Some(video::trim_to_osd::osd_start_end(input_video_file, osd_file.as_deref(), None, None, None, None, false)?),
+            false => None,
+        };
+        video::cut(input_video_file, output_video_file, *overwrite, osd_start_end.as_ref().unwrap_or(start_end), ! no_preserve_metadata, *container).await?;
+    }
+    Ok(())
+}
 
-        match osd_args.osd_file_path(transcode_args.input_video_file())? {
-            Some(osd_file_path) => video::transcode_burn_osd(transcode_args, osd_file_path, osd_args).await?,
-            None => video::transcode(transcode_args).await?,
-        }
+async fn transcode_video_command(command: &Commands) -> anyhow::Result<()> {
+    if let Commands::TranscodeVideo { osd_args, transcode_args } = command {
+        video::transcode_with_osd_args(transcode_args, osd_args).await?;
     }
     Ok(())
 }
 
-async fn fix_video_audio_command<P: AsRef<Path>, Q: AsRef<Path>>(input_video_file: P, output_video_file: &Option<Q>, overwrite: bool, sync: bool, volume: bool) -> anyhow::Result<()> {
+async fn fix_video_audio_command<P: AsRef<Path>, Q: AsRef<Path>>(input_video_file: P, output_video_file: &Option<Q>, overwrite: bool, sync: bool, volume: bool,
+        auto_gain: bool, sync_factor: Option<AudioSyncFactor>, estimate_sync_factor: bool, preserve_metadata: bool,
+        container: Option<Container>) -> anyhow::Result<()> {
     let fix_type = match (sync, volume) {
         (true, true) | (false, false) => VideoAudioFixType::SyncAndVolume,
         (true, false) => VideoAudioFixType::Sync,
         (false, true) => VideoAudioFixType::Volume,
     };
-    video::fix_dji_air_unit_audio(input_video_file, output_video_file, overwrite, fix_type).await?;
+    let sync_factor_source = match (sync_factor, estimate_sync_factor) {
+        (Some(sync_factor), _) => AudioSyncFactorSource::Value(sync_factor.value()),
+        (None, true) => AudioSyncFactorSource::Estimate,
+        (None, false) => AudioSyncFactorSource::Model,
+    };
+    video::fix_dji_air_unit_audio(input_video_file, output_video_file, overwrite, fix_type, auto_gain, sync_factor_source, preserve_metadata, container).await?;
+    Ok(())
+}
+
+async fn analyze_audio_command<P: AsRef<Path>>(input_file: P) -> anyhow::Result<()> {
+    let analysis = video::audio_analysis::analyze(input_file).await?;
+    println!("{analysis}");
+    match analysis.normalization_gain() {
+        Some(gain) => println!("Normalization gain: {gain:.2}x ({:+.1} dB)", 20.0 * gain.log10()),
+        None => println!("Normalization gain: n/a (silent audio)"),
+    }
+    Ok(())
+}
+
+fn queue_command(queue_file: &Option<PathBuf>, command: &QueueCommands) -> anyhow::Result<()> {
+    let state_file_path = match queue_file {
+        Some(queue_file) => queue_file.clone(),
+        None => job_queue::default_state_file_path()?,
+    };
+    match command {
+        QueueCommands::Add { args } => {
+            let cli = Cli::try_parse_from(std::iter::once(env!("CARGO_PKG_NAME")).chain(args.iter().map(String::as_str)))?;
+            if matches!(cli.command, Commands::Queue {..}) { return Err(anyhow!("cannot add a queue command to the queue")) }
+            let working_dir = std::env::current_dir().map_err(|error| anyhow!("failed to get current directory: {error}"))?;
+            let id = job_queue::JobQueue::update(&state_file_path, |queue| Ok(queue.add(working_dir, args.clone())))?;
+            log::info!("added job #{id} to the queue");
+        },
+        QueueCommands::List => {
+            let queue = job_queue::JobQueue::load(&state_file_path)?;
+            for job in queue.jobs() { println!("{job}") }
+            if queue.is_paused() { println!("queue is paused") }
+        },
+        QueueCommands::Run { retries, pre_job_hook, post_job_hook } => {
+            let current_exe = current_exe().map_err(|error| anyhow!("failed to get exe path: {error}"))?;
+            let hooks = Config::load()?.hooks().overridden_by(&Hooks::new(pre_job_hook.clone(), post_job_hook.clone()));
+            job_queue::run(&state_file_path, current_exe, *retries, &hooks)?;
+        },
+        QueueCommands::Pause => {
+            job_queue::JobQueue::load(&state_file_path)?.pause()?;
+            log::info!("the queue will stop once the current job is done");
+        },
+        QueueCommands::Remove { ids } =>
+            job_queue::JobQueue::update(&state_file_path, |queue| ids.iter().try_for_each(|id| queue.remove(*id).map(|_| ())))?,
+        QueueCommands::Clear { all } =>
+            job_queue::JobQueue::update(&state_file_path, |queue| { queue.clear(*all); Ok(()) })?,
+    }
     Ok(())
 }
 
@@ -172,12 +327,72 @@ fn generate_man_pages_command() -> anyhow::Result<()> {
     Ok(())
 }
 
+// uses the ffmpeg and mpv binaries specified with the command line options or in the configuration file, checking that they work,
+// and applies the other settings of the configuration file
+fn configure_binaries(cli: &Cli) -> anyhow::Result<()> {
+    let config = Config::load()?;
+    if let Some(ffmpeg_path) = cli.ffmpeg_path().as_ref().or(config.ffmpeg_path().as_ref()) {
+        let version = process::check_program("ffmpeg", ffmpeg_path, "-version",
+            "check the --ffmpeg-path option or the ffmpeg_path key of the configuration file")?;
+        log::info!("using ffmpeg binary {}: {version}", ffmpeg_path.to_string_lossy());
+        ffmpeg::set_binary_path(ffmpeg_path);
+    }
+    if let Some(mpv_path) = cli.mpv_path().as_ref().or(config.mpv_path().as_ref()) {
+        let version = process::check_program("mpv", mpv_path, "--version",
+            "check the --mpv-path option or the mpv_path key of the configuration file")?;
+        log::info!("using mpv binary {}: {version}", mpv_path.to_string_lossy());
+        video::mpv::set_binary_path(mpv_path);
+    }
+    configure_video_encoder(&config)?;
+    configure_video_defects(&config)
+}
+
+// removes the video defects of the configuration file, e.g. the ones found by the analyze-defects command, when transcoding without --remove-video-defects
+fn configure_video_defects(config: &Config) -> anyhow::Result<()> {
+    let Some(regions) = config.remove_video_defects() else { return Ok(()) };
+    let regions = regions.split(';').map(str::parse::<video::Region>).collect::<Result<Vec<_>, _>>()
+        .map_err(|error| anyhow!("invalid remove_video_defects key of the configuration file: {error}"))?;
+    hd_fpv_video_tool::cli::transcode_video_args::set_configured_video_defects(regions);
+    Ok(())
+}
+
+// uses the video encoder of the configuration file, e.g. the one recommended by the benchmark command, when transcoding without --video-encoder
+fn configure_video_encoder(config: &Config) -> anyhow::Result<()> {
+    if let Some(video_encoder) = config.video_encoder() {
+        hd_fpv_video_tool::cli::transcode_video_args::set_configured_video_encoder(video_encoder);
+    }
+    #[cfg(feature = "hwaccel")]
+    if let Some(hw_encoder) = config.hw_encoder() {
+        use clap::ValueEnum;
+        let backend = video::hw_accel::EncoderBackend::from_str(hw_encoder, true)
+            .map_err(|_| anyhow!("invalid hw_encoder key of the configuration file: {hw_encoder}, expected one of nvenc, qsv or vaapi"))?;
+        hd_fpv_video_tool::cli::transcode_video_args::set_configured_hw_encoder(backend);
+    }
+    #[cfg(not(feature = "hwaccel"))]
+    if config.hw_encoder().is_some() {
+        log::warn!("the hw_encoder key of the configuration file is ignored, this build does not support hardware encoders");
+    }
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() {
-    let cli = Cli::parse();
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(error) => error_report::report_command_line_error(error),
+    };
+
+    // nothing is written in dry-run mode, the log file included
+    if let Some(log_file) = cli.log_file().as_ref().filter(|_| ! cli.dry_run()) {
+        if let Err(error) = log_file::open(log_file) {
+            exit(error_report::report(&anyhow::Error::new(error), cli.error_format()));
+        }
+        log_file::write(&format!("\n# {}\n", std::env::args().collect::<Vec<_>>().join(" ")));
+    }
 
     env_logger::builder()
         .format(|buf, record| {
+            log_file::write(&format!("{:<5} > {}\n", record.level(), record.args()));
             let level_style = buf.default_level_style(record.level());
             write!(buf, "{:<5}", level_style.value(record.level()))?;
             let mut style = buf.style();
@@ -188,29 +403,137 @@ async fn main() {
         .parse_filters(cli.log_level().to_string().as_str())
         .init();
 
+    if let Err(error) = configure_binaries(&cli) {
+        exit(error_report::report(&error, cli.error_format()));
+    }
+
+    hd_fpv_video_tool::process::set_dry_run(cli.dry_run());
+    hd_fpv_video_tool::osd::tile_resize::set_cache_enabled(! cli.no_tile_cache());
+    hd_fpv_video_tool::disk_space::set_enforced(! cli.ignore_disk_space());
+    hd_fpv_video_tool::osd::file::set_extended_discovery(! cli.no_osd_auto());
+    if let Some(output_dir) = cli.output_dir() { output_path::set_output_dir(output_dir) }
+    if let Some(output_template) = cli.output_template() { output_path::set_template(output_template.clone()) }
+    if let Some(max_cpu) = cli.max_cpu() { throttle::set_max_cpu(max_cpu) }
+
+    // SIGUSR1 pauses the spawned processes and SIGUSR2 resumes them
+    tokio::spawn(async {
+        if let Err(error) = throttle::handle_signals().await {
+            log::warn!("failed installing the pause and resume signal handlers: {error}");
+        }
+    });
+
+    // on Ctrl-C the spawned processes are killed and the incomplete output files removed before exiting
+    let error_format = cli.error_format();
+    tokio::spawn(async move {
+        if let Err(error) = cancellation::wait_for_signal().await {
+            log::warn!("failed installing the signal handlers, interrupting a command may leave incomplete files behind: {error}");
+            return;
+        }
+        cancellation::cancel();
+        exit(error_report::report(&anyhow::Error::new(cancellation::Cancelled), error_format));
+    });
+
+    let start_time = std::time::Instant::now();
     let command_result = match &cli.command {
 
         command @ Commands::GenerateOverlayFrames {..} => generate_overlay_frames_command(command),
         command @ Commands::GenerateOverlayVideo {..} => generate_overlay_video_command(command).await,
+        command @ Commands::GenerateCalibrationOverlay {..} => generate_calibration_overlay_command(command).await,
         command @ Commands::TranscodeVideo {..} => transcode_video_command(command).await,
-        Commands::DisplayOSDFileInfo { osd_file } => display_osd_file_info_command(osd_file),
+        Commands::DisplayOSDFileInfo { stats, osd_file } => display_osd_file_info_command(osd_file, *stats),
+
+        Commands::DisplayFontInfo { font_options, tile_kind, contact_sheet } => display_font_info_command(font_options, *tile_kind, contact_sheet),
+
+        Commands::List { no_recursive, json, dirs } => list_command(dirs, ! no_recursive, *json),
+
+        command @ Commands::CutVideo {..} => cut_video_command(command).await,
+
+        Commands::AutoSplit { args } =>
+            video::auto_split::auto_split(args).await.map_err(anyhow::Error::new),
+
+        Commands::FixVideoAudio { input_video_file, output_video_file, overwrite, sync, volume, auto_gain, sync_factor, estimate_sync_factor,
+                no_preserve_metadata, container } =>
+            fix_video_audio_command(input_video_file, output_video_file, *overwrite, *sync, *volume, *auto_gain, *sync_factor, *estimate_sync_factor,
+                ! no_preserve_metadata, *container).await,
+
+        Commands::AnalyzeAudio { input_file } => analyze_audio_command(input_file).await,
+
+        Commands::PlayVideoWithOSD { args } => match args.osd_file_path() {
+            Ok(Some(osd_file_path)) => video::play_with_live_osd(args, osd_file_path).await.map_err(anyhow::Error::new),
+            Ok(None) => video::play_with_osd(args).map_err(anyhow::Error::new),
+            Err(error) => Err(anyhow::Error::new(error)),
+        },
+
+        Commands::GenerateThumbnails { args } =>
+            video::thumbnails::generate_thumbnails(args).await.map_err(anyhow::Error::new),
+
+        Commands::ExtractFrames { args } =>
+            video::frame_extraction::extract_frames(args).await.map_err(anyhow::Error::new),
+
+        Commands::CompareVideos { args } =>
+            video::compare::compare_videos(args).await.map_err(anyhow::Error::new),
 
-        Commands::CutVideo { start_end, input_video_file, output_video_file, overwrite } =>
-            video::cut(input_video_file, output_video_file, *overwrite, start_end).await.map_err(anyhow::Error::new),
+        Commands::Pip { args } =>
+            video::pip::pip(args).await.map_err(anyhow::Error::new),
 
-        Commands::FixVideoAudio { input_video_file, output_video_file, overwrite, sync, volume } =>
-            fix_video_audio_command(input_video_file, output_video_file, *overwrite, *sync, *volume).await,
+        Commands::ExportOSDTimestamps { osd_file, osd_frame_shift, osd_video_offset, start_end, format, video_file, output_file } =>
+            start_end.check_valid().map_err(anyhow::Error::new).and_then(|_|
+                video::export_osd_frame_mapping(video_file, osd_file, *osd_frame_shift, *osd_video_offset, start_end, *format, output_file)
+                    .map_err(anyhow::Error::new)
+            ),
 
-        Commands::PlayVideoWithOSD { video_file, osd_video_file } =>
-            video::play_with_osd(video_file, osd_video_file).map_err(anyhow::Error::new),
+        #[cfg(feature = "telemetry")]
+        Commands::ExportTelemetry { format, osd_file, output_file } =>
+            osd::telemetry::export_telemetry(osd_file, *format, output_file).map_err(anyhow::Error::new),
+
+        Commands::ExportOSDFrames { format, osd_file, output_file } =>
+            osd::frames_document::export_osd_frames(osd_file, *format, output_file).map_err(anyhow::Error::new),
+
+        Commands::ImportOSDFile { overwrite, document_file, osd_file } =>
+            osd::frames_document::import_osd_file(document_file, osd_file, *overwrite).map_err(anyhow::Error::new),
+
+        Commands::RepairOSDFile { renumber_duplicates, max_frame_gap, overwrite, osd_file, output_file } =>
+            match output_file.clone().or_else(|| output_path::default_output_path(osd_file, "repaired", Some(OsStr::new("osd")))) {
+                Some(output_file) =>
+                    osd::repair::repair_osd_file(osd_file, output_file, *max_frame_gap, *renumber_duplicates, *overwrite).map(|_| ()).map_err(anyhow::Error::new),
+                None => Err(anyhow!("OSD file has no file name")),
+            },
+
+        Commands::MergeOSDFiles { video_files, durations, overwrite, output_file, osd_files } =>
+            merge_osd_files_command(osd_files, video_files, durations, output_file, *overwrite),
+
+        Commands::PackageSession { output_dir, overwrite, video_files } =>
+            video::package_session(video_files, output_dir, *overwrite).await.map_err(anyhow::Error::new),
+
+        Commands::ExportWeb { args } =>
+            video::web_export::export_web(args).await.map_err(anyhow::Error::new),
+        Commands::MakeClip { args } =>
+            video::clip::make_clip(args).await.map_err(anyhow::Error::new),
+
+        #[cfg(feature = "hwaccel")]
+        Commands::Benchmark { codec, duration, no_save } =>
+            video::benchmark::benchmark(*codec, *duration, ! no_save).await.map_err(anyhow::Error::new),
+
+        Commands::AnalyzeDefects { video_file, samples, save } =>
+            video::defect_analysis::analyze_defects(video_file, *samples, *save).await.map_err(anyhow::Error::new),
+
+        Commands::Doctor { font_dir } => hd_fpv_video_tool::doctor::doctor(font_dir).map_err(anyhow::Error::new),
+
+        Commands::Queue { queue_file, command } => queue_command(queue_file, command),
 
         Commands::GenerateShellAutocompletionFiles { shell } => generate_shell_autocompletion_files_command(shell),
 
         Commands::GenerateManPages => generate_man_pages_command(),
     };
 
+    if cli.notify() {
+        let command_name: &'static str = (&cli.command).into();
+        let error_message = command_result.as_ref().err().map(ToString::to_string);
+        let title = format!("{command_name} {}", if error_message.is_some() { "failed" } else { "completed" });
+        notification::notify(&title, &notification::summary(start_time.elapsed(), error_message.as_deref()));
+    }
+
     if let Err(error) = command_result {
-        log::error!("{}", error);
-        exit(1);
+        exit(error_report::report(&error, cli.error_format()));
     }
 }
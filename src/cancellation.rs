@@ -0,0 +1,99 @@
+// Cancellation of the running command when the process is interrupted
+//
+// The spawned FFMpeg/MPV processes, the output files created by `file::touch` and the temporary files the atomic FFMpeg
+// outputs are written to are tracked so that the processes can be killed and the incomplete outputs removed instead of
+// being left behind.
+
+use std::{collections::BTreeSet, path::{Path, PathBuf}, process::{self, ExitStatus}, sync::{atomic::{AtomicBool, Ordering}, Mutex}};
+
+use thiserror::Error;
+
+
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+static CHILD_PROCESS_IDS: Mutex<BTreeSet<u32>> = Mutex::new(BTreeSet::new());
+static INCOMPLETE_OUTPUTS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Error)]
+#[error("cancelled")]
+pub struct Cancelled;
+
+pub fn is_cancelled() -> bool {
+    CANCELLED.load(Ordering::Relaxed)
+}
+
+/// Keeps a child process registered to be killed on cancellation until it is dropped
+pub struct ChildGuard(u32);
+
+impl Drop for ChildGuard {
+    fn drop(&mut self) {
+        CHILD_PROCESS_IDS.lock().unwrap().remove(&self.0);
+    }
+}
+
+/// Registers a spawned child process to be killed on cancellation, drop the guard once the process has exited
+pub fn track_child(process_id: u32) -> ChildGuard {
+    CHILD_PROCESS_IDS.lock().unwrap().insert(process_id);
+    ChildGuard(process_id)
+}
+
+/// Runs a command to completion with its process killed on cancellation, the replacement of `Command::status`
+pub fn run_tracked(command: &mut process::Command) -> std::io::Result<ExitStatus> {
+    let mut child = command.spawn()?;
+    let _child_guard = track_child(child.id());
+    child.wait()
+}
+
+/// Registers an output file being written to be removed on cancellation
+pub fn track_output<P: AsRef<Path>>(path: P) {
+    INCOMPLETE_OUTPUTS.lock().unwrap().push(path.as_ref().to_path_buf());
+}
+
+/// Unregisters an output file once it has been fully written
+pub fn output_completed<P: AsRef<Path>>(path: P) {
+    INCOMPLETE_OUTPUTS.lock().unwrap().retain(|output_path| output_path != path.as_ref());
+}
+
+#[cfg(unix)]
+fn terminate(process_id: u32) {
+    // SAFETY: only sends a signal, the process ids are removed from the set once the processes have been waited for
+    // the process may have been stopped by a pause, it only handles SIGTERM once continued
+    unsafe {
+        libc::kill(process_id as libc::pid_t, libc::SIGTERM);
+        libc::kill(process_id as libc::pid_t, libc::SIGCONT);
+    }
+}
+
+// the console Ctrl-C event is delivered to the child processes as well
+#[cfg(not(unix))]
+fn terminate(_process_id: u32) {}
+
+/// Kills the tracked child processes and removes the incomplete output files, only the first call has an effect
+pub fn cancel() {
+    if CANCELLED.swap(true, Ordering::Relaxed) { return }
+    for process_id in CHILD_PROCESS_IDS.lock().unwrap().iter() {
+        log::debug!("killing child process {process_id}");
+        terminate(*process_id);
+    }
+    for output_path in INCOMPLETE_OUTPUTS.lock().unwrap().drain(..) {
+        match fs_err::remove_file(&output_path) {
+            Ok(()) => log::info!("removed incomplete output file {}", output_path.to_string_lossy()),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {},
+            Err(error) => log::warn!("failed removing incomplete output file: {error}"),
+        }
+    }
+}
+
+/// Waits for SIGINT (Ctrl-C) or SIGTERM
+#[cfg(feature = "overlay")]
+pub async fn wait_for_signal() -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        let mut terminate_signal = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result,
+            _ = terminate_signal.recv() => Ok(()),
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await
+}
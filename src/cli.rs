@@ -2,4 +2,20 @@
 pub mod font_options;
 pub mod transcode_video_args;
 pub mod generate_overlay_args;
-pub mod start_end_args;
\ No newline at end of file
+pub mod start_end_args;
+pub mod stick_overlay_args;
+pub mod enlargement_args;
+pub mod play_with_osd_args;
+pub mod generate_thumbnails_args;
+pub mod auto_split_args;
+pub mod video_transform_args;
+pub mod speed_args;
+pub mod music_args;
+pub mod metadata_args;
+pub mod export_web_args;
+pub mod make_clip_args;
+pub mod extract_frames_args;
+pub mod color_grading_args;
+pub mod remote_encoding_args;
+pub mod compare_videos_args;
+pub mod pip_args;
\ No newline at end of file
@@ -0,0 +1,71 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use getset::{Getters, CopyGetters};
+
+use crate::osd::{file::find_associated_to_video_file, overlay::video_timeline::OSDFrameShift};
+
+use super::transcode_video_args::RequestedOSDButNoFileProvidedNorFound;
+
+
+#[derive(Args, Getters, CopyGetters)]
+pub struct AutoSplitArgs {
+
+    /// path to FPV.WTF .osd file to detect the flights from, defaults to the OSD file associated with the video file
+    ///
+    /// The OSD file is searched the same way as with the --osd option of the `transcode-video` command.
+    #[clap(long, value_parser, value_name = "OSD file path")]
+    osd_file: Option<PathBuf>,
+
+    /// shift frames to sync OSD with video: OSD frames (60Hz) like -37 or -37f, seconds like +1.5s or a timestamp like -00:00:02.25
+    #[clap(short = 'o', long, value_parser, allow_hyphen_values(true), value_name = "shift")]
+    #[getset(get_copy = "pub")]
+    osd_frame_shift: Option<OSDFrameShift>,
+
+    /// flights shorter than this are ignored
+    #[clap(long, value_parser, default_value_t = 10, value_name = "seconds")]
+    #[getset(get_copy = "pub")]
+    min_flight_duration: u32,
+
+    /// time kept before arming and after disarming
+    #[clap(long, value_parser, default_value_t = 2, value_name = "seconds")]
+    #[getset(get_copy = "pub")]
+    padding: u32,
+
+    /// only print the detected flights without cutting the video
+    #[clap(long, value_parser)]
+    #[getset(get_copy = "pub")]
+    list: bool,
+
+    /// do not copy the metadata and the modification time of the input video to the flight video files
+    #[clap(long, value_parser)]
+    no_preserve_metadata: bool,
+
+    /// overwrite output files if they exist
+    #[clap(short = 'y', long, value_parser)]
+    #[getset(get_copy = "pub")]
+    overwrite: bool,
+
+    /// input video file path
+    #[getset(get = "pub")]
+    video_file: PathBuf,
+
+    /// directory in which the flight video files are written, defaults to the directory of the input video file
+    #[getset(get = "pub")]
+    output_dir: Option<PathBuf>,
+}
+
+impl AutoSplitArgs {
+
+    pub fn preserve_metadata(&self) -> bool {
+        ! self.no_preserve_metadata
+    }
+
+    pub fn osd_file_path(&self) -> Result<PathBuf, RequestedOSDButNoFileProvidedNorFound> {
+        match &self.osd_file {
+            Some(osd_file) => Ok(osd_file.clone()),
+            None => find_associated_to_video_file(&self.video_file).ok_or(RequestedOSDButNoFileProvidedNorFound),
+        }
+    }
+
+}
@@ -0,0 +1,39 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use getset::{CopyGetters, Getters};
+
+use crate::{ffmpeg, video::color_grading::ColorEq};
+
+
+#[derive(Args, Getters, CopyGetters)]
+pub struct ColorGradingArgs {
+
+    /// apply a 3D LUT to the video, e.g. to convert a flat color profile
+    ///
+    /// Any file format supported by the FFMpeg lut3d filter can be used: .cube, .3dl, .dat, .m3d or .csp.
+    /// The LUT is applied before the OSD is burnt so that the OSD colors are not altered.
+    #[clap(long, value_parser, value_name = "LUT file path")]
+    #[getset(get = "pub")]
+    lut3d: Option<PathBuf>,
+
+    /// adjust the brightness, contrast and saturation of the video
+    ///
+    /// The format is <setting>=<value>[:<setting>=<value>...], e.g. `contrast=1.1:saturation=1.3`.{n}
+    /// Settings: brightness from -1 to 1 (default 0), contrast from -1000 to 1000 (default 1), saturation from 0 to 3 (default 1).{n}
+    /// The adjustments are applied after the LUT and before the OSD is burnt so that the OSD colors are not altered.
+    #[clap(long, value_parser, value_name = "adjustments")]
+    #[getset(get_copy = "pub")]
+    eq: Option<ColorEq>,
+
+}
+
+impl ColorGradingArgs {
+
+    /// FFMpeg filters applying the color grading, empty if no color grading was requested
+    pub fn ffmpeg_filter_parts(&self) -> Vec<String> {
+        let lut_filter = self.lut3d.as_ref().map(|lut_file| format!("lut3d=file={}", ffmpeg::escape_filter_option_value(lut_file.to_string_lossy())));
+        lut_filter.into_iter().chain(self.eq.map(|color_eq| color_eq.ffmpeg_filter_string())).collect()
+    }
+
+}
@@ -0,0 +1,73 @@
+use std::{ffi::OsStr, path::PathBuf};
+
+use clap::Args;
+use getset::{Getters, CopyGetters};
+
+use crate::{output_path, video::compare::CompareLayout};
+
+
+#[derive(Args, Getters, CopyGetters)]
+pub struct CompareVideosArgs {
+
+    /// how the videos are arranged
+    #[clap(short, long, value_parser, default_value_t = CompareLayout::default())]
+    #[getset(get_copy = "pub")]
+    layout: CompareLayout,
+
+    /// common height (side-by-side layout) or width (top-bottom layout) the videos are scaled to, defaults to the smallest one
+    #[clap(short, long, value_parser = clap::value_parser!(u32).range(16..=8192), value_name = "pixels")]
+    #[getset(get_copy = "pub")]
+    size: Option<u32>,
+
+    /// OSD overlay video generated with the `generate-overlay-video` command to overlay onto the first video
+    #[clap(long, value_parser, value_name = "OSD video file path")]
+    #[getset(get = "pub")]
+    first_osd_video: Option<PathBuf>,
+
+    /// OSD overlay video generated with the `generate-overlay-video` command to overlay onto the second video
+    #[clap(long, value_parser, value_name = "OSD video file path")]
+    #[getset(get = "pub")]
+    second_osd_video: Option<PathBuf>,
+
+    /// video encoder to use
+    ///
+    /// This value is directly passed to the `-c:v` FFMpeg argument.{n}
+    /// Run `ffmpeg -encoders` for a list of available encoders
+    #[clap(long, value_parser, default_value = "libx265")]
+    #[getset(get = "pub")]
+    video_encoder: String,
+
+    /// video constant quality setting
+    #[clap(long, value_parser, default_value_t = 25)]
+    #[getset(get_copy = "pub")]
+    video_crf: u8,
+
+    /// overwrite output file if it exists
+    #[clap(short = 'y', long, value_parser)]
+    #[getset(get_copy = "pub")]
+    overwrite: bool,
+
+    #[getset(get = "pub")]
+    first_video_file: PathBuf,
+
+    #[getset(get = "pub")]
+    second_video_file: PathBuf,
+
+    /// output video file, defaults to <FIRST_VIDEO_FILE basename>_vs_<SECOND_VIDEO_FILE basename>.mp4
+    output_video_file: Option<PathBuf>,
+}
+
+impl CompareVideosArgs {
+
+    /// Output video file, `None` if it is not specified and one of the video files has no file name
+    pub fn output_video_file(&self) -> Option<PathBuf> {
+        match &self.output_video_file {
+            Some(output_video_file) => Some(output_video_file.clone()),
+            None => {
+                let suffix = format!("vs_{}", self.second_video_file.file_stem()?.to_string_lossy());
+                output_path::default_output_path(&self.first_video_file, &suffix, Some(OsStr::new("mp4")))
+            },
+        }
+    }
+
+}
@@ -0,0 +1,38 @@
+use clap::Args;
+use getset::{CopyGetters, Getters};
+
+use crate::osd::{
+    self,
+    overlay::enlargement::{self, Enlargement, EnlargementFactor},
+};
+
+
+#[derive(Args, Getters, CopyGetters)]
+pub struct EnlargementArgs {
+
+    /// render rectangular regions of the OSD larger than the rest of the OSD, e.g. to keep the warnings readable when the OSD is scaled down
+    ///
+    /// The parameter is a `;` separated list of regions with the format of --hide-regions/--osd-hide-regions, time ranges are not
+    /// supported. Each region is centered on its original location and moved back inside the frame if it does not fit.
+    #[clap(long, value_parser, value_delimiter = ';', value_name = "REGIONS")]
+    #[getset(get = "pub")]
+    enlarge_regions: Vec<osd::Region>,
+
+    /// render OSD items larger than the rest of the OSD, see --hide-items/--osd-hide-items for the available items
+    #[clap(long, value_parser, value_delimiter = ',', value_name = "ITEM_NAMES")]
+    #[getset(get = "pub")]
+    enlarge_items: Vec<String>,
+
+    /// scale factor of the enlarged regions and items relative to the rest of the OSD, greater than 1 and up to 4
+    #[clap(long, value_parser, value_name = "factor", default_value_t = enlargement::DEFAULT_FACTOR)]
+    #[getset(get_copy = "pub")]
+    enlarge_factor: EnlargementFactor,
+}
+
+impl EnlargementArgs {
+
+    pub fn enlargement(&self) -> Option<Enlargement> {
+        Enlargement::new(self.enlarge_factor, &self.enlarge_regions, &self.enlarge_items)
+    }
+
+}
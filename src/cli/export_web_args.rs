@@ -0,0 +1,65 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use getset::{Getters, CopyGetters};
+
+use crate::{ffmpeg::bitrate::Bitrate, output_path};
+
+
+#[derive(Args, Getters, CopyGetters)]
+pub struct ExportWebArgs {
+
+    /// overlay the OSD video associated with the input video, named like the ones generated by the `generate-overlay-video` command
+    #[clap(long, value_parser, conflicts_with = "osd_video")]
+    #[getset(get_copy = "pub")]
+    osd: bool,
+
+    /// overlay this OSD video generated with the `generate-overlay-video` command
+    #[clap(long, value_parser, value_name = "OSD video file path")]
+    #[getset(get = "pub")]
+    osd_video: Option<PathBuf>,
+
+    /// heights of the renditions of the HLS ladder, the ones higher than the input video are skipped
+    #[clap(long, value_parser, value_delimiter = ',', default_value = "1080,720,480", value_name = "heights")]
+    #[getset(get = "pub")]
+    renditions: Vec<u32>,
+
+    /// duration of the HLS segments
+    #[clap(long, value_parser = clap::value_parser!(u32).range(1..=60), default_value_t = 6, value_name = "seconds")]
+    #[getset(get_copy = "pub")]
+    segment_duration: u32,
+
+    /// video constant quality setting of the renditions, their bitrate is also capped according to their resolution
+    #[clap(long, value_parser, default_value_t = 23)]
+    #[getset(get_copy = "pub")]
+    video_crf: u8,
+
+    /// audio bitrate of the renditions
+    #[clap(long, value_parser, default_value = "128k")]
+    #[getset(get_copy = "pub")]
+    audio_bitrate: Bitrate,
+
+    /// overwrite the output files if they exist
+    #[clap(short = 'y', long, value_parser)]
+    #[getset(get_copy = "pub")]
+    overwrite: bool,
+
+    /// input video file path
+    #[getset(get = "pub")]
+    input_video_file: PathBuf,
+
+    /// directory in which the playlists and segments are written, defaults to <INPUT_VIDEO_FILE basename>_hls
+    output_dir: Option<PathBuf>,
+}
+
+impl ExportWebArgs {
+
+    /// Output directory, `None` if it is not specified and the input video file has no file name
+    pub fn output_dir(&self) -> Option<PathBuf> {
+        match &self.output_dir {
+            Some(output_dir) => Some(output_dir.clone()),
+            None => output_path::default_output_path(&self.input_video_file, "hls", None),
+        }
+    }
+
+}
@@ -0,0 +1,89 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use getset::{Getters, CopyGetters};
+
+use crate::{output_path, osd::{overlay::{scaling::OSDScalingArgs, video_timeline::OSDFrameShift}, file::find_associated_to_video_file}, video::frame_extraction::ExtractedFrameFormat};
+
+use super::{font_options::OSDFontOptions, start_end_args::StartEndArgs, transcode_video_args::RequestedOSDButNoFileProvidedNorFound};
+
+
+#[derive(Args, Getters, CopyGetters)]
+#[clap(group(clap::ArgGroup::new("selection").required(true).args(&["frames", "every"])))]
+pub struct ExtractFramesArgs {
+
+    /// indices of the video frames to extract, starting from 0
+    #[clap(long, value_parser, value_delimiter = ',', value_name = "indices")]
+    #[getset(get = "pub")]
+    frames: Vec<u32>,
+
+    /// extract one frame every this many video frames, between the `--start` and `--end` timestamps if specified
+    #[clap(long, value_parser = clap::value_parser!(u32).range(1..), value_name = "frames")]
+    #[getset(get_copy = "pub")]
+    every: Option<u32>,
+
+    #[clap(flatten)]
+    #[getset(get = "pub")]
+    start_end: StartEndArgs,
+
+    /// image file format of the extracted frames
+    #[clap(short, long, value_parser, default_value_t = ExtractedFrameFormat::default())]
+    #[getset(get_copy = "pub")]
+    format: ExtractedFrameFormat,
+
+    /// composite the OSD onto each extracted frame, try to find the OSD file automatically
+    ///
+    /// The OSD file is searched the same way as with the --osd option of the `transcode-video` command.
+    #[clap(long, value_parser)]
+    #[getset(get_copy = "pub")]
+    osd: bool,
+
+    /// path to FPV.WTF .osd file to composite onto each extracted frame
+    #[clap(long, value_parser, value_name = "OSD file path")]
+    osd_file: Option<PathBuf>,
+
+    /// shift frames to sync OSD with video: OSD frames (60Hz) like -37 or -37f, seconds like +1.5s or a timestamp like -00:00:02.25
+    #[clap(short = 'o', long, value_parser, allow_hyphen_values(true), value_name = "shift")]
+    #[getset(get_copy = "pub")]
+    osd_frame_shift: Option<OSDFrameShift>,
+
+    #[clap(flatten)]
+    #[getset(get = "pub")]
+    osd_scaling_args: OSDScalingArgs,
+
+    #[clap(flatten)]
+    #[getset(get = "pub")]
+    osd_font_options: OSDFontOptions,
+
+    /// overwrite the extracted frame files if they exist
+    #[clap(short = 'y', long, value_parser)]
+    #[getset(get_copy = "pub")]
+    overwrite: bool,
+
+    #[getset(get = "pub")]
+    video_file: PathBuf,
+
+    /// directory in which the frames are written, defaults to <VIDEO_FILE basename>_frames
+    output_dir: Option<PathBuf>,
+}
+
+impl ExtractFramesArgs {
+
+    /// Path of the OSD file to composite onto the frames, none if the OSD is not requested
+    pub fn osd_file_path(&self) -> Result<Option<PathBuf>, RequestedOSDButNoFileProvidedNorFound> {
+        Ok(match (self.osd, &self.osd_file) {
+            (_, Some(osd_file)) => Some(osd_file.clone()),
+            (true, None) => Some(find_associated_to_video_file(&self.video_file).ok_or(RequestedOSDButNoFileProvidedNorFound)?),
+            (false, None) => None,
+        })
+    }
+
+    /// Output directory, `None` if it is not specified and the video file has no file name
+    pub fn output_dir(&self) -> Option<PathBuf> {
+        match &self.output_dir {
+            Some(output_dir) => Some(output_dir.clone()),
+            None => output_path::default_output_path(&self.video_file, "frames", None),
+        }
+    }
+
+}
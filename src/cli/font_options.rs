@@ -6,6 +6,10 @@ use clap::Args;
 use derive_more::From;
 use thiserror::Error;
 
+use hd_fpv_osd_font_tool::prelude::tile;
+
+use crate::osd::{FontDir, FontVariant, tile_remap::TileRemap};
+
 const DEFAULT_HOME_RELATIVE_FONT_DIR: &str = ".local/share/hd_fpv_video_tool/fonts";
 const FONT_DIR_ENV_VAR_NAME: &str = "DJI_OSD_FONTS_DIR";
 
@@ -18,6 +22,35 @@ pub struct FontOptions {
     /// force using this font identifier when loading fonts, default is automatic
     #[clap(short = 'i', long, value_parser, value_name = "ident")]
     font_ident: Option<String>,
+
+    /// force using this font variant instead of the one read from the OSD file
+    ///
+    /// Useful when the OSD file reports an unknown font variant and the wrong tile set is used.
+    #[clap(long, value_parser, value_name = "variant")]
+    font_variant: Option<FontVariant>,
+
+    /// font variants tried in order when the font of the requested variant or ident is missing
+    #[clap(long, value_parser, value_delimiter = ',', default_value = "generic", value_name = "variants")]
+    font_fallback: Vec<FontVariant>,
+
+    /// draw OSD tile indices with the glyphs of other font tile indices
+    ///
+    /// Format: <first index>[-<last index>]:<target index>, e.g. `256-511:0` draws the second font page tile indices with
+    /// the first page glyphs. Useful with multi-page fonts whose pages are not laid out like the flight controller expects.
+    #[clap(long, value_parser, value_delimiter = ',', value_name = "remaps")]
+    font_tile_remap: Vec<TileRemap>,
+
+    /// file mapping tile indices to PNG images drawn instead of the font glyphs, e.g. to display a logo
+    ///
+    /// Each line is formatted as <tile index> <PNG file path>. Defaults to the `custom_tiles.txt` file of the font directory if it exists.
+    #[clap(long, value_parser, value_name = "file")]
+    font_custom_tiles: Option<PathBuf>,
+
+    /// TOML file of the substitution tables drawing the glyphs missing from the font with equivalent glyphs
+    ///
+    /// Defaults to the `glyph_substitutions.toml` file of the font directory if it exists.
+    #[clap(long, value_parser, value_name = "file")]
+    font_glyph_substitutions: Option<PathBuf>,
 }
 
 #[derive(Args)]
@@ -29,6 +62,35 @@ pub struct OSDFontOptions {
     /// force using this font identifier when loading fonts, default is automatic
     #[clap(short = 'i', long, value_parser, value_name = "ident")]
     osd_font_ident: Option<String>,
+
+    /// force using this font variant instead of the one read from the OSD file
+    ///
+    /// Useful when the OSD file reports an unknown font variant and the wrong tile set is used.
+    #[clap(long, value_parser, value_name = "variant")]
+    osd_font_variant: Option<FontVariant>,
+
+    /// font variants tried in order when the font of the requested variant or ident is missing
+    #[clap(long, value_parser, value_delimiter = ',', default_value = "generic", value_name = "variants")]
+    osd_font_fallback: Vec<FontVariant>,
+
+    /// draw OSD tile indices with the glyphs of other font tile indices
+    ///
+    /// Format: <first index>[-<last index>]:<target index>, e.g. `256-511:0` draws the second font page tile indices with
+    /// the first page glyphs. Useful with multi-page fonts whose pages are not laid out like the flight controller expects.
+    #[clap(long, value_parser, value_delimiter = ',', value_name = "remaps")]
+    osd_font_tile_remap: Vec<TileRemap>,
+
+    /// file mapping tile indices to PNG images drawn instead of the font glyphs, e.g. to display a logo
+    ///
+    /// Each line is formatted as <tile index> <PNG file path>. Defaults to the `custom_tiles.txt` file of the font directory if it exists.
+    #[clap(long, value_parser, value_name = "file")]
+    osd_font_custom_tiles: Option<PathBuf>,
+
+    /// TOML file of the substitution tables drawing the glyphs missing from the font with equivalent glyphs
+    ///
+    /// Defaults to the `glyph_substitutions.toml` file of the font directory if it exists.
+    #[clap(long, value_parser, value_name = "file")]
+    osd_font_glyph_substitutions: Option<PathBuf>,
 }
 
 #[derive(Debug, Error, From)]
@@ -42,7 +104,17 @@ pub enum OSDFontDirError {
     },
 }
 
-fn font_dir_base(font_dir: &Option<PathBuf>) -> Result<PathBuf, OSDFontDirError> {
+/// Parses a tile kind argument: `sd` or `hd`
+pub fn parse_tile_kind(value: &str) -> Result<tile::Kind, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "sd" => Ok(tile::Kind::SD),
+        "hd" => Ok(tile::Kind::HD),
+        _ => Err("the tile kind is either sd or hd".to_owned()),
+    }
+}
+
+/// Fonts directory: the specified one or else the one of the environment variable or else the default one
+pub fn font_dir_base(font_dir: &Option<PathBuf>) -> Result<PathBuf, OSDFontDirError> {
     let font_dir = match font_dir {
         Some(font_dir) => font_dir.clone(),
         None => {
@@ -65,6 +137,19 @@ impl FontOptions {
         font_dir_base(&self.font_dir)
     }
 
+    /// Font directory loading the fonts with the requested fallback chain, tile remaps, custom tiles and glyph substitutions
+    pub fn font_dir_with_fallback(&self) -> Result<FontDir, OSDFontDirError> {
+        let mut font_dir = FontDir::new(self.font_dir()?);
+        font_dir.set_fallback_variants(&self.font_fallback).set_tile_remaps(&self.font_tile_remap)
+            .set_custom_tiles_file(self.font_custom_tiles.clone()).set_glyph_substitutions_file(self.font_glyph_substitutions.clone());
+        Ok(font_dir)
+    }
+
+    /// Font variant to use: the forced one or else the one read from the OSD file
+    pub fn font_variant(&self, osd_file_font_variant: FontVariant) -> FontVariant {
+        self.font_variant.unwrap_or(osd_file_font_variant)
+    }
+
     pub fn font_ident(&self) -> Option<Option<&str>> {
         match self.font_ident.as_deref() {
             Some("") => Some(None),
@@ -81,6 +166,19 @@ impl OSDFontOptions {
         font_dir_base(&self.osd_font_dir)
     }
 
+    /// Font directory loading the fonts with the requested fallback chain, tile remaps, custom tiles and glyph substitutions
+    pub fn osd_font_dir_with_fallback(&self) -> Result<FontDir, OSDFontDirError> {
+        let mut font_dir = FontDir::new(self.osd_font_dir()?);
+        font_dir.set_fallback_variants(&self.osd_font_fallback).set_tile_remaps(&self.osd_font_tile_remap)
+            .set_custom_tiles_file(self.osd_font_custom_tiles.clone()).set_glyph_substitutions_file(self.osd_font_glyph_substitutions.clone());
+        Ok(font_dir)
+    }
+
+    /// Font variant to use: the forced one or else the one read from the OSD file
+    pub fn osd_font_variant(&self, osd_file_font_variant: FontVariant) -> FontVariant {
+        self.osd_font_variant.unwrap_or(osd_file_font_variant)
+    }
+
     pub fn osd_font_ident(&self) -> Option<Option<&str>> {
         match self.osd_font_ident.as_deref() {
             Some("") => Some(None),
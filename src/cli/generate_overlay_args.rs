@@ -6,9 +6,9 @@ use anyhow::anyhow;
 use itertools::Itertools;
 use strum::IntoEnumIterator;
 
-use crate::{prelude::ScalingArgs, video, osd::{item::LocationData, font_variant::FontVariant}};
+use crate::{prelude::ScalingArgs, video, osd::{item::LocationData, font_variant::FontVariant, overlay::video_timeline::OSDFrameShift, text_injection::TextInjection, units::UnitSystem}};
 
-use super::{font_options::FontOptions, start_end_args::StartEndArgs};
+use super::{enlargement_args::EnlargementArgs, font_options::FontOptions, start_end_args::StartEndArgs, stick_overlay_args::StickOverlayArgs};
 use crate::osd;
 
 
@@ -25,15 +25,32 @@ pub struct GenerateOverlayArgs {
     /// hide rectangular regions from the OSD
     ///
     /// The parameter is a `;` separated list of regions.{n}
-    /// The format for a region is: <left_x>,<top_y>[:<width>x<height>]{n}
-    /// If the size is not specified it will default to 1x1
+    /// The format for a region is: [<start>-<end>@]<left_x>,<top_y>[:<width>x<height>]{n}
+    /// If the size is not specified it will default to 1x1{n}
+    /// If a time range is specified the region is only hidden from <start> (included) to <end> (excluded), timestamps are formatted as
+    /// [HH:]MM:SS and either of them can be omitted, e.g. `0:00-0:30@1,22:10x2`
     #[clap(long, value_parser, value_delimiter = ';', value_name = "REGIONS")]
     hide_regions: Vec<osd::Region>,
 
+    /// fail when a region passed to --hide-regions does not fit the OSD grid instead of correcting it
+    ///
+    /// Without this option regions with swapped x/y coordinates are swapped back and regions extending beyond the grid are clamped, with a warning
+    #[clap(long, value_parser)]
+    #[getset(skip)]
+    #[getset(get_copy = "pub")]
+    strict_regions: bool,
+
     /// hide items from the OSD
     #[clap(long, value_parser, value_delimiter = ',', value_name = "ITEM_NAMES", help = osd_hide_items_arg_help())]
     hide_items: Vec<String>,
 
+    /// write text into the OSD, e.g. the craft name or the pilot handle when the flight controller did not display it
+    ///
+    /// The format is <x>,<y>:<text>, x and y being the OSD grid coordinates of the first character. The option can be repeated.{n}
+    /// The text is rendered in upper case with the OSD font, over the tiles displayed at its location.
+    #[clap(long, value_parser, value_name = "x,y:text")]
+    add_text: Vec<TextInjection>,
+
     #[clap(flatten)]
     start_end: StartEndArgs,
 
@@ -43,10 +60,37 @@ pub struct GenerateOverlayArgs {
     #[clap(flatten)]
     font_options: FontOptions,
 
+    #[clap(flatten)]
+    stick_overlay_args: StickOverlayArgs,
+
+    #[clap(flatten)]
+    enlargement_args: EnlargementArgs,
+
+    /// convert the altitude and speed displayed by the OSD to the units of this unit system
+    ///
+    /// Only supported with the font variants providing the location of the `alt` and `speed` items (Ardupilot, INAV).
+    #[clap(long, value_parser)]
+    #[getset(skip)]
+    #[getset(get_copy = "pub")]
+    units: Option<UnitSystem>,
+
+    /// smooth the altitude and speed values between OSD updates
+    ///
+    /// The OSD is usually updated at about 10Hz, with this option frames are inserted between the updates with the
+    /// values interpolated so that they change smoothly on 60FPS videos. Only supported with the font variants
+    /// providing the location of the `alt` and `speed` items (Ardupilot, INAV).
+    #[clap(long, value_parser)]
+    #[getset(skip)]
+    #[getset(get_copy = "pub")]
+    interpolate_values: bool,
+
     /// Shift the output by that number of frames. Use this option to sync the OSD to a particular video.
-    #[clap(short = 'o', long, value_parser, value_name = "frames", allow_negative_numbers(true))]
+    ///
+    /// The shift is a number of OSD frames (60Hz) like -37 or -37f, seconds like +1.5s or a timestamp like -00:00:02.25.
+    /// Fractional shifts are rounded to the nearest frame.
+    #[clap(short = 'o', long, value_parser, value_name = "shift", allow_hyphen_values(true))]
     #[getset(skip)]
-    frame_shift: Option<i32>,
+    frame_shift: Option<OSDFrameShift>,
 
     /// path to FPV.WTF .osd file
     osd_file: PathBuf,
@@ -81,17 +125,13 @@ impl GenerateOverlayArgs {
         Ok(())
     }
 
+    /// OSD frame shift to apply, fractional shifts are rounded to the nearest frame since the overlay frames are generated with the OSD 60Hz time base
     pub fn frame_shift(&self) -> anyhow::Result<i32> {
         Ok(match (self.frame_shift, &self.target_video_file) {
-            (Some(frame_shift), _) => frame_shift,
+            (Some(frame_shift), _) => frame_shift.nearest_frames(),
             (None, Some(target_video_file)) => {
-                if video::probe(target_video_file)?.has_audio() {
-                    let frame_shift = crate::osd::dji::AU_OSD_FRAME_SHIFT;
-                    log::info!("target video file contains audio, assuming DJI AU origin, applying {frame_shift} OSD frames shift");
-                    frame_shift
-                } else {
-                    0
-                }
+                let mut osd_file = osd::file::open(&self.osd_file)?;
+                video::osd_frame_shift_for_video(None, &video::probe(target_video_file)?, &mut osd_file)?.nearest_frames()
             },
             (None, None) => 0,
         })
@@ -0,0 +1,79 @@
+use std::{ffi::OsStr, path::PathBuf};
+
+use clap::Args;
+use getset::{Getters, CopyGetters};
+
+use crate::{output_path, osd::{overlay::{scaling::OSDScalingArgs, video_timeline::OSDFrameShift}, file::find_associated_to_video_file}, video};
+
+use super::{font_options::OSDFontOptions, transcode_video_args::RequestedOSDButNoFileProvidedNorFound};
+
+
+#[derive(Args, Getters, CopyGetters)]
+pub struct GenerateThumbnailsArgs {
+
+    /// number of thumbnail columns and rows of the contact sheet
+    #[clap(short, long, value_parser, value_name = "COLUMNSxROWS", default_value = "4x4")]
+    #[getset(get_copy = "pub")]
+    grid: video::Dimensions,
+
+    /// width of each thumbnail in pixels, the height is computed to keep the aspect ratio of the video
+    #[clap(short = 'w', long, value_parser = clap::value_parser!(u16).range(16..=3840), default_value_t = 320, value_name = "pixels")]
+    #[getset(get_copy = "pub")]
+    thumbnail_width: u16,
+
+    /// burn the OSD onto each thumbnail, try to find the OSD file automatically
+    ///
+    /// The OSD file is searched the same way as with the --osd option of the `transcode-video` command.
+    #[clap(long, value_parser)]
+    #[getset(get_copy = "pub")]
+    osd: bool,
+
+    /// path to FPV.WTF .osd file to burn onto each thumbnail
+    #[clap(long, value_parser, value_name = "OSD file path")]
+    osd_file: Option<PathBuf>,
+
+    /// shift frames to sync OSD with video: OSD frames (60Hz) like -37 or -37f, seconds like +1.5s or a timestamp like -00:00:02.25
+    #[clap(short = 'o', long, value_parser, allow_hyphen_values(true), value_name = "shift")]
+    #[getset(get_copy = "pub")]
+    osd_frame_shift: Option<OSDFrameShift>,
+
+    #[clap(flatten)]
+    #[getset(get = "pub")]
+    osd_scaling_args: OSDScalingArgs,
+
+    #[clap(flatten)]
+    #[getset(get = "pub")]
+    osd_font_options: OSDFontOptions,
+
+    /// overwrite output file if it exists
+    #[clap(short = 'y', long, value_parser)]
+    #[getset(get_copy = "pub")]
+    overwrite: bool,
+
+    #[getset(get = "pub")]
+    video_file: PathBuf,
+
+    /// output image file, defaults to <VIDEO_FILE basename>_thumbnails.jpg
+    output_file: Option<PathBuf>,
+}
+
+impl GenerateThumbnailsArgs {
+
+    /// Path of the OSD file to burn onto the thumbnails, none if the OSD is not requested
+    pub fn osd_file_path(&self) -> Result<Option<PathBuf>, RequestedOSDButNoFileProvidedNorFound> {
+        Ok(match (self.osd, &self.osd_file) {
+            (_, Some(osd_file)) => Some(osd_file.clone()),
+            (true, None) => Some(find_associated_to_video_file(&self.video_file).ok_or(RequestedOSDButNoFileProvidedNorFound)?),
+            (false, None) => None,
+        })
+    }
+
+    /// Output file, `None` if it is not specified and the video file has no file name
+    pub fn output_file(&self) -> Option<PathBuf> {
+        match &self.output_file {
+            Some(output_file) => Some(output_file.clone()),
+            None => output_path::default_output_path(&self.video_file, "thumbnails", Some(OsStr::new("jpg"))),
+        }
+    }
+
+}
@@ -0,0 +1,71 @@
+use std::{ffi::OsStr, path::PathBuf};
+
+use clap::Args;
+use getset::{Getters, CopyGetters};
+
+use crate::{output_path, video::clip::ClipFormat};
+
+use super::start_end_args::StartEndArgs;
+
+
+#[derive(Args, Getters, CopyGetters)]
+pub struct MakeClipArgs {
+
+    #[clap(flatten)]
+    #[getset(get = "pub")]
+    start_end: StartEndArgs,
+
+    /// animated image format of the clip
+    #[clap(short, long, value_parser, default_value_t = ClipFormat::default())]
+    #[getset(get_copy = "pub")]
+    format: ClipFormat,
+
+    /// width of the clip, the height keeps the aspect ratio of the video
+    #[clap(short, long, value_parser = clap::value_parser!(u32).range(16..=3840), default_value_t = 480, value_name = "pixels")]
+    #[getset(get_copy = "pub")]
+    width: u32,
+
+    /// frame rate of the clip
+    #[clap(long, value_parser = clap::value_parser!(u32).range(1..=60), default_value_t = 15)]
+    #[getset(get_copy = "pub")]
+    fps: u32,
+
+    /// number of times the clip is repeated after being played, 0 to loop forever
+    #[clap(long, value_parser, default_value_t = 0)]
+    #[getset(get_copy = "pub")]
+    loop_count: u16,
+
+    /// overlay the OSD video associated with the input video, named like the ones generated by the `generate-overlay-video` command
+    #[clap(long, value_parser, conflicts_with = "osd_video")]
+    #[getset(get_copy = "pub")]
+    osd: bool,
+
+    /// overlay this OSD video generated with the `generate-overlay-video` command
+    #[clap(long, value_parser, value_name = "OSD video file path")]
+    #[getset(get = "pub")]
+    osd_video: Option<PathBuf>,
+
+    /// overwrite output file if it exists
+    #[clap(short = 'y', long, value_parser)]
+    #[getset(get_copy = "pub")]
+    overwrite: bool,
+
+    /// input video file path
+    #[getset(get = "pub")]
+    input_video_file: PathBuf,
+
+    /// output file, defaults to <INPUT_VIDEO_FILE basename>_clip.<gif|webp>
+    output_file: Option<PathBuf>,
+}
+
+impl MakeClipArgs {
+
+    /// Output file, `None` if it is not specified and the input video file has no file name
+    pub fn output_file(&self) -> Option<PathBuf> {
+        match &self.output_file {
+            Some(output_file) => Some(output_file.clone()),
+            None => output_path::default_output_path(&self.input_video_file, "clip", Some(OsStr::new(self.format.extension()))),
+        }
+    }
+
+}
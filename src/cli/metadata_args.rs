@@ -0,0 +1,52 @@
+use clap::Args;
+use getset::{CopyGetters, Getters};
+
+
+#[derive(Args, Getters, CopyGetters)]
+pub struct MetadataArgs {
+
+    /// title written in the metadata of the output video
+    #[clap(long, value_parser, value_name = "title")]
+    #[getset(get = "pub")]
+    title: Option<String>,
+
+    /// copy the creation time of the input video into the metadata of the output video
+    #[clap(long, value_parser)]
+    #[getset(get_copy = "pub")]
+    copy_creation_time: bool,
+
+    /// write the first GPS position of the OSD telemetry as the location of the output video, requires burning the OSD
+    #[cfg(feature = "telemetry")]
+    #[clap(long, value_parser)]
+    #[getset(get_copy = "pub")]
+    gps_location: bool,
+
+    /// add a chapter mark for each flight detected between the arm and disarm events on the OSD, requires burning the OSD
+    #[clap(long, value_parser)]
+    #[getset(get_copy = "pub")]
+    flight_chapters: bool,
+
+    /// do not copy the metadata and the modification time of the input video to the output video
+    #[clap(long, value_parser)]
+    no_preserve_metadata: bool,
+
+}
+
+impl MetadataArgs {
+
+    /// Whether an option needs the OSD telemetry
+    pub fn requires_osd(&self) -> bool {
+        self.gps_location() || self.flight_chapters
+    }
+
+    /// Whether the GPS position is written as the location of the output video, always false without the `telemetry` feature
+    #[cfg(not(feature = "telemetry"))]
+    pub fn gps_location(&self) -> bool {
+        false
+    }
+
+    pub fn preserve_metadata(&self) -> bool {
+        ! self.no_preserve_metadata
+    }
+
+}
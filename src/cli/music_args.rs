@@ -0,0 +1,34 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use getset::{CopyGetters, Getters};
+
+
+#[derive(Args, Getters, CopyGetters)]
+pub struct MusicArgs {
+
+    /// mix the audio of this file into the output as background music
+    ///
+    /// The music starts at the beginning of the output video and is cut at its end. Videos without audio get the music as their audio track.
+    #[clap(long, value_parser, value_name = "audio file path")]
+    #[getset(get = "pub")]
+    music: Option<PathBuf>,
+
+    /// volume factor applied to the background music, e.g. `0.5` for half the original volume
+    #[clap(long, value_parser = parse_music_volume, value_name = "factor", default_value_t = 0.3, requires = "music")]
+    #[getset(get_copy = "pub")]
+    music_volume: f64,
+
+    /// lower the volume of the background music while the original audio is loud (ducking)
+    #[clap(long, value_parser, requires = "music")]
+    #[getset(get_copy = "pub")]
+    music_ducking: bool,
+
+}
+
+fn parse_music_volume(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(volume) if volume.is_finite() && volume >= 0.0 => Ok(volume),
+        _ => Err("the music volume must be a positive number".to_owned()),
+    }
+}
@@ -0,0 +1,77 @@
+use std::{ffi::OsStr, path::PathBuf};
+
+use clap::Args;
+use getset::{Getters, CopyGetters};
+
+use crate::{output_path, video::pip::PipCorner};
+
+
+#[derive(Args, Getters, CopyGetters)]
+pub struct PipArgs {
+
+    /// corner of the main video in which the picture-in-picture window is placed
+    #[clap(short, long, value_parser, default_value_t = PipCorner::default())]
+    #[getset(get_copy = "pub")]
+    corner: PipCorner,
+
+    /// width of the picture-in-picture window in percent of the main video width
+    #[clap(short = 's', long, value_parser = clap::value_parser!(u8).range(5..=90), default_value_t = 30, value_name = "percent")]
+    #[getset(get_copy = "pub")]
+    pip_size: u8,
+
+    /// distance between the picture-in-picture window and the edges of the main video
+    #[clap(short, long, value_parser, default_value_t = 20, value_name = "pixels")]
+    #[getset(get_copy = "pub")]
+    margin: u32,
+
+    /// width of the border drawn around the picture-in-picture window, 0 for no border
+    #[clap(short, long, value_parser = clap::value_parser!(u32).range(0..=100), default_value_t = 0, value_name = "pixels")]
+    #[getset(get_copy = "pub")]
+    border: u32,
+
+    /// color of the border, any FFMpeg color name or hexadecimal value, e.g. `white` or `0xFF8800`
+    #[clap(long, value_parser, default_value = "white")]
+    #[getset(get = "pub")]
+    border_color: String,
+
+    /// video encoder to use
+    ///
+    /// This value is directly passed to the `-c:v` FFMpeg argument.{n}
+    /// Run `ffmpeg -encoders` for a list of available encoders
+    #[clap(long, value_parser, default_value = "libx265")]
+    #[getset(get = "pub")]
+    video_encoder: String,
+
+    /// video constant quality setting
+    #[clap(long, value_parser, default_value_t = 25)]
+    #[getset(get_copy = "pub")]
+    video_crf: u8,
+
+    /// overwrite output file if it exists
+    #[clap(short = 'y', long, value_parser)]
+    #[getset(get_copy = "pub")]
+    overwrite: bool,
+
+    /// video over which the picture-in-picture window is overlaid, its audio is kept
+    #[getset(get = "pub")]
+    main_video_file: PathBuf,
+
+    /// video displayed in the picture-in-picture window
+    #[getset(get = "pub")]
+    pip_video_file: PathBuf,
+
+    /// output video file, defaults to <MAIN_VIDEO_FILE basename>_pip.mp4
+    output_video_file: Option<PathBuf>,
+}
+
+impl PipArgs {
+
+    /// Output video file, `None` if it is not specified and the main video file has no file name
+    pub fn output_video_file(&self) -> Option<PathBuf> {
+        match &self.output_video_file {
+            Some(output_video_file) => Some(output_video_file.clone()),
+            None => output_path::default_output_path(&self.main_video_file, "pip", Some(OsStr::new("mp4"))),
+        }
+    }
+
+}
@@ -0,0 +1,72 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use getset::{Getters, CopyGetters};
+
+use crate::osd::{overlay::{scaling::OSDScalingArgs, video_timeline::OSDFrameShift}, file::find_associated_to_video_file};
+
+use super::{font_options::OSDFontOptions, transcode_video_args::RequestedOSDButNoFileProvidedNorFound};
+
+
+#[derive(Args, Getters, CopyGetters)]
+pub struct PlayWithOSDArgs {
+
+    /// render the OSD live from the OSD file instead of playing an OSD overlay video, try to find the OSD file automatically
+    ///
+    /// The OSD file is searched the same way as with the --osd option of the `transcode-video` command.
+    #[clap(long, value_parser, conflicts_with = "osd_video_file")]
+    #[getset(get_copy = "pub")]
+    osd: bool,
+
+    /// path to FPV.WTF .osd file to render the OSD live from instead of playing an OSD overlay video
+    #[clap(long, value_parser, value_name = "OSD file path", conflicts_with = "osd_video_file")]
+    osd_file: Option<PathBuf>,
+
+    /// shift frames to sync OSD with video when rendering the OSD live: OSD frames (60Hz) like -37 or -37f, seconds like +1.5s or a timestamp like -00:00:02.25
+    #[clap(short = 'o', long, value_parser, allow_hyphen_values(true), value_name = "shift")]
+    #[getset(get_copy = "pub")]
+    osd_frame_shift: Option<OSDFrameShift>,
+
+    /// initial opacity of the OSD, it can be changed while playing with Alt+- and Alt+=
+    ///
+    /// The OSD can also be toggled with Alt+o.
+    #[clap(long, value_parser = clap::value_parser!(u8).range(0..=100), default_value_t = 100, value_name = "percent")]
+    #[getset(get_copy = "pub")]
+    osd_opacity: u8,
+
+    /// start MPV with its JSON IPC server listening on this socket path
+    ///
+    /// The OSD can then be controlled by other programs with the `script-message osd-toggle` and
+    /// `script-message osd-opacity <+/-percent>` commands.
+    #[clap(long, value_parser, value_name = "socket path")]
+    #[getset(get = "pub")]
+    mpv_ipc_server: Option<PathBuf>,
+
+    #[clap(flatten)]
+    #[getset(get = "pub")]
+    osd_scaling_args: OSDScalingArgs,
+
+    #[clap(flatten)]
+    #[getset(get = "pub")]
+    osd_font_options: OSDFontOptions,
+
+    #[getset(get = "pub")]
+    video_file: PathBuf,
+
+    /// OSD overlay video file, defaults to <VIDEO_FILE basename>_osd.webm
+    #[getset(get = "pub")]
+    osd_video_file: Option<PathBuf>,
+}
+
+impl PlayWithOSDArgs {
+
+    /// Path of the OSD file to render the OSD live from, none when playing an OSD overlay video
+    pub fn osd_file_path(&self) -> Result<Option<PathBuf>, RequestedOSDButNoFileProvidedNorFound> {
+        Ok(match (self.osd, &self.osd_file) {
+            (_, Some(osd_file)) => Some(osd_file.clone()),
+            (true, None) => Some(find_associated_to_video_file(&self.video_file).ok_or(RequestedOSDButNoFileProvidedNorFound)?),
+            (false, None) => None,
+        })
+    }
+
+}
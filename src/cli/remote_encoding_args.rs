@@ -0,0 +1,37 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use getset::Getters;
+
+use crate::{ffmpeg, remote::RemoteHost};
+
+
+#[derive(Args, Getters)]
+#[getset(get = "pub")]
+pub struct RemoteEncodingArgs {
+
+    /// run the FFMpeg encode on a remote host through SSH, e.g. `user@desktop`
+    ///
+    /// The input video file is copied to the remote host with SCP, the OSD frames are still rendered locally and streamed
+    /// to the remote FFMpeg process through the SSH connection and the output video file is copied back once done.
+    /// SSH must be able to connect without prompting for a password, e.g. with public key authentication.
+    #[clap(long, value_parser, value_name = "[user@]host")]
+    remote_host: Option<String>,
+
+    /// path of the FFMpeg binary on the remote host
+    #[clap(long, value_parser, default_value = ffmpeg::DEFAULT_BINARY_PATH, value_name = "path", requires = "remote_host")]
+    remote_ffmpeg_path: String,
+
+    /// directory of the remote host in which the video files are stored during the encode
+    #[clap(long, value_parser, default_value = "/tmp", value_name = "dirpath", requires = "remote_host")]
+    remote_work_dir: PathBuf,
+
+}
+
+impl RemoteEncodingArgs {
+
+    pub fn host(&self) -> Option<RemoteHost> {
+        self.remote_host.as_ref().map(|destination| RemoteHost::new(destination, &self.remote_ffmpeg_path, &self.remote_work_dir))
+    }
+
+}
@@ -0,0 +1,41 @@
+use clap::Args;
+
+use crate::video::speed::Speed;
+
+
+#[derive(Args)]
+pub struct SpeedArgs {
+
+    /// change the speed of the video by this factor, e.g. `2` to play it twice as fast or `0.5` for slow motion
+    ///
+    /// The frame rate is kept by dropping or duplicating frames and the audio tempo is changed to match.
+    /// The burnt OSD follows the speed of the video.
+    #[clap(long, value_parser = parse_speed_factor, value_name = "factor")]
+    speed: Option<f64>,
+
+    /// make a timelapse keeping only one frame every N frames, the audio is dropped
+    ///
+    /// The burnt OSD follows the speed of the video.
+    #[clap(long, value_parser = clap::value_parser!(u32).range(2..), value_name = "N", conflicts_with = "speed")]
+    timelapse: Option<u32>,
+
+}
+
+fn parse_speed_factor(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(factor) if factor.is_finite() && factor > 0.0 => Ok(factor),
+        _ => Err("the speed factor must be a positive number".to_owned()),
+    }
+}
+
+impl SpeedArgs {
+
+    pub fn speed(&self) -> Option<Speed> {
+        match (self.speed, self.timelapse) {
+            (_, Some(frame_interval)) => Some(Speed::Timelapse(frame_interval)),
+            (Some(factor), None) if factor != 1.0 => Some(Speed::Factor(factor)),
+            _ => None,
+        }
+    }
+
+}
@@ -25,6 +25,10 @@ pub struct StartGreaterThanEndError;
 
 impl StartEndArgs {
 
+    pub fn new(start: Option<Timestamp>, end: Option<Timestamp>) -> Self {
+        Self { start, end }
+    }
+
     pub fn are_valid(&self) -> bool {
         if let (Some(start), Some(end)) = (self.start, self.end) {
             return start < end;
@@ -0,0 +1,44 @@
+use clap::Args;
+use getset::CopyGetters;
+
+use crate::osd::{
+    self,
+    overlay::stick::{StickMode, StickOverlay, StickOverlayPreset},
+};
+
+
+#[derive(Args, CopyGetters)]
+pub struct StickOverlayArgs {
+
+    /// render a virtual sticks widget from the RC channel values displayed by the OSD (INAV)
+    ///
+    /// The OSD needs to display the roll, pitch, throttle and yaw channel values on consecutive rows,
+    /// the position of the first row is specified with --stick-overlay-channels.
+    /// Combine with --hide-regions/--osd-hide-regions to hide the raw values from the rendered OSD.
+    #[clap(long, value_parser, value_name = "PRESET", requires = "stick_overlay_channels")]
+    stick_overlay: Option<StickOverlayPreset>,
+
+    /// position in the OSD grid of the first RC channel value row
+    #[clap(long, value_parser, value_name = "x,y")]
+    stick_overlay_channels: Option<osd::Coordinates>,
+
+    /// stick mode used to assign channels to the left and right sticks
+    #[clap(long, value_parser, default_value = "mode2")]
+    #[getset(get_copy = "pub")]
+    stick_overlay_mode: StickMode,
+
+    /// size of each stick box as a percentage of the overlay height
+    #[clap(long, value_parser = clap::value_parser!(u8).range(1..=50), value_name = "percent", default_value = "15")]
+    #[getset(get_copy = "pub")]
+    stick_overlay_size: u8,
+}
+
+impl StickOverlayArgs {
+
+    pub fn stick_overlay(&self) -> Option<StickOverlay> {
+        let preset = self.stick_overlay?;
+        let channels_position = self.stick_overlay_channels.clone()?;
+        Some(StickOverlay::new(preset, self.stick_overlay_mode, self.stick_overlay_size, channels_position))
+    }
+
+}
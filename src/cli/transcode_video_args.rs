@@ -1,13 +1,15 @@
 
-use std::path::{PathBuf, Path};
+use std::{path::{PathBuf, Path}, sync::OnceLock};
 
 use clap::Args;
 use getset::{Getters, CopyGetters};
 use thiserror::Error;
 
-use crate::{osd::{self, overlay::scaling::OSDScalingArgs, file::find_associated_to_video_file}, video};
+use crate::{ffmpeg::{bitrate::Bitrate, encoder_args::EncoderArgs, progress::ProgressCallback}, output_path, osd::{self, text_injection::TextInjection, overlay::{scaling::OSDScalingArgs, placement::OSDMarginArgs, video_timeline::{OSDFrameShift, OSDVideoOffset, OSDTimebaseCorrection, OSDSpeed}}, file::find_associated_to_video_file}, video::{self, audio_sync, container::{self, Container}, continuation::{self, Continuation}, quality_preset::{PresetSettings, QualityPreset}}};
+#[cfg(feature = "hwaccel")]
+use crate::video::hw_accel::EncoderBackend;
 
-use super::{font_options::OSDFontOptions, start_end_args::StartEndArgs, generate_overlay_args};
+use super::{font_options::OSDFontOptions, start_end_args::StartEndArgs, video_transform_args::VideoTransformArgs, speed_args::SpeedArgs, music_args::MusicArgs, metadata_args::MetadataArgs, color_grading_args::ColorGradingArgs, remote_encoding_args::RemoteEncodingArgs, generate_overlay_args, stick_overlay_args::StickOverlayArgs, enlargement_args::EnlargementArgs};
 
 
 #[derive(Args, Getters, CopyGetters)]
@@ -21,6 +23,9 @@ pub struct TranscodeVideoOSDArgs {
     /// DJIG0000_something.mp4 => DJIG0000.osd{n}
     /// DJIU0000.mp4 => DJIG0000.osd{n}
     /// DJIU0000_something.mp4 => DJIG0000.osd{n}
+    /// AvatarS0000.mp4 => AvatarG0000.osd or AvatarS0000.osd{n}
+    /// The names are compared case-insensitively, copy suffixes like ` (1)` are ignored and the `osd` subdirectory of the
+    /// video directory is searched as well unless --no-osd-auto is specified.
     #[clap(long, value_parser)]
     #[getset(get_copy = "pub")]
     osd: bool,
@@ -29,32 +34,151 @@ pub struct TranscodeVideoOSDArgs {
     #[getset(get = "pub")]
     osd_scaling_args: OSDScalingArgs,
 
+    #[clap(flatten)]
+    #[getset(get = "pub")]
+    osd_margin_args: OSDMarginArgs,
+
     #[clap(flatten)]
     #[getset(get = "pub")]
     osd_font_options: OSDFontOptions,
 
-    /// shift frames to sync OSD with video
-    #[clap(short = 'o', long, value_parser, allow_negative_numbers(true), value_name = "frames")]
+    /// shift frames to sync OSD with video: OSD frames (60Hz) like -37 or -37f, seconds like +1.5s or a timestamp like -00:00:02.25
+    #[clap(short = 'o', long, value_parser, allow_hyphen_values(true), value_name = "shift")]
+    #[getset(get_copy = "pub")]
+    osd_frame_shift: Option<OSDFrameShift>,
+
+    /// position of the start of the OSD recording in the video, to burn the OSD onto footage from another camera
+    ///
+    /// Either in seconds with an optional `s` suffix or in OSD frames (60Hz) with an `f` suffix, fractional values are accepted,
+    /// e.g. `12.35`, `-2.5s` or `741f`. A negative value means that the OSD recording started before the video.{n}
+    /// The OSD frames are mapped onto the frame rate of the video, which does not need to be 60FPS.
+    #[clap(long, value_parser, allow_negative_numbers(true), value_name = "offset", conflicts_with = "osd_frame_shift")]
+    #[getset(get_copy = "pub")]
+    osd_video_offset: Option<OSDVideoOffset>,
+
+    /// factor correcting the drift of the OSD timestamps relative to the video, e.g. `1.002` if the OSD runs 0.2% too fast
+    ///
+    /// The OSD frames are remapped proportionally to their time instead of being shifted by a constant offset.
+    #[clap(long, value_parser, value_name = "factor")]
+    #[getset(get_copy = "pub")]
+    osd_timebase_correction: Option<OSDTimebaseCorrection>,
+
+    /// estimate the drift of the Walksnail OSD timestamps relative to the video and correct it
+    ///
+    /// The drift is estimated by comparing the duration of the OSD recording with the duration of the video, which must last
+    /// at least 2 minutes. Only use it when the whole OSD recording matches the video since any difference between the durations
+    /// is taken for drift.
+    #[clap(long, value_parser, conflicts_with = "osd_timebase_correction")]
+    #[getset(get_copy = "pub")]
+    osd_estimate_timebase_correction: bool,
+
+    /// speed of the input video relative to the OSD recording, e.g. `0.25` when it is a slow motion export at 25% speed
+    ///
+    /// The OSD frame timeline is stretched to follow the footage, the OSD frames are repeated when it is slowed down.{n}
+    /// The OSD video offset and frame shift are in the time of the original recording. Unlike --speed the speed of the
+    /// video is not changed.
+    #[clap(long, value_parser, value_name = "factor")]
+    #[getset(get_copy = "pub")]
+    osd_speed: Option<OSDSpeed>,
+
+    /// find the OSD video offset automatically by correlating the audio of the input video with the audio of this DJI video
+    ///
+    /// Use this when burning the OSD onto footage from another camera recording the same flight: the video recorded
+    /// by the DJI goggles or air unit with the OSD file is used as the reference to place the OSD onto the input video.
+    /// The OSD frame shift relative to the reference video can be adjusted with --osd-frame-shift.
+    #[clap(long, value_parser, value_name = "DJI video file path", conflicts_with = "osd_video_offset")]
+    #[getset(get = "pub")]
+    osd_audio_sync_reference: Option<PathBuf>,
+
+    /// maximum offset in seconds searched between the reference video and the input video with --osd-audio-sync-reference
+    #[clap(long, value_parser = clap::value_parser!(u32).range(1..=600), default_value_t = audio_sync::DEFAULT_MAX_OFFSET_SECS, value_name = "seconds")]
     #[getset(get_copy = "pub")]
-    osd_frame_shift: Option<i32>,
+    osd_audio_sync_max_offset: u32,
 
     /// hide rectangular regions from the OSD
     ///
     /// The parameter is a `;` separated list of regions.{n}
-    /// The format for a region is: <left_x>,<top_y>[:<width>x<height>]{n}
-    /// If the size is not specified it will default to 1x1
+    /// The format for a region is: [<start>-<end>@]<left_x>,<top_y>[:<width>x<height>]{n}
+    /// If the size is not specified it will default to 1x1{n}
+    /// If a time range is specified the region is only hidden from <start> (included) to <end> (excluded), timestamps are formatted as
+    /// [HH:]MM:SS in the time of the input video, also when --start is specified, and either of them can be omitted, e.g. `0:00-0:30@1,22:10x2`
     #[clap(long, value_parser, value_delimiter = ';', value_name = "REGIONS")]
     #[getset(get = "pub")]
     osd_hide_regions: Vec<osd::Region>,
 
+    /// fail when a region passed to --osd-hide-regions does not fit the OSD grid instead of correcting it
+    ///
+    /// Without this option regions with swapped x/y coordinates are swapped back and regions extending beyond the grid are clamped, with a warning
+    #[clap(long, value_parser)]
+    #[getset(get_copy = "pub")]
+    osd_strict_regions: bool,
+
     /// hide items from the OSD
     #[clap(long, value_parser, value_delimiter = ',', value_name = "OSD_ITEM_NAMES", help = generate_overlay_args::osd_hide_items_arg_help())]
     #[getset(get = "pub")]
     osd_hide_items: Vec<String>,
 
+    #[clap(flatten)]
+    #[getset(get = "pub")]
+    stick_overlay_args: StickOverlayArgs,
+
+    #[clap(flatten)]
+    #[getset(get = "pub")]
+    enlargement_args: EnlargementArgs,
+
+    /// write text into the OSD, e.g. the craft name or the pilot handle, see the --add-text option of `generate-overlay-frames`
+    #[clap(long, value_parser, value_name = "x,y:text")]
+    #[getset(get = "pub")]
+    osd_add_text: Vec<TextInjection>,
+
+    /// convert the altitude and speed displayed by the OSD to the units of this unit system, see the --units option of `generate-overlay-frames`
+    #[clap(long, value_parser, value_name = "unit system")]
+    #[getset(get_copy = "pub")]
+    osd_units: Option<osd::units::UnitSystem>,
+
+    /// smooth the OSD altitude and speed values between OSD updates, see the --interpolate-values option of `generate-overlay-frames`
+    #[clap(long, value_parser)]
+    #[getset(get_copy = "pub")]
+    osd_interpolate_values: bool,
+
+    /// number of threads rendering the OSD frames ahead while FFMpeg is encoding the previous ones
+    ///
+    /// 0 uses one thread per CPU, 1 renders the frames one at a time when FFMpeg requests them
+    #[clap(long, value_parser = clap::value_parser!(u16).range(0..=256), default_value_t = 0, value_name = "count")]
+    #[getset(get_copy = "pub")]
+    osd_render_threads: u16,
+
     /// path to FPV.WTF .osd file to use to generate OSD frames to burn onto video
     #[clap(long, value_parser, value_name = "OSD file path")]
     osd_file: Option<PathBuf>,
+
+    /// video file continuing the recording of the input video, can be repeated
+    ///
+    /// The DJI goggles and air units split long recordings in several files of about 4GB with their own OSD file.
+    /// The continuations are read after the input video and their OSD frames are placed after the ones of the input video
+    /// so that the whole flight is transcoded into one video with the OSD.{n}
+    /// The OSD file of a continuation is found the same way as the one of the input video, it can also be specified
+    /// after a comma, e.g. `DJIG0002.mp4,DJIG0002.osd`. The concat script listing the video files and the joined OSD file
+    /// are written to the staging directory of --copy-local-first.
+    #[clap(long = "continuation", value_parser, value_name = "video file path[,OSD file path]")]
+    continuations: Vec<Continuation>,
+
+    /// find the continuations of the input video automatically, see --continuation
+    ///
+    /// The video files following the input video in the DJI numbering are joined as long as the previous file was split at the 4GB limit
+    #[clap(long, value_parser, conflicts_with = "continuations")]
+    #[getset(get_copy = "pub")]
+    auto_continuations: bool,
+
+    /// burn the OSD frames from a directory generated with the `generate-overlay-frames` command instead of rendering them
+    ///
+    /// Frame files can be edited before burning them, e.g. to blur some information on specific frames.
+    /// The frames must have been generated for the whole video with the right frame shift,
+    /// e.g. with the --target-video-file option of `generate-overlay-frames`, so the OSD speed and time base options do not apply.
+    #[clap(long, value_parser, value_name = "dirpath", conflicts_with_all = ["osd", "osd_file", "osd_frame_shift", "osd_video_offset", "osd_audio_sync_reference", "continuations",
+        "auto_continuations", "osd_speed", "osd_timebase_correction", "osd_estimate_timebase_correction"])]
+    #[getset(get = "pub")]
+    osd_frames_dir: Option<PathBuf>,
 }
 
 #[derive(Debug, Error)]
@@ -72,6 +196,49 @@ impl TranscodeVideoOSDArgs {
         Ok(osd_file_path)
     }
 
+    /// Video files continuing the recording of the input video, the ones found automatically with --auto-continuations
+    pub fn continuations<P: AsRef<Path>>(&self, video_file_path: P) -> Vec<Continuation> {
+        match self.auto_continuations {
+            true => continuation::find_continuations(video_file_path).into_iter().map(|video_file| Continuation::new(video_file, None)).collect(),
+            false => self.continuations.clone(),
+        }
+    }
+
+}
+
+pub const DEFAULT_VIDEO_ENCODER: &str = "libx265";
+pub const DEFAULT_VIDEO_BITRATE: Bitrate = Bitrate::from_mbps(25);
+pub const DEFAULT_VIDEO_CRF: u8 = 25;
+
+static CONFIGURED_VIDEO_ENCODER: OnceLock<String> = OnceLock::new();
+#[cfg(feature = "hwaccel")]
+static CONFIGURED_HW_ENCODER: OnceLock<EncoderBackend> = OnceLock::new();
+
+/// Sets the video encoder used when --video-encoder is not specified, e.g. the one recommended by the `benchmark` command,
+/// only the first encoder set is used
+pub fn set_configured_video_encoder(encoder_name: &str) {
+    let _ = CONFIGURED_VIDEO_ENCODER.set(encoder_name.to_owned());
+}
+
+static CONFIGURED_VIDEO_DEFECTS: OnceLock<Vec<video::Region>> = OnceLock::new();
+
+/// Sets the regions removed when --remove-video-defects is not specified, e.g. the ones found by the `analyze-defects` command,
+/// only the first regions set are used
+pub fn set_configured_video_defects(regions: Vec<video::Region>) {
+    let _ = CONFIGURED_VIDEO_DEFECTS.set(regions);
+}
+
+/// Sets the hardware encoder backend used with the configured video encoder when --video-encoder is not specified
+#[cfg(feature = "hwaccel")]
+pub fn set_configured_hw_encoder(backend: EncoderBackend) {
+    let _ = CONFIGURED_HW_ENCODER.set(backend);
+}
+
+fn parse_blur_strength(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(sigma) if sigma.is_finite() && sigma > 0.0 => Ok(sigma),
+        _ => Err("the blur strength must be a strictly positive number".to_owned()),
+    }
 }
 
 #[derive(Args, Getters, CopyGetters)]
@@ -98,30 +265,138 @@ pub struct TranscodeVideoArgs {
     /// video encoder to use
     ///
     /// This value is directly passed to the `-c:v` FFMpeg argument.{n}
-    /// Run `ffmpeg -encoders` for a list of available encoders
-    #[clap(long, value_parser, default_value = "libx265")]
-    video_encoder: String,
+    /// Run `ffmpeg -encoders` for a list of available encoders{n}
+    /// Defaults to the `video_encoder` key of the configuration file, set by the `benchmark` command, or libx265
+    #[clap(long, value_parser)]
+    #[getset(skip)]
+    video_encoder: Option<String>,
+
+    /// use a hardware encoder for the codec of --video-encoder
+    ///
+    /// When no backend is specified the first available one is used, in order: nvenc, qsv, vaapi.{n}
+    /// The value of --video-crf is used as the constant quality setting of the hardware encoder.
+    #[cfg(feature = "hwaccel")]
+    #[clap(long, value_parser, value_name = "backend", num_args = 0..=1, require_equals = true)]
+    #[getset(skip)]
+    hw_encoder: Option<Option<EncoderBackend>>,
+
+    /// encoding quality/speed preset
+    ///
+    /// Sets the constant quality, max bitrate and speed options suited to the video encoder, --video-crf and --video-bitrate
+    /// override the values of the preset
+    #[clap(long, value_parser)]
+    #[getset(skip)]
+    #[getset(get_copy = "pub")]
+    preset: Option<QualityPreset>,
+
+    /// video max bitrate [default: 25M or the bitrate of the --preset]
+    #[clap(long, value_parser)]
+    #[getset(skip)]
+    video_bitrate: Option<Bitrate>,
 
-    /// video max bitrate
-    #[clap(long, value_parser, default_value = "25M")]
-    video_bitrate: String,
+    /// video constant quality setting [default: 25 or the quality of the --preset]
+    #[clap(long, value_parser)]
+    #[getset(skip)]
+    video_crf: Option<u8>,
+
+    /// encoder specific arguments passed to FFMpeg after the other video encoder settings, e.g. `-preset slow -tune film`
+    ///
+    /// The arguments are split on whitespace, quotes can be used for values containing spaces. Options changing the inputs,
+    /// outputs, timing or codecs are rejected, as well as values which do not follow an option.
+    #[clap(long, value_parser, allow_hyphen_values(true), value_name = "ARGS")]
+    encoder_args: Option<EncoderArgs>,
 
-    /// video constant quality setting
-    #[clap(long, value_parser, default_value_t = 25)]
+    /// use two-pass encoding to target the --video-bitrate average bitrate
+    ///
+    /// Use this when the output needs to fit a size budget. The --video-crf option is ignored in this mode.
+    #[clap(long, value_parser)]
     #[getset(skip)]
     #[getset(get_copy = "pub")]
-    video_crf: u8,
+    two_pass: bool,
+
+    /// encode 10-bit input videos as 8-bit videos
+    ///
+    /// Without this option the bit depth of 10-bit videos, e.g. DJI O3 D-Log M or HDR recordings, is kept when the encoder supports it
+    #[clap(long = "force-8-bit", value_parser)]
+    #[getset(skip)]
+    #[getset(get_copy = "pub")]
+    force_8_bit: bool,
+
+    /// convert a variable frame rate (VFR) input video to a constant frame rate before processing it
+    ///
+    /// Frames are duplicated or dropped to get the nominal frame rate of the video. Without this option the OSD burnt onto
+    /// a VFR video is placed according to the timestamps of the video frames.
+    #[clap(long, value_parser)]
+    #[getset(skip)]
+    #[getset(get_copy = "pub")]
+    cfr: bool,
 
     /// remove video defects
     ///
     /// uses the FFMpeg delogo filter to remove small video defects
     ///
     /// The parameter is a `;` separated list of regions.{n}
-    /// The format for a region is: <left_x>,<top_y>[:<width>x<height>]{n}
-    /// If the size is not specified it will default to 1x1
+    /// The format for a region is: [<start>-<end>@]<left_x>,<top_y>[:<width>x<height>]{n}
+    /// If the size is not specified it will default to 1x1{n}
+    /// The coordinates and dimensions are in pixels.{n}
+    /// If a time range is specified the defect is only removed from <start> (included) to <end> (excluded), timestamps are formatted as
+    /// [HH:]MM:SS in the time of the input video, also when --start is specified, and either of them can be omitted,
+    /// e.g. `0:00-0:30@120,80:16x16`{n}
+    /// The regions of the configuration file are used when this option is not specified.
     #[clap(long, value_parser, value_delimiter = ';', value_name = "REGIONS")]
+    #[getset(skip)]
     remove_video_defects: Vec<video::Region>,
 
+    /// blur regions of the video, e.g. a licence plate or a face
+    ///
+    /// Unlike --remove-video-defects which looks blocky on large regions the regions are blurred with a gaussian blur.
+    /// The parameter is a `;` separated list of regions formatted like the --remove-video-defects regions, the coordinates
+    /// and dimensions being in pixels, e.g. `0:10-0:25@600,400:200x80`
+    #[clap(long, value_parser, value_delimiter = ';', value_name = "REGIONS")]
+    blur_regions: Vec<video::Region>,
+
+    /// strength of the blur of the --blur-regions regions: sigma of the gaussian blur
+    #[clap(long, value_parser = parse_blur_strength, default_value_t = 20.0, value_name = "SIGMA", requires = "blur_regions")]
+    #[getset(skip)]
+    #[getset(get_copy = "pub")]
+    blur_strength: f64,
+
+    #[clap(flatten)]
+    color_grading: ColorGradingArgs,
+
+    #[clap(flatten)]
+    video_transform: VideoTransformArgs,
+
+    #[clap(flatten)]
+    speed_args: SpeedArgs,
+
+    /// JSON file describing a title card prepended to the video and timed text annotations drawn on the video
+    ///
+    /// Example: {"title_card": {"title": "Bando session", "date": "2024-05-12", "location": "Lyon", "quad": "Apex 5", "duration": 4},
+    /// "annotations": [{"start": 12, "end": 16.5, "text": "power loop", "position": "top", "font_size": 60}], "font_file": "/path/to/font.ttf"}{n}
+    /// All the fields are optional except the times and text of the annotations. The annotation times are in seconds relative to the
+    /// start of the transcoded video, the title card excluded. The audio is delayed by the duration of the title card.
+    #[clap(long, value_parser, value_name = "JSON file path")]
+    annotations: Option<PathBuf>,
+
+    #[clap(flatten)]
+    music_args: MusicArgs,
+
+    #[clap(flatten)]
+    metadata_args: MetadataArgs,
+
+    /// also write a lower resolution H.264 proxy of the output video to this file, encoded in the same pass as the output video
+    ///
+    /// The proxy has the same content as the output video, it is meant for editing or previewing large videos
+    #[clap(long, value_parser, value_name = "file path")]
+    proxy_output: Option<PathBuf>,
+
+    /// height of the proxy video, the width keeps the aspect ratio of the output video
+    #[clap(long, value_parser, value_name = "pixels", default_value_t = 1080, requires = "proxy_output")]
+    #[getset(skip)]
+    #[getset(get_copy = "pub")]
+    proxy_height: u32,
+
     /// audio encoder to use
     ///
     /// This value is directly passed to the `-c:a` FFMpeg argument.{n}
@@ -131,11 +406,84 @@ pub struct TranscodeVideoArgs {
 
     /// max audio bitrate
     #[clap(long, value_parser, default_value = "93k")]
-    audio_bitrate: String,
+    #[getset(skip)]
+    #[getset(get_copy = "pub")]
+    audio_bitrate: Bitrate,
+
+    /// index of the audio track of the input video to keep, 0 being the first one
+    ///
+    /// By default only the first audio track is kept when the video is filtered, otherwise FFMpeg selects the track
+    #[clap(long, value_parser, value_name = "index", conflicts_with = "all_audio_tracks")]
+    #[getset(skip)]
+    #[getset(get_copy = "pub")]
+    audio_track: Option<usize>,
+
+    /// keep all the audio tracks of the input video, e.g. the audio of an external microphone muxed along with the camera audio
+    ///
+    /// The audio filters, e.g. the audio fix, are applied to every track. The proxy video only gets the first track.
+    #[clap(long, value_parser, conflicts_with = "music")]
+    #[getset(skip)]
+    #[getset(get_copy = "pub")]
+    all_audio_tracks: bool,
 
     #[clap(flatten)]
     start_end: StartEndArgs,
 
+    /// cut the video to the part covered by the OSD recording, e.g. to drop the end of the recording after disarming
+    ///
+    /// The start and end are set to the first and last frames of the OSD file burnt onto the video, or of the OSD file
+    /// associated with the video when not burning the OSD, rounded outwards to the second.
+    #[clap(long, value_parser, conflicts_with_all = ["start", "end"])]
+    #[getset(skip)]
+    #[getset(get_copy = "pub")]
+    trim_to_osd: bool,
+
+    // function called with the progress updates of the FFMpeg process encoding the output, set by the Python module
+    #[clap(skip)]
+    #[getset(skip)]
+    progress_callback: Option<ProgressCallback>,
+
+    /// copy the input video file into a local staging directory before transcoding
+    ///
+    /// Use this option when the input file is on removable media (SD card) to avoid I/O errors in the middle of long encodes.
+    /// Read errors while copying are retried with backoff.
+    #[clap(long, value_parser)]
+    #[getset(skip)]
+    #[getset(get_copy = "pub")]
+    copy_local_first: bool,
+
+    /// directory in which input files are staged with --copy-local-first, defaults to the system temporary directory
+    #[clap(long, value_parser, value_name = "dirpath", requires = "copy_local_first")]
+    staging_dir: Option<PathBuf>,
+
+    #[clap(flatten)]
+    remote_encoding: RemoteEncodingArgs,
+
+    /// container format of the output video: by default the one matching the extension of the output file
+    ///
+    /// The extension of the default output file name is the one of the container
+    #[clap(long, value_parser)]
+    #[getset(skip)]
+    #[getset(get_copy = "pub")]
+    container: Option<Container>,
+
+    /// do not move the index of MP4 and MOV output videos to the start of the file
+    ///
+    /// By default the output videos are written with `-movflags +faststart` so that they can be played while being
+    /// downloaded, e.g. once uploaded, which requires rewriting the file once the encode is done
+    #[clap(long, value_parser)]
+    #[getset(skip)]
+    #[getset(get_copy = "pub")]
+    no_faststart: bool,
+
+    /// write a fragmented MP4 or MOV output video which can be played or streamed while it is being encoded
+    ///
+    /// The output video is written directly to its final path instead of a temporary file so that it can be previewed
+    #[clap(long, value_parser, conflicts_with = "no_faststart")]
+    #[getset(skip)]
+    #[getset(get_copy = "pub")]
+    fragmented_mp4: bool,
+
     /// input video file path
     input_video_file: PathBuf,
 
@@ -160,6 +508,16 @@ pub enum OutputVideoFileError {
 
 impl TranscodeVideoArgs {
 
+    /// Sets a function called with each progress update of the FFMpeg process encoding the output
+    pub fn set_progress_callback(&mut self, progress_callback: Option<ProgressCallback>) -> &mut Self {
+        self.progress_callback = progress_callback;
+        self
+    }
+
+    pub fn progress_callback(&self) -> Option<ProgressCallback> {
+        self.progress_callback.clone()
+    }
+
     pub fn video_audio_fix(&self) -> Option<video::AudioFixType> {
         use video::AudioFixType::*;
         match (self.fix_audio, self.fix_audio_sync, self.fix_audio_volume) {
@@ -170,6 +528,91 @@ impl TranscodeVideoArgs {
         }
     }
 
+    /// Settings of the --preset for the specified encoder, `None` without preset or if the encoder is not known
+    pub fn preset_settings(&self, encoder_name: &str) -> Option<PresetSettings> {
+        self.preset?.encoder_settings(encoder_name)
+    }
+
+    pub fn video_bitrate(&self, encoder_name: &str) -> Bitrate {
+        match (self.video_bitrate, self.preset_settings(encoder_name)) {
+            (Some(video_bitrate), _) => video_bitrate,
+            (None, Some(preset_settings)) => preset_settings.bitrate(),
+            (None, None) => DEFAULT_VIDEO_BITRATE,
+        }
+    }
+
+    /// CRF value to pass to the encoder, none with two-pass encoding since the output is bitrate targeted
+    pub fn video_crf_setting(&self, encoder_name: &str) -> Option<u8> {
+        if self.two_pass { return None }
+        Some(self.video_crf.or_else(|| self.preset_settings(encoder_name).map(|preset_settings| preset_settings.crf())).unwrap_or(DEFAULT_VIDEO_CRF))
+    }
+
+    /// Regions to remove: the ones of --remove-video-defects or else the ones of the configuration file
+    pub fn remove_video_defects(&self) -> &[video::Region] {
+        match (self.remove_video_defects.is_empty(), CONFIGURED_VIDEO_DEFECTS.get()) {
+            (true, Some(configured_regions)) => configured_regions,
+            _ => &self.remove_video_defects,
+        }
+    }
+
+    /// Video encoder: the one of --video-encoder, the one of the configuration file or libx265
+    pub fn video_encoder(&self) -> &str {
+        self.video_encoder.as_deref()
+            .or(CONFIGURED_VIDEO_ENCODER.get().map(String::as_str))
+            .unwrap_or(DEFAULT_VIDEO_ENCODER)
+    }
+
+    /// Whether a hardware encoder was requested and with which backend, `Some(None)` meaning automatic detection
+    ///
+    /// The hardware encoder of the configuration file is used with the configured video encoder unless --video-encoder is specified.
+    #[cfg(feature = "hwaccel")]
+    pub fn hw_encoder(&self) -> Option<Option<EncoderBackend>> {
+        match (self.hw_encoder, &self.video_encoder) {
+            (None, None) => CONFIGURED_HW_ENCODER.get().copied().map(Some),
+            (hw_encoder, _) => hw_encoder,
+        }
+    }
+
+    /// Whether a hardware encoder was requested, always false without the `hwaccel` feature
+    pub fn uses_hw_encoder(&self) -> bool {
+        #[cfg(feature = "hwaccel")]
+        return self.hw_encoder().is_some();
+        #[cfg(not(feature = "hwaccel"))]
+        return false;
+    }
+
+    /// `-movflags` of the output video: faststart by default or the fragmented MP4 flags, none for the containers not supporting them
+    pub fn output_movflags(&self, output_video_file: &Path) -> Option<&'static str> {
+        let container = self.container.or_else(|| Container::from_path(output_video_file));
+        if ! container.is_some_and(|container| container.supports_movflags()) {
+            if self.fragmented_mp4 { log::warn!("--fragmented-mp4 ignored: the output container is not MP4 or MOV"); }
+            return None;
+        }
+        match (self.fragmented_mp4, self.no_faststart) {
+            (true, _) => Some(container::FRAGMENTED_MOVFLAGS),
+            (false, false) => Some(container::FASTSTART_MOVFLAGS),
+            (false, true) => None,
+        }
+    }
+
+    /// Whether the audio tracks to keep were selected with --audio-track or --all-audio-tracks
+    pub fn selects_audio_tracks(&self) -> bool {
+        self.audio_track.is_some() || self.all_audio_tracks
+    }
+
+    /// Stream specifier of the input audio track mixed with the music or written to the proxy video: the --audio-track or the first one
+    pub fn audio_track_stream(&self) -> String {
+        format!("0:a:{}", self.audio_track.unwrap_or(0))
+    }
+
+    /// Stream specifier of the input audio tracks kept in the output video
+    pub fn audio_tracks_mapping(&self) -> String {
+        match self.all_audio_tracks {
+            true => "0:a".to_owned(),
+            false => self.audio_track_stream(),
+        }
+    }
+
     pub fn output_video_file_provided(&self) -> bool {
         self.output_video_file.is_some()
     }
@@ -178,11 +621,9 @@ impl TranscodeVideoArgs {
         Ok(match &self.output_video_file {
             Some(output_video_file) => output_video_file.clone(),
             None => {
-                let mut output_file_stem = Path::new(self.input_video_file.file_stem().ok_or(OutputVideoFileError::InputHasNoFileName)?).as_os_str().to_os_string();
-                let suffix = if with_osd { "_with_osd" } else { "_transcoded" };
-                output_file_stem.push(suffix);
-                let input_file_extension = self.input_video_file.extension().ok_or(OutputVideoFileError::InputHasNoExtension)?;
-                self.input_video_file.with_file_name(output_file_stem).with_extension(input_file_extension)
+                let suffix = if with_osd { "with_osd" } else { "transcoded" };
+                let output_file_extension = container::output_extension(&self.input_video_file, self.container).ok_or(OutputVideoFileError::InputHasNoExtension)?;
+                output_path::default_output_path(&self.input_video_file, suffix, Some(output_file_extension)).ok_or(OutputVideoFileError::InputHasNoFileName)?
             }
         })
     }
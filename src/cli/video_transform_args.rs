@@ -0,0 +1,76 @@
+use clap::Args;
+use getset::CopyGetters;
+
+use crate::video::{Resolution, transform::{AspectRatio, Crop, CropError, Rotation}};
+
+
+#[derive(Args, CopyGetters)]
+#[getset(get_copy = "pub")]
+pub struct VideoTransformArgs {
+
+    /// rotate the video clockwise by this angle in degrees, the OSD is burnt upright on the rotated video
+    #[clap(long, value_parser, value_name = "degrees")]
+    rotate: Option<Rotation>,
+
+    /// flip the video horizontally (mirror), applied after the rotation
+    #[clap(long, value_parser)]
+    hflip: bool,
+
+    /// flip the video vertically, applied after the rotation
+    #[clap(long, value_parser)]
+    vflip: bool,
+
+    /// crop the video, applied after the rotation and flips
+    ///
+    /// The format is [<left_x>,<top_y>:]<width>x<height>, the area is centered when its position is not specified.{n}
+    /// The OSD is scaled to fit the cropped video.
+    #[clap(long, value_parser, value_name = "area")]
+    crop: Option<Crop>,
+
+    /// crop the video to the largest centered area with this aspect ratio, e.g. `16:9` for 4:3 videos or `9:16` for vertical videos
+    ///
+    /// Applied after the rotation and flips. The OSD is scaled to fit the cropped video.
+    #[clap(long, value_parser, value_name = "width:height", conflicts_with = "crop")]
+    aspect: Option<AspectRatio>,
+
+}
+
+impl VideoTransformArgs {
+
+    fn rotated_resolution(&self, resolution: Resolution) -> Resolution {
+        match self.rotate {
+            Some(rotation) => rotation.rotated_resolution(resolution),
+            None => resolution,
+        }
+    }
+
+    // the crop area is relative to the rotated video
+    fn crop_area(&self, resolution: Resolution) -> Option<Crop> {
+        self.crop.or_else(|| self.aspect.map(|aspect| aspect.crop(self.rotated_resolution(resolution))))
+    }
+
+    /// FFMpeg filters applying the transforms to an input video of the specified resolution, empty if no transform was requested
+    pub fn ffmpeg_filter_parts(&self, resolution: Resolution) -> Result<Vec<String>, CropError> {
+        let mut parts = self.rotate.map(|rotation| rotation.ffmpeg_filter_parts()).unwrap_or_default();
+        if self.hflip { parts.push("hflip".to_owned()) }
+        if self.vflip { parts.push("vflip".to_owned()) }
+        if let Some(crop) = self.crop_area(resolution) {
+            parts.push(crop.ffmpeg_filter_string(self.rotated_resolution(resolution))?);
+        }
+        Ok(parts)
+    }
+
+    /// Whether no transform was requested
+    pub fn is_identity(&self) -> bool {
+        self.rotate.is_none() && ! self.hflip && ! self.vflip && self.crop.is_none() && self.aspect.is_none()
+    }
+
+    /// Resolution of a video of the specified resolution once transformed
+    pub fn transformed_resolution(&self, resolution: Resolution) -> Resolution {
+        match self.crop_area(resolution) {
+            Some(crop) => crop.dimensions(),
+            None => self.rotated_resolution(resolution),
+        }
+    }
+
+}
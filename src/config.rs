@@ -0,0 +1,104 @@
+use std::{io::{Error as IOError, ErrorKind as IOErrorKind}, path::PathBuf};
+
+use getset::Getters;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{create_path::{create_path, CreatePathError}, hooks::Hooks};
+
+
+const CONFIG_FILE_NAME: &str = "config.json";
+
+/// Settings read from the configuration file, the command line options take precedence over them
+///
+/// Example:
+/// ```json
+/// { "ffmpeg_path": "/opt/ffmpeg/bin/ffmpeg", "mpv_path": "/usr/local/bin/mpv", "video_encoder": "libx265", "hw_encoder": "nvenc" }
+/// ```
+#[derive(Debug, Default, Deserialize, Serialize, Getters)]
+#[serde(deny_unknown_fields)]
+#[getset(get = "pub")]
+pub struct Config {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ffmpeg_path: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mpv_path: Option<PathBuf>,
+    /// video encoder used by `transcode-video` when --video-encoder is not specified
+    #[serde(skip_serializing_if = "Option::is_none")]
+    video_encoder: Option<String>,
+    /// hardware encoder backend used with `video_encoder` when --video-encoder is not specified: nvenc, qsv or vaapi
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hw_encoder: Option<String>,
+    /// regions removed by `transcode-video` when --remove-video-defects is not specified, e.g. the ones found by `analyze-defects`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    remove_video_defects: Option<String>,
+    /// commands run before and after each job of the queue
+    #[serde(default, skip_serializing_if = "Hooks::is_empty")]
+    hooks: Hooks,
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("failed to read configuration file {file}: {error}")]
+    ReadError {
+        file: PathBuf,
+        error: IOError,
+    },
+    #[error("invalid configuration file {file}: {error}")]
+    InvalidConfigFile {
+        file: PathBuf,
+        error: serde_json::Error,
+    },
+    #[error("failed to write configuration file {file}: {error}")]
+    WriteError {
+        file: PathBuf,
+        error: IOError,
+    },
+    #[error(transparent)]
+    CreatePathError(#[from] CreatePathError),
+    #[error("cannot determine the path of the configuration file, neither XDG_CONFIG_HOME nor the home directory are known")]
+    NoConfigFilePath,
+}
+
+/// Path of the configuration file: `$XDG_CONFIG_HOME/hd_fpv_video_tool/config.json` or `~/.config/hd_fpv_video_tool/config.json`
+pub fn file_path() -> Option<PathBuf> {
+    let config_dir = match std::env::var_os("XDG_CONFIG_HOME").filter(|dir| ! dir.is_empty()) {
+        Some(config_dir) => PathBuf::from(config_dir),
+        None => home::home_dir()?.join(".config"),
+    };
+    Some(config_dir.join(env!("CARGO_PKG_NAME")).join(CONFIG_FILE_NAME))
+}
+
+impl Config {
+
+    /// Loads the configuration file, the default configuration is returned if it does not exist
+    pub fn load() -> Result<Self, ConfigError> {
+        let Some(file) = file_path() else { return Ok(Self::default()) };
+        let content = match fs_err::read_to_string(&file) {
+            Ok(content) => content,
+            Err(error) if error.kind() == IOErrorKind::NotFound => return Ok(Self::default()),
+            Err(error) => return Err(ConfigError::ReadError { file, error }),
+        };
+        serde_json::from_str(&content).map_err(|error| ConfigError::InvalidConfigFile { file, error })
+    }
+
+    /// Sets the video encoder `transcode-video` uses by default, with the hardware encoder backend to use if any
+    pub fn set_video_encoder(&mut self, video_encoder: &str, hw_encoder: Option<&str>) {
+        self.video_encoder = Some(video_encoder.to_owned());
+        self.hw_encoder = hw_encoder.map(str::to_owned);
+    }
+
+    pub fn set_remove_video_defects(&mut self, regions: &str) {
+        self.remove_video_defects = Some(regions.to_owned());
+    }
+
+    /// Writes the configuration file, returns its path
+    pub fn save(&self) -> Result<PathBuf, ConfigError> {
+        let file = file_path().ok_or(ConfigError::NoConfigFilePath)?;
+        if let Some(config_dir) = file.parent() { create_path(config_dir)?; }
+        let content = serde_json::to_string_pretty(self).expect("failed to serialize the configuration");
+        fs_err::write(&file, content + "\n").map_err(|error| ConfigError::WriteError { file: file.clone(), error })?;
+        Ok(file)
+    }
+
+}
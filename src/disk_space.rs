@@ -0,0 +1,107 @@
+// Pre-flight check of the space available for the outputs
+//
+// The size of an output is estimated before starting the command so that a job taking hours does not fail at the end
+// because the file system is full.
+
+use std::{path::{Path, PathBuf}, sync::atomic::{AtomicBool, Ordering}};
+
+use thiserror::Error;
+
+use crate::{ffmpeg::bitrate::Bitrate, video::Resolution};
+
+
+// an estimate above this fraction of the available space leaves too little margin for the estimation error
+const LOW_MARGIN_RATIO: f64 = 0.9;
+// average compression ratio of the overlay frames PNG files relative to the raw RGBA frames, the overlay is mostly transparent
+const OVERLAY_PNG_COMPRESSION_RATIO: u64 = 40;
+
+static ENFORCED: AtomicBool = AtomicBool::new(true);
+
+/// Whether the commands refuse to start when an output does not fit in the available space, only a warning is logged otherwise
+pub fn set_enforced(enabled: bool) {
+    ENFORCED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_enforced() -> bool {
+    ENFORCED.load(Ordering::Relaxed)
+}
+
+#[derive(Debug, Error)]
+#[error("not enough disk space to write {}: about {} needed, {} available", path.to_string_lossy(), format_size(*estimated_size),
+    format_size(*available_space))]
+pub struct InsufficientDiskSpaceError {
+    path: PathBuf,
+    estimated_size: u64,
+    available_space: u64,
+}
+
+/// Formats a size in bytes with a binary unit, e.g. `1.5GiB`
+pub fn format_size(size: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    let mut value = size as f64;
+    let mut unit = "B";
+    for next_unit in UNITS {
+        if value < 1024.0 { break }
+        value /= 1024.0;
+        unit = next_unit;
+    }
+    match unit {
+        "B" => format!("{size}B"),
+        unit => format!("{value:.1}{unit}"),
+    }
+}
+
+/// Estimated size of a stream encoded at `bitrate` for `duration_secs` seconds
+pub fn bitrate_size_estimate(bitrate: Bitrate, duration_secs: f64) -> u64 {
+    (bitrate.bps() as f64 / 8.0 * duration_secs.max(0.0)) as u64
+}
+
+/// Estimated size of `frame_count` overlay frames of this resolution saved as PNG files
+pub fn overlay_frames_size_estimate(frame_count: u64, resolution: Resolution) -> u64 {
+    let raw_frame_size = resolution.width as u64 * resolution.height as u64 * 4;
+    frame_count * (raw_frame_size / OVERLAY_PNG_COMPRESSION_RATIO)
+}
+
+/// Space available to the user on the file system on which the specified file is going to be written
+#[cfg(unix)]
+pub fn available_space<P: AsRef<Path>>(path: P) -> Option<u64> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+    let path = path.as_ref();
+    let dir = match path.parent() {
+        Some(dir) if ! dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let dir = CString::new(dir.as_os_str().as_bytes()).ok()?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: the path is a valid NUL terminated string and the structure is only read when the call succeeded
+    let stat = unsafe {
+        if libc::statvfs(dir.as_ptr(), stat.as_mut_ptr()) != 0 { return None }
+        stat.assume_init()
+    };
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+pub fn available_space<P: AsRef<Path>>(_path: P) -> Option<u64> {
+    None
+}
+
+/// Checks that an output of the estimated size fits in the space available where it is going to be written
+///
+/// Returns an error if it does not fit unless the check is not enforced, logs a warning if it leaves little free space.
+/// Nothing is checked when the available space cannot be determined or in dry-run mode.
+pub fn check<P: AsRef<Path>>(path: P, estimated_size: u64) -> Result<(), InsufficientDiskSpaceError> {
+    let path = path.as_ref();
+    if crate::process::is_dry_run() { return Ok(()) }
+    let Some(available_space) = available_space(path) else { return Ok(()) };
+    log::debug!("{}: estimated size {}, available space {}", path.to_string_lossy(), format_size(estimated_size), format_size(available_space));
+    if estimated_size > available_space {
+        let error = InsufficientDiskSpaceError { path: path.to_path_buf(), estimated_size, available_space };
+        if is_enforced() { return Err(error) }
+        log::warn!("{error}");
+    } else if estimated_size as f64 > available_space as f64 * LOW_MARGIN_RATIO {
+        log::warn!("{} is estimated to take about {} out of the {} available, the disk may get full before the end",
+            path.to_string_lossy(), format_size(estimated_size), format_size(available_space));
+    }
+    Ok(())
+}
@@ -0,0 +1,213 @@
+use std::{fmt::Display, path::{Path, PathBuf}};
+
+use hd_fpv_osd_font_tool::prelude::*;
+use strum::IntoEnumIterator;
+use thiserror::Error;
+
+use crate::{
+    cli::font_options::{font_dir_base, OSDFontDirError},
+    ffmpeg,
+    osd::{font_dir::PAGE_TILE_COUNT, FontDir, FontVariant, tile_indices::TileIndex},
+    process::program_version,
+    video::mpv,
+};
+#[cfg(feature = "hwaccel")]
+use std::collections::HashSet;
+#[cfg(feature = "hwaccel")]
+use itertools::Itertools;
+#[cfg(feature = "hwaccel")]
+use crate::video::hw_accel::{self, Codec, EncoderBackend};
+
+
+// encoders used by default by the commands, the others are only needed with some options
+const REQUIRED_ENCODERS: [(&str, &str); 3] = [
+    ("libx265", "default video encoder of `transcode-video`"),
+    ("aac", "default audio encoder of `transcode-video`"),
+    ("libvpx", "default codec of `generate-overlay-video` (VP8)"),
+];
+const OPTIONAL_ENCODERS: [(&str, &str); 2] = [
+    ("libvpx-vp9", "VP9 codec of `generate-overlay-video`"),
+    ("libsvtav1", "AV1 codec of `generate-overlay-video`"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::Display)]
+pub enum CheckStatus {
+    #[strum(serialize = " OK ")]
+    Ok,
+    #[strum(serialize = "WARN")]
+    Warning,
+    #[strum(serialize = "FAIL")]
+    Failed,
+}
+
+/// Result of a check of the environment with the steps to fix it when it did not pass
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    status: CheckStatus,
+    name: String,
+    details: String,
+    remediation: Option<String>,
+}
+
+impl CheckResult {
+
+    fn ok(name: &str, details: impl Into<String>) -> Self {
+        Self { status: CheckStatus::Ok, name: name.to_owned(), details: details.into(), remediation: None }
+    }
+
+    fn problem(status: CheckStatus, name: &str, details: impl Into<String>, remediation: impl Into<String>) -> Self {
+        Self { status, name: name.to_owned(), details: details.into(), remediation: Some(remediation.into()) }
+    }
+
+    pub fn status(&self) -> CheckStatus {
+        self.status
+    }
+
+}
+
+impl Display for CheckResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {}: {}", self.status, self.name, self.details)?;
+        if let Some(remediation) = &self.remediation {
+            write!(f, "\n       -> {remediation}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("{0} check(s) failed")]
+pub struct ChecksFailed(usize);
+
+fn check_ffmpeg(ffmpeg_path: &Path) -> CheckResult {
+    match program_version(ffmpeg_path, "-version") {
+        Some(version) => CheckResult::ok("ffmpeg", version),
+        None => CheckResult::problem(CheckStatus::Failed, "ffmpeg", format!("`{}` not found or not working", ffmpeg_path.to_string_lossy()),
+            "install FFMpeg (e.g. `sudo dnf install ffmpeg` or `sudo apt install ffmpeg`) and make sure it is in the PATH or specify its path with --ffmpeg-path"),
+    }
+}
+
+fn check_mpv(mpv_path: &Path) -> CheckResult {
+    match program_version(mpv_path, "--version") {
+        Some(version) => CheckResult::ok("mpv", version),
+        None => CheckResult::problem(CheckStatus::Warning, "mpv", format!("`{}` not found or not working", mpv_path.to_string_lossy()),
+            "install MPV to use the `play-video-with-osd` command (e.g. `sudo dnf install mpv` or `sudo apt install mpv`) or specify its path with --mpv-path"),
+    }
+}
+
+fn check_encoders(ffmpeg_path: &Path) -> Vec<CheckResult> {
+    let encoders = match ffmpeg::available_encoders(ffmpeg_path) {
+        Ok(encoders) => encoders,
+        Err(error) => return vec![CheckResult::problem(CheckStatus::Failed, "encoders", format!("failed running {}: {error}", ffmpeg_path.to_string_lossy()),
+            "fix the FFMpeg installation first")],
+    };
+    let encoder_checks = REQUIRED_ENCODERS.iter().map(|encoder| (encoder, CheckStatus::Failed))
+        .chain(OPTIONAL_ENCODERS.iter().map(|encoder| (encoder, CheckStatus::Warning)));
+    let results = encoder_checks.map(|((encoder, usage), missing_status)| {
+        let name = format!("{encoder} encoder");
+        match encoders.contains(*encoder) {
+            true => CheckResult::ok(&name, format!("available, {usage}")),
+            false => CheckResult::problem(missing_status, &name, format!("not available, {usage}"),
+                format!("install an FFMpeg build with the {encoder} encoder enabled, e.g. the RPM Fusion or the static builds")),
+        }
+    });
+    #[cfg(feature = "hwaccel")]
+    let results = results.chain(check_hw_encoders(ffmpeg_path, &encoders));
+    results.collect()
+}
+
+#[cfg(feature = "hwaccel")]
+fn check_hw_encoders(ffmpeg_path: &Path, encoders: &HashSet<String>) -> Vec<CheckResult> {
+    let mut results = vec![];
+    // the encoders listed by FFMpeg are tried since they are listed whether the GPU and its drivers are present or not
+    let (hw_encoders, failing_hw_encoders): (Vec<_>, Vec<_>) = EncoderBackend::DETECTION_ORDER.iter()
+        .cartesian_product([Codec::H264, Codec::Hevc, Codec::Av1])
+        .map(|(backend, codec)| (*backend, backend.encoder_name(codec)))
+        .filter(|(_, encoder_name)| encoders.contains(encoder_name))
+        .partition(|(backend, encoder_name)| hw_accel::encoder_works(ffmpeg_path, *backend, encoder_name));
+    let encoder_names = |encoders: &[(EncoderBackend, String)]| encoders.iter().map(|(_, encoder_name)| encoder_name.as_str()).join(", ");
+    results.push(match (hw_encoders.is_empty(), failing_hw_encoders.is_empty()) {
+        (true, true) => CheckResult::problem(CheckStatus::Warning, "hardware encoders", "none available",
+            "the --hw-encoder option cannot be used, install the GPU drivers and an FFMpeg build with NVENC, QSV or VAAPI support to use it"),
+        (true, false) => CheckResult::problem(CheckStatus::Warning, "hardware encoders",
+            format!("none working, failed to encode a test frame: {}", encoder_names(&failing_hw_encoders)),
+            "the --hw-encoder option cannot be used, install the drivers of the GPU supporting these encoders to use it"),
+        (false, true) => CheckResult::ok("hardware encoders", encoder_names(&hw_encoders)),
+        (false, false) => CheckResult::ok("hardware encoders",
+            format!("{}, failed to encode a test frame: {}", encoder_names(&hw_encoders), encoder_names(&failing_hw_encoders))),
+    });
+    if failing_hw_encoders.iter().chain(&hw_encoders).any(|(backend, _)| *backend == EncoderBackend::Vaapi) {
+        let device = Path::new(hw_accel::DEFAULT_VAAPI_DEVICE);
+        results.push(match device.exists() {
+            true => CheckResult::ok("VAAPI device", device.to_string_lossy()),
+            false => CheckResult::problem(CheckStatus::Warning, "VAAPI device", format!("{} not found", device.to_string_lossy()),
+                "install the VAAPI drivers of the GPU (e.g. `mesa-va-drivers` or `intel-media-va-driver`) to use --hw-encoder=vaapi"),
+        });
+    }
+    results
+}
+
+fn check_fonts(font_dir: Result<PathBuf, OSDFontDirError>) -> Vec<CheckResult> {
+    let font_dir_path = match font_dir {
+        Ok(font_dir_path) => font_dir_path,
+        Err(error) => return vec![CheckResult::problem(CheckStatus::Failed, "fonts directory", error.to_string(),
+            "download the fonts (see the README) and pass their directory with --font-dir or the DJI_OSD_FONTS_DIR environment variable")],
+    };
+    let mut results = vec![CheckResult::ok("fonts directory", font_dir_path.to_string_lossy())];
+    let font_dir = FontDir::new(&font_dir_path);
+    let mut found_count = 0;
+    for variant in FontVariant::iter().filter(|variant| *variant != FontVariant::Unknown) {
+        let ident = variant.font_set_ident();
+        let mut found_kinds = vec![];
+        for tile_kind in tile::Kind::iter() {
+            // loading up to a tile of the second page loads the extended font
+            for (page_count, highest_tile_index) in [(1, 0), (2, PAGE_TILE_COUNT as TileIndex)] {
+                match font_dir.load(tile_kind, &ident, highest_tile_index) {
+                    Ok(_) => found_kinds.push(format!("{tile_kind} {page_count} page(s)")),
+                    Err(error) if error.because_file_is_missing() => {},
+                    Err(error) => results.push(CheckResult::problem(CheckStatus::Failed, &format!("{variant} {tile_kind} font"), error.to_string(),
+                        "download the font files again, the file is probably truncated or not a font file")),
+                }
+            }
+        }
+        let name = format!("{variant} font");
+        results.push(match (found_kinds.is_empty(), variant) {
+            (false, _) => { found_count += 1; CheckResult::ok(&name, found_kinds.join(", ")) },
+            (true, FontVariant::Generic) => CheckResult::problem(CheckStatus::Warning, &name, "not found",
+                "the generic font is the default fallback font when the font of a variant is missing, download it (see the README)"),
+            (true, _) => CheckResult::problem(CheckStatus::Warning, &name, "not found",
+                format!("download the {variant} font to render the OSD of {variant} flight controllers")),
+        });
+    }
+    if found_count == 0 {
+        results.push(CheckResult::problem(CheckStatus::Failed, "fonts", format!("no font found in {}", font_dir_path.to_string_lossy()),
+            "download the fonts (see the README) into this directory"));
+    }
+    results
+}
+
+/// Runs the checks of the environment: FFMpeg, MPV, encoders and fonts
+pub fn run_checks(font_dir: &Option<PathBuf>) -> Vec<CheckResult> {
+    let ffmpeg_path = ffmpeg::binary_path();
+    let ffmpeg_check = check_ffmpeg(ffmpeg_path);
+    let encoder_checks = match ffmpeg_check.status() {
+        CheckStatus::Failed => vec![],
+        _ => check_encoders(ffmpeg_path),
+    };
+    [ffmpeg_check, check_mpv(mpv::binary_path())].into_iter()
+        .chain(encoder_checks)
+        .chain(check_fonts(font_dir_base(font_dir)))
+        .collect()
+}
+
+/// Prints the results of the checks of the environment, fails if any check failed
+pub fn doctor(font_dir: &Option<PathBuf>) -> Result<(), ChecksFailed> {
+    let results = run_checks(font_dir);
+    for result in &results { println!("{result}") }
+    let failed_count = results.iter().filter(|result| result.status() == CheckStatus::Failed).count();
+    let warning_count = results.iter().filter(|result| result.status() == CheckStatus::Warning).count();
+    println!();
+    println!("{} check(s): {failed_count} failed, {warning_count} warning(s)", results.len());
+    if failed_count > 0 { return Err(ChecksFailed(failed_count)) }
+    Ok(())
+}
@@ -0,0 +1,312 @@
+// C interface to the OSD file parsing and overlay rendering
+//
+// Build the shared library with `cargo build --lib --release --no-default-features --features ffi,overlay`, the matching
+// declarations are in `include/hd_fpv_video_tool.h`. Without the `overlay` feature only the OSD file functions are
+// exported, this is the subset built for the wasm32 targets with `--target wasm32-unknown-unknown --no-default-features --features ffi`,
+// the content of the OSD files is then passed with `hd_fpv_osd_file_parse`.
+//
+// The functions returning a pointer return NULL on error and the functions returning an integer return a negative value
+// on error, the message of the last error of the calling thread is then returned by [`hd_fpv_last_error`]. A panic does
+// not unwind into the caller, it is reported as an error when the library is built with `panic = "unwind"`.
+
+use std::{cell::RefCell, ffi::{c_char, c_int, CStr, CString}, fmt::Display, panic::{self, AssertUnwindSafe}, path::PathBuf, ptr, slice};
+
+use crate::osd::{
+    self,
+    dji,
+    file::{GenericReader, SortedUniqFrames},
+    wsa,
+};
+#[cfg(feature = "overlay")]
+use crate::osd::{
+    overlay::{Generator as OverlayGenerator, scaling::Scaling},
+    FontDir,
+};
+
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = RefCell::new(None);
+}
+
+fn set_last_error(error: impl Display) {
+    let message = CString::new(error.to_string().replace('\0', " ")).unwrap();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+}
+
+// runs the body of an exported function, a panic is reported as an error returning `error_value`
+fn catch_panic<T>(error_value: T, body: impl FnOnce() -> T) -> T {
+    match panic::catch_unwind(AssertUnwindSafe(body)) {
+        Ok(value) => value,
+        Err(payload) => {
+            let message = payload.downcast_ref::<&str>().map(|message| message.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown error".to_owned());
+            set_last_error(format!("internal error: {message}"));
+            error_value
+        },
+    }
+}
+
+// converts a pointer argument, NULL is an error
+unsafe fn ref_arg<'a, T>(pointer: *const T, name: &str) -> Option<&'a T> {
+    let reference = pointer.as_ref();
+    if reference.is_none() { set_last_error(format!("{name} is NULL")) }
+    reference
+}
+
+unsafe fn mut_arg<'a, T>(pointer: *mut T, name: &str) -> Option<&'a mut T> {
+    let reference = pointer.as_mut();
+    if reference.is_none() { set_last_error(format!("{name} is NULL")) }
+    reference
+}
+
+// converts a buffer argument, NULL is only accepted for empty buffers
+unsafe fn slice_arg<'a, T>(pointer: *const T, len: usize, name: &str) -> Option<&'a [T]> {
+    if len == 0 { return Some(&[]) }
+    if pointer.is_null() {
+        set_last_error(format!("{name} is NULL"));
+        return None
+    }
+    Some(slice::from_raw_parts(pointer, len))
+}
+
+unsafe fn mut_slice_arg<'a, T>(pointer: *mut T, len: usize, name: &str) -> Option<&'a mut [T]> {
+    if len == 0 { return Some(&mut []) }
+    if pointer.is_null() {
+        set_last_error(format!("{name} is NULL"));
+        return None
+    }
+    Some(slice::from_raw_parts_mut(pointer, len))
+}
+
+// converts a C string argument, NULL and non UTF-8 strings are errors
+unsafe fn path_arg(path: *const c_char, name: &str) -> Option<PathBuf> {
+    if path.is_null() {
+        set_last_error(format!("{name} is NULL"));
+        return None
+    }
+    match CStr::from_ptr(path).to_str() {
+        Ok(path) => Some(PathBuf::from(path)),
+        Err(_) => {
+            set_last_error(format!("{name} is not valid UTF-8"));
+            None
+        },
+    }
+}
+
+/// Frames read from an OSD file
+pub struct HdFpvOSDFile {
+    frames: SortedUniqFrames,
+}
+
+impl HdFpvOSDFile {
+
+    fn frame(&self, frame_position: u32) -> Option<&osd::file::Frame> {
+        let frame = self.frames.get(frame_position as usize);
+        if frame.is_none() { set_last_error(format!("OSD frame position out of range: {frame_position}")) }
+        frame
+    }
+
+}
+
+/// Renders the overlay frames of an OSD file with its native resolution
+#[cfg(feature = "overlay")]
+pub struct HdFpvOverlayRenderer {
+    generator: OverlayGenerator<'static>,
+}
+
+/// Message of the last error of the calling thread, NULL if there was none
+///
+/// The message is owned by the library and stays valid until the next failing call on the same thread.
+#[no_mangle]
+pub extern "C" fn hd_fpv_last_error() -> *const c_char {
+    catch_panic(ptr::null(), ||
+        LAST_ERROR.with(|last_error| last_error.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+    )
+}
+
+/// Opens a DJI or Walksnail Avatar OSD file and reads all its frames
+///
+/// # Safety
+/// `path` must be NULL or a valid NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn hd_fpv_osd_file_open(path: *const c_char) -> *mut HdFpvOSDFile {
+    catch_panic(ptr::null_mut(), || {
+        let Some(path) = path_arg(path, "path") else { return ptr::null_mut() };
+        let frames = match osd::file::open(&path) {
+            Ok(mut osd_file) => osd_file.frames(),
+            Err(error) => { set_last_error(error); return ptr::null_mut() },
+        };
+        match frames {
+            Ok(frames) => Box::into_raw(Box::new(HdFpvOSDFile { frames })),
+            Err(error) => { set_last_error(error); ptr::null_mut() },
+        }
+    })
+}
+
+/// Parses the content of a DJI or Walksnail Avatar OSD file held in memory
+///
+/// # Safety
+/// `data` must be NULL or point to at least `len` bytes, NULL is only accepted when `len` is 0.
+#[no_mangle]
+pub unsafe extern "C" fn hd_fpv_osd_file_parse(data: *const u8, len: usize) -> *mut HdFpvOSDFile {
+    catch_panic(ptr::null_mut(), || {
+        let Some(data) = slice_arg(data, len, "data") else { return ptr::null_mut() };
+        let frames = match dji::file::parse(data) {
+            Ok(frames) => frames,
+            Err(dji::file::ParseError::InvalidSignature) => match wsa::file::parse(data) {
+                Ok(frames) => frames,
+                Err(error) => { set_last_error(error); return ptr::null_mut() },
+            },
+            Err(error) => { set_last_error(error); return ptr::null_mut() },
+        };
+        Box::into_raw(Box::new(HdFpvOSDFile { frames }))
+    })
+}
+
+/// Frees an OSD file returned by [`hd_fpv_osd_file_open`] or [`hd_fpv_osd_file_parse`], NULL is ignored
+///
+/// # Safety
+/// `osd_file` must be NULL or a pointer returned by [`hd_fpv_osd_file_open`] or [`hd_fpv_osd_file_parse`] which has not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn hd_fpv_osd_file_free(osd_file: *mut HdFpvOSDFile) {
+    catch_panic((), || if ! osd_file.is_null() { drop(Box::from_raw(osd_file)) })
+}
+
+/// Number of frames of the OSD file, negative on error
+///
+/// # Safety
+/// `osd_file` must be NULL or a valid pointer returned by [`hd_fpv_osd_file_open`] or [`hd_fpv_osd_file_parse`].
+#[no_mangle]
+pub unsafe extern "C" fn hd_fpv_osd_file_frame_count(osd_file: *const HdFpvOSDFile) -> i64 {
+    catch_panic(-1, || {
+        let Some(osd_file) = ref_arg(osd_file, "osd_file") else { return -1 };
+        osd_file.frames.len() as i64
+    })
+}
+
+/// Width and height of the OSD grid in tiles, returns 0 on success
+///
+/// # Safety
+/// `osd_file` must be NULL or a valid pointer returned by [`hd_fpv_osd_file_open`] or [`hd_fpv_osd_file_parse`], `width` and
+/// `height` NULL or valid pointers.
+#[no_mangle]
+pub unsafe extern "C" fn hd_fpv_osd_file_dimensions_tiles(osd_file: *const HdFpvOSDFile, width: *mut u32, height: *mut u32) -> c_int {
+    catch_panic(-1, || {
+        let (Some(osd_file), Some(width), Some(height)) = (ref_arg(osd_file, "osd_file"), mut_arg(width, "width"), mut_arg(height, "height"))
+            else { return -1 };
+        let dimensions = osd_file.frames.kind().dimensions_tiles();
+        *width = dimensions.width;
+        *height = dimensions.height;
+        0
+    })
+}
+
+/// Index of the video frame at which an OSD frame is displayed, negative if `frame_position` is out of range
+///
+/// # Safety
+/// `osd_file` must be NULL or a valid pointer returned by [`hd_fpv_osd_file_open`] or [`hd_fpv_osd_file_parse`].
+#[no_mangle]
+pub unsafe extern "C" fn hd_fpv_osd_file_frame_index(osd_file: *const HdFpvOSDFile, frame_position: u32) -> i64 {
+    catch_panic(-1, || {
+        let Some(osd_file) = ref_arg(osd_file, "osd_file") else { return -1 };
+        osd_file.frame(frame_position).map_or(-1, |frame| frame.index() as i64)
+    })
+}
+
+/// Copies the tile indices of an OSD frame, column by column, returns the number of tile indices of the frame
+///
+/// At most `capacity` tile indices are copied, a negative value is returned if `frame_position` is out of range.
+///
+/// # Safety
+/// `osd_file` must be NULL or a valid pointer returned by [`hd_fpv_osd_file_open`] or [`hd_fpv_osd_file_parse`] and
+/// `tile_indices` point to at least `capacity` values, NULL is only accepted when `capacity` is 0.
+#[no_mangle]
+pub unsafe extern "C" fn hd_fpv_osd_file_frame_tile_indices(osd_file: *const HdFpvOSDFile, frame_position: u32,
+                                                             tile_indices: *mut u16, capacity: usize) -> i64 {
+    catch_panic(-1, || {
+        let (Some(osd_file), Some(tile_indices)) = (ref_arg(osd_file, "osd_file"), mut_slice_arg(tile_indices, capacity, "tile_indices"))
+            else { return -1 };
+        let Some(frame) = osd_file.frame(frame_position) else { return -1 };
+        let frame_tile_indices = frame.tile_indices();
+        let copied_count = frame_tile_indices.len().min(capacity);
+        tile_indices[..copied_count].copy_from_slice(&frame_tile_indices[..copied_count]);
+        frame_tile_indices.len() as i64
+    })
+}
+
+/// Loads the fonts needed to render the overlay frames of an OSD file, the font variant is the one read from the OSD file
+///
+/// The OSD file can be freed once the renderer has been created.
+///
+/// # Safety
+/// `osd_file` must be NULL or a valid pointer returned by [`hd_fpv_osd_file_open`] or [`hd_fpv_osd_file_parse`] and
+/// `font_dir` NULL or a valid NUL terminated string.
+#[cfg(feature = "overlay")]
+#[no_mangle]
+pub unsafe extern "C" fn hd_fpv_overlay_renderer_new(osd_file: *const HdFpvOSDFile, font_dir: *const c_char) -> *mut HdFpvOverlayRenderer {
+    catch_panic(ptr::null_mut(), || {
+        let (Some(osd_file), Some(font_dir)) = (ref_arg(osd_file, "osd_file"), path_arg(font_dir, "font_dir")) else { return ptr::null_mut() };
+        let frames = osd_file.frames.clone();
+        let font_variant = frames.font_variant();
+        match OverlayGenerator::new(frames, font_variant, &FontDir::new(font_dir), &None, Scaling::No { target_resolution: None }, &[], false, &[]) {
+            Ok(generator) => Box::into_raw(Box::new(HdFpvOverlayRenderer { generator })),
+            Err(error) => { set_last_error(error); ptr::null_mut() },
+        }
+    })
+}
+
+/// Frees a renderer returned by [`hd_fpv_overlay_renderer_new`], NULL is ignored
+///
+/// # Safety
+/// `renderer` must be NULL or a pointer returned by [`hd_fpv_overlay_renderer_new`] which has not been freed yet.
+#[cfg(feature = "overlay")]
+#[no_mangle]
+pub unsafe extern "C" fn hd_fpv_overlay_renderer_free(renderer: *mut HdFpvOverlayRenderer) {
+    catch_panic((), || if ! renderer.is_null() { drop(Box::from_raw(renderer)) })
+}
+
+/// Width and height of the rendered overlay frames in pixels, returns 0 on success
+///
+/// # Safety
+/// `renderer` must be NULL or a valid pointer returned by [`hd_fpv_overlay_renderer_new`], `width` and `height` NULL or valid pointers.
+#[cfg(feature = "overlay")]
+#[no_mangle]
+pub unsafe extern "C" fn hd_fpv_overlay_renderer_frame_dimensions(renderer: *const HdFpvOverlayRenderer, width: *mut u32, height: *mut u32) -> c_int {
+    catch_panic(-1, || {
+        let (Some(renderer), Some(width), Some(height)) = (ref_arg(renderer, "renderer"), mut_arg(width, "width"), mut_arg(height, "height"))
+            else { return -1 };
+        let dimensions = renderer.generator.frame_dimensions();
+        *width = dimensions.width;
+        *height = dimensions.height;
+        0
+    })
+}
+
+/// Renders the overlay of an OSD frame as RGBA pixels, returns 0 on success
+///
+/// `buffer_size` must be at least width * height * 4 bytes, see [`hd_fpv_overlay_renderer_frame_dimensions`].
+///
+/// # Safety
+/// `renderer` must be NULL or a valid pointer returned by [`hd_fpv_overlay_renderer_new`] and `buffer` point to at least
+/// `buffer_size` bytes, NULL is only accepted when `buffer_size` is 0.
+#[cfg(feature = "overlay")]
+#[no_mangle]
+pub unsafe extern "C" fn hd_fpv_overlay_renderer_render(renderer: *const HdFpvOverlayRenderer, frame_position: u32,
+                                                         buffer: *mut u8, buffer_size: usize) -> c_int {
+    catch_panic(-1, || {
+        let (Some(renderer), Some(buffer)) = (ref_arg(renderer, "renderer"), mut_slice_arg(buffer, buffer_size, "buffer")) else { return -1 };
+        let frame = match renderer.generator.render_osd_frame(frame_position as usize) {
+            Some(Ok(frame)) => frame,
+            Some(Err(error)) => { set_last_error(error); return -1 },
+            None => { set_last_error(format!("OSD frame position out of range: {frame_position}")); return -1 },
+        };
+        let pixels = frame.as_raw();
+        if buffer.len() < pixels.len() {
+            set_last_error(format!("buffer too small: {} bytes needed", pixels.len()));
+            return -1
+        }
+        buffer[..pixels.len()].copy_from_slice(pixels);
+        0
+    })
+}
@@ -1,20 +1,60 @@
 
-use std::{process, path::{Path, PathBuf}, ffi::OsString, fmt::Display, io::{Error as IOError, Read}};
+use std::{collections::HashSet, process, path::{Path, PathBuf}, ffi::{OsStr, OsString}, fmt::Display, io::{Error as IOError, Read}, sync::OnceLock};
 
 use derive_more::{Deref, DerefMut};
 use getset::{Getters, Setters, CopyGetters};
 use indicatif::{ProgressStyle, ProgressBar};
-use regex::Regex;
 use thiserror::Error;
-use lazy_static::lazy_static;
 use tokio::task::JoinHandle;
-use ringbuffer::{self, ConstGenericRingBuffer, RingBufferWrite, RingBufferExt};
+use ringbuffer::{self, AllocRingBuffer, RingBufferWrite, RingBufferExt};
 
 use crate::video::{self, Resolution, Timestamp};
-use crate::process::Command as ProcessCommand;
+use crate::process::{self as process_command, Command as ProcessCommand};
+use crate::log_file;
+use crate::file;
+use crate::{cancellation::{self, ChildGuard}, notification, throttle::{self, SharedChild}};
+use crate::remote::RemoteHost;
 
+pub mod progress;
+pub mod frame_pipe;
+pub mod encoder_args;
+pub mod bitrate;
 
-const DEFAULT_BINARY_PATH: &str = "ffmpeg";
+use progress::{ProgressCallback, ProgressUpdate};
+use encoder_args::EncoderArgs;
+use bitrate::Bitrate;
+
+
+pub const DEFAULT_BINARY_PATH: &str = "ffmpeg";
+
+static BINARY_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// Sets the path of the FFMpeg binary to run instead of the one found in the PATH, only the first path set is used
+pub fn set_binary_path<P: AsRef<Path>>(binary_path: P) {
+    let _ = BINARY_PATH.set(binary_path.as_ref().to_path_buf());
+}
+
+/// Path of the FFMpeg binary to run
+pub fn binary_path() -> &'static Path {
+    BINARY_PATH.get().map(PathBuf::as_path).unwrap_or(Path::new(DEFAULT_BINARY_PATH))
+}
+
+/// Returns the names of the encoders supported by the FFMpeg binary
+pub fn available_encoders<P: AsRef<Path>>(ffmpeg_path: P) -> Result<HashSet<String>, IOError> {
+    let output = process::Command::new(ffmpeg_path.as_ref()).args(["-hide_banner", "-encoders"]).output()?;
+    // encoder lines look like ` V....D h264_nvenc           NVIDIA NVENC H.264 encoder (codec h264)`
+    Ok(String::from_utf8_lossy(&output.stdout).lines().filter_map(|line| {
+        let mut fields = line.split_whitespace();
+        let flags = fields.next()?;
+        let name = fields.next()?;
+        if flags.len() != 6 || name == "=" { return None }
+        Some(name.to_owned())
+    }).collect())
+}
+
+// number of the last lines of the FFMpeg output reported when it fails
+const REPORTED_OUTPUT_LINE_COUNT: usize = 16;
+const DEBUG_REPORTED_OUTPUT_LINE_COUNT: usize = 256;
 
 #[derive(Debug, Clone)]
 pub enum Input {
@@ -22,11 +62,29 @@ pub enum Input {
         path: PathBuf,
         start: Option<Timestamp>,
         end: Option<Timestamp>,
+        // decoder to use instead of the default one of the codec
+        decoder: Option<String>,
     },
     StdinPipedRaw {
         resolution: Resolution,
+        // integer or `<numerator>/<denominator>`
+        frame_rate: String,
+    },
+    ImageSequence {
+        path_pattern: PathBuf,
         frame_rate: u16,
-    }
+        start_number: u32,
+    },
+    // concat demuxer script, the paths it contains are absolute
+    ConcatScript {
+        path: PathBuf,
+        start: Option<Timestamp>,
+        end: Option<Timestamp>,
+    },
+    // source filter graph, e.g. `testsrc2=size=1920x1080:rate=60:duration=10`
+    Lavfi {
+        graph: String,
+    },
 }
 
 impl Input {
@@ -34,7 +92,7 @@ impl Input {
         let mut args = vec![];
         match self {
 
-            Input::File { path, start, end } => {
+            Input::File { path, start, end, decoder } => {
                 if let Some(start) = start {
                     args.push("-ss".into());
                     args.push(start.to_ffmpeg_position().into());
@@ -43,6 +101,10 @@ impl Input {
                     args.push("-to".into());
                     args.push(end.to_ffmpeg_position().into());
                 }
+                if let Some(decoder) = decoder {
+                    args.push("-c:v".into());
+                    args.push(decoder.into());
+                }
                 args.push("-i".into());
                 args.push(path.clone().into_os_string());
             },
@@ -51,10 +113,37 @@ impl Input {
                 args.append(&mut ["-f", "rawvideo", "-pix_fmt", "rgba", "-video_size" ].map(Into::into).into());
                 args.push(resolution.to_string().into());
                 args.push("-r".into());
-                args.push(frame_rate.to_string().into());
+                args.push(frame_rate.into());
                 args.append(&mut ["-i", "pipe:0"].map(Into::into).into());
             },
 
+            Input::ImageSequence { path_pattern, frame_rate, start_number } => {
+                args.append(&mut ["-f", "image2", "-framerate"].map(Into::into).into());
+                args.push(frame_rate.to_string().into());
+                args.push("-start_number".into());
+                args.push(start_number.to_string().into());
+                args.push("-i".into());
+                args.push(path_pattern.clone().into_os_string());
+            },
+
+            Input::ConcatScript { path, start, end } => {
+                if let Some(start) = start {
+                    args.push("-ss".into());
+                    args.push(start.to_ffmpeg_position().into());
+                }
+                if let Some(end) = end {
+                    args.push("-to".into());
+                    args.push(end.to_ffmpeg_position().into());
+                }
+                args.append(&mut ["-f", "concat", "-safe", "0", "-i"].map(Into::into).into());
+                args.push(path.clone().into_os_string());
+            },
+
+            Input::Lavfi { graph } => {
+                args.append(&mut ["-f", "lavfi", "-i"].map(Into::into).into());
+                args.push(graph.into());
+            },
+
         }
         args
     }
@@ -85,7 +174,7 @@ impl Filter {
 #[getset(get = "pub", set = "pub(self)")]
 pub struct CommonOutputStreamSettings {
     codec: Option<String>,
-    bitrate: Option<String>,
+    bitrate: Option<Bitrate>,
 }
 
 #[derive(Debug, Clone, Deref, DerefMut, Default)]
@@ -179,6 +268,89 @@ impl Mapping {
 
 }
 
+#[cfg(windows)]
+const NULL_OUTPUT_PATH: &str = "NUL";
+#[cfg(not(windows))]
+const NULL_OUTPUT_PATH: &str = "/dev/null";
+
+pub const TWO_PASS_COUNT: u8 = 2;
+
+#[derive(Debug, Clone, CopyGetters, Getters)]
+pub struct TwoPassSettings {
+    #[getset(get_copy = "pub")]
+    pass: u8,
+    #[getset(get = "pub")]
+    log_file_prefix: PathBuf,
+}
+
+impl TwoPassSettings {
+
+    pub fn new<P: AsRef<Path>>(pass: u8, log_file_prefix: P) -> Self {
+        Self { pass, log_file_prefix: log_file_prefix.as_ref().to_path_buf() }
+    }
+
+    pub fn is_first_pass(&self) -> bool {
+        self.pass == 1
+    }
+
+    pub fn to_args(&self, video_codec: Option<&str>) -> Vec<OsString> {
+        let mut args = vec![];
+        match video_codec {
+            // x265 does not support the generic pass options
+            Some("libx265") => {
+                let mut x265_params = OsString::from(format!("pass={}:stats=", self.pass));
+                x265_params.push(self.log_file_prefix.as_os_str());
+                args.push("-x265-params".into());
+                args.push(x265_params);
+            },
+            _ => {
+                args.push("-pass".into());
+                args.push(self.pass.to_string().into());
+                args.push("-passlogfile".into());
+                args.push(self.log_file_prefix.clone().into_os_string());
+            },
+        }
+        args
+    }
+
+}
+
+/// Escapes a value, e.g. a file path, to be used as a filter option value in a filter graph
+///
+/// The value is escaped twice: once for the filter option parser and once for the filter graph parser.
+pub fn escape_filter_option_value<S: AsRef<str>>(value: S) -> String {
+    let escape = |value: &str, special_chars: &[char]| value.chars().fold(String::with_capacity(value.len()), |mut escaped, c| {
+        if special_chars.contains(&c) { escaped.push('\\') }
+        escaped.push(c);
+        escaped
+    });
+    escape(&escape(value.as_ref(), &['\\', '\'', ':']), &['\\', '\'', '[', ']', ',', ';'])
+}
+
+/// Returns the prefix of the log files written by ffmpeg during two-pass encoding of the specified output file
+pub fn two_pass_log_file_prefix<P: AsRef<Path>>(output_file: P) -> PathBuf {
+    let output_file = output_file.as_ref();
+    let mut file_name = output_file.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".ffmpeg2pass");
+    output_file.with_file_name(file_name)
+}
+
+/// Removes the log files written by ffmpeg during two-pass encoding
+pub fn remove_two_pass_log_files<P: AsRef<Path>>(log_file_prefix: P) {
+    let log_file_prefix = log_file_prefix.as_ref();
+    let (Some(dir), Some(prefix)) = (log_file_prefix.parent(), log_file_prefix.file_name()) else { return };
+    let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    let Ok(dir_entries) = std::fs::read_dir(dir) else { return };
+    let prefix = prefix.to_string_lossy();
+    for dir_entry in dir_entries.flatten() {
+        if dir_entry.file_name().to_string_lossy().starts_with(prefix.as_ref()) {
+            if let Err(error) = std::fs::remove_file(dir_entry.path()) {
+                log::warn!("failed to remove two-pass log file {}: {error}", dir_entry.path().to_string_lossy());
+            }
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 #[error("failed to build FFMpeg command: {0}")]
 pub struct BuildCommandError(&'static str);
@@ -187,29 +359,147 @@ pub struct BuildCommandError(&'static str);
 #[error("only one stdin input possible")]
 pub struct CommandHasAlreadyOneStdinInput;
 
+/// Output file written by the same command as the main output from the same decoded inputs, with its own streams and settings
+#[derive(Debug, Clone, Getters)]
+#[getset(get = "pub")]
+pub struct AdditionalOutput {
+    mappings: Vec<Mapping>,
+    video_output_settings: VideoOutputSettings,
+    audio_output_settings: AudioOutputSettings,
+    args: Vec<String>,
+    path: PathBuf,
+}
+
+impl AdditionalOutput {
+
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            mappings: vec![],
+            video_output_settings: VideoOutputSettings::default(),
+            audio_output_settings: AudioOutputSettings::default(),
+            args: vec![],
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn add_mapping(&mut self, mapping: Mapping) -> &mut Self {
+        self.mappings.push(mapping);
+        self
+    }
+
+    pub fn set_output_video_settings(&mut self, codec: Option<&str>, bitrate: Option<Bitrate>, crf: Option<u8>) -> &mut Self {
+        self.video_output_settings.set_codec(codec.map(str::to_string));
+        self.video_output_settings.set_bitrate(bitrate);
+        self.video_output_settings.set_crf(crf);
+        self
+    }
+
+    pub fn set_output_audio_settings(&mut self, codec: Option<&str>, bitrate: Option<Bitrate>) -> &mut Self {
+        self.audio_output_settings.set_codec(codec.map(str::to_string));
+        self.audio_output_settings.set_bitrate(bitrate);
+        self
+    }
+
+    pub fn add_args(&mut self, args: &[&str]) -> &mut Self {
+        self.args.extend(args.iter().map(|arg| arg.to_string()));
+        self
+    }
+
+    fn to_args(&self, written_path: &Path) -> Vec<OsString> {
+        let mut args = vec![];
+        for mapping in &self.mappings {
+            args.append(&mut mapping.to_args());
+        }
+        args.append(&mut self.audio_output_settings.to_args());
+        args.append(&mut self.video_output_settings.to_args());
+        args.extend(self.args.iter().map(OsString::from));
+        args.push(written_path.as_os_str().to_os_string());
+        args
+    }
+
+    // the streams are discarded, the filtergraph outputs they come from still need to be consumed
+    fn to_null_output_args(&self) -> Vec<OsString> {
+        let mut args = vec![];
+        for mapping in &self.mappings {
+            args.append(&mut mapping.to_args());
+        }
+        args.extend(["-f", "null", NULL_OUTPUT_PATH].map(OsString::from));
+        args
+    }
+
+}
+
 #[derive(Default, Getters, Clone)]
 #[getset(get = "pub")]
 pub struct CommandBuilder {
     bin_path: Option<PathBuf>,
+    global_args: Vec<String>,
     inputs: Vec<Input>,
     filters: Vec<Filter>,
     mappings: Vec<Mapping>,
+    map_metadata: Option<i32>,
     video_output_settings: VideoOutputSettings,
     audio_output_settings: AudioOutputSettings,
     args: Vec<String>,
+    encoder_args: Vec<String>,
+    output_format: Option<String>,
+    output_movflags: Option<String>,
     output: Option<PathBuf>,
+    additional_outputs: Vec<AdditionalOutput>,
     overwrite_output_file: bool,
+    two_pass: Option<TwoPassSettings>,
+    remote_host: Option<RemoteHost>,
+    progress_callback: Option<ProgressCallback>,
+    atomic_output: bool,
 }
 
 impl CommandBuilder {
 
+    /// Writes the output files under a temporary name in the same directory, they are renamed to their final path once the
+    /// process has exited successfully so that a failed run does not leave truncated files behind. Not used when encoding remotely.
+    pub fn set_atomic_output(&mut self, yes: bool) -> &mut Self {
+        self.atomic_output = yes;
+        self
+    }
+
+    /// Sets a function called with each progress update of the process spawned with [`Command::spawn_with_progress`]
+    pub fn set_progress_callback(&mut self, callback: Option<ProgressCallback>) -> &mut Self {
+        self.progress_callback = callback;
+        self
+    }
+
+    /// Runs FFMpeg on the specified host through SSH, the paths of the inputs and output are paths of the remote host
+    pub fn set_remote_host(&mut self, remote_host: Option<RemoteHost>) -> &mut Self {
+        self.remote_host = remote_host;
+        self
+    }
+
     pub fn set_ffmpeg_binary_path<P: AsRef<Path>>(&mut self, binary_path: P) -> &mut Self {
         self.bin_path = Some(binary_path.as_ref().to_path_buf());
         self
     }
 
+    /// Adds arguments placed before the inputs, e.g. hardware device initialization
+    pub fn add_global_args(&mut self, args: &[String]) -> &mut Self {
+        self.global_args.extend_from_slice(args);
+        self
+    }
+
+    /// Adds an input file read from `start` to `end`, the whole file being read when they are not specified
     pub fn add_input_file_slice<P: AsRef<Path>>(&mut self, file_path: P, start: Option<Timestamp>, end: Option<Timestamp>) -> &mut Self {
-        self.inputs.push(Input::File { path: file_path.as_ref().to_path_buf(), start, end });
+        self.inputs.push(Input::File { path: file_path.as_ref().to_path_buf(), start, end, decoder: None });
+        self
+    }
+
+    /// Same as [`Self::add_input_file_with_decoder`] with only the part of the file between `start` and `end` being read
+    pub fn add_input_file_slice_with_decoder<P: AsRef<Path>>(&mut self, file_path: P, start: Option<Timestamp>, end: Option<Timestamp>, decoder: &str) -> &mut Self {
+        self.inputs.push(Input::File { path: file_path.as_ref().to_path_buf(), start, end, decoder: Some(decoder.to_owned()) });
+        self
+    }
+
+    /// Adds an input file decoded with the specified video decoder, e.g. `libvpx` to decode the alpha channel of VP8 videos
+    pub fn add_input_file_with_decoder<P: AsRef<Path>>(&mut self, file_path: P, decoder: &str) -> &mut Self {
+        self.inputs.push(Input::File { path: file_path.as_ref().to_path_buf(), start: None, end: None, decoder: Some(decoder.to_owned()) });
         self
     }
 
@@ -218,16 +508,49 @@ impl CommandBuilder {
         self
     }
 
+    pub fn input_count(&self) -> usize {
+        self.inputs.len()
+    }
+
     pub fn has_stdin_input(&self) -> bool {
         self.inputs().iter().any(|input| matches!(input, Input::StdinPipedRaw {..}))
     }
 
     pub fn add_stdin_input(&mut self, resolution: Resolution, frame_rate: u16) -> Result<&mut Self, CommandHasAlreadyOneStdinInput>  {
         if self.has_stdin_input() { return Err(CommandHasAlreadyOneStdinInput) }
-        self.inputs.push(Input::StdinPipedRaw { resolution, frame_rate });
+        self.inputs.push(Input::StdinPipedRaw { resolution, frame_rate: frame_rate.to_string() });
         Ok(self)
     }
 
+    /// Same as [`Self::add_stdin_input`] with a fractional frame rate, e.g. 30000/1001 for NTSC rates
+    pub fn add_stdin_input_with_rational_frame_rate(&mut self, resolution: Resolution, frame_rate_numerator: i32, frame_rate_denominator: i32) -> Result<&mut Self, CommandHasAlreadyOneStdinInput>  {
+        if self.has_stdin_input() { return Err(CommandHasAlreadyOneStdinInput) }
+        self.inputs.push(Input::StdinPipedRaw { resolution, frame_rate: format!("{frame_rate_numerator}/{frame_rate_denominator}") });
+        Ok(self)
+    }
+
+    /// Adds a numbered image files input, `path_pattern` being an image2 demuxer pattern like `frames/%010d.png`
+    pub fn add_image_sequence_input<P: AsRef<Path>>(&mut self, path_pattern: P, frame_rate: u16, start_number: u32) -> &mut Self {
+        self.inputs.push(Input::ImageSequence { path_pattern: path_pattern.as_ref().to_path_buf(), frame_rate, start_number });
+        self
+    }
+
+    pub fn add_concat_script_input<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.add_concat_script_input_slice(path, None, None)
+    }
+
+    /// Adds the concatenation of the files listed in a concat demuxer script as an input, read from `start` to `end`
+    pub fn add_concat_script_input_slice<P: AsRef<Path>>(&mut self, path: P, start: Option<Timestamp>, end: Option<Timestamp>) -> &mut Self {
+        self.inputs.push(Input::ConcatScript { path: path.as_ref().to_path_buf(), start, end });
+        self
+    }
+
+    /// Adds an input generated by a source filter graph, e.g. `testsrc2=size=1920x1080:rate=60:duration=10`
+    pub fn add_lavfi_input(&mut self, graph: &str) -> &mut Self {
+        self.inputs.push(Input::Lavfi { graph: graph.to_owned() });
+        self
+    }
+
     pub fn add_audio_filter(&mut self, filter: &str) -> &mut Self {
         self.filters.push(Filter::Audio(filter.to_string()));
         self
@@ -271,13 +594,19 @@ impl CommandBuilder {
         self
     }
 
+    /// Copies the global metadata of the specified input, e.g. the creation time, to the output, `-1` removes the metadata
+    pub fn set_map_metadata(&mut self, input_index: Option<i32>) -> &mut Self {
+        self.map_metadata = input_index;
+        self
+    }
+
     pub fn set_output_video_codec(&mut self, codec: Option<&str>) -> &mut Self {
         self.video_output_settings.set_codec(codec.map(str::to_string));
         self
     }
 
-    pub fn set_output_video_bitrate(&mut self, bitrate: Option<&str>) -> &mut Self {
-        self.video_output_settings.set_bitrate(bitrate.map(str::to_string));
+    pub fn set_output_video_bitrate(&mut self, bitrate: Option<Bitrate>) -> &mut Self {
+        self.video_output_settings.set_bitrate(bitrate);
         self
     }
 
@@ -286,7 +615,7 @@ impl CommandBuilder {
         self
     }
 
-    pub fn set_output_video_settings(&mut self, codec: Option<&str>, bitrate: Option<&str>, crf: Option<u8>) -> &mut Self {
+    pub fn set_output_video_settings(&mut self, codec: Option<&str>, bitrate: Option<Bitrate>, crf: Option<u8>) -> &mut Self {
         self
             .set_output_video_codec(codec)
             .set_output_video_bitrate(bitrate)
@@ -298,12 +627,12 @@ impl CommandBuilder {
         self
     }
 
-    pub fn set_output_audio_bitrate(&mut self, bitrate: Option<&str>) -> &mut Self {
-        self.audio_output_settings.set_bitrate(bitrate.map(str::to_string));
+    pub fn set_output_audio_bitrate(&mut self, bitrate: Option<Bitrate>) -> &mut Self {
+        self.audio_output_settings.set_bitrate(bitrate);
         self
     }
 
-    pub fn set_output_audio_settings(&mut self, codec: Option<&str>, bitrate: Option<&str>) -> &mut Self {
+    pub fn set_output_audio_settings(&mut self, codec: Option<&str>, bitrate: Option<Bitrate>) -> &mut Self {
         self
             .set_output_audio_codec(codec)
             .set_output_audio_bitrate(bitrate)
@@ -319,19 +648,51 @@ impl CommandBuilder {
         self
     }
 
+    /// Adds encoder specific arguments for the main output, they are placed last so that they override the other settings
+    pub fn add_encoder_args(&mut self, encoder_args: &EncoderArgs) -> &mut Self {
+        self.encoder_args.extend_from_slice(encoder_args.args());
+        self
+    }
+
+    /// Adds an output written after the main output, e.g. a lower resolution proxy, it is discarded by the first pass of a two-pass encode
+    pub fn add_additional_output(&mut self, output: AdditionalOutput) -> &mut Self {
+        self.additional_outputs.push(output);
+        self
+    }
+
+    /// Runs the specified pass of a two-pass encode, the first pass output is discarded
+    pub fn set_two_pass<P: AsRef<Path>>(&mut self, pass: u8, log_file_prefix: P) -> &mut Self {
+        self.two_pass = Some(TwoPassSettings::new(pass, log_file_prefix));
+        self
+    }
+
     pub fn set_overwrite_output_file(&mut self, yes: bool) -> &mut Self {
         self.overwrite_output_file = yes;
         self
     }
 
+    /// Forces the muxer of the main output instead of the one inferred from its file extension, e.g. `matroska`
+    pub fn set_output_format(&mut self, format: Option<&str>) -> &mut Self {
+        self.output_format = format.map(str::to_owned);
+        self
+    }
+
+    /// Sets the `-movflags` of the main output, only accepted by the MP4 and MOV muxers, e.g. `+faststart`
+    pub fn set_output_movflags(&mut self, movflags: Option<&str>) -> &mut Self {
+        self.output_movflags = movflags.map(str::to_owned);
+        self
+    }
+
     pub fn set_output_file<P: AsRef<Path>>(&mut self, file_path: P) -> &mut Self {
         self.output = Some(file_path.as_ref().to_path_buf());
         self
     }
 
     pub fn build(&self) -> Result<Command, BuildCommandError> {
-        let binary_path = self.bin_path.clone().unwrap_or_else(|| PathBuf::from(DEFAULT_BINARY_PATH));
-        let mut pcommand = ProcessCommand::new(binary_path);
+        let bin_path = self.bin_path.clone().unwrap_or_else(|| binary_path().to_path_buf());
+        let mut pcommand = ProcessCommand::new(bin_path);
+
+        pcommand.args(&self.global_args);
 
         if self.inputs.is_empty() { return Err(BuildCommandError("no input"))}
         for input in &self.inputs {
@@ -346,19 +707,54 @@ impl CommandBuilder {
             pcommand.args(mapping.to_args());
         }
 
+        if let Some(input_index) = self.map_metadata {
+            pcommand.args(["-map_metadata".to_owned(), input_index.to_string()]);
+        }
+
         pcommand.args(self.audio_output_settings.to_args());
         pcommand.args(self.video_output_settings.to_args());
 
+        if let Some(two_pass) = &self.two_pass {
+            pcommand.args(two_pass.to_args(self.video_output_settings.codec().as_deref()));
+        }
+
         pcommand.args(self.args.iter().map(OsString::from).collect::<Vec<_>>());
+        pcommand.args(&self.encoder_args);
 
         if self.overwrite_output_file { pcommand.arg("-y"); }
 
-        match &self.output {
-            Some(output) => pcommand.arg(output),
-            None => return Err(BuildCommandError("no output")),
-        };
+        let atomic_output = self.atomic_output && self.remote_host.is_none();
+        let mut output_files = vec![];
+        match (&self.output, &self.two_pass) {
+            (None, _) => return Err(BuildCommandError("no output")),
+            (Some(_), Some(two_pass)) if two_pass.is_first_pass() => {
+                pcommand.args(["-an", "-f", "null", NULL_OUTPUT_PATH]);
+                for additional_output in &self.additional_outputs {
+                    pcommand.args(additional_output.to_null_output_args());
+                }
+            },
+            (Some(output), _) => {
+                if let Some(output_format) = &self.output_format { pcommand.args(["-f", output_format]); }
+                if let Some(output_movflags) = &self.output_movflags { pcommand.args(["-movflags", output_movflags]); }
+                let output_file = OutputFile::new(output, atomic_output);
+                pcommand.arg(output_file.written_path());
+                output_files.push(output_file);
+                for additional_output in &self.additional_outputs {
+                    let output_file = OutputFile::new(&additional_output.path, atomic_output);
+                    pcommand.args(additional_output.to_args(output_file.written_path()));
+                    output_files.push(output_file);
+                }
+            },
+        }
+
+        if let Some(remote_host) = &self.remote_host {
+            pcommand = remote_host.ssh_command(OsStr::new(remote_host.ffmpeg_path()), pcommand.get_args());
+        }
 
-        Ok(Command { command: pcommand, has_stdin_input: self.has_stdin_input() })
+        let pass = self.two_pass.as_ref().map(TwoPassSettings::pass);
+
+        Ok(Command { command: pcommand, has_stdin_input: self.has_stdin_input(), pass, progress_callback: self.progress_callback.clone(), output_files,
+            remote: self.remote_host.is_some() })
     }
 
 }
@@ -368,10 +764,36 @@ pub struct Command {
     command: ProcessCommand,
     #[getset(get_copy = "pub")]
     has_stdin_input: bool,
+    #[getset(get_copy = "pub")]
+    pass: Option<u8>,
+    progress_callback: Option<ProgressCallback>,
+    // files written by the process, they are no longer removed on cancellation once it has exited successfully
+    output_files: Vec<OutputFile>,
+    // run through SSH, the local process is then not stopped when pausing or throttling
+    remote: bool,
+}
+
+// output file of a process, written under a temporary name when the output is atomic
+#[derive(Clone)]
+struct OutputFile {
+    path: PathBuf,
+    temp_path: Option<PathBuf>,
+}
+
+impl OutputFile {
+
+    fn new(path: &Path, atomic: bool) -> Self {
+        Self { path: path.to_path_buf(), temp_path: atomic.then(|| file::temp_output_path(path)) }
+    }
+
+    fn written_path(&self) -> &Path {
+        self.temp_path.as_deref().unwrap_or(&self.path)
+    }
+
 }
 
 #[derive(Debug, Error)]
-#[error("failed spawning ffmpeg process: {bin_path}: {error}")]
+#[error("failed spawning ffmpeg process: {bin_path}: {error}, make sure FFMpeg is installed or specify its path with --ffmpeg-path")]
 pub struct SpawnError {
     bin_path: String,
     error: IOError,
@@ -380,19 +802,25 @@ pub struct SpawnError {
 impl Command {
 
     fn spawn_base(mut self, output_type: ProcessOutputType) -> Result<Process, SpawnError> {
+        if process_command::is_dry_run() {
+            println!("{self}");
+            return Ok(Process::dry_run());
+        }
         log::debug!("spawning process: {self}");
+        log_file::write(&format!("$ {self}\n"));
         let stdin_stdio = if self.has_stdin_input() { process::Stdio::piped() } else { process::Stdio::null() };
         let (stdout_stdio, stderr_stdio) = match output_type {
             ProcessOutputType::Inherited => (process::Stdio::inherit(), process::Stdio::inherit()),
             ProcessOutputType::Progress {..} | ProcessOutputType::None =>
                 (process::Stdio::null(), process::Stdio::piped()),
+            ProcessOutputType::PipedStdout => (process::Stdio::piped(), process::Stdio::piped()),
         };
         let mut process_handle = self.command
             .stdin(stdin_stdio).stdout(stdout_stdio).stderr(stderr_stdio)
             .spawn()
             .map_err(|error| SpawnError { error, bin_path: self.command.get_program().to_string_lossy().to_string() })?;
         let process_stdin = if self.has_stdin_input() { process_handle.stdin.take() } else { None };
-        Ok(Process::new(process_handle, process_stdin, output_type))
+        Ok(Process::new(process_handle, process_stdin, output_type, self.progress_callback, self.output_files, ! self.remote))
     }
 
     pub fn spawn(self) -> Result<Process, SpawnError> {
@@ -403,11 +831,17 @@ impl Command {
         self.spawn_base(ProcessOutputType::None)
     }
 
+    /// Spawns the process with its standard output piped, to be taken with [`Process::take_stdout`]
+    pub fn spawn_piped_output(self) -> Result<Process, SpawnError> {
+        self.spawn_base(ProcessOutputType::PipedStdout)
+    }
+
+    /// Spawns the process displaying a progress bar with the ETA and the encoding speed, `frame_count` being the number of frames to encode
     pub fn spawn_with_progress(self, frame_count: u64) -> Result<Process, SpawnError> {
         let output_type = if frame_count == 0 {
             ProcessOutputType::None
         } else {
-            ProcessOutputType::Progress { frame_count }
+            ProcessOutputType::Progress { frame_count, pass: self.pass }
         };
         self.spawn_base(output_type)
     }
@@ -417,8 +851,11 @@ impl Command {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ProcessOutputType {
     Inherited,
-    Progress { frame_count: u64 },
+    /// when `pass` is set the progress is displayed across all the passes of a two-pass encode
+    Progress { frame_count: u64, pass: Option<u8> },
     None,
+    /// the output file is `pipe:1` and is read by another process
+    PipedStdout,
 }
 
 impl Display for Command {
@@ -432,10 +869,16 @@ impl Display for Command {
 pub struct ProcessError {
     exit_status: process::ExitStatus,
     stderr_content: Option<String>,
+    /// error renaming a temporary output file to its final path once the process exited successfully
+    #[source]
+    output_rename_error: Option<IOError>,
 }
 
 impl Display for ProcessError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(output_rename_error) = &self.output_rename_error {
+            return write!(f, "failed renaming the temporary output file to its final path: {output_rename_error}");
+        }
         write!(f, "ffmpeg process exited with an error: {}", self.exit_status)?;
         if let Some(stderr_content) = &self.stderr_content {
             f.write_str("\n\nFFMpeg last lines:\n\n")?;
@@ -446,34 +889,101 @@ impl Display for ProcessError {
 }
 
 pub struct Process {
-    handle: process::Child,
+    // none in dry-run mode
+    handle: Option<SharedChild>,
     monitor_handle: Option<JoinHandle<Vec<String>>>,
     stdin: Option<process::ChildStdin>,
+    child_guard: Option<ChildGuard>,
+    output_files: Vec<OutputFile>,
+    // number of frames encoded when known, reported in the summary of the command
+    frame_count: Option<u64>,
 }
 
 impl Process {
 
-    fn new(mut handle: process::Child, stdin: Option<process::ChildStdin>, output_type: ProcessOutputType) -> Self {
+    fn new(mut handle: process::Child, stdin: Option<process::ChildStdin>, output_type: ProcessOutputType, progress_callback: Option<ProgressCallback>,
+            output_files: Vec<OutputFile>, throttled: bool) -> Self {
         let monitor_handle = match output_type {
             ProcessOutputType::Inherited => None,
-            ProcessOutputType::Progress { frame_count } =>
-                Some(tokio::spawn(Self::monitor(handle.stderr.take().unwrap(), Some(frame_count)))),
-            ProcessOutputType::None =>
-                Some(tokio::spawn(Self::monitor(handle.stderr.take().unwrap(), None))),
+            ProcessOutputType::Progress { frame_count, pass } =>
+                Some(tokio::spawn(Self::monitor(handle.stderr.take().unwrap(), Some(frame_count), pass, progress_callback))),
+            ProcessOutputType::None | ProcessOutputType::PipedStdout =>
+                Some(tokio::spawn(Self::monitor(handle.stderr.take().unwrap(), None, None, progress_callback))),
+        };
+        let child_guard = Some(cancellation::track_child(handle.id()));
+        for temp_path in output_files.iter().filter_map(|output_file| output_file.temp_path.as_ref()) {
+            cancellation::track_output(temp_path);
+        }
+        let frame_count = match output_type {
+            ProcessOutputType::Progress { frame_count, .. } => Some(frame_count),
+            _ => None,
         };
-        Process { handle, monitor_handle, stdin }
+        let handle = SharedChild::new(handle.into());
+        if throttled { throttle::track_ffmpeg(&handle) }
+        Process { handle: Some(handle), monitor_handle, stdin, child_guard, output_files, frame_count }
+    }
+
+    // process which was not spawned because of the dry-run mode, it has no stdin/stdout and exits successfully
+    fn dry_run() -> Self {
+        Process { handle: None, monitor_handle: None, stdin: None, child_guard: None, output_files: vec![], frame_count: None }
+    }
+
+    // the temporary output files are renamed to their final path on success and removed otherwise, every output is
+    // renamed even when renaming one of them failed, the temporary file left by a failed rename is removed
+    fn exited(&mut self, exit_status: process::ExitStatus) -> Result<(), ProcessError> {
+        self.child_guard = None;
+        let mut output_rename_error = None;
+        for output_file in &self.output_files {
+            if let Some(temp_path) = &output_file.temp_path {
+                cancellation::output_completed(temp_path);
+                if exit_status.success() {
+                    if let Err(error) = fs_err::rename(temp_path, &output_file.path) {
+                        let _ = fs_err::remove_file(temp_path);
+                        output_rename_error.get_or_insert(error);
+                        continue;
+                    }
+                } else {
+                    let _ = fs_err::remove_file(temp_path);
+                }
+            }
+            if exit_status.success() {
+                cancellation::output_completed(&output_file.path);
+            }
+        }
+        if let Some(error) = output_rename_error {
+            return Err(ProcessError { exit_status, stderr_content: None, output_rename_error: Some(error) });
+        }
+        if exit_status.success() {
+            // the frames are counted once when the process writes several outputs, the first pass of a two-pass encode has no output
+            for (index, output_file) in self.output_files.iter().enumerate() {
+                notification::record_output(&output_file.path, self.frame_count.filter(|_| index == 0));
+            }
+        }
+        Ok(())
     }
 
-    async fn monitor(mut ffmpeg_stderr: process::ChildStderr, frame_count: Option<u64>) -> Vec<String> {
+    async fn monitor(mut ffmpeg_stderr: process::ChildStderr, frame_count: Option<u64>, pass: Option<u8>, progress_callback: Option<ProgressCallback>) -> Vec<String> {
 
         let mut output_buf = String::new();
         let mut read_buf = [0; 1024];
-        let mut last_lines = ConstGenericRingBuffer::<_, 16>::new();
+        // more lines are kept to be reported when debugging, the ring buffer capacity needs to be a power of two
+        let last_line_count = if log::log_enabled!(log::Level::Debug) { DEBUG_REPORTED_OUTPUT_LINE_COUNT } else { REPORTED_OUTPUT_LINE_COUNT };
+        let mut last_lines = AllocRingBuffer::with_capacity(last_line_count);
+
+        // with two-pass encoding the progress bar covers both passes
+        let frame_offset = match (frame_count, pass) {
+            (Some(frame_count), Some(pass)) => frame_count * (pass as u64 - 1),
+            _ => 0,
+        };
 
         let progress_bar = frame_count.map(|frame_count| {
-            let progress_style = ProgressStyle::with_template("{wide_bar} {percent:>3}% [ETA {eta:>3}]").unwrap();
-            let progress_bar = ProgressBar::new(frame_count).with_style(progress_style);
-            progress_bar.set_position(0);
+            let (template, total_frame_count) = match pass {
+                Some(pass) => (format!("pass {pass}/{TWO_PASS_COUNT} {{wide_bar}} {{percent:>3}}% [ETA {{eta:>3}}] {{msg}}"), frame_count * TWO_PASS_COUNT as u64),
+                None => ("{wide_bar} {percent:>3}% [ETA {eta:>3}] {msg}".to_owned(), frame_count),
+            };
+            let progress_style = ProgressStyle::with_template(&template).unwrap();
+            let progress_bar = ProgressBar::new(total_frame_count).with_style(progress_style);
+            progress_bar.set_position(frame_offset);
             progress_bar
         });
 
@@ -481,21 +991,25 @@ impl Process {
 
             let read_count = ffmpeg_stderr.read(&mut read_buf).unwrap();
             if read_count == 0 { break }
-            output_buf.push_str(String::from_utf8_lossy(&read_buf[0..read_count]).to_string().as_str());
+            let output = String::from_utf8_lossy(&read_buf[0..read_count]);
+            // the progress lines end with a carriage return
+            if log_file::is_open() { log_file::write(&output.replace('\r', "\n")) }
+            output_buf.push_str(&output);
 
             let mut lines = output_buf.split_inclusive('\n').map(str::to_string);
             let last_line = lines.next_back().unwrap();
 
             let last_cr_lines = last_line.split_inclusive('\r').map(str::to_string).collect::<Vec<_>>();
 
-            if let Some(progress_bar) = &progress_bar {
-                if let Some(cr_line) = last_cr_lines.iter().rfind(|cr_pl| cr_pl.ends_with('\r')) {
-                    lazy_static! {
-                        static ref PROGRESS_RE: Regex = Regex::new(r"\Aframe=\s*(\d+)").unwrap();
+            if progress_bar.is_some() || progress_callback.is_some() {
+                let progress_update = last_cr_lines.iter().rfind(|cr_pl| cr_pl.ends_with('\r')).and_then(|cr_line| ProgressUpdate::parse(cr_line));
+                if let Some(progress_update) = progress_update {
+                    if let Some(progress_bar) = &progress_bar {
+                        progress_bar.set_position(frame_offset + progress_update.frame());
+                        progress_bar.set_message(progress_update.speed_summary());
                     }
-                    if let Some(captures) = PROGRESS_RE.captures(cr_line) {
-                        let frame: u64 = captures.get(1).unwrap().as_str().parse().unwrap();
-                        progress_bar.set_position(frame);
+                    if let Some(progress_callback) = &progress_callback {
+                        progress_callback(&progress_update);
                     }
                 }
             }
@@ -525,8 +1039,12 @@ impl Process {
         self.stdin.take()
     }
 
-    pub fn id(&self) -> u32 {
-        self.handle.id()
+    pub fn take_stdout(&mut self) -> Option<process::ChildStdout> {
+        self.handle.as_ref()?.lock().unwrap().stdout.take()
+    }
+
+    pub fn id(&self) -> Option<u32> {
+        self.handle.as_ref().map(|handle| handle.lock().unwrap().id())
     }
 
     async fn last_output_lines(&mut self) -> Option<String> {
@@ -537,38 +1055,96 @@ impl Process {
     }
 
     pub async fn try_wait(&mut self) -> Result<bool, ProcessError> {
-        match self.handle.try_wait().unwrap() {
+        let Some(handle) = &self.handle else { return Ok(true) };
+        let exit_status = throttle::try_wait(handle).unwrap();
+        if let Some(exit_status) = exit_status { self.exited(exit_status)? }
+        match exit_status {
             Some(exit_status) =>
                 if exit_status.success() {
                     Ok(true)
                 } else {
-                    Err(ProcessError { exit_status, stderr_content: self.last_output_lines().await })
+                    Err(ProcessError { exit_status, stderr_content: self.last_output_lines().await, output_rename_error: None })
                 },
             None => Ok(false),
         }
     }
 
     pub async fn wait(&mut self) -> Result<(), ProcessError> {
-        match self.handle.wait().unwrap() {
+        let Some(handle) = &self.handle else { return Ok(()) };
+        let exit_status = throttle::wait_async(handle).await.unwrap();
+        self.exited(exit_status)?;
+        match exit_status {
             exit_status if exit_status.success() => Ok(()),
-            exit_status => Err(ProcessError { exit_status, stderr_content: self.last_output_lines().await })
+            exit_status => Err(ProcessError { exit_status, stderr_content: self.last_output_lines().await, output_rename_error: None })
         }
     }
 
     pub fn kill(mut self) -> Result<(), IOError> {
-        self.handle.kill()
+        let Some(handle) = &self.handle else { return Ok(()) };
+        handle.lock().unwrap().kill()?;
+        for temp_path in self.output_files.iter().filter_map(|output_file| output_file.temp_path.as_ref()) {
+            cancellation::output_completed(temp_path);
+            let _ = fs_err::remove_file(temp_path);
+        }
+        Ok(())
     }
 
 }
 
 impl video::Region {
-    pub fn to_ffmpeg_filter_string(&self) -> String {
+    // timeline option of the filters processing the region when it has a time range
+    fn ffmpeg_enable_option(&self, input_start_secs: u32) -> String {
+        self.time_range().as_ref().and_then(|time_range| time_range.ffmpeg_enable_expression(input_start_secs))
+            .map(|expression| format!(":enable={expression}")).unwrap_or_default()
+    }
+
+    /// Options of the `delogo` filter processing the region, `input_start_secs` is the time of the input at which the filtered
+    /// stream starts, see [`crate::osd::region::TimeRange::ffmpeg_enable_expression`]
+    pub fn to_ffmpeg_filter_string(&self, input_start_secs: u32) -> String {
+        let enable = self.ffmpeg_enable_option(input_start_secs);
         format!(
-            "x={}:y={}:w={}:h={}",
+            "x={}:y={}:w={}:h={}{enable}",
             self.top_left_corner().x,
             self.top_left_corner().y,
             self.dimensions().width,
             self.dimensions().height
         )
     }
-}
\ No newline at end of file
+
+    /// Filter chain blurring the region with a gaussian blur of the specified strength
+    ///
+    /// The region is cropped from a copy of the picture, blurred then overlaid back at its position. `label` must be unique
+    /// within the filtergraph since it is used to name the pads of the chain. `input_start_secs` is the time of the input at
+    /// which the filtered stream starts.
+    pub fn to_ffmpeg_blur_filter_string(&self, label: &str, sigma: f64, input_start_secs: u32) -> String {
+        let (x, y) = (self.top_left_corner().x, self.top_left_corner().y);
+        let (width, height) = (self.dimensions().width, self.dimensions().height);
+        let enable = self.ffmpeg_enable_option(input_start_secs);
+        format!("split[{label}_main][{label}_region];[{label}_region]crop={width}:{height}:{x}:{y},gblur=sigma={sigma}[{label}_blurred];\
+            [{label}_main][{label}_blurred]overlay={x}:{y}:format=auto{enable}")
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn region_filter_strings() {
+        let region: video::Region = "0:10-0:25@600,400:200x80".parse().unwrap();
+        assert_eq!(region.to_ffmpeg_filter_string(0), "x=600:y=400:w=200:h=80:enable=gte(t\\,10)*lt(t\\,25)");
+        assert_eq!(region.to_ffmpeg_blur_filter_string("blur0", 20.0, 0),
+            "split[blur0_main][blur0_region];[blur0_region]crop=200:80:600:400,gblur=sigma=20[blur0_blurred];\
+            [blur0_main][blur0_blurred]overlay=600:400:format=auto:enable=gte(t\\,10)*lt(t\\,25)");
+        let region: video::Region = "600,400:200x80".parse().unwrap();
+        assert_eq!(region.to_ffmpeg_filter_string(5), "x=600:y=400:w=200:h=80");
+    }
+
+    #[test]
+    fn region_filter_strings_shifted_by_input_start() {
+        let region: video::Region = "0:10-0:25@600,400:200x80".parse().unwrap();
+        assert_eq!(region.to_ffmpeg_filter_string(15), "x=600:y=400:w=200:h=80:enable=gte(t\\,-5)*lt(t\\,10)");
+        let region: video::Region = "1:00-@0,0:10x10".parse().unwrap();
+        assert!(region.to_ffmpeg_blur_filter_string("blur1", 10.0, 30).ends_with(":enable=gte(t\\,30)"));
+    }
+
+}
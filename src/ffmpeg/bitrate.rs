@@ -0,0 +1,74 @@
+use std::{fmt::Display, str::FromStr};
+
+use thiserror::Error;
+
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum InvalidBitrate {
+    #[error("invalid bitrate `{0}`: expected a number of bit/s optionally followed by a k, M or G suffix, e.g. `25M` or `93k`")]
+    InvalidFormat(String),
+    #[error("invalid bitrate `{0}`: bitrate too high")]
+    TooHigh(String),
+}
+
+/// Bitrate of a stream in bit/s
+///
+/// Parsed from the FFMpeg syntax: a number optionally followed by a `k`, `M` or `G` suffix, e.g. `25M`, `1.5M`, `93k` or
+/// `4000000`. It is displayed with the largest suffix dividing it exactly so that it can be passed back to FFMpeg.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Bitrate(u64);
+
+impl Bitrate {
+
+    pub const fn from_bps(bps: u64) -> Self {
+        Self(bps)
+    }
+
+    pub const fn from_kbps(kbps: u64) -> Self {
+        Self(kbps * 1_000)
+    }
+
+    pub const fn from_mbps(mbps: u64) -> Self {
+        Self(mbps * 1_000_000)
+    }
+
+    pub const fn bps(&self) -> u64 {
+        self.0
+    }
+
+    pub const fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+
+}
+
+impl FromStr for Bitrate {
+    type Err = InvalidBitrate;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bitrate = s.trim();
+        let (value, multiplier) = match bitrate.char_indices().last() {
+            Some((index, 'k' | 'K')) => (&bitrate[..index], 1_000.0),
+            Some((index, 'M')) => (&bitrate[..index], 1_000_000.0),
+            Some((index, 'G')) => (&bitrate[..index], 1_000_000_000.0),
+            _ => (bitrate, 1.0),
+        };
+        let value = value.parse::<f64>().ok().filter(|value| value.is_finite() && *value >= 0.0)
+            .ok_or_else(|| InvalidBitrate::InvalidFormat(s.to_owned()))?;
+        let bps = (value * multiplier).round();
+        if bps > u64::MAX as f64 { return Err(InvalidBitrate::TooHigh(s.to_owned())) }
+        Ok(Self(bps as u64))
+    }
+}
+
+impl Display for Bitrate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            0 => write!(f, "0"),
+            bps if bps % 1_000_000_000 == 0 => write!(f, "{}G", bps / 1_000_000_000),
+            bps if bps % 1_000_000 == 0 => write!(f, "{}M", bps / 1_000_000),
+            bps if bps % 1_000 == 0 => write!(f, "{}k", bps / 1_000),
+            bps => write!(f, "{bps}"),
+        }
+    }
+}
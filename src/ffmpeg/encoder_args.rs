@@ -0,0 +1,143 @@
+use std::{fmt::Display, str::FromStr};
+
+use thiserror::Error;
+
+
+// options which would change the inputs, the outputs, the streams, the timing or the encoder selected by the tool
+const FORBIDDEN_OPTIONS: [&str; 30] = [
+    "i", "y", "n", "f", "map", "filter_complex", "lavfi", "filter", "vf", "af",
+    "c", "codec", "vcodec", "acodec", "scodec", "pass", "passlogfile", "frames", "vframes", "aframes",
+    "vn", "an", "sn", "dn", "ss", "t", "to", "fs", "r", "s",
+];
+
+// options which are not followed by a value, any other option takes the next argument as its value like FFMpeg does
+const VALUELESS_OPTIONS: [&str; 8] = [
+    "hide_banner", "nostats", "stats", "shortest", "copyts", "start_at_zero", "bitexact", "ignore_unknown",
+];
+
+#[derive(Debug, Error)]
+pub enum InvalidEncoderArgs {
+    #[error("invalid encoder arguments: unbalanced quote")]
+    UnbalancedQuote,
+    #[error("invalid encoder arguments: the {0} option is set by the tool and cannot be overridden")]
+    ForbiddenOption(String),
+    #[error("invalid encoder arguments: `{0}` is not the value of an option, it would be taken as an output file")]
+    PositionalArgument(String),
+    #[error("invalid encoder arguments: the {0} option is missing its value")]
+    MissingValue(String),
+}
+
+/// Encoder specific FFMpeg arguments passed through to the video encoder, e.g. `-preset slow -tune film`
+///
+/// The arguments are split on whitespace, quotes can be used for values containing spaces. Options changing the inputs,
+/// outputs, timing or codecs are rejected and every value must follow an option taking a value so that none of them can be taken
+/// as an output file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EncoderArgs(Vec<String>);
+
+impl EncoderArgs {
+
+    pub fn args(&self) -> &[String] {
+        &self.0
+    }
+
+}
+
+fn split_args(s: &str) -> Result<Vec<String>, InvalidEncoderArgs> {
+    let mut args = vec![];
+    let mut current_arg: Option<String> = None;
+    let mut quote = None;
+    for c in s.chars() {
+        match (quote, c) {
+            (Some(quote_char), c) if c == quote_char => quote = None,
+            (Some(_), c) => current_arg.get_or_insert_with(String::new).push(c),
+            (None, '\'' | '"') => { quote = Some(c); current_arg.get_or_insert_with(String::new); },
+            (None, c) if c.is_whitespace() => args.extend(current_arg.take()),
+            (None, c) => current_arg.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quote.is_some() { return Err(InvalidEncoderArgs::UnbalancedQuote) }
+    args.extend(current_arg);
+    Ok(args)
+}
+
+fn is_option(arg: &str) -> bool {
+    arg.len() > 1 && arg.starts_with('-') && ! arg[1..].starts_with(|c: char| c.is_ascii_digit() || c == '.')
+}
+
+impl FromStr for EncoderArgs {
+    type Err = InvalidEncoderArgs;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let args = split_args(s)?;
+        let mut option_expecting_value: Option<&String> = None;
+        for arg in &args {
+            if option_expecting_value.take().is_some() { continue }
+            if ! is_option(arg) { return Err(InvalidEncoderArgs::PositionalArgument(arg.clone())) }
+            // stream specifiers are ignored, e.g. -c:v is the -c option
+            let name = arg[1..].split(':').next().unwrap();
+            if FORBIDDEN_OPTIONS.contains(&name) { return Err(InvalidEncoderArgs::ForbiddenOption(arg.clone())) }
+            if ! VALUELESS_OPTIONS.contains(&name) { option_expecting_value = Some(arg) }
+        }
+        if let Some(option) = option_expecting_value { return Err(InvalidEncoderArgs::MissingValue(option.clone())) }
+        Ok(Self(args))
+    }
+}
+
+impl Display for EncoderArgs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EncoderArgs, InvalidEncoderArgs};
+
+    fn parse(s: &str) -> Result<Vec<String>, InvalidEncoderArgs> {
+        s.parse::<EncoderArgs>().map(|encoder_args| encoder_args.args().to_vec())
+    }
+
+    #[test]
+    fn options_with_values() {
+        assert_eq!(parse("-preset slow -tune film").unwrap(), ["-preset", "slow", "-tune", "film"]);
+        assert_eq!(parse("-x264-params 'keyint=60:min-keyint=60'").unwrap(), ["-x264-params", "keyint=60:min-keyint=60"]);
+        assert_eq!(parse("-crf:v -1 -qp 0").unwrap(), ["-crf:v", "-1", "-qp", "0"]);
+        assert!(parse("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn value_following_an_option_is_taken_as_is() {
+        assert_eq!(parse("-metadata -title").unwrap(), ["-metadata", "-title"]);
+    }
+
+    #[test]
+    fn valueless_options() {
+        assert_eq!(parse("-bitexact -preset slow").unwrap(), ["-bitexact", "-preset", "slow"]);
+        assert!(matches!(parse("-bitexact output.mp4"), Err(InvalidEncoderArgs::PositionalArgument(arg)) if arg == "output.mp4"));
+    }
+
+    #[test]
+    fn positional_arguments_are_rejected() {
+        assert!(matches!(parse("output.mp4"), Err(InvalidEncoderArgs::PositionalArgument(arg)) if arg == "output.mp4"));
+        assert!(matches!(parse("-preset slow output.mp4"), Err(InvalidEncoderArgs::PositionalArgument(arg)) if arg == "output.mp4"));
+    }
+
+    #[test]
+    fn missing_value_is_rejected() {
+        assert!(matches!(parse("-preset slow -tune"), Err(InvalidEncoderArgs::MissingValue(option)) if option == "-tune"));
+    }
+
+    #[test]
+    fn forbidden_options_are_rejected() {
+        for option in ["-i", "-c:v", "-vn", "-an", "-ss", "-t", "-to", "-fs", "-vframes", "-frames:v", "-r", "-s", "-scodec"] {
+            assert!(matches!(parse(&format!("{option} 1")), Err(InvalidEncoderArgs::ForbiddenOption(arg)) if arg == option), "{option}");
+        }
+    }
+
+    #[test]
+    fn unbalanced_quote_is_rejected() {
+        assert!(matches!(parse("-x264-params 'keyint=60"), Err(InvalidEncoderArgs::UnbalancedQuote)));
+    }
+
+}
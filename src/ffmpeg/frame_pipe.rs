@@ -0,0 +1,97 @@
+use std::{
+    io::{self, IoSlice, Write},
+    process::ChildStdin,
+    sync::{Arc, mpsc::{self, Receiver, SyncSender}},
+    thread::JoinHandle,
+};
+
+
+// frames queued for the writer thread, one is written while the next one is rendered
+const QUEUED_FRAME_COUNT: usize = 2;
+
+// maximum number of queued frames written with a single vectored write
+const MAX_FRAMES_PER_WRITE: usize = 4;
+
+#[derive(Debug)]
+pub struct WriterStopped;
+
+/// Streams raw frames to the stdin of an FFMpeg process from a dedicated thread
+///
+/// The frames are shared with the sender so they are written without being copied and the caller can render the
+/// next frame while the previous one is being written. The pipe is enlarged on Linux to reduce the number of context switches.
+pub struct FramePipe<T: AsRef<[u8]> + Send + Sync + 'static> {
+    sender: SyncSender<Arc<T>>,
+    writer: JoinHandle<io::Result<()>>,
+}
+
+impl<T: AsRef<[u8]> + Send + Sync + 'static> FramePipe<T> {
+
+    pub fn new(stdin: ChildStdin, frame_size: usize) -> Self {
+        enlarge_pipe(&stdin, frame_size);
+        let (sender, receiver) = mpsc::sync_channel(QUEUED_FRAME_COUNT);
+        let writer = std::thread::spawn(move || Self::writer_loop(stdin, &receiver));
+        Self { sender, writer }
+    }
+
+    /// Queues a frame, blocks while the writer thread is behind and fails if it stopped because of a write error
+    pub fn send(&self, frame: Arc<T>) -> Result<(), WriterStopped> {
+        self.sender.send(frame).map_err(|_| WriterStopped)
+    }
+
+    /// Waits for the queued frames to be written and closes the pipe
+    pub fn finish(self) -> io::Result<()> {
+        drop(self.sender);
+        self.writer.join().expect("frame pipe writer thread panicked")
+    }
+
+    fn writer_loop(mut stdin: ChildStdin, receiver: &Receiver<Arc<T>>) -> io::Result<()> {
+        let mut frames = Vec::with_capacity(MAX_FRAMES_PER_WRITE);
+        while let Ok(frame) = receiver.recv() {
+            frames.push(frame);
+            frames.extend(receiver.try_iter().take(MAX_FRAMES_PER_WRITE - 1));
+            write_all_vectored(&mut stdin, &frames.iter().map(|frame| T::as_ref(frame)).collect::<Vec<_>>())?;
+            frames.clear();
+        }
+        Ok(())
+    }
+
+}
+
+// same as the unstable `Write::write_all_vectored`
+fn write_all_vectored(writer: &mut impl Write, buffers: &[&[u8]]) -> io::Result<()> {
+    let (mut buffer_index, mut offset) = (0, 0);
+    while buffer_index < buffers.len() {
+        let slices = std::iter::once(IoSlice::new(&buffers[buffer_index][offset..]))
+            .chain(buffers[buffer_index + 1..].iter().map(|buffer| IoSlice::new(buffer)))
+            .collect::<Vec<_>>();
+        let mut written = match writer.write_vectored(&slices) {
+            Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+            Ok(written) => written,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error),
+        };
+        while buffer_index < buffers.len() && written >= buffers[buffer_index].len() - offset {
+            written -= buffers[buffer_index].len() - offset;
+            (buffer_index, offset) = (buffer_index + 1, 0);
+        }
+        offset += written;
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn enlarge_pipe(stdin: &ChildStdin, frame_size: usize) {
+    use std::os::fd::AsRawFd;
+    // the size is limited by /proc/sys/fs/pipe-max-size for unprivileged users, 1MiB by default
+    for pipe_size in [frame_size, 1 << 20] {
+        let result = unsafe { libc::fcntl(stdin.as_raw_fd(), libc::F_SETPIPE_SZ, pipe_size as libc::c_int) };
+        if result >= 0 {
+            log::debug!("FFMpeg stdin pipe size set to {result} bytes");
+            return;
+        }
+    }
+    log::debug!("failed to enlarge FFMpeg stdin pipe: {}", io::Error::last_os_error());
+}
+
+#[cfg(not(target_os = "linux"))]
+fn enlarge_pipe(_stdin: &ChildStdin, _frame_size: usize) {}
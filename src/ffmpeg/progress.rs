@@ -0,0 +1,73 @@
+use std::{sync::Arc, time::Duration};
+
+use getset::CopyGetters;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+
+/// Function called with each progress update parsed from the FFMpeg output
+pub type ProgressCallback = Arc<dyn Fn(&ProgressUpdate) + Send + Sync>;
+
+/// Progress statistics FFMpeg periodically prints while encoding
+///
+/// The fields other than the frame number are none when FFMpeg reports them as `N/A`, e.g. at the start of the encode.
+#[derive(Debug, Clone, Copy, PartialEq, CopyGetters)]
+#[getset(get_copy = "pub")]
+pub struct ProgressUpdate {
+    /// number of frames encoded so far
+    frame: u64,
+    /// encoding speed in frames per second
+    fps: Option<f64>,
+    /// bitrate of the output so far in kbit/s
+    bitrate: Option<f64>,
+    /// encoding speed relative to the playback speed
+    speed: Option<f64>,
+    /// timestamp of the output reached
+    time: Option<Duration>,
+}
+
+fn parse_time(value: &str) -> Option<Duration> {
+    let (sign, value) = match value.strip_prefix('-') {
+        Some(value) => (-1.0, value),
+        None => (1.0, value),
+    };
+    let mut parts = value.split(':');
+    let (hours, minutes, seconds) = (parts.next()?.parse::<f64>().ok()?, parts.next()?.parse::<f64>().ok()?, parts.next()?.parse::<f64>().ok()?);
+    // negative timestamps happen at the start of some encodes
+    Duration::try_from_secs_f64((sign * (hours * 3600.0 + minutes * 60.0 + seconds)).max(0.0)).ok()
+}
+
+impl ProgressUpdate {
+
+    /// Parses a progress line of the FFMpeg output, e.g.
+    /// `frame=  240 fps= 60 q=28.0 size=    1024kB time=00:00:04.00 bitrate=2097.2kbits/s speed=1.99x`
+    pub fn parse(line: &str) -> Option<Self> {
+        lazy_static! {
+            static ref FRAME_RE: Regex = Regex::new(r"\Aframe=\s*(\d+)").unwrap();
+            static ref FIELD_RE: Regex = Regex::new(r"(\w+)=\s*(\S+)").unwrap();
+        }
+        let frame = FRAME_RE.captures(line.trim_start())?.get(1).unwrap().as_str().parse().ok()?;
+        let mut update = Self { frame, fps: None, bitrate: None, speed: None, time: None };
+        for captures in FIELD_RE.captures_iter(line) {
+            let value = captures.get(2).unwrap().as_str();
+            match captures.get(1).unwrap().as_str() {
+                "fps" => update.fps = value.parse().ok(),
+                "bitrate" => update.bitrate = value.strip_suffix("kbits/s").and_then(|value| value.parse().ok()),
+                "speed" => update.speed = value.strip_suffix('x').and_then(|value| value.parse().ok()),
+                "time" => update.time = parse_time(value),
+                _ => {},
+            }
+        }
+        Some(update)
+    }
+
+    /// Short summary of the encoding speed to be displayed along with the progress
+    pub fn speed_summary(&self) -> String {
+        [
+            self.fps.map(|fps| format!("{fps:.0} fps")),
+            self.speed.map(|speed| format!("{speed:.2}x")),
+            self.bitrate.map(|bitrate| format!("{bitrate:.0} kbit/s")),
+        ].into_iter().flatten().collect::<Vec<_>>().join(" ")
+    }
+
+}
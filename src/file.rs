@@ -25,7 +25,15 @@ pub enum TouchError {
     CreateError(#[from] IOError),
 }
 
-pub fn touch<P: AsRef<Path>>(path: P) -> Result<(), TouchError> {
+/// Sets the modification time of a file to the one of another file
+pub fn copy_modification_time<P: AsRef<Path>, Q: AsRef<Path>>(source: P, destination: Q) -> Result<(), IOError> {
+    if crate::process::is_dry_run() { return Ok(()) }
+    let modification_time = filetime::FileTime::from_last_modification_time(&fs_err::metadata(source)?);
+    filetime::set_file_mtime(destination, modification_time)
+}
+
+/// Checks that a file can be created at this path without creating it, for the outputs written under a temporary name
+pub fn check_output_path<P: AsRef<Path>>(path: P) -> Result<(), TouchError> {
     let path = path.as_ref();
     let dir = path.parent().ok_or_else(|| TouchError::InvalidPath(path.to_path_buf()))?;
     if ! dir.as_os_str().is_empty() && ! dir.exists() {
@@ -34,6 +42,30 @@ pub fn touch<P: AsRef<Path>>(path: P) -> Result<(), TouchError> {
             dir_path: dir.to_path_buf()
         })
     }
-    File::create(path)?;
+    Ok(())
+}
+
+/// Path in the same directory under which an output file is written before being renamed to its final path
+///
+/// The extension is kept since FFMpeg decides the container format from it: `video.mp4` is written as `video.tmp.mp4`.
+pub fn temp_output_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    let path = path.as_ref();
+    let mut file_name = path.file_stem().unwrap_or_default().to_os_string();
+    file_name.push(".tmp");
+    if let Some(extension) = path.extension() {
+        file_name.push(".");
+        file_name.push(extension);
+    }
+    path.with_file_name(file_name)
+}
+
+pub fn touch<P: AsRef<Path>>(path: P) -> Result<(), TouchError> {
+    let path = path.as_ref();
+    check_output_path(path)?;
+    if ! crate::process::is_dry_run() {
+        File::create(path)?;
+        // the file is removed if the command is cancelled before it has been fully written
+        crate::cancellation::track_output(path);
+    }
     Ok(())
 }
\ No newline at end of file
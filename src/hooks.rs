@@ -0,0 +1,103 @@
+// Commands run before and after each job of the queue
+//
+// The hooks are shell commands, e.g. to upload the output, send a notification or move the input video to an archive directory.
+// They are run from the working directory of the job with environment variables describing the job.
+
+use std::process::{Command, ExitStatus};
+
+use getset::Getters;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::job_queue::{Job, JobStatus};
+
+
+/// Hooks of the queue jobs, read from the `hooks` key of the configuration file
+///
+/// Example:
+/// ```json
+/// { "hooks": { "post_job": "test $HD_FPV_JOB_STATUS = done && rclone copy . remote:fpv" } }
+/// ```
+#[derive(Debug, Clone, Default, Deserialize, Serialize, Getters)]
+#[serde(deny_unknown_fields)]
+#[getset(get = "pub")]
+pub struct Hooks {
+    /// run before each job, the job is not run and is marked as failed if the command fails
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pre_job: Option<String>,
+    /// run after each job whether it succeeded or not
+    #[serde(skip_serializing_if = "Option::is_none")]
+    post_job: Option<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum HookError {
+    #[error("failed running hook `{command}`: {error}")]
+    FailedRunning {
+        command: String,
+        error: std::io::Error,
+    },
+    #[error("hook `{command}` failed: {exit_status}")]
+    Failed {
+        command: String,
+        exit_status: ExitStatus,
+    },
+}
+
+impl Hooks {
+
+    pub fn new(pre_job: Option<String>, post_job: Option<String>) -> Self {
+        Self { pre_job, post_job }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pre_job.is_none() && self.post_job.is_none()
+    }
+
+    /// Hooks of `self` replaced by the ones set in `other`
+    pub fn overridden_by(&self, other: &Self) -> Self {
+        Self {
+            pre_job: other.pre_job.clone().or_else(|| self.pre_job.clone()),
+            post_job: other.post_job.clone().or_else(|| self.post_job.clone()),
+        }
+    }
+
+}
+
+fn shell_command(command: &str) -> Command {
+    #[cfg(unix)]
+    let (shell, option) = ("sh", "-c");
+    #[cfg(not(unix))]
+    let (shell, option) = ("cmd", "/C");
+    let mut shell_command = Command::new(shell);
+    shell_command.args([option, command]);
+    shell_command
+}
+
+/// Runs a hook command for a job with the environment variables describing it:
+///
+/// - `HD_FPV_JOB_ID`: id of the job in the queue
+/// - `HD_FPV_JOB_STATUS`: `running` before the job, `done` or `failed` after it
+/// - `HD_FPV_JOB_ATTEMPTS`: number of times the job has been run
+/// - `HD_FPV_JOB_WORKING_DIR`: directory the job is run from, also the working directory of the hook
+/// - `HD_FPV_JOB_COMMAND`: command line arguments of the job, space separated
+pub fn run_job_hook(command: &str, job: &Job, status: JobStatus, attempts: u32) -> Result<(), HookError> {
+    if crate::process::is_dry_run() {
+        println!("(cd {} && {command})", job.working_dir().to_string_lossy());
+        return Ok(());
+    }
+    log::debug!("running hook `{command}` for job #{}", job.id());
+    let mut shell_command = shell_command(command);
+    shell_command
+        .current_dir(job.working_dir())
+        .env("HD_FPV_JOB_ID", job.id().to_string())
+        .env("HD_FPV_JOB_STATUS", status.to_string())
+        .env("HD_FPV_JOB_ATTEMPTS", attempts.to_string())
+        .env("HD_FPV_JOB_WORKING_DIR", job.working_dir())
+        .env("HD_FPV_JOB_COMMAND", job.args().iter().join(" "));
+    let exit_status = crate::cancellation::run_tracked(&mut shell_command)
+        .map_err(|error| HookError::FailedRunning { command: command.to_owned(), error })?;
+    if ! exit_status.success() { return Err(HookError::Failed { command: command.to_owned(), exit_status }) }
+    Ok(())
+}
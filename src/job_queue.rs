@@ -0,0 +1,381 @@
+use std::{
+    fmt::Display,
+    io::{Error as IOError, ErrorKind as IOErrorKind},
+    path::{Path, PathBuf},
+    process::ExitStatus,
+    str::FromStr,
+};
+
+use fs2::FileExt;
+use getset::{CopyGetters, Getters};
+use itertools::Itertools;
+use thiserror::Error;
+
+use crate::{create_path::{create_path, CreatePathError}, hooks::{self, Hooks}, process::{self, Command}};
+
+
+const STATE_FILE_NAME: &str = "queue";
+const PAUSE_FILE_EXTENSION: &str = "pause";
+const LOCK_FILE_EXTENSION: &str = "lock";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::Display, strum::EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum JobStatus {
+    Pending,
+    Running,
+    Done,
+    Failed,
+}
+
+/// Command of the tool waiting in the queue, stored as its command line arguments with the directory it was queued from
+#[derive(Debug, Clone, Getters, CopyGetters)]
+pub struct Job {
+    #[getset(get_copy = "pub")]
+    id: u32,
+    #[getset(get_copy = "pub")]
+    status: JobStatus,
+    #[getset(get_copy = "pub")]
+    attempts: u32,
+    /// directory the relative paths of the arguments are relative to
+    #[getset(get = "pub")]
+    working_dir: PathBuf,
+    #[getset(get = "pub")]
+    args: Vec<String>,
+}
+
+impl Display for Job {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let args = self.args.iter().map(|arg| if arg.contains(' ') { format!("\"{arg}\"") } else { arg.clone() }).join(" ");
+        write!(f, "#{} [{}] {args}", self.id, self.status)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum JobQueueError {
+    #[error("failed to read queue state file {path}: {error}")]
+    ReadError {
+        path: PathBuf,
+        error: IOError,
+    },
+    #[error("failed to write queue state file {path}: {error}")]
+    WriteError {
+        path: PathBuf,
+        error: IOError,
+    },
+    #[error("failed to lock queue state file {path}: {error}")]
+    LockError {
+        path: PathBuf,
+        error: IOError,
+    },
+    #[error(transparent)]
+    CreateStateDirError(#[from] CreatePathError),
+    #[error("invalid queue state file {path}: line {line_number}")]
+    InvalidStateFile {
+        path: PathBuf,
+        line_number: usize,
+    },
+    #[error("no home directory to store the queue state file in, use --queue-file")]
+    NoHomeDir,
+    #[error("no job with id {0} in the queue")]
+    JobNotFound(u32),
+    #[error("cannot remove job {0} while it is running")]
+    JobIsRunning(u32),
+    #[error("failed to start job {id}: {error}")]
+    FailedStartingJob {
+        id: u32,
+        error: IOError,
+    },
+    #[error("{0} job(s) failed")]
+    JobsFailed(usize),
+}
+
+/// Default path of the queue state file: `$XDG_STATE_HOME/hd_fpv_video_tool/queue` or `~/.local/state/hd_fpv_video_tool/queue`
+pub fn default_state_file_path() -> Result<PathBuf, JobQueueError> {
+    let state_dir = match std::env::var_os("XDG_STATE_HOME").filter(|dir| ! dir.is_empty()) {
+        Some(state_dir) => PathBuf::from(state_dir),
+        None => home::home_dir().ok_or(JobQueueError::NoHomeDir)?.join(".local").join("state"),
+    };
+    Ok(state_dir.join(env!("CARGO_PKG_NAME")).join(STATE_FILE_NAME))
+}
+
+// the arguments are separated with tabs so tabs, new lines and backslashes are escaped
+fn escape_arg(arg: &str) -> String {
+    arg.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n")
+}
+
+fn unescape_arg(arg: &str) -> String {
+    let mut unescaped = String::with_capacity(arg.len());
+    let mut chars = arg.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('t') => unescaped.push('\t'),
+                Some('n') => unescaped.push('\n'),
+                Some(c) => unescaped.push(c),
+                None => unescaped.push('\\'),
+            },
+            c => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+fn create_state_dir(state_file_path: &Path) -> Result<(), JobQueueError> {
+    if let Some(state_dir) = state_file_path.parent().filter(|dir| ! dir.as_os_str().is_empty()) {
+        create_path(state_dir)?;
+    }
+    Ok(())
+}
+
+/// Exclusive lock of a queue state file held while the queue is read, modified and saved so that the concurrent
+/// invocations of the tool do not lose or duplicate jobs, it is released when dropped
+pub struct StateFileLock(std::fs::File);
+
+impl StateFileLock {
+
+    /// Takes the lock of the state file, waiting for the other invocations holding it to release it
+    pub fn acquire<P: AsRef<Path>>(state_file_path: P) -> Result<Self, JobQueueError> {
+        let state_file_path = state_file_path.as_ref();
+        create_state_dir(state_file_path)?;
+        let lock_file_path = state_file_path.with_extension(LOCK_FILE_EXTENSION);
+        let lock_error = |error| JobQueueError::LockError { path: lock_file_path.clone(), error };
+        let lock_file = std::fs::OpenOptions::new().create(true).write(true).open(&lock_file_path).map_err(lock_error)?;
+        lock_file.lock_exclusive().map_err(lock_error)?;
+        Ok(Self(lock_file))
+    }
+
+}
+
+impl Drop for StateFileLock {
+    fn drop(&mut self) {
+        let _ = FileExt::unlock(&self.0);
+    }
+}
+
+/// Queue of commands of the tool persisted in a state file so that long encodes can be run one after the other
+///
+/// Each line of the state file is a job: its id, status, number of attempts, working directory and its command line arguments, tab separated.
+#[derive(Debug, Clone, Getters)]
+pub struct JobQueue {
+    #[getset(get = "pub")]
+    state_file_path: PathBuf,
+    #[getset(get = "pub")]
+    jobs: Vec<Job>,
+}
+
+impl JobQueue {
+
+    /// Loads the queue from its state file, the queue is empty if the file does not exist
+    pub fn load<P: AsRef<Path>>(state_file_path: P) -> Result<Self, JobQueueError> {
+        let path = state_file_path.as_ref().to_path_buf();
+        let content = match fs_err::read_to_string(&path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == IOErrorKind::NotFound => String::new(),
+            Err(error) => return Err(JobQueueError::ReadError { path, error }),
+        };
+        let mut jobs = vec![];
+        for (line_index, line) in content.lines().enumerate().filter(|(_, line)| ! line.is_empty()) {
+            let invalid = || JobQueueError::InvalidStateFile { path: path.clone(), line_number: line_index + 1 };
+            let mut fields = line.split('\t');
+            let (Some(id), Some(status), Some(attempts), Some(working_dir)) = (fields.next(), fields.next(), fields.next(), fields.next()) else {
+                return Err(invalid())
+            };
+            jobs.push(Job {
+                id: id.parse().map_err(|_| invalid())?,
+                status: JobStatus::from_str(status).map_err(|_| invalid())?,
+                attempts: attempts.parse().map_err(|_| invalid())?,
+                working_dir: PathBuf::from(unescape_arg(working_dir)),
+                args: fields.map(unescape_arg).collect(),
+            });
+        }
+        Ok(Self { state_file_path: path, jobs })
+    }
+
+    /// Loads the queue, modifies it with `modify` and saves it while holding the state file lock, returns the result of `modify`
+    pub fn update<P: AsRef<Path>, T, F: FnOnce(&mut Self) -> Result<T, JobQueueError>>(state_file_path: P, modify: F) -> Result<T, JobQueueError> {
+        let _lock = StateFileLock::acquire(&state_file_path)?;
+        let mut queue = Self::load(state_file_path)?;
+        let result = modify(&mut queue)?;
+        queue.save()?;
+        Ok(result)
+    }
+
+    /// Writes the queue to its state file, the file is replaced atomically
+    ///
+    /// The state file lock needs to be held since the queue was loaded for the jobs modified meanwhile not to be lost,
+    /// see [`Self::update`].
+    pub fn save(&self) -> Result<(), JobQueueError> {
+        let write_error = |error| JobQueueError::WriteError { path: self.state_file_path.clone(), error };
+        create_state_dir(&self.state_file_path)?;
+        let content = self.jobs.iter().map(|job|
+            [job.id.to_string(), job.status.to_string(), job.attempts.to_string(), escape_arg(&job.working_dir.to_string_lossy())].into_iter()
+                .chain(job.args.iter().map(|arg| escape_arg(arg)))
+                .join("\t") + "\n"
+        ).collect::<String>();
+        let mut temp_file_path = self.state_file_path.clone().into_os_string();
+        temp_file_path.push(".tmp");
+        fs_err::write(&temp_file_path, content).map_err(write_error)?;
+        fs_err::rename(&temp_file_path, &self.state_file_path).map_err(write_error)
+    }
+
+    /// Adds a job with the specified command line arguments to be run from the specified directory, returns its id
+    pub fn add<P: AsRef<Path>>(&mut self, working_dir: P, args: Vec<String>) -> u32 {
+        let id = self.jobs.iter().map(Job::id).max().unwrap_or(0) + 1;
+        self.jobs.push(Job { id, status: JobStatus::Pending, attempts: 0, working_dir: working_dir.as_ref().to_path_buf(), args });
+        id
+    }
+
+    pub fn remove(&mut self, id: u32) -> Result<Job, JobQueueError> {
+        let index = self.jobs.iter().position(|job| job.id == id).ok_or(JobQueueError::JobNotFound(id))?;
+        if self.jobs[index].status == JobStatus::Running { return Err(JobQueueError::JobIsRunning(id)) }
+        Ok(self.jobs.remove(index))
+    }
+
+    /// Removes the finished jobs or all the jobs which are not running
+    pub fn clear(&mut self, all: bool) {
+        self.jobs.retain(|job| match all {
+            true => job.status == JobStatus::Running,
+            false => matches!(job.status, JobStatus::Pending | JobStatus::Running),
+        });
+    }
+
+    fn job_mut(&mut self, id: u32) -> Result<&mut Job, JobQueueError> {
+        self.jobs.iter_mut().find(|job| job.id == id).ok_or(JobQueueError::JobNotFound(id))
+    }
+
+    fn count(&self, status: JobStatus) -> usize {
+        self.jobs.iter().filter(|job| job.status == status).count()
+    }
+
+    fn pause_file_path(&self) -> PathBuf {
+        self.state_file_path.with_extension(PAUSE_FILE_EXTENSION)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.pause_file_path().exists()
+    }
+
+    /// Requests the running queue to stop once the current job is done
+    pub fn pause(&self) -> Result<(), JobQueueError> {
+        fs_err::write(self.pause_file_path(), "").map_err(|error| JobQueueError::WriteError { path: self.pause_file_path(), error })
+    }
+
+    fn clear_pause(&self) -> Result<(), JobQueueError> {
+        match fs_err::remove_file(self.pause_file_path()) {
+            Err(error) if error.kind() != IOErrorKind::NotFound => Err(JobQueueError::WriteError { path: self.pause_file_path(), error }),
+            _ => Ok(()),
+        }
+    }
+
+}
+
+fn run_job(executable: &Path, job: &Job) -> Result<ExitStatus, JobQueueError> {
+    let mut command = Command::new(executable);
+    command.args(&job.args).current_dir(&job.working_dir);
+    let child = command.spawn().map_err(|error| JobQueueError::FailedStartingJob { id: job.id, error })?;
+    let _child_guard = crate::cancellation::track_child(child.id());
+    let child = crate::throttle::SharedChild::new(child.into());
+    // pausing the queue runner pauses the job, which pauses its own processes
+    crate::throttle::track_job(&child);
+    crate::throttle::wait(&child).map_err(|error| JobQueueError::FailedStartingJob { id: job.id, error })
+}
+
+/// Runs the pending jobs of the queue one after the other with the specified executable
+///
+/// The state file is reloaded between the jobs so that jobs can be added while the queue is running, it is locked
+/// while it is updated but not while the jobs run. A failed job is retried up to `retries` times. Jobs left running by
+/// an interrupted run are run again. The queue stops after the current job when it is paused, running it again resumes
+/// it. The pre-job hook is run before each job, the job is marked as failed without being run if it fails, and the
+/// post-job hook after each job.
+pub fn run<P: AsRef<Path>, Q: AsRef<Path>>(state_file_path: P, executable: Q, retries: u32, hooks: &Hooks) -> Result<(), JobQueueError> {
+    let state_file_path = state_file_path.as_ref();
+    let executable = executable.as_ref();
+
+    // in dry-run mode the jobs are only marked as done in memory
+    let mut queue = match process::is_dry_run() {
+        true => JobQueue::load(state_file_path)?,
+        false => JobQueue::update(state_file_path, |queue| {
+            queue.clear_pause()?;
+            for job in queue.jobs.iter_mut().filter(|job| job.status == JobStatus::Running) {
+                job.status = JobStatus::Pending;
+            }
+            Ok(queue.clone())
+        })?,
+    };
+
+    let mut failed_count = 0;
+    let mut run_count = 0;
+    loop {
+        // the job is marked as running in the state file as reloaded under the lock, with the jobs added or removed meanwhile
+        let start_next_job = |queue: &mut JobQueue| -> Result<Option<(Job, usize)>, JobQueueError> {
+            let pending_count = queue.count(JobStatus::Pending);
+            let Some(job) = queue.jobs.iter_mut().find(|job| job.status == JobStatus::Pending) else { return Ok(None) };
+            job.status = JobStatus::Running;
+            Ok(Some((job.clone(), pending_count)))
+        };
+        let next_job = match process::is_dry_run() {
+            true => start_next_job(&mut queue)?,
+            false => JobQueue::update(state_file_path, start_next_job)?,
+        };
+        let Some((job, pending_count)) = next_job else { break };
+        let total_count = run_count + pending_count;
+        run_count += 1;
+
+        if process::is_dry_run() {
+            if let Some(pre_job_hook) = hooks.pre_job() { let _ = hooks::run_job_hook(pre_job_hook, &job, JobStatus::Running, job.attempts); }
+            println!("(cd {} && {} {})", job.working_dir.to_string_lossy(), executable.to_string_lossy(), job.args.join(" "));
+            if let Some(post_job_hook) = hooks.post_job() { let _ = hooks::run_job_hook(post_job_hook, &job, JobStatus::Done, job.attempts); }
+            queue.job_mut(job.id)?.status = JobStatus::Done;
+            continue;
+        }
+
+        log::info!("[{run_count}/{total_count}] running job {job}");
+
+        let mut attempts = job.attempts;
+        let pre_job_hook_result = match hooks.pre_job() {
+            Some(pre_job_hook) => hooks::run_job_hook(pre_job_hook, &job, JobStatus::Running, attempts),
+            None => Ok(()),
+        };
+        let succeeded = match pre_job_hook_result {
+            Ok(()) => loop {
+                attempts += 1;
+                let exit_status = run_job(executable, &job)?;
+                if exit_status.success() { break true }
+                log::error!("job #{} failed: {exit_status}", job.id);
+                if attempts > job.attempts + retries { break false }
+                log::info!("retrying job #{} ({} retries left)", job.id, job.attempts + retries + 1 - attempts);
+            },
+            Err(error) => {
+                log::error!("job #{} not run: {error}", job.id);
+                false
+            },
+        };
+
+        if let Some(post_job_hook) = hooks.post_job() {
+            let status = if succeeded { JobStatus::Done } else { JobStatus::Failed };
+            if let Err(error) = hooks::run_job_hook(post_job_hook, &job, status, attempts) {
+                log::warn!("post-job hook of job #{}: {error}", job.id);
+            }
+        }
+
+        // the job may have been removed while it was running
+        queue = JobQueue::update(state_file_path, |queue| {
+            if let Ok(queued_job) = queue.job_mut(job.id) {
+                queued_job.status = if succeeded { JobStatus::Done } else { JobStatus::Failed };
+                queued_job.attempts = attempts;
+            }
+            Ok(queue.clone())
+        })?;
+        if ! succeeded { failed_count += 1 }
+
+        if queue.is_paused() {
+            queue.clear_pause()?;
+            log::info!("queue paused with {} pending job(s), run the queue again to resume", queue.count(JobStatus::Pending));
+            break;
+        }
+    }
+
+    if run_count == 0 { log::info!("no pending job in the queue") }
+    if failed_count > 0 { return Err(JobQueueError::JobsFailed(failed_count)) }
+    Ok(())
+}
@@ -8,6 +8,32 @@ pub mod file;
 pub mod image;
 pub mod video;
 pub mod prelude;
+#[cfg(feature = "cli")]
 pub mod cli;
+#[cfg(feature = "overlay")]
 pub mod ffmpeg;
-pub mod process;
\ No newline at end of file
+pub mod process;
+pub mod log_file;
+#[cfg(feature = "cli")]
+pub mod config;
+#[cfg(feature = "cli")]
+pub mod removable_media;
+#[cfg(feature = "overlay")]
+pub mod disk_space;
+#[cfg(feature = "cli")]
+pub mod job_queue;
+#[cfg(feature = "cli")]
+pub mod hooks;
+#[cfg(feature = "overlay")]
+pub mod remote;
+#[cfg(feature = "cli")]
+pub mod doctor;
+pub mod cancellation;
+#[cfg(feature = "cli")]
+pub mod throttle;
+pub mod notification;
+pub mod output_path;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "python")]
+mod python;
\ No newline at end of file
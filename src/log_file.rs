@@ -0,0 +1,22 @@
+use std::{io::{Error as IOError, Write}, path::Path, sync::Mutex};
+
+
+static LOG_FILE: Mutex<Option<fs_err::File>> = Mutex::new(None);
+
+/// Opens the file receiving the logs of the tool along with the full output of the FFMpeg processes, appending to it if it exists
+pub fn open<P: AsRef<Path>>(path: P) -> Result<(), IOError> {
+    let file = fs_err::OpenOptions::new().create(true).append(true).open(path.as_ref())?;
+    *LOG_FILE.lock().unwrap() = Some(file);
+    Ok(())
+}
+
+pub fn is_open() -> bool {
+    LOG_FILE.lock().unwrap().is_some()
+}
+
+/// Writes to the log file if it is open, failing to write is ignored so that logging never makes a command fail
+pub fn write(text: &str) {
+    if let Some(file) = LOG_FILE.lock().unwrap().as_mut() {
+        let _ = file.write_all(text.as_bytes());
+    }
+}
@@ -1,8 +1,8 @@
 
-use clap::ValueEnum;
 use strum::Display;
 
-#[derive(Copy, Clone, Display, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+#[derive(Copy, Clone, Display, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
 pub enum LogLevel {
     Off,
     Error,
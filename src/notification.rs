@@ -0,0 +1,66 @@
+// Notification of the end of a command
+//
+// With `--notify` the end of a command is signaled with a terminal bell and, when the tool is built with the `notifications`
+// feature, a desktop notification. The summary gives the duration of the command, the files written by FFMpeg and the
+// average encoding speed.
+
+use std::{path::{Path, PathBuf}, sync::{atomic::{AtomicU64, Ordering}, Mutex}, time::Duration};
+
+use itertools::Itertools;
+
+
+static OUTPUT_FILES: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+static ENCODED_FRAME_COUNT: AtomicU64 = AtomicU64::new(0);
+
+/// Records an output file written successfully by FFMpeg with the number of frames encoded into it if known
+pub fn record_output<P: AsRef<Path>>(path: P, frame_count: Option<u64>) {
+    OUTPUT_FILES.lock().unwrap().push(path.as_ref().to_path_buf());
+    ENCODED_FRAME_COUNT.fetch_add(frame_count.unwrap_or(0), Ordering::Relaxed);
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..=59 => format!("{secs}s"),
+        60..=3599 => format!("{}m{:02}s", secs / 60, secs % 60),
+        _ => format!("{}h{:02}m{:02}s", secs / 3600, secs / 60 % 60, secs % 60),
+    }
+}
+
+/// Summary of the command: duration, output files and average encoding speed, `error` being the error the command failed with
+pub fn summary(duration: Duration, error: Option<&str>) -> String {
+    let mut lines = vec![match error {
+        None => format!("completed in {}", format_duration(duration)),
+        Some(error) => format!("failed after {}: {error}", format_duration(duration)),
+    }];
+    let output_files = OUTPUT_FILES.lock().unwrap();
+    if ! output_files.is_empty() {
+        lines.push(format!("output: {}", output_files.iter().map(|path| path.to_string_lossy()).join(", ")));
+    }
+    let encoded_frame_count = ENCODED_FRAME_COUNT.load(Ordering::Relaxed);
+    if encoded_frame_count > 0 && ! duration.is_zero() {
+        lines.push(format!("average speed: {:.1} fps", encoded_frame_count as f64 / duration.as_secs_f64()));
+    }
+    lines.join("\n")
+}
+
+#[cfg(feature = "notifications")]
+fn show_desktop_notification(title: &str, body: &str) {
+    let result = notify_rust::Notification::new()
+        .appname(env!("CARGO_PKG_NAME"))
+        .summary(title)
+        .body(body)
+        .show();
+    if let Err(error) = result {
+        log::warn!("failed to show the desktop notification: {error}");
+    }
+}
+
+/// Rings the terminal bell and shows a desktop notification if supported by this build, the summary is also logged
+pub fn notify(title: &str, body: &str) {
+    log::info!("{title}: {}", body.replace('\n', ", "));
+    // the bell is written to stderr to not mix it with the output of the commands printing to stdout
+    eprint!("\x07");
+    #[cfg(feature = "notifications")]
+    show_desktop_notification(title, body);
+}
@@ -3,8 +3,10 @@ pub mod file;
 pub mod font_variant;
 pub mod font_dir;
 pub mod kind;
+#[cfg(feature = "overlay")]
 pub mod overlay;
 pub mod dji;
+#[cfg(feature = "overlay")]
 pub mod tile_resize;
 pub mod tile;
 pub mod region;
@@ -12,11 +14,35 @@ pub mod coordinates;
 pub mod item;
 pub mod tile_indices;
 pub mod wsa;
+pub mod charset;
+pub mod frame_mapping;
+#[cfg(feature = "telemetry")]
+pub mod telemetry;
+pub mod frames_document;
+pub mod repair;
+pub mod concat;
+pub mod flights;
+pub mod tile_remap;
+pub mod numeric_item;
+pub mod interpolation;
+pub mod units;
+pub mod text_injection;
+pub mod custom_tiles;
+pub mod glyph_substitution;
+pub mod stats;
+pub mod font_info;
 
 use hd_fpv_osd_font_tool::dimensions::Dimensions as GenericDimensions;
 
 pub type Dimensions = GenericDimensions<u32>;
 
+/// Rate at which the OSD frames are recorded whatever the video frame rate is, the OSD frame indices are in units of its
+/// period, it is also the frame rate of the overlay videos
+pub const OSD_FRAME_RATE: u16 = 60;
+
+/// [`OSD_FRAME_RATE`] as a frequency for the time computations
+pub const OSD_TIME_BASE_HZ: f64 = OSD_FRAME_RATE as f64;
+
 pub use region::Region as Region;
 pub use coordinates::{
     Coordinate,
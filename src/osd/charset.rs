@@ -0,0 +1,55 @@
+use super::{FontVariant, TileIndices, TileIndex, Coordinate};
+
+
+impl FontVariant {
+
+    /// Returns the character represented by a tile index for this font variant if it represents one
+    ///
+    /// All the supported font variants are using the ASCII layout for the printable characters
+    pub fn tile_index_to_char(&self, tile_index: TileIndex) -> Option<char> {
+        match tile_index {
+            0x20..=0x5f => Some(tile_index as u8 as char),
+            _ => None,
+        }
+    }
+
+    /// Returns the tile index representing a character for this font variant, the reverse of [`Self::tile_index_to_char`]
+    pub fn char_to_tile_index(&self, char: char) -> Option<TileIndex> {
+        match char {
+            ' '..='_' => Some(char as TileIndex),
+            _ => None,
+        }
+    }
+
+}
+
+impl TileIndices {
+
+    /// Reads `width` tiles starting at the specified coordinates and returns the corresponding text
+    ///
+    /// Empty tiles are returned as spaces and tiles not representing a character as `?`
+    pub fn read_text(&self, font_variant: FontVariant, x: Coordinate, y: Coordinate, width: u8) -> String {
+        let grid_width = super::tile_indices::DIMENSIONS.width as Coordinate;
+        let grid_height = super::tile_indices::DIMENSIONS.height as Coordinate;
+        if y >= grid_height { return String::new() }
+        (x..x.saturating_add(width).min(grid_width)).map(|x| {
+            match self[(x, y)] {
+                0 => ' ',
+                tile_index => font_variant.tile_index_to_char(tile_index).unwrap_or('?'),
+            }
+        }).collect()
+    }
+
+    /// Writes text starting at the specified coordinates, the characters which have no tile or do not fit the grid are skipped
+    pub fn write_text(&mut self, font_variant: FontVariant, x: Coordinate, y: Coordinate, text: &str) {
+        let grid_width = super::tile_indices::DIMENSIONS.width as Coordinate;
+        let grid_height = super::tile_indices::DIMENSIONS.height as Coordinate;
+        if y >= grid_height { return }
+        for (x, char) in (x..grid_width).zip(text.chars()) {
+            if let Some(tile_index) = font_variant.char_to_tile_index(char) {
+                self.set_tile_index_at(x, y, tile_index);
+            }
+        }
+    }
+
+}
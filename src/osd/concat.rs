@@ -0,0 +1,168 @@
+use std::{io::Error as IOError, path::{Path, PathBuf}};
+
+use derive_more::From;
+use thiserror::Error;
+
+use super::{
+    dji::file::{WriteError as DJIWriteError, Writer as DJIWriter},
+    file::{self, Frame, GenericReader, OpenError, ReadError, SortedUniqFrames},
+    wsa::file::Writer as WSAWriter,
+    Kind, OSD_TIME_BASE_HZ,
+};
+
+
+#[derive(Debug, Error, From)]
+pub enum ConcatOSDFramesError {
+    #[error("no OSD frames to concatenate")]
+    NoSegment,
+    #[error("the OSD files do not have the same tile layout: {0} and {1}")]
+    #[from(ignore)]
+    MismatchedOSDKind(Kind, Kind),
+    #[error("failed to write OSD file: {0}")]
+    WriteError(DJIWriteError),
+    #[error("failed to write WSA OSD file: {0}")]
+    WSAWriteError(IOError),
+}
+
+#[derive(Debug, Error, From)]
+pub enum MergeOSDFilesError {
+    #[error(transparent)]
+    OpenError(OpenError),
+    #[error("OSD file read error: {0}")]
+    OSDFileReadError(ReadError),
+    #[error(transparent)]
+    ConcatOSDFramesError(ConcatOSDFramesError),
+    #[error("the durations of the {0} first videos are needed to merge {1} OSD files, got {2}")]
+    #[from(ignore)]
+    MissingDurations(usize, usize, usize),
+    #[error("output OSD file exists: {0}")]
+    OutputFileExists(PathBuf),
+}
+
+/// Index of the OSD frame at which each recording starts when the videos of the recordings are concatenated, from the durations
+/// of the videos in seconds, the duration of the last video is not needed
+pub fn segment_start_frames(video_durations: &[f64], segment_count: usize) -> Vec<u32> {
+    // the OSD frames are numbered at 60Hz whatever the frame rate of the video is
+    let mut start_time = 0.0;
+    let mut start_frames = vec![];
+    for video_duration in video_durations.iter().chain(std::iter::repeat(&0.0)).take(segment_count) {
+        start_frames.push((start_time * OSD_TIME_BASE_HZ).round() as u32);
+        start_time += video_duration;
+    }
+    start_frames
+}
+
+/// Concatenates the frames of consecutive OSD recordings, the frame indices of each recording are rebased by the index
+/// of the video frame at which it starts in the concatenated video, e.g. the frame count of the preceding videos
+///
+/// The recordings restart their frame indices from zero. The frames of a recording overlapping the end of the previous
+/// one are dropped so that the indices keep increasing. The font variant is the one of the first recording.
+pub fn concat_frames(segments: &[SortedUniqFrames], segment_start_frames: &[u32]) -> Result<SortedUniqFrames, ConcatOSDFramesError> {
+    let first_segment = segments.first().ok_or(ConcatOSDFramesError::NoSegment)?;
+    let mut frames: Vec<Frame> = Vec::with_capacity(segments.iter().map(|segment| segment.len()).sum());
+    for (segment, start_frame) in segments.iter().zip(segment_start_frames) {
+        if segment.kind() != first_segment.kind() {
+            return Err(ConcatOSDFramesError::MismatchedOSDKind(first_segment.kind(), segment.kind()));
+        }
+        let last_frame_index = frames.last().map(Frame::index);
+        let rebased_frames = segment.iter()
+            .map(|frame| Frame::new(frame.index() + start_frame, frame.tile_indices().clone()))
+            .filter(|frame| ! matches!(last_frame_index, Some(last_frame_index) if frame.index() <= last_frame_index));
+        frames.extend(rebased_frames);
+    }
+    Ok(SortedUniqFrames::new(first_segment.kind(), first_segment.font_variant(), frames))
+}
+
+/// Writes OSD frames to an OSD file of the format they were read from: a WSA OSD file for the WSA frames, a DJI OSD file otherwise
+pub fn write_osd_file<P: AsRef<Path>>(path: P, frames: &SortedUniqFrames) -> Result<(), ConcatOSDFramesError> {
+    if matches!(frames.kind(), Kind::WSA) {
+        let mut writer = WSAWriter::create(path, frames.font_variant())?;
+        for frame in frames.iter() {
+            writer.write_frame(frame)?;
+        }
+        writer.finish()?;
+        return Ok(());
+    }
+    let mut writer = DJIWriter::create(path, frames.kind(), frames.font_variant())?;
+    for frame in frames.iter() {
+        writer.write_frame(frame)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+/// Merges the OSD files of consecutive recordings into one OSD file matching the concatenation of their videos
+///
+/// `video_durations` are the durations in seconds of the videos recorded with the OSD files, in the same order.
+pub fn merge_osd_files<P: AsRef<Path>, Q: AsRef<Path>>(osd_files: &[P], video_durations: &[f64], output_file: Q, overwrite: bool)
+        -> Result<(), MergeOSDFilesError> {
+    let output_file = output_file.as_ref();
+    if ! overwrite && output_file.exists() { return Err(MergeOSDFilesError::OutputFileExists(output_file.to_path_buf())) }
+    let needed_duration_count = osd_files.len().saturating_sub(1);
+    if video_durations.len() < needed_duration_count {
+        return Err(MergeOSDFilesError::MissingDurations(needed_duration_count, osd_files.len(), video_durations.len()));
+    }
+
+    let segments = osd_files.iter().map(|osd_file| Ok(file::open(osd_file)?.frames()?)).collect::<Result<Vec<_>, MergeOSDFilesError>>()?;
+    let start_frames = segment_start_frames(video_durations, segments.len());
+    for (osd_file, start_frame) in osd_files.iter().zip(&start_frames) {
+        log::info!("{}: frames rebased by {start_frame}", osd_file.as_ref().to_string_lossy());
+    }
+    let frames = concat_frames(&segments, &start_frames)?;
+    if crate::process::is_dry_run() {
+        println!("merged OSD file: {}", output_file.to_string_lossy());
+        return Ok(());
+    }
+    write_osd_file(output_file, &frames)?;
+
+    log::info!("wrote {} OSD frames to {}", frames.len(), output_file.to_string_lossy());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::osd::{tile_indices, FontVariant, TileIndices};
+
+    use super::*;
+
+    fn segment(kind: Kind, frame_indices: &[u32]) -> SortedUniqFrames {
+        let frames = frame_indices.iter().map(|index| Frame::new(*index, TileIndices::new(vec![*index as u16; tile_indices::COUNT]))).collect();
+        SortedUniqFrames::new(kind, FontVariant::INAV, frames)
+    }
+
+    fn frame_indices(frames: &SortedUniqFrames) -> Vec<u32> {
+        frames.iter().map(Frame::index).collect()
+    }
+
+    #[test]
+    fn segments_start_at_the_sum_of_the_preceding_durations() {
+        assert_eq!(segment_start_frames(&[10.0, 2.5, 1.0], 3), [0, 600, 750]);
+        assert_eq!(segment_start_frames(&[10.0], 2), [0, 600]);
+        assert_eq!(segment_start_frames(&[], 1), [0]);
+        assert_eq!(segment_start_frames(&[0.1, 0.1], 3), [0, 6, 12]);
+    }
+
+    #[test]
+    fn concatenated_frames_are_rebased() {
+        let frames = concat_frames(&[segment(Kind::DJI_HD, &[0, 10, 20]), segment(Kind::DJI_HD, &[0, 5])], &[0, 30]).unwrap();
+        assert_eq!(frame_indices(&frames), [0, 10, 20, 30, 35]);
+        assert_eq!(frames.kind(), Kind::DJI_HD);
+        assert_eq!(frames.font_variant(), FontVariant::INAV);
+        // the tile indices are kept
+        assert_eq!(frames.last().unwrap().tile_indices().tile_index_at(0, 0), 5);
+    }
+
+    #[test]
+    fn overlapping_frames_are_dropped() {
+        let frames = concat_frames(&[segment(Kind::WSA, &[0, 10, 20]), segment(Kind::WSA, &[0, 5, 10])], &[0, 15]).unwrap();
+        assert_eq!(frame_indices(&frames), [0, 10, 20, 25]);
+    }
+
+    #[test]
+    fn segments_of_different_kinds_are_rejected() {
+        let result = concat_frames(&[segment(Kind::DJI_HD, &[0]), segment(Kind::WSA, &[0])], &[0, 60]);
+        assert!(matches!(result, Err(ConcatOSDFramesError::MismatchedOSDKind(..))));
+        assert!(matches!(concat_frames(&[], &[]), Err(ConcatOSDFramesError::NoSegment)));
+    }
+
+}
@@ -0,0 +1,98 @@
+// User supplied tiles drawn instead of the font glyphs
+//
+// The mapping file lists one PNG image per line with the tile index it is drawn for, either an index unused by the font
+// to display a logo or a custom symbol or the index of a glyph to replace. The images are resized to the tile dimensions.
+
+use std::{io::Error as IOError, path::{Path, PathBuf}};
+
+use hd_fpv_osd_font_tool::prelude::*;
+use image::imageops::FilterType;
+use thiserror::Error;
+
+use crate::image::{read_image_file, ReadError as ImageReadError};
+
+use super::{tile_indices::TileIndex, tile_remap::parse_tile_index};
+
+
+/// Name of the mapping file loaded from the font directory when no other mapping file is specified
+pub const DEFAULT_MAPPING_FILE_NAME: &str = "custom_tiles.txt";
+
+#[derive(Debug, Error)]
+pub enum CustomTilesError {
+    #[error("failed reading custom tiles mapping file {file_path}: {error}")]
+    ReadError {
+        file_path: PathBuf,
+        error: IOError,
+    },
+    #[error("{file_path}:{line_number}: invalid custom tile mapping `{line}`: the format is <tile index> <PNG file path>")]
+    InvalidMapping {
+        file_path: PathBuf,
+        line_number: usize,
+        line: String,
+    },
+    #[error(transparent)]
+    ImageReadError(#[from] ImageReadError),
+}
+
+/// PNG image drawn for an OSD tile index
+#[derive(Debug, Clone)]
+pub struct CustomTile {
+    tile_index: TileIndex,
+    image_path: PathBuf,
+}
+
+impl CustomTile {
+
+    pub fn tile_index(&self) -> TileIndex {
+        self.tile_index
+    }
+
+    pub fn image_path(&self) -> &Path {
+        &self.image_path
+    }
+
+    fn load_image(&self, width: u32, height: u32) -> Result<tile::Image, CustomTilesError> {
+        let image = read_image_file(&self.image_path)?.into_rgba8();
+        if image.dimensions() == (width, height) { return Ok(image) }
+        log::debug!("resizing custom tile {} from {}x{} to {width}x{height}", self.image_path.to_string_lossy(), image.width(), image.height());
+        Ok(image::imageops::resize(&image, width, height, FilterType::Lanczos3))
+    }
+
+}
+
+/// Reads a mapping file made of `<tile index> <PNG file path>` lines
+///
+/// The tile indices can be written in hexadecimal with the 0x prefix, relative image paths are relative to the directory of
+/// the mapping file. Empty lines and lines starting with `#` are ignored.
+pub fn read_mapping_file<P: AsRef<Path>>(file_path: P) -> Result<Vec<CustomTile>, CustomTilesError> {
+    let file_path = file_path.as_ref();
+    let content = fs_err::read_to_string(file_path)
+        .map_err(|error| CustomTilesError::ReadError { file_path: file_path.to_path_buf(), error })?;
+    let base_dir = file_path.parent().unwrap_or(Path::new(""));
+    let mut custom_tiles = vec![];
+    for (line_index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') { continue }
+        let invalid = || CustomTilesError::InvalidMapping { file_path: file_path.to_path_buf(), line_number: line_index + 1, line: line.to_owned() };
+        let (tile_index, image_path) = line.split_once(char::is_whitespace).ok_or_else(invalid)?;
+        let tile_index = parse_tile_index(tile_index).ok_or_else(invalid)?;
+        let image_path = image_path.trim();
+        custom_tiles.push(CustomTile { tile_index, image_path: base_dir.join(image_path) });
+    }
+    Ok(custom_tiles)
+}
+
+/// Replaces the tile images with the custom tiles resized to the dimensions of the tiles, the later mappings of an index apply
+pub fn apply(custom_tiles: &[CustomTile], tile_images: &mut Vec<tile::Image>) -> Result<(), CustomTilesError> {
+    if custom_tiles.is_empty() || tile_images.is_empty() { return Ok(()) }
+    let (tile_width, tile_height) = tile_images[0].dimensions();
+    for custom_tile in custom_tiles {
+        let tile_index = custom_tile.tile_index as usize;
+        if tile_images.len() <= tile_index {
+            tile_images.resize(tile_index + 1, tile::Image::new(tile_width, tile_height));
+        }
+        tile_images[tile_index] = custom_tile.load_image(tile_width, tile_height)?;
+    }
+    log::info!("loaded {} custom tiles", custom_tiles.len());
+    Ok(())
+}
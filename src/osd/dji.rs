@@ -1,6 +1,7 @@
 
 pub mod file;
 
+/// OSD frame shift of the DJI Air Unit recordings whose OSD recording started before the video recording
 pub const AU_OSD_FRAME_SHIFT: i32 = -36;
 
 pub mod dimensions {
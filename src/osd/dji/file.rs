@@ -2,8 +2,9 @@
 use std::{
     fmt::Display,
     io::{
+        BufWriter,
         Error as IOError,
-        SeekFrom, Read, Seek,
+        SeekFrom, Read, Seek, Write,
     },
     path::{
         Path,
@@ -25,13 +26,16 @@ use hd_fpv_osd_font_tool::prelude::*;
 
 use crate::{
     osd::{
-        Dimensions, FontVariant, file::{ReadError, Frame, sorted_frames::SortedUniqFrames, GenericReader}, Kind, TileIndices, tile_indices::TileIndex, kind::InvalidDimensionsError,
+        Dimensions, FontVariant, file::{ReadError, Frame, sorted_frames::SortedUniqFrames, GenericReader}, Kind, TileIndices, tile_indices::{self, TileIndex}, kind::InvalidDimensionsError,
     },
     video::FrameIndex as VideoFrameIndex,
 };
 
 const SIGNATURE: &str = "MSPOSD\x00";
-const SUPPORTED_FORMAT_VERSIONS: RangeInclusive<u16> = 1..=1;
+/// Version 2 files are read with the version 1 layout, the frame data following the tile indices is skipped
+const SUPPORTED_FORMAT_VERSIONS: RangeInclusive<u16> = 1..=2;
+/// Files are written with the first format version which is the one understood by all the tools
+const WRITTEN_FORMAT_VERSION: u16 = 1;
 
 #[derive(Debug, Error, From)]
 pub enum OpenError {
@@ -45,6 +49,19 @@ pub enum OpenError {
     UnsupportedFileFormatVersion(u16),
 }
 
+/// Errors of [`parse`], the content is not read from a file so they carry no path
+#[derive(Debug, Error)]
+pub enum ParseError {
+    #[error("invalid DJI OSD file header")]
+    InvalidSignature,
+    #[error("invalid OSD dimensions: {0}")]
+    InvalidOSDDimensions(Dimensions),
+    #[error("unsupported OSD file format version: {0}")]
+    UnsupportedFileFormatVersion(u16),
+    #[error("unexpected end of data")]
+    UnexpectedEnd,
+}
+
 impl OpenError {
 
     fn invalid_signature<P: AsRef<Path>>(file_path: P) -> Self {
@@ -111,6 +128,18 @@ impl FileHeader {
     }
 }
 
+/// ID stored in the file header for a font variant, the unknown variant is stored as the generic one
+pub fn font_variant_id(font_variant: FontVariant) -> u8 {
+    use FontVariant::*;
+    match font_variant {
+        Generic | Unknown => 0,
+        Betaflight => 1,
+        INAV => 2,
+        Ardupilot => 3,
+        KISSUltra => 4,
+    }
+}
+
 impl From<FileHeaderRaw> for FileHeader {
     fn from(fhr: FileHeaderRaw) -> Self {
         Self {
@@ -133,6 +162,52 @@ pub struct FrameHeader {
 
 const FIRST_FRAME_FILE_POS: u64 = (SIGNATURE.len() + FileHeaderRaw::BYTE_LEN) as u64;
 
+// frames with less tile indices than the largest grid are padded with empty tiles so that every coordinate can be accessed
+fn tile_indices_from_bytes(bytes: &[u8]) -> TileIndices {
+    let mut tile_indices = bytes.chunks_exact(u16::BYTE_LEN)
+        .map(|bytes| u16::from_le_bytes(bytes.try_into().unwrap())).collect::<Vec<_>>();
+    tile_indices.resize(tile_indices::COUNT, 0);
+    TileIndices::new(tile_indices)
+}
+
+fn sorted_uniq_frames(osd_kind: Kind, font_variant: FontVariant, osd_dimensions: Dimensions, frames: Vec<Frame>) -> SortedUniqFrames {
+    let frames = frames.into_iter().sorted_unstable_by_key(Frame::index).unique_by(Frame::index).collect::<Vec<Frame>>();
+    'outer: for frame in frames.iter() {
+        for (coordinates, tile_index) in frame.enumerate_tile_indices() {
+            if tile_index > 0 && (coordinates.x as u32 >= osd_dimensions.width || coordinates.y as u32 >= osd_dimensions.height) {
+                log::warn!("the OSD dimensions in the OSD file header do not seem to match the actual data in the file, the OSD might not be rendered fully");
+                break 'outer;
+            }
+        }
+    }
+    SortedUniqFrames::new(osd_kind, font_variant, frames)
+}
+
+/// Parses the content of a DJI OSD file held in memory, e.g. by the fuzz targets
+pub fn parse(bytes: &[u8]) -> Result<SortedUniqFrames, ParseError> {
+    let mut data = bytes;
+    if split_bytes(&mut data, SIGNATURE.len()).ok_or(ParseError::UnexpectedEnd)? != SIGNATURE.as_bytes() {
+        return Err(ParseError::InvalidSignature);
+    }
+    let header_raw = FileHeaderRaw::read_bytes(split_bytes(&mut data, FileHeaderRaw::BYTE_LEN).ok_or(ParseError::UnexpectedEnd)?);
+    if ! SUPPORTED_FORMAT_VERSIONS.contains(&header_raw.format_version) {
+        return Err(ParseError::UnsupportedFileFormatVersion(header_raw.format_version));
+    }
+    let header: FileHeader = header_raw.into();
+    let osd_kind = Kind::try_from(header.osd_dimensions())
+        .map_err(|InvalidDimensionsError(dimensions)| ParseError::InvalidOSDDimensions(dimensions))?;
+    let mut frames = vec![];
+    while ! data.is_empty() {
+        let frame_header = FrameHeader::read_bytes(split_bytes(&mut data, FrameHeader::BYTE_LEN).ok_or(ParseError::UnexpectedEnd)?);
+        let tile_index_count = (frame_header.data_len() as usize).min(tile_indices::COUNT);
+        let tile_indices_bytes = split_bytes(&mut data, tile_index_count * u16::BYTE_LEN).ok_or(ParseError::UnexpectedEnd)?;
+        let extension_len = (frame_header.data_len() as usize - tile_index_count).saturating_mul(u16::BYTE_LEN);
+        split_bytes(&mut data, extension_len).ok_or(ParseError::UnexpectedEnd)?;
+        frames.push(Frame::new(frame_header.frame_index(), tile_indices_from_bytes(tile_indices_bytes)));
+    }
+    Ok(sorted_uniq_frames(osd_kind, header.font_variant(), header.osd_dimensions, frames))
+}
+
 #[derive(Getters, CopyGetters)]
 pub struct Reader {
     file: File,
@@ -171,7 +246,7 @@ impl Reader {
             let InvalidDimensionsError(dimensions) = error;
             OpenError::invalid_osd_dimensions(&file_path, dimensions)
         })?;
-        log::info!("detected OSD file with {osd_kind} tile layout");
+        log::info!("detected OSD file with {osd_kind} tile layout (format version {})", header.format_version);
         Ok(Self { file, header, osd_kind })
     }
 
@@ -251,11 +326,13 @@ impl GenericReader for Reader {
             Some(header) => header,
             None => return Ok(None),
         };
-        let mut data_bytes= vec![0; header.data_len() as usize * 2];
+        // the data following the tile indices of the largest grid is an unknown per-frame extension and is skipped
+        let tile_index_count = (header.data_len() as usize).min(tile_indices::COUNT);
+        let mut data_bytes= vec![0; tile_index_count * u16::BYTE_LEN];
         self.file.read_exact(&mut data_bytes)?;
-        let tile_indices = TileIndices::new(data_bytes.chunks_exact(u16::BYTE_LEN)
-            .map(|bytes| u16::from_le_bytes(bytes.try_into().unwrap())).collect());
-        Ok(Some(Frame::new(header.frame_index(), tile_indices)))
+        let extension_len = (header.data_len() as usize - tile_index_count) * u16::BYTE_LEN;
+        if extension_len > 0 { self.file.seek(SeekFrom::Current(extension_len as i64))?; }
+        Ok(Some(Frame::new(header.frame_index(), tile_indices_from_bytes(&data_bytes))))
     }
 
     fn frames(&mut self) -> Result<SortedUniqFrames, ReadError> {
@@ -270,29 +347,20 @@ impl GenericReader for Reader {
                 Err(error) => return Err(error),
             }
         }
-        let frames = frames.into_iter().sorted_unstable_by_key(Frame::index).unique_by(Frame::index).collect::<Vec<Frame>>();
-        'outer: for frame in frames.iter() {
-            for (coordinates, tile_index) in frame.enumerate_tile_indices() {
-                if tile_index > 0 && (coordinates.x as u32 >= osd_dimensions.width || coordinates.y as u32 >= osd_dimensions.height) {
-                    log::warn!("the OSD dimensions in the OSD file header do not seem to match the actual data in the file, the OSD might not be rendered fully");
-                    break 'outer;
-                }
-            }
-        }
-        Ok(SortedUniqFrames::new(osd_kind, font_variant, frames))
+        Ok(sorted_uniq_frames(osd_kind, font_variant, osd_dimensions, frames))
     }
 
     fn last_frame_frame_index(&mut self) -> Result<u32, ReadError> {
         self.keep_position_do(|reader| {
-            Ok(reader.frames()?.last().unwrap().index())
+            Ok(reader.frames()?.last().ok_or(ReadError::NoFrames)?.index())
         })
     }
 
     fn max_used_tile_index(&mut self) -> Result<TileIndex, ReadError> {
         self.keep_position_do(|reader| {
-            Ok(*reader.frames()?.iter().flat_map(|frame|
+            reader.frames()?.iter().flat_map(|frame|
                 frame.tile_indices().as_slice()
-            ).max().unwrap())
+            ).max().copied().ok_or(ReadError::NoFrames)
         })
     }
 
@@ -345,21 +413,68 @@ impl<'a> IntoIterator for &'a mut Reader {
     }
 }
 
-pub fn find_associated_to_video_file<P: AsRef<Path>>(video_file_path: P) -> Option<PathBuf> {
-    let video_file_path = video_file_path.as_ref();
-    let file_stem = video_file_path.file_stem()?.to_string_lossy();
-    lazy_static! { static ref DJI_VIDEO_FILE_RE: Regex = Regex::new(r"\A(?:DJI(?:G|U)(\d{4}))").unwrap(); }
-
-    if let Some(captures) = DJI_VIDEO_FILE_RE.captures(&file_stem) {
-        let dji_file_number = captures.get(1).unwrap().as_str();
-        let osd_file_path = video_file_path.with_file_name(format!("DJIG{dji_file_number}")).with_extension("osd");
-        if osd_file_path.is_file() {
-            log::info!("found: {}", osd_file_path.to_string_lossy());
-            return Some(osd_file_path);
-        } else {
-            log::info!("not found: {}", osd_file_path.to_string_lossy());
+#[derive(Debug, Error, From)]
+pub enum WriteError {
+    #[error(transparent)]
+    FileError(IOError),
+    #[error("the {0} OSD layout cannot be stored in a DJI OSD file")]
+    UnsupportedOSDKind(Kind),
+}
+
+/// Writes DJI OSD files which can be read back by the `Reader` and by the other WTF.FPV OSD tools
+pub struct Writer {
+    file: BufWriter<File>,
+}
+
+impl Writer {
+
+    pub fn create<P: AsRef<Path>>(file_path: P, osd_kind: Kind, font_variant: FontVariant) -> Result<Self, WriteError> {
+        if matches!(osd_kind, Kind::WSA) { return Err(WriteError::UnsupportedOSDKind(osd_kind)) }
+        let osd_dimensions = osd_kind.dimensions_tiles();
+        let tile_dimensions = osd_kind.tile_kind().dimensions();
+        let header = FileHeaderRaw {
+            format_version: WRITTEN_FORMAT_VERSION,
+            width_tiles: osd_dimensions.width as u8,
+            height_tiles: osd_dimensions.height as u8,
+            tile_width: tile_dimensions.width as u8,
+            tile_height: tile_dimensions.height as u8,
+            x_offset: 0,
+            y_offset: 0,
+            font_variant: font_variant_id(font_variant),
+        };
+        let mut header_bytes = [0; FileHeaderRaw::BYTE_LEN];
+        header.write_bytes(&mut header_bytes);
+
+        let mut file = BufWriter::new(File::create(file_path)?);
+        file.write_all(SIGNATURE.as_bytes())?;
+        file.write_all(&header_bytes)?;
+        Ok(Self { file })
+    }
+
+    pub fn write_frame(&mut self, frame: &Frame) -> Result<(), WriteError> {
+        let header = FrameHeader { frame_index: frame.index(), data_len: frame.tile_indices().len() as u32 };
+        let mut header_bytes = [0; FrameHeader::BYTE_LEN];
+        header.write_bytes(&mut header_bytes);
+        self.file.write_all(&header_bytes)?;
+        for tile_index in frame.tile_indices().iter() {
+            self.file.write_all(&tile_index.to_le_bytes())?;
         }
+        Ok(())
     }
 
-    None
+    pub fn finish(mut self) -> Result<(), WriteError> {
+        self.file.flush()?;
+        Ok(())
+    }
+
+}
+
+/// Names of the OSD file recorded along with a video file named like the ones of the DJI goggles, e.g. `DJIG0001.osd` for
+/// `DJIU0001.mp4`, the prefix is compared case-insensitively
+pub fn associated_file_names<P: AsRef<Path>>(video_file_path: P) -> Vec<String> {
+    let Some(file_stem) = video_file_path.as_ref().file_stem() else { return vec![] };
+    let file_stem = file_stem.to_string_lossy();
+    lazy_static! { static ref DJI_VIDEO_FILE_RE: Regex = Regex::new(r"(?i)\A(?:DJI(?:G|U)(\d{4}))").unwrap(); }
+    let Some(captures) = DJI_VIDEO_FILE_RE.captures(&file_stem) else { return vec![] };
+    vec![format!("DJIG{}.osd", captures.get(1).unwrap().as_str())]
 }
\ No newline at end of file
@@ -1,10 +1,12 @@
 
 
-use std::{io::Error as IOError, path::{PathBuf, Path}};
+use std::{collections::HashMap, io::Error as IOError, path::{PathBuf, Path}, sync::atomic::{AtomicBool, AtomicU32, Ordering}};
 
 use derive_more::From;
 use thiserror::Error;
 use ambassador::{delegatable_trait, Delegate};
+use lazy_static::lazy_static;
+use regex::Regex;
 
 pub mod frame;
 pub mod sorted_frames;
@@ -13,14 +15,21 @@ pub use frame::Frame;
 
 pub use self::sorted_frames::SortedUniqFrames;
 
-use super::{tile_indices::TileIndex, FontVariant};
+use super::{tile_indices::TileIndex, FontVariant, Kind};
+
+// name of the subdirectory of the video directory in which the OSD files can be stored, compared case-insensitively
+const SIDECAR_DIR_NAME: &str = "osd";
+
+static EXTENDED_DISCOVERY: AtomicBool = AtomicBool::new(true);
 
 #[derive(Debug, Error, From)]
 pub enum ReadError {
     #[error(transparent)]
     FileError(IOError),
     #[error("Unexpected end of file: {file_path}")]
-    UnexpectedEOF { file_path: PathBuf }
+    UnexpectedEOF { file_path: PathBuf },
+    #[error("OSD file has no frames")]
+    NoFrames,
 }
 
 impl ReadError {
@@ -29,6 +38,14 @@ impl ReadError {
     }
 }
 
+/// Splits the first `len` bytes off in-memory OSD file content, none if there are not enough bytes left
+pub(crate) fn split_bytes<'a>(bytes: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    if bytes.len() < len { return None }
+    let (head, tail) = bytes.split_at(len);
+    *bytes = tail;
+    Some(head)
+}
+
 #[delegatable_trait]
 pub trait GenericReader {
     fn read_frame(&mut self) -> Result<Option<Frame>, ReadError>;
@@ -38,27 +55,98 @@ pub trait GenericReader {
     fn font_variant(&self) -> FontVariant;
 }
 
-pub fn find_associated_to_video_file<P: AsRef<Path>>(video_file_path: P) -> Option<PathBuf> {
+/// Whether the OSD file auto-detection also looks for the files with another letter case, named after the original name of
+/// a renamed copy of the video or stored in an `osd` subdirectory
+pub fn set_extended_discovery(enabled: bool) {
+    EXTENDED_DISCOVERY.store(enabled, Ordering::Relaxed);
+}
+
+// stem of the original video of a renamed copy: the ` (1)` and ` copy` suffixes are removed, a `-1` suffix is only removed when
+// the video without it exists next to the copy since it is also how recordings are commonly numbered, e.g. `flight-2.mp4`
+fn original_file_stem(video_file_path: &Path, file_stem: &str) -> Option<String> {
+    lazy_static! {
+        static ref COPY_SUFFIX_RE: Regex = Regex::new(r"(?i)(?:\s*\(\d+\)|[ _-]copy(?:\s*\d+)?)+\z").unwrap();
+        static ref NUMBERED_COPY_SUFFIX_RE: Regex = Regex::new(r"-\d+\z").unwrap();
+    }
+    let mut original_file_stem = COPY_SUFFIX_RE.replace(file_stem, "").into_owned();
+    if let Some(suffix) = NUMBERED_COPY_SUFFIX_RE.find(&original_file_stem) {
+        let unnumbered_file_stem = &original_file_stem[..suffix.start()];
+        let mut unnumbered_video_file_name = std::ffi::OsString::from(unnumbered_file_stem);
+        if let Some(extension) = video_file_path.extension() {
+            unnumbered_video_file_name.push(".");
+            unnumbered_video_file_name.push(extension);
+        }
+        if ! unnumbered_file_stem.is_empty() && video_file_path.with_file_name(unnumbered_video_file_name).is_file() {
+            original_file_stem.truncate(suffix.start());
+        }
+    }
+    (! original_file_stem.is_empty() && original_file_stem != file_stem).then_some(original_file_stem)
+}
+
+/// Names of the OSD files which can be associated to a video file, from the most to the least specific
+pub fn associated_file_names<P: AsRef<Path>>(video_file_path: P) -> Vec<String> {
     let video_file_path = video_file_path.as_ref();
-    log::info!("looking for OSD file associated to video file: {}", video_file_path.to_string_lossy());
+    let Some(file_stem) = video_file_path.file_stem() else { return vec![] };
+    let file_stem = file_stem.to_string_lossy();
+    let mut file_names = vec![format!("{file_stem}.osd")];
+    if EXTENDED_DISCOVERY.load(Ordering::Relaxed) {
+        if let Some(original_file_stem) = original_file_stem(video_file_path, &file_stem) {
+            file_names.push(format!("{original_file_stem}.osd"));
+        }
+    }
+    file_names.extend(super::dji::file::associated_file_names(video_file_path));
+    file_names.extend(super::wsa::file::associated_file_names(video_file_path));
+    file_names
+}
 
-    let osd_file_path = video_file_path.with_extension("osd");
-    if osd_file_path.is_file() {
-        log::info!("found: {}", osd_file_path.to_string_lossy());
-        return Some(osd_file_path);
-    } else {
-        log::info!("not found: {}", osd_file_path.to_string_lossy());
+// OSD files of a directory indexed by their lower case name
+fn osd_files_in_dir(dir: &Path) -> HashMap<String, PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else { return HashMap::new() };
+    entries.flatten().map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("osd")) && path.is_file())
+        .map(|path| (path.file_name().unwrap().to_string_lossy().to_lowercase(), path))
+        .collect()
+}
+
+/// Looks for the OSD file associated to a video file without logging the attempts, see [`find_associated_to_video_file`]
+pub fn locate_associated_to_video_file<P: AsRef<Path>>(video_file_path: P) -> Option<PathBuf> {
+    let video_file_path = video_file_path.as_ref();
+    let file_names = associated_file_names(video_file_path);
+    let video_dir = match video_file_path.parent() {
+        Some(dir) if ! dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    if ! EXTENDED_DISCOVERY.load(Ordering::Relaxed) {
+        return file_names.iter().map(|file_name| video_dir.join(file_name)).find(|path| path.is_file());
     }
 
-    let file_stem = video_file_path.file_stem()?.to_string_lossy();
+    let sidecar_dirs = std::fs::read_dir(video_dir).into_iter().flatten().flatten().map(|entry| entry.path())
+        .filter(|path| path.file_name().is_some_and(|name| name.eq_ignore_ascii_case(SIDECAR_DIR_NAME)) && path.is_dir());
+    for dir in std::iter::once(video_dir.to_path_buf()).chain(sidecar_dirs) {
+        let osd_files = osd_files_in_dir(&dir);
+        if let Some(path) = file_names.iter().find_map(|file_name| osd_files.get(&file_name.to_lowercase())) {
+            return Some(path.clone());
+        }
+    }
+    None
+}
 
-    if file_stem.starts_with("DJI") {
-        super::dji::file::find_associated_to_video_file(video_file_path)
-    } else if file_stem.starts_with("Avatar") {
-        super::wsa::file::find_associated_to_video_file(video_file_path)
-    } else {
-        None
+/// Looks for the OSD file recorded along with a video file
+///
+/// The file is looked for in the directory of the video under the names returned by [`associated_file_names`]: the name of
+/// the video with the .osd extension, then the names given by the DJI and Walksnail goggles. Unless the extended discovery
+/// is disabled the names are compared case-insensitively, the original name of renamed copies of the video, e.g.
+/// `DJIG0001 (1).mp4`, is also tried and the files can be stored in an `osd` subdirectory.
+pub fn find_associated_to_video_file<P: AsRef<Path>>(video_file_path: P) -> Option<PathBuf> {
+    let video_file_path = video_file_path.as_ref();
+    log::info!("looking for OSD file associated to video file: {}", video_file_path.to_string_lossy());
+    let osd_file_path = locate_associated_to_video_file(video_file_path);
+    match &osd_file_path {
+        Some(osd_file_path) => log::info!("found: {}", osd_file_path.to_string_lossy()),
+        None => log::info!("not found, tried: {}", associated_file_names(video_file_path).join(", ")),
     }
+    osd_file_path
 }
 
 #[derive(Delegate)]
@@ -68,12 +156,97 @@ pub enum Reader {
     WSA(crate::osd::wsa::file::Reader),
 }
 
+impl Reader {
+
+    /// Kind of OSD recorded in the file, read from its header
+    pub fn osd_kind(&self) -> Kind {
+        match self {
+            Reader::DJI(reader) => reader.osd_kind(),
+            Reader::WSA(_) => Kind::WSA,
+        }
+    }
+
+}
+
 #[derive(Debug, Error)]
 #[error("unrecognized OSD file: {0}")]
 pub struct UnrecognizedOSDFile(PathBuf);
 
-pub fn open(path: impl AsRef<Path>) -> Result<Reader, UnrecognizedOSDFile> {
+impl UnrecognizedOSDFile {
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+/// Path designating the standard input as OSD file
+pub const STDIN_PATH: &str = "-";
+
+#[derive(Debug, Error)]
+pub enum OpenError {
+    #[error(transparent)]
+    UnrecognizedOSDFile(#[from] UnrecognizedOSDFile),
+    #[error("failed to read OSD file from {source_name}: {error}")]
+    SourceReadError { source_name: String, error: IOError },
+    #[cfg(feature = "remote-osd")]
+    #[error("failed to download OSD file {url}: {error}")]
+    DownloadError { url: String, error: reqwest::Error },
+    #[error("cannot download OSD file {0}: this build does not support URLs, it needs the `remote-osd` feature")]
+    URLNotSupported(String),
+}
+
+fn is_url(path: &str) -> bool {
+    path.starts_with("http://") || path.starts_with("https://")
+}
+
+/// Whether the OSD file path designates the standard input or a URL instead of a local file
+pub fn is_stream_source(path: impl AsRef<Path>) -> bool {
+    let path = path.as_ref().to_string_lossy();
+    path == STDIN_PATH || is_url(&path)
+}
+
+// the readers need to seek so the content is spooled into a temporary file first
+fn spool_to_temp_file(source_name: &str, source: &mut impl std::io::Read) -> Result<PathBuf, OpenError> {
+    static SPOOL_FILE_COUNT: AtomicU32 = AtomicU32::new(0);
+    let read_error = |error| OpenError::SourceReadError { source_name: source_name.to_owned(), error };
+    let temp_file_path = std::env::temp_dir().join(format!("{}_{}_{}.osd", env!("CARGO_PKG_NAME"), std::process::id(),
+        SPOOL_FILE_COUNT.fetch_add(1, Ordering::Relaxed)));
+    let mut temp_file = fs_err::File::create(&temp_file_path).map_err(read_error)?;
+    if let Err(error) = std::io::copy(source, &mut temp_file) {
+        let _ = fs_err::remove_file(&temp_file_path);
+        return Err(read_error(error));
+    }
+    Ok(temp_file_path)
+}
+
+#[cfg(feature = "remote-osd")]
+fn download(url: &str) -> Result<PathBuf, OpenError> {
+    log::info!("downloading OSD file {url}");
+    let download_error = |error| OpenError::DownloadError { url: url.to_owned(), error };
+    let mut response = reqwest::blocking::get(url).and_then(reqwest::blocking::Response::error_for_status).map_err(download_error)?;
+    spool_to_temp_file(url, &mut response)
+}
+
+#[cfg(not(feature = "remote-osd"))]
+fn download(url: &str) -> Result<PathBuf, OpenError> {
+    Err(OpenError::URLNotSupported(url.to_owned()))
+}
+
+/// Opens an OSD file, the path can also be `-` to read it from the standard input or a http(s):// URL
+pub fn open(path: impl AsRef<Path>) -> Result<Reader, OpenError> {
     let path = path.as_ref();
+    let path_str = path.to_string_lossy();
+    let spooled_file_path = match path_str.as_ref() {
+        STDIN_PATH => spool_to_temp_file("standard input", &mut std::io::stdin().lock())?,
+        url if is_url(url) => download(url)?,
+        _ => return Ok(open_file(path)?),
+    };
+    let open_result = open_file(&spooled_file_path);
+    // the opened file stays readable once unlinked
+    let _ = fs_err::remove_file(&spooled_file_path);
+    open_result.map_err(|_| UnrecognizedOSDFile(path.to_owned()).into())
+}
+
+fn open_file(path: &Path) -> Result<Reader, UnrecognizedOSDFile> {
     if let Some(file_stem) = path.file_stem() {
         let file_stem = file_stem.to_string_lossy();
         if file_stem.starts_with("DJIG") {
@@ -96,4 +269,45 @@ pub fn open(path: impl AsRef<Path>) -> Result<Reader, UnrecognizedOSDFile> {
     }
 
     Err(UnrecognizedOSDFile(path.to_owned()))
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("hd_fpv_video_tool_{name}_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn exact_name_first() {
+        assert_eq!(associated_file_names("/videos/DJIG0001 (1).mp4").first().map(String::as_str), Some("DJIG0001 (1).osd"));
+    }
+
+    #[test]
+    fn copy_suffixes_removed() {
+        for video_file in ["/videos/DJIG0001 (1).mp4", "/videos/DJIG0001 copy.mp4", "/videos/DJIG0001_copy 2.mp4", "/videos/DJIG0001 copy (3).mp4"] {
+            assert!(associated_file_names(video_file).contains(&"DJIG0001.osd".to_owned()), "{video_file}");
+        }
+    }
+
+    #[test]
+    fn numbered_suffix_kept_without_original_video() {
+        let dir = temp_dir("numbered_suffix_kept");
+        let file_names = associated_file_names(dir.join("flight-2.mp4"));
+        assert!(file_names.contains(&"flight-2.osd".to_owned()));
+        assert!(! file_names.contains(&"flight.osd".to_owned()));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn numbered_suffix_removed_with_original_video() {
+        let dir = temp_dir("numbered_suffix_removed");
+        std::fs::write(dir.join("DJIG0001.mp4"), []).unwrap();
+        let file_names = associated_file_names(dir.join("DJIG0001-1.mp4"));
+        assert_eq!(file_names[..2], ["DJIG0001-1.osd".to_owned(), "DJIG0001.osd".to_owned()]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+}
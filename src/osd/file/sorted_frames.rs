@@ -3,10 +3,15 @@
 
 use derive_more::Deref;
 use getset::CopyGetters;
+#[cfg(feature = "overlay")]
 use rayon::{iter::{plumbing::bridge as rayon_iter_bridge, ParallelIterator, IndexedParallelIterator}};
+#[cfg(feature = "overlay")]
 use rayon::iter::plumbing::Consumer as RayonConsumer;
+#[cfg(feature = "overlay")]
 use rayon::iter::plumbing::ProducerCallback as RayonProducerCallback;
+#[cfg(feature = "overlay")]
 use rayon::iter::plumbing::Producer as RayonProducer;
+#[cfg(feature = "overlay")]
 use rayon::iter::plumbing::UnindexedConsumer as RayonUnindexedConsumer;
 use strum::EnumIter;
 
@@ -120,6 +125,7 @@ pub struct SortedUniqFrameIndices(Vec<VideoFrameIndex>);
 pub trait GetFramesExt {
     fn highest_video_frame_index(&self) -> Option<VideoFrameIndex>;
     fn highest_used_tile_index(&self) -> Option<TileIndex>;
+    fn used_tile_indices(&self) -> BTreeSet<TileIndex>;
     fn first_video_frame_index(&self, first_video_frame: u32, video_frame_shift: i32) -> Option<u32>;
     fn video_frame_indices(&self, video_frame_shift: i32) -> SortedUniqFrameIndices;
     fn shift_iter(&self, video_frame_shift: i32) -> ShiftIter;
@@ -137,6 +143,11 @@ impl<T> GetFramesExt for T where T: GetFrames {
         self.frames().iter().flat_map(|frame| frame.tile_indices().as_slice()).max().cloned()
     }
 
+    /// Tile indices used by at least one frame, empty tiles excluded
+    fn used_tile_indices(&self) -> BTreeSet<TileIndex> {
+        self.frames().iter().flat_map(|frame| frame.tile_indices().as_slice()).copied().filter(|tile_index| *tile_index != 0).collect()
+    }
+
     /// returns the video frame shifted index of the first frame which has a video frame shifted index greater than the specified first video frame
     fn first_video_frame_index(&self, first_video_frame: u32, video_frame_shift: i32) -> Option<u32> {
         let first_video_frame_index = first_video_frame as i32 - video_frame_shift;
@@ -309,6 +320,7 @@ pub struct ParallelShiftIter<'a> {
     video_frame_shift: i32,
 }
 
+#[cfg(feature = "overlay")]
 impl<'a> ParallelIterator for ParallelShiftIter<'a> {
     type Item = (u32, &'a Frame);
 
@@ -319,6 +331,7 @@ impl<'a> ParallelIterator for ParallelShiftIter<'a> {
     }
 }
 
+#[cfg(feature = "overlay")]
 impl<'a> IndexedParallelIterator for ParallelShiftIter<'a> {
     fn len(&self) -> usize {
         self.frames.len()
@@ -333,6 +346,7 @@ impl<'a> IndexedParallelIterator for ParallelShiftIter<'a> {
     }
 }
 
+#[cfg(feature = "overlay")]
 impl<'a> RayonProducer for ParallelShiftIter<'a> {
     type Item = ShiftIterItem<'a>;
 
@@ -507,6 +521,7 @@ impl<'a> ParallelVideoFramesRelIndexIter<'a> {
 
 }
 
+#[cfg(feature = "overlay")]
 impl<'a> ParallelIterator for ParallelVideoFramesRelIndexIter<'a> {
     type Item = VideoFramesRelIndexIterItem<'a>;
 
@@ -517,6 +532,7 @@ impl<'a> ParallelIterator for ParallelVideoFramesRelIndexIter<'a> {
     }
 }
 
+#[cfg(feature = "overlay")]
 impl<'a> IndexedParallelIterator for ParallelVideoFramesRelIndexIter<'a> {
     fn len(&self) -> usize {
         use EndOfFramesAction::*;
@@ -536,6 +552,7 @@ impl<'a> IndexedParallelIterator for ParallelVideoFramesRelIndexIter<'a> {
     }
 }
 
+#[cfg(feature = "overlay")]
 impl<'a> RayonProducer for ParallelVideoFramesRelIndexIter<'a> {
     type Item = VideoFramesRelIndexIterItem<'a>;
 
@@ -601,7 +618,7 @@ impl<'a> RayonProducer for ParallelVideoFramesRelIndexIter<'a> {
 
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "overlay"))]
 mod tests {
     use derive_more::Deref;
     use rayon::iter::plumbing::Producer;
@@ -0,0 +1,70 @@
+use getset::CopyGetters;
+
+use super::{FontVariant, OSD_TIME_BASE_HZ, TileIndices, file::Frame};
+
+
+// text displayed by Betaflight, INAV and Ardupilot while the craft is disarmed
+const DISARMED_TEXT: &str = "DISARMED";
+
+/// Flight detected in an OSD recording, between the OSD frames on which the craft was armed and disarmed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CopyGetters)]
+#[getset(get_copy = "pub")]
+pub struct Flight {
+    /// index of the first OSD frame on which the craft is armed
+    start_osd_frame_index: u32,
+    /// index of the OSD frame on which the craft is disarmed or of the end of the recording, exclusive
+    end_osd_frame_index: u32,
+}
+
+impl Flight {
+
+    pub fn start_secs(&self) -> f64 {
+        self.start_osd_frame_index as f64 / OSD_TIME_BASE_HZ
+    }
+
+    pub fn end_secs(&self) -> f64 {
+        self.end_osd_frame_index as f64 / OSD_TIME_BASE_HZ
+    }
+
+    pub fn duration_secs(&self) -> f64 {
+        self.end_secs() - self.start_secs()
+    }
+
+}
+
+impl TileIndices {
+
+    /// Whether the OSD shows the craft is disarmed
+    pub fn shows_disarmed(&self, font_variant: FontVariant) -> bool {
+        let dimensions = super::tile_indices::DIMENSIONS;
+        (0..dimensions.height as super::Coordinate).any(|y| {
+            self.read_text(font_variant, 0, y, dimensions.width as u8).contains(DISARMED_TEXT)
+        })
+    }
+
+}
+
+/// Detects the flights recorded in an OSD file from the frames showing the craft is disarmed
+///
+/// A frame is considered armed as long as it does not show the disarmed warning. Flights shorter than
+/// `min_duration_secs` are dropped, they are usually arming tests or the OSD briefly losing the warning.
+pub fn detect_flights(frames: &[Frame], font_variant: FontVariant, min_duration_secs: f64) -> Vec<Flight> {
+    let mut flights = vec![];
+    let mut armed_since = None;
+    for frame in frames {
+        let disarmed = frame.tile_indices().shows_disarmed(font_variant);
+        match (armed_since, disarmed) {
+            (None, false) => armed_since = Some(frame.index()),
+            (Some(start_osd_frame_index), true) => {
+                flights.push(Flight { start_osd_frame_index, end_osd_frame_index: frame.index() });
+                armed_since = None;
+            },
+            _ => {},
+        }
+    }
+    if let (Some(start_osd_frame_index), Some(last_frame)) = (armed_since, frames.last()) {
+        flights.push(Flight { start_osd_frame_index, end_osd_frame_index: last_frame.index() + 1 });
+    }
+    flights.retain(|flight| flight.duration_secs() >= min_duration_secs);
+    flights
+}
@@ -1,59 +1,174 @@
 
-use std::path::{PathBuf, Path};
+use std::{io::{Error as IOError, ErrorKind as IOErrorKind}, path::{PathBuf, Path}};
 
 use hd_fpv_osd_font_tool::prelude::*;
 
-use crate::osd::{font_variant::FontVariant, tile_indices::TileIndex};
+use crate::osd::{custom_tiles::{self, CustomTile, CustomTilesError}, font_variant::FontVariant,
+    glyph_substitution::{self, GlyphSubstitutionError, GlyphSubstitutions}, tile_indices::TileIndex, tile_remap::TileRemap};
 
 
-pub struct FontDir(PathBuf);
+/// Number of tiles of a font page, the base fonts have one page and the extended fonts two
+pub const PAGE_TILE_COUNT: usize = 256;
+
+/// Highest number of font pages an OSD can use
+pub const MAX_PAGE_COUNT: usize = 3;
+
+/// Number of font pages needed to draw the tile indices up to the specified one
+pub fn page_count(highest_used_tile_index: TileIndex) -> usize {
+    highest_used_tile_index as usize / PAGE_TILE_COUNT + 1
+}
+
+pub struct FontDir {
+    path: PathBuf,
+    fallback_variants: Vec<FontVariant>,
+    tile_remaps: Vec<TileRemap>,
+    custom_tiles_file: Option<PathBuf>,
+    glyph_substitutions_file: Option<PathBuf>,
+}
 
 impl FontDir {
 
     pub fn new<P: AsRef<Path>>(dir_path: P) -> Self {
-        Self(dir_path.as_ref().to_path_buf())
+        Self { path: dir_path.as_ref().to_path_buf(), fallback_variants: vec![FontVariant::Generic], tile_remaps: vec![], custom_tiles_file: None, glyph_substitutions_file: None }
+    }
+
+    /// Sets the remaps applied to the tiles once loaded, see [`TileRemap`]
+    pub fn set_tile_remaps(&mut self, tile_remaps: &[TileRemap]) -> &mut Self {
+        self.tile_remaps = tile_remaps.to_vec();
+        self
+    }
+
+    pub fn tile_remaps(&self) -> &[TileRemap] {
+        &self.tile_remaps
+    }
+
+    /// Sets the mapping file of the custom tiles, defaults to the `custom_tiles.txt` file of the font directory if it exists
+    pub fn set_custom_tiles_file(&mut self, custom_tiles_file: Option<PathBuf>) -> &mut Self {
+        self.custom_tiles_file = custom_tiles_file;
+        self
+    }
+
+    /// Custom tiles drawn instead of the font glyphs, see [`custom_tiles::read_mapping_file`]
+    pub fn custom_tiles(&self) -> Result<Vec<CustomTile>, CustomTilesError> {
+        let mapping_file = match &self.custom_tiles_file {
+            Some(mapping_file) => mapping_file.clone(),
+            None => {
+                let mapping_file = self.path.join(custom_tiles::DEFAULT_MAPPING_FILE_NAME);
+                if ! mapping_file.is_file() { return Ok(vec![]) }
+                mapping_file
+            },
+        };
+        log::info!("loading custom tiles mapping file {}", mapping_file.to_string_lossy());
+        custom_tiles::read_mapping_file(mapping_file)
+    }
+
+    /// Sets the glyph substitution tables file, defaults to the `glyph_substitutions.toml` file of the font directory if it exists
+    pub fn set_glyph_substitutions_file(&mut self, glyph_substitutions_file: Option<PathBuf>) -> &mut Self {
+        self.glyph_substitutions_file = glyph_substitutions_file;
+        self
+    }
+
+    /// Glyph substitution tables, see [`GlyphSubstitutions`]
+    pub fn glyph_substitutions(&self) -> Result<GlyphSubstitutions, GlyphSubstitutionError> {
+        let file_path = match &self.glyph_substitutions_file {
+            Some(file_path) => file_path.clone(),
+            None => {
+                let file_path = self.path.join(glyph_substitution::DEFAULT_FILE_NAME);
+                if ! file_path.is_file() { return Ok(GlyphSubstitutions::default()) }
+                file_path
+            },
+        };
+        log::info!("loading glyph substitutions file {}", file_path.to_string_lossy());
+        GlyphSubstitutions::load(file_path)
+    }
+
+    /// Sets the font variants whose fonts are tried in order when the requested font is missing, defaults to the generic font
+    pub fn set_fallback_variants(&mut self, variants: &[FontVariant]) -> &mut Self {
+        self.fallback_variants = variants.to_vec();
+        self
     }
 
     pub fn load(&self, tile_kind: tile::Kind, ident: &Option<&str>, max_used_tile_index: TileIndex) -> Result<Vec<Tile>, bin_file::LoadError> {
-        match max_used_tile_index {
-            max_index if max_index <= bin_file::TILE_COUNT as u16 => bin_file::load_base_norm(&self.0, tile_kind, ident),
-            _ => bin_file::load_extended_norm(&self.0, tile_kind, ident)
+        match page_count(max_used_tile_index) {
+            1 => bin_file::load_base_norm(&self.path, tile_kind, ident),
+            _ => bin_file::load_extended_norm(&self.path, tile_kind, ident)
+        }
+    }
+
+    // `font[_<ident>][_hd]_<page number>.bin` like the second page files of the extended fonts
+    fn page_file_path(&self, tile_kind: tile::Kind, ident: &Option<&str>, page_index: usize) -> PathBuf {
+        let ident_part = ident.map(|ident| format!("_{ident}")).unwrap_or_default();
+        let tile_kind_part = match tile_kind {
+            tile::Kind::SD => "",
+            tile::Kind::HD => "_hd",
+        };
+        self.path.join(format!("font{ident_part}{tile_kind_part}_{}.bin", page_index + 1))
+    }
+
+    /// Loads the images of a font page beyond the pages of the extended fonts
+    ///
+    /// The page file is searched for the requested ident then for the fallback variants, returns none if no file was found.
+    /// Like the other font files the page file contains the raw RGBA pixels of its tiles one after the other.
+    pub fn load_page_images(&self, tile_kind: tile::Kind, ident: &Option<&str>, page_index: usize) -> Result<Option<Vec<tile::Image>>, IOError> {
+        let idents = std::iter::once(*ident).chain(self.fallback_variants.iter().map(FontVariant::font_set_ident));
+        for ident in idents {
+            let file_path = self.page_file_path(tile_kind, &ident, page_index);
+            let data = match fs_err::read(&file_path) {
+                Ok(data) => data,
+                Err(error) if error.kind() == IOErrorKind::NotFound => continue,
+                Err(error) => return Err(error),
+            };
+            let tile_dimensions = tile_kind.dimensions();
+            let tile_size = tile_dimensions.width as usize * tile_dimensions.height as usize * 4;
+            if data.len() != tile_size * PAGE_TILE_COUNT {
+                return Err(IOError::new(IOErrorKind::InvalidData, format!("{}: invalid font page file size: {}", file_path.to_string_lossy(), data.len())));
+            }
+            log::info!("loading font page {} from {}", page_index + 1, file_path.to_string_lossy());
+            let images = data.chunks_exact(tile_size).map(|tile_data|
+                tile::Image::from_raw(tile_dimensions.width, tile_dimensions.height, tile_data.to_vec()).unwrap()
+            ).collect();
+            return Ok(Some(images))
+        }
+        Ok(None)
+    }
+
+    // tries loading the font with the requested ident then the fonts of the fallback variants until one is found
+    fn load_with_fallback_chain(&self, tile_kind: tile::Kind, requested: &str, ident: &Option<&str>, max_used_tile_index: TileIndex) -> Result<Vec<Tile>, bin_file::LoadError> {
+        let mut error = match self.load(tile_kind, ident, max_used_tile_index) {
+            Ok(tiles) => return Ok(tiles),
+            Err(error) if error.because_file_is_missing() => error,
+            Err(error) => return Err(error),
+        };
+        let mut tried_idents = vec![*ident];
+        for fallback_variant in &self.fallback_variants {
+            let fallback_ident = fallback_variant.font_set_ident();
+            if tried_idents.contains(&fallback_ident) { continue }
+            log::warn!("font for {requested} not found, falling back to {fallback_variant} font");
+            match self.load(tile_kind, &fallback_ident, max_used_tile_index) {
+                Ok(tiles) => return Ok(tiles),
+                Err(fallback_error) if fallback_error.because_file_is_missing() => error = fallback_error,
+                Err(fallback_error) => return Err(fallback_error),
+            }
+            tried_idents.push(fallback_ident);
         }
+        Err(error)
     }
 
     pub fn load_variant_with_fallback(&self, tile_kind: tile::Kind, variant: &FontVariant, max_used_tile_index: TileIndex) -> Result<Vec<Tile>, bin_file::LoadError> {
         let ident = variant.font_set_ident();
-        let ident_load_result = self.load(tile_kind, &ident, max_used_tile_index);
-        let tiles = match (ident, ident_load_result) {
-            (None, Ok(tiles)) | (Some(_), Ok(tiles)) => tiles,
-            (None, error @ Err(_)) => return error,
-            (Some(ident), Err(error)) => {
-                if error.because_file_is_missing() {
-                    log::warn!("font for {variant} ({ident} ident) not found, falling back to generic font");
-                    self.load(tile_kind, &None, max_used_tile_index)?
-                } else {
-                    return Err(error);
-                }
-            },
+        let requested = match ident {
+            Some(ident) => format!("{variant} ({ident} ident)"),
+            None => variant.to_string(),
         };
-        Ok(tiles)
+        self.load_with_fallback_chain(tile_kind, &requested, &ident, max_used_tile_index)
     }
 
     pub fn load_with_fallback(&self, tile_kind: tile::Kind, ident: &Option<&str>, highest_used_tile_index: TileIndex) -> Result<Vec<Tile>, bin_file::LoadError> {
-        let ident_load_result = self.load(tile_kind, ident, highest_used_tile_index);
-        let tiles = match (ident, ident_load_result) {
-            (None, Ok(tiles)) | (Some(_), Ok(tiles)) => tiles,
-            (None, error @ Err(_)) => return error,
-            (Some(ident), Err(error)) => {
-                if error.because_file_is_missing() {
-                    log::warn!("font with ident `{ident}` not found, falling back to generic font");
-                    self.load(tile_kind, &None, highest_used_tile_index)?
-                } else {
-                    return Err(error);
-                }
-            },
+        let requested = match ident {
+            Some(ident) => format!("ident `{ident}`"),
+            None => "generic ident".to_owned(),
         };
-        Ok(tiles)
+        self.load_with_fallback_chain(tile_kind, &requested, ident, highest_used_tile_index)
     }
 
-}
\ No newline at end of file
+}
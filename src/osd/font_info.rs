@@ -0,0 +1,143 @@
+// Inspection of the fonts
+//
+// Loads all the pages of a font exactly as stored in the font directory, without falling back to other fonts, and renders
+// its tiles on a contact sheet labelled with their indices to check that the installed font matches the firmware.
+
+use std::{io::Error as IOError, path::Path};
+
+use derive_more::From;
+use getset::{CopyGetters, Getters};
+use hd_fpv_osd_font_tool::prelude::*;
+use image::{Rgba, RgbaImage};
+use thiserror::Error;
+
+use crate::image::{WriteError as ImageWriteError, WriteImageFile};
+
+use super::{font_dir::{self, FontDir, MAX_PAGE_COUNT, PAGE_TILE_COUNT}, tile_indices::TileIndex};
+
+
+// number of tiles per row of the contact sheet, the tile indices of a row only differ by their last hexadecimal digit
+const CONTACT_SHEET_COLUMNS: u32 = 16;
+
+// space around the tiles of the contact sheet
+const CELL_MARGIN: u32 = 2;
+
+// the indices are written below the tiles with 3x5 pixels hexadecimal digits
+pub(crate) const DIGIT_WIDTH: u32 = 3;
+pub(crate) const DIGIT_HEIGHT: u32 = 5;
+const CAPTION_HEIGHT: u32 = DIGIT_HEIGHT + 2;
+
+// rows of the 3x5 pixels hexadecimal digits, the most significant of the 3 bits is the leftmost pixel
+const HEX_DIGITS: [[u8; DIGIT_HEIGHT as usize]; 16] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111], [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111], [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001], [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111], [0b111, 0b001, 0b001, 0b001, 0b001],
+    [0b111, 0b101, 0b111, 0b101, 0b111], [0b111, 0b101, 0b111, 0b001, 0b111],
+    [0b010, 0b101, 0b111, 0b101, 0b101], [0b110, 0b101, 0b110, 0b101, 0b110],
+    [0b011, 0b100, 0b100, 0b100, 0b011], [0b110, 0b101, 0b101, 0b101, 0b110],
+    [0b111, 0b100, 0b111, 0b100, 0b111], [0b111, 0b100, 0b111, 0b100, 0b100],
+];
+
+// mid gray so that both the white glyphs and their black outlines are visible
+const BACKGROUND_COLOR: Rgba<u8> = Rgba([96, 96, 96, 255]);
+const CAPTION_COLOR: Rgba<u8> = Rgba([255, 255, 0, 255]);
+
+#[derive(Debug, Error, From)]
+pub enum FontInfoError {
+    #[error("failed to load font file: {0}")]
+    FontLoadError(bin_file::LoadError),
+    #[error("failed to load font page: {0}")]
+    PageLoadError(IOError),
+    #[error(transparent)]
+    ContactSheetWriteError(ImageWriteError),
+}
+
+#[derive(Getters, CopyGetters)]
+pub struct FontInfo {
+    #[getset(get_copy = "pub")]
+    tile_kind: tile::Kind,
+    #[getset(get = "pub")]
+    tile_images: Vec<tile::Image>,
+}
+
+impl FontInfo {
+
+    /// Loads all the pages of the font with the specified ident, the ones of the extended fonts and the separate page files
+    pub fn load(font_dir: &FontDir, tile_kind: tile::Kind, ident: &Option<&str>) -> Result<Self, FontInfoError> {
+        // loading up to a tile of the second page loads the extended font, the base font is loaded if there is none
+        let tiles = match font_dir.load(tile_kind, ident, PAGE_TILE_COUNT as TileIndex) {
+            Ok(tiles) => tiles,
+            Err(error) if error.because_file_is_missing() => font_dir.load(tile_kind, ident, 0)?,
+            Err(error) => return Err(error.into()),
+        };
+        let mut tile_images = tiles.into_iter().map(|tile| tile.image().clone()).collect::<Vec<_>>();
+        for page_index in font_dir::page_count((tile_images.len() as TileIndex).saturating_sub(1))..MAX_PAGE_COUNT {
+            let Some(page_images) = font_dir.load_page_images(tile_kind, ident, page_index)? else { break };
+            tile_images.extend(page_images);
+        }
+        Ok(Self { tile_kind, tile_images })
+    }
+
+    pub fn tile_count(&self) -> usize {
+        self.tile_images.len()
+    }
+
+    pub fn page_count(&self) -> usize {
+        (self.tile_count() + PAGE_TILE_COUNT - 1) / PAGE_TILE_COUNT
+    }
+
+    pub fn tile_dimensions(&self) -> (u32, u32) {
+        self.tile_images.first().map_or((0, 0), |tile_image| tile_image.dimensions())
+    }
+
+    /// Number of fully transparent tiles, the tile indices without glyph
+    pub fn blank_tile_count(&self) -> usize {
+        self.tile_images.iter().filter(|tile_image| tile_image.pixels().all(|pixel| pixel[3] == 0)).count()
+    }
+
+    /// Image of all the tiles, 16 per row, each of them labelled with its hexadecimal index
+    pub fn contact_sheet(&self) -> RgbaImage {
+        let (tile_width, tile_height) = self.tile_dimensions();
+        // the 3 digits of the indices need to fit below the narrowest tiles
+        let cell_width = tile_width.max(3 * (DIGIT_WIDTH + 1)) + 2 * CELL_MARGIN;
+        let cell_height = tile_height + CAPTION_HEIGHT + 2 * CELL_MARGIN;
+        let rows = (self.tile_count() as u32 + CONTACT_SHEET_COLUMNS - 1) / CONTACT_SHEET_COLUMNS;
+        let mut sheet = RgbaImage::from_pixel(cell_width * CONTACT_SHEET_COLUMNS, cell_height * rows, BACKGROUND_COLOR);
+        for (tile_index, tile_image) in self.tile_images.iter().enumerate() {
+            let cell_x = (tile_index as u32 % CONTACT_SHEET_COLUMNS) * cell_width;
+            let cell_y = (tile_index as u32 / CONTACT_SHEET_COLUMNS) * cell_height;
+            image::imageops::overlay(&mut sheet, tile_image, (cell_x + CELL_MARGIN) as i64, (cell_y + CELL_MARGIN) as i64);
+            draw_hex_number(&mut sheet, &format!("{tile_index:03x}"), cell_x + CELL_MARGIN, cell_y + CELL_MARGIN + tile_height + 1, 1, CAPTION_COLOR);
+        }
+        sheet
+    }
+
+    pub fn write_contact_sheet<P: AsRef<Path>>(&self, path: P) -> Result<(), FontInfoError> {
+        Ok(self.contact_sheet().write_image_file(path)?)
+    }
+
+}
+
+/// Width in pixels of a number drawn with [`draw_hex_number`]
+pub(crate) fn hex_number_width(digit_count: u32, scale: u32) -> u32 {
+    (digit_count * (DIGIT_WIDTH + 1)).saturating_sub(1) * scale
+}
+
+/// Draws hexadecimal digits with the built-in 3x5 pixels font, each font pixel being a `scale`x`scale` square, decimal
+/// numbers can be drawn as well since their digits are a subset
+pub(crate) fn draw_hex_number(image: &mut RgbaImage, digits: &str, x: u32, y: u32, scale: u32, color: Rgba<u8>) {
+    for (digit_index, digit) in digits.chars().enumerate() {
+        let Some(digit) = digit.to_digit(16) else { continue };
+        let digit_x = x + digit_index as u32 * (DIGIT_WIDTH + 1) * scale;
+        for (row_index, row) in HEX_DIGITS[digit as usize].iter().enumerate() {
+            for column_index in 0..DIGIT_WIDTH {
+                if row & (1 << (DIGIT_WIDTH - 1 - column_index)) != 0 {
+                    for (dx, dy) in (0..scale).flat_map(|dx| (0..scale).map(move |dy| (dx, dy))) {
+                        image.put_pixel(digit_x + column_index * scale + dx, y + row_index as u32 * scale + dy, color);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,13 +1,15 @@
 
-use strum::{Display, EnumIter};
+use strum::{Display, EnumIter, EnumString};
 
-#[derive(Debug, Display, Clone, Copy, EnumIter, PartialEq, Eq, Hash)]
+#[derive(Debug, Display, EnumString, Clone, Copy, EnumIter, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
 pub enum FontVariant {
     Generic,
     Ardupilot,
     Betaflight,
     INAV,
     KISSUltra,
+    #[cfg_attr(feature = "cli", value(skip))]
     Unknown
 }
 
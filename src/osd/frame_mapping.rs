@@ -0,0 +1,73 @@
+use std::{io::{self, Write}, ops::Range};
+
+use getset::CopyGetters;
+use serde::Serialize;
+
+use crate::video::FrameIndex as VideoFrameIndex;
+
+use super::{OSD_TIME_BASE_HZ, file::{SortedUniqFrames, sorted_frames::GetFrames}};
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::Display)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[strum(serialize_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+/// Which OSD frame is displayed on a video frame
+#[derive(Debug, Clone, Copy, PartialEq, CopyGetters, Serialize)]
+#[getset(get_copy = "pub")]
+pub struct FrameMappingEntry {
+    video_frame_index: VideoFrameIndex,
+    /// time of the video frame in seconds from the start of the video
+    video_timestamp: f64,
+    /// index of the OSD frame in the OSD file, none before the first OSD frame
+    osd_frame_index: Option<VideoFrameIndex>,
+    /// timestamp of the displayed OSD frame in seconds from the start of the OSD recording
+    osd_timestamp: Option<f64>,
+}
+
+/// Returns which OSD frame is displayed for each video frame of the specified range
+///
+/// `osd_time_index` returns the index in the OSD time base of the time of a video frame, e.g.
+/// `VideoTimeline::osd_frame_index`, so that the mapping is the one used when burning the OSD whatever the video frame rate
+/// and OSD offset are. The displayed OSD frame is the last one recorded at or before that time.
+pub fn frame_mapping<F>(osd_file_frames: &SortedUniqFrames, video_frames: Range<VideoFrameIndex>, video_frame_rate: f64, osd_time_index: F) -> Vec<FrameMappingEntry>
+        where F: Fn(VideoFrameIndex) -> Option<VideoFrameIndex> {
+    let frames = osd_file_frames.frames();
+    video_frames.map(|video_frame_index| {
+        let osd_frame_index = osd_time_index(video_frame_index).and_then(|osd_time_index| {
+            let recorded_frame_count = frames.partition_point(|frame| frame.index() <= osd_time_index);
+            recorded_frame_count.checked_sub(1).map(|position| frames[position].index())
+        });
+        FrameMappingEntry {
+            video_frame_index,
+            video_timestamp: video_frame_index as f64 / video_frame_rate,
+            osd_frame_index,
+            osd_timestamp: osd_frame_index.map(|index| index as f64 / OSD_TIME_BASE_HZ),
+        }
+    }).collect()
+}
+
+fn format_optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+pub fn write_frame_mapping<W: Write>(writer: &mut W, entries: &[FrameMappingEntry], format: ExportFormat) -> io::Result<()> {
+    match format {
+        ExportFormat::Csv => {
+            writeln!(writer, "video_frame_index,video_timestamp,osd_frame_index,osd_timestamp")?;
+            for entry in entries {
+                writeln!(writer, "{},{:.6},{},{}", entry.video_frame_index, entry.video_timestamp, format_optional(entry.osd_frame_index),
+                    format_optional(entry.osd_timestamp.map(|timestamp| format!("{timestamp:.6}"))))?;
+            }
+        },
+        ExportFormat::Json => {
+            serde_json::to_writer_pretty(&mut *writer, entries)?;
+            writeln!(writer)?;
+        },
+    }
+    Ok(())
+}
@@ -0,0 +1,188 @@
+use std::{io::{self, Write}, path::{Path, PathBuf}};
+
+use derive_more::From;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::{
+    Dimensions, FontVariant, Kind, TileIndex, TileIndices,
+    dji::file::{WriteError as DJIWriteError, Writer as DJIWriter},
+    file::{self, Frame, GenericReader, OpenError, ReadError, SortedUniqFrames},
+    tile_indices,
+};
+
+
+/// Version of the document layout, increased on incompatible changes
+pub const DOCUMENT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::Display)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[strum(serialize_all = "lowercase")]
+pub enum ExportFormat {
+    Json,
+    Cbor,
+}
+
+/// OSD frame with its tiles as a grid of rows
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FrameDocument {
+    /// index of the video frame at which the OSD frame is displayed
+    pub index: u32,
+    /// `tiles[y][x]` is the index of the tile displayed at column `x` of row `y`, 0 for no tile
+    pub tiles: Vec<Vec<TileIndex>>,
+}
+
+/// Representation of the content of an OSD file which does not depend on the binary format of the file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FramesDocument {
+    pub version: u32,
+    /// OSD layout, one of `DJI_SD`, `DJI_FakeHD`, `DJI_HD` or `WSA`
+    pub osd_kind: String,
+    pub font_variant: String,
+    pub width_tiles: u32,
+    pub height_tiles: u32,
+    pub frames: Vec<FrameDocument>,
+}
+
+impl FramesDocument {
+
+    pub fn new(frames: &SortedUniqFrames) -> Self {
+        let dimensions = frames.kind().dimensions_tiles();
+        let frame_documents = frames.iter().map(|frame|
+            FrameDocument {
+                index: frame.index(),
+                tiles: (0..dimensions.height).map(|y|
+                    (0..dimensions.width).map(|x| frame.tile_indices().tile_index_at(x as _, y as _)).collect()
+                ).collect(),
+            }
+        ).collect();
+        Self {
+            version: DOCUMENT_VERSION,
+            osd_kind: frames.kind().to_string(),
+            font_variant: frames.font_variant().to_string(),
+            width_tiles: dimensions.width,
+            height_tiles: dimensions.height,
+            frames: frame_documents,
+        }
+    }
+
+    /// Decodes a document written as JSON or CBOR, the format is detected from the content
+    pub fn decode(bytes: &[u8]) -> Result<Self, ImportOSDFileError> {
+        let is_json = bytes.iter().find(|byte| ! byte.is_ascii_whitespace()) == Some(&b'{');
+        let document: Self = match is_json {
+            true => serde_json::from_slice(bytes).map_err(|error| ImportOSDFileError::DecodeError(error.to_string()))?,
+            false => ciborium::de::from_reader(bytes).map_err(|error| ImportOSDFileError::DecodeError(error.to_string()))?,
+        };
+        if document.version != DOCUMENT_VERSION { return Err(ImportOSDFileError::UnsupportedDocumentVersion(document.version)) }
+        Ok(document)
+    }
+
+    pub fn osd_dimensions(&self) -> Dimensions {
+        Dimensions::new(self.width_tiles, self.height_tiles)
+    }
+
+    /// Converts the frames of the document, checking that the tile grid of each frame matches the document dimensions
+    pub fn to_frames(&self) -> Result<Vec<Frame>, ImportOSDFileError> {
+        let dimensions = self.osd_dimensions();
+        self.frames.iter().map(|frame| {
+            let valid_grid = frame.tiles.len() == dimensions.height as usize
+                && frame.tiles.iter().all(|row| row.len() == dimensions.width as usize);
+            if ! valid_grid { return Err(ImportOSDFileError::InvalidFrameTileGrid { frame_index: frame.index, dimensions }) }
+            let mut tile_indices = TileIndices::new(vec![0; tile_indices::COUNT]);
+            for (y, row) in frame.tiles.iter().enumerate() {
+                for (x, tile_index) in row.iter().enumerate() {
+                    tile_indices.set_tile_index_at(x as _, y as _, *tile_index);
+                }
+            }
+            Ok(Frame::new(frame.index, tile_indices))
+        }).collect()
+    }
+
+}
+
+#[derive(Debug, Error, From)]
+pub enum ExportOSDFramesError {
+    #[error(transparent)]
+    OpenError(OpenError),
+    #[error("OSD file read error: {0}")]
+    OSDFileReadError(ReadError),
+    #[error("failed to write OSD frames: {0}")]
+    WriteError(io::Error),
+    #[error("failed to encode OSD frames: {0}")]
+    EncodeError(String),
+}
+
+pub fn write_frames_document<W: Write>(writer: &mut W, document: &FramesDocument, format: ExportFormat) -> Result<(), ExportOSDFramesError> {
+    match format {
+        ExportFormat::Json => {
+            serde_json::to_writer(&mut *writer, document).map_err(|error| ExportOSDFramesError::EncodeError(error.to_string()))?;
+            writeln!(writer)?;
+        },
+        ExportFormat::Cbor => ciborium::ser::into_writer(document, &mut *writer).map_err(|error| ExportOSDFramesError::EncodeError(error.to_string()))?,
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes the frames of an OSD file as a JSON or CBOR document so that other tools do not need to parse the binary OSD file formats
+///
+/// The document is written to stdout if no output file is specified.
+pub fn export_osd_frames<P: AsRef<Path>>(osd_file: P, format: ExportFormat, output_file: &Option<PathBuf>) -> Result<(), ExportOSDFramesError> {
+    let mut reader = file::open(osd_file)?;
+    let document = FramesDocument::new(&reader.frames()?);
+    log::info!("exporting {} OSD frames as {format}", document.frames.len());
+
+    match output_file {
+        Some(output_file) if crate::process::is_dry_run() => println!("OSD frames document file: {}", output_file.to_string_lossy()),
+        Some(output_file) => write_frames_document(&mut io::BufWriter::new(fs_err::File::create(output_file)?), &document, format)?,
+        None => write_frames_document(&mut io::stdout().lock(), &document, format)?,
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Error, From)]
+pub enum ImportOSDFileError {
+    #[error("failed to read OSD frames document: {0}")]
+    ReadError(io::Error),
+    #[error("failed to decode OSD frames document: {0}")]
+    DecodeError(String),
+    #[error("unsupported OSD frames document version: {0}")]
+    UnsupportedDocumentVersion(u32),
+    #[error("OSD dimensions not supported by the DJI OSD file format: {0}")]
+    UnsupportedOSDDimensions(Dimensions),
+    #[error("unknown font variant: {0}")]
+    UnknownFontVariant(String),
+    #[error("tile grid of OSD frame {frame_index} does not match the OSD dimensions {dimensions}")]
+    InvalidFrameTileGrid { frame_index: u32, dimensions: Dimensions },
+    #[error("output OSD file exists: {0}")]
+    OutputFileExists(PathBuf),
+    #[error("failed to write OSD file: {0}")]
+    WriteError(DJIWriteError),
+}
+
+/// Writes a DJI OSD file from a JSON or CBOR document in the format written by `export_osd_frames`
+///
+/// This allows editing the content of an OSD file, e.g. to hide coordinates or fix corrupt frames, with any tool.
+pub fn import_osd_file<P: AsRef<Path>, Q: AsRef<Path>>(document_file: P, osd_file: Q, overwrite: bool) -> Result<(), ImportOSDFileError> {
+    let osd_file = osd_file.as_ref();
+    if ! overwrite && osd_file.exists() { return Err(ImportOSDFileError::OutputFileExists(osd_file.to_path_buf())) }
+
+    let document = FramesDocument::decode(&fs_err::read(document_file)?)?;
+    let osd_kind = Kind::try_from(&document.osd_dimensions()).map_err(|_| ImportOSDFileError::UnsupportedOSDDimensions(document.osd_dimensions()))?;
+    let font_variant = document.font_variant.parse::<FontVariant>().map_err(|_| ImportOSDFileError::UnknownFontVariant(document.font_variant.clone()))?;
+    let frames = document.to_frames()?;
+    if crate::process::is_dry_run() {
+        println!("imported OSD file: {}", osd_file.to_string_lossy());
+        return Ok(());
+    }
+
+    let mut writer = DJIWriter::create(osd_file, osd_kind, font_variant)?;
+    for frame in &frames {
+        writer.write_frame(frame)?;
+    }
+    writer.finish()?;
+
+    log::info!("wrote {} OSD frames to {}", frames.len(), osd_file.to_string_lossy());
+    Ok(())
+}
@@ -0,0 +1,138 @@
+// Substitution of the glyphs missing from a font
+//
+// Some fonts lack the glyphs of some tile indices used by the flight controllers, e.g. localized characters or symbols only
+// drawn by some firmware versions, which leaves blank spots in the rendered OSD. The substitution tables map these tile indices
+// to the indices of visually equivalent glyphs, per font variant.
+
+use std::{collections::{BTreeMap, BTreeSet, HashMap}, io::Error as IOError, path::{Path, PathBuf}};
+
+use hd_fpv_osd_font_tool::prelude::*;
+use itertools::Itertools;
+use strum::IntoEnumIterator;
+use thiserror::Error;
+
+use super::{font_variant::FontVariant, tile_indices::TileIndex, tile_remap::{parse_tile_index, MAX_TILE_INDEX}};
+
+
+/// Name of the substitution tables file loaded from the font directory when no other file is specified
+pub const DEFAULT_FILE_NAME: &str = "glyph_substitutions.toml";
+
+// table applying to all the font variants, the tables of the font variants take precedence over it
+const ALL_VARIANTS_TABLE_NAME: &str = "all";
+
+#[derive(Debug, Error)]
+pub enum GlyphSubstitutionError {
+    #[error("failed reading glyph substitutions file {file_path}: {error}")]
+    ReadError {
+        file_path: PathBuf,
+        error: IOError,
+    },
+    #[error("invalid glyph substitutions file {file_path}: {error}")]
+    ParseError {
+        file_path: PathBuf,
+        error: toml::de::Error,
+    },
+    #[error("glyph substitutions file {file_path}: unknown font variant `{name}`, the tables are named after the font variants or `all`")]
+    UnknownFontVariant {
+        file_path: PathBuf,
+        name: String,
+    },
+    #[error("glyph substitutions file {file_path}: [{table}] {value}: invalid tile index, indices are up to {MAX_TILE_INDEX}")]
+    InvalidTileIndex {
+        file_path: PathBuf,
+        table: String,
+        value: String,
+    },
+}
+
+/// Tile indices mapped to the indices of the glyphs drawn when their own glyphs are missing
+pub type SubstitutionTable = BTreeMap<TileIndex, TileIndex>;
+
+/// Substitution tables read from a TOML file with one table per font variant and an `all` table for every variant
+///
+/// Example:
+/// ```toml
+/// [all]
+/// 0x7f = 0x3f
+///
+/// [inav]
+/// 0x1a0 = 0x41
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct GlyphSubstitutions {
+    all_variants: SubstitutionTable,
+    variants: HashMap<FontVariant, SubstitutionTable>,
+}
+
+impl GlyphSubstitutions {
+
+    pub fn load<P: AsRef<Path>>(file_path: P) -> Result<Self, GlyphSubstitutionError> {
+        let file_path = file_path.as_ref();
+        let content = fs_err::read_to_string(file_path)
+            .map_err(|error| GlyphSubstitutionError::ReadError { file_path: file_path.to_path_buf(), error })?;
+        let tables: BTreeMap<String, BTreeMap<String, u64>> = toml::from_str(&content)
+            .map_err(|error| GlyphSubstitutionError::ParseError { file_path: file_path.to_path_buf(), error })?;
+
+        let mut substitutions = Self::default();
+        for (table_name, entries) in tables {
+            let invalid = |value: String| GlyphSubstitutionError::InvalidTileIndex { file_path: file_path.to_path_buf(), table: table_name.clone(), value };
+            let mut table = SubstitutionTable::new();
+            for (tile_index, glyph_index) in entries {
+                let tile_index = parse_tile_index(&tile_index).ok_or_else(|| invalid(tile_index.clone()))?;
+                let glyph_index = TileIndex::try_from(glyph_index).ok().filter(|glyph_index| *glyph_index <= MAX_TILE_INDEX)
+                    .ok_or_else(|| invalid(glyph_index.to_string()))?;
+                table.insert(tile_index, glyph_index);
+            }
+            if table_name.eq_ignore_ascii_case(ALL_VARIANTS_TABLE_NAME) {
+                substitutions.all_variants = table;
+                continue;
+            }
+            let font_variant = FontVariant::iter().find(|font_variant| font_variant.to_string().eq_ignore_ascii_case(&table_name))
+                .ok_or_else(|| GlyphSubstitutionError::UnknownFontVariant { file_path: file_path.to_path_buf(), name: table_name.clone() })?;
+            substitutions.variants.insert(font_variant, table);
+        }
+        Ok(substitutions)
+    }
+
+    /// Substitution table of a font variant merged with the table of all the variants
+    pub fn table(&self, font_variant: FontVariant) -> SubstitutionTable {
+        let mut table = self.all_variants.clone();
+        if let Some(variant_table) = self.variants.get(&font_variant) {
+            table.extend(variant_table);
+        }
+        table
+    }
+
+}
+
+fn is_missing(tile_images: &[tile::Image], tile_index: TileIndex) -> bool {
+    match tile_images.get(tile_index as usize) {
+        Some(tile_image) => tile_image.pixels().all(|pixel| pixel[3] == 0),
+        None => true,
+    }
+}
+
+/// Draws the used tile indices whose glyphs are missing with the glyphs of their substitutes, returns the substitutions made
+///
+/// A substitution is only made when the substitute glyph is not missing itself, fully transparent glyphs like the space
+/// count as missing since substituting them would not draw anything.
+pub fn apply(table: &SubstitutionTable, used_tile_indices: &BTreeSet<TileIndex>, tile_images: &mut Vec<tile::Image>) -> Vec<(TileIndex, TileIndex)> {
+    if table.is_empty() || tile_images.is_empty() { return vec![] }
+    let (tile_width, tile_height) = tile_images[0].dimensions();
+    let mut substitutions = vec![];
+    for tile_index in used_tile_indices {
+        let Some(glyph_index) = table.get(tile_index) else { continue };
+        if ! is_missing(tile_images, *tile_index) || is_missing(tile_images, *glyph_index) { continue }
+        if tile_images.len() <= *tile_index as usize {
+            tile_images.resize(*tile_index as usize + 1, tile::Image::new(tile_width, tile_height));
+        }
+        tile_images[*tile_index as usize] = tile_images[*glyph_index as usize].clone();
+        substitutions.push((*tile_index, *glyph_index));
+    }
+    substitutions
+}
+
+/// Formats substitutions like `0x1a0→0x41, 0x1a1→0x42`
+pub fn format_substitutions(substitutions: &[(TileIndex, TileIndex)]) -> String {
+    substitutions.iter().map(|(tile_index, glyph_index)| format!("{tile_index:#x}→{glyph_index:#x}")).join(", ")
+}
@@ -0,0 +1,59 @@
+use super::{
+    FontVariant,
+    file::{Frame, SortedUniqFrames},
+    numeric_item::NumericItem,
+};
+
+
+/// OSD items whose values are interpolated, the font variant needs to provide their location data
+pub const INTERPOLATED_ITEMS: [&str; 2] = ["alt", "speed"];
+
+/// Frames further apart than this are not interpolated since the OSD was frozen or not recorded in between (60Hz frames)
+pub const DEFAULT_MAX_GAP: u32 = 30;
+
+fn interpolate_frame_pair(frame: &Frame, next_frame: &Frame, font_variant: FontVariant, interpolated_frames: &mut Vec<Frame>) {
+    let items = INTERPOLATED_ITEMS.iter().filter_map(|item_name| {
+        let item = NumericItem::read(frame.tile_indices(), font_variant, item_name)?;
+        let next_item = NumericItem::read(next_frame.tile_indices(), font_variant, item_name)?;
+        (item.is_displayed_like(&next_item) && item.value() != next_item.value()).then_some((item, next_item.value()))
+    }).collect::<Vec<_>>();
+    if items.is_empty() { return }
+
+    let gap = next_frame.index() - frame.index();
+    let mut previous_tile_indices = frame.tile_indices().clone();
+    for step in 1..gap {
+        let ratio = step as f64 / gap as f64;
+        let mut tile_indices = frame.tile_indices().clone();
+        for (item, next_value) in &items {
+            let value = item.value() + (next_value - item.value()) * ratio;
+            item.write_value(&mut tile_indices, font_variant, value, item.decimals());
+        }
+        // the previous frame stays displayed while the rounded values do not change
+        if tile_indices == previous_tile_indices { continue }
+        previous_tile_indices = tile_indices.clone();
+        interpolated_frames.push(Frame::new(frame.index() + step, tile_indices));
+    }
+}
+
+/// Inserts frames between the OSD updates with the numeric values of [`INTERPOLATED_ITEMS`] interpolated
+///
+/// The OSD is usually updated at about 10Hz which makes the values change in steps on 60FPS videos. Only the
+/// values displayed at the same place with the same unit and precision in two consecutive frames less than
+/// `max_gap` frames apart are interpolated, the rest of the OSD is the one of the first frame.
+pub fn interpolate_frames(frames: &SortedUniqFrames, font_variant: FontVariant, max_gap: u32) -> SortedUniqFrames {
+    let mut interpolated_frames = Vec::with_capacity(frames.len());
+    for (frame, next_frame) in frames.iter().zip(frames.iter().skip(1)) {
+        interpolated_frames.push(frame.clone());
+        if next_frame.index() - frame.index() <= max_gap {
+            interpolate_frame_pair(frame, next_frame, font_variant, &mut interpolated_frames);
+        }
+    }
+    interpolated_frames.extend(frames.last().cloned());
+    let added_frame_count = interpolated_frames.len() - frames.len();
+    match added_frame_count {
+        0 => log::warn!("no OSD value could be interpolated, the {font_variant} font variant may not provide the location of the {} items",
+            INTERPOLATED_ITEMS.join(", ")),
+        _ => log::info!("added {added_frame_count} OSD frames with interpolated values"),
+    }
+    SortedUniqFrames::new(frames.kind(), frames.font_variant(), interpolated_frames)
+}
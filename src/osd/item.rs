@@ -66,18 +66,28 @@ const fn lde(name: &'static str, marker_tile_indices: &'static [TileIndex], top_
 mod location_data {
     use super::{LocationData, ld, ldo};
 
-    pub const INAV: [LocationData; 3] = [
+    pub const INAV: [LocationData; 4] = [
         ld("gpslat", &[3], 10),
         ld("gpslon", &[4], 10),
         ldo("alt", &[0x76, 0x77, 0x78, 0x79], -4, 5),
+        ldo("speed", &[0x90, 0x91, 0x92], -3, 4),
     ];
 
-    pub const ARDUPILOT: [LocationData; 5] = [
+    // the altitude is displayed left aligned after its symbol with a variable number of digits, it is located with its symbol
+    pub const BETAFLIGHT: [LocationData; 4] = [
+        ld("gpslat", &[0x89], 12),
+        ld("gpslon", &[0x98], 13),
+        ld("alt", &[0x7F], 7),
+        ldo("speed", &[0x9E, 0x9D], -3, 4),
+    ];
+
+    pub const ARDUPILOT: [LocationData; 6] = [
         ld("gpslat", &[0xA6], 10),
         ld("gpslon", &[0xA7], 11),
         ldo("alt", &[0xB1, 0xB3], -4, 5),
         ldo("short+code", &[0x2B], -4, 8),
         ldo("long+code", &[0x2B], -8, 12),
+        ldo("speed", &[0xA1, 0xB0], -4, 5),
     ];
 
 }
@@ -87,7 +97,7 @@ impl FontVariant {
         match self {
             FontVariant::Generic => &[],
             FontVariant::Ardupilot => &location_data::ARDUPILOT,
-            FontVariant::Betaflight => &[],
+            FontVariant::Betaflight => &location_data::BETAFLIGHT,
             FontVariant::INAV => &location_data::INAV,
             FontVariant::KISSUltra => &[],
             FontVariant::Unknown => &[],
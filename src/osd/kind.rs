@@ -6,7 +6,7 @@ use thiserror::Error;
 use super::{dji, wsa, Dimensions};
 
 
-#[derive(Debug, strum::Display, Clone, Copy)]
+#[derive(Debug, strum::Display, Clone, Copy, PartialEq, Eq, serde::Serialize)]
 #[allow(non_camel_case_types)]
 pub enum Kind {
     DJI_SD,
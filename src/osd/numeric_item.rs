@@ -0,0 +1,67 @@
+use getset::CopyGetters;
+
+use super::{Coordinate, Coordinates, FontVariant, TileIndex, TileIndices};
+
+
+/// Numeric value displayed by an OSD item, the digits are right aligned in the tiles left of the item unit marker
+#[derive(Debug, Clone, PartialEq, CopyGetters)]
+#[getset(get_copy = "pub")]
+pub struct NumericItem {
+    marker_tile_index: TileIndex,
+    #[getset(skip)]
+    x: Coordinate,
+    #[getset(skip)]
+    y: Coordinate,
+    #[getset(skip)]
+    width: u8,
+    value: f64,
+    /// number of digits displayed after the decimal point
+    decimals: usize,
+}
+
+impl NumericItem {
+
+    /// Reads a numeric OSD item, only the items whose location data places the value before a unit marker can be read
+    pub fn read(tile_indices: &TileIndices, font_variant: FontVariant, item_name: &str) -> Option<Self> {
+        let location_data = font_variant.find_osd_item_location_data(item_name)?;
+        let offset = location_data.top_left_offset();
+        if offset.x() >= 0 || location_data.dimensions().height != 1 { return None }
+        let (marker_tile_index, Coordinates { x: marker_x, y }) = location_data.marker_tile_indices().iter().find_map(|marker_tile_index| {
+            let (coordinates, _) = tile_indices.enumerate().find(|(_, tile_index)| tile_index == marker_tile_index)?;
+            Some((*marker_tile_index, coordinates))
+        })?;
+        let width = offset.x().unsigned_abs();
+        let x = marker_x.checked_sub(width)?;
+        let text = tile_indices.read_text(font_variant, x, y, width);
+        let text = text.trim();
+        if ! text.chars().all(|char| char.is_ascii_digit() || char == '.' || char == '-') { return None }
+        let value = text.parse::<f64>().ok()?;
+        let decimals = text.split_once('.').map_or(0, |(_, decimals)| decimals.len());
+        Some(Self { marker_tile_index, x, y, width, value, decimals })
+    }
+
+    /// Whether the item is displayed at the same place with the same unit and precision
+    pub fn is_displayed_like(&self, other: &Self) -> bool {
+        (self.marker_tile_index, self.x, self.y, self.width, self.decimals) == (other.marker_tile_index, other.x, other.y, other.width, other.decimals)
+    }
+
+    /// Writes a value in place of the displayed one with the specified number of decimals, returns false if it does not fit
+    pub fn write_value(&self, tile_indices: &mut TileIndices, font_variant: FontVariant, value: f64, decimals: usize) -> bool {
+        let text = format!("{:>width$.decimals$}", value, width = self.width as usize);
+        if text.len() > self.width as usize { return false }
+        // spaces are written as empty tiles
+        let Some(text_tile_indices) = text.chars().map(|char| match char {
+            ' ' => Some(0),
+            char => font_variant.char_to_tile_index(char),
+        }).collect::<Option<Vec<_>>>() else { return false };
+        for (offset, tile_index) in text_tile_indices.into_iter().enumerate() {
+            tile_indices.set_tile_index_at(self.x + offset as Coordinate, self.y, tile_index);
+        }
+        true
+    }
+
+    pub fn write_marker(&self, tile_indices: &mut TileIndices, marker_tile_index: TileIndex) {
+        tile_indices.set_tile_index_at(self.x + self.width, self.y, marker_tile_index);
+    }
+
+}
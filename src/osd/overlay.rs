@@ -1,26 +1,40 @@
 
 use std::{
+    collections::BTreeSet,
+    sync::Arc,
     path::{
         Path,
         PathBuf
     },
     io::{
         Error as IOError,
-        Write, self
+        self
     },
 };
 
 use derive_more::{From, Deref};
 use getset::{CopyGetters, Getters};
+use itertools::Itertools;
 use path_absolutize::Absolutize;
 use thiserror::Error;
 use image::{ImageBuffer, Rgba, GenericImage, ImageResult};
-use indicatif::{ProgressStyle, ParallelProgressIterator, ProgressBar};
+use indicatif::{ProgressStyle, ParallelProgressIterator, ProgressBar, MultiProgress};
 use rayon::prelude::{ParallelIterator, IndexedParallelIterator};
 
 pub mod scaling;
 pub mod margins;
 pub mod osd_kind_ext;
+pub mod stick;
+pub mod frame_link;
+pub mod frame_cache;
+pub mod video_timeline;
+pub mod frame_writer;
+pub mod frames_manifest;
+pub mod render_ahead;
+pub mod frame_image_format;
+pub mod placement;
+pub mod calibration;
+pub mod enlargement;
 
 use hd_fpv_osd_font_tool::{
     dimensions::Dimensions as GenericDimensions,
@@ -32,18 +46,16 @@ use crate::{
         CreatePathError,
         create_path,
     },
-    ffmpeg,
+    disk_space::{self, InsufficientDiskSpaceError},
+    ffmpeg::{self, bitrate::Bitrate, frame_pipe::FramePipe, progress::ProgressCallback},
     file::{
         self,
         TouchError,
     },
-    image::{
-        WriteImageFile,
-        WriteError as ImageWriteError,
-    },
+    image::WriteError as ImageWriteError,
     video::{
         FrameIndex as VideoFrameIndex,
-        resolution::Resolution as VideoResolution, timestamp::{Timestamp, StartEndOverlayFrameIndex},
+        resolution::Resolution as VideoResolution, timestamp::{Timestamp, StartEndOverlayFrameIndex}, quality_preset::QualityPreset,
     }, osd::file::sorted_frames::EndOfFramesAction,
 };
 
@@ -53,10 +65,14 @@ use super::{
         SortedUniqFrames as OSDFileSortedFrames,
     },
     Region,
-    tile_resize::ResizeTiles, font_variant::FontVariant, file::{ReadError, sorted_frames::{GetFramesExt, VideoFramesIter, GetFrames}}, tile_indices::UnknownOSDItem, FontDir,
+    region::{check_regions, active_regions, active_regions_mask, InvalidRegionError},
+    tile_resize::{ResizeTiles, ScalingFilter}, font_variant::FontVariant, file::{ReadError, sorted_frames::{GetFramesExt, VideoFramesIter, GetFrames}}, tile_indices::{TileIndex, UnknownOSDItem}, FontDir,
+    font_dir, tile_remap::TileRemap, custom_tiles::{self, CustomTilesError},
+    glyph_substitution::{self, GlyphSubstitutionError}, OSD_FRAME_RATE, OSD_TIME_BASE_HZ,
 };
 
-use self::scaling::Scaling;
+use self::{scaling::Scaling, stick::StickOverlay, enlargement::{EnlargedTiles, Enlargement}, video_timeline::{OSDFrameShift, OSDSpeed, OSDVideoOffset, VideoTimeline}, frame_link::FrameLinkMode, frame_cache::FrameCache, frame_writer::{FrameWriterPool, WriteJob}, frames_manifest::ManifestBuilder,
+    render_ahead::{PlannedFrame, RenderAhead, RenderContext}, frame_image_format::{FrameFileFormat, FrameImageFormat}};
 
 pub type Dimensions = GenericDimensions<u32>;
 #[derive(Deref, Clone, CopyGetters)]
@@ -85,15 +101,26 @@ impl Frame {
     }
 }
 
+impl AsRef<[u8]> for Frame {
+    fn as_ref(&self) -> &[u8] {
+        self.image.as_raw()
+    }
+}
+
 
 impl super::file::Frame {
 
-    fn draw_overlay_frame(&self, dimensions: Dimensions, font_variant: FontVariant, tile_images: &[tile::Image], hidden_regions: &[Region], hidden_items: &[impl AsRef<str>]) -> Result<Frame, UnknownOSDItem> {
+    fn draw_overlay_frame(&self, dimensions: Dimensions, font_variant: FontVariant, tile_images: &[tile::Image], hidden_regions: &[Region], hidden_items: &[impl AsRef<str>],
+                          enlarged_tiles: Option<&EnlargedTiles>) -> Result<Frame, UnknownOSDItem> {
         let (tiles_width, tiles_height) = tile_images.first().unwrap().dimensions();
         let mut frame = Frame::new(dimensions);
         let mut tile_indices = self.tile_indices().clone();
         tile_indices.erase_regions(hidden_regions);
         tile_indices.erase_osd_items(font_variant, hidden_items)?;
+        let enlarged_regions = match enlarged_tiles {
+            Some(enlarged_tiles) => enlarged_tiles.take_regions(&mut tile_indices, font_variant)?,
+            None => vec![],
+        };
         for (osd_coordinates, tile_index) in tile_indices.enumerate() {
             let Some(tile_image) = tile_images.get(tile_index as usize) else {
                 continue;
@@ -108,6 +135,9 @@ impl super::file::Frame {
                 ).unwrap();
             }
         }
+        if let Some(enlarged_tiles) = enlarged_tiles {
+            enlarged_tiles.draw(&mut frame, (tiles_width, tiles_height), &enlarged_regions);
+        }
         Ok(frame)
     }
 
@@ -124,41 +154,70 @@ pub enum DrawFrameOverlayError {
     FontLoadError(bin_file::LoadError),
     #[error("video resolution {video_resolution} too small to render {osd_kind} OSD kind without scaling")]
     VideoResolutionTooSmallError{ osd_kind: super::Kind, video_resolution: VideoResolution },
+    #[error(transparent)]
+    InvalidRegionError(InvalidRegionError),
+    #[error(transparent)]
+    CustomTilesError(CustomTilesError),
+    #[error(transparent)]
+    GlyphSubstitutionError(GlyphSubstitutionError),
 }
 
-pub fn format_overlay_frame_file_index(frame_index: VideoFrameIndex) -> String {
-    format!("{:010}.png", frame_index)
+/// FFMpeg image2 demuxer pattern matching the PNG overlay frame file names
+pub const OVERLAY_FRAME_FILE_NAME_PATTERN: &str = "%010d.png";
+
+pub fn format_overlay_frame_file_index(frame_index: VideoFrameIndex, image_format: FrameImageFormat) -> String {
+    format!("{:010}.{}", frame_index, image_format.extension())
 }
 
-pub fn make_overlay_frame_file_path<P: AsRef<Path>>(dir_path: P, frame_index: VideoFrameIndex) -> PathBuf {
-    [dir_path.as_ref().to_str().unwrap(), &format_overlay_frame_file_index(frame_index)].iter().collect()
+pub fn make_overlay_frame_file_path<P: AsRef<Path>>(dir_path: P, frame_index: VideoFrameIndex, image_format: FrameImageFormat) -> PathBuf {
+    [dir_path.as_ref().to_str().unwrap(), &format_overlay_frame_file_index(frame_index, image_format)].iter().collect()
 }
 
 
-#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
 pub enum OverlayVideoCodec {
     Vp8,
-    Vp9
+    Vp9,
+    /// AV1 encoders do not support transparency so the alpha channel is stored as a grayscale picture stacked
+    /// under the color picture, see [`AV1_STACKED_ALPHA_FILTER`] and [`AV1_STACKED_ALPHA_MERGE_FILTER`]
+    Av1,
 }
 
+/// Filter graph storing the alpha channel of the overlay frames as a grayscale picture under the color picture
+pub const AV1_STACKED_ALPHA_FILTER: &str =
+    "split[color][alpha];[alpha]alphaextract,format=yuv420p[alpha];[color]format=yuv420p[color];[color][alpha]vstack";
+
+/// Filter restoring the transparency of overlay frames encoded with [`AV1_STACKED_ALPHA_FILTER`]
+pub const AV1_STACKED_ALPHA_MERGE_FILTER: &str =
+    "split[color][alpha];[color]crop=iw:ih/2:0:0[color];[alpha]crop=iw:ih/2:0:ih/2[alpha];[color][alpha]alphamerge";
+
+// bitrate assumed to estimate the size of the overlay videos encoded at constant quality, the overlay is mostly transparent
+const OVERLAY_VIDEO_BITRATE_ESTIMATE: Bitrate = Bitrate::from_mbps(1);
+
 #[derive(Debug, Clone, Getters, CopyGetters)]
 #[getset(get_copy = "pub")]
 pub struct OverlayVideoCodecParams {
     encoder: &'static str,
-    bitrate: Option<&'static str>,
+    bitrate: Option<Bitrate>,
     crf: Option<u8>,
 
+    #[getset(skip)]
+    #[getset(get = "pub")]
+    speed_args: Vec<&'static str>,
+
     #[getset(skip)]
     #[getset(get = "pub")]
     additional_args: Vec<&'static str>,
 }
 
 impl OverlayVideoCodecParams {
-    pub fn new(encoder: &'static str, bitrate: Option<&'static str>, crf: Option<u8>, additional_args: &[&'static str]) -> Self {
+    pub fn new(encoder: &'static str, bitrate: Option<Bitrate>, crf: Option<u8>, speed_args: &[&'static str], additional_args: &[&'static str]) -> Self {
         Self {
             encoder,
             bitrate,
             crf,
+            speed_args: speed_args.to_vec(),
             additional_args: additional_args.to_vec(),
         }
     }
@@ -168,10 +227,22 @@ impl OverlayVideoCodec {
     pub fn params(&self) -> OverlayVideoCodecParams {
         use OverlayVideoCodec::*;
         match self {
-            Vp8 => OverlayVideoCodecParams::new("libvpx", Some("1M"), Some(40), &["-auto-alt-ref", "0"]),
-            Vp9 => OverlayVideoCodecParams::new("libvpx-vp9", Some("0"), Some(40), &[]),
+            Vp8 => OverlayVideoCodecParams::new("libvpx", Some(Bitrate::from_mbps(1)), Some(40), &[], &["-auto-alt-ref", "0"]),
+            Vp9 => OverlayVideoCodecParams::new("libvpx-vp9", Some(Bitrate::from_bps(0)), Some(40), &[], &[]),
+            Av1 => OverlayVideoCodecParams::new("libsvtav1", None, Some(40), &["-preset", "8"],
+                                                &["-vf", AV1_STACKED_ALPHA_FILTER, "-pix_fmt", "yuv420p"]),
         }
     }
+
+    /// Same as [`Self::params`] with the quality and speed settings of the preset, the bitrate is kept since it is suited to overlay videos
+    pub fn params_with_preset(&self, preset: Option<QualityPreset>) -> OverlayVideoCodecParams {
+        let mut params = self.params();
+        if let Some(preset_settings) = preset.and_then(|preset| preset.encoder_settings(params.encoder)) {
+            params.crf = Some(preset_settings.crf());
+            params.speed_args = preset_settings.speed_args().clone();
+        }
+        params
+    }
 }
 
 #[derive(Debug, Error, From)]
@@ -184,14 +255,18 @@ pub enum SaveFramesToDirError {
     ReadError(ReadError),
     #[error(transparent)]
     ImageWriteError(ImageWriteError),
-    #[error(transparent)] #[from(ignore)]
-    SymlinkError(IOError),
+    #[error("failed to link frame file: {0}")] #[from(ignore)]
+    LinkError(IOError),
     #[error("no frame to write")]
     NoFrameToWrite,
+    #[error("frame writer threads stopped unexpectedly")]
+    FrameWritersStopped,
     #[error("target directory exists: {0}")]
     TargetDirectoryExists(PathBuf),
     #[error(transparent)]
     UnknownOSDItem(UnknownOSDItem),
+    #[error(transparent)]
+    InsufficientDiskSpace(InsufficientDiskSpaceError),
 }
 
 #[derive(Debug, Error, From)]
@@ -212,6 +287,8 @@ pub enum GenerateOverlayVideoError {
     UnknownOSDItem(UnknownOSDItem),
     #[error(transparent)]
     WriteToFileError(TouchError),
+    #[error(transparent)]
+    InsufficientDiskSpace(InsufficientDiskSpaceError),
 }
 
 impl From<SendFramesToFFMpegError> for GenerateOverlayVideoError {
@@ -246,7 +323,7 @@ fn best_settings_for_requested_scaling(osd_kind: super::Kind, scaling: &Scaling)
             }
         },
 
-        Scaling::Yes { min_margins, target_resolution } => {
+        Scaling::Yes { min_margins, target_resolution, .. } => {
             let max_resolution = VideoResolution::new(
                 target_resolution.dimensions().width - 2 * min_margins.horizontal(),
                 target_resolution.dimensions().height - 2 * min_margins.vertical(),
@@ -255,7 +332,7 @@ fn best_settings_for_requested_scaling(osd_kind: super::Kind, scaling: &Scaling)
             (overlay_dimensions, tile_kind, Some(tile_dimensions))
         },
 
-        Scaling::Auto { min_margins, min_resolution, target_resolution } => {
+        Scaling::Auto { min_margins, min_resolution, target_resolution, filter } => {
             let (overlay_resolution, tile_kind, tile_scaling) =
 
                 // check results without scaling
@@ -273,13 +350,13 @@ fn best_settings_for_requested_scaling(osd_kind: super::Kind, scaling: &Scaling)
                             values
                         } else {
                             // else return parameters with scaling enabled
-                            best_settings_for_requested_scaling(osd_kind, &Scaling::Yes { target_resolution, min_margins })?
+                            best_settings_for_requested_scaling(osd_kind, &Scaling::Yes { target_resolution, min_margins, filter })?
                         }
 
                     },
 
                     // no scaling does not work, return parameters with scaling enabled
-                    Err(_) => best_settings_for_requested_scaling(osd_kind, &Scaling::Yes { target_resolution, min_margins })?,
+                    Err(_) => best_settings_for_requested_scaling(osd_kind, &Scaling::Yes { target_resolution, min_margins, filter })?,
                 };
 
             let tile_scaling_yes_no = match tile_scaling { Some(_) => "yes", None => "no" };
@@ -294,33 +371,101 @@ fn best_settings_for_requested_scaling(osd_kind: super::Kind, scaling: &Scaling)
 pub struct Generator<'a> {
     osd_file_frames: OSDFileSortedFrames,
     font_variant: FontVariant,
-    tile_images: Vec<tile::Image>,
-    hidden_regions: &'a [Region],
+    tile_images: Arc<Vec<tile::Image>>,
+    native_tile_images: Vec<tile::Image>,
+    scaling_filter: ScalingFilter,
+    enlarged_tiles: Option<Arc<EnlargedTiles>>,
+    hidden_regions: Vec<Region>,
     hidden_items: Vec<&'a str>,
+    stick_overlay: Option<StickOverlay>,
+    frame_link_mode: FrameLinkMode,
+    frame_writer_count: usize,
+    frame_file_format: FrameFileFormat,
+    render_pool: Option<Arc<rayon::ThreadPool>>,
+    progress_callback: Option<ProgressCallback>,
 
     #[getset(get_copy = "pub")]
     frame_dimensions: Dimensions,
 }
 
+// formats sorted tile indices collapsing the consecutive ones into ranges, e.g. `256-259, 300`
+fn format_tile_index_ranges(tile_indices: &[TileIndex]) -> String {
+    let mut ranges: Vec<(TileIndex, TileIndex)> = vec![];
+    for tile_index in tile_indices {
+        match ranges.last_mut() {
+            Some((_, last)) if *last + 1 == *tile_index => *last = *tile_index,
+            _ => ranges.push((*tile_index, *tile_index)),
+        }
+    }
+    ranges.iter().map(|(first, last)| if first == last { first.to_string() } else { format!("{first}-{last}") }).join(", ")
+}
+
 impl<'a> Generator<'a> {
 
     pub fn new(osd_file_frames: OSDFileSortedFrames, font_variant: FontVariant, font_dir: &FontDir, font_ident: &Option<Option<&str>>,
-                    scaling: Scaling, hidden_regions: &'a [Region], hidden_items: &'a [String]) -> Result<Self, DrawFrameOverlayError> {
+                    scaling: Scaling, hidden_regions: &[Region], strict_regions: bool, hidden_items: &'a [String]) -> Result<Self, DrawFrameOverlayError> {
 
         if osd_file_frames.is_empty() { return Err(DrawFrameOverlayError::OSDFileIsEmpty) }
+        if font_variant != osd_file_frames.font_variant() {
+            log::info!("using the {font_variant} font variant instead of the {} font variant read from the OSD file", osd_file_frames.font_variant());
+        }
+
+        let hidden_regions = check_regions(hidden_regions, osd_file_frames.kind().dimensions_tiles(), strict_regions)?;
 
         let (overlay_resolution, tile_kind, tile_scaling) =
             best_settings_for_requested_scaling(osd_file_frames.kind(), &scaling)?;
 
-        let highest_used_tile_index = osd_file_frames.highest_used_tile_index().unwrap();
+        if crate::process::is_dry_run() {
+            let tile_scaling_yes_no = match tile_scaling { Some(_) => "yes", None => "no" };
+            println!("overlay: OSD kind: {} - tile kind: {tile_kind} - scaling: {tile_scaling_yes_no} - overlay resolution: {overlay_resolution}",
+                osd_file_frames.kind());
+        }
+
+        // the fonts pages to load depend on the glyphs used once the tile indices are remapped, not counting the custom tiles
+        let custom_tiles = font_dir.custom_tiles()?;
+        let used_tile_indices = osd_file_frames.used_tile_indices();
+        let highest_used_glyph_index = used_tile_indices.iter()
+            .filter(|tile_index| ! custom_tiles.iter().any(|custom_tile| custom_tile.tile_index() == **tile_index))
+            .map(|tile_index| TileRemap::glyph_index(font_dir.tile_remaps(), *tile_index))
+            .filter(|glyph_index| font_dir::page_count(*glyph_index) <= font_dir::MAX_PAGE_COUNT)
+            .max().unwrap_or_default();
         let tiles = match font_ident {
-            Some(font_ident) => font_dir.load_with_fallback(tile_kind, font_ident, highest_used_tile_index)?,
-            None => font_dir.load_variant_with_fallback(tile_kind, &osd_file_frames.font_variant(), highest_used_tile_index)?,
+            Some(font_ident) => font_dir.load_with_fallback(tile_kind, font_ident, highest_used_glyph_index)?,
+            None => font_dir.load_variant_with_fallback(tile_kind, &font_variant, highest_used_glyph_index)?,
         };
 
+        // the tiles are prepared at the dimensions of the font then resized, enlarged tiles being resized from the same tiles
+        let mut native_tile_images: Vec<tile::Image> = tiles.into_iter().map(|tile| tile.image().clone()).collect();
+
+        // the font files only go up to two pages, the next ones are loaded separately
+        for page_index in font_dir::page_count(native_tile_images.len() as TileIndex - 1)..font_dir::page_count(highest_used_glyph_index) {
+            let ident = match font_ident {
+                Some(font_ident) => *font_ident,
+                None => font_variant.font_set_ident(),
+            };
+            let page_images = match font_dir.load_page_images(tile_kind, &ident, page_index) {
+                Ok(Some(page_images)) => page_images,
+                Ok(None) => break,
+                Err(error) => { log::warn!("failed loading font page {}: {error}", page_index + 1); break },
+            };
+            let (tile_width, tile_height) = native_tile_images[0].dimensions();
+            native_tile_images.resize(page_index * font_dir::PAGE_TILE_COUNT, tile::Image::new(tile_width, tile_height));
+            native_tile_images.extend(page_images);
+        }
+
+        TileRemap::apply(font_dir.tile_remaps(), &mut native_tile_images);
+        custom_tiles::apply(&custom_tiles, &mut native_tile_images)?;
+
+        let substitution_table = font_dir.glyph_substitutions()?.table(font_variant);
+        let substitutions = glyph_substitution::apply(&substitution_table, &used_tile_indices, &mut native_tile_images);
+        if ! substitutions.is_empty() {
+            log::info!("substituted {} missing glyphs: {}", substitutions.len(), glyph_substitution::format_substitutions(&substitutions));
+        }
+
+        let scaling_filter = scaling.filter().unwrap_or_default();
         let tile_images = match tile_scaling {
-            Some(tile_dimensions) => tiles.as_slice().resized_tiles_par_with_progress(tile_dimensions),
-            None => tiles.into_iter().map(|tile| tile.image().clone()).collect(),
+            Some(tile_dimensions) => native_tile_images.as_slice().resized_tiles_cached(tile_dimensions, scaling_filter),
+            None => native_tile_images.clone(),
         };
 
         if let Scaling::No { target_resolution: Some(target_resolution) } = scaling {
@@ -335,30 +480,117 @@ impl<'a> Generator<'a> {
             }
         }
 
-        Self::check_osd_file_frames_tile_indices(&osd_file_frames, &tile_images);
+        Self::check_missing_glyphs(&osd_file_frames, &used_tile_indices, &tile_images);
 
         let hidden_items = hidden_items.iter().map(String::as_str).collect();
 
-        Ok(Self { osd_file_frames, tile_images, frame_dimensions: overlay_resolution, hidden_regions, hidden_items, font_variant })
+        Ok(Self { osd_file_frames, tile_images: Arc::new(tile_images), native_tile_images, scaling_filter, enlarged_tiles: None,
+            frame_dimensions: overlay_resolution, hidden_regions, hidden_items, font_variant, stick_overlay: None, frame_link_mode: FrameLinkMode::default(), frame_writer_count: frame_writer::DEFAULT_WRITER_COUNT,
+            frame_file_format: FrameFileFormat::default(), render_pool: None, progress_callback: None })
     }
 
-    fn check_osd_file_frames_tile_indices(osd_file_frames: &OSDFileSortedFrames, tile_images: &[tile::Image]) {
-        let mut invalid_tile_indices = vec![];
-        for osd_frame in osd_file_frames.frames() {
-            for tile_index in osd_frame.tile_indices().iter() {
-                if *tile_index as usize > tile_images.len() - 1 {
-                    invalid_tile_indices.push(*tile_index);
-                }
-            }
-        }
+    /// Renders a virtual sticks widget from the RC channel values displayed by the OSD on top of each overlay frame
+    pub fn set_stick_overlay(&mut self, stick_overlay: Option<StickOverlay>) -> &mut Self {
+        self.stick_overlay = stick_overlay;
+        self
+    }
+
+    /// Renders the regions and items of the enlargement with tiles larger than the rest of the OSD
+    pub fn set_enlargement(&mut self, enlargement: Option<Enlargement>) -> &mut Self {
+        self.enlarged_tiles = enlargement.map(|enlargement| {
+            let (tile_width, tile_height) = self.tile_images[0].dimensions();
+            let tile_dimensions = enlargement.factor().tile_dimensions((tile_width, tile_height));
+            let tile_images = self.native_tile_images.as_slice().resized_tiles_cached(tile_dimensions, self.scaling_filter);
+            Arc::new(EnlargedTiles::new(enlargement, self.osd_file_frames.kind().dimensions_tiles(), tile_images))
+        });
+        self
+    }
+
+    /// Sets how frames repeating the previous OSD frame are written by [`Self::save_frames_to_dir`]
+    pub fn set_frame_link_mode(&mut self, frame_link_mode: FrameLinkMode) -> &mut Self {
+        self.frame_link_mode = frame_link_mode;
+        self
+    }
+
+    /// Sets the number of threads encoding and writing frame files in [`Self::save_frames_to_dir`]
+    pub fn set_frame_writer_count(&mut self, frame_writer_count: usize) -> &mut Self {
+        self.frame_writer_count = frame_writer_count;
+        self
+    }
+
+    /// Sets the image format and alpha mode of the frame files written by [`Self::save_frames_to_dir`]
+    pub fn set_frame_file_format(&mut self, frame_file_format: FrameFileFormat) -> &mut Self {
+        self.frame_file_format = frame_file_format;
+        self
+    }
+
+    /// Sets a function called with each progress update of the FFMpeg process encoding the overlay video
+    pub fn set_progress_callback(&mut self, progress_callback: Option<ProgressCallback>) -> &mut Self {
+        self.progress_callback = progress_callback;
+        self
+    }
+
+    /// Sets the number of threads rendering overlay frames ahead of [`FramesIter`] while the previous frames are being encoded
+    ///
+    /// 0 uses one thread per CPU and 1 renders the frames on the iterating thread when they are requested.
+    pub fn set_render_thread_count(&mut self, render_thread_count: usize) -> &mut Self {
+        self.render_pool = match render_thread_count {
+            1 => None,
+            _ => match rayon::ThreadPoolBuilder::new().num_threads(render_thread_count).thread_name(|index| format!("overlay render {index}")).build() {
+                Ok(render_pool) => Some(Arc::new(render_pool)),
+                Err(error) => {
+                    log::warn!("failed to create the overlay render threads, rendering on a single thread: {error}");
+                    None
+                },
+            },
+        };
+        self
+    }
+
+    // warns about the tile indices used by the OSD file which the font does not have a glyph for
+    //
+    // The glyphs of the pages after the first one are also considered missing when they are blank since the extended
+    // fonts usually leave their unused glyphs blank.
+    fn check_missing_glyphs(osd_file_frames: &OSDFileSortedFrames, used_tile_indices: &BTreeSet<TileIndex>, tile_images: &[tile::Image]) {
+        let max_tile_index_count = font_dir::MAX_PAGE_COUNT * font_dir::PAGE_TILE_COUNT;
+        let (invalid_tile_indices, valid_tile_indices): (Vec<TileIndex>, Vec<TileIndex>) =
+            used_tile_indices.iter().partition(|tile_index| **tile_index as usize >= max_tile_index_count);
+
         if ! invalid_tile_indices.is_empty() {
-            let invalid_tile_indices_str = invalid_tile_indices.iter().map(u16::to_string).collect::<Vec<_>>().join(", ");
-            log::warn!("the OSD file contains invalid tile indices, it is probably corrupted: {}", invalid_tile_indices_str);
+            log::warn!("the OSD file contains invalid tile indices, it is probably corrupted: {}", format_tile_index_ranges(&invalid_tile_indices));
         }
+
+        let missing_glyph_indices = valid_tile_indices.into_iter().filter(|tile_index| {
+            match tile_images.get(*tile_index as usize) {
+                Some(tile_image) => *tile_index as usize >= font_dir::PAGE_TILE_COUNT && tile_image.pixels().all(|pixel| pixel[3] == 0),
+                None => true,
+            }
+        }).collect::<Vec<_>>();
+        if missing_glyph_indices.is_empty() { return }
+
+        let frame_count = osd_file_frames.frames().iter()
+            .filter(|frame| frame.tile_indices().iter().any(|tile_index| missing_glyph_indices.binary_search(tile_index).is_ok()))
+            .count();
+        let pages = missing_glyph_indices.iter().group_by(|tile_index| **tile_index as usize / font_dir::PAGE_TILE_COUNT);
+        let pages_str = pages.into_iter().map(|(page_index, tile_indices)| {
+            format!("page {}: {}", page_index + 1, format_tile_index_ranges(&tile_indices.copied().collect::<Vec<_>>()))
+        }).join(" - ");
+        log::warn!("the font is missing {} glyphs used on {frame_count} OSD frames, they will not be drawn: {pages_str}", missing_glyph_indices.len());
+        log::warn!("use a font with more pages or remap the tile indices onto existing glyphs with the font tile remap option");
+    }
+
+    fn draw_frame(&self, osd_file_frame: &OSDFileFrame, video_frame_index: u32) -> Result<Frame, UnknownOSDItem> {
+        let hidden_regions = active_regions(&self.hidden_regions, video_frame_index);
+        render_overlay_frame(osd_file_frame, self.frame_dimensions, self.font_variant, &self.tile_images, &hidden_regions,
+                             &self.hidden_items, self.enlarged_tiles.as_deref(), self.stick_overlay.as_ref())
     }
 
-    fn draw_frame(&self, osd_file_frame: &OSDFileFrame) -> Result<Frame, UnknownOSDItem> {
-        osd_file_frame.draw_overlay_frame(self.frame_dimensions, self.font_variant, &self.tile_images, self.hidden_regions, &self.hidden_items)
+    /// Renders the overlay of a single OSD frame, `None` if there is no OSD frame at this position in the OSD file
+    ///
+    /// The hidden regions are selected as if the OSD frame was displayed on the video frame with the same index.
+    pub fn render_osd_frame(&self, osd_frame_position: usize) -> Option<Result<Frame, UnknownOSDItem>> {
+        let osd_file_frame = self.osd_file_frames.get(osd_frame_position)?;
+        Some(self.draw_frame(osd_file_frame, osd_file_frame.index()))
     }
 
     pub fn save_frames_to_dir<P: AsRef<Path> + std::marker::Sync>(&mut self, start: Option<Timestamp>, end: Option<Timestamp>,
@@ -368,8 +600,10 @@ impl<'a> Generator<'a> {
             return Err(SaveFramesToDirError::TargetDirectoryExists(path.as_ref().to_path_buf()));
         }
 
-        create_path(&path)?;
-        log::info!("generating overlay frames and saving into directory: {}", path.as_ref().to_string_lossy());
+        if crate::process::is_dry_run() {
+            println!("overlay frames directory: {}", path.as_ref().to_string_lossy());
+            return Ok(());
+        }
 
         let first_video_frame = start.start_overlay_frame_count();
         let last_video_frame = end.end_overlay_frame_index();
@@ -382,42 +616,120 @@ impl<'a> Generator<'a> {
             osd_file_frames_slice.video_frames_rel_index_par_iter(EndOfFramesAction::ContinueToLastVideoFrame);
         let frame_count = iter.len();
 
-        let progress_style = ProgressStyle::with_template("{wide_bar} {pos:>6}/{len}").unwrap();
-        let progress_bar = ProgressBar::new(frame_count as u64).with_style(progress_style);
-        progress_bar.enable_steady_tick(std::time::Duration::new(0, 100_000_000));
+        disk_space::check(&path, disk_space::overlay_frames_size_estimate(frame_count as u64, self.frame_dimensions))?;
+        create_path(&path)?;
+        log::info!("generating overlay frames and saving into directory: {}", path.as_ref().to_string_lossy());
+
+        // rendering and writing are progressing separately since the frames are written by a dedicated pool of threads
+        let multi_progress = MultiProgress::new();
+        let render_progress_style = ProgressStyle::with_template("rendered {wide_bar} {pos:>6}/{len}").unwrap();
+        let render_progress_bar = multi_progress.add(ProgressBar::new(frame_count as u64).with_style(render_progress_style));
+        let write_progress_style = ProgressStyle::with_template("written  {wide_bar} {pos:>6}/{len} {per_sec:>12} [ETA {eta:>3}]").unwrap();
+        let write_progress_bar = multi_progress.add(ProgressBar::new(frame_count as u64).with_style(write_progress_style));
+        write_progress_bar.enable_steady_tick(std::time::Duration::new(0, 100_000_000));
 
         let abs_output_dir_path = path.as_ref().absolutize().unwrap();
+        let deferred_links = std::sync::Mutex::new(vec![]);
+        let writer_pool = FrameWriterPool::new(self.frame_writer_count, self.frame_file_format, write_progress_bar.clone());
+        let image_format = self.frame_file_format.image_format();
+        let hidden_regions_change = |prev_rel_index: u32, rel_index: u32| {
+            active_regions_mask(&self.hidden_regions, first_video_frame + prev_rel_index)
+                != active_regions_mask(&self.hidden_regions, first_video_frame + rel_index)
+        };
 
-        iter.progress_with(progress_bar).try_for_each(|item| {
+        let manifest_builder = self.frame_link_mode.writes_manifest().then(|| std::sync::Mutex::new(ManifestBuilder::new(frame_count)));
+
+        let render_result = iter.progress_with(render_progress_bar).try_for_each_with(writer_pool.sender(), |frame_sender, item| {
             use crate::osd::file::sorted_frames::VideoFramesRelIndexIterItem::*;
+            let queue_frame = |frame_image: Frame, rel_index: u32| {
+                let file_path = match &manifest_builder {
+                    Some(manifest_builder) => {
+                        let file_name = frames_manifest::content_addressed_file_name(&frame_image, image_format);
+                        if ! manifest_builder.lock().unwrap().add_frame(rel_index, &file_name) {
+                            write_progress_bar.inc(1);
+                            return Ok(());
+                        }
+                        path.as_ref().join(file_name)
+                    },
+                    None => make_overlay_frame_file_path(&path, rel_index, image_format),
+                };
+                frame_sender.send(WriteJob::new(frame_image, file_path)).map_err(|_| SaveFramesToDirError::FrameWritersStopped)
+            };
             match item {
                 Existing { rel_index, frame } => {
                     log::debug!("existing {}", &rel_index);
-                    let frame_image = self.draw_frame(frame)?;
-                    frame_image.write_image_file(make_overlay_frame_file_path(&path, rel_index))?;
+                    queue_frame(self.draw_frame(frame, first_video_frame + rel_index)?, rel_index)?;
                 },
                 FirstNonExisting => {
                     log::debug!("first non existing");
-                    let frame_0_path = make_overlay_frame_file_path(&path, 0);
-                    Frame::new(self.frame_dimensions).write_image_file(frame_0_path)?;
+                    queue_frame(Frame::new(self.frame_dimensions), 0)?;
+                },
+                NonExisting { prev_rel_index, rel_index } if hidden_regions_change(prev_rel_index, rel_index) => {
+                    // the previous frame cannot be reused since it has not been drawn with the same hidden regions
+                    log::debug!("non existing {} -> {} with different hidden regions", rel_index, prev_rel_index);
+                    let prev_frame = osd_file_frames_slice.binary_search_by_key(&(prev_rel_index as i64), |frame| {
+                        frame.index() as i64 + frame_shift as i64 - first_video_frame as i64
+                    }).ok().map(|index| &osd_file_frames_slice[index]);
+                    let frame_image = match prev_frame {
+                        Some(prev_frame) => self.draw_frame(prev_frame, first_video_frame + rel_index)?,
+                        None => Frame::new(self.frame_dimensions),
+                    };
+                    queue_frame(frame_image, rel_index)?;
                 },
                 NonExisting { prev_rel_index, rel_index } => {
                     log::debug!("non existing {} -> {}", rel_index, prev_rel_index);
-                    let prev_path = make_overlay_frame_file_path(&abs_output_dir_path, prev_rel_index);
-                    let link_path = make_overlay_frame_file_path(&path, rel_index);
-                    fs_err::os::unix::fs::symlink(prev_path, link_path)
-                        .map_err(SaveFramesToDirError::SymlinkError)?;
+                    if self.frame_link_mode.needs_existing_target() {
+                        // the previous frame file may not have been written yet by the writer threads
+                        deferred_links.lock().unwrap().push((prev_rel_index, rel_index));
+                    } else {
+                        self.link_frame_file(&abs_output_dir_path, &path, prev_rel_index, rel_index)?;
+                        write_progress_bar.inc(1);
+                    }
                 },
             }
             Ok::<(), SaveFramesToDirError>(())
-        })?;
+        });
+
+        // a write error makes the rendering fail with FrameWritersStopped so report the write error first
+        writer_pool.finish()?;
+        render_result?;
+
+        let mut deferred_links = deferred_links.into_inner().unwrap();
+        if let Some(manifest_builder) = manifest_builder {
+            let mut manifest_builder = manifest_builder.into_inner().unwrap();
+            deferred_links.sort_unstable_by_key(|(_, rel_index)| *rel_index);
+            for (prev_rel_index, rel_index) in deferred_links {
+                manifest_builder.repeat_frame(prev_rel_index, rel_index);
+                write_progress_bar.inc(1);
+            }
+            write_progress_bar.finish();
+            let written_file_count = manifest_builder.written_file_count();
+            manifest_builder.build().write_to_dir(&path)?;
+            log::info!("overlay frames generation completed: {written_file_count} unique frame files written for {frame_count} frames");
+            return Ok(());
+        }
+
+        for (prev_rel_index, rel_index) in deferred_links {
+            self.link_frame_file(&abs_output_dir_path, &path, prev_rel_index, rel_index)?;
+            write_progress_bar.inc(1);
+        }
+        write_progress_bar.finish();
 
         log::info!("overlay frames generation completed: {} frame files written", frame_count);
         Ok(())
     }
 
-    pub async fn generate_overlay_video<P: AsRef<Path>>(&mut self, codec: OverlayVideoCodec, start: Option<Timestamp>, end: Option<Timestamp>,
-                                    output_video_path: P, frame_shift: i32, overwrite_output: bool) -> Result<(), GenerateOverlayVideoError> {
+    fn link_frame_file<P: AsRef<Path>, Q: AsRef<Path>>(&self, abs_output_dir_path: P, output_dir_path: Q, prev_rel_index: u32, rel_index: u32) -> Result<(), SaveFramesToDirError> {
+        let image_format = self.frame_file_format.image_format();
+        let prev_path = make_overlay_frame_file_path(abs_output_dir_path, prev_rel_index, image_format);
+        let link_path = make_overlay_frame_file_path(output_dir_path, rel_index, image_format);
+        self.frame_link_mode.link(prev_path, link_path).map_err(SaveFramesToDirError::LinkError)
+    }
+
+    /// Generates the overlay video, with a speed other than normal the OSD frame timeline is stretched to follow the speed
+    /// of the footage, the start and end being in the OSD recording time base
+    pub async fn generate_overlay_video<P: AsRef<Path>>(&mut self, codec: OverlayVideoCodec, preset: Option<QualityPreset>, start: Option<Timestamp>, end: Option<Timestamp>,
+                                    output_video_path: P, frame_shift: i32, osd_speed: OSDSpeed, overwrite_output: bool) -> Result<(), GenerateOverlayVideoError> {
 
         let output_video_path = output_video_path.as_ref();
 
@@ -429,26 +741,56 @@ impl<'a> Generator<'a> {
             return Err(GenerateOverlayVideoError::TargetVideoFileExists(output_video_path.to_path_buf()));
         }
 
-        file::touch(output_video_path)?;
+        file::check_output_path(output_video_path)?;
 
         log::info!("generating overlay video: {}", output_video_path.to_string_lossy());
 
-        let frames_iter =
-            self.iter_advanced(start.start_overlay_frame_count(), end.end_overlay_frame_index(), frame_shift);
-        let frame_count = frames_iter.len();
+        let (first_frame, last_frame) = (start.start_overlay_frame_count(), end.end_overlay_frame_index());
+        let video_timeline = (osd_speed != OSDSpeed::NORMAL).then(|| {
+            let mut video_timeline = VideoTimeline::new(OSD_FRAME_RATE.into(), 1, OSDVideoOffset::from_osd_frame_shift(OSDFrameShift::from_frames(frame_shift)));
+            video_timeline.set_speed(osd_speed.factor());
+            video_timeline
+        });
+        // frame range of the overlay video at the requested speed
+        let timeline_frame_range = video_timeline.map(|_| {
+            let last_frame = last_frame.unwrap_or_else(||
+                self.osd_file_frames.highest_video_frame_index().map_or(0, |index| (index as i32 + frame_shift).max(0) as u32));
+            (osd_speed.video_frame_index(first_frame), osd_speed.video_frame_index(last_frame))
+        });
+        let frame_count = match timeline_frame_range {
+            Some((first_video_frame, last_video_frame)) => (last_video_frame + 1).saturating_sub(first_video_frame) as usize,
+            None => self.osd_file_frames.video_frames_iter(first_frame, last_frame, frame_shift).len(),
+        };
+
+        let codec_params = codec.params_with_preset(preset);
+        let bitrate_estimate = codec_params.bitrate().filter(|bitrate| ! bitrate.is_zero()).unwrap_or(OVERLAY_VIDEO_BITRATE_ESTIMATE);
+        disk_space::check(output_video_path, disk_space::bitrate_size_estimate(bitrate_estimate, frame_count as f64 / OSD_TIME_BASE_HZ))?;
 
         let mut ffmpeg_command = ffmpeg::CommandBuilder::default();
 
         ffmpeg_command
-            .add_stdin_input(self.frame_dimensions, 60).unwrap()
-            .set_output_video_settings(Some(codec.params().encoder()), codec.params().bitrate(), codec.params().crf())
-            .add_args(codec.params().additional_args())
+            .add_stdin_input(self.frame_dimensions, OSD_FRAME_RATE).unwrap()
+            .set_output_video_settings(Some(codec_params.encoder()), codec_params.bitrate(), codec_params.crf())
+            .add_args(codec_params.speed_args())
+            .add_args(codec_params.additional_args())
             .set_output_file(output_video_path)
-            .set_overwrite_output_file(true);
+            .set_overwrite_output_file(true)
+            .set_atomic_output(true)
+            .set_progress_callback(self.progress_callback.clone());
 
         let ffmpeg_process = ffmpeg_command.build().unwrap().spawn_with_progress(frame_count as u64)?;
 
-        frames_iter.send_frames_to_ffmpeg_and_wait(ffmpeg_process).await?;
+        match (video_timeline, timeline_frame_range) {
+            (Some(video_timeline), Some((first_video_frame, last_video_frame))) => {
+                log::debug!("mapping OSD frames onto video timeline: {video_timeline:?}");
+                let frames_iter = self.iter_for_video_timeline(first_video_frame, last_video_frame, video_timeline);
+                frames_iter.send_frames_to_ffmpeg_and_wait(ffmpeg_process).await?;
+            },
+            _ => {
+                let frames_iter = self.iter_advanced(first_frame, last_frame, frame_shift);
+                frames_iter.send_frames_to_ffmpeg_and_wait(ffmpeg_process).await?;
+            },
+        }
 
         log::info!("overlay video generation completed: {} frames", frame_count);
         Ok(())
@@ -459,14 +801,33 @@ impl<'a> Generator<'a> {
     }
 
     pub fn iter_advanced(&self, first_frame: u32, last_frame: Option<u32>, frame_shift: i32) -> FramesIter {
+        let render_ahead = self.render_pool.as_ref().map(|render_pool| {
+            let context = RenderContext {
+                frame_dimensions: self.frame_dimensions,
+                font_variant: self.font_variant,
+                tile_images: self.tile_images.clone(),
+                hidden_items: self.hidden_items.iter().map(ToString::to_string).collect(),
+                enlarged_tiles: self.enlarged_tiles.clone(),
+                stick_overlay: self.stick_overlay.clone(),
+            };
+            RenderAhead::new(render_pool.clone(), context)
+        });
         FramesIter {
             frame_dimensions: self.frame_dimensions,
             font_variant: self.font_variant,
             tile_images: &self.tile_images,
             vframes_iter: self.osd_file_frames.video_frames_iter(first_frame, last_frame, frame_shift),
-            hidden_regions: self.hidden_regions,
+            hidden_regions: &self.hidden_regions,
             hidden_items: &self.hidden_items,
-            prev_frame: Frame::new(self.frame_dimensions)
+            enlarged_tiles: self.enlarged_tiles.as_deref(),
+            stick_overlay: self.stick_overlay.as_ref(),
+            cache: FrameCache::default(),
+            prev_frame: Arc::new(Frame::new(self.frame_dimensions)),
+            prev_osd_file_frame: None,
+            video_frame_index: first_frame,
+            active_regions_mask: vec![],
+            active_regions: Arc::new(vec![]),
+            render_ahead,
         }
     }
 
@@ -501,17 +862,91 @@ pub struct FramesIter<'a> {
     vframes_iter: VideoFramesIter<'a>,
     hidden_regions: &'a [Region],
     hidden_items: &'a [&'a str],
-    prev_frame: Frame
+    enlarged_tiles: Option<&'a EnlargedTiles>,
+    stick_overlay: Option<&'a StickOverlay>,
+    cache: FrameCache,
+    prev_frame: Arc<Frame>,
+    prev_osd_file_frame: Option<&'a OSDFileFrame>,
+    video_frame_index: u32,
+    active_regions_mask: Vec<bool>,
+    active_regions: Arc<Vec<Region>>,
+    render_ahead: Option<RenderAhead<'a>>,
 }
 
 impl<'a> FramesIter<'a> {
 
-    pub fn send_frames_to_ffmpeg(&mut self, ffmpeg_process: &mut ffmpeg::Process) -> Result<(), SendFramesToFFMpegError> {
-        let mut ffmpeg_stdin = ffmpeg_process.take_stdin().unwrap();
-        for osd_frame_image in self {
-            ffmpeg_stdin.write_all(osd_frame_image?.as_raw())?;
+    /// Same as [`Iterator::next`] but returns the frame shared with the cache to avoid copying it
+    pub fn next_shared(&mut self) -> Option<Result<Arc<Frame>, UnknownOSDItem>> {
+        let planned_frame = match self.render_ahead.is_some() {
+            true => {
+                self.fill_render_ahead();
+                self.render_ahead.as_mut().unwrap().pop()?
+            },
+            false => self.plan_next_frame()?,
+        };
+        // cached frames have been drawn with the previous hidden regions
+        if planned_frame.clears_cache { self.cache.clear() }
+        let frame = match (planned_frame.osd_file_frame, planned_frame.rendered) {
+            (Some(osd_file_frame), Some(rendered)) => {
+                match rendered.recv().expect("overlay render thread panicked") {
+                    Ok(frame) => self.cache.insert(osd_file_frame.tile_indices(), frame),
+                    Err(error) => return Some(Err(error)),
+                }
+            },
+            (Some(osd_file_frame), None) => {
+                let render_frame = || render_overlay_frame(osd_file_frame, self.frame_dimensions, self.font_variant, self.tile_images,
+                                                           &planned_frame.hidden_regions, self.hidden_items, self.enlarged_tiles,
+                                                           self.stick_overlay);
+                match self.cache.get_or_try_insert_with(osd_file_frame.tile_indices(), render_frame) {
+                    Ok(frame) => frame,
+                    Err(error) => return Some(Err(error)),
+                }
+            },
+            (None, _) => self.prev_frame.clone(),
+        };
+        self.prev_frame = frame.clone();
+        Some(Ok(frame))
+    }
+
+    // decides which OSD frame is displayed on the next video frame and with which hidden regions
+    fn plan_next_frame(&mut self) -> Option<PlannedFrame<'a>> {
+        let osd_file_frame = self.vframes_iter.next()?;
+        let hidden_regions_changed = self.update_active_regions();
+        self.video_frame_index += 1;
+        // the OSD frame needs to be drawn again when hidden regions appear or disappear while it is displayed
+        let osd_file_frame = match osd_file_frame {
+            None if hidden_regions_changed => self.prev_osd_file_frame,
+            osd_file_frame => osd_file_frame,
+        };
+        if osd_file_frame.is_some() { self.prev_osd_file_frame = osd_file_frame }
+        Some(PlannedFrame { osd_file_frame, hidden_regions: self.active_regions.clone(), clears_cache: hidden_regions_changed, rendered: None })
+    }
+
+    // plans frames until the render ahead queue is full, starting the rendering of the frames which are not cached
+    //
+    // A frame found in the cache may be evicted before it is requested, it is then rendered on the iterating thread.
+    fn fill_render_ahead(&mut self) {
+        while ! self.render_ahead.as_ref().unwrap().is_full() {
+            let Some(planned_frame) = self.plan_next_frame() else { break };
+            let render_ahead = self.render_ahead.as_mut().unwrap();
+            let is_cached = ! planned_frame.clears_cache && ! render_ahead.cache_clear_pending()
+                && planned_frame.osd_file_frame.map_or(true, |osd_file_frame| self.cache.contains(osd_file_frame.tile_indices()));
+            render_ahead.push(planned_frame, is_cached);
         }
-        drop(ffmpeg_stdin);
+    }
+
+    // returns whether the set of hidden regions applying to the current video frame changed
+    fn update_active_regions(&mut self) -> bool {
+        let mask = active_regions_mask(self.hidden_regions, self.video_frame_index);
+        if mask == self.active_regions_mask { return false }
+        self.active_regions = Arc::new(active_regions(self.hidden_regions, self.video_frame_index));
+        self.active_regions_mask = mask;
+        true
+    }
+
+    pub fn send_frames_to_ffmpeg(&mut self, ffmpeg_process: &mut ffmpeg::Process) -> Result<(), SendFramesToFFMpegError> {
+        send_frames_to_ffmpeg(|| self.next_shared(), ffmpeg_process)?;
+        log::debug!("overlay frame cache: {} hits, {} misses", self.cache.hits(), self.cache.misses());
         Ok(())
     }
 
@@ -526,22 +961,44 @@ impl<'a> FramesIter<'a> {
 
 }
 
+fn render_overlay_frame(osd_file_frame: &OSDFileFrame, frame_dimensions: Dimensions, font_variant: FontVariant, tile_images: &[tile::Image],
+                        hidden_regions: &[Region], hidden_items: &[impl AsRef<str>], enlarged_tiles: Option<&EnlargedTiles>,
+                        stick_overlay: Option<&StickOverlay>) -> Result<Frame, UnknownOSDItem> {
+    let mut frame = osd_file_frame.draw_overlay_frame(frame_dimensions, font_variant, tile_images, hidden_regions, hidden_items, enlarged_tiles)?;
+    if let Some(stick_overlay) = stick_overlay {
+        stick_overlay.draw(&mut frame, osd_file_frame.tile_indices(), font_variant);
+    }
+    Ok(frame)
+}
+
+fn send_frames_to_ffmpeg(mut next_frame: impl FnMut() -> Option<Result<Arc<Frame>, UnknownOSDItem>>,
+                         ffmpeg_process: &mut ffmpeg::Process) -> Result<(), SendFramesToFFMpegError> {
+    // the process has no stdin in dry-run mode, there is no need to render the frames
+    let Some(ffmpeg_stdin) = ffmpeg_process.take_stdin() else { return Ok(()) };
+    let mut osd_frame_image = match next_frame() {
+        Some(osd_frame_image) => osd_frame_image?,
+        None => return Ok(()),
+    };
+    let frame_pipe = FramePipe::new(ffmpeg_stdin, osd_frame_image.as_raw().len());
+    let render_result = loop {
+        // the writer thread only stops on a write error which is reported by finish
+        if frame_pipe.send(osd_frame_image).is_err() { break Ok(()) }
+        osd_frame_image = match next_frame() {
+            Some(Ok(osd_frame_image)) => osd_frame_image,
+            Some(Err(error)) => break Err(error),
+            None => break Ok(()),
+        };
+    };
+    frame_pipe.finish()?;
+    render_result?;
+    Ok(())
+}
+
 impl<'a> Iterator for FramesIter<'a> {
     type Item = Result<Frame, UnknownOSDItem>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match self.vframes_iter.next()? {
-            Some(osd_file_frame) => {
-                let frame = match osd_file_frame.draw_overlay_frame(self.frame_dimensions, self.font_variant,
-                                                                           self.tile_images, self.hidden_regions, self.hidden_items) {
-                    Ok(frame) => frame,
-                    Err(error) => return Some(Err(error)),
-                };
-                self.prev_frame = frame.clone();
-                Some(Ok(frame))
-            },
-            None => Some(Ok(self.prev_frame.clone())),
-        }
+        Some(self.next_shared()?.map(|frame| Frame::clone(&frame)))
     }
 }
 
@@ -0,0 +1,191 @@
+// Calibration overlay
+//
+// Renders the tile grid of an OSD kind with the exact settings the overlay of an OSD file would be generated with: the cells
+// are outlined and labelled with their --hide-regions coordinates, the edges of the grid and its center are marked. Played
+// over the footage it shows whether the OSD would be scaled and placed correctly before spending time burning it.
+
+use std::{path::{Path, PathBuf}, io::Error as IOError, sync::Arc};
+
+use derive_more::From;
+use image::{Rgba, RgbaImage};
+use thiserror::Error;
+
+use crate::{
+    disk_space::{self, InsufficientDiskSpaceError},
+    ffmpeg,
+    file::{self, TouchError},
+    image::{WriteError as ImageWriteError, WriteImageFile},
+    osd::{self, font_info::{draw_hex_number, hex_number_width, DIGIT_HEIGHT}, tile_indices::UnknownOSDItem},
+};
+
+use super::{best_settings_for_requested_scaling, scaling::Scaling, DrawFrameOverlayError, Frame, OverlayVideoCodec,
+    SendFramesToFFMpegError, OVERLAY_VIDEO_BITRATE_ESTIMATE};
+
+
+const GRID_COLOR: Rgba<u8> = Rgba([255, 255, 255, 96]);
+// every tenth line is highlighted to count the cells faster
+const GRID_TENS_COLOR: Rgba<u8> = Rgba([0, 255, 255, 160]);
+const BORDER_COLOR: Rgba<u8> = Rgba([255, 0, 0, 255]);
+const CORNER_COLOR: Rgba<u8> = Rgba([255, 0, 255, 255]);
+const CENTER_COLOR: Rgba<u8> = Rgba([0, 255, 0, 255]);
+const LABEL_COLOR: Rgba<u8> = Rgba([255, 255, 0, 255]);
+
+const BORDER_WIDTH: u32 = 3;
+
+// frame rate of the calibration videos, the one of the overlay videos
+const FRAME_RATE: u16 = crate::osd::OSD_FRAME_RATE;
+
+#[derive(Debug, Error, From)]
+pub enum GenerateCalibrationOverlayError {
+    #[error(transparent)]
+    DrawFrameOverlayError(DrawFrameOverlayError),
+    #[error("target file exists: {0}")]
+    TargetFileExists(PathBuf),
+    #[error("output file extension needs to be .png or .webm")]
+    UnsupportedOutputFileExtension,
+    #[error(transparent)]
+    ImageWriteError(ImageWriteError),
+    #[error(transparent)]
+    WriteToFileError(TouchError),
+    #[error(transparent)]
+    InsufficientDiskSpace(InsufficientDiskSpaceError),
+    #[error(transparent)]
+    FailedSpawningFFMpegProcess(ffmpeg::SpawnError),
+    #[error("failed sending calibration frames to ffmpeg process: {0}")]
+    FailedSendingFramesToFFMpeg(IOError),
+    #[error(transparent)]
+    UnknownOSDItem(UnknownOSDItem),
+    #[error(transparent)]
+    FFMpegExitedWithError(ffmpeg::ProcessError),
+}
+
+impl From<SendFramesToFFMpegError> for GenerateCalibrationOverlayError {
+    fn from(error: SendFramesToFFMpegError) -> Self {
+        use SendFramesToFFMpegError::*;
+        match error {
+            PipeError(error) => Self::FailedSendingFramesToFFMpeg(error),
+            UnknownOSDItem(error) => Self::UnknownOSDItem(error),
+            FFMpegExitedWithError(error) => Self::FFMpegExitedWithError(error),
+        }
+    }
+}
+
+fn draw_horizontal_line(image: &mut RgbaImage, y: u32, x_range: std::ops::Range<u32>, color: Rgba<u8>) {
+    for x in x_range { image.put_pixel(x, y, color) }
+}
+
+fn draw_vertical_line(image: &mut RgbaImage, x: u32, y_range: std::ops::Range<u32>, color: Rgba<u8>) {
+    for y in y_range { image.put_pixel(x, y, color) }
+}
+
+fn fill_rectangle(image: &mut RgbaImage, x: u32, y: u32, width: u32, height: u32, color: Rgba<u8>) {
+    for y in y..y + height {
+        draw_horizontal_line(image, y, x..x + width, color);
+    }
+}
+
+/// Draws the calibration frame of an OSD kind with the overlay dimensions and tile kind selected for the requested scaling
+pub fn draw_calibration_frame(osd_kind: osd::Kind, scaling: &Scaling) -> Result<Frame, DrawFrameOverlayError> {
+    let (dimensions, _, _) = best_settings_for_requested_scaling(osd_kind, scaling)?;
+    let grid = osd_kind.dimensions_tiles();
+    let (tile_width, tile_height) = (dimensions.width / grid.width, dimensions.height / grid.height);
+    let mut image = RgbaImage::new(dimensions.width, dimensions.height);
+
+    for column in 1..grid.width {
+        let color = if column % 10 == 0 { GRID_TENS_COLOR } else { GRID_COLOR };
+        draw_vertical_line(&mut image, column * tile_width, 0..dimensions.height, color);
+    }
+    for row in 1..grid.height {
+        let color = if row % 10 == 0 { GRID_TENS_COLOR } else { GRID_COLOR };
+        draw_horizontal_line(&mut image, row * tile_height, 0..dimensions.width, color);
+    }
+
+    // border of the grid with a filled tile in each corner
+    let border_width = BORDER_WIDTH.min(tile_width / 2).min(tile_height / 2).max(1);
+    fill_rectangle(&mut image, 0, 0, dimensions.width, border_width, BORDER_COLOR);
+    fill_rectangle(&mut image, 0, dimensions.height - border_width, dimensions.width, border_width, BORDER_COLOR);
+    fill_rectangle(&mut image, 0, 0, border_width, dimensions.height, BORDER_COLOR);
+    fill_rectangle(&mut image, dimensions.width - border_width, 0, border_width, dimensions.height, BORDER_COLOR);
+    for (x, y) in [(0, 0), (dimensions.width - tile_width, 0), (0, dimensions.height - tile_height),
+                   (dimensions.width - tile_width, dimensions.height - tile_height)] {
+        fill_rectangle(&mut image, x, y, tile_width, tile_height, CORNER_COLOR);
+    }
+
+    // cross at the center of the grid spanning two cells in each direction
+    let (center_x, center_y) = (dimensions.width / 2, dimensions.height / 2);
+    draw_horizontal_line(&mut image, center_y, center_x.saturating_sub(2 * tile_width)..(center_x + 2 * tile_width).min(dimensions.width), CENTER_COLOR);
+    draw_vertical_line(&mut image, center_x, center_y.saturating_sub(2 * tile_height)..(center_y + 2 * tile_height).min(dimensions.height), CENTER_COLOR);
+
+    // the coordinates are written inside the border in the cells of the first row and of the first column when they fit
+    let scale = ((tile_width - border_width) / hex_number_width(2, 1)).min((tile_height - border_width) / DIGIT_HEIGHT);
+    if scale > 0 {
+        for column in 1..grid.width {
+            draw_hex_number(&mut image, &format!("{:02}", column % 100), column * tile_width + 1, border_width, scale, LABEL_COLOR);
+        }
+        for row in 1..grid.height {
+            draw_hex_number(&mut image, &format!("{:02}", row % 100), border_width, row * tile_height + 1, scale, LABEL_COLOR);
+        }
+    }
+
+    Ok(Frame { dimensions, image })
+}
+
+/// Writes the calibration overlay of an OSD kind either as a PNG image or as a .webm overlay video lasting `duration` seconds
+pub async fn generate_calibration_overlay<P: AsRef<Path>>(osd_kind: osd::Kind, scaling: &Scaling, output_path: P, duration: u32,
+        codec: OverlayVideoCodec, overwrite: bool) -> Result<(), GenerateCalibrationOverlayError> {
+    let output_path = output_path.as_ref();
+    let extension = output_path.extension().map(|extension| extension.to_ascii_lowercase());
+    let is_video = match extension.as_ref().and_then(|extension| extension.to_str()) {
+        Some("webm") => true,
+        Some("png") => false,
+        _ => return Err(GenerateCalibrationOverlayError::UnsupportedOutputFileExtension),
+    };
+
+    if ! overwrite && output_path.exists() {
+        return Err(GenerateCalibrationOverlayError::TargetFileExists(output_path.to_path_buf()));
+    }
+
+    file::check_output_path(output_path)?;
+
+    let frame = draw_calibration_frame(osd_kind, scaling)?;
+    log::info!("calibration overlay: {} tiles grid, {} px", osd_kind.dimensions_tiles(), frame.dimensions());
+
+    if ! is_video {
+        if crate::process::is_dry_run() {
+            println!("calibration overlay image: {}", output_path.to_string_lossy());
+            return Ok(());
+        }
+        frame.image.write_image_file(output_path)?;
+        log::info!("calibration overlay written to {}", output_path.to_string_lossy());
+        return Ok(());
+    }
+
+    let frame_count = duration * FRAME_RATE as u32;
+    let codec_params = codec.params();
+    let bitrate_estimate = codec_params.bitrate().filter(|bitrate| ! bitrate.is_zero()).unwrap_or(OVERLAY_VIDEO_BITRATE_ESTIMATE);
+    disk_space::check(output_path, disk_space::bitrate_size_estimate(bitrate_estimate, duration as f64))?;
+
+    let mut ffmpeg_command = ffmpeg::CommandBuilder::default();
+    ffmpeg_command
+        .add_stdin_input(frame.dimensions(), FRAME_RATE).unwrap()
+        .set_output_video_settings(Some(codec_params.encoder()), codec_params.bitrate(), codec_params.crf())
+        .add_args(codec_params.speed_args())
+        .add_args(codec_params.additional_args())
+        .set_output_file(output_path)
+        .set_overwrite_output_file(true)
+        .set_atomic_output(true);
+
+    let mut ffmpeg_process = ffmpeg_command.build().unwrap().spawn_with_progress(frame_count as u64)?;
+
+    let frame = Arc::new(frame);
+    let mut remaining_frames = frame_count;
+    let send_result = super::send_frames_to_ffmpeg(|| {
+        remaining_frames = remaining_frames.checked_sub(1)?;
+        Some(Ok(frame.clone()))
+    }, &mut ffmpeg_process);
+    ffmpeg_process.wait().await?;
+    send_result?;
+
+    log::info!("calibration overlay video written to {}", output_path.to_string_lossy());
+    Ok(())
+}
@@ -0,0 +1,304 @@
+// Enlarged OSD regions
+//
+// When the OSD is scaled down to fit the video the warnings and other critical items become hard to read. Selected regions
+// of the OSD grid and OSD items can be rendered with tiles larger than the rest of the grid: the tiles of each region are
+// taken out of the normal rendering and blended over the frame, centered on the original location of the region and kept
+// inside the frame.
+
+use std::{fmt::Display, str::FromStr};
+
+use hd_fpv_osd_font_tool::prelude::*;
+use thiserror::Error;
+
+use crate::osd::{
+    self,
+    Region,
+    TileIndices,
+    font_variant::FontVariant,
+    tile_indices::{TileIndex, UnknownOSDItem},
+};
+
+use super::Frame;
+
+
+/// Scale factor of the enlarged regions relative to the rest of the OSD when not specified
+pub const DEFAULT_FACTOR: EnlargementFactor = EnlargementFactor(1.5);
+
+const MAX_FACTOR: f64 = 4.0;
+
+#[derive(Debug, Error)]
+#[error("invalid enlargement factor `{0}`: expected a number greater than 1 and up to {MAX_FACTOR}, e.g. 1.5")]
+pub struct InvalidEnlargementFactor(String);
+
+/// Scale factor of the enlarged regions relative to the tiles of the rest of the OSD
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EnlargementFactor(f64);
+
+impl EnlargementFactor {
+
+    pub fn value(&self) -> f64 {
+        self.0
+    }
+
+    /// Dimensions of the enlarged tiles, rounded to the nearest pixel
+    pub fn tile_dimensions(&self, tile_dimensions: (u32, u32)) -> TileDimensions {
+        let enlarge = |dimension: u32| (dimension as f64 * self.0).round() as u32;
+        TileDimensions { width: enlarge(tile_dimensions.0), height: enlarge(tile_dimensions.1) }
+    }
+
+}
+
+impl FromStr for EnlargementFactor {
+    type Err = InvalidEnlargementFactor;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.trim().parse::<f64>().ok().filter(|factor| *factor > 1.0 && *factor <= MAX_FACTOR)
+            .map(Self).ok_or_else(|| InvalidEnlargementFactor(s.to_owned()))
+    }
+}
+
+impl Display for EnlargementFactor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Regions and items of the OSD rendered with tiles enlarged by a factor
+#[derive(Debug, Clone)]
+pub struct Enlargement {
+    factor: EnlargementFactor,
+    regions: Vec<Region>,
+    items: Vec<String>,
+}
+
+impl Enlargement {
+
+    /// `None` when there is nothing to enlarge
+    pub fn new(factor: EnlargementFactor, regions: &[Region], items: &[String]) -> Option<Self> {
+        if regions.is_empty() && items.is_empty() { return None }
+        Some(Self { factor, regions: regions.to_vec(), items: items.to_vec() })
+    }
+
+    pub fn factor(&self) -> EnlargementFactor {
+        self.factor
+    }
+
+    // fits the regions to the OSD grid, the time ranges are ignored since the regions are enlarged for the whole video
+    fn fitted_to_grid(mut self, grid_dimensions: osd::Dimensions) -> Self {
+        self.regions = self.regions.iter().filter_map(|region| {
+            if region.time_range().is_some() {
+                log::warn!("the time range of the enlarged OSD region {region} is ignored, the region is enlarged for the whole video");
+            }
+            let region = Region::new(region.top_left_corner().clone(), *region.dimensions());
+            match region.check_against_grid(grid_dimensions) {
+                Ok(()) => Some(region),
+                Err(issue) => {
+                    let fitted_region = region.fitted_to_grid(grid_dimensions);
+                    match &fitted_region {
+                        Some(fitted_region) => log::warn!("enlarged OSD region {region} {issue} ({grid_dimensions} tiles), using {fitted_region} instead"),
+                        None => log::warn!("enlarged OSD region {region} {issue} ({grid_dimensions} tiles), ignoring it"),
+                    }
+                    fitted_region
+                },
+            }
+        }).collect();
+        self
+    }
+
+}
+
+fn overlap(region: &Region, other_region: &Region) -> bool {
+    let (bottom_right_corner, other_bottom_right_corner) = (region.bottom_right_corner(), other_region.bottom_right_corner());
+    region.top_left_corner().x() <= other_bottom_right_corner.x() && other_region.top_left_corner().x() <= bottom_right_corner.x()
+        && region.top_left_corner().y() <= other_bottom_right_corner.y() && other_region.top_left_corner().y() <= bottom_right_corner.y()
+}
+
+// smallest region containing both regions
+fn bounding_region(region: &Region, other_region: &Region) -> Region {
+    let (bottom_right_corner, other_bottom_right_corner) = (region.bottom_right_corner(), other_region.bottom_right_corner());
+    let top_left_corner = osd::SignedCoordinates::new(
+        region.top_left_corner().x().min(other_region.top_left_corner().x()),
+        region.top_left_corner().y().min(other_region.top_left_corner().y()),
+    );
+    let dimensions = osd::Dimensions {
+        width: (bottom_right_corner.x().max(other_bottom_right_corner.x()) as i32 - top_left_corner.x() as i32 + 1) as u32,
+        height: (bottom_right_corner.y().max(other_bottom_right_corner.y()) as i32 - top_left_corner.y() as i32 + 1) as u32,
+    };
+    Region::new(top_left_corner, dimensions)
+}
+
+/// Enlargement with the enlarged tile images it is rendered with
+pub struct EnlargedTiles {
+    enlargement: Enlargement,
+    tile_images: Vec<tile::Image>,
+}
+
+/// Tiles of a region taken out of the normal rendering to be drawn enlarged
+pub struct TakenRegion {
+    region: Region,
+    tiles: Vec<(osd::Coordinates, TileIndex)>,
+}
+
+impl EnlargedTiles {
+
+    pub fn new(enlargement: Enlargement, grid_dimensions: osd::Dimensions, tile_images: Vec<tile::Image>) -> Self {
+        let enlargement = enlargement.fitted_to_grid(grid_dimensions);
+        let mut enlarged = enlargement.regions.iter().map(Region::to_string).collect::<Vec<_>>();
+        enlarged.extend(enlargement.items.iter().cloned());
+        log::info!("enlarging OSD regions and items by {}: {}", enlargement.factor, enlarged.join(" "));
+        Self { enlargement, tile_images }
+    }
+
+    /// Removes the tiles of the enlarged regions and items from the tile indices and returns them
+    ///
+    /// The tiles are taken once: a region only gets the tiles which were not already taken by the previous regions and
+    /// a region left without tiles is dropped.
+    pub fn take_regions(&self, tile_indices: &mut TileIndices, font_variant: FontVariant) -> Result<Vec<TakenRegion>, UnknownOSDItem> {
+        let mut regions = self.enlargement.regions.clone();
+        for item_name in &self.enlargement.items {
+            let oild = font_variant.find_osd_item_location_data(item_name)
+                .ok_or_else(|| UnknownOSDItem::new(font_variant, item_name))?;
+            // the regions located from several marker tiles of the same item displayed next to each other overlap, they are
+            // merged so that the item is enlarged once
+            let mut item_regions: Vec<Region> = vec![];
+            for (coordinates, tile_index) in tile_indices.enumerate() {
+                if ! oild.marker_tile_indices().contains(&tile_index) { continue }
+                let mut item_region = oild.region(coordinates);
+                while let Some(position) = item_regions.iter().position(|region| overlap(region, &item_region)) {
+                    item_region = bounding_region(&item_regions.remove(position), &item_region);
+                }
+                item_regions.push(item_region);
+            }
+            regions.extend(item_regions);
+        }
+        Ok(regions.into_iter().filter_map(|region| {
+            let coordinates_range = region.to_coordinates_range();
+            let tiles = tile_indices.enumerate().filter(|(coordinates, _)| coordinates_range.contains(coordinates.clone())).collect::<Vec<_>>();
+            if tiles.is_empty() { return None }
+            tile_indices.erase_region(&region);
+            Some(TakenRegion { region, tiles })
+        }).collect())
+    }
+
+    /// Blends the enlarged tiles of the taken regions over the frame
+    ///
+    /// Each region is centered on its location at the normal tile dimensions and moved back inside the frame if it overflows.
+    pub fn draw(&self, frame: &mut Frame, tile_dimensions: (u32, u32), taken_regions: &[TakenRegion]) {
+        let Some(first_tile_image) = self.tile_images.first() else { return };
+        let (tile_width, tile_height) = (tile_dimensions.0 as i64, tile_dimensions.1 as i64);
+        let (enlarged_tile_width, enlarged_tile_height) = (first_tile_image.width() as i64, first_tile_image.height() as i64);
+        let (frame_width, frame_height) = (frame.dimensions().width as i64, frame.dimensions().height as i64);
+        for TakenRegion { region, tiles } in taken_regions {
+            let (region_x, region_y) = (region.top_left_corner().x() as i64, region.top_left_corner().y() as i64);
+            let (region_width, region_height) = (region.dimensions().width as i64, region.dimensions().height as i64);
+            let place = |start: i64, dimension: i64, enlarged_dimension: i64, frame_dimension: i64| {
+                let centered = start + (dimension - enlarged_dimension) / 2;
+                centered.min(frame_dimension - enlarged_dimension).max(0)
+            };
+            let x = place(region_x * tile_width, region_width * tile_width, region_width * enlarged_tile_width, frame_width);
+            let y = place(region_y * tile_height, region_height * tile_height, region_height * enlarged_tile_height, frame_height);
+            for (coordinates, tile_index) in tiles {
+                let Some(tile_image) = self.tile_images.get(*tile_index as usize) else { continue };
+                let tile_x = x + (coordinates.x as i64 - region_x) * enlarged_tile_width;
+                let tile_y = y + (coordinates.y as i64 - region_y) * enlarged_tile_height;
+                image::imageops::overlay(&mut frame.image, tile_image, tile_x, tile_y);
+            }
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+    use crate::osd::{tile_indices, file::Frame as OSDFileFrame};
+
+    const NATIVE_COLOR: Rgba<u8> = Rgba([255, 0, 0, 255]);
+    const ENLARGED_COLOR: Rgba<u8> = Rgba([0, 0, 255, 255]);
+    const TRANSPARENT: Rgba<u8> = Rgba([0, 0, 0, 0]);
+
+    fn region(x: osd::SignedCoordinate, y: osd::SignedCoordinate, width: u32, height: u32) -> Region {
+        Region::new(osd::SignedCoordinates::new(x, y), osd::Dimensions { width, height })
+    }
+
+    fn tile_indices_with(tiles: &[(osd::Coordinate, osd::Coordinate, TileIndex)]) -> TileIndices {
+        let mut tile_indices = TileIndices::new(vec![0; tile_indices::COUNT]);
+        for (x, y, tile_index) in tiles {
+            tile_indices.set_tile_index_at(*x, *y, *tile_index);
+        }
+        tile_indices
+    }
+
+    fn enlarged_tiles(regions: &[Region], items: &[String], tile_images: Vec<tile::Image>) -> EnlargedTiles {
+        let enlargement = Enlargement::new(EnlargementFactor(2.0), regions, items).unwrap();
+        EnlargedTiles::new(enlargement, tile_indices::DIMENSIONS, tile_images)
+    }
+
+    #[test]
+    fn takes_an_item_displaying_several_marker_tiles_once() {
+        // Ardupilot speed with both of its unit marker tiles displayed after the digits
+        let mut tile_indices = tile_indices_with(&[(10, 3, 0x31), (11, 3, 0x32), (12, 3, 0x33), (13, 3, 0xA1), (14, 3, 0xB0)]);
+        let enlarged_tiles = enlarged_tiles(&[], &["speed".to_owned()], vec![]);
+        let taken_regions = enlarged_tiles.take_regions(&mut tile_indices, FontVariant::Ardupilot).unwrap();
+        assert_eq!(taken_regions.len(), 1);
+        assert_eq!(taken_regions[0].region.to_string(), "9,3:6x1");
+        assert_eq!(taken_regions[0].tiles.iter().map(|(_, tile_index)| *tile_index).collect::<Vec<_>>(), [0x31, 0x32, 0x33, 0xA1, 0xB0]);
+        assert_eq!(tile_indices.enumerate().count(), 0);
+    }
+
+    #[test]
+    fn takes_the_tiles_of_duplicated_regions_once() {
+        let mut tile_indices = tile_indices_with(&[(4, 2, 1), (5, 2, 1), (0, 0, 1)]);
+        let enlarged_tiles = enlarged_tiles(&[region(4, 2, 2, 1), region(4, 2, 2, 1)], &[], vec![]);
+        let taken_regions = enlarged_tiles.take_regions(&mut tile_indices, FontVariant::Generic).unwrap();
+        assert_eq!(taken_regions.len(), 1);
+        assert_eq!(taken_regions[0].tiles.len(), 2);
+        assert_eq!(tile_indices.enumerate().map(|(coordinates, _)| (coordinates.x, coordinates.y)).collect::<Vec<_>>(), [(0, 0)]);
+    }
+
+    #[test]
+    fn rejects_unknown_items() {
+        let enlarged_tiles = enlarged_tiles(&[], &["unknown".to_owned()], vec![]);
+        assert!(enlarged_tiles.take_regions(&mut tile_indices_with(&[]), FontVariant::Ardupilot).is_err());
+    }
+
+    #[test]
+    fn enlarged_region_replaces_the_native_tiles() {
+        let native_tile_images = vec![tile::Image::from_pixel(2, 2, NATIVE_COLOR); 2];
+        // the enlarged tiles are 4x4 with a transparent pixel over the location of the native tile
+        let mut enlarged_tile_image = tile::Image::from_pixel(4, 4, ENLARGED_COLOR);
+        enlarged_tile_image.put_pixel(3, 1, TRANSPARENT);
+        let enlarged_tiles = enlarged_tiles(&[region(4, 2, 2, 1)], &[], vec![enlarged_tile_image; 2]);
+        let osd_file_frame = OSDFileFrame::new(0, tile_indices_with(&[(4, 2, 1), (5, 2, 1), (0, 0, 1)]));
+        let no_items: [&str; 0] = [];
+        let frame = osd_file_frame.draw_overlay_frame(osd::Dimensions { width: 32, height: 16 }, FontVariant::Generic, &native_tile_images, &[], &no_items,
+            Some(&enlarged_tiles)).unwrap();
+
+        // the tile outside of the region is drawn natively
+        assert_eq!(*frame.get_pixel(0, 0), NATIVE_COLOR);
+        // the 2x1 region at 8,4 in pixels is enlarged to 8x4 pixels centered on it, at 6,3
+        for y in 3..7 {
+            for x in 6..14 {
+                let expected_color = if (x, y) == (9, 4) { TRANSPARENT } else { ENLARGED_COLOR };
+                assert_eq!(*frame.get_pixel(x, y), expected_color, "pixel {x},{y}");
+            }
+        }
+        assert_eq!(*frame.get_pixel(5, 3), TRANSPARENT);
+        assert_eq!(*frame.get_pixel(14, 6), TRANSPARENT);
+    }
+
+    #[test]
+    fn enlarged_region_is_kept_inside_the_frame() {
+        let enlarged_tiles = enlarged_tiles(&[region(0, 0, 1, 1)], &[], vec![tile::Image::from_pixel(4, 4, ENLARGED_COLOR); 2]);
+        let mut tile_indices = tile_indices_with(&[(0, 0, 1)]);
+        let taken_regions = enlarged_tiles.take_regions(&mut tile_indices, FontVariant::Generic).unwrap();
+        let mut frame = Frame::new(osd::Dimensions { width: 8, height: 8 });
+        enlarged_tiles.draw(&mut frame, (2, 2), &taken_regions);
+        assert_eq!(*frame.get_pixel(0, 0), ENLARGED_COLOR);
+        assert_eq!(*frame.get_pixel(3, 3), ENLARGED_COLOR);
+        assert_eq!(*frame.get_pixel(4, 4), TRANSPARENT);
+    }
+
+}
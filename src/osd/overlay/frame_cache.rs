@@ -0,0 +1,83 @@
+use std::{collections::{HashMap, VecDeque}, sync::Arc};
+
+use crate::osd::TileIndices;
+
+use super::Frame;
+
+
+// a few recent frames are enough to catch the OSD going back and forth between a small number of states
+const DEFAULT_CAPACITY: usize = 16;
+
+/// Cache of rendered overlay frames keyed by the OSD frame content
+///
+/// The oldest entry is evicted when the cache is full.
+pub struct FrameCache {
+    capacity: usize,
+    frames: HashMap<TileIndices, Arc<Frame>>,
+    insertion_order: VecDeque<TileIndices>,
+    hits: u64,
+    misses: u64,
+}
+
+impl Default for FrameCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl FrameCache {
+
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, frames: HashMap::with_capacity(capacity), insertion_order: VecDeque::with_capacity(capacity), hits: 0, misses: 0 }
+    }
+
+    /// Returns the cached frame for the specified OSD content or renders it with `render` and caches it
+    pub fn get_or_try_insert_with<E, F>(&mut self, tile_indices: &TileIndices, render: F) -> Result<Arc<Frame>, E>
+    where F: FnOnce() -> Result<Frame, E>
+    {
+        if let Some(frame) = self.frames.get(tile_indices) {
+            self.hits += 1;
+            return Ok(frame.clone());
+        }
+        self.misses += 1;
+        Ok(self.store(tile_indices, Arc::new(render()?)))
+    }
+
+    pub fn contains(&self, tile_indices: &TileIndices) -> bool {
+        self.frames.contains_key(tile_indices)
+    }
+
+    /// Caches a frame which has been rendered ahead of being requested, it counts as a miss
+    pub fn insert(&mut self, tile_indices: &TileIndices, frame: Frame) -> Arc<Frame> {
+        self.misses += 1;
+        self.store(tile_indices, Arc::new(frame))
+    }
+
+    fn store(&mut self, tile_indices: &TileIndices, frame: Arc<Frame>) -> Arc<Frame> {
+        if self.capacity > 0 {
+            if self.frames.len() >= self.capacity {
+                if let Some(oldest) = self.insertion_order.pop_front() {
+                    self.frames.remove(&oldest);
+                }
+            }
+            self.frames.insert(tile_indices.clone(), frame.clone());
+            self.insertion_order.push_back(tile_indices.clone());
+        }
+        frame
+    }
+
+    /// Drops all the cached frames, needed when the rendering settings change
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.insertion_order.clear();
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+
+}
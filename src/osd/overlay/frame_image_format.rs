@@ -0,0 +1,143 @@
+use std::path::Path;
+
+use getset::CopyGetters;
+use image::{ImageBuffer, Rgba};
+use thiserror::Error;
+
+use crate::image::{WriteError as ImageWriteError, WriteImageFile};
+
+use super::Frame;
+
+
+/// Image file format of the overlay frames written to a directory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, strum::Display)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[strum(serialize_all = "lowercase")]
+pub enum FrameImageFormat {
+    /// 8-bit PNG
+    #[default]
+    Png,
+    /// 16-bit PNG
+    Png16,
+    /// 8-bit TIFF
+    Tiff,
+    /// 16-bit TIFF
+    Tiff16,
+    /// 32-bit float OpenEXR with linear color values
+    Exr,
+}
+
+/// How the color values of the overlay frame pixels relate to their alpha value
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, strum::Display)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[strum(serialize_all = "lowercase")]
+pub enum AlphaMode {
+    /// color values independent of the alpha value
+    #[default]
+    Straight,
+    /// color values multiplied by the alpha value
+    Premultiplied,
+}
+
+impl FrameImageFormat {
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Png | Self::Png16 => "png",
+            Self::Tiff | Self::Tiff16 => "tiff",
+            Self::Exr => "exr",
+        }
+    }
+
+    /// Alpha mode used when none is requested, the one specified by the format or expected by most tools
+    pub fn default_alpha_mode(&self) -> AlphaMode {
+        match self {
+            Self::Exr => AlphaMode::Premultiplied,
+            _ => AlphaMode::Straight,
+        }
+    }
+
+    /// Whether the format can store pixels with the specified alpha mode, PNG only stores straight alpha and OpenEXR premultiplied alpha
+    pub fn supports_alpha_mode(&self, alpha_mode: AlphaMode) -> bool {
+        match self {
+            Self::Png | Self::Png16 => alpha_mode == AlphaMode::Straight,
+            Self::Exr => alpha_mode == AlphaMode::Premultiplied,
+            Self::Tiff | Self::Tiff16 => true,
+        }
+    }
+
+}
+
+#[derive(Debug, Error)]
+#[error("the {image_format} image format does not support {alpha_mode} alpha")]
+pub struct UnsupportedAlphaMode {
+    image_format: FrameImageFormat,
+    alpha_mode: AlphaMode,
+}
+
+/// Image format and alpha mode of the overlay frame files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, CopyGetters)]
+#[getset(get_copy = "pub")]
+pub struct FrameFileFormat {
+    image_format: FrameImageFormat,
+    alpha_mode: AlphaMode,
+}
+
+impl FrameFileFormat {
+
+    /// The default alpha mode of the image format is used if `alpha_mode` is not specified
+    pub fn new(image_format: FrameImageFormat, alpha_mode: Option<AlphaMode>) -> Result<Self, UnsupportedAlphaMode> {
+        let alpha_mode = alpha_mode.unwrap_or_else(|| image_format.default_alpha_mode());
+        if ! image_format.supports_alpha_mode(alpha_mode) { return Err(UnsupportedAlphaMode { image_format, alpha_mode }) }
+        Ok(Self { image_format, alpha_mode })
+    }
+
+    /// Writes the frame converted to the bit depth and alpha mode of the format, the path needs the extension of the image format
+    pub fn write_frame<P: AsRef<Path>>(&self, frame: &Frame, path: P) -> Result<(), ImageWriteError> {
+        let premultiplied = self.alpha_mode == AlphaMode::Premultiplied;
+        match self.image_format {
+            FrameImageFormat::Png | FrameImageFormat::Tiff if premultiplied => premultiplied_8_bit_image(frame).write_image_file(path),
+            FrameImageFormat::Png | FrameImageFormat::Tiff => frame.write_image_file(path),
+            FrameImageFormat::Png16 | FrameImageFormat::Tiff16 => image_16_bit(frame, premultiplied).write_image_file(path),
+            FrameImageFormat::Exr => linear_premultiplied_float_image(frame).write_image_file(path),
+        }
+    }
+
+}
+
+fn premultiplied_8_bit_image(frame: &Frame) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    ImageBuffer::from_fn(frame.width(), frame.height(), |x, y| {
+        let [red, green, blue, alpha] = frame.get_pixel(x, y).0;
+        let premultiply = |value: u8| ((value as u16 * alpha as u16 + 127) / 255) as u8;
+        Rgba([premultiply(red), premultiply(green), premultiply(blue), alpha])
+    })
+}
+
+fn image_16_bit(frame: &Frame, premultiplied: bool) -> ImageBuffer<Rgba<u16>, Vec<u16>> {
+    ImageBuffer::from_fn(frame.width(), frame.height(), |x, y| {
+        let [red, green, blue, alpha] = frame.get_pixel(x, y).0;
+        // computed from the 8-bit values in one step to avoid losing the precision gained from the larger range
+        let convert = |value: u8| match premultiplied {
+            true => ((value as u32 * alpha as u32 * 65535 + 255 * 255 / 2) / (255 * 255)) as u16,
+            false => value as u16 * 257,
+        };
+        Rgba([convert(red), convert(green), convert(blue), alpha as u16 * 257])
+    })
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    match value {
+        value if value <= 0.04045 => value / 12.92,
+        value => ((value + 0.055) / 1.055).powf(2.4),
+    }
+}
+
+// OpenEXR stores linear light values with premultiplied alpha
+fn linear_premultiplied_float_image(frame: &Frame) -> ImageBuffer<Rgba<f32>, Vec<f32>> {
+    ImageBuffer::from_fn(frame.width(), frame.height(), |x, y| {
+        let [red, green, blue, alpha] = frame.get_pixel(x, y).0;
+        let alpha = alpha as f32 / 255.0;
+        let convert = |value: u8| srgb_to_linear(value as f32 / 255.0) * alpha;
+        Rgba([convert(red), convert(green), convert(blue), alpha])
+    })
+}
@@ -0,0 +1,57 @@
+use std::{io::Error as IOError, path::Path};
+
+
+/// How the frames repeating the previous OSD frame are written when saving overlay frames to a directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::Display)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[strum(serialize_all = "lowercase")]
+pub enum FrameLinkMode {
+    /// symbolic links to the previous frame file, creating symbolic links on Windows requires developer mode or admin rights
+    Symlink,
+    /// hard links to the previous frame file, needs a file system supporting hard links (not FAT)
+    Hardlink,
+    /// full copies of the previous frame file, uses much more disk space but works everywhere
+    Copy,
+    /// only the unique frames are written, named after their content, and `manifest.txt` lists the frame file of each video frame
+    Manifest,
+}
+
+impl Default for FrameLinkMode {
+    fn default() -> Self {
+        if cfg!(unix) { Self::Symlink } else { Self::Hardlink }
+    }
+}
+
+impl FrameLinkMode {
+
+    /// Whether the target file needs to exist when the link is created
+    pub fn needs_existing_target(&self) -> bool {
+        ! matches!(self, Self::Symlink)
+    }
+
+    /// Whether the frames are content addressed and listed in a manifest instead of being named after their index
+    pub fn writes_manifest(&self) -> bool {
+        matches!(self, Self::Manifest)
+    }
+
+    pub fn link<P: AsRef<Path>, Q: AsRef<Path>>(&self, target_path: P, link_path: Q) -> Result<(), IOError> {
+        match self {
+            Self::Symlink => symlink(target_path, link_path),
+            Self::Hardlink => fs_err::hard_link(target_path, link_path),
+            Self::Copy => fs_err::copy(target_path, link_path).map(|_| ()),
+            // the repeated frames are only listed in the manifest
+            Self::Manifest => Ok(()),
+        }
+    }
+
+}
+
+#[cfg(unix)]
+fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(target_path: P, link_path: Q) -> Result<(), IOError> {
+    fs_err::os::unix::fs::symlink(target_path, link_path)
+}
+
+#[cfg(windows)]
+fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(target_path: P, link_path: Q) -> Result<(), IOError> {
+    fs_err::os::windows::fs::symlink_file(target_path, link_path)
+}
@@ -0,0 +1,82 @@
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex, mpsc::{self, Receiver, SyncSender}},
+    thread::JoinHandle,
+};
+
+use indicatif::ProgressBar;
+
+use crate::image::WriteError as ImageWriteError;
+
+use super::{Frame, frame_image_format::FrameFileFormat};
+
+
+/// Number of threads encoding and writing frame files
+pub const DEFAULT_WRITER_COUNT: usize = 4;
+
+// frames waiting to be written per writer thread, bounds the memory used when the target is slower than rendering
+const QUEUED_FRAMES_PER_WRITER: usize = 4;
+
+pub struct WriteJob {
+    frame: Frame,
+    path: PathBuf,
+}
+
+impl WriteJob {
+    pub fn new(frame: Frame, path: PathBuf) -> Self {
+        Self { frame, path }
+    }
+}
+
+pub type FrameSender = SyncSender<WriteJob>;
+
+/// Pool of threads encoding and writing overlay frames so that slow target storage does not stall the rendering
+pub struct FrameWriterPool {
+    sender: FrameSender,
+    workers: Vec<JoinHandle<Result<(), ImageWriteError>>>,
+}
+
+impl FrameWriterPool {
+
+    /// `progress_bar` is incremented each time a frame file has been written
+    pub fn new(writer_count: usize, frame_file_format: FrameFileFormat, progress_bar: ProgressBar) -> Self {
+        let writer_count = writer_count.max(1);
+        let (sender, receiver) = mpsc::sync_channel(writer_count * QUEUED_FRAMES_PER_WRITER);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = (0..writer_count).map(|_| {
+            let receiver = receiver.clone();
+            let progress_bar = progress_bar.clone();
+            std::thread::spawn(move || Self::writer_loop(&receiver, frame_file_format, &progress_bar))
+        }).collect();
+        Self { sender, workers }
+    }
+
+    fn writer_loop(receiver: &Mutex<Receiver<WriteJob>>, frame_file_format: FrameFileFormat, progress_bar: &ProgressBar) -> Result<(), ImageWriteError> {
+        loop {
+            // the lock is released as soon as a job has been received so that the other writers can receive the next one
+            let job = match receiver.lock().unwrap().recv() {
+                Ok(job) => job,
+                Err(_) => return Ok(()),
+            };
+            frame_file_format.write_frame(&job.frame, &job.path)?;
+            progress_bar.inc(1);
+        }
+    }
+
+    /// Returns a sender to queue frames for writing, blocks when the queue is full
+    pub fn sender(&self) -> FrameSender {
+        self.sender.clone()
+    }
+
+    /// Waits for all the queued frames to be written and returns the first write error if any
+    pub fn finish(self) -> Result<(), ImageWriteError> {
+        drop(self.sender);
+        let mut result = Ok(());
+        for worker in self.workers {
+            let worker_result = worker.join().expect("frame writer thread panicked");
+            if result.is_ok() { result = worker_result }
+        }
+        result
+    }
+
+}
@@ -0,0 +1,164 @@
+use std::{
+    collections::{HashSet, hash_map::DefaultHasher},
+    hash::Hasher,
+    io::{self, BufRead, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use derive_more::Deref;
+use getset::{CopyGetters, Getters};
+use path_absolutize::Absolutize;
+use thiserror::Error;
+
+use crate::{osd::OSD_TIME_BASE_HZ, video::FrameIndex as VideoFrameIndex};
+
+use super::{Frame, frame_image_format::FrameImageFormat};
+
+
+pub const MANIFEST_FILE_NAME: &str = "manifest.txt";
+const MANIFEST_HEADER: &str = "# overlay frames manifest: <first video frame index> <last video frame index> <frame file name>";
+
+/// File name of an overlay frame derived from its content so that identical frames are written only once
+pub fn content_addressed_file_name(frame: &Frame, image_format: FrameImageFormat) -> String {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(frame.as_raw());
+    format!("{:016x}.{}", hasher.finish(), image_format.extension())
+}
+
+#[derive(Debug, Error)]
+pub enum ManifestReadError {
+    #[error("failed to read overlay frames manifest: {0}")]
+    IOError(#[from] io::Error),
+    #[error("invalid overlay frames manifest line {line_number}: {line}")]
+    InvalidLine { line_number: usize, line: String },
+}
+
+/// Range of video frames on which the same overlay frame file is displayed
+#[derive(Debug, Clone, PartialEq, Eq, CopyGetters, Getters)]
+pub struct ManifestEntry {
+    #[getset(get_copy = "pub")]
+    first_frame_index: VideoFrameIndex,
+    #[getset(get_copy = "pub")]
+    last_frame_index: VideoFrameIndex,
+    #[getset(get = "pub")]
+    file_name: String,
+}
+
+impl ManifestEntry {
+
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.splitn(3, ' ');
+        let first_frame_index = fields.next()?.parse().ok()?;
+        let last_frame_index = fields.next()?.parse().ok()?;
+        let file_name = fields.next().filter(|file_name| ! file_name.is_empty())?.to_owned();
+        (first_frame_index <= last_frame_index).then_some(Self { first_frame_index, last_frame_index, file_name })
+    }
+
+}
+
+/// Lists which overlay frame file is displayed on each video frame of a directory of content addressed frames
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deref)]
+pub struct FramesManifest(Vec<ManifestEntry>);
+
+impl FramesManifest {
+
+    /// Builds the manifest from the file name of each video frame, consecutive frames using the same file are grouped
+    pub fn from_frame_file_names<S: AsRef<str>>(file_names: impl IntoIterator<Item = S>) -> Self {
+        let mut entries: Vec<ManifestEntry> = vec![];
+        for (frame_index, file_name) in file_names.into_iter().enumerate() {
+            let frame_index = frame_index as VideoFrameIndex;
+            match entries.last_mut() {
+                Some(entry) if entry.file_name == file_name.as_ref() => entry.last_frame_index = frame_index,
+                _ => entries.push(ManifestEntry { first_frame_index: frame_index, last_frame_index: frame_index, file_name: file_name.as_ref().to_owned() }),
+            }
+        }
+        Self(entries)
+    }
+
+    pub fn path<P: AsRef<Path>>(frames_dir: P) -> PathBuf {
+        frames_dir.as_ref().join(MANIFEST_FILE_NAME)
+    }
+
+    pub fn exists_in<P: AsRef<Path>>(frames_dir: P) -> bool {
+        Self::path(frames_dir).is_file()
+    }
+
+    pub fn write_to_dir<P: AsRef<Path>>(&self, frames_dir: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(fs_err::File::create(Self::path(frames_dir))?);
+        writeln!(writer, "{MANIFEST_HEADER}")?;
+        for entry in &self.0 {
+            writeln!(writer, "{} {} {}", entry.first_frame_index, entry.last_frame_index, entry.file_name)?;
+        }
+        writer.flush()
+    }
+
+    pub fn read_from_dir<P: AsRef<Path>>(frames_dir: P) -> Result<Self, ManifestReadError> {
+        let reader = io::BufReader::new(fs_err::File::open(Self::path(frames_dir))?);
+        let mut entries = vec![];
+        for (line_index, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.is_empty() || line.starts_with('#') { continue }
+            let entry = ManifestEntry::parse(&line).ok_or_else(|| ManifestReadError::InvalidLine { line_number: line_index + 1, line: line.clone() })?;
+            entries.push(entry);
+        }
+        Ok(Self(entries))
+    }
+
+    pub fn file_name_at(&self, frame_index: VideoFrameIndex) -> Option<&str> {
+        self.0.iter().find(|entry| (entry.first_frame_index..=entry.last_frame_index).contains(&frame_index)).map(|entry| entry.file_name.as_str())
+    }
+
+    /// Writes an FFMpeg concat demuxer script displaying the frames with a 60Hz time base starting at `first_frame_index`
+    pub fn write_concat_script<P: AsRef<Path>, Q: AsRef<Path>>(&self, frames_dir: P, first_frame_index: VideoFrameIndex, script_path: Q) -> io::Result<()> {
+        let frames_dir = frames_dir.as_ref().absolutize()?;
+        let quoted_path = |file_name: &str| frames_dir.join(file_name).to_string_lossy().replace('\'', r"'\''");
+        let mut writer = BufWriter::new(fs_err::File::create(script_path.as_ref())?);
+        writeln!(writer, "ffconcat version 1.0")?;
+        let mut last_file_name = None;
+        for entry in self.0.iter().filter(|entry| entry.last_frame_index >= first_frame_index) {
+            let frame_count = entry.last_frame_index - entry.first_frame_index.max(first_frame_index) + 1;
+            writeln!(writer, "file '{}'", quoted_path(&entry.file_name))?;
+            writeln!(writer, "duration {:.6}", frame_count as f64 / OSD_TIME_BASE_HZ)?;
+            last_file_name = Some(&entry.file_name);
+        }
+        // the duration of the last file is ignored by the concat demuxer unless the file is listed again
+        if let Some(last_file_name) = last_file_name {
+            writeln!(writer, "file '{}'", quoted_path(last_file_name))?;
+        }
+        writer.flush()
+    }
+
+}
+
+/// Collects the file name of each frame while the frames are rendered in parallel
+pub struct ManifestBuilder {
+    file_names: Vec<Option<String>>,
+    written_file_names: HashSet<String>,
+}
+
+impl ManifestBuilder {
+
+    pub fn new(frame_count: usize) -> Self {
+        Self { file_names: vec![None; frame_count], written_file_names: HashSet::new() }
+    }
+
+    /// Records the file of a rendered frame, returns whether the file still needs to be written
+    pub fn add_frame(&mut self, rel_index: VideoFrameIndex, file_name: &str) -> bool {
+        self.file_names[rel_index as usize] = Some(file_name.to_owned());
+        self.written_file_names.insert(file_name.to_owned())
+    }
+
+    /// Records a frame repeating a previous frame, the repeats have to be recorded in increasing frame index order
+    pub fn repeat_frame(&mut self, prev_rel_index: VideoFrameIndex, rel_index: VideoFrameIndex) {
+        self.file_names[rel_index as usize] = self.file_names[prev_rel_index as usize].clone();
+    }
+
+    pub fn written_file_count(&self) -> usize {
+        self.written_file_names.len()
+    }
+
+    pub fn build(self) -> FramesManifest {
+        FramesManifest::from_frame_file_names(self.file_names.into_iter().map(Option::unwrap_or_default))
+    }
+
+}
@@ -0,0 +1,92 @@
+#[cfg(feature = "cli")]
+use clap::Args;
+use getset::CopyGetters;
+
+use crate::video::Resolution;
+
+
+/// Position of the overlay along one axis of the video
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AxisPlacement {
+    #[default]
+    Centered,
+    /// distance in pixels between the start of the video (left/top) and the start of the overlay
+    Start(u32),
+    /// distance in pixels between the end of the overlay and the end of the video (right/bottom)
+    End(u32),
+}
+
+impl AxisPlacement {
+
+    // FFMpeg overlay filter expression of the position, `video_size` and `overlay_size` are the filter variables of the axis (W/w or H/h)
+    fn ffmpeg_expression(&self, video_size: char, overlay_size: char) -> String {
+        match self {
+            Self::Centered => format!("({video_size}-{overlay_size})/2"),
+            Self::Start(margin) => margin.to_string(),
+            Self::End(margin) => format!("{video_size}-{overlay_size}-{margin}"),
+        }
+    }
+
+}
+
+#[cfg(feature = "cli")]
+#[derive(Args, CopyGetters)]
+#[getset(get_copy = "pub")]
+pub struct OSDMarginArgs {
+
+    /// place the OSD this many pixels from the left edge of the video instead of centering it or using the OSD file offset
+    #[clap(long, value_parser, value_name = "pixels", conflicts_with = "osd_margin_right")]
+    osd_margin_left: Option<u32>,
+
+    /// place the OSD this many pixels from the right edge of the video instead of centering it or using the OSD file offset
+    #[clap(long, value_parser, value_name = "pixels")]
+    osd_margin_right: Option<u32>,
+
+    /// place the OSD this many pixels from the top edge of the video instead of centering it or using the OSD file offset
+    #[clap(long, value_parser, value_name = "pixels", conflicts_with = "osd_margin_bottom")]
+    osd_margin_top: Option<u32>,
+
+    /// place the OSD this many pixels from the bottom edge of the video instead of centering it or using the OSD file offset
+    #[clap(long, value_parser, value_name = "pixels")]
+    osd_margin_bottom: Option<u32>,
+
+}
+
+/// Position of the OSD overlay on the video, centered by default
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, CopyGetters)]
+#[getset(get_copy = "pub")]
+pub struct Placement {
+    horizontal: AxisPlacement,
+    vertical: AxisPlacement,
+}
+
+impl Placement {
+
+    /// Placement from the video offset stored in a DJI OSD file header
+    ///
+    /// The offset is the position of the OSD in the recorded video for the native OSD resolution, it is scaled along
+    /// with the overlay. A zero offset means that the OSD is centered.
+    pub fn from_osd_file_offset(offset: (u16, u16), native_overlay_resolution: Resolution, overlay_resolution: Resolution) -> Self {
+        if offset == (0, 0) { return Self::default() }
+        let scale = |value: u16, overlay_size: u32, native_size: u32| (value as u64 * overlay_size as u64 / native_size.max(1) as u64) as u32;
+        Self {
+            horizontal: AxisPlacement::Start(scale(offset.0, overlay_resolution.width, native_overlay_resolution.width)),
+            vertical: AxisPlacement::Start(scale(offset.1, overlay_resolution.height, native_overlay_resolution.height)),
+        }
+    }
+
+    /// Overrides the placement along the axes for which a margin is specified
+    #[cfg(feature = "cli")]
+    pub fn with_margins(mut self, margins: &OSDMarginArgs) -> Self {
+        let axis_placement = |start: Option<u32>, end: Option<u32>| start.map(AxisPlacement::Start).or(end.map(AxisPlacement::End));
+        if let Some(horizontal) = axis_placement(margins.osd_margin_left, margins.osd_margin_right) { self.horizontal = horizontal }
+        if let Some(vertical) = axis_placement(margins.osd_margin_top, margins.osd_margin_bottom) { self.vertical = vertical }
+        self
+    }
+
+    /// Position options of the FFMpeg overlay filter
+    pub fn ffmpeg_overlay_position(&self) -> String {
+        format!("x={}:y={}", self.horizontal.ffmpeg_expression('W', 'w'), self.vertical.ffmpeg_expression('H', 'h'))
+    }
+
+}
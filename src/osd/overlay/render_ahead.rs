@@ -0,0 +1,107 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::{Arc, mpsc::{self, Receiver}},
+};
+
+use hd_fpv_osd_font_tool::prelude::*;
+
+use crate::osd::{
+    Region,
+    TileIndices,
+    file::Frame as OSDFileFrame,
+    font_variant::FontVariant,
+    tile_indices::UnknownOSDItem,
+};
+
+use super::{Dimensions, Frame, enlargement::EnlargedTiles, stick::StickOverlay};
+
+
+// frames rendered ahead per render thread, enough to keep all the threads busy while the oldest frame is being written
+const FRAMES_AHEAD_PER_THREAD: usize = 2;
+
+/// Everything needed to render overlay frames on the threads of a render pool
+pub struct RenderContext {
+    pub frame_dimensions: Dimensions,
+    pub font_variant: FontVariant,
+    pub tile_images: Arc<Vec<tile::Image>>,
+    pub hidden_items: Vec<String>,
+    pub enlarged_tiles: Option<Arc<EnlargedTiles>>,
+    pub stick_overlay: Option<StickOverlay>,
+}
+
+impl RenderContext {
+
+    pub fn render(&self, osd_file_frame: &OSDFileFrame, hidden_regions: &[Region]) -> Result<Frame, UnknownOSDItem> {
+        super::render_overlay_frame(osd_file_frame, self.frame_dimensions, self.font_variant, &self.tile_images, hidden_regions,
+                                    &self.hidden_items, self.enlarged_tiles.as_deref(), self.stick_overlay.as_ref())
+    }
+
+}
+
+pub type RenderResult = Receiver<Result<Frame, UnknownOSDItem>>;
+
+/// Overlay frame planned by the frames iterator, in video frame order
+pub struct PlannedFrame<'a> {
+    /// OSD frame to draw, `None` when the previous overlay frame is repeated
+    pub osd_file_frame: Option<&'a OSDFileFrame>,
+    pub hidden_regions: Arc<Vec<Region>>,
+    /// whether the hidden regions changed, the frames cached before this one cannot be reused
+    pub clears_cache: bool,
+    /// result of the rendering started on the render pool, `None` if the frame is to be taken from the cache
+    pub rendered: Option<RenderResult>,
+}
+
+/// Queue of planned frames whose rendering is started on a thread pool before they are requested
+pub struct RenderAhead<'a> {
+    pool: Arc<rayon::ThreadPool>,
+    context: Arc<RenderContext>,
+    queue: VecDeque<PlannedFrame<'a>>,
+    rendering: HashSet<TileIndices>,
+    capacity: usize,
+}
+
+impl<'a> RenderAhead<'a> {
+
+    pub fn new(pool: Arc<rayon::ThreadPool>, context: RenderContext) -> Self {
+        let capacity = pool.current_num_threads() * FRAMES_AHEAD_PER_THREAD;
+        Self { pool, context: Arc::new(context), queue: VecDeque::with_capacity(capacity), rendering: HashSet::new(), capacity }
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.queue.len() >= self.capacity
+    }
+
+    /// Whether a queued frame clears the cache when it is requested, the frames currently cached cannot be used until then
+    pub fn cache_clear_pending(&self) -> bool {
+        self.queue.iter().any(|planned_frame| planned_frame.clears_cache)
+    }
+
+    /// Queues a planned frame, starting its rendering unless `is_cached` or the same OSD frame is already being rendered
+    pub fn push(&mut self, mut planned_frame: PlannedFrame<'a>, is_cached: bool) {
+        // the frames being rendered use the previous hidden regions
+        if planned_frame.clears_cache { self.rendering.clear() }
+        if let Some(osd_file_frame) = planned_frame.osd_file_frame {
+            if ! is_cached && self.rendering.insert(osd_file_frame.tile_indices().clone()) {
+                let (sender, receiver) = mpsc::channel();
+                let context = self.context.clone();
+                let osd_file_frame = osd_file_frame.clone();
+                let hidden_regions = planned_frame.hidden_regions.clone();
+                self.pool.spawn(move || {
+                    // the receiver is gone if the iterator has been dropped
+                    let _ = sender.send(context.render(&osd_file_frame, &hidden_regions));
+                });
+                planned_frame.rendered = Some(receiver);
+            }
+        }
+        self.queue.push_back(planned_frame);
+    }
+
+    pub fn pop(&mut self) -> Option<PlannedFrame<'a>> {
+        let planned_frame = self.queue.pop_front()?;
+        if let (Some(osd_file_frame), Some(_)) = (planned_frame.osd_file_frame, &planned_frame.rendered) {
+            self.rendering.remove(osd_file_frame.tile_indices());
+        }
+        Some(planned_frame)
+    }
+
+}
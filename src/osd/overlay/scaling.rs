@@ -1,23 +1,27 @@
 
+#[cfg(feature = "cli")]
 use std::path::Path;
 
+#[cfg(feature = "cli")]
 use clap::Args;
 use derive_more::From;
+#[cfg(feature = "cli")]
 use getset::{CopyGetters, Getters};
 use thiserror::Error;
 
 use super::margins::Margins;
 
+use crate::osd::tile_resize::ScalingFilter;
+
 use crate::video::{
     resolution::{
         Resolution as VideoResolution,
         TargetResolution,
     },
-    probe::{
-        probe as video_probe,
-        Error as VideoProbeError,
-    }
+    probe::Error as VideoProbeError,
 };
+#[cfg(feature = "cli")]
+use crate::video::probe::probe as video_probe;
 
 #[derive(Debug, Clone, Copy)]
 pub enum Scaling {
@@ -27,11 +31,13 @@ pub enum Scaling {
     Yes {
         target_resolution: TargetResolution,
         min_margins: Margins,
+        filter: ScalingFilter,
     },
     Auto {
         target_resolution: TargetResolution,
         min_margins: Margins,
         min_resolution: VideoResolution,
+        filter: ScalingFilter,
     }
 }
 
@@ -49,6 +55,7 @@ pub enum ScalingArgsError {
     VideoProbeError(VideoProbeError),
 }
 
+#[cfg(feature = "cli")]
 #[derive(Args, Getters, CopyGetters)]
 #[getset(get_copy = "pub")]
 pub struct ScalingArgs {
@@ -72,8 +79,13 @@ pub struct ScalingArgs {
     /// minimum percentage of OSD coverage under which scaling will be used if --scaling/--no-scaling options are not provided
     #[clap(long, value_parser = clap::value_parser!(u8).range(1..=100), value_name = "percent", default_value = "90")]
     min_coverage: u8,
+
+    /// filter used to resize the tiles when scaling, `nearest` only applies to integer scale factors
+    #[clap(long, value_parser, value_name = "filter", default_value_t)]
+    scaling_filter: ScalingFilter,
 }
 
+#[cfg(feature = "cli")]
 #[derive(Args, CopyGetters)]
 #[getset(get_copy = "pub")]
 pub struct OSDScalingArgs {
@@ -93,10 +105,23 @@ pub struct OSDScalingArgs {
     /// minimum percentage of OSD coverage under which scaling will be used if --scaling/--no-scaling options are not provided
     #[clap(long, value_parser = clap::value_parser!(u8).range(1..=100), value_name = "percent", default_value = "90")]
     min_osd_coverage: u8,
+
+    /// filter used to resize the OSD tiles when scaling, `nearest` only applies to integer scale factors
+    #[clap(long, value_parser, value_name = "filter", default_value_t)]
+    osd_scaling_filter: ScalingFilter,
 }
 
 impl Scaling {
 
+    /// Filter used to resize the tiles, `None` when scaling is disabled
+    pub fn filter(&self) -> Option<ScalingFilter> {
+        match self {
+            Self::No { .. } => None,
+            Self::Yes { filter, .. } | Self::Auto { filter, .. } => Some(*filter),
+        }
+    }
+
+    #[cfg(feature = "cli")]
     pub fn try_from_scaling_args<P: AsRef<Path>>(args: &ScalingArgs, target_video_file: &Option<P>) -> Result<Self, ScalingArgsError> {
         let target_resolution = match (args.target_resolution, target_video_file) {
             (Some(target_resolution), None) => Some(target_resolution),
@@ -112,7 +137,7 @@ impl Scaling {
             (true, true) => return Err(ScalingArgsError::IncompatibleArguments),
             (true, false) => {
                 let target_resolution = target_resolution.ok_or(ScalingArgsError::NeedTargetVideoResolution)?;
-                Scaling::Yes { target_resolution, min_margins: args.min_margins }
+                Scaling::Yes { target_resolution, min_margins: args.min_margins, filter: args.scaling_filter }
             },
             (false, true) => Scaling::No { target_resolution },
             (false, false) => {
@@ -123,7 +148,7 @@ impl Scaling {
                         (target_resolution.dimensions().width as f64 * min_coverage) as u32,
                         (target_resolution.dimensions().height as f64 * min_coverage) as u32
                     );
-                    Scaling::Auto { target_resolution, min_margins: args.min_margins, min_resolution }
+                    Scaling::Auto { target_resolution, min_margins: args.min_margins, min_resolution, filter: args.scaling_filter }
                     },
                     None => Scaling::No { target_resolution }
                 }
@@ -131,10 +156,12 @@ impl Scaling {
         })
     }
 
+    #[cfg(feature = "cli")]
     pub fn try_from_osd_args(args: &OSDScalingArgs, video_resolution: VideoResolution) -> Result<Self, ScalingArgsError> {
         Ok(match (args.osd_scaling, args.no_osd_scaling) {
             (true, true) => return Err(ScalingArgsError::IncompatibleArguments),
-            (true, false) => Scaling::Yes { target_resolution: TargetResolution::Custom(video_resolution), min_margins: args.min_osd_margins },
+            (true, false) => Scaling::Yes { target_resolution: TargetResolution::Custom(video_resolution), min_margins: args.min_osd_margins,
+                filter: args.osd_scaling_filter },
             (false, true) => Scaling::No { target_resolution: Some(TargetResolution::Custom(video_resolution)) },
             (false, false) => {
                 let target_resolution = TargetResolution::Custom(video_resolution);
@@ -143,7 +170,7 @@ impl Scaling {
                     (target_resolution.dimensions().width as f64 * min_coverage) as u32,
                     (target_resolution.dimensions().height as f64 * min_coverage) as u32
                 );
-                Scaling::Auto { target_resolution, min_margins: args.min_osd_margins, min_resolution }
+                Scaling::Auto { target_resolution, min_margins: args.min_osd_margins, min_resolution, filter: args.osd_scaling_filter }
             },
         })
     }
@@ -0,0 +1,184 @@
+use getset::CopyGetters;
+use image::Rgba;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::osd::{self, FontVariant, TileIndices};
+
+use super::{Frame, Dimensions};
+
+
+const BACKGROUND_COLOR: Rgba<u8> = Rgba([0, 0, 0, 96]);
+const OUTLINE_COLOR: Rgba<u8> = Rgba([255, 255, 255, 192]);
+const CROSSHAIR_COLOR: Rgba<u8> = Rgba([255, 255, 255, 64]);
+const STICK_COLOR: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+// number of tiles read on each RC channel value row
+const CHANNEL_ROW_WIDTH: u8 = 10;
+
+/// Where the sticks widget is placed on the overlay
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::Display)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[strum(serialize_all = "kebab-case")]
+pub enum StickOverlayPreset {
+    /// both sticks next to each other at the bottom center of the overlay
+    BottomCenter,
+    /// left stick in the bottom left corner and right stick in the bottom right corner
+    BottomCorners,
+    /// both sticks next to each other at the top center of the overlay
+    TopCenter,
+}
+
+/// Which channels are driven by which stick
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::Display)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[strum(serialize_all = "kebab-case")]
+pub enum StickMode {
+    /// left stick: yaw/pitch, right stick: roll/throttle
+    Mode1,
+    /// left stick: yaw/throttle, right stick: roll/pitch
+    Mode2,
+}
+
+/// Stick positions normalized to the -1.0..=1.0 range, channels in AETR order
+#[derive(Debug, Clone, Copy, Default, PartialEq, CopyGetters)]
+#[getset(get_copy = "pub")]
+pub struct SticksPosition {
+    roll: f32,
+    pitch: f32,
+    throttle: f32,
+    yaw: f32,
+}
+
+impl SticksPosition {
+
+    /// Decodes the RC channel values displayed by the OSD
+    ///
+    /// The OSD is expected to display one channel per row starting at `channels_position` in AETR order.
+    /// Values can either be PWM values (1000-2000) or percentages (-100-100).
+    pub fn decode(tile_indices: &TileIndices, font_variant: FontVariant, channels_position: &osd::Coordinates) -> Option<Self> {
+        let channel = |index: u8| {
+            let text = tile_indices.read_text(font_variant, channels_position.x, channels_position.y.saturating_add(index), CHANNEL_ROW_WIDTH);
+            parse_channel_value(&text)
+        };
+        Some(Self {
+            roll: channel(0)?,
+            pitch: channel(1)?,
+            throttle: channel(2)?,
+            yaw: channel(3)?,
+        })
+    }
+
+    fn sticks(&self, mode: StickMode) -> ((f32, f32), (f32, f32)) {
+        match mode {
+            StickMode::Mode1 => ((self.yaw, self.pitch), (self.roll, self.throttle)),
+            StickMode::Mode2 => ((self.yaw, self.throttle), (self.roll, self.pitch)),
+        }
+    }
+
+}
+
+fn parse_channel_value(text: &str) -> Option<f32> {
+    lazy_static! {
+        static ref VALUE_RE: Regex = Regex::new(r"(-?\d{1,4})\s*%?\s*\z").unwrap();
+    }
+    let value: i32 = VALUE_RE.captures(text.trim_end())?.get(1).unwrap().as_str().parse().ok()?;
+    let normalized = match value {
+        800..=2200 => (value - 1500) as f32 / 500.0,
+        -100..=100 => value as f32 / 100.0,
+        _ => return None,
+    };
+    Some(normalized.clamp(-1.0, 1.0))
+}
+
+/// Virtual sticks widget rendered as an extra layer over the OSD from the RC channel values displayed by the OSD
+#[derive(Debug, Clone, CopyGetters)]
+#[getset(get_copy = "pub")]
+pub struct StickOverlay {
+    preset: StickOverlayPreset,
+    mode: StickMode,
+    /// size of each stick box as a percentage of the overlay height
+    size_percent: u8,
+    #[getset(skip)]
+    channels_position: osd::Coordinates,
+}
+
+impl StickOverlay {
+
+    pub fn new(preset: StickOverlayPreset, mode: StickMode, size_percent: u8, channels_position: osd::Coordinates) -> Self {
+        Self { preset, mode, size_percent, channels_position }
+    }
+
+    pub fn channels_position(&self) -> &osd::Coordinates {
+        &self.channels_position
+    }
+
+    fn box_size(&self, frame_dimensions: Dimensions) -> u32 {
+        (frame_dimensions.height * self.size_percent as u32 / 100).max(8)
+    }
+
+    /// returns the top left corners of the left and right stick boxes
+    fn boxes_origins(&self, frame_dimensions: Dimensions) -> ((u32, u32), (u32, u32)) {
+        let size = self.box_size(frame_dimensions);
+        let margin = size / 4;
+        let bottom_y = frame_dimensions.height.saturating_sub(size + margin);
+        let center_left_x = (frame_dimensions.width / 2).saturating_sub(size + margin / 2);
+        let center_right_x = frame_dimensions.width / 2 + margin / 2;
+        match self.preset {
+            StickOverlayPreset::BottomCenter => ((center_left_x, bottom_y), (center_right_x, bottom_y)),
+            StickOverlayPreset::BottomCorners =>
+                ((margin, bottom_y), (frame_dimensions.width.saturating_sub(size + margin), bottom_y)),
+            StickOverlayPreset::TopCenter => ((center_left_x, margin), (center_right_x, margin)),
+        }
+    }
+
+    pub fn draw(&self, frame: &mut Frame, tile_indices: &TileIndices, font_variant: FontVariant) {
+        let Some(sticks_position) = SticksPosition::decode(tile_indices, font_variant, &self.channels_position) else {
+            return;
+        };
+        let size = self.box_size(frame.dimensions());
+        let (left_stick, right_stick) = sticks_position.sticks(self.mode);
+        let (left_origin, right_origin) = self.boxes_origins(frame.dimensions());
+        draw_stick_box(frame, left_origin, size, left_stick);
+        draw_stick_box(frame, right_origin, size, right_stick);
+    }
+
+}
+
+fn draw_stick_box(frame: &mut Frame, (x, y): (u32, u32), size: u32, (stick_x, stick_y): (f32, f32)) {
+    let (width, height) = (frame.image.width(), frame.image.height());
+    let mut put_pixel = |px: u32, py: u32, color: Rgba<u8>| {
+        if px < width && py < height {
+            frame.image.put_pixel(px, py, color);
+        }
+    };
+
+    let half = size / 2;
+    for dy in 0..size {
+        for dx in 0..size {
+            let color = if dx == 0 || dy == 0 || dx == size - 1 || dy == size - 1 {
+                OUTLINE_COLOR
+            } else if dx == half || dy == half {
+                CROSSHAIR_COLOR
+            } else {
+                BACKGROUND_COLOR
+            };
+            put_pixel(x + dx, y + dy, color);
+        }
+    }
+
+    let radius = (size / 16).max(2) as i32;
+    let travel = (half as i32 - radius - 1) as f32;
+    let center_x = x as i32 + half as i32 + (stick_x * travel).round() as i32;
+    let center_y = y as i32 + half as i32 - (stick_y * travel).round() as i32;
+    for dy in -radius..=radius {
+        for dx in -radius..=radius {
+            if dx * dx + dy * dy <= radius * radius {
+                let (px, py) = (center_x + dx, center_y + dy);
+                if px >= 0 && py >= 0 {
+                    put_pixel(px as u32, py as u32, STICK_COLOR);
+                }
+            }
+        }
+    }
+}
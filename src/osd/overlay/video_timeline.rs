@@ -0,0 +1,379 @@
+use std::{fmt::Display, str::FromStr, sync::Arc};
+
+use thiserror::Error;
+
+use crate::{ffmpeg, osd::OSD_TIME_BASE_HZ};
+
+use super::{Frame, FramesIter, Generator, SendFramesToFFMpegError, UnknownOSDItem};
+
+
+/// Position of the start of the OSD recording in the video, negative if the OSD recording started before the video
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct OSDVideoOffset(f64);
+
+impl OSDVideoOffset {
+
+    pub fn from_secs(secs: f64) -> Self {
+        Self(secs)
+    }
+
+    /// Offset equivalent to an OSD frame shift
+    pub fn from_osd_frame_shift(frame_shift: OSDFrameShift) -> Self {
+        Self(frame_shift.frames() / OSD_TIME_BASE_HZ)
+    }
+
+    pub fn as_secs(&self) -> f64 {
+        self.0
+    }
+
+}
+
+#[derive(Debug, Error)]
+#[error("invalid OSD video offset: {0}: the format is <seconds>[s] or <OSD frames>f, fractional values are accepted")]
+pub struct InvalidOSDVideoOffset(String);
+
+impl FromStr for OSDVideoOffset {
+    type Err = InvalidOSDVideoOffset;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |value_s: &str| value_s.parse::<f64>().ok().filter(|value| value.is_finite()).ok_or_else(|| InvalidOSDVideoOffset(s.to_owned()));
+        match s.strip_suffix('f') {
+            Some(frames_s) => Ok(Self(parse(frames_s)? / OSD_TIME_BASE_HZ)),
+            None => Ok(Self(parse(s.strip_suffix('s').unwrap_or(s))?)),
+        }
+    }
+}
+
+/// Shift of the OSD frames relative to the video in OSD frames (60Hz time base), possibly fractional
+///
+/// The video timelines place the OSD with a sub-frame precision, the iterators sending the OSD frames 1:1 with the
+/// video frames use the nearest whole frame shift.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct OSDFrameShift(f64);
+
+impl OSDFrameShift {
+
+    pub fn from_frames(frames: i32) -> Self {
+        Self(frames as f64)
+    }
+
+    pub fn frames(&self) -> f64 {
+        self.0
+    }
+
+    /// Nearest whole frame shift
+    pub fn nearest_frames(&self) -> i32 {
+        self.0.round() as i32
+    }
+
+    pub fn is_whole(&self) -> bool {
+        self.0.fract() == 0.0
+    }
+
+}
+
+impl Display for OSDFrameShift {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("invalid OSD frame shift: {0}: the format is <OSD frames>[f], <seconds>s or [HH:]MM:SS[.fraction] with an optional sign, fractional values are accepted")]
+pub struct InvalidOSDFrameShift(String);
+
+impl FromStr for OSDFrameShift {
+    type Err = InvalidOSDFrameShift;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || InvalidOSDFrameShift(s.to_owned());
+        let parse = |value_s: &str| value_s.parse::<f64>().ok().filter(|value| value.is_finite()).ok_or_else(error);
+        if let Some(secs_s) = s.strip_suffix('s') { return Ok(Self(parse(secs_s)? * OSD_TIME_BASE_HZ)) }
+        if ! s.contains(':') { return Ok(Self(parse(s.strip_suffix('f').unwrap_or(s))?)) }
+
+        // [+-][HH:]MM:SS[.fraction]
+        let (sign, timestamp_s) = match s.strip_prefix('-') {
+            Some(timestamp_s) => (-1.0, timestamp_s),
+            None => (1.0, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let parts = timestamp_s.split(':').collect::<Vec<_>>();
+        if parts.len() > 3 { return Err(error()) }
+        let (seconds_s, hours_minutes) = parts.split_last().unwrap();
+        let seconds = parse(seconds_s).ok().filter(|seconds| (0.0..60.0).contains(seconds)).ok_or_else(error)?;
+        let mut secs = 0.0;
+        for (index, part) in hours_minutes.iter().enumerate() {
+            let value = part.parse::<u32>().map_err(|_| error())?;
+            // the minutes need to be less than 60 when the hours are specified
+            if index == 1 && value >= 60 { return Err(error()) }
+            secs = secs * 60.0 + value as f64;
+        }
+        Ok(Self(sign * (secs * 60.0 + seconds) * OSD_TIME_BASE_HZ))
+    }
+}
+
+/// Largest OSD time base correction accepted, the OSD clock drift is much smaller than this
+const MAX_TIMEBASE_CORRECTION_DEVIATION: f64 = 0.02;
+
+/// Videos shorter than this do not allow estimating the OSD clock drift: the OSD and video recordings not stopping
+/// at the same time would be taken for drift
+const MIN_DRIFT_ESTIMATION_DURATION_SECS: f64 = 120.0;
+
+/// Factor correcting the drift of the OSD timestamps relative to the video: the OSD frame displayed at a time `t`
+/// after the start of the OSD recording is the one with the timestamp `t * factor`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OSDTimebaseCorrection(f64);
+
+impl OSDTimebaseCorrection {
+
+    pub const NONE: Self = Self(1.0);
+
+    pub fn factor(&self) -> f64 {
+        self.0
+    }
+
+    /// Estimates the correction from the duration of the OSD recording and the duration of the video it covers,
+    /// `None` if the video is too short or if the durations differ too much for the difference to be caused by drift
+    pub fn estimate(osd_duration_secs: f64, video_duration_secs: f64) -> Option<Self> {
+        if video_duration_secs < MIN_DRIFT_ESTIMATION_DURATION_SECS { return None }
+        let factor = osd_duration_secs / video_duration_secs;
+        ((factor - 1.0).abs() <= MAX_TIMEBASE_CORRECTION_DEVIATION).then_some(Self(factor))
+    }
+
+}
+
+#[derive(Debug, Error)]
+#[error("invalid OSD timebase correction: {0}: expected a factor between {min} and {max}",
+    min = 1.0 - MAX_TIMEBASE_CORRECTION_DEVIATION, max = 1.0 + MAX_TIMEBASE_CORRECTION_DEVIATION)]
+pub struct InvalidOSDTimebaseCorrection(String);
+
+impl FromStr for OSDTimebaseCorrection {
+    type Err = InvalidOSDTimebaseCorrection;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse::<f64>() {
+            Ok(factor) if (factor - 1.0).abs() <= MAX_TIMEBASE_CORRECTION_DEVIATION => Ok(Self(factor)),
+            _ => Err(InvalidOSDTimebaseCorrection(s.to_owned())),
+        }
+    }
+}
+
+/// Speed of a video relative to the OSD recording, e.g. `0.25` for footage exported in slow motion at 25% speed
+///
+/// The OSD frame timeline is stretched by the inverse of the factor so that the OSD stays in sync with the footage,
+/// the OSD frames being repeated when the video is slowed down.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OSDSpeed(f64);
+
+impl OSDSpeed {
+
+    pub const NORMAL: Self = Self(1.0);
+
+    pub fn factor(&self) -> f64 {
+        self.0
+    }
+
+    /// Index of the frame of the video at this speed showing the specified frame of the OSD recording time base
+    pub fn video_frame_index(&self, osd_frame_index: u32) -> u32 {
+        (osd_frame_index as f64 / self.0).round() as u32
+    }
+
+}
+
+#[derive(Debug, Error)]
+#[error("invalid OSD speed: {0}: expected a positive factor, e.g. `0.25` for a video slowed down to 25%")]
+pub struct InvalidOSDSpeed(String);
+
+impl FromStr for OSDSpeed {
+    type Err = InvalidOSDSpeed;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse::<f64>() {
+            Ok(factor) if factor.is_finite() && factor > 0.0 => Ok(Self(factor)),
+            _ => Err(InvalidOSDSpeed(s.to_owned())),
+        }
+    }
+}
+
+/// Timeline of a video onto which the OSD is mapped, used when the video frame rate is not the OSD 60Hz time base
+/// or when the OSD has to be placed with a sub-frame precision, e.g. when burning the OSD onto footage from an external camera
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VideoTimeline {
+    frame_rate_numerator: i32,
+    frame_rate_denominator: i32,
+    osd_offset: OSDVideoOffset,
+    speed: f64,
+    osd_timebase_correction: OSDTimebaseCorrection,
+}
+
+impl VideoTimeline {
+
+    pub fn new(frame_rate_numerator: i32, frame_rate_denominator: i32, osd_offset: OSDVideoOffset) -> Self {
+        Self { frame_rate_numerator, frame_rate_denominator, osd_offset, speed: 1.0, osd_timebase_correction: OSDTimebaseCorrection::NONE }
+    }
+
+    /// Sets the correction of the OSD timestamps drift, the OSD frames are then remapped proportionally to their time
+    pub fn set_osd_timebase_correction(&mut self, osd_timebase_correction: OSDTimebaseCorrection) -> &mut Self {
+        self.osd_timebase_correction = osd_timebase_correction;
+        self
+    }
+
+    /// Sets the speed factor of the video, the video frames are then indexed in the sped up video timeline
+    pub fn set_speed(&mut self, speed: f64) -> &mut Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Index of the OSD frame (60Hz time base) displayed on the specified video frame, none before the OSD recording started
+    pub fn osd_frame_index(&self, video_frame_index: u32) -> Option<u32> {
+        let video_time = video_frame_index as f64 * self.speed * self.frame_rate_denominator as f64 / self.frame_rate_numerator as f64;
+        let osd_time = (video_time - self.osd_offset.as_secs()) * self.osd_timebase_correction.factor();
+        let osd_frame_index = (osd_time * OSD_TIME_BASE_HZ + 1e-6).floor();
+        (osd_frame_index >= 0.0).then_some(osd_frame_index as u32)
+    }
+
+    /// Time in the video timeline, in seconds, at which the OSD frame with the specified timestamp is displayed
+    pub fn video_time(&self, osd_time: f64) -> f64 {
+        (osd_time / self.osd_timebase_correction.factor() + self.osd_offset.as_secs()) / self.speed
+    }
+
+}
+
+/// Iterator over the overlay frames of each frame of a video timeline
+///
+/// The OSD frames are iterated over with their 60Hz time base and the ones displayed on each video frame are picked.
+pub struct VideoTimelineFramesIter<'a> {
+    osd_frames_iter: FramesIter<'a>,
+    // index of the next frame to be returned by `osd_frames_iter`
+    next_osd_frame_index: u32,
+    current_frame: Arc<Frame>,
+    timeline: VideoTimeline,
+    video_frame_index: u32,
+    last_video_frame: u32,
+}
+
+impl<'a> Generator<'a> {
+
+    pub fn iter_for_video_timeline(&self, first_video_frame: u32, last_video_frame: u32, timeline: VideoTimeline) -> VideoTimelineFramesIter {
+        let last_osd_frame = timeline.osd_frame_index(last_video_frame).unwrap_or_default();
+        VideoTimelineFramesIter {
+            osd_frames_iter: self.iter_advanced(0, Some(last_osd_frame), 0),
+            next_osd_frame_index: 0,
+            current_frame: Arc::new(Frame::new(self.frame_dimensions)),
+            timeline,
+            video_frame_index: first_video_frame,
+            last_video_frame,
+        }
+    }
+
+}
+
+impl<'a> VideoTimelineFramesIter<'a> {
+
+    pub fn next_shared(&mut self) -> Option<Result<Arc<Frame>, UnknownOSDItem>> {
+        if self.video_frame_index > self.last_video_frame { return None }
+        let osd_frame_index = self.timeline.osd_frame_index(self.video_frame_index);
+        self.video_frame_index += 1;
+        let Some(osd_frame_index) = osd_frame_index else {
+            return Some(Ok(self.current_frame.clone()))
+        };
+        while self.next_osd_frame_index <= osd_frame_index {
+            match self.osd_frames_iter.next_shared() {
+                Some(Ok(frame)) => self.current_frame = frame,
+                Some(Err(error)) => return Some(Err(error)),
+                // past the end of the OSD the last frame stays displayed
+                None => break,
+            }
+            self.next_osd_frame_index += 1;
+        }
+        Some(Ok(self.current_frame.clone()))
+    }
+
+    pub async fn send_frames_to_ffmpeg_and_wait(mut self, mut ffmpeg_process: ffmpeg::Process) -> Result<(), SendFramesToFFMpegError> {
+        let send_result = super::send_frames_to_ffmpeg(|| self.next_shared(), &mut ffmpeg_process);
+
+        ffmpeg_process.wait().await?;
+        send_result?;
+
+        Ok(())
+    }
+
+}
+
+impl<'a> Iterator for VideoTimelineFramesIter<'a> {
+    type Item = Result<Frame, UnknownOSDItem>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.next_shared()?.map(|frame| Frame::clone(&frame)))
+    }
+}
+
+impl<'a> ExactSizeIterator for VideoTimelineFramesIter<'a> {
+    fn len(&self) -> usize {
+        (self.last_video_frame + 1).saturating_sub(self.video_frame_index) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn osd_speed_timeline(speed: &str, frame_shift: i32) -> VideoTimeline {
+        let mut video_timeline = VideoTimeline::new(OSD_TIME_BASE_HZ as i32, 1, OSDVideoOffset::from_osd_frame_shift(OSDFrameShift::from_frames(frame_shift)));
+        video_timeline.set_speed(speed.parse::<OSDSpeed>().unwrap().factor());
+        video_timeline
+    }
+
+    #[test]
+    fn parses_positive_osd_speeds() {
+        assert_eq!("0.25".parse::<OSDSpeed>().unwrap(), OSDSpeed(0.25));
+        assert_eq!("1".parse::<OSDSpeed>().unwrap(), OSDSpeed::NORMAL);
+        assert_eq!("2.5".parse::<OSDSpeed>().unwrap(), OSDSpeed(2.5));
+    }
+
+    #[test]
+    fn rejects_invalid_osd_speeds() {
+        for speed_s in ["0", "0.0", "-0.5", "-1", "inf", "NaN", "", "fast", "0.5x"] {
+            assert!(speed_s.parse::<OSDSpeed>().is_err(), "{speed_s}");
+        }
+    }
+
+    #[test]
+    fn maps_osd_frames_to_video_frames_at_osd_speed() {
+        assert_eq!(OSDSpeed::NORMAL.video_frame_index(10), 10);
+        assert_eq!(OSDSpeed(0.25).video_frame_index(10), 40);
+        assert_eq!(OSDSpeed(2.0).video_frame_index(10), 5);
+        assert_eq!(OSDSpeed(2.0).video_frame_index(11), 6);
+    }
+
+    #[test]
+    fn maps_video_frames_to_osd_frames_at_speed() {
+        let slowed_down = osd_speed_timeline("0.25", 0);
+        assert_eq!(slowed_down.osd_frame_index(0), Some(0));
+        assert_eq!(slowed_down.osd_frame_index(3), Some(0));
+        assert_eq!(slowed_down.osd_frame_index(4), Some(1));
+        assert_eq!(slowed_down.osd_frame_index(40), Some(10));
+
+        let sped_up = osd_speed_timeline("2", 0);
+        assert_eq!(sped_up.osd_frame_index(5), Some(10));
+        assert_eq!(sped_up.osd_frame_index(6), Some(12));
+    }
+
+    #[test]
+    fn maps_video_frames_to_osd_frames_at_speed_with_frame_shift() {
+        let slowed_down = osd_speed_timeline("0.25", 10);
+        assert_eq!(slowed_down.osd_frame_index(39), None);
+        assert_eq!(slowed_down.osd_frame_index(40), Some(0));
+        assert_eq!(slowed_down.osd_frame_index(44), Some(1));
+        assert!((slowed_down.video_time(1.0) - (1.0 + 10.0 / OSD_TIME_BASE_HZ) / 0.25).abs() < 1e-9);
+
+        let sped_up = osd_speed_timeline("2", 10);
+        assert_eq!(sped_up.osd_frame_index(4), None);
+        assert_eq!(sped_up.osd_frame_index(5), Some(0));
+        assert_eq!(sped_up.osd_frame_index(10), Some(10));
+
+        let shifted_back = osd_speed_timeline("0.5", -10);
+        assert_eq!(shifted_back.osd_frame_index(0), Some(10));
+        assert_eq!(shifted_back.osd_frame_index(2), Some(11));
+    }
+
+}
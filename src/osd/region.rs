@@ -1,5 +1,5 @@
 
-use std::str::FromStr;
+use std::{str::FromStr, fmt::Display};
 
 use derive_more::From;
 use getset::Getters;
@@ -7,19 +7,99 @@ use crate::prelude::*;
 use thiserror::Error;
 
 use crate::osd;
+use crate::video::{FrameIndex as VideoFrameIndex, Timestamp};
 
 
+/// Video time interval, either bound can be left open
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TimeRange {
+    start: Option<Timestamp>,
+    end: Option<Timestamp>,
+}
+
+impl TimeRange {
+
+    pub fn new(start: Option<Timestamp>, end: Option<Timestamp>) -> Self {
+        Self { start, end }
+    }
+
+    /// Whether the specified video frame (60Hz OSD time base) is inside the interval, the end bound being exclusive
+    pub fn contains(&self, video_frame_index: VideoFrameIndex) -> bool {
+        self.start.map_or(true, |start| video_frame_index >= start.overlay_frame_count())
+            && self.end.map_or(true, |end| video_frame_index < end.overlay_frame_count())
+    }
+
+    /// FFMpeg timeline expression enabling a filter during the interval, none when both bounds are open
+    ///
+    /// The end bound is excluded like in [`Self::contains`], the commas are escaped for the expression to be used in a filtergraph.
+    /// `input_start_secs` is the time of the input at which the filtered stream starts, e.g. the --start of the transcode: the
+    /// timestamps of an input read from a start time begin at 0 so the bounds are shifted by it.
+    pub fn ffmpeg_enable_expression(&self, input_start_secs: u32) -> Option<String> {
+        let stream_secs = |timestamp: Timestamp| timestamp.total_seconds() as i64 - input_start_secs as i64;
+        match (self.start, self.end) {
+            (Some(start), Some(end)) => Some(format!("gte(t\\,{})*lt(t\\,{})", stream_secs(start), stream_secs(end))),
+            (Some(start), None) => Some(format!("gte(t\\,{})", stream_secs(start))),
+            (None, Some(end)) => Some(format!("lt(t\\,{})", stream_secs(end))),
+            (None, None) => None,
+        }
+    }
+
+}
+
+impl Display for TimeRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(start) = self.start { write!(f, "{start}")? }
+        f.write_str("-")?;
+        if let Some(end) = self.end { write!(f, "{end}")? }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("invalid time range: {0}: the format is [<start>]-[<end>] with timestamps formatted as [HH:]MM:SS")]
+pub struct InvalidTimeRange(String);
+
+impl FromStr for TimeRange {
+    type Err = InvalidTimeRange;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidTimeRange(s.to_owned());
+        let (start_s, end_s) = s.split_once('-').ok_or_else(invalid)?;
+        let parse_bound = |bound_s: &str| match bound_s {
+            "" => Ok(None),
+            bound_s => Timestamp::from_str(bound_s).map(Some).map_err(|_| invalid()),
+        };
+        let time_range = Self::new(parse_bound(start_s)?, parse_bound(end_s)?);
+        if let (Some(start), Some(end)) = (time_range.start, time_range.end) {
+            if start >= end { return Err(invalid()) }
+        }
+        Ok(time_range)
+    }
+}
+
 #[derive(Debug, Clone, Getters)]
 #[getset(get = "pub")]
 pub struct Region {
     top_left_corner: osd::SignedCoordinates,
     dimensions: osd::Dimensions,
+    /// the region is only hidden during this interval when specified
+    time_range: Option<TimeRange>,
 }
 
 impl Region {
 
     pub fn new(top_left_corner: osd::SignedCoordinates, dimensions: osd::Dimensions) -> Self {
-        Self { top_left_corner, dimensions }
+        Self { top_left_corner, dimensions, time_range: None }
+    }
+
+    pub fn with_time_range(mut self, time_range: Option<TimeRange>) -> Self {
+        self.time_range = time_range;
+        self
+    }
+
+    /// Whether the region applies to the specified video frame
+    pub fn is_active_at(&self, video_frame_index: VideoFrameIndex) -> bool {
+        self.time_range.map_or(true, |time_range| time_range.contains(video_frame_index))
     }
 
     pub fn bottom_right_corner(&self) -> osd::SignedCoordinates {
@@ -35,6 +115,102 @@ impl Region {
 
 }
 
+impl Display for Region {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(time_range) = &self.time_range { write!(f, "{time_range}@")? }
+        write!(f, "{},{}:{}x{}", self.top_left_corner.x(), self.top_left_corner.y(), self.dimensions.width, self.dimensions.height)
+    }
+}
+
+/// Problem found when checking a region against the OSD grid
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::Display)]
+pub enum GridIssue {
+    #[strum(serialize = "is outside the OSD grid but fits with x and y swapped")]
+    SwappedCoordinates,
+    #[strum(serialize = "extends beyond the OSD grid")]
+    ExceedsGrid,
+    #[strum(serialize = "is outside the OSD grid")]
+    OutsideGrid,
+}
+
+#[derive(Debug, Error)]
+#[error("OSD region {region} {issue} ({grid_dimensions} tiles)")]
+pub struct InvalidRegionError {
+    region: Region,
+    issue: GridIssue,
+    grid_dimensions: osd::Dimensions,
+}
+
+impl Region {
+
+    fn origin_in_grid(x: osd::SignedCoordinate, y: osd::SignedCoordinate, grid_dimensions: osd::Dimensions) -> bool {
+        x >= 0 && y >= 0 && (x as u32) < grid_dimensions.width && (y as u32) < grid_dimensions.height
+    }
+
+    /// Checks that the region is inside the OSD grid with the specified dimensions
+    pub fn check_against_grid(&self, grid_dimensions: osd::Dimensions) -> Result<(), GridIssue> {
+        let (x, y) = (self.top_left_corner.x(), self.top_left_corner.y());
+        if ! Self::origin_in_grid(x, y, grid_dimensions) {
+            return Err(if Self::origin_in_grid(y, x, grid_dimensions) { GridIssue::SwappedCoordinates } else { GridIssue::OutsideGrid });
+        }
+        if x as u32 + self.dimensions.width > grid_dimensions.width || y as u32 + self.dimensions.height > grid_dimensions.height {
+            return Err(GridIssue::ExceedsGrid);
+        }
+        Ok(())
+    }
+
+    /// Returns the region corrected to fit the OSD grid: coordinates swapped back if needed and size clamped to the grid,
+    /// none if the region cannot be corrected
+    pub fn fitted_to_grid(&self, grid_dimensions: osd::Dimensions) -> Option<Self> {
+        let (mut x, mut y) = (self.top_left_corner.x(), self.top_left_corner.y());
+        if ! Self::origin_in_grid(x, y, grid_dimensions) {
+            if ! Self::origin_in_grid(y, x, grid_dimensions) { return None }
+            (x, y) = (y, x);
+        }
+        let dimensions = osd::Dimensions::new(
+            self.dimensions.width.min(grid_dimensions.width - x as u32),
+            self.dimensions.height.min(grid_dimensions.height - y as u32),
+        );
+        Some(Self::new(osd::SignedCoordinates::new(x, y), dimensions).with_time_range(self.time_range))
+    }
+
+}
+
+/// Returns which regions apply to the specified video frame
+pub fn active_regions_mask(regions: &[Region], video_frame_index: VideoFrameIndex) -> Vec<bool> {
+    regions.iter().map(|region| region.is_active_at(video_frame_index)).collect()
+}
+
+/// Returns the regions applying to the specified video frame
+pub fn active_regions(regions: &[Region], video_frame_index: VideoFrameIndex) -> Vec<Region> {
+    regions.iter().filter(|region| region.is_active_at(video_frame_index)).cloned().collect()
+}
+
+/// Checks the regions against the OSD grid and logs the regions which are going to be used
+///
+/// In strict mode an error is returned for the first region not fitting the grid otherwise the regions are corrected
+/// with a warning, regions which cannot be corrected are ignored.
+pub fn check_regions(regions: &[Region], grid_dimensions: osd::Dimensions, strict: bool) -> Result<Vec<Region>, InvalidRegionError> {
+    let mut checked_regions = Vec::with_capacity(regions.len());
+    for region in regions {
+        match region.check_against_grid(grid_dimensions) {
+            Ok(()) => checked_regions.push(region.clone()),
+            Err(issue) if strict => return Err(InvalidRegionError { region: region.clone(), issue, grid_dimensions }),
+            Err(issue) => match region.fitted_to_grid(grid_dimensions) {
+                Some(fitted_region) => {
+                    log::warn!("OSD region {region} {issue} ({grid_dimensions} tiles), using {fitted_region} instead");
+                    checked_regions.push(fitted_region);
+                },
+                None => log::warn!("OSD region {region} {issue} ({grid_dimensions} tiles), ignoring it"),
+            },
+        }
+    }
+    if ! checked_regions.is_empty() {
+        log::info!("hiding OSD regions: {}", checked_regions.iter().map(Region::to_string).collect::<Vec<_>>().join(" "));
+    }
+    Ok(checked_regions)
+}
+
 #[derive(Debug, Error)]
 #[error("invalid OSD region format: {value}: {error}")]
 pub enum FormatError {
@@ -53,14 +229,21 @@ pub enum InvalidRegionString {
     #[error(transparent)]
     FormatError(FormatError),
     #[error("invalid dimensions: {0}: dimension component cannot be 0")]
-    InvalidDimensionValue(String)
+    InvalidDimensionValue(String),
+    #[error(transparent)]
+    InvalidTimeRange(InvalidTimeRange),
 }
 
 impl FromStr for Region {
     type Err = InvalidRegionString;
 
+    /// Parses `[<time range>@]<left_x>,<top_y>[:<width>x<height>]`
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(match s.split_once(':') {
+        let (time_range, s) = match s.split_once('@') {
+            Some((time_range_s, region_s)) => (Some(TimeRange::from_str(time_range_s)?), region_s),
+            None => (None, s),
+        };
+        let region = match s.split_once(':') {
 
             Some((origin_s, dimensions_s)) => {
                 let origin = osd::Coordinates::from_str(origin_s)
@@ -70,21 +253,16 @@ impl FromStr for Region {
                 if dimensions.width == 0 || dimensions.height == 0 {
                     return Err(InvalidRegionString::InvalidDimensionValue(dimensions_s.to_owned()));
                 }
-                Region {
-                    top_left_corner: osd::SignedCoordinates::from(origin),
-                    dimensions
-                }
+                Region::new(osd::SignedCoordinates::from(origin), dimensions)
             },
 
             None => {
                 let origin = osd::Coordinates::from_str(s)
                     .map_err(|error| FormatError::Origin { value: s.to_owned(), error })?;
-                Region {
-                    top_left_corner: osd::SignedCoordinates::from(origin),
-                    dimensions: osd::Dimensions::new(1, 1),
-                }
+                Region::new(osd::SignedCoordinates::from(origin), osd::Dimensions::new(1, 1))
             },
 
-        })
+        };
+        Ok(region.with_time_range(time_range))
     }
 }
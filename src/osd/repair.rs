@@ -0,0 +1,183 @@
+use std::{io, path::{Path, PathBuf}};
+
+use derive_more::From;
+use getset::CopyGetters;
+use thiserror::Error;
+
+use super::{
+    dji::file::{WriteError as DJIWriteError, Writer as DJIWriter},
+    file::{self, Frame, GenericReader, OpenError, ReadError},
+    font_dir::{MAX_PAGE_COUNT, PAGE_TILE_COUNT},
+};
+
+
+const MAX_TILE_INDEX_COUNT: usize = MAX_PAGE_COUNT * PAGE_TILE_COUNT;
+
+#[derive(Debug, Error, From)]
+pub enum RepairOSDFileError {
+    #[error(transparent)]
+    OpenError(OpenError),
+    #[error("OSD file read error: {0}")]
+    OSDFileReadError(ReadError),
+    #[error("only DJI OSD files can be repaired")]
+    UnsupportedOSDFileFormat,
+    #[error("no valid frame found in the OSD file")]
+    NoValidFrame,
+    #[error("output OSD file exists: {0}")]
+    OutputFileExists(PathBuf),
+    #[error("failed to write OSD file: {0}")]
+    WriteError(DJIWriteError),
+}
+
+/// Number of frames of each kind of damage found in the repaired file
+#[derive(Debug, Clone, Copy, Default, CopyGetters)]
+#[getset(get_copy = "pub")]
+pub struct RepairSummary {
+    kept_frames: usize,
+    /// whether the last frame of the file was incomplete
+    truncated: bool,
+    invalid_tile_index_frames: usize,
+    frame_index_jump_frames: usize,
+    dropped_duplicate_frames: usize,
+    renumbered_duplicate_frames: usize,
+}
+
+fn is_truncation(error: &ReadError) -> bool {
+    match error {
+        ReadError::UnexpectedEOF { .. } => true,
+        ReadError::FileError(error) => error.kind() == io::ErrorKind::UnexpectedEof,
+        ReadError::NoFrames => false,
+    }
+}
+
+// whether a frame can follow a frame with the index `previous_frame_index` without being a duplicate or a frame index jump
+fn follows(previous_frame_index: u32, frame_index: u32, max_frame_gap: u32) -> bool {
+    frame_index > previous_frame_index && frame_index - previous_frame_index <= max_frame_gap
+}
+
+// position of the first frame of the longest run of frames following each other, the frames of a damaged file are
+// checked against this run rather than against the first frame which may be corrupted itself
+fn anchor_position(frames: &[Frame], max_frame_gap: u32) -> usize {
+    let mut anchor_run = (0, 0);
+    let mut run_start = 0;
+    for position in 1..=frames.len() {
+        let run_continues = position < frames.len() && follows(frames[position - 1].index(), frames[position].index(), max_frame_gap);
+        if ! run_continues {
+            if position - run_start > anchor_run.1 { anchor_run = (run_start, position - run_start) }
+            run_start = position;
+        }
+    }
+    anchor_run.0
+}
+
+// checks the frames preceding the anchor frame backwards, returns the ones kept in file order
+fn repair_leading_frames(frames: Vec<Frame>, anchor_frame_index: u32, max_frame_gap: u32, renumber_duplicates: bool, summary: &mut RepairSummary) -> Vec<Frame> {
+    let mut repaired_frames: Vec<Frame> = Vec::with_capacity(frames.len());
+    let mut next_frame_index = anchor_frame_index;
+    for frame in frames.into_iter().rev() {
+        let frame = if follows(frame.index(), next_frame_index, max_frame_gap) {
+            frame
+        } else if frame.index() < next_frame_index || frame.index() - next_frame_index > max_frame_gap {
+            summary.frame_index_jump_frames += 1;
+            continue;
+        } else if renumber_duplicates && next_frame_index > 0 {
+            summary.renumbered_duplicate_frames += 1;
+            Frame::new(next_frame_index - 1, frame.tile_indices().clone())
+        } else {
+            summary.dropped_duplicate_frames += 1;
+            continue;
+        };
+        next_frame_index = frame.index();
+        repaired_frames.push(frame);
+    }
+    repaired_frames.reverse();
+    repaired_frames
+}
+
+/// Filters the frames read from a damaged OSD file
+///
+/// Frames using tile indices no font can have are dropped. The remaining frames are checked against the longest run of
+/// frames following each other so that a corrupted first frame does not get every other frame dropped: frames whose
+/// index is further than `max_frame_gap` from the index of their neighbour in the run are dropped. Frames whose index
+/// is out of order but close to the one of their neighbour are dropped or, if `renumber_duplicates` is set, get the
+/// index next to the one of their neighbour.
+pub fn repair_frames(frames: Vec<Frame>, max_frame_gap: u32, renumber_duplicates: bool, summary: &mut RepairSummary) -> Vec<Frame> {
+    let frames = frames.into_iter().filter(|frame| {
+        let valid = frame.tile_indices().iter().all(|tile_index| (*tile_index as usize) < MAX_TILE_INDEX_COUNT);
+        if ! valid { summary.invalid_tile_index_frames += 1 }
+        valid
+    }).collect::<Vec<_>>();
+    let anchor_position = anchor_position(&frames, max_frame_gap);
+    let mut frames = frames.into_iter();
+    let leading_frames = frames.by_ref().take(anchor_position).collect::<Vec<_>>();
+    let anchor_frame = match frames.next() {
+        Some(anchor_frame) => anchor_frame,
+        None => return vec![],
+    };
+
+    let mut repaired_frames = repair_leading_frames(leading_frames, anchor_frame.index(), max_frame_gap, renumber_duplicates, summary);
+    let mut previous_frame_index = anchor_frame.index();
+    repaired_frames.push(anchor_frame);
+    for frame in frames {
+        if frame.index() <= previous_frame_index {
+            if renumber_duplicates {
+                summary.renumbered_duplicate_frames += 1;
+                previous_frame_index += 1;
+                repaired_frames.push(Frame::new(previous_frame_index, frame.tile_indices().clone()));
+            } else {
+                summary.dropped_duplicate_frames += 1;
+            }
+        } else if frame.index() - previous_frame_index > max_frame_gap {
+            summary.frame_index_jump_frames += 1;
+        } else {
+            previous_frame_index = frame.index();
+            repaired_frames.push(frame);
+        }
+    }
+    summary.kept_frames = repaired_frames.len();
+    repaired_frames
+}
+
+/// Writes a cleaned copy of a DJI OSD file damaged by a crash or a corrupted recording
+///
+/// The incomplete frame at the end of a truncated file is discarded and the frames are filtered with `repair_frames`.
+pub fn repair_osd_file<P: AsRef<Path>, Q: AsRef<Path>>(osd_file: P, output_file: Q, max_frame_gap: u32, renumber_duplicates: bool, overwrite: bool)
+        -> Result<RepairSummary, RepairOSDFileError> {
+    let output_file = output_file.as_ref();
+    if ! overwrite && output_file.exists() { return Err(RepairOSDFileError::OutputFileExists(output_file.to_path_buf())) }
+
+    let mut reader = match file::open(osd_file)? {
+        file::Reader::DJI(reader) => reader,
+        file::Reader::WSA(_) => return Err(RepairOSDFileError::UnsupportedOSDFileFormat),
+    };
+
+    let mut summary = RepairSummary::default();
+    let mut frames = vec![];
+    loop {
+        match reader.read_frame() {
+            Ok(Some(frame)) => frames.push(frame),
+            Ok(None) => break,
+            Err(error) if is_truncation(&error) => { summary.truncated = true; break },
+            Err(error) => return Err(error.into()),
+        }
+    }
+
+    let frames = repair_frames(frames, max_frame_gap, renumber_duplicates, &mut summary);
+    if frames.is_empty() { return Err(RepairOSDFileError::NoValidFrame) }
+    if crate::process::is_dry_run() {
+        println!("repaired OSD file: {}", output_file.to_string_lossy());
+        return Ok(summary);
+    }
+
+    let mut writer = DJIWriter::create(output_file, reader.osd_kind(), reader.font_variant())?;
+    for frame in &frames {
+        writer.write_frame(frame)?;
+    }
+    writer.finish()?;
+
+    if summary.truncated { log::info!("discarded the incomplete last frame") }
+    log::info!("dropped {} frames with invalid tile indices, {} frames with a frame index jump and {} duplicate frames, renumbered {} duplicate frames",
+        summary.invalid_tile_index_frames, summary.frame_index_jump_frames, summary.dropped_duplicate_frames, summary.renumbered_duplicate_frames);
+    log::info!("wrote {} OSD frames to {}", summary.kept_frames, output_file.to_string_lossy());
+    Ok(summary)
+}
@@ -0,0 +1,121 @@
+// Coverage statistics of an OSD recording
+//
+// Shows which cells of the tile grid are ever drawn, which tiles are the most used and where the OSD items known for the
+// font variant are located, to help crafting the --hide-regions and --hide-items arguments and to debug layout mismatches.
+
+use std::collections::BTreeMap;
+
+use getset::{CopyGetters, Getters};
+use itertools::Itertools;
+
+use super::{file::SortedUniqFrames, Dimensions, FontVariant, TileIndex};
+
+
+// characters of the heatmap cells from the cells drawn on the fewest frames to the ones drawn on all the frames
+const HEATMAP_LEVELS: [char; 9] = ['.', ':', '-', '=', '+', '*', '#', '%', '@'];
+
+// number of tile indices listed in the histogram
+const HISTOGRAM_LENGTH: usize = 20;
+
+const HISTOGRAM_BAR_WIDTH: usize = 40;
+
+/// OSD item located with the marker tiles of the font variant
+#[derive(Debug, Clone, Getters, CopyGetters)]
+pub struct DetectedItem {
+    #[getset(get_copy = "pub")]
+    name: &'static str,
+    /// region formatted like the --hide-regions regions
+    #[getset(get = "pub")]
+    region: String,
+    /// number of frames the item is displayed at this location on
+    #[getset(get_copy = "pub")]
+    frame_count: usize,
+}
+
+#[derive(Debug, Clone, Getters, CopyGetters)]
+pub struct OSDStats {
+    #[getset(get_copy = "pub")]
+    frame_count: usize,
+    #[getset(get_copy = "pub")]
+    grid_dimensions: Dimensions,
+    /// number of frames each cell of the grid is not empty on, row by row
+    #[getset(get = "pub")]
+    cell_usage: Vec<usize>,
+    /// number of times each tile index is drawn over all the frames
+    #[getset(get = "pub")]
+    tile_index_counts: BTreeMap<TileIndex, usize>,
+    #[getset(get = "pub")]
+    detected_items: Vec<DetectedItem>,
+}
+
+impl OSDStats {
+
+    pub fn compute(frames: &SortedUniqFrames) -> Self {
+        let grid_dimensions = frames.kind().dimensions_tiles();
+        let mut cell_usage = vec![0; grid_dimensions.width as usize * grid_dimensions.height as usize];
+        let mut tile_index_counts = BTreeMap::new();
+        for frame in frames.iter() {
+            for (coordinates, tile_index) in frame.enumerate_tile_indices() {
+                *tile_index_counts.entry(tile_index).or_default() += 1;
+                if (coordinates.x as u32) < grid_dimensions.width && (coordinates.y as u32) < grid_dimensions.height {
+                    cell_usage[coordinates.y as usize * grid_dimensions.width as usize + coordinates.x as usize] += 1;
+                }
+            }
+        }
+        let detected_items = detect_items(frames, frames.font_variant());
+        Self { frame_count: frames.len(), grid_dimensions, cell_usage, tile_index_counts, detected_items }
+    }
+
+    /// Grid of the cells drawn on at least one frame, the denser the character the more frames the cell is drawn on: from `.`
+    /// to `@` for the cells drawn on every frame
+    pub fn heatmap(&self) -> String {
+        let width = self.grid_dimensions.width as usize;
+        let tens_ruler = (0..width).map(|x| if x % 10 == 0 { char::from_digit((x / 10 % 10) as u32, 10).unwrap() } else { ' ' }).collect::<String>();
+        let units_ruler = (0..width).map(|x| char::from_digit((x % 10) as u32, 10).unwrap()).collect::<String>();
+        let mut heatmap = format!("    {tens_ruler}\n    {units_ruler}\n");
+        for (y, row) in self.cell_usage.chunks(width).enumerate() {
+            let row = row.iter().map(|usage| self.heatmap_char(*usage)).collect::<String>();
+            heatmap.push_str(&format!("{y:>3} {row}\n"));
+        }
+        heatmap
+    }
+
+    fn heatmap_char(&self, usage: usize) -> char {
+        if usage == 0 { return ' ' }
+        let level = ((usage * HEATMAP_LEVELS.len()) as f64 / self.frame_count.max(1) as f64).ceil() as usize;
+        let level = level.clamp(1, HEATMAP_LEVELS.len());
+        HEATMAP_LEVELS[level - 1]
+    }
+
+    /// Most drawn tile indices with the character they represent for the font variant, if any
+    pub fn histogram(&self, font_variant: FontVariant) -> String {
+        let most_used = self.tile_index_counts.iter().sorted_by(|(_, a), (_, b)| b.cmp(a)).take(HISTOGRAM_LENGTH).collect::<Vec<_>>();
+        let max_count = most_used.first().map_or(1, |(_, count)| **count);
+        most_used.into_iter().map(|(tile_index, count)| {
+            let char = font_variant.tile_index_to_char(*tile_index).map_or("   ".to_owned(), |char| format!("'{char}'"));
+            let bar = "#".repeat(((count * HISTOGRAM_BAR_WIDTH) as f64 / max_count as f64).ceil() as usize);
+            format!("{tile_index:#05x} {char} {count:>9} {bar}\n")
+        }).collect()
+    }
+
+}
+
+// locations of the OSD items of the font variant, with the number of frames they are displayed at each of them on
+fn detect_items(frames: &SortedUniqFrames, font_variant: FontVariant) -> Vec<DetectedItem> {
+    let mut items = BTreeMap::<(&'static str, String), usize>::new();
+    for frame in frames.iter() {
+        for location_data in font_variant.osd_items_location_data() {
+            let regions = frame.enumerate_tile_indices()
+                .filter(|(_, tile_index)| location_data.marker_tile_indices().contains(tile_index))
+                .map(|(coordinates, _)| location_data.region(coordinates).to_string())
+                .unique();
+            for region in regions {
+                *items.entry((location_data.name(), region)).or_default() += 1;
+            }
+        }
+    }
+    items.into_iter()
+        .map(|((name, region), frame_count)| DetectedItem { name, region, frame_count })
+        .sorted_by(|a, b| b.frame_count.cmp(&a.frame_count))
+        .collect()
+}
@@ -0,0 +1,190 @@
+use std::{io::{self, Write}, path::{Path, PathBuf}};
+
+use derive_more::From;
+use getset::CopyGetters;
+use lazy_static::lazy_static;
+use regex::Regex;
+use thiserror::Error;
+
+use super::{FontVariant, OSD_TIME_BASE_HZ, TileIndex, TileIndices, file::{self, Frame, GenericReader, OpenError, ReadError}, units::unit_factor};
+
+
+// OSD items which are decoded, any of them is enough to produce a sample
+const LATITUDE_ITEM: &str = "gpslat";
+const LONGITUDE_ITEM: &str = "gpslon";
+const ALTITUDE_ITEM: &str = "alt";
+const SPEED_ITEM: &str = "speed";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, strum::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Gpx,
+}
+
+/// Telemetry values decoded from an OSD frame, converted to SI units
+#[derive(Debug, Clone, Copy, PartialEq, CopyGetters)]
+#[getset(get_copy = "pub")]
+pub struct Sample {
+    osd_frame_index: u32,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+    /// meters
+    altitude: Option<f64>,
+    /// meters per second
+    speed: Option<f64>,
+}
+
+impl Sample {
+
+    /// Time of the sample in seconds from the start of the OSD recording
+    pub fn timestamp(&self) -> f64 {
+        self.osd_frame_index as f64 / OSD_TIME_BASE_HZ
+    }
+
+    /// Latitude and longitude, `None` until the GPS gets a fix
+    pub fn position(&self) -> Option<(f64, f64)> {
+        match (self.latitude, self.longitude) {
+            // 0,0 is displayed before the GPS gets a fix
+            (Some(latitude), Some(longitude)) if latitude != 0.0 || longitude != 0.0 => Some((latitude, longitude)),
+            _ => None,
+        }
+    }
+
+}
+
+fn parse_number(text: &str) -> Option<f64> {
+    lazy_static! {
+        static ref NUMBER_RE: Regex = Regex::new(r"-?\d+(\.\d+)?").unwrap();
+    }
+    NUMBER_RE.find(text)?.as_str().parse().ok()
+}
+
+impl TileIndices {
+
+    /// Reads the value of an OSD item, returning the marker tile index it was located with and the item text
+    ///
+    /// The first occurrence of the item is used if it is displayed more than once.
+    pub fn read_osd_item(&self, font_variant: FontVariant, item_name: &str) -> Option<(TileIndex, String)> {
+        let location_data = font_variant.find_osd_item_location_data(item_name)?;
+        location_data.marker_tile_indices().iter().find_map(|marker_tile_index| {
+            let (coordinates, _) = self.enumerate().find(|(_, tile_index)| tile_index == marker_tile_index)?;
+            let region = location_data.region(coordinates);
+            let x = region.top_left_corner().x().max(0) as super::Coordinate;
+            let y = region.top_left_corner().y().max(0) as super::Coordinate;
+            let text = self.read_text(font_variant, x, y, region.dimensions().width as u8);
+            Some((*marker_tile_index, text))
+        })
+    }
+
+}
+
+/// Decodes the telemetry values displayed on an OSD frame, returns `None` if none of them could be decoded
+pub fn decode_frame(frame: &Frame, font_variant: FontVariant) -> Option<Sample> {
+    let tile_indices = frame.tile_indices();
+    let read_value = |item_name: &str| {
+        let (marker_tile_index, text) = tile_indices.read_osd_item(font_variant, item_name)?;
+        Some(parse_number(&text)? * unit_factor(font_variant, marker_tile_index))
+    };
+    let sample = Sample {
+        osd_frame_index: frame.index(),
+        latitude: read_value(LATITUDE_ITEM),
+        longitude: read_value(LONGITUDE_ITEM),
+        altitude: read_value(ALTITUDE_ITEM),
+        speed: read_value(SPEED_ITEM),
+    };
+    if sample.latitude.is_none() && sample.longitude.is_none() && sample.altitude.is_none() && sample.speed.is_none() {
+        return None
+    }
+    Some(sample)
+}
+
+pub fn decode_frames(frames: &[Frame], font_variant: FontVariant) -> Vec<Sample> {
+    frames.iter().filter_map(|frame| decode_frame(frame, font_variant)).collect()
+}
+
+fn format_optional(value: Option<f64>, precision: usize) -> String {
+    value.map(|value| format!("{value:.precision$}")).unwrap_or_default()
+}
+
+pub fn write_telemetry<W: Write>(writer: &mut W, samples: &[Sample], format: ExportFormat, track_name: &str) -> io::Result<()> {
+    match format {
+        ExportFormat::Csv => {
+            writeln!(writer, "osd_frame_index,timestamp,latitude,longitude,altitude_m,speed_mps")?;
+            for sample in samples {
+                writeln!(writer, "{},{:.3},{},{},{},{}", sample.osd_frame_index, sample.timestamp(),
+                    format_optional(sample.latitude, 7), format_optional(sample.longitude, 7),
+                    format_optional(sample.altitude, 1), format_optional(sample.speed, 2))?;
+            }
+        },
+        ExportFormat::Gpx => {
+            writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+            writeln!(writer, r#"<gpx version="1.1" creator="hd_fpv_video_tool" xmlns="http://www.topografix.com/GPX/1/1">"#)?;
+            writeln!(writer, "  <trk>")?;
+            writeln!(writer, "    <name>{}</name>", xml_escape(track_name))?;
+            writeln!(writer, "    <trkseg>")?;
+            let mut prev_position = None;
+            // the OSD does not contain the date so the points are not timestamped
+            for sample in samples {
+                let Some(position) = sample.position() else { continue };
+                if prev_position == Some(position) { continue }
+                prev_position = Some(position);
+                let (latitude, longitude) = position;
+                match sample.altitude {
+                    Some(altitude) => writeln!(writer, r#"      <trkpt lat="{latitude:.7}" lon="{longitude:.7}"><ele>{altitude:.1}</ele></trkpt>"#)?,
+                    None => writeln!(writer, r#"      <trkpt lat="{latitude:.7}" lon="{longitude:.7}"/>"#)?,
+                }
+            }
+            writeln!(writer, "    </trkseg>")?;
+            writeln!(writer, "  </trk>")?;
+            writeln!(writer, "</gpx>")?;
+        },
+    }
+    Ok(())
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[derive(Debug, Error, From)]
+pub enum ExportTelemetryError {
+    #[error(transparent)]
+    OpenError(OpenError),
+    #[error("OSD file read error: {0}")]
+    OSDFileReadError(ReadError),
+    #[error("decoding telemetry is not supported with the {0} font variant")]
+    UnsupportedFontVariant(FontVariant),
+    #[error("no telemetry value could be decoded from the OSD file")]
+    NoTelemetryFound,
+    #[error("failed to write telemetry: {0}")]
+    WriteError(io::Error),
+}
+
+/// Decodes the GPS position, altitude and speed displayed on the OSD and writes them as a table or a GPS track
+///
+/// The telemetry is written to stdout if no output file is specified.
+pub fn export_telemetry<P: AsRef<Path>>(osd_file: P, format: ExportFormat, output_file: &Option<PathBuf>) -> Result<(), ExportTelemetryError> {
+    let osd_file = osd_file.as_ref();
+    let mut reader = file::open(osd_file)?;
+    let font_variant = reader.font_variant();
+    if font_variant.find_osd_item_location_data(LATITUDE_ITEM).is_none() {
+        return Err(ExportTelemetryError::UnsupportedFontVariant(font_variant))
+    }
+
+    let samples = decode_frames(&reader.frames()?, font_variant);
+    if samples.is_empty() { return Err(ExportTelemetryError::NoTelemetryFound) }
+    log::info!("decoded telemetry from {} OSD frames", samples.len());
+
+    let track_name = osd_file.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+    match output_file {
+        Some(output_file) if crate::process::is_dry_run() => println!("telemetry file: {}", output_file.to_string_lossy()),
+        Some(output_file) => {
+            let mut writer = io::BufWriter::new(fs_err::File::create(output_file)?);
+            write_telemetry(&mut writer, &samples, format, &track_name)?;
+        },
+        None => write_telemetry(&mut io::stdout().lock(), &samples, format, &track_name)?,
+    }
+
+    Ok(())
+}
@@ -0,0 +1,69 @@
+// Text written into the OSD frames
+//
+// Used to display the craft name or the pilot handle with the OSD font when the flight controller did not display them during
+// the flight. The text is written into a free area of the OSD grid in every frame.
+
+use std::str::FromStr;
+
+use getset::Getters;
+use thiserror::Error;
+
+use super::{
+    Coordinates, FontVariant, TileIndices,
+    file::{Frame, SortedUniqFrames},
+};
+
+
+#[derive(Debug, Error)]
+pub enum TextInjectionFormatError {
+    #[error("invalid OSD text format, expected <x>,<y>:<text>: {0}")]
+    InvalidFormat(String),
+    #[error("character not available in the OSD fonts: {0:?}")]
+    UnsupportedCharacter(char),
+}
+
+/// Text written at the specified coordinates of the OSD grid, parsed from `<x>,<y>:<text>`
+///
+/// The fonts only have upper case letters so the text is converted to upper case.
+#[derive(Debug, Clone, Getters)]
+#[getset(get = "pub")]
+pub struct TextInjection {
+    position: Coordinates,
+    text: String,
+}
+
+impl FromStr for TextInjection {
+    type Err = TextInjectionFormatError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (position, text) = value.split_once(':').ok_or_else(|| TextInjectionFormatError::InvalidFormat(value.to_owned()))?;
+        let position = Coordinates::from_str(position).map_err(|_| TextInjectionFormatError::InvalidFormat(value.to_owned()))?;
+        let text = text.to_uppercase();
+        if text.is_empty() { return Err(TextInjectionFormatError::InvalidFormat(value.to_owned())) }
+        // all the font variants share the ASCII layout for the printable characters
+        if let Some(char) = text.chars().find(|char| FontVariant::Generic.char_to_tile_index(*char).is_none()) {
+            return Err(TextInjectionFormatError::UnsupportedCharacter(char));
+        }
+        Ok(Self { position, text })
+    }
+}
+
+/// Writes the texts into every OSD frame, over the tiles displayed at their location
+pub fn inject_text(frames: &SortedUniqFrames, font_variant: FontVariant, injections: &[TextInjection]) -> SortedUniqFrames {
+    let grid_dimensions = frames.kind().dimensions_tiles();
+    for injection in injections {
+        let (x, y) = (injection.position.x, injection.position.y);
+        if y as u32 >= grid_dimensions.height || x as u32 + injection.text.chars().count() as u32 > grid_dimensions.width {
+            log::warn!("OSD text `{}` at {x},{y} does not fit the {}x{} grid of the {} OSD, it is truncated",
+                injection.text, grid_dimensions.width, grid_dimensions.height, frames.kind());
+        }
+    }
+    let injected_frames = frames.iter().map(|frame| {
+        let mut tile_indices = frame.tile_indices().clone();
+        for injection in injections {
+            tile_indices.write_text(font_variant, injection.position.x, injection.position.y, &injection.text);
+        }
+        Frame::new(frame.index(), tile_indices)
+    }).collect();
+    SortedUniqFrames::new(frames.kind(), frames.font_variant(), injected_frames)
+}
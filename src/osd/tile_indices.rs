@@ -25,7 +25,7 @@ impl UnknownOSDItem {
     pub fn new(font_variant: FontVariant, item_name: &str) -> Self { Self { font_variant, item_name: item_name.to_owned() } }
 }
 
-#[derive(Debug, Deref, Clone, PartialEq, Eq)]
+#[derive(Debug, Deref, Clone, PartialEq, Eq, Hash)]
 pub struct TileIndices(Vec<TileIndex>);
 
 impl TileIndices {
@@ -45,6 +45,15 @@ impl TileIndices {
         )
     }
 
+    /// Index of the tile displayed at the specified screen coordinates, 0 if there is none
+    pub fn tile_index_at(&self, x: osd::Coordinate, y: osd::Coordinate) -> TileIndex {
+        self.0.get(Self::screen_coordinates_to_index(x, y)).copied().unwrap_or(0)
+    }
+
+    pub fn set_tile_index_at(&mut self, x: osd::Coordinate, y: osd::Coordinate, tile_index: TileIndex) {
+        self.0[Self::screen_coordinates_to_index(x, y)] = tile_index;
+    }
+
     pub fn enumerate(&self) -> TileIndicesEnumeratorIter {
         TileIndicesEnumeratorIter(self.iter().enumerate())
     }
@@ -0,0 +1,95 @@
+use std::{fmt::Display, str::FromStr};
+
+use hd_fpv_osd_font_tool::prelude::*;
+use thiserror::Error;
+
+use super::{font_dir::{MAX_PAGE_COUNT, PAGE_TILE_COUNT}, tile_indices::TileIndex};
+
+
+pub(crate) const MAX_TILE_INDEX: TileIndex = (MAX_PAGE_COUNT * PAGE_TILE_COUNT - 1) as TileIndex;
+
+/// Draws the OSD tile indices of a range with the glyphs of another range of the font
+///
+/// Used when the font pages are not laid out like the flight controller expects, e.g. a multi-page font whose
+/// second page glyphs are at different indices or an OSD file using the second page with a single page font.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileRemap {
+    first: TileIndex,
+    last: TileIndex,
+    target: TileIndex,
+}
+
+impl TileRemap {
+
+    pub fn source_range(&self) -> std::ops::RangeInclusive<TileIndex> {
+        self.first..=self.last
+    }
+
+    /// Index of the font glyph used to draw the specified OSD tile index, none if the remap does not apply to it
+    pub fn remapped(&self, tile_index: TileIndex) -> Option<TileIndex> {
+        self.source_range().contains(&tile_index).then(|| self.target + (tile_index - self.first))
+    }
+
+    /// Index of the font glyph used to draw an OSD tile index with the specified remaps, the last matching remap applies
+    pub fn glyph_index(remaps: &[TileRemap], tile_index: TileIndex) -> TileIndex {
+        remaps.iter().rev().find_map(|remap| remap.remapped(tile_index)).unwrap_or(tile_index)
+    }
+
+    /// Remaps the tile images so that they can keep being indexed with the OSD tile indices
+    ///
+    /// The glyphs are taken from the table before any remap is applied so that remaps can be chained or swapped.
+    pub fn apply(remaps: &[TileRemap], tile_images: &mut Vec<tile::Image>) {
+        if remaps.is_empty() || tile_images.is_empty() { return }
+        let original_tile_images = tile_images.clone();
+        let (tile_width, tile_height) = original_tile_images[0].dimensions();
+        for remap in remaps {
+            for tile_index in remap.source_range() {
+                let target_index = remap.remapped(tile_index).unwrap() as usize;
+                let Some(target_image) = original_tile_images.get(target_index) else { continue };
+                if tile_images.len() <= tile_index as usize {
+                    tile_images.resize(tile_index as usize + 1, tile::Image::new(tile_width, tile_height));
+                }
+                tile_images[tile_index as usize] = target_image.clone();
+            }
+        }
+    }
+
+}
+
+impl Display for TileRemap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.first == self.last {
+            true => write!(f, "{}:{}", self.first, self.target),
+            false => write!(f, "{}-{}:{}", self.first, self.last, self.target),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("invalid tile remap: {0}: the format is <first index>[-<last index>]:<target index>, indices are up to {MAX_TILE_INDEX} and can be written in hexadecimal with the 0x prefix")]
+pub struct InvalidTileRemap(String);
+
+pub(crate) fn parse_tile_index(value: &str) -> Option<TileIndex> {
+    let value = value.trim();
+    let tile_index = match value.strip_prefix("0x") {
+        Some(hex_value) => TileIndex::from_str_radix(hex_value, 16).ok()?,
+        None => value.parse().ok()?,
+    };
+    (tile_index <= MAX_TILE_INDEX).then_some(tile_index)
+}
+
+impl FromStr for TileRemap {
+    type Err = InvalidTileRemap;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidTileRemap(s.to_owned());
+        let (source, target) = s.split_once(':').ok_or_else(invalid)?;
+        let (first, last) = match source.split_once('-') {
+            Some((first, last)) => (parse_tile_index(first).ok_or_else(invalid)?, parse_tile_index(last).ok_or_else(invalid)?),
+            None => { let index = parse_tile_index(source).ok_or_else(invalid)?; (index, index) },
+        };
+        let target = parse_tile_index(target).ok_or_else(invalid)?;
+        if first > last || target as usize + (last - first) as usize > MAX_TILE_INDEX as usize { return Err(invalid()) }
+        Ok(Self { first, last, target })
+    }
+}
@@ -1,22 +1,210 @@
 
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, SystemTime},
+};
+
+use filetime::FileTime;
+use image::imageops::FilterType;
 use indicatif::{ParallelProgressIterator, ProgressStyle};
 use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
+use sha2::{Digest, Sha256};
 
 use hd_fpv_osd_font_tool::prelude::*;
 
 
+static CACHE_ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Maximum total size of the resized tiles cache, the least recently used atlases are removed beyond it
+const MAX_CACHE_SIZE: u64 = 256 * 1024 * 1024;
+/// The atlases not used for this long are removed from the resized tiles cache
+const MAX_CACHE_AGE: Duration = Duration::from_secs(90 * 24 * 3600);
+
+/// Enables or disables the cache of resized tiles for the whole process
+pub fn set_cache_enabled(enabled: bool) {
+    CACHE_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Directory of the resized tiles cache: `$XDG_CACHE_HOME/hd_fpv_video_tool/resized_tiles` or `~/.cache/hd_fpv_video_tool/resized_tiles`
+pub fn cache_dir() -> Option<PathBuf> {
+    let cache_dir = match std::env::var_os("XDG_CACHE_HOME").filter(|dir| ! dir.is_empty()) {
+        Some(cache_dir) => PathBuf::from(cache_dir),
+        None => home::home_dir()?.join(".cache"),
+    };
+    Some(cache_dir.join(env!("CARGO_PKG_NAME")).join("resized_tiles"))
+}
+
+/// Each dimension of the tiles is upscaled to at least this many times the target dimension before downscaling when supersampling
+const SUPERSAMPLING_FACTOR: u32 = 4;
+
+/// Filter used to resize the tiles when the OSD is scaled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, strum::Display)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[strum(serialize_all = "kebab-case")]
+pub enum ScalingFilter {
+    /// sharp glyphs with little ringing
+    #[default]
+    Lanczos3,
+    /// slightly softer than Lanczos3 with less ringing around the glyph outlines
+    CatmullRom,
+    /// pixel perfect glyphs for integer scale factors, Lanczos3 is used for fractional scale factors
+    Nearest,
+    /// nearest neighbour upscaling to a large integer multiple of the tile dimensions then Lanczos3 downscaling: anti-aliased glyph edges without blur
+    Supersample,
+}
+
+fn is_integer_scaling(dimensions: (u32, u32), new_dimensions: TileDimensions) -> bool {
+    new_dimensions.width % dimensions.0 == 0 && new_dimensions.height % dimensions.1 == 0
+}
+
+impl ScalingFilter {
+
+    // filter actually used for resizing tiles with the specified dimensions
+    fn effective(&self, dimensions: (u32, u32), new_dimensions: TileDimensions) -> Self {
+        match self {
+            Self::Nearest if ! is_integer_scaling(dimensions, new_dimensions) => {
+                log::warn!("the nearest scaling filter requires an integer scale factor, using lanczos3 to resize the tiles from {}x{} to {new_dimensions}",
+                    dimensions.0, dimensions.1);
+                Self::Lanczos3
+            },
+            filter => *filter,
+        }
+    }
+
+    fn resize(&self, tile_image: &tile::Image, new_dimensions: TileDimensions) -> tile::Image {
+        let resize = |tile_image, filter_type| image::imageops::resize(tile_image, new_dimensions.width, new_dimensions.height, filter_type);
+        match self {
+            Self::Lanczos3 => resize(tile_image, FilterType::Lanczos3),
+            Self::CatmullRom => resize(tile_image, FilterType::CatmullRom),
+            Self::Nearest => resize(tile_image, FilterType::Nearest),
+            Self::Supersample => {
+                let (width, height) = tile_image.dimensions();
+                let factor_for = |new_dimension: u32, dimension: u32| (SUPERSAMPLING_FACTOR * new_dimension + dimension - 1) / dimension;
+                let factor = factor_for(new_dimensions.width, width).max(factor_for(new_dimensions.height, height));
+                let supersampled = image::imageops::resize(tile_image, width * factor, height * factor, FilterType::Nearest);
+                resize(&supersampled, FilterType::Lanczos3)
+            },
+        }
+    }
+
+}
+
+// the resized tiles are stored stacked vertically in a single PNG file
+fn load_atlas(path: &Path, tile_dimensions: TileDimensions) -> Option<Vec<tile::Image>> {
+    let atlas = image::open(path).ok()?.into_rgba8();
+    if atlas.width() != tile_dimensions.width || atlas.height() % tile_dimensions.height != 0 { return None }
+    Some((0..atlas.height() / tile_dimensions.height).map(|tile_index|
+        image::imageops::crop_imm(&atlas, 0, tile_index * tile_dimensions.height, tile_dimensions.width, tile_dimensions.height).to_image()
+    ).collect())
+}
+
+fn save_atlas(path: &Path, tile_images: &[tile::Image], tile_dimensions: TileDimensions) -> Result<(), String> {
+    let mut atlas = tile::Image::new(tile_dimensions.width, tile_dimensions.height * tile_images.len() as u32);
+    for (tile_index, tile_image) in tile_images.iter().enumerate() {
+        image::imageops::replace(&mut atlas, tile_image, 0, tile_index as i64 * tile_dimensions.height as i64);
+    }
+    fs_err::create_dir_all(path.parent().unwrap()).map_err(|error| error.to_string())?;
+    // written under a temporary name so that concurrent runs never read a partially written atlas
+    let temp_path = path.with_extension(format!("{}.tmp", std::process::id()));
+    atlas.save_with_format(&temp_path, image::ImageFormat::Png).map_err(|error| error.to_string())?;
+    fs_err::rename(&temp_path, path).map_err(|error| error.to_string())
+}
+
+// the modification time of the atlases is updated when they are used so that it tells which ones were used last
+fn mark_used(path: &Path) {
+    let _ = filetime::set_file_mtime(path, FileTime::now());
+}
+
+// removes the atlases not used for MAX_CACHE_AGE then the least recently used ones until the cache fits in MAX_CACHE_SIZE
+fn evict(cache_dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(cache_dir) else { return };
+    let mut atlases = entries.filter_map(|entry| {
+        let path = entry.ok()?.path();
+        if path.extension() != Some(OsStr::new("png")) { return None }
+        let metadata = path.metadata().ok()?;
+        Some((metadata.modified().ok()?, metadata.len(), path))
+    }).collect::<Vec<_>>();
+    atlases.sort_unstable_by(|(modified, ..), (other_modified, ..)| other_modified.cmp(modified));
+    let now = SystemTime::now();
+    let mut cache_size = 0;
+    for (modified, size, path) in atlases {
+        cache_size += size;
+        if now.duration_since(modified).unwrap_or_default() > MAX_CACHE_AGE || cache_size > MAX_CACHE_SIZE {
+            log::debug!("removing resized tiles cache file {}", path.to_string_lossy());
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
 pub trait ResizeTiles {
-    fn resized_tiles_par_with_progress(&self, new_dimensions: TileDimensions) -> Vec<tile::Image>;
+    fn resized_tiles_par_with_progress(&self, new_dimensions: TileDimensions, filter: ScalingFilter) -> Vec<tile::Image>;
+
+    /// SHA-256 of the dimensions and pixels of the tiles to resize as a hex string, identifies the font in the resized
+    /// tiles cache, the hash must not change between builds for the cache to be reused
+    fn source_hash(&self) -> String;
+
+    /// Same as [`Self::resized_tiles_par_with_progress`] but the resized tiles are read from the cache when the same
+    /// tiles have already been resized to the same dimensions with the same filter
+    fn resized_tiles_cached(&self, new_dimensions: TileDimensions, filter: ScalingFilter) -> Vec<tile::Image> {
+        let (cache_dir, cache_file) = match cache_dir() {
+            Some(cache_dir) if CACHE_ENABLED.load(Ordering::Relaxed) => {
+                let cache_file = cache_dir.join(format!("{}_{}x{}_{filter}.png", self.source_hash(), new_dimensions.width, new_dimensions.height));
+                (cache_dir, cache_file)
+            },
+            _ => return self.resized_tiles_par_with_progress(new_dimensions, filter),
+        };
+        if let Some(tile_images) = load_atlas(&cache_file, new_dimensions) {
+            log::info!("using cached resized tiles: {}", cache_file.to_string_lossy());
+            if ! crate::process::is_dry_run() { mark_used(&cache_file) }
+            return tile_images;
+        }
+        let tile_images = self.resized_tiles_par_with_progress(new_dimensions, filter);
+        if crate::process::is_dry_run() { return tile_images }
+        match save_atlas(&cache_file, &tile_images, new_dimensions) {
+            Ok(()) => evict(&cache_dir),
+            Err(error) => log::warn!("failed writing resized tiles cache file {}: {error}", cache_file.to_string_lossy()),
+        }
+        tile_images
+    }
+}
+
+fn hash_tile_images<'a>(tile_images: impl Iterator<Item = &'a tile::Image>) -> String {
+    let mut hasher = Sha256::new();
+    for tile_image in tile_images {
+        hasher.update(tile_image.width().to_le_bytes());
+        hasher.update(tile_image.height().to_le_bytes());
+        hasher.update(tile_image.as_raw());
+    }
+    hasher.finalize().iter().map(|byte| format!("{byte:02x}")).collect()
 }
 
 impl ResizeTiles for &[Tile]
 {
-    fn resized_tiles_par_with_progress(&self, new_dimensions: TileDimensions) -> Vec<tile::Image> {
+    fn resized_tiles_par_with_progress(&self, new_dimensions: TileDimensions, filter: ScalingFilter) -> Vec<tile::Image> {
         let tile_dimensions = self.first().unwrap().dimensions();
-        log::info!("resizing {} tiles from {}x{} to {new_dimensions}", self.len(), tile_dimensions.0, tile_dimensions.1);
+        let filter = filter.effective(tile_dimensions, new_dimensions);
+        log::info!("resizing {} tiles from {}x{} to {new_dimensions} with the {filter} filter", self.len(), tile_dimensions.0, tile_dimensions.1);
         let progress_style = ProgressStyle::with_template("{wide_bar} {pos:>6}/{len}").unwrap();
-        self.par_iter().progress_with_style(progress_style).map(|tile|
-            image::imageops::resize(tile.image(), new_dimensions.width, new_dimensions.height, image::imageops::FilterType::Lanczos3)
-        ).collect()
+        self.par_iter().progress_with_style(progress_style).map(|tile| filter.resize(tile.image(), new_dimensions)).collect()
+    }
+
+    fn source_hash(&self) -> String {
+        hash_tile_images(self.iter().map(Tile::image))
     }
-}
\ No newline at end of file
+}
+
+impl ResizeTiles for &[tile::Image]
+{
+    fn resized_tiles_par_with_progress(&self, new_dimensions: TileDimensions, filter: ScalingFilter) -> Vec<tile::Image> {
+        let Some(first_tile_image) = self.first() else { return vec![] };
+        let filter = filter.effective(first_tile_image.dimensions(), new_dimensions);
+        let progress_style = ProgressStyle::with_template("{wide_bar} {pos:>6}/{len}").unwrap();
+        self.par_iter().progress_with_style(progress_style).map(|tile_image| filter.resize(tile_image, new_dimensions)).collect()
+    }
+
+    fn source_hash(&self) -> String {
+        hash_tile_images(self.iter())
+    }
+}
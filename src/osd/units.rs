@@ -0,0 +1,97 @@
+use itertools::Itertools;
+
+use super::{
+    FontVariant, TileIndex, TileIndices,
+    file::{Frame, SortedUniqFrames},
+    numeric_item::NumericItem,
+};
+
+
+/// OSD items whose unit can be converted
+pub const CONVERTED_ITEMS: [&str; 2] = ["alt", "speed"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::Display)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[strum(serialize_all = "lowercase")]
+pub enum UnitSystem {
+    /// meters and km/h
+    Metric,
+    /// feet and mph
+    Imperial,
+}
+
+/// Factor converting the value displayed before a unit symbol to SI units
+pub fn unit_factor(font_variant: FontVariant, unit_tile_index: TileIndex) -> f64 {
+    match (font_variant, unit_tile_index) {
+        (FontVariant::INAV, 0x77) => 1000.0,
+        (FontVariant::INAV, 0x78) | (FontVariant::Ardupilot, 0xB3) => 0.3048,
+        (FontVariant::INAV, 0x79) => 304.8,
+        (FontVariant::INAV, 0x90) | (FontVariant::Ardupilot, 0xA1) | (FontVariant::Betaflight, 0x9E) => 1.0 / 3.6,
+        (FontVariant::INAV, 0x91) | (FontVariant::Ardupilot, 0xB0) | (FontVariant::Betaflight, 0x9D) => 0.44704,
+        (FontVariant::INAV, 0x92) => 0.514444,
+        _ => 1.0,
+    }
+}
+
+// unit marker of the item once converted to the unit system
+fn target_marker_tile_index(font_variant: FontVariant, item_name: &str, unit_system: UnitSystem) -> Option<TileIndex> {
+    use UnitSystem::*;
+    Some(match (font_variant, item_name, unit_system) {
+        (FontVariant::INAV, "alt", Metric) => 0x76,
+        (FontVariant::INAV, "alt", Imperial) => 0x78,
+        (FontVariant::INAV, "speed", Metric) => 0x90,
+        (FontVariant::INAV, "speed", Imperial) => 0x91,
+        (FontVariant::Ardupilot, "alt", Metric) => 0xB1,
+        (FontVariant::Ardupilot, "alt", Imperial) => 0xB3,
+        (FontVariant::Ardupilot, "speed", Metric) => 0xA1,
+        (FontVariant::Ardupilot, "speed", Imperial) => 0xB0,
+        // the Betaflight altitude is not displayed before its unit marker so only the speed can be converted
+        (FontVariant::Betaflight, "speed", Metric) => 0x9E,
+        (FontVariant::Betaflight, "speed", Imperial) => 0x9D,
+        _ => return None,
+    })
+}
+
+impl TileIndices {
+
+    /// Converts the values of the [`CONVERTED_ITEMS`] displayed in another unit system, returns whether an item has been converted
+    ///
+    /// The converted value keeps the precision of the displayed value unless it does not fit, e.g. `0.5` km is `1640` ft.
+    pub fn convert_units(&mut self, font_variant: FontVariant, unit_system: UnitSystem) -> bool {
+        let mut converted = false;
+        for item_name in CONVERTED_ITEMS {
+            let Some(target_marker_tile_index) = target_marker_tile_index(font_variant, item_name, unit_system) else { continue };
+            let Some(item) = NumericItem::read(self, font_variant, item_name) else { continue };
+            if item.marker_tile_index() == target_marker_tile_index { continue }
+            let value = item.value() * unit_factor(font_variant, item.marker_tile_index()) / unit_factor(font_variant, target_marker_tile_index);
+            if (0..=item.decimals()).rev().any(|decimals| item.write_value(self, font_variant, value, decimals)) {
+                item.write_marker(self, target_marker_tile_index);
+                converted = true;
+            }
+        }
+        converted
+    }
+
+}
+
+/// Converts the altitude and speed displayed by the OSD frames to the specified unit system
+///
+/// This allows publishing videos in the units preferred by the audience without changing the flight controller settings.
+pub fn convert_frames(frames: &SortedUniqFrames, font_variant: FontVariant, unit_system: UnitSystem) -> SortedUniqFrames {
+    let mut converted_frame_count = 0;
+    let converted_frames = frames.iter().map(|frame| {
+        let mut tile_indices = frame.tile_indices().clone();
+        if tile_indices.convert_units(font_variant, unit_system) { converted_frame_count += 1 }
+        Frame::new(frame.index(), tile_indices)
+    }).collect();
+    let unsupported_items = CONVERTED_ITEMS.iter().filter(|item_name| target_marker_tile_index(font_variant, item_name, unit_system).is_none()).collect::<Vec<_>>();
+    match converted_frame_count {
+        0 if unsupported_items.len() == CONVERTED_ITEMS.len() =>
+            log::warn!("OSD unit conversion is not supported with the {font_variant} font variant"),
+        _ if ! unsupported_items.is_empty() =>
+            log::warn!("converted the units of {converted_frame_count} OSD frames to {unit_system} units, the {} OSD items cannot be converted with the {font_variant} font variant",
+                unsupported_items.iter().join(", ")),
+        _ => log::info!("converted the units of {converted_frame_count} OSD frames to {unit_system} units"),
+    }
+    SortedUniqFrames::new(frames.kind(), frames.font_variant(), converted_frames)
+}
@@ -1,8 +1,9 @@
 
 use std::{
     io::{
+        BufWriter,
         Error as IOError,
-        SeekFrom, Read, Seek,
+        SeekFrom, Read, Seek, Write,
     },
     path::{
         Path,
@@ -27,11 +28,13 @@ use crate::{
             ReadError,
             Frame,
             sorted_frames::SortedUniqFrames,
-            GenericReader
+            GenericReader,
+            split_bytes,
         },
         Kind,
         TileIndices,
         TileIndex, tile_indices,
+        OSD_TIME_BASE_HZ,
     },
     video::FrameIndex as VideoFrameIndex,
 };
@@ -49,6 +52,15 @@ pub enum OpenError {
     InvalidSize(PathBuf),
 }
 
+/// Errors of [`parse`], the content is not read from a file so they carry no path
+#[derive(Debug, Error)]
+pub enum ParseError {
+    #[error("invalid WSA OSD file header")]
+    InvalidHeader,
+    #[error("WSA OSD file content has an invalid size")]
+    InvalidSize,
+}
+
 #[derive(ByteStruct, Debug)]
 #[byte_struct_le]
 pub struct FileHeaderRaw {
@@ -75,6 +87,15 @@ impl FileHeaderRaw {
 
 }
 
+/// Identifier of the font variant stored in the file header, the font variants which are not identified are stored as `BTFL`
+pub fn font_variant_id(font_variant: FontVariant) -> [u8; 4] {
+    match font_variant {
+        FontVariant::INAV => *b"INAV",
+        FontVariant::Ardupilot => *b"ARDU",
+        _ => *b"BTFL",
+    }
+}
+
 #[derive(Debug, Getters, CopyGetters)]
 pub struct FileHeader {
     #[getset(get = "pub")]
@@ -95,27 +116,162 @@ impl From<FileHeaderRaw> for FileHeader {
     }
 }
 
-#[derive(ByteStruct, Debug, CopyGetters)]
+/// Size of the tile indices stored in the frame records, the first revision of the format stores them on 16 bits and newer
+/// firmwares on 32 bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileIndexSize {
+    Bits16,
+    Bits32,
+}
+
+impl TileIndexSize {
+
+    pub const fn bits(&self) -> usize {
+        self.byte_len() * 8
+    }
+
+    const fn byte_len(&self) -> usize {
+        match self {
+            Self::Bits16 => 2,
+            Self::Bits32 => 4,
+        }
+    }
+
+}
+
+const FRAME_TIMESTAMP_LEN: usize = 4;
+const FRAME_TILE_COUNT: usize = (DIMENSIONS.width * DIMENSIONS.height) as usize;
+
+// padding following the tile indices accepted in the frame records, newer firmwares extend the records with unknown data
+const MAX_FRAME_RECORD_PADDING: usize = 64;
+const MAX_FRAME_RECORD_LEN: usize = FrameLayout::unpadded(TileIndexSize::Bits32).record_len + MAX_FRAME_RECORD_PADDING;
+
+// count of records whose timestamps are checked to select the layout when several ones fit the size of the file
+const LAYOUT_CHECK_RECORD_COUNT: usize = 8;
+const LAYOUT_CHECK_LEN: usize = LAYOUT_CHECK_RECORD_COUNT * MAX_FRAME_RECORD_LEN;
+
+// longest interval between the timestamps of consecutive records of a recording accepted when selecting the layout
+const MAX_FRAME_INTERVAL_MS: u32 = 5_000;
+
+/// Layout of the frame records of a WSA OSD file
+///
+/// A record is made of the timestamp of the frame followed by the tile indices of the grid stored row by row, followed by
+/// padding bytes with the newer firmwares. The layout is not recorded in the header so it is derived from the size of the
+/// file: the layouts of the known revisions are tried first then the padded ones. A layout other than the original one is
+/// only selected when it reads timestamps following each other like the ones of a recording.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CopyGetters)]
 #[getset(get_copy = "pub")]
-#[byte_struct_le]
-pub struct FrameRaw {
-    frame_timestamp: u32, // *100µs
-    tile_indices: [[u16; DIMENSIONS.width as usize]; DIMENSIONS.height as usize],
+pub struct FrameLayout {
+    tile_index_size: TileIndexSize,
+    /// length of the records, padding included
+    record_len: usize,
 }
 
-impl FrameRaw {
-    pub fn frame_index(&self) -> VideoFrameIndex {
-        (self.frame_timestamp as f64 * 60.0 / 1_000.0).round() as VideoFrameIndex
+impl FrameLayout {
+
+    /// Layout of the first revision of the format: 16 bit tile indices without padding
+    pub const ORIGINAL: Self = Self::unpadded(TileIndexSize::Bits16);
+
+    const fn unpadded(tile_index_size: TileIndexSize) -> Self {
+        Self { tile_index_size, record_len: FRAME_TIMESTAMP_LEN + FRAME_TILE_COUNT * tile_index_size.byte_len() }
+    }
+
+    pub fn padding(&self) -> usize {
+        self.record_len - Self::unpadded(self.tile_index_size).record_len
+    }
+
+    // in the order they are tried
+    fn candidates() -> impl Iterator<Item = Self> {
+        (0..=MAX_FRAME_RECORD_PADDING).step_by(2).flat_map(|padding|
+            [TileIndexSize::Bits16, TileIndexSize::Bits32].into_iter().map(move |tile_index_size| {
+                let unpadded = Self::unpadded(tile_index_size);
+                Self { record_len: unpadded.record_len + padding, ..unpadded }
+            })
+        )
     }
+
+    /// Layout of `frames_data_len` bytes of frame records starting with `frames_data_start`, `None` if no layout fits
+    ///
+    /// The first layout fitting the size and reading the timestamps of a recording from the first records is selected. The
+    /// original layout is used when it fits the size and no layout reads such timestamps, the other layouts are rejected
+    /// since truncated files of the original layout can fit their size.
+    pub fn detect(frames_data_len: u64, frames_data_start: &[u8]) -> Option<Self> {
+        let fits = |layout: &Self| frames_data_len % layout.record_len as u64 == 0;
+        Self::candidates().filter(fits).find(|layout| layout.reads_recording_timestamps(frames_data_start))
+            .or_else(|| Some(Self::ORIGINAL).filter(fits))
+    }
+
+    fn timestamp(record: &[u8]) -> u32 {
+        u32::from_le_bytes(record[..FRAME_TIMESTAMP_LEN].try_into().unwrap())
+    }
+
+    // non-decreasing timestamps without gaps longer than a recording would have, false when there are not enough records to tell
+    fn reads_recording_timestamps(&self, frames_data_start: &[u8]) -> bool {
+        let timestamps = frames_data_start.chunks_exact(self.record_len).take(LAYOUT_CHECK_RECORD_COUNT).map(Self::timestamp).collect::<Vec<_>>();
+        timestamps.len() >= 2 && timestamps.windows(2).all(|pair| pair[1].checked_sub(pair[0]).is_some_and(|interval| interval <= MAX_FRAME_INTERVAL_MS))
+    }
+
+    // the tile indices which do not fit the 16 bit indices of the fonts are replaced with the blank tile
+    fn tile_index(&self, record: &[u8], x: usize, y: usize) -> TileIndex {
+        let position = FRAME_TIMESTAMP_LEN + (y * DIMENSIONS.width as usize + x) * self.tile_index_size.byte_len();
+        match self.tile_index_size {
+            TileIndexSize::Bits16 => u16::from_le_bytes(record[position..position + 2].try_into().unwrap()),
+            TileIndexSize::Bits32 => TileIndex::try_from(u32::from_le_bytes(record[position..position + 4].try_into().unwrap())).unwrap_or(0),
+        }
+    }
+
+    /// Frame stored in a record, `record` must be `record_len` bytes long
+    pub fn frame(&self, record: &[u8]) -> Frame {
+        let mut tile_indices = Vec::with_capacity(tile_indices::COUNT);
+        let (x_range, y_range) = (0..DIMENSIONS.width as usize, 0..DIMENSIONS.height as usize);
+        for x in 0..tile_indices::DIMENSIONS.width as usize {
+            for y in 0..tile_indices::DIMENSIONS.height as usize {
+                if x_range.contains(&x) && y_range.contains(&y) {
+                    tile_indices.push(self.tile_index(record, x, y));
+                } else {
+                    tile_indices.push(0);
+                }
+            }
+        }
+        Frame::new(frame_index(Self::timestamp(record)), TileIndices::new(tile_indices))
+    }
+
+}
+
+fn frame_index(frame_timestamp: u32) -> VideoFrameIndex {
+    (frame_timestamp as f64 * OSD_TIME_BASE_HZ / 1_000.0).round() as VideoFrameIndex
+}
+
+fn frame_timestamp(frame_index: VideoFrameIndex) -> u32 {
+    (frame_index as f64 * 1_000.0 / OSD_TIME_BASE_HZ).round() as u32
 }
 
 const FIRST_FRAME_FILE_POS: u64 = FileHeaderRaw::BYTE_LEN as u64;
 
-#[derive(Getters)]
+fn sorted_uniq_frames(font_variant: FontVariant, frames: Vec<Frame>) -> SortedUniqFrames {
+    let frames = frames.into_iter().sorted_unstable_by_key(Frame::index).unique_by(Frame::index).collect();
+    SortedUniqFrames::new(Kind::WSA, font_variant, frames)
+}
+
+/// Parses the content of a WSA OSD file held in memory, e.g. by the fuzz targets
+pub fn parse(bytes: &[u8]) -> Result<SortedUniqFrames, ParseError> {
+    let mut data = bytes;
+    let header: FileHeader = FileHeaderRaw::read_bytes(split_bytes(&mut data, FileHeaderRaw::BYTE_LEN).ok_or(ParseError::InvalidSize)?).into();
+    if header.osd_dimensions != DIMENSIONS {
+        return Err(ParseError::InvalidHeader);
+    }
+    let frame_layout = FrameLayout::detect(data.len() as u64, data).ok_or(ParseError::InvalidSize)?;
+    let frames = data.chunks_exact(frame_layout.record_len).map(|record| frame_layout.frame(record)).collect();
+    Ok(sorted_uniq_frames(header.font_variant(), frames))
+}
+
+#[derive(Getters, CopyGetters)]
 pub struct Reader {
     file: File,
     #[getset(get = "pub")]
     header: FileHeader,
+    #[getset(get_copy = "pub")]
+    frame_layout: FrameLayout,
 }
 
 impl Reader {
@@ -133,10 +289,17 @@ impl Reader {
         if header.osd_dimensions != DIMENSIONS {
             return Err(OpenError::InvalidHeader(file_path.as_ref().to_owned()));
         }
-        if (file.metadata()?.len() - FileHeaderRaw::BYTE_LEN as u64) % FrameRaw::BYTE_LEN as u64 != 0 {
-            return Err(OpenError::InvalidSize(file_path.as_ref().to_owned()));
+        let frames_data_len = file.metadata()?.len() - FileHeaderRaw::BYTE_LEN as u64;
+        let mut frames_data_start = Vec::with_capacity(LAYOUT_CHECK_LEN);
+        file.by_ref().take(LAYOUT_CHECK_LEN as u64).read_to_end(&mut frames_data_start)?;
+        let frame_layout = FrameLayout::detect(frames_data_len, &frames_data_start)
+            .ok_or_else(|| OpenError::InvalidSize(file_path.as_ref().to_owned()))?;
+        if frame_layout != FrameLayout::ORIGINAL {
+            log::debug!("{}: frame records with {} bit tile indices and {} padding bytes", file_path.as_ref().to_string_lossy(),
+                frame_layout.tile_index_size().bits(), frame_layout.padding());
         }
-        Ok(Self { file, header })
+        file.seek(SeekFrom::Start(FIRST_FRAME_FILE_POS))?;
+        Ok(Self { file, header, frame_layout })
     }
 
     pub fn rewind(&mut self) -> Result<(), IOError> {
@@ -161,24 +324,12 @@ impl Reader {
 
 impl GenericReader for Reader {
     fn read_frame(&mut self) -> Result<Option<Frame>, ReadError> {
-        let mut frame_raw_bytes = [0; FrameRaw::BYTE_LEN];
-        let frame_raw = match self.file.read(&mut frame_raw_bytes)? {
-            0 => return Ok(None),
-            FrameRaw::BYTE_LEN => FrameRaw::read_bytes(&frame_raw_bytes),
-            _ => return Err(ReadError::unexpected_eof(self.file.path()))
-        };
-        let mut tile_indices = Vec::with_capacity(tile_indices::COUNT);
-        let (x_range, y_range) = (0..DIMENSIONS.width as usize, 0..DIMENSIONS.height as usize);
-        for x in 0..tile_indices::DIMENSIONS.width as usize {
-            for y in 0..tile_indices::DIMENSIONS.height as usize {
-                if x_range.contains(&x) && y_range.contains(&y) {
-                    tile_indices.push(frame_raw.tile_indices[y][x]);
-                } else {
-                    tile_indices.push(0);
-                }
-            }
+        let mut record = vec![0; self.frame_layout.record_len];
+        match self.file.read(&mut record)? {
+            0 => Ok(None),
+            read_len if read_len == record.len() => Ok(Some(self.frame_layout.frame(&record))),
+            _ => Err(ReadError::unexpected_eof(self.file.path()))
         }
-        Ok(Some(Frame::new(frame_raw.frame_index(), TileIndices::new(tile_indices))))
     }
 
     fn frames(&mut self) -> Result<SortedUniqFrames, ReadError> {
@@ -191,21 +342,20 @@ impl GenericReader for Reader {
                 Err(error) => return Err(error),
             }
         }
-        let frames = frames.into_iter().sorted_unstable_by_key(Frame::index).unique_by(Frame::index).collect();
-        Ok(SortedUniqFrames::new(Kind::WSA, font_variant, frames))
+        Ok(sorted_uniq_frames(font_variant, frames))
     }
 
     fn last_frame_frame_index(&mut self) -> Result<u32, ReadError> {
         self.keep_position_do(|reader| {
-            Ok(reader.frames()?.last().unwrap().index())
+            Ok(reader.frames()?.last().ok_or(ReadError::NoFrames)?.index())
         })
     }
 
     fn max_used_tile_index(&mut self) -> Result<TileIndex, ReadError> {
         self.keep_position_do(|reader| {
-            Ok(*reader.frames()?.iter().flat_map(|frame|
+            reader.frames()?.iter().flat_map(|frame|
                 frame.tile_indices().as_slice()
-            ).max().unwrap())
+            ).max().copied().ok_or(ReadError::NoFrames)
         })
     }
 
@@ -258,21 +408,55 @@ impl<'a> IntoIterator for &'a mut Reader {
     }
 }
 
-pub fn find_associated_to_video_file<P: AsRef<Path>>(video_file_path: P) -> Option<PathBuf> {
-    let video_file_path = video_file_path.as_ref();
-    let file_stem = video_file_path.file_stem()?.to_string_lossy();
-    lazy_static! { static ref DJI_VIDEO_FILE_RE: Regex = Regex::new(r"\A(?:Avatar(?:G|S)(\d{4}))").unwrap(); }
-
-    if let Some(captures) = DJI_VIDEO_FILE_RE.captures(&file_stem) {
-        let dji_file_number = captures.get(1).unwrap().as_str();
-        let osd_file_path = video_file_path.with_file_name(format!("AvatarG{dji_file_number}")).with_extension("osd");
-        if osd_file_path.is_file() {
-            log::info!("found: {}", osd_file_path.to_string_lossy());
-            return Some(osd_file_path);
-        } else {
-            log::info!("not found: {}", osd_file_path.to_string_lossy());
+/// Writes WSA OSD files with the frame records layout of the first revision of the format: 16 bit tile indices without padding
+pub struct Writer {
+    file: BufWriter<File>,
+}
+
+impl Writer {
+
+    pub fn create<P: AsRef<Path>>(file_path: P, font_variant: FontVariant) -> Result<Self, IOError> {
+        let header = FileHeaderRaw {
+            font_variant_id: font_variant_id(font_variant),
+            unused: [0; 32],
+            width_tiles: DIMENSIONS.width as u16,
+            height_tiles: DIMENSIONS.height as u16,
+        };
+        let mut header_bytes = [0; FileHeaderRaw::BYTE_LEN];
+        header.write_bytes(&mut header_bytes);
+
+        let mut file = BufWriter::new(File::create(file_path)?);
+        file.write_all(&header_bytes)?;
+        Ok(Self { file })
+    }
+
+    /// Writes a frame, the grid is stored row by row and the tiles outside of the WSA grid are dropped
+    pub fn write_frame(&mut self, frame: &Frame) -> Result<(), IOError> {
+        self.file.write_all(&frame_timestamp(frame.index()).to_le_bytes())?;
+        for y in 0..DIMENSIONS.height {
+            for x in 0..DIMENSIONS.width {
+                self.file.write_all(&frame.tile_indices().tile_index_at(x as _, y as _).to_le_bytes())?;
+            }
         }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<(), IOError> {
+        self.file.flush()
     }
 
-    None
+}
+
+/// Names of the OSD file recorded along with a video file named like the ones of the Walksnail goggles, e.g. `AvatarG0001.osd`
+/// for `AvatarS0001.mp4`, then the name of the `.srt` file recorded along with the video, e.g. `AvatarS0001.osd`, under
+/// which some tools save the OSD file. The prefix is compared case-insensitively.
+pub fn associated_file_names<P: AsRef<Path>>(video_file_path: P) -> Vec<String> {
+    let Some(file_stem) = video_file_path.as_ref().file_stem() else { return vec![] };
+    let file_stem = file_stem.to_string_lossy();
+    lazy_static! { static ref WSA_VIDEO_FILE_RE: Regex = Regex::new(r"(?i)\A(?:Avatar(G|S)(\d{4}))").unwrap(); }
+    let Some(captures) = WSA_VIDEO_FILE_RE.captures(&file_stem) else { return vec![] };
+    let (source, number) = (captures.get(1).unwrap().as_str().to_uppercase(), captures.get(2).unwrap().as_str());
+    let mut file_names = vec![format!("AvatarG{number}.osd")];
+    if source != "G" { file_names.push(format!("Avatar{source}{number}.osd")) }
+    file_names
 }
\ No newline at end of file
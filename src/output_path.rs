@@ -0,0 +1,170 @@
+// Default output paths
+//
+// When no output path is specified the commands derive it from their input file: by default the stem of the input with a
+// suffix naming what the command did, in the directory of the input, e.g. `DJIG0000_cut.mp4`. The file name template and the
+// directory of these default outputs can be changed for all the commands with the --output-template and --output-dir options.
+
+use std::{ffi::{OsStr, OsString}, fmt::Display, path::{Path, PathBuf}, str::FromStr, sync::OnceLock};
+
+use thiserror::Error;
+
+
+/// Template of the default output file names
+pub const DEFAULT_TEMPLATE: &str = "{stem}_{suffix}.{ext}";
+
+static OUTPUT_DIR: OnceLock<PathBuf> = OnceLock::new();
+static TEMPLATE: OnceLock<OutputTemplate> = OnceLock::new();
+
+#[derive(Debug, Error)]
+pub enum InvalidOutputTemplate {
+    #[error("invalid output template: unknown placeholder {{{0}}}, the placeholders are {{stem}}, {{suffix}} and {{ext}}")]
+    UnknownPlaceholder(String),
+    #[error("invalid output template: unclosed placeholder")]
+    UnclosedPlaceholder,
+    #[error("invalid output template: it must contain {{stem}} or {{suffix}} for the output not to be the same file for every input")]
+    MissingStemAndSuffix,
+    #[error("invalid output template: the file name cannot contain a path separator")]
+    PathSeparator,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TemplatePart {
+    Text(String),
+    Stem,
+    Suffix,
+    Extension,
+}
+
+/// File name template of the default outputs with the `{stem}`, `{suffix}` and `{ext}` placeholders
+///
+/// `{stem}` is replaced with the file name of the input without extension, `{suffix}` with the suffix of the command, e.g.
+/// `cut` or `with_osd`, and `{ext}` with the extension of the output. A `.` before `{ext}` is dropped when the output has no
+/// extension.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputTemplate {
+    template: String,
+    parts: Vec<TemplatePart>,
+}
+
+impl OutputTemplate {
+
+    /// Renders the file name of an output
+    pub fn render(&self, stem: &OsStr, suffix: &str, extension: Option<&OsStr>) -> OsString {
+        let mut file_name = OsString::new();
+        for (index, part) in self.parts.iter().enumerate() {
+            match part {
+                TemplatePart::Text(text) => {
+                    let text = match (self.parts.get(index + 1), extension) {
+                        (Some(TemplatePart::Extension), None) => text.strip_suffix('.').unwrap_or(text),
+                        _ => text,
+                    };
+                    file_name.push(text);
+                },
+                TemplatePart::Stem => file_name.push(stem),
+                TemplatePart::Suffix => file_name.push(suffix),
+                TemplatePart::Extension => if let Some(extension) = extension { file_name.push(extension) },
+            }
+        }
+        file_name
+    }
+
+}
+
+impl Default for OutputTemplate {
+    fn default() -> Self {
+        DEFAULT_TEMPLATE.parse().unwrap()
+    }
+}
+
+impl FromStr for OutputTemplate {
+    type Err = InvalidOutputTemplate;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.contains(std::path::is_separator) { return Err(InvalidOutputTemplate::PathSeparator) }
+        let mut parts = vec![];
+        let mut rest = s;
+        while let Some(start) = rest.find('{') {
+            if start > 0 { parts.push(TemplatePart::Text(rest[..start].to_owned())) }
+            let end = rest[start..].find('}').ok_or(InvalidOutputTemplate::UnclosedPlaceholder)? + start;
+            parts.push(match &rest[start + 1..end] {
+                "stem" => TemplatePart::Stem,
+                "suffix" => TemplatePart::Suffix,
+                "ext" => TemplatePart::Extension,
+                placeholder => return Err(InvalidOutputTemplate::UnknownPlaceholder(placeholder.to_owned())),
+            });
+            rest = &rest[end + 1..];
+        }
+        if ! rest.is_empty() { parts.push(TemplatePart::Text(rest.to_owned())) }
+        if ! parts.iter().any(|part| matches!(part, TemplatePart::Stem | TemplatePart::Suffix)) {
+            return Err(InvalidOutputTemplate::MissingStemAndSuffix);
+        }
+        Ok(Self { template: s.to_owned(), parts })
+    }
+}
+
+impl Display for OutputTemplate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.template)
+    }
+}
+
+/// Sets the directory in which the default outputs are written instead of the directory of their input, only the first
+/// directory set is used
+pub fn set_output_dir(dir: &Path) {
+    let _ = OUTPUT_DIR.set(dir.to_path_buf());
+}
+
+/// Sets the template of the default output file names, only the first template set is used
+pub fn set_template(template: OutputTemplate) {
+    let _ = TEMPLATE.set(template);
+}
+
+/// Default path of an output derived from an input file, `None` if the input has no file name
+///
+/// The output is written in the --output-dir directory if set, else in the directory of the input.
+pub fn default_output_path<P: AsRef<Path>>(input_file: P, suffix: &str, extension: Option<&OsStr>) -> Option<PathBuf> {
+    let input_file = input_file.as_ref();
+    let stem = input_file.file_stem()?;
+    let file_name = TEMPLATE.get_or_init(OutputTemplate::default).render(stem, suffix, extension);
+    Some(match OUTPUT_DIR.get() {
+        Some(output_dir) => output_dir.join(file_name),
+        None => input_file.with_file_name(file_name),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(template: &str, extension: Option<&str>) -> OsString {
+        template.parse::<OutputTemplate>().unwrap().render(OsStr::new("DJIG0000"), "with_osd", extension.map(OsStr::new))
+    }
+
+    #[test]
+    fn default_template() {
+        assert_eq!(render(DEFAULT_TEMPLATE, Some("mp4")), "DJIG0000_with_osd.mp4");
+        assert_eq!(OutputTemplate::default().to_string(), DEFAULT_TEMPLATE);
+    }
+
+    #[test]
+    fn custom_template() {
+        assert_eq!(render("{suffix}-{stem}.{ext}", Some("mp4")), "with_osd-DJIG0000.mp4");
+        assert_eq!(render("edit {stem}.{ext}", Some("webm")), "edit DJIG0000.webm");
+        assert_eq!(render("{stem}", Some("mp4")), "DJIG0000");
+    }
+
+    #[test]
+    fn dot_before_extension_dropped_without_extension() {
+        assert_eq!(render(DEFAULT_TEMPLATE, None), "DJIG0000_with_osd");
+        assert_eq!(render("{stem}.{ext}.{suffix}", None), "DJIG0000.with_osd");
+    }
+
+    #[test]
+    fn invalid_templates() {
+        assert!(matches!("{stem}_{name}.{ext}".parse::<OutputTemplate>(), Err(InvalidOutputTemplate::UnknownPlaceholder(placeholder)) if placeholder == "name"));
+        assert!(matches!("{stem".parse::<OutputTemplate>(), Err(InvalidOutputTemplate::UnclosedPlaceholder)));
+        assert!(matches!("output.{ext}".parse::<OutputTemplate>(), Err(InvalidOutputTemplate::MissingStemAndSuffix)));
+        assert!(matches!("out/{stem}.{ext}".parse::<OutputTemplate>(), Err(InvalidOutputTemplate::PathSeparator)));
+    }
+
+}
@@ -1,11 +1,5 @@
 
 pub use crate::{
-    cli::{
-        transcode_video_args::TranscodeVideoArgs,
-        generate_overlay_args::GenerateOverlayArgs,
-        start_end_args::StartEndArgs,
-        transcode_video_args::TranscodeVideoOSDArgs,
-    },
     file,
     osd::{
         self,
@@ -17,16 +11,8 @@ pub use crate::{
                 Reader as OSDFileReader,
             },
         },
-        overlay::{
-            DrawFrameOverlayError,
-            Generator as OverlayGenerator,
-            SaveFramesToDirError,
-            scaling::{
-                Scaling,
-                ScalingArgs,
-            },
-            OverlayVideoCodec,
-        },
+        frame_mapping::ExportFormat as OSDFrameMappingExportFormat,
+        frames_document::ExportFormat as OSDFramesExportFormat,
         region::{
             Region as OSDRegion,
         },
@@ -37,16 +23,70 @@ pub use crate::{
         }
     },
     log_level::LogLevel,
+    video,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::video::probe::Error as VideoProbingError;
+
+#[cfg(feature = "overlay")]
+pub use crate::{
+    osd::overlay::{
+        DrawFrameOverlayError,
+        Generator as OverlayGenerator,
+        SaveFramesToDirError,
+        scaling::Scaling,
+        OverlayVideoCodec,
+        frame_link::FrameLinkMode,
+        frame_writer::DEFAULT_WRITER_COUNT as DEFAULT_FRAME_WRITER_COUNT,
+        frame_image_format::{
+            AlphaMode as FrameAlphaMode,
+            FrameFileFormat,
+            FrameImageFormat,
+        },
+        stick::{
+            StickOverlay,
+            StickOverlayPreset,
+            StickMode,
+        },
+        video_timeline::{
+            OSDFrameShift,
+            OSDSpeed,
+            OSDVideoOffset,
+        },
+    },
+    video::quality_preset::QualityPreset,
+};
+
+#[cfg(feature = "cli")]
+pub use crate::{
+    cli::{
+        transcode_video_args::TranscodeVideoArgs,
+        generate_overlay_args::GenerateOverlayArgs,
+        start_end_args::StartEndArgs,
+        transcode_video_args::TranscodeVideoOSDArgs,
+        play_with_osd_args::PlayWithOSDArgs,
+        generate_thumbnails_args::GenerateThumbnailsArgs,
+        extract_frames_args::ExtractFramesArgs,
+        auto_split_args::AutoSplitArgs,
+        compare_videos_args::CompareVideosArgs,
+        pip_args::PipArgs,
+        export_web_args::ExportWebArgs,
+        make_clip_args::MakeClipArgs,
+    },
+    osd::overlay::scaling::ScalingArgs,
     video::{
-        self,
         AudioFixType as VideoAudioFixType,
-        probe::Error as VideoProbingError,
+        audio_sync_model::{SyncFactor as AudioSyncFactor, SyncFactorSource as AudioSyncFactorSource},
     },
 };
 
+#[cfg(feature = "telemetry")]
+pub use crate::osd::telemetry::ExportFormat as TelemetryExportFormat;
+
 pub use hd_fpv_osd_font_tool::{
     dimensions::{
         Dimensions as GenericDimensions,
         FormatError as GenericDimensionsFormatError,
     },
-};
\ No newline at end of file
+};
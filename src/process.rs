@@ -1,9 +1,21 @@
 
-use std::{ffi::OsStr, process, fmt::Display};
+use std::{ffi::OsStr, path::{Path, PathBuf}, process, fmt::Display, sync::atomic::{AtomicBool, Ordering}};
 
 use derive_more::{Deref, DerefMut};
+use thiserror::Error;
 
 
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+/// Enables the dry-run mode: the external commands are printed instead of being executed and no file is written
+pub fn set_dry_run(enabled: bool) {
+    DRY_RUN.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_dry_run() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
+}
+
 #[derive(Deref, DerefMut)]
 pub struct Command(process::Command);
 
@@ -32,3 +44,27 @@ impl Display for Command {
         f.write_str(components.join(" ").as_str())
     }
 }
+
+/// First line of the output of `<program> <version arg>`, none if the program could not be run
+pub fn program_version<P: AsRef<OsStr>>(program: P, version_arg: &str) -> Option<String> {
+    let output = process::Command::new(program).arg(version_arg).output().ok()?;
+    if ! output.status.success() { return None }
+    String::from_utf8_lossy(&output.stdout).lines().next().map(str::to_owned)
+}
+
+#[derive(Debug, Error)]
+#[error("{name} binary not found or not working: {}, {remediation}", path.to_string_lossy())]
+pub struct ProgramNotWorking {
+    name: &'static str,
+    path: PathBuf,
+    remediation: String,
+}
+
+/// Checks that a program can be run, returns its version
+///
+/// `remediation` tells how to specify another path to the program in the error returned if it cannot be run.
+pub fn check_program<P: AsRef<Path>>(name: &'static str, path: P, version_arg: &str, remediation: &str) -> Result<String, ProgramNotWorking> {
+    let path = path.as_ref();
+    program_version(path, version_arg)
+        .ok_or_else(|| ProgramNotWorking { name, path: path.to_path_buf(), remediation: remediation.to_owned() })
+}
@@ -0,0 +1,224 @@
+// Python module exposing the video probing, the OSD file reading, the overlay generation and the transcoding
+//
+// Build it with `just build-python-module`, the commands running FFMpeg release the GIL while they run.
+
+use std::{path::PathBuf, sync::Arc};
+
+use clap::Parser;
+use pyo3::{create_exception, exceptions::PyException, prelude::*, types::PyBytes};
+
+use crate::{
+    cli::transcode_video_args::{TranscodeVideoArgs, TranscodeVideoOSDArgs},
+    ffmpeg::{self, progress::ProgressUpdate as FFMpegProgressUpdate},
+    osd::{
+        self,
+        file::{GenericReader, SortedUniqFrames},
+        overlay::{Generator as OverlayGenerator, OverlayVideoCodec, scaling::Scaling, video_timeline::OSDSpeed},
+        FontDir,
+    },
+    video,
+};
+
+
+create_exception!(hd_fpv_video_tool, Error, PyException);
+
+fn to_py_error(error: impl std::fmt::Display) -> PyErr {
+    Error::new_err(error.to_string())
+}
+
+/// Details of a video file
+#[pyclass(get_all)]
+pub struct VideoInfo {
+    width: u32,
+    height: u32,
+    frame_rate: f64,
+    frame_count: u64,
+    duration: f64,
+    has_audio: bool,
+    video_codec: Option<String>,
+}
+
+/// Progress statistics of the running FFMpeg process
+#[pyclass(get_all)]
+pub struct ProgressUpdate {
+    frame: u64,
+    fps: Option<f64>,
+    bitrate: Option<f64>,
+    speed: Option<f64>,
+    time: Option<f64>,
+}
+
+impl From<&FFMpegProgressUpdate> for ProgressUpdate {
+    fn from(update: &FFMpegProgressUpdate) -> Self {
+        Self { frame: update.frame(), fps: update.fps(), bitrate: update.bitrate(), speed: update.speed(), time: update.time().map(|time| time.as_secs_f64()) }
+    }
+}
+
+// calls the Python progress callback with the progress updates of an FFMpeg process
+fn progress_callback(callback: Option<PyObject>) -> Option<ffmpeg::progress::ProgressCallback> {
+    callback.map(|callback| -> ffmpeg::progress::ProgressCallback {
+        Arc::new(move |update| Python::with_gil(|py| {
+            if let Err(error) = callback.call1(py, (ProgressUpdate::from(update),)) {
+                log::warn!("progress callback failed: {error}");
+            }
+        }))
+    })
+}
+
+fn block_on<E: std::fmt::Display, F: std::future::Future<Output = Result<(), E>> + Send>(py: Python, future: F) -> PyResult<()> {
+    let runtime = tokio::runtime::Runtime::new().map_err(to_py_error)?;
+    py.allow_threads(|| runtime.block_on(future)).map_err(to_py_error)
+}
+
+/// Returns the details of a video file
+#[pyfunction]
+fn probe(path: PathBuf) -> PyResult<VideoInfo> {
+    let video_info = video::probe(&path).map_err(to_py_error)?;
+    Ok(VideoInfo {
+        width: video_info.resolution().width,
+        height: video_info.resolution().height,
+        frame_rate: f64::from(video_info.frame_rate()),
+        frame_count: video_info.frame_count(),
+        duration: video_info.duration(),
+        has_audio: video_info.has_audio(),
+        video_codec: video_info.video_codec().clone(),
+    })
+}
+
+/// Frames read from a DJI or Walksnail Avatar OSD file
+#[pyclass]
+pub struct OSDFile {
+    frames: SortedUniqFrames,
+}
+
+#[pymethods]
+impl OSDFile {
+
+    #[new]
+    fn new(path: PathBuf) -> PyResult<Self> {
+        let mut osd_file = osd::file::open(&path).map_err(to_py_error)?;
+        Ok(Self { frames: osd_file.frames().map_err(to_py_error)? })
+    }
+
+    #[getter]
+    fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    #[getter]
+    fn font_variant(&self) -> String {
+        self.frames.font_variant().to_string()
+    }
+
+    #[getter]
+    fn kind(&self) -> String {
+        self.frames.kind().to_string()
+    }
+
+    /// Width and height of the OSD grid in tiles
+    #[getter]
+    fn dimensions_tiles(&self) -> (u32, u32) {
+        let dimensions = self.frames.kind().dimensions_tiles();
+        (dimensions.width, dimensions.height)
+    }
+
+    /// Index of the video frame at which the OSD frame at this position is displayed
+    fn frame_index(&self, frame_position: usize) -> PyResult<u32> {
+        Ok(self.frame(frame_position)?.index())
+    }
+
+    /// Tile indices of the OSD frame at this position, column by column
+    fn tile_indices(&self, frame_position: usize) -> PyResult<Vec<u16>> {
+        Ok(self.frame(frame_position)?.tile_indices().to_vec())
+    }
+
+}
+
+impl OSDFile {
+    fn frame(&self, frame_position: usize) -> PyResult<&osd::file::Frame> {
+        self.frames.get(frame_position).ok_or_else(|| to_py_error(format!("OSD frame position out of range: {frame_position}")))
+    }
+}
+
+fn overlay_generator(osd_file: &OSDFile, font_dir: PathBuf) -> PyResult<OverlayGenerator<'static>> {
+    let font_variant = osd_file.frames.font_variant();
+    OverlayGenerator::new(osd_file.frames.clone(), font_variant, &FontDir::new(font_dir), &None, Scaling::No { target_resolution: None }, &[], false, &[])
+        .map_err(to_py_error)
+}
+
+/// Renders the overlay frames of an OSD file with its native resolution
+#[pyclass]
+pub struct OverlayRenderer {
+    generator: OverlayGenerator<'static>,
+}
+
+#[pymethods]
+impl OverlayRenderer {
+
+    #[new]
+    fn new(osd_file: PyRef<OSDFile>, font_dir: PathBuf) -> PyResult<Self> {
+        Ok(Self { generator: overlay_generator(&osd_file, font_dir)? })
+    }
+
+    /// Width and height of the rendered frames in pixels
+    #[getter]
+    fn frame_dimensions(&self) -> (u32, u32) {
+        let dimensions = self.generator.frame_dimensions();
+        (dimensions.width, dimensions.height)
+    }
+
+    /// Renders the overlay of the OSD frame at this position as RGBA pixels
+    fn render<'py>(&self, py: Python<'py>, frame_position: usize) -> PyResult<&'py PyBytes> {
+        let frame = self.generator.render_osd_frame(frame_position)
+            .ok_or_else(|| to_py_error(format!("OSD frame position out of range: {frame_position}")))?
+            .map_err(to_py_error)?;
+        Ok(PyBytes::new(py, frame.as_raw()))
+    }
+
+}
+
+/// Generates a transparent WebM overlay video of an OSD file with its native resolution
+#[pyfunction]
+#[pyo3(signature = (osd_file, font_dir, output_video_file, overwrite = false, progress = None))]
+fn generate_overlay_video(py: Python, osd_file: PyRef<OSDFile>, font_dir: PathBuf, output_video_file: PathBuf, overwrite: bool,
+                            progress: Option<PyObject>) -> PyResult<()> {
+    let mut generator = overlay_generator(&osd_file, font_dir)?;
+    generator.set_progress_callback(progress_callback(progress));
+    block_on(py, async move {
+        generator.generate_overlay_video(OverlayVideoCodec::Vp8, None, None, None, output_video_file, 0, OSDSpeed::NORMAL, overwrite).await
+    })
+}
+
+#[derive(Parser)]
+#[clap(name = "transcode_video", no_binary_name = true)]
+struct TranscodeVideoCommand {
+    #[clap(flatten)]
+    osd_args: TranscodeVideoOSDArgs,
+
+    #[clap(flatten)]
+    transcode_args: TranscodeVideoArgs,
+}
+
+/// Transcodes a video, the arguments are the ones of the `transcode-video` command
+///
+/// e.g. `transcode_video(["--osd", "--preset", "fast", "DJIG0000.mp4"], progress=print)`
+#[pyfunction]
+#[pyo3(signature = (args, progress = None))]
+fn transcode_video(py: Python, args: Vec<String>, progress: Option<PyObject>) -> PyResult<()> {
+    let mut command = TranscodeVideoCommand::try_parse_from(args).map_err(to_py_error)?;
+    command.transcode_args.set_progress_callback(progress_callback(progress));
+    block_on(py, async move { video::transcode_with_osd_args(&command.transcode_args, &command.osd_args).await })
+}
+
+#[pymodule]
+fn hd_fpv_video_tool(py: Python, module: &PyModule) -> PyResult<()> {
+    module.add("Error", py.get_type::<Error>())?;
+    module.add_class::<VideoInfo>()?;
+    module.add_class::<ProgressUpdate>()?;
+    module.add_class::<OSDFile>()?;
+    module.add_class::<OverlayRenderer>()?;
+    module.add_function(wrap_pyfunction!(probe, module)?)?;
+    module.add_function(wrap_pyfunction!(generate_overlay_video, module)?)?;
+    module.add_function(wrap_pyfunction!(transcode_video, module)?)?;
+    Ok(())
+}
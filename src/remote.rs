@@ -0,0 +1,154 @@
+use std::{
+    ffi::{OsStr, OsString},
+    io::Error as IOError,
+    path::{Path, PathBuf},
+    process::ExitStatus,
+};
+
+use getset::Getters;
+use thiserror::Error;
+
+use crate::process::{self, Command};
+
+
+const SSH_BINARY_PATH: &str = "ssh";
+const SCP_BINARY_PATH: &str = "scp";
+const TWO_PASS_LOG_FILE_SUFFIX: &str = ".ffmpeg2pass";
+
+#[derive(Debug, Error)]
+pub enum RemoteError {
+    #[error("failed running {program}: {error}")]
+    FailedRunning {
+        program: String,
+        error: IOError,
+    },
+    #[error("command failed with {exit_status}: {command}")]
+    CommandFailed {
+        command: String,
+        exit_status: ExitStatus,
+    },
+    #[error("file has no file name: {0}")]
+    NoFileName(PathBuf),
+}
+
+/// Quotes an argument for the POSIX shell of the remote host
+pub fn shell_quote(arg: &OsStr) -> String {
+    format!("'{}'", arg.to_string_lossy().replace('\'', r"'\''"))
+}
+
+/// Host on which FFMpeg is run through SSH, the files are copied with SCP
+#[derive(Debug, Clone, Getters)]
+#[getset(get = "pub")]
+pub struct RemoteHost {
+    /// SSH destination: [user@]host
+    destination: String,
+    ffmpeg_path: String,
+    /// directory of the remote host in which the input and output files are stored during the encode
+    work_dir: PathBuf,
+}
+
+impl RemoteHost {
+
+    pub fn new<P: AsRef<Path>>(destination: &str, ffmpeg_path: &str, work_dir: P) -> Self {
+        Self { destination: destination.to_owned(), ffmpeg_path: ffmpeg_path.to_owned(), work_dir: work_dir.as_ref().to_path_buf() }
+    }
+
+    /// Path in the remote work directory of a file with the specified name, prefixed to not clash with other encodes
+    pub fn work_file_path(&self, file_name: &OsStr) -> PathBuf {
+        let mut work_file_name = OsString::from(format!("{}_{}_", env!("CARGO_PKG_NAME"), std::process::id()));
+        work_file_name.push(file_name);
+        self.work_dir.join(work_file_name)
+    }
+
+    /// Command running the specified program with its arguments on the remote host, the standard streams are forwarded
+    pub fn ssh_command<I: IntoIterator<Item = S>, S: AsRef<OsStr>>(&self, program: &OsStr, args: I) -> Command {
+        let remote_command = std::iter::once(shell_quote(program))
+            .chain(args.into_iter().map(|arg| shell_quote(arg.as_ref())))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut command = Command::new(SSH_BINARY_PATH);
+        command.args([self.destination.as_str(), "--", &remote_command]);
+        command
+    }
+
+    fn scp_path(&self, remote_path: &Path) -> OsString {
+        let mut scp_path = OsString::from(format!("{}:", self.destination));
+        scp_path.push(remote_path.as_os_str());
+        scp_path
+    }
+
+    pub fn upload<P: AsRef<Path>, Q: AsRef<Path>>(&self, local_path: P, remote_path: Q) -> Result<(), RemoteError> {
+        log::info!("copying {} to {}", local_path.as_ref().to_string_lossy(), self.scp_path(remote_path.as_ref()).to_string_lossy());
+        let mut command = Command::new(SCP_BINARY_PATH);
+        command.arg("-q").arg(local_path.as_ref()).arg(self.scp_path(remote_path.as_ref()));
+        run(command)
+    }
+
+    pub fn download<P: AsRef<Path>, Q: AsRef<Path>>(&self, remote_path: P, local_path: Q) -> Result<(), RemoteError> {
+        log::info!("copying {} to {}", self.scp_path(remote_path.as_ref()).to_string_lossy(), local_path.as_ref().to_string_lossy());
+        let mut command = Command::new(SCP_BINARY_PATH);
+        command.arg("-q").arg(self.scp_path(remote_path.as_ref())).arg(local_path.as_ref());
+        run(command)
+    }
+
+    /// Removes files of the remote host, the paths are not quoted so that they can contain wildcards
+    pub fn remove_files(&self, paths: &[OsString]) -> Result<(), RemoteError> {
+        let mut command = Command::new(SSH_BINARY_PATH);
+        let paths = paths.iter().map(|path| path.to_string_lossy()).collect::<Vec<_>>().join(" ");
+        command.args([self.destination.as_str(), "--", &format!("rm -f -- {paths}")]);
+        run(command)
+    }
+
+}
+
+fn run(mut command: Command) -> Result<(), RemoteError> {
+    if process::is_dry_run() {
+        println!("{command}");
+        return Ok(());
+    }
+    log::debug!("running: {command}");
+    let exit_status = crate::cancellation::run_tracked(&mut command).map_err(|error| RemoteError::FailedRunning { program: command.get_program().to_string_lossy().to_string(), error })?;
+    if ! exit_status.success() { return Err(RemoteError::CommandFailed { command: command.to_string(), exit_status }) }
+    Ok(())
+}
+
+/// Encode of a local input file into a local output file run on a remote host
+///
+/// The input file is copied to the remote work directory beforehand and the output file is copied back once done.
+#[derive(Debug, Getters)]
+#[getset(get = "pub")]
+pub struct RemoteEncode {
+    host: RemoteHost,
+    input_path: PathBuf,
+    output_path: PathBuf,
+    #[getset(skip)]
+    local_output_path: PathBuf,
+}
+
+impl RemoteEncode {
+
+    pub fn prepare<P: AsRef<Path>, Q: AsRef<Path>>(host: RemoteHost, local_input_path: P, local_output_path: Q) -> Result<Self, RemoteError> {
+        let (local_input_path, local_output_path) = (local_input_path.as_ref(), local_output_path.as_ref());
+        let file_name = |path: &Path| path.file_name().map(OsStr::to_os_string).ok_or_else(|| RemoteError::NoFileName(path.to_path_buf()));
+        let input_path = host.work_file_path(&file_name(local_input_path)?);
+        let output_path = host.work_file_path(&file_name(local_output_path)?);
+        host.upload(local_input_path, &input_path)?;
+        Ok(Self { host, input_path, output_path, local_output_path: local_output_path.to_path_buf() })
+    }
+
+    /// Copies the output file back if the encode succeeded and removes the files of the encode from the remote host
+    pub fn finish(self, encode_succeeded: bool) -> Result<(), RemoteError> {
+        let download_result = match encode_succeeded {
+            true => self.host.download(&self.output_path, &self.local_output_path),
+            false => Ok(()),
+        };
+        let mut two_pass_log_files = OsString::from(shell_quote(self.output_path.as_os_str()));
+        two_pass_log_files.push(format!("{TWO_PASS_LOG_FILE_SUFFIX}*"));
+        let work_files = [shell_quote(self.input_path.as_os_str()).into(), shell_quote(self.output_path.as_os_str()).into(), two_pass_log_files];
+        if let Err(error) = self.host.remove_files(&work_files) {
+            log::warn!("failed to remove the work files from the remote host: {error}");
+        }
+        download_result
+    }
+
+}
@@ -0,0 +1,282 @@
+use std::{
+    io::{
+        Error as IOError,
+        ErrorKind as IOErrorKind,
+        Read,
+        Seek,
+        SeekFrom,
+        Write,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+    time::Duration,
+};
+
+use fs_err::File;
+use getset::{Getters, CopyGetters};
+use indicatif::{ProgressBar, ProgressStyle};
+use path_absolutize::Absolutize;
+use thiserror::Error;
+
+use crate::create_path::{create_path, CreatePathError};
+
+
+const READ_RETRY_COUNT: u32 = 4;
+const READ_RETRY_INITIAL_BACKOFF: Duration = Duration::from_millis(250);
+const COPY_CHUNK_SIZE: usize = 8 * 1024 * 1024;
+const FAT_MAX_FILE_SIZE: u64 = 4 * 1024 * 1024 * 1024 - 1;
+const DEFAULT_STAGING_DIR_NAME: &str = "hd_fpv_video_tool_staging";
+
+// EIO on all the unix platforms
+const EIO: i32 = 5;
+
+#[derive(Debug, Clone, Getters, CopyGetters)]
+#[getset(get = "pub")]
+pub struct MountInfo {
+    mount_point: PathBuf,
+    fs_type: String,
+    source: String,
+    options: Vec<String>,
+    #[getset(skip)]
+    #[getset(get_copy = "pub")]
+    removable: bool,
+}
+
+impl MountInfo {
+
+    pub fn has_option(&self, option: &str) -> bool {
+        self.options.iter().any(|mount_option| mount_option == option)
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.has_option("ro")
+    }
+
+    pub fn is_fat(&self) -> bool {
+        matches!(self.fs_type.as_str(), "vfat" | "msdos" | "fat")
+    }
+
+}
+
+// decodes the octal escapes used in /proc/self/mountinfo (e.g. `\040` for space)
+#[cfg(target_os = "linux")]
+fn decode_mountinfo_field(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'\\' && index + 3 < bytes.len() {
+            if let Ok(value) = u8::from_str_radix(&field[index + 1..index + 4], 8) {
+                decoded.push(value);
+                index += 4;
+                continue;
+            }
+        }
+        decoded.push(bytes[index]);
+        index += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+#[cfg(target_os = "linux")]
+fn block_device_is_removable(source: &str) -> bool {
+    let Some(device_name) = source.strip_prefix("/dev/") else { return false };
+    if device_name.starts_with("mmcblk") { return true }
+    let device_sys_path = Path::new("/sys/class/block").join(device_name);
+    // partitions do not have the `removable` attribute, their parent device does
+    [device_sys_path.join("removable"), device_sys_path.join("../removable")].iter().any(|removable_attr_path|
+        matches!(std::fs::read_to_string(removable_attr_path), Ok(value) if value.trim() == "1")
+    )
+}
+
+/// Returns information about the file system on which the specified path is located
+#[cfg(target_os = "linux")]
+pub fn mount_info<P: AsRef<Path>>(path: P) -> Option<MountInfo> {
+    let path = path.as_ref().absolutize().ok()?.to_path_buf();
+    let mountinfo = std::fs::read_to_string("/proc/self/mountinfo").ok()?;
+    mountinfo.lines().filter_map(|line| {
+        let (mount_fields, fs_fields) = line.split_once(" - ")?;
+        let mount_fields = mount_fields.split(' ').collect::<Vec<_>>();
+        let mut fs_fields = fs_fields.split(' ');
+        let mount_point = PathBuf::from(decode_mountinfo_field(mount_fields.get(4)?));
+        if ! path.starts_with(&mount_point) { return None }
+        let fs_type = fs_fields.next()?.to_owned();
+        let source = decode_mountinfo_field(fs_fields.next()?);
+        let mut options = mount_fields.get(5)?.split(',').map(str::to_owned).collect::<Vec<_>>();
+        if let Some(super_options) = fs_fields.next() {
+            options.extend(super_options.split(',').map(str::to_owned));
+        }
+        let removable = block_device_is_removable(&source);
+        Some(MountInfo { mount_point, fs_type, source, options, removable })
+    }).max_by_key(|mount_info| mount_info.mount_point.components().count())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn mount_info<P: AsRef<Path>>(_path: P) -> Option<MountInfo> {
+    None
+}
+
+/// Logs warnings when an input file is located on removable media
+pub fn check_input<P: AsRef<Path>>(path: P) {
+    let path = path.as_ref();
+    let Some(mount_info) = mount_info(path) else { return };
+    if ! mount_info.removable() { return }
+    log::warn!("input file {} is located on removable media ({}), consider using --copy-local-first to avoid I/O errors during long encodes",
+        path.to_string_lossy(), mount_info.source());
+    if ! mount_info.is_read_only() {
+        log::info!("tip: mounting the card read-only (`ro` mount option) protects the original recordings");
+    }
+}
+
+/// Logs warnings when an output file is going to be written on a file system with problematic mount options
+pub fn check_output<P: AsRef<Path>>(path: P, estimated_size: Option<u64>) {
+    let path = path.as_ref();
+    let Some(mount_info) = mount_info(path) else { return };
+    let mount_point = mount_info.mount_point().to_string_lossy();
+    if mount_info.is_read_only() {
+        log::warn!("output file {} is on a file system mounted read-only ({mount_point})", path.to_string_lossy());
+    }
+    if mount_info.has_option("sync") {
+        log::warn!("{mount_point} is mounted with the `sync` option, writing the output file will be very slow and wear the card");
+    }
+    if mount_info.is_fat() && matches!(estimated_size, Some(size) if size > FAT_MAX_FILE_SIZE) {
+        log::warn!("{mount_point} is a FAT file system which cannot store files larger than 4GB, the output file will likely be truncated");
+    }
+}
+
+fn is_transient_read_error(error: &IOError) -> bool {
+    matches!(error.kind(), IOErrorKind::Interrupted | IOErrorKind::TimedOut | IOErrorKind::WouldBlock)
+        || error.raw_os_error() == Some(EIO)
+}
+
+/// Runs the specified I/O operation retrying with exponential backoff when it fails with a transient error
+pub fn retry_with_backoff<T, F>(description: &str, mut operation: F) -> Result<T, IOError>
+where F: FnMut() -> Result<T, IOError>
+{
+    let mut backoff = READ_RETRY_INITIAL_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        match operation() {
+            Err(error) if attempt <= READ_RETRY_COUNT && is_transient_read_error(&error) => {
+                log::warn!("{description}: {error}, retrying in {}ms ({attempt}/{READ_RETRY_COUNT})", backoff.as_millis());
+                std::thread::sleep(backoff);
+                backoff *= 2;
+                attempt += 1;
+            },
+            result => return result,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum StageError {
+    #[error(transparent)]
+    CreatePathError(#[from] CreatePathError),
+    #[error("failed to copy {file_path} to the staging directory: {error}")]
+    CopyError {
+        file_path: PathBuf,
+        error: IOError,
+    },
+    #[error("invalid file path: {0}")]
+    InvalidFilePath(PathBuf),
+}
+
+/// A file copied into a local staging directory, the copy is removed when the value is dropped
+#[derive(Debug)]
+pub struct StagedFile {
+    path: PathBuf,
+    staged: bool,
+}
+
+impl StagedFile {
+
+    /// Wraps a path without staging it so that callers can use the same code path in both cases
+    pub fn unstaged<P: AsRef<Path>>(path: P) -> Self {
+        Self { path: path.as_ref().to_path_buf(), staged: false }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+}
+
+impl Drop for StagedFile {
+    fn drop(&mut self) {
+        if self.staged {
+            if let Err(error) = std::fs::remove_file(&self.path) {
+                log::warn!("failed to remove staged file {}: {error}", self.path.to_string_lossy());
+            }
+        }
+    }
+}
+
+pub fn default_staging_dir() -> PathBuf {
+    std::env::temp_dir().join(DEFAULT_STAGING_DIR_NAME)
+}
+
+fn copy_with_retry(source_path: &Path, destination_path: &Path, progress_bar: &ProgressBar) -> Result<(), IOError> {
+    let mut source = retry_with_backoff("opening input file", || File::open(source_path))?;
+    let mut destination = File::create(destination_path)?;
+    let mut buffer = vec![0; COPY_CHUNK_SIZE];
+    let mut position = 0;
+    loop {
+        let read_count = retry_with_backoff("reading input file", || {
+            source.seek(SeekFrom::Start(position))?;
+            source.read(&mut buffer)
+        })?;
+        if read_count == 0 { break }
+        destination.write_all(&buffer[..read_count])?;
+        position += read_count as u64;
+        progress_bar.set_position(position);
+    }
+    destination.flush()
+}
+
+// whether both paths lead to the same file, the staged path not existing yet is not the same file
+fn is_same_file(path: &Path, staged_path: &Path) -> bool {
+    match (path.canonicalize(), staged_path.canonicalize()) {
+        (Ok(path), Ok(staged_path)) => path == staged_path,
+        _ => false,
+    }
+}
+
+/// Copies the specified file into the staging directory so that long running operations do not read from slow or unreliable media
+///
+/// The file is not staged when the staging directory is its own directory since it would be overwritten by its copy.
+pub fn stage_locally<P: AsRef<Path>, Q: AsRef<Path>>(path: P, staging_dir: Q) -> Result<StagedFile, StageError> {
+    let path = path.as_ref();
+    let staging_dir = staging_dir.as_ref();
+    let file_name = path.file_name().ok_or_else(|| StageError::InvalidFilePath(path.to_path_buf()))?;
+    create_path(staging_dir)?;
+    let staged_path = staging_dir.join(file_name);
+    if is_same_file(path, &staged_path) {
+        log::warn!("the staging directory {} is the directory of {}, not copying it", staging_dir.to_string_lossy(), path.to_string_lossy());
+        return Ok(StagedFile::unstaged(path));
+    }
+
+    log::info!("copying {} to {} before processing", path.to_string_lossy(), staged_path.to_string_lossy());
+    let copy_error = |error| StageError::CopyError { file_path: path.to_path_buf(), error };
+    let file_size = std::fs::metadata(path).map_err(copy_error)?.len();
+    let progress_style = ProgressStyle::with_template("{wide_bar} {bytes:>10}/{total_bytes} [ETA {eta:>3}]").unwrap();
+    let progress_bar = ProgressBar::new(file_size).with_style(progress_style);
+
+    // create the value before copying so that a partial copy gets removed if copying fails
+    let staged_file = StagedFile { path: staged_path, staged: true };
+    copy_with_retry(path, staged_file.path(), &progress_bar).map_err(copy_error)?;
+    progress_bar.finish_and_clear();
+
+    Ok(staged_file)
+}
+
+pub fn stage_if_requested<P: AsRef<Path>>(path: P, copy_local_first: bool, staging_dir: &Option<PathBuf>) -> Result<StagedFile, StageError> {
+    if copy_local_first && ! crate::process::is_dry_run() {
+        let staging_dir = staging_dir.clone().unwrap_or_else(default_staging_dir);
+        stage_locally(path, staging_dir)
+    } else {
+        check_input(&path);
+        Ok(StagedFile::unstaged(path))
+    }
+}
@@ -0,0 +1,149 @@
+// Pausing and throttling of the spawned processes
+//
+// The local FFMpeg processes are stopped with SIGSTOP and continued with SIGCONT, either on request when this process receives
+// SIGUSR1 (pause) and SIGUSR2 (resume) or periodically to limit the share of the time they run to the `--max-cpu` percentage.
+// The jobs run by the queue are sent SIGUSR1 and SIGUSR2 instead of being stopped so that they pause their own processes.
+
+use std::{io::Error as IOError, process, sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex, Weak}, time::Duration};
+
+use crate::cancellation;
+
+
+// the processes run and are stopped in turn within this period when throttled
+const THROTTLE_PERIOD: Duration = Duration::from_millis(200);
+// the shared child processes are polled for their exit so that they are never locked while waited for
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Handle of a child process shared with the throttling, it is locked while the process is signalled
+pub type SharedChild = Arc<Mutex<process::Child>>;
+
+// only the FFMpeg processes and the queue jobs are tracked, the other child processes like the player or the SSH
+// transfers are never stopped
+struct TrackedProcess {
+    child: Weak<Mutex<process::Child>>,
+    is_job: bool,
+}
+
+static PAUSED: AtomicBool = AtomicBool::new(false);
+static TRACKED_PROCESSES: Mutex<Vec<TrackedProcess>> = Mutex::new(Vec::new());
+
+pub fn is_paused() -> bool {
+    PAUSED.load(Ordering::Relaxed)
+}
+
+fn track(child: &SharedChild, is_job: bool) {
+    let mut tracked_processes = TRACKED_PROCESSES.lock().unwrap();
+    tracked_processes.retain(|process| process.child.strong_count() > 0);
+    tracked_processes.push(TrackedProcess { child: Arc::downgrade(child), is_job });
+}
+
+/// Registers a FFMpeg child process, it is stopped while paused or throttled until its handle is dropped
+pub fn track_ffmpeg(child: &SharedChild) {
+    track(child, false)
+}
+
+/// Registers a child process running a queue job, it is sent the pause and resume signals instead of being stopped
+pub fn track_job(child: &SharedChild) {
+    track(child, true)
+}
+
+/// Returns the exit status of a shared child process if it has exited
+pub fn try_wait(child: &SharedChild) -> Result<Option<process::ExitStatus>, IOError> {
+    child.lock().unwrap().try_wait()
+}
+
+/// Waits for a shared child process to exit, blocking the current thread
+pub fn wait(child: &SharedChild) -> Result<process::ExitStatus, IOError> {
+    loop {
+        if let Some(exit_status) = try_wait(child)? { return Ok(exit_status) }
+        std::thread::sleep(WAIT_POLL_INTERVAL);
+    }
+}
+
+/// Waits for a shared child process to exit without blocking the runtime
+pub async fn wait_async(child: &SharedChild) -> Result<process::ExitStatus, IOError> {
+    loop {
+        if let Some(exit_status) = try_wait(child)? { return Ok(exit_status) }
+        tokio::time::sleep(WAIT_POLL_INTERVAL).await;
+    }
+}
+
+#[cfg(unix)]
+fn signal_children(run: bool, include_jobs: bool) {
+    let tracked_processes = TRACKED_PROCESSES.lock().unwrap();
+    for process in tracked_processes.iter() {
+        let signal = match (process.is_job, run) {
+            (true, _) if ! include_jobs => continue,
+            (true, true) => libc::SIGUSR2,
+            (true, false) => libc::SIGUSR1,
+            (false, true) => libc::SIGCONT,
+            (false, false) => libc::SIGSTOP,
+        };
+        let Some(child) = process.child.upgrade() else { continue };
+        let mut child = child.lock().unwrap();
+        // the process id can not have been reused as long as the process has not been waited for, the handle stays
+        // locked until it has been signalled
+        if ! matches!(child.try_wait(), Ok(None)) { continue }
+        // SAFETY: only sends a signal to a running child process
+        unsafe { libc::kill(child.id() as libc::pid_t, signal); }
+    }
+}
+
+#[cfg(not(unix))]
+fn signal_children(_run: bool, _include_jobs: bool) {}
+
+/// Stops the child processes until [`resume`] is called
+pub fn pause() {
+    if PAUSED.swap(true, Ordering::Relaxed) { return }
+    log::info!("pausing, send SIGUSR2 to process {} to resume", std::process::id());
+    signal_children(false, true);
+}
+
+/// Continues the child processes stopped by [`pause`]
+pub fn resume() {
+    if ! PAUSED.swap(false, Ordering::Relaxed) { return }
+    log::info!("resuming");
+    signal_children(true, true);
+}
+
+/// Limits the share of the time the child processes run to `percent`, the processes are not limited with 100
+///
+/// The processes are stopped for the rest of each 200ms period once they have run for their share of it, which reduces
+/// the average CPU usage of the encoding in the same proportion. Only supported on unix systems.
+pub fn set_max_cpu(percent: u8) {
+    if percent >= 100 { return }
+    if cfg!(not(unix)) {
+        log::warn!("limiting the CPU usage is not supported on this platform");
+        return;
+    }
+    let run_duration = THROTTLE_PERIOD * percent as u32 / 100;
+    let stop_duration = THROTTLE_PERIOD - run_duration;
+    log::debug!("throttling the child processes to {percent}% of the time");
+    std::thread::spawn(move || {
+        while ! cancellation::is_cancelled() {
+            std::thread::sleep(run_duration);
+            if ! is_paused() { signal_children(false, false) }
+            std::thread::sleep(stop_duration);
+            if ! is_paused() { signal_children(true, false) }
+        }
+    });
+}
+
+/// Pauses on SIGUSR1 and resumes on SIGUSR2 until an error occurs, returns immediately on non-unix systems
+pub async fn handle_signals() -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut pause_signal = signal(SignalKind::user_defined1())?;
+        let mut resume_signal = signal(SignalKind::user_defined2())?;
+        log::debug!("send SIGUSR1 to process {} to pause and SIGUSR2 to resume", std::process::id());
+        loop {
+            tokio::select! {
+                _ = pause_signal.recv() => pause(),
+                _ = resume_signal.recv() => resume(),
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    Ok(())
+}
@@ -0,0 +1,149 @@
+use std::path::{Path, PathBuf};
+
+use ffmpeg_next::Rational;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::ffmpeg::escape_filter_option_value;
+
+use super::Resolution;
+
+
+/// Duration of the title card when it is not specified in the annotation file
+const DEFAULT_TITLE_CARD_DURATION_SECS: f64 = 4.0;
+
+#[derive(Debug, Error)]
+pub enum ReadError {
+    #[error("failed to read annotation file {0}: {1}")]
+    IOError(PathBuf, std::io::Error),
+    #[error("invalid annotation file {0}: {1}")]
+    DecodeError(PathBuf, serde_json::Error),
+    #[error("invalid annotation file {file_path}: the end of the `{text}` annotation is not after its start")]
+    InvalidTimeRange { file_path: PathBuf, text: String },
+    #[error("invalid annotation file {0}: the title card duration must be positive")]
+    InvalidTitleCardDuration(PathBuf),
+}
+
+/// Card displayed before the video, each specified field is drawn on its own line
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TitleCard {
+    title: Option<String>,
+    /// date of the flight, free form
+    date: Option<String>,
+    location: Option<String>,
+    /// name of the quad
+    quad: Option<String>,
+    /// duration in seconds
+    #[serde(default = "default_title_card_duration")]
+    duration: f64,
+}
+
+fn default_title_card_duration() -> f64 {
+    DEFAULT_TITLE_CARD_DURATION_SECS
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnnotationPosition {
+    Top,
+    Center,
+    #[default]
+    Bottom,
+}
+
+/// Text drawn on the video between two times
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Annotation {
+    /// time in seconds relative to the start of the transcoded video, the title card excluded
+    start: f64,
+    end: f64,
+    text: String,
+    #[serde(default)]
+    position: AnnotationPosition,
+    /// font size in pixels, defaults to 1/20 of the video height
+    font_size: Option<u32>,
+}
+
+/// Content of an annotation file
+///
+/// Example:
+/// ```json
+/// {
+///   "title_card": { "title": "Bando session", "date": "2024-05-12", "location": "Lyon", "quad": "Apex 5" },
+///   "annotations": [ { "start": 12, "end": 16.5, "text": "power loop", "position": "top" } ]
+/// }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Annotations {
+    title_card: Option<TitleCard>,
+    #[serde(default)]
+    annotations: Vec<Annotation>,
+    /// font file used to draw the texts, the default font of FFMpeg is used if not specified
+    font_file: Option<PathBuf>,
+}
+
+impl Annotations {
+
+    pub fn read_from_file<P: AsRef<Path>>(file_path: P) -> Result<Self, ReadError> {
+        let file_path = file_path.as_ref();
+        let content = fs_err::read(file_path).map_err(|error| ReadError::IOError(file_path.to_path_buf(), error))?;
+        let annotations: Self = serde_json::from_slice(&content).map_err(|error| ReadError::DecodeError(file_path.to_path_buf(), error))?;
+        if let Some(annotation) = annotations.annotations.iter().find(|annotation| annotation.end <= annotation.start) {
+            return Err(ReadError::InvalidTimeRange { file_path: file_path.to_path_buf(), text: annotation.text.clone() });
+        }
+        if annotations.title_card.as_ref().is_some_and(|title_card| title_card.duration <= 0.0) {
+            return Err(ReadError::InvalidTitleCardDuration(file_path.to_path_buf()));
+        }
+        Ok(annotations)
+    }
+
+    /// Duration in seconds of the title card, 0 if there is none
+    pub fn title_card_duration(&self) -> f64 {
+        self.title_card.as_ref().map_or(0.0, |title_card| title_card.duration)
+    }
+
+    fn drawtext_filter(&self, text: &str, font_size: u32, x: &str, y: &str, enable: Option<(f64, f64)>) -> String {
+        let mut filter = format!("drawtext=expansion=none:text={}:fontsize={font_size}:fontcolor=white:box=1:boxcolor=black@0.5:boxborderw={}:x={x}:y={y}",
+            escape_filter_option_value(text), font_size / 4);
+        if let Some(font_file) = &self.font_file {
+            filter.push_str(&format!(":fontfile={}", escape_filter_option_value(&font_file.to_string_lossy())));
+        }
+        if let Some((start, end)) = enable {
+            filter.push_str(&format!(":enable=between(t\\,{start}\\,{end})"));
+        }
+        filter
+    }
+
+    /// Filters drawing the timed annotations, to be appended to the filter chain of the video
+    pub fn ffmpeg_annotation_filter_parts(&self, resolution: Resolution) -> Vec<String> {
+        self.annotations.iter().map(|annotation| {
+            let font_size = annotation.font_size.unwrap_or(resolution.height / 20);
+            let y = match annotation.position {
+                AnnotationPosition::Top => "h/20",
+                AnnotationPosition::Center => "(h-text_h)/2",
+                AnnotationPosition::Bottom => "h-text_h-h/20",
+            };
+            self.drawtext_filter(&annotation.text, font_size, "(w-text_w)/2", y, Some((annotation.start, annotation.end)))
+        }).collect()
+    }
+
+    /// Filtergraph chains generating the title card with the specified label, `None` if there is no title card
+    pub fn ffmpeg_title_card_chain(&self, resolution: Resolution, frame_rate: Rational, label: &str) -> Option<String> {
+        let title_card = self.title_card.as_ref()?;
+        let lines = [&title_card.title, &title_card.date, &title_card.location, &title_card.quad].into_iter().flatten().collect::<Vec<_>>();
+        let font_size = resolution.height / 15;
+        let line_height = font_size * 3 / 2;
+        let first_line_y = (resolution.height as i64 - (lines.len() as i64 * line_height as i64)) / 2;
+        let drawtext_filters = lines.iter().enumerate().map(|(line_index, line)| {
+            let y = (first_line_y + line_index as i64 * line_height as i64).to_string();
+            self.drawtext_filter(line, font_size, "(w-text_w)/2", &y, None)
+        });
+        let source = format!("color=c=black:s={}x{}:r={}/{}:d={},setsar=1", resolution.width, resolution.height,
+            frame_rate.numerator(), frame_rate.denominator(), title_card.duration);
+        Some(format!("{}[{label}]", std::iter::once(source).chain(drawtext_filters).collect::<Vec<_>>().join(",")))
+    }
+
+}
@@ -0,0 +1,183 @@
+use std::{fmt::Display, io::Error as IOError, path::{Path, PathBuf}};
+
+use getset::CopyGetters;
+use lazy_static::lazy_static;
+use regex::Regex;
+use thiserror::Error;
+
+use crate::ffmpeg;
+
+
+/// Integrated loudness the gain computed from an analysis brings the audio to, the usual target of online video platforms
+pub const TARGET_LOUDNESS_LUFS: f64 = -14.0;
+
+/// Maximum true peak level after applying the gain computed from an analysis, keeps a margin to avoid clipping
+pub const MAX_TRUE_PEAK_DBFS: f64 = -1.0;
+
+#[derive(Debug, Error)]
+pub enum AnalyzeAudioError {
+    #[error("failed running {ffmpeg_path}: {error}")]
+    FailedRunningFFMpeg {
+        ffmpeg_path: String,
+        error: IOError,
+    },
+    #[error("failed analyzing the audio of {file_path}: {stderr}")]
+    FailedAnalyzingAudio {
+        file_path: PathBuf,
+        stderr: String,
+    },
+    #[error("no audio level found in the FFMpeg output for {0}, the file may not have an audio stream")]
+    NoAudioLevels(PathBuf),
+}
+
+/// Audio levels of a file measured by the FFMpeg `volumedetect` and `ebur128` filters, in dB
+#[derive(Debug, Clone, Copy, PartialEq, CopyGetters)]
+#[getset(get_copy = "pub")]
+pub struct AudioAnalysis {
+    /// mean volume in dBFS
+    mean_volume: f64,
+    /// highest sample level in dBFS, 0 means that the audio is clipping
+    max_volume: f64,
+    /// integrated loudness in LUFS, `None` if the audio is silent
+    integrated_loudness: Option<f64>,
+    /// loudness range in LU
+    loudness_range: Option<f64>,
+    /// true peak level in dBFS, takes the inter-sample peaks into account
+    true_peak: Option<f64>,
+}
+
+// parses a level which is `-inf` for silent audio
+fn parse_level(value: &str) -> f64 {
+    value.parse().unwrap_or(f64::NEG_INFINITY)
+}
+
+impl AudioAnalysis {
+
+    fn parse(ffmpeg_output: &str) -> Option<Self> {
+        lazy_static! {
+            static ref MEAN_VOLUME_RE: Regex = Regex::new(r"mean_volume: (-?[\d.]+|-inf) dB").unwrap();
+            static ref MAX_VOLUME_RE: Regex = Regex::new(r"max_volume: (-?[\d.]+|-inf) dB").unwrap();
+            static ref INTEGRATED_LOUDNESS_RE: Regex = Regex::new(r"I:\s+(-?[\d.]+) LUFS").unwrap();
+            static ref LOUDNESS_RANGE_RE: Regex = Regex::new(r"LRA:\s+(-?[\d.]+) LU").unwrap();
+            static ref TRUE_PEAK_RE: Regex = Regex::new(r"Peak:\s+(-?[\d.]+|-inf) dBFS").unwrap();
+        }
+        // the summary of the ebur128 filter is printed last
+        let last_value = |re: &Regex| re.captures_iter(ffmpeg_output).last().map(|captures| parse_level(&captures[1]));
+        Some(Self {
+            mean_volume: last_value(&MEAN_VOLUME_RE)?,
+            max_volume: last_value(&MAX_VOLUME_RE)?,
+            integrated_loudness: last_value(&INTEGRATED_LOUDNESS_RE).filter(|loudness| loudness.is_finite() && *loudness > -70.0),
+            loudness_range: last_value(&LOUDNESS_RANGE_RE),
+            true_peak: last_value(&TRUE_PEAK_RE),
+        })
+    }
+
+    /// Whether some samples reach the full scale
+    pub fn is_clipping(&self) -> bool {
+        self.max_volume >= 0.0
+    }
+
+    /// Gain factor bringing the integrated loudness to [`TARGET_LOUDNESS_LUFS`] without the true peak exceeding
+    /// [`MAX_TRUE_PEAK_DBFS`], `None` if the audio is silent
+    pub fn normalization_gain(&self) -> Option<f64> {
+        let loudness_gain = TARGET_LOUDNESS_LUFS - self.integrated_loudness?;
+        let peak = self.true_peak.unwrap_or(self.max_volume);
+        let gain_db = if peak.is_finite() { loudness_gain.min(MAX_TRUE_PEAK_DBFS - peak) } else { loudness_gain };
+        Some(10f64.powf(gain_db / 20.0))
+    }
+
+}
+
+impl Display for AudioAnalysis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let format_level = |level: Option<f64>, unit: &str| match level {
+            Some(level) if level.is_finite() => format!("{level:.1} {unit}"),
+            Some(_) => format!("-inf {unit}"),
+            None => "n/a".to_owned(),
+        };
+        writeln!(f, "Mean volume: {}", format_level(Some(self.mean_volume), "dBFS"))?;
+        writeln!(f, "Max volume: {}{}", format_level(Some(self.max_volume), "dBFS"), if self.is_clipping() { " (clipping)" } else { "" })?;
+        writeln!(f, "True peak: {}", format_level(self.true_peak, "dBFS"))?;
+        writeln!(f, "Integrated loudness: {}", format_level(self.integrated_loudness, "LUFS"))?;
+        write!(f, "Loudness range: {}", format_level(self.loudness_range, "LU"))
+    }
+}
+
+/// Measures the levels of the first audio stream of a file
+pub async fn analyze<P: AsRef<Path>>(file_path: P) -> Result<AudioAnalysis, AnalyzeAudioError> {
+    let file_path = file_path.as_ref();
+    log::info!("analyzing audio: {}", file_path.to_string_lossy());
+    let output = tokio::process::Command::new(ffmpeg::binary_path())
+        .args(["-hide_banner", "-nostats", "-v", "info", "-i"])
+        .arg(file_path)
+        .args(["-map", "0:a:0", "-af", "volumedetect,ebur128=peak=true:framelog=verbose", "-f", "null", "-"])
+        .output()
+        .await
+        .map_err(|error| AnalyzeAudioError::FailedRunningFFMpeg { ffmpeg_path: ffmpeg::binary_path().to_string_lossy().to_string(), error })?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if ! output.status.success() {
+        return Err(AnalyzeAudioError::FailedAnalyzingAudio { file_path: file_path.to_path_buf(), stderr: stderr.trim().to_owned() });
+    }
+    AudioAnalysis::parse(&stderr).ok_or_else(|| AnalyzeAudioError::NoAudioLevels(file_path.to_path_buf()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FFMPEG_OUTPUT: &str = "\
+[Parsed_ebur128_1 @ 0x5581] t: 0.1      TARGET:-23 LUFS    M: -45.2 S:-120.7     I: -45.2 LUFS       LRA:   0.0 LU  FTPK: -20.1 dBFS  TPK: -20.1 dBFS
+[Parsed_volumedetect_0 @ 0x5580] n_samples: 88200
+[Parsed_volumedetect_0 @ 0x5580] mean_volume: -23.4 dB
+[Parsed_volumedetect_0 @ 0x5580] max_volume: -3.2 dB
+[Parsed_ebur128_1 @ 0x5581] Summary:
+
+  Integrated loudness:
+    I:         -18.6 LUFS
+    Threshold: -29.0 LUFS
+
+  Loudness range:
+    LRA:         5.3 LU
+    Threshold: -39.1 LUFS
+    LRA low:   -22.0 LUFS
+    LRA high:  -16.7 LUFS
+
+  True peak:
+    Peak:       -2.1 dBFS
+";
+
+    #[test]
+    fn parses_the_summary_levels() {
+        let analysis = AudioAnalysis::parse(FFMPEG_OUTPUT).unwrap();
+        assert_eq!(analysis, AudioAnalysis {
+            mean_volume: -23.4,
+            max_volume: -3.2,
+            integrated_loudness: Some(-18.6),
+            loudness_range: Some(5.3),
+            true_peak: Some(-2.1),
+        });
+        assert!(! analysis.is_clipping());
+    }
+
+    #[test]
+    fn parses_silent_audio() {
+        let analysis = AudioAnalysis::parse("mean_volume: -inf dB\nmax_volume: -inf dB\nI:         -70.0 LUFS\nLRA:         0.0 LU\nPeak:       -inf dBFS\n").unwrap();
+        assert_eq!(analysis.mean_volume(), f64::NEG_INFINITY);
+        assert_eq!(analysis.integrated_loudness(), None);
+        assert_eq!(analysis.true_peak(), Some(f64::NEG_INFINITY));
+        assert_eq!(analysis.normalization_gain(), None);
+    }
+
+    #[test]
+    fn output_without_volume_levels_is_not_parsed() {
+        assert_eq!(AudioAnalysis::parse("Stream #0:0: Video: h264\n"), None);
+    }
+
+    #[test]
+    fn normalization_gain_is_limited_by_the_true_peak() {
+        let analysis = AudioAnalysis::parse(FFMPEG_OUTPUT).unwrap();
+        let expected_gain_db = MAX_TRUE_PEAK_DBFS + 2.1;
+        assert!((analysis.normalization_gain().unwrap() - 10f64.powf(expected_gain_db / 20.0)).abs() < 1e-9);
+    }
+
+}
@@ -0,0 +1,122 @@
+use std::{io::Error as IOError, path::{Path, PathBuf}};
+
+use getset::CopyGetters;
+use thiserror::Error;
+
+use crate::ffmpeg;
+
+
+// sample rate of the audio extracted for the correlation, enough to capture the motor noise and voice envelopes
+const SAMPLE_RATE: u32 = 8000;
+
+// envelope rates of the coarse search over the whole offset range and of the refinement around the coarse offset
+const COARSE_ENVELOPE_RATE: u32 = 100;
+const FINE_ENVELOPE_RATE: u32 = 1000;
+
+/// Maximum time offset searched between the two recordings
+pub const DEFAULT_MAX_OFFSET_SECS: u32 = 60;
+
+// correlation below which the offset found is most likely wrong
+const LOW_CONFIDENCE_THRESHOLD: f64 = 0.3;
+
+#[derive(Debug, Error)]
+pub enum AudioSyncError {
+    #[error("failed running {ffmpeg_path}: {error}")]
+    FailedRunningFFMpeg {
+        ffmpeg_path: String,
+        error: IOError,
+    },
+    #[error("failed extracting audio from {file_path}: {stderr}")]
+    FailedExtractingAudio {
+        file_path: PathBuf,
+        stderr: String,
+    },
+    #[error("not enough audio in file to synchronize: {0}")]
+    NotEnoughAudio(PathBuf),
+}
+
+/// Time offset between two recordings of the same scene found by correlating their audio
+#[derive(Debug, Clone, Copy, PartialEq, CopyGetters)]
+#[getset(get_copy = "pub")]
+pub struct AudioOffset {
+    /// position of the start of the reference recording in the other recording in seconds, negative if the reference
+    /// recording started before the other one
+    offset_secs: f64,
+    /// normalized correlation of the audio envelopes at this offset, from 0 (unrelated) to 1 (identical)
+    correlation: f64,
+}
+
+fn extract_audio(file_path: &Path, duration_secs: u32) -> Result<Vec<f32>, AudioSyncError> {
+    let output = std::process::Command::new(ffmpeg::binary_path())
+        .args(["-hide_banner", "-v", "error", "-i"])
+        .arg(file_path)
+        .args(["-t", &duration_secs.to_string(), "-vn", "-ac", "1", "-ar", &SAMPLE_RATE.to_string(), "-f", "f32le", "pipe:1"])
+        .output()
+        .map_err(|error| AudioSyncError::FailedRunningFFMpeg { ffmpeg_path: ffmpeg::binary_path().to_string_lossy().to_string(), error })?;
+    if ! output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_owned();
+        return Err(AudioSyncError::FailedExtractingAudio { file_path: file_path.to_path_buf(), stderr });
+    }
+    Ok(output.stdout.chunks_exact(4).map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap())).collect())
+}
+
+// RMS envelope with the mean removed so that the correlation is not biased by the recording levels
+fn envelope(samples: &[f32], rate: u32) -> Vec<f64> {
+    let window = (SAMPLE_RATE / rate) as usize;
+    let envelope: Vec<f64> = samples.chunks_exact(window).map(|chunk| {
+        (chunk.iter().map(|sample| (*sample as f64).powi(2)).sum::<f64>() / window as f64).sqrt()
+    }).collect();
+    let mean = envelope.iter().sum::<f64>() / envelope.len().max(1) as f64;
+    envelope.into_iter().map(|value| value - mean).collect()
+}
+
+// normalized correlation of `reference` with `other` when `reference[i]` is aligned with `other[i + lag]`
+fn correlation(reference: &[f64], other: &[f64], lag: i64) -> f64 {
+    let (mut product_sum, mut reference_energy, mut other_energy) = (0.0, 0.0, 0.0);
+    for (index, reference_value) in reference.iter().enumerate() {
+        let other_index = index as i64 + lag;
+        if other_index < 0 { continue }
+        let Some(other_value) = other.get(other_index as usize) else { break };
+        product_sum += reference_value * other_value;
+        reference_energy += reference_value.powi(2);
+        other_energy += other_value.powi(2);
+    }
+    if reference_energy == 0.0 || other_energy == 0.0 { return 0.0 }
+    product_sum / (reference_energy * other_energy).sqrt()
+}
+
+fn best_lag(reference: &[f64], other: &[f64], lags: impl Iterator<Item = i64>) -> (i64, f64) {
+    lags.map(|lag| (lag, correlation(reference, other, lag)))
+        .fold((0, f64::MIN), |best, candidate| if candidate.1 > best.1 { candidate } else { best })
+}
+
+/// Finds where the recording of `reference_file` starts in the recording of `other_file` by cross-correlating their audio
+///
+/// The offset is first searched over the whole range with a 10ms precision then refined to a 1ms precision.
+pub fn find_offset<P: AsRef<Path>, Q: AsRef<Path>>(reference_file: P, other_file: Q, max_offset_secs: u32) -> Result<AudioOffset, AudioSyncError> {
+    let (reference_file, other_file) = (reference_file.as_ref(), other_file.as_ref());
+    log::info!("synchronizing {} with {} using their audio", reference_file.to_string_lossy(), other_file.to_string_lossy());
+
+    // enough audio is extracted from the other recording to find the reference one at the maximum offset
+    let reference_samples = extract_audio(reference_file, max_offset_secs * 2)?;
+    let other_samples = extract_audio(other_file, max_offset_secs * 3)?;
+    for (file_path, samples) in [(reference_file, &reference_samples), (other_file, &other_samples)] {
+        if samples.len() < SAMPLE_RATE as usize { return Err(AudioSyncError::NotEnoughAudio(file_path.to_path_buf())) }
+    }
+
+    let coarse_max_lag = (max_offset_secs * COARSE_ENVELOPE_RATE) as i64;
+    let (coarse_lag, _) = best_lag(&envelope(&reference_samples, COARSE_ENVELOPE_RATE), &envelope(&other_samples, COARSE_ENVELOPE_RATE),
+        -coarse_max_lag..=coarse_max_lag);
+
+    let fine_factor = (FINE_ENVELOPE_RATE / COARSE_ENVELOPE_RATE) as i64;
+    let fine_center = coarse_lag * fine_factor;
+    let (fine_lag, correlation) = best_lag(&envelope(&reference_samples, FINE_ENVELOPE_RATE), &envelope(&other_samples, FINE_ENVELOPE_RATE),
+        fine_center - fine_factor..=fine_center + fine_factor);
+
+    let offset = AudioOffset { offset_secs: fine_lag as f64 / FINE_ENVELOPE_RATE as f64, correlation };
+    log::info!("audio offset found: {:.3}s (correlation {:.2})", offset.offset_secs, offset.correlation);
+    if offset.correlation < LOW_CONFIDENCE_THRESHOLD {
+        log::warn!("low audio correlation, the offset found is probably wrong, specify it manually with --osd-video-offset");
+    }
+    Ok(offset)
+}
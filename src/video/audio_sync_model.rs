@@ -0,0 +1,135 @@
+use std::{fmt::Display, path::Path, str::FromStr};
+
+use super::probe;
+
+
+/// Tempo factor correcting the audio drift of the DJI FPV Air Unit and Caddx Vista recordings
+pub const DEFAULT_SYNC_FACTOR: f64 = 1.001480;
+
+// range outside of which a factor estimated from the stream durations is not a drift but a truncated stream
+const MIN_ESTIMATED_SYNC_FACTOR: f64 = 0.995;
+const MAX_ESTIMATED_SYNC_FACTOR: f64 = 1.005;
+
+// minimum video duration for the difference between the stream durations to be significant
+const MIN_ESTIMATION_DURATION_SECS: f64 = 60.0;
+
+/// Audio drift of the recordings of an air unit firmware or recording profile
+#[derive(Debug, Clone, Copy)]
+pub struct SyncModel {
+    pub name: &'static str,
+    /// prefix of the name of the files recorded by the device, matches any file name if `None`
+    pub file_name_prefix: Option<&'static str>,
+    /// prefix of the container `encoder` tag written by the firmware, matches any recording if `None`
+    pub encoder_prefix: Option<&'static str>,
+    /// nominal frame rate of the recording profile, matches any frame rate if `None`
+    pub frame_rate: Option<(i32, i32)>,
+    /// `atempo` factor re-synchronizing the audio with the video
+    pub factor: f64,
+}
+
+impl SyncModel {
+
+    fn matches(&self, video_file: &Path, video_info: &probe::Result) -> bool {
+        let file_name_matches = self.file_name_prefix.map_or(true, |prefix|
+            video_file.file_name().is_some_and(|file_name| file_name.to_string_lossy().starts_with(prefix)));
+        let encoder_matches = self.encoder_prefix.map_or(true, |prefix|
+            video_info.encoder().as_ref().is_some_and(|encoder| encoder.starts_with(prefix)));
+        let frame_rate_matches = self.frame_rate.map_or(true, |(numerator, denominator)| {
+            let frame_rate = video_info.frame_rate();
+            frame_rate.numerator() as i64 * denominator as i64 == numerator as i64 * frame_rate.denominator() as i64
+        });
+        file_name_matches && encoder_matches && frame_rate_matches
+    }
+
+}
+
+/// Known sync models, the first matching model is used
+///
+/// Entries for other firmwares can be added once their drift is measured with `fix-video-audio --estimate-sync-factor`.
+pub const SYNC_MODELS: &[SyncModel] = &[
+    // the audio recorded by the O3 Air Unit and the Walksnail Avatar VTXs does not drift, only its volume may need fixing
+    SyncModel { name: "DJI O3 Air Unit", file_name_prefix: Some("DJI_"), encoder_prefix: None, frame_rate: None, factor: 1.0 },
+    SyncModel { name: "Walksnail Avatar", file_name_prefix: Some("Avatar"), encoder_prefix: None, frame_rate: None, factor: 1.0 },
+    SyncModel { name: "DJI FPV Air Unit / Caddx Vista", file_name_prefix: Some("DJI"), encoder_prefix: None, frame_rate: None, factor: DEFAULT_SYNC_FACTOR },
+];
+
+/// How the sync factor applied to the audio is chosen
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum SyncFactorSource {
+    /// factor of the first sync model matching the video
+    #[default]
+    Model,
+    /// factor estimated from the durations of the audio and video streams, falls back to the matching model
+    Estimate,
+    /// factor specified by the user
+    Value(f64),
+}
+
+/// Sync factor specified on the command line, must be strictly positive
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SyncFactor(f64);
+
+impl SyncFactor {
+    pub fn value(&self) -> f64 { self.0 }
+}
+
+impl FromStr for SyncFactor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse::<f64>() {
+            Ok(factor) if factor.is_finite() && factor > 0.0 => Ok(Self(factor)),
+            _ => Err(format!("invalid sync factor `{s}`, must be a positive number, e.g. 1.00148")),
+        }
+    }
+}
+
+impl Display for SyncFactor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Estimates the sync factor as the ratio of the audio stream duration to the video stream duration
+///
+/// Returns `None` if the video is too short for the estimation to be accurate or if the ratio is not plausible.
+pub fn estimate(video_info: &probe::Result) -> Option<f64> {
+    let audio_duration = video_info.audio_duration()?;
+    let video_duration = video_info.duration();
+    if video_duration < MIN_ESTIMATION_DURATION_SECS { return None }
+    let factor = audio_duration / video_duration;
+    (MIN_ESTIMATED_SYNC_FACTOR..=MAX_ESTIMATED_SYNC_FACTOR).contains(&factor).then_some(factor)
+}
+
+/// First sync model matching the video
+pub fn model(video_file: &Path, video_info: &probe::Result) -> Option<&'static SyncModel> {
+    SYNC_MODELS.iter().find(|model| model.matches(video_file, video_info))
+}
+
+/// Sync factor to apply to the audio of the video
+pub fn sync_factor(source: SyncFactorSource, video_file: &Path, video_info: &probe::Result) -> f64 {
+    let model_factor = || match model(video_file, video_info) {
+        Some(model) => {
+            log::info!("using the {} audio sync model, factor {:.6}", model.name, model.factor);
+            model.factor
+        },
+        None => {
+            log::info!("no audio sync model matches the video, using the default factor {DEFAULT_SYNC_FACTOR:.6}");
+            DEFAULT_SYNC_FACTOR
+        },
+    };
+    match source {
+        SyncFactorSource::Value(factor) => factor,
+        SyncFactorSource::Model => model_factor(),
+        SyncFactorSource::Estimate => match estimate(video_info) {
+            Some(factor) => {
+                log::info!("estimated the audio sync factor from the stream durations: {factor:.6}");
+                factor
+            },
+            None => {
+                log::warn!("failed to estimate the audio sync factor from the stream durations");
+                model_factor()
+            },
+        },
+    }
+}
@@ -0,0 +1,103 @@
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::{
+    cli::{auto_split_args::AutoSplitArgs, start_end_args::StartEndArgs, transcode_video_args::RequestedOSDButNoFileProvidedNorFound},
+    osd::{self, file::{GenericReader, ReadError as OSDFileReadError, OpenError as OSDFileOpenError}, flights::Flight},
+};
+
+use super::{probe, probe::Error as VideoProbingError, CutVideoError, Timestamp};
+
+
+#[derive(Debug, Error)]
+pub enum AutoSplitError {
+    #[error("input video file does not exist: {0}")]
+    InputVideoFileDoesNotExist(PathBuf),
+    #[error("input video file has no file name or extension: {0}")]
+    InvalidInputVideoFileName(PathBuf),
+    #[error("output directory does not exist: {0}")]
+    OutputDirDoesNotExist(PathBuf),
+    #[error("no flight detected in the OSD file")]
+    NoFlightDetected,
+    #[error("failed to get video details: {0}")]
+    FailedToGetVideoDetails(#[from] VideoProbingError),
+    #[error(transparent)]
+    RequestedOSDButNoFileProvidedNorFound(#[from] RequestedOSDButNoFileProvidedNorFound),
+    #[error(transparent)]
+    OSDFileOpenError(#[from] OSDFileOpenError),
+    #[error("OSD file read error: {0}")]
+    OSDFileReadError(#[from] OSDFileReadError),
+    #[error("failed cutting flight {flight_number}: {error}")]
+    FailedCuttingFlight {
+        flight_number: usize,
+        error: CutVideoError,
+    },
+}
+
+fn format_duration(secs: f64) -> String {
+    let secs = secs.round() as u32;
+    format!("{}m{:02}s", secs / 60, secs % 60)
+}
+
+fn flight_video_file_path(args: &AutoSplitArgs, flight_number: usize, flight: &Flight) -> Result<PathBuf, AutoSplitError> {
+    let video_file = args.video_file();
+    let invalid_file_name = || AutoSplitError::InvalidInputVideoFileName(video_file.clone());
+    let (file_stem, extension) = (video_file.file_stem().ok_or_else(invalid_file_name)?, video_file.extension().ok_or_else(invalid_file_name)?);
+    let mut file_name = file_stem.to_os_string();
+    file_name.push(format!("_flight{flight_number:02}_{}.", format_duration(flight.duration_secs())));
+    file_name.push(extension);
+    let output_dir = match args.output_dir() {
+        Some(output_dir) => output_dir.as_path(),
+        None => video_file.parent().unwrap_or(Path::new("")),
+    };
+    Ok(output_dir.join(file_name))
+}
+
+// cut interval in the video of a flight, the cut timestamps are rounded outwards to the second
+fn flight_start_end(flight: &Flight, osd_video_offset_secs: f64, padding_secs: u32, video_duration_secs: f64) -> StartEndArgs {
+    let start_secs = flight.start_secs() + osd_video_offset_secs - padding_secs as f64;
+    let end_secs = flight.end_secs() + osd_video_offset_secs + padding_secs as f64;
+    let start = (start_secs > 0.0).then(|| Timestamp::from_total_seconds(start_secs.floor() as u32));
+    let end = (end_secs < video_duration_secs).then(|| Timestamp::from_total_seconds(end_secs.ceil() as u32));
+    StartEndArgs::new(start, end)
+}
+
+/// Detects the flights from the arm/disarm events shown on the OSD and cuts the video into one file per flight
+///
+/// The flight files are named after the input video file with the flight number and duration appended.
+pub async fn auto_split(args: &AutoSplitArgs) -> Result<(), AutoSplitError> {
+    let video_file = args.video_file();
+    if ! video_file.exists() { return Err(AutoSplitError::InputVideoFileDoesNotExist(video_file.clone())) }
+    if let Some(output_dir) = args.output_dir() {
+        if ! output_dir.is_dir() { return Err(AutoSplitError::OutputDirDoesNotExist(output_dir.clone())) }
+    }
+
+    let video_info = probe(video_file)?;
+    let mut osd_file = osd::file::open(args.osd_file_path()?)?;
+    let osd_frame_shift = super::osd_frame_shift_for_video(args.osd_frame_shift(), &video_info, &mut osd_file)?;
+    let osd_video_offset_secs = osd::overlay::video_timeline::OSDVideoOffset::from_osd_frame_shift(osd_frame_shift).as_secs();
+    let frame_rate = video_info.frame_rate();
+    let video_duration_secs = video_info.frame_count() as f64 * frame_rate.denominator() as f64 / frame_rate.numerator() as f64;
+
+    let font_variant = osd_file.font_variant();
+    let flights = osd::flights::detect_flights(&osd_file.frames()?, font_variant, args.min_flight_duration() as f64);
+    if flights.is_empty() { return Err(AutoSplitError::NoFlightDetected) }
+    log::info!("detected {} flight(s)", flights.len());
+
+    for (flight_index, flight) in flights.iter().enumerate() {
+        let flight_number = flight_index + 1;
+        let start_end = flight_start_end(flight, osd_video_offset_secs, args.padding(), video_duration_secs);
+        let output_file = flight_video_file_path(args, flight_number, flight)?;
+        log::info!("flight {flight_number}: {} -> {} ({})",
+            start_end.start().unwrap_or_default(),
+            start_end.end().map(|end| end.to_string()).unwrap_or_else(|| "end".to_owned()),
+            format_duration(flight.duration_secs())
+        );
+        if args.list() { continue }
+        super::cut(video_file, &Some(&output_file), args.overwrite(), &start_end, args.preserve_metadata(), None).await
+            .map_err(|error| AutoSplitError::FailedCuttingFlight { flight_number, error })?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,150 @@
+// Encoding speed benchmark of the available video encoders
+//
+// A test video generated by FFMpeg is encoded with the software encoder and each available hardware encoder of every codec
+// using the default settings of `transcode-video`. The fastest encoder of the requested codec is recommended and written to
+// the configuration file so that `transcode-video` uses it when no encoder is specified.
+
+use std::{path::Path, time::Instant};
+
+use getset::{CopyGetters, Getters};
+use thiserror::Error;
+
+use crate::{
+    cancellation,
+    cli::transcode_video_args::{DEFAULT_VIDEO_BITRATE, DEFAULT_VIDEO_CRF},
+    config::{Config, ConfigError},
+    disk_space::format_size,
+    ffmpeg,
+};
+
+use super::hw_accel::{self, Codec, EncoderBackend};
+
+
+const TEST_VIDEO_RESOLUTION: &str = "1920x1080";
+const TEST_VIDEO_FRAME_RATE: u64 = 60;
+
+// software encoder used by default for each codec
+const SOFTWARE_ENCODERS: [(Codec, &str); 3] = [(Codec::H264, "libx264"), (Codec::Hevc, "libx265"), (Codec::Av1, "libsvtav1")];
+
+#[derive(Debug, Error)]
+pub enum BenchmarkError {
+    #[error("failed running {ffmpeg_path}: {error}")]
+    FailedRunningFFMpeg {
+        ffmpeg_path: String,
+        error: std::io::Error,
+    },
+    #[error("no working {0} encoder")]
+    NoWorkingEncoder(Codec),
+    #[error(transparent)]
+    FailedSpawningFFMpegProcess(#[from] ffmpeg::SpawnError),
+    #[error(transparent)]
+    ConfigError(#[from] ConfigError),
+}
+
+/// Encoding speed and output size of an encoder
+#[derive(Debug, Clone, Getters, CopyGetters)]
+pub struct EncoderBenchmark {
+    #[getset(get = "pub")]
+    encoder_name: String,
+    #[getset(get_copy = "pub")]
+    codec: Codec,
+    #[getset(get_copy = "pub")]
+    backend: Option<EncoderBackend>,
+    /// encoded frames per second
+    #[getset(get_copy = "pub")]
+    fps: f64,
+    /// size of the output video in bytes
+    #[getset(get_copy = "pub")]
+    output_size: u64,
+}
+
+// encoders to benchmark: the software encoder and the available hardware encoders of each codec
+fn encoders_to_benchmark() -> Result<Vec<(Codec, String, Option<EncoderBackend>)>, BenchmarkError> {
+    let ffmpeg_path = ffmpeg::binary_path();
+    let available_encoders = ffmpeg::available_encoders(ffmpeg_path)
+        .map_err(|error| BenchmarkError::FailedRunningFFMpeg { ffmpeg_path: ffmpeg_path.to_string_lossy().to_string(), error })?;
+    let mut encoders = vec![];
+    for (codec, software_encoder) in SOFTWARE_ENCODERS {
+        if available_encoders.contains(software_encoder) { encoders.push((codec, software_encoder.to_owned(), None)) }
+        for backend in EncoderBackend::DETECTION_ORDER {
+            let encoder_name = backend.encoder_name(codec);
+            if available_encoders.contains(&encoder_name) { encoders.push((codec, encoder_name, Some(backend))) }
+        }
+    }
+    Ok(encoders)
+}
+
+// encodes the test video, returns the encoding speed in frames per second or `None` if the encoder failed
+async fn encode_test_video(encoder_name: &str, backend: Option<EncoderBackend>, duration_secs: u32, output_file: &Path) -> Result<Option<f64>, BenchmarkError> {
+    let frame_count = duration_secs as u64 * TEST_VIDEO_FRAME_RATE;
+    let mut ffmpeg_command = ffmpeg::CommandBuilder::default();
+    ffmpeg_command.add_lavfi_input(&format!("testsrc2=size={TEST_VIDEO_RESOLUTION}:rate={TEST_VIDEO_FRAME_RATE}:duration={duration_secs}"));
+    match backend {
+        Some(backend) => {
+            ffmpeg_command
+                .add_global_args(&backend.global_args())
+                .add_video_filter(&hw_accel::transcode_video_filter_parts(backend, None, false).join(","))
+                .set_output_video_settings(Some(encoder_name), Some(DEFAULT_VIDEO_BITRATE), None);
+            let quality_args = backend.quality_args(DEFAULT_VIDEO_CRF);
+            ffmpeg_command.add_args(&quality_args.iter().map(String::as_str).collect::<Vec<_>>());
+        },
+        None => { ffmpeg_command.set_output_video_settings(Some(encoder_name), Some(DEFAULT_VIDEO_BITRATE), Some(DEFAULT_VIDEO_CRF)); },
+    }
+    ffmpeg_command.set_output_file(output_file).set_overwrite_output_file(true);
+
+    log::info!("benchmarking {encoder_name} encoder");
+    let start = Instant::now();
+    let result = ffmpeg_command.build().unwrap().spawn_with_progress(frame_count)?.wait().await;
+    let elapsed_secs = start.elapsed().as_secs_f64();
+    match result {
+        Ok(()) => Ok(Some(frame_count as f64 / elapsed_secs.max(f64::EPSILON))),
+        Err(error) => {
+            log::warn!("{encoder_name} encoder failed, it is excluded from the results: {error}");
+            Ok(None)
+        },
+    }
+}
+
+/// Encodes a test video of `duration_secs` seconds with each available encoder and prints their speed and output size
+///
+/// The fastest encoder of `codec` is recommended, with `save` it is written to the configuration file to be used by
+/// `transcode-video` when no encoder is specified.
+pub async fn benchmark(codec: Codec, duration_secs: u32, save: bool) -> Result<(), BenchmarkError> {
+    let mut results = vec![];
+    for (encoder_codec, encoder_name, backend) in encoders_to_benchmark()? {
+        let output_file = std::env::temp_dir().join(format!("{}_{}_benchmark_{encoder_name}.mp4", env!("CARGO_PKG_NAME"), std::process::id()));
+        cancellation::track_output(&output_file);
+        let fps = encode_test_video(&encoder_name, backend, duration_secs, &output_file).await;
+        let output_size = fs_err::metadata(&output_file).map(|metadata| metadata.len()).unwrap_or_default();
+        cancellation::output_completed(&output_file);
+        let _ = std::fs::remove_file(&output_file);
+        if let Some(fps) = fps? {
+            results.push(EncoderBenchmark { encoder_name, codec: encoder_codec, backend, fps, output_size });
+        }
+    }
+
+    if crate::process::is_dry_run() { return Ok(()) }
+
+    println!("{:<16} {:>6} {:>10}", "encoder", "fps", "size");
+    for result in &results {
+        println!("{:<16} {:>6.1} {:>10}", result.encoder_name, result.fps, format_size(result.output_size));
+    }
+
+    let recommended = results.iter()
+        .filter(|result| result.codec == codec)
+        .max_by(|result, other| result.fps.total_cmp(&other.fps))
+        .ok_or(BenchmarkError::NoWorkingEncoder(codec))?;
+    println!("\nrecommended {codec} encoder: {}", recommended.encoder_name);
+
+    if save {
+        let mut config = Config::load()?;
+        let software_encoder = SOFTWARE_ENCODERS.iter().find(|(software_codec, _)| *software_codec == codec).map(|(_, encoder_name)| *encoder_name).unwrap();
+        let backend = recommended.backend.map(|backend| backend.to_string());
+        // the hardware encoders are selected from the software encoder of the codec
+        config.set_video_encoder(software_encoder, backend.as_deref());
+        let config_file = config.save()?;
+        log::info!("default video encoder saved to {}", config_file.to_string_lossy());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,117 @@
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+use crate::{cli::{make_clip_args::MakeClipArgs, start_end_args::StartGreaterThanEndError}, ffmpeg, file::{self, TouchError}};
+
+use super::{probe, probe::Error as VideoProbingError, osd_video::{self, OSDVideoError}};
+
+
+// animated images get large quickly, longer clips are most likely a mistake
+const LONG_CLIP_DURATION_SECS: f64 = 30.0;
+
+/// Animated image format of the clips
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum, strum::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum ClipFormat {
+    #[default]
+    Gif,
+    /// animated WebP, smaller and with more colors than GIF but not supported everywhere
+    Webp,
+}
+
+impl ClipFormat {
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Gif => "gif",
+            Self::Webp => "webp",
+        }
+    }
+
+}
+
+#[derive(Debug, Error)]
+pub enum MakeClipError {
+    #[error("input video file does not exist: {0}")]
+    InputVideoFileDoesNotExist(PathBuf),
+    #[error("invalid video file path: {0}")]
+    InvalidVideoFilePath(PathBuf),
+    #[error("output file exists: {0}")]
+    OutputFileExists(PathBuf),
+    #[error(transparent)]
+    StartGreaterThanEnd(#[from] StartGreaterThanEndError),
+    #[error(transparent)]
+    OSDVideo(#[from] OSDVideoError),
+    #[error("failed to get video details: {0}")]
+    FailedToGetVideoDetails(#[from] VideoProbingError),
+    #[error(transparent)]
+    TouchError(#[from] TouchError),
+    #[error(transparent)]
+    FailedSpawningFFMpegProcess(#[from] ffmpeg::SpawnError),
+    #[error(transparent)]
+    FFMpegExitedWithError(#[from] ffmpeg::ProcessError),
+}
+
+/// Makes a short looped GIF or animated WebP clip from a part of a video, e.g. to share a crash on a forum
+///
+/// The OSD overlay video generated with the `generate-overlay-video` command can be burned into the clip. GIF clips are
+/// encoded with a palette generated from the clip itself to get the best quality out of the 256 colors of the format.
+pub async fn make_clip(args: &MakeClipArgs) -> Result<(), MakeClipError> {
+    let input_video_file = args.input_video_file();
+    if ! input_video_file.exists() { return Err(MakeClipError::InputVideoFileDoesNotExist(input_video_file.clone())) }
+    args.start_end().check_valid()?;
+    let output_file = args.output_file().ok_or_else(|| MakeClipError::InvalidVideoFilePath(input_video_file.clone()))?;
+    if ! args.overwrite() && output_file.exists() { return Err(MakeClipError::OutputFileExists(output_file)) }
+    let osd_video_file = osd_video::resolve_file(input_video_file, args.osd_video().as_ref(), args.osd())?;
+
+    let video_info = probe(input_video_file)?;
+    let start_secs = args.start_end().start().map_or(0, |start| start.total_seconds()) as f64;
+    let end_secs = args.start_end().end().map_or(video_info.duration(), |end| (end.total_seconds() as f64).min(video_info.duration()));
+    let duration_secs = end_secs - start_secs;
+    if duration_secs > LONG_CLIP_DURATION_SECS {
+        log::warn!("the clip lasts {duration_secs:.0}s, use --start and --end to make a shorter clip, the file may be very large");
+    }
+    let frame_count = (duration_secs * args.fps() as f64).ceil() as u64;
+
+    log::info!("making {} clip: {} -> {}", args.format(), input_video_file.to_string_lossy(), output_file.to_string_lossy());
+
+    file::touch(&output_file)?;
+
+    let mut ffmpeg_command = ffmpeg::CommandBuilder::default();
+    ffmpeg_command.add_input_file_slice(input_video_file, args.start_end().start(), args.start_end().end());
+
+    let mut filter_chains = vec![];
+    let source = match &osd_video_file {
+        Some(osd_video_file) => {
+            filter_chains.push(osd_video::add_input(&mut ffmpeg_command, osd_video_file, args.start_end().start(), args.start_end().end(), "osd")?);
+            "[0:v][osd]overlay=x=(W-w)/2:y=(H-h)/2:eof_action=pass,"
+        },
+        None => "[0:v]",
+    };
+    let resample = format!("fps={},scale={}:-2:flags=lanczos", args.fps(), args.width());
+    match args.format() {
+        // the palette is computed from the frames that changed to keep the colors of the moving parts
+        ClipFormat::Gif => filter_chains.push(format!("{source}{resample},split[frames][palette_frames];\
+            [palette_frames]palettegen=stats_mode=diff[palette];[frames][palette]paletteuse=dither=bayer:bayer_scale=5:diff_mode=rectangle[vo]")),
+        ClipFormat::Webp => filter_chains.push(format!("{source}{resample}[vo]")),
+    }
+
+    ffmpeg_command
+        .add_complex_filter(&filter_chains.join(";"))
+        .add_mapping("[vo]")
+        .add_args(&["-an", "-loop", &args.loop_count().to_string()])
+        .set_output_file(&output_file)
+        .set_overwrite_output_file(true)
+        .set_atomic_output(true);
+    if args.format() == ClipFormat::Webp {
+        ffmpeg_command
+            .set_output_video_codec(Some("libwebp_anim"))
+            .add_args(&["-quality", "75", "-compression_level", "6"]);
+    }
+
+    ffmpeg_command.build().unwrap().spawn_with_progress(frame_count)?.wait().await?;
+
+    log::info!("clip written: {}", output_file.to_string_lossy());
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use std::{fmt::Display, str::FromStr};
+
+use getset::CopyGetters;
+use thiserror::Error;
+
+
+/// Basic color adjustments applied with the FFMpeg eq filter
+#[derive(Debug, Clone, Copy, PartialEq, CopyGetters)]
+#[getset(get_copy = "pub")]
+pub struct ColorEq {
+    /// -1.0 to 1.0, 0 leaves the brightness unchanged
+    brightness: f64,
+    /// -1000.0 to 1000.0, 1 leaves the contrast unchanged
+    contrast: f64,
+    /// 0.0 to 3.0, 1 leaves the saturation unchanged
+    saturation: f64,
+}
+
+impl Default for ColorEq {
+    fn default() -> Self {
+        Self { brightness: 0.0, contrast: 1.0, saturation: 1.0 }
+    }
+}
+
+impl ColorEq {
+
+    pub fn ffmpeg_filter_string(&self) -> String {
+        format!("eq=brightness={}:contrast={}:saturation={}", self.brightness, self.contrast, self.saturation)
+    }
+
+}
+
+impl Display for ColorEq {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "brightness={}:contrast={}:saturation={}", self.brightness, self.contrast, self.saturation)
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("invalid color adjustments `{0}`: the format is <setting>=<value>[:<setting>=<value>...] with the settings brightness (-1 to 1), contrast (-1000 to 1000) and saturation (0 to 3)")]
+pub struct InvalidColorEq(String);
+
+impl FromStr for ColorEq {
+    type Err = InvalidColorEq;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidColorEq(s.to_owned());
+        let mut color_eq = Self::default();
+        for setting in s.split(':') {
+            let (name, value) = setting.split_once('=').ok_or_else(invalid)?;
+            let value: f64 = value.trim().parse().map_err(|_| invalid())?;
+            let (field, range) = match name.trim() {
+                "brightness" => (&mut color_eq.brightness, -1.0..=1.0),
+                "contrast" => (&mut color_eq.contrast, -1000.0..=1000.0),
+                "saturation" => (&mut color_eq.saturation, 0.0..=3.0),
+                _ => return Err(invalid()),
+            };
+            if ! range.contains(&value) { return Err(invalid()) }
+            *field = value;
+        }
+        Ok(color_eq)
+    }
+}
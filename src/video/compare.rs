@@ -0,0 +1,120 @@
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+use crate::{cli::compare_videos_args::CompareVideosArgs, ffmpeg, file::{self, TouchError}};
+
+use super::{probe, probe::Error as VideoProbingError, osd_video::{self, OSDVideoError}};
+
+
+/// How the two videos are arranged in the comparison video
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum, strum::Display)]
+#[strum(serialize_all = "kebab-case")]
+pub enum CompareLayout {
+    /// the videos are scaled to the same height and placed next to each other
+    #[default]
+    SideBySide,
+    /// the videos are scaled to the same width and placed one above the other
+    TopBottom,
+}
+
+impl CompareLayout {
+
+    fn scale_filter(&self, size: u32) -> String {
+        match self {
+            Self::SideBySide => format!("scale=-2:{size},setsar=1"),
+            Self::TopBottom => format!("scale={size}:-2,setsar=1"),
+        }
+    }
+
+    fn stack_filter(&self) -> &'static str {
+        match self {
+            Self::SideBySide => "hstack=inputs=2",
+            Self::TopBottom => "vstack=inputs=2",
+        }
+    }
+
+}
+
+#[derive(Debug, Error)]
+pub enum CompareVideosError {
+    #[error("input video file does not exist: {0}")]
+    InputVideoFileDoesNotExist(PathBuf),
+    #[error("invalid video file path: {0}")]
+    InvalidVideoFilePath(PathBuf),
+    #[error("output video file exists: {0}")]
+    OutputVideoFileExists(PathBuf),
+    #[error(transparent)]
+    OSDVideo(#[from] OSDVideoError),
+    #[error("failed to get video details: {0}")]
+    FailedToGetVideoDetails(#[from] VideoProbingError),
+    #[error(transparent)]
+    TouchError(#[from] TouchError),
+    #[error(transparent)]
+    FailedSpawningFFMpegProcess(#[from] ffmpeg::SpawnError),
+    #[error(transparent)]
+    FFMpegExitedWithError(#[from] ffmpeg::ProcessError),
+}
+
+/// Renders two videos side by side or one above the other into a single video, e.g. to compare two quads or two encoder settings
+///
+/// Each video can have its OSD overlay video generated with the `generate-overlay-video` command overlaid onto it.
+/// The videos are scaled to a common height (side by side) or width (top/bottom) and the audio of the first video is kept.
+pub async fn compare_videos(args: &CompareVideosArgs) -> Result<(), CompareVideosError> {
+    let video_files = [args.first_video_file(), args.second_video_file()];
+    for video_file in video_files {
+        if ! video_file.exists() { return Err(CompareVideosError::InputVideoFileDoesNotExist(video_file.clone())) }
+    }
+    let output_video_file = args.output_video_file().ok_or_else(|| CompareVideosError::InvalidVideoFilePath(args.first_video_file().clone()))?;
+    if ! args.overwrite() && output_video_file.exists() { return Err(CompareVideosError::OutputVideoFileExists(output_video_file)) }
+    let osd_video_files = [
+        osd_video::resolve_file(video_files[0], args.first_osd_video().as_ref(), false)?,
+        osd_video::resolve_file(video_files[1], args.second_osd_video().as_ref(), false)?,
+    ];
+
+    let video_infos = [probe(video_files[0])?, probe(video_files[1])?];
+    let layout = args.layout();
+    let size = match args.size() {
+        Some(size) => size,
+        None => match layout {
+            CompareLayout::SideBySide => video_infos.iter().map(|video_info| video_info.resolution().height).min().unwrap(),
+            CompareLayout::TopBottom => video_infos.iter().map(|video_info| video_info.resolution().width).min().unwrap(),
+        },
+    };
+
+    log::info!("comparing videos {layout}: {} + {} -> {}", video_files[0].to_string_lossy(), video_files[1].to_string_lossy(),
+        output_video_file.to_string_lossy());
+
+    file::touch(&output_video_file)?;
+
+    let mut ffmpeg_command = ffmpeg::CommandBuilder::default();
+    ffmpeg_command.add_input_file(video_files[0]).add_input_file(video_files[1]);
+
+    let mut filter_parts = vec![];
+    for (side, osd_video_file) in osd_video_files.iter().enumerate() {
+        let scale_filter = layout.scale_filter(size);
+        match osd_video_file {
+            Some(osd_video_file) => {
+                filter_parts.push(osd_video::add_input(&mut ffmpeg_command, osd_video_file, None, None, &format!("osd{side}"))?);
+                filter_parts.push(format!("[{side}:v][osd{side}]overlay=eof_action=repeat:x=(W-w)/2:y=(H-h)/2,{scale_filter}[v{side}]"));
+            },
+            None => filter_parts.push(format!("[{side}:v]{scale_filter}[v{side}]")),
+        }
+    }
+    filter_parts.push(format!("[v0][v1]{}[vo]", layout.stack_filter()));
+
+    ffmpeg_command
+        .add_complex_filter(&filter_parts.join(";"))
+        .add_mapping("[vo]")
+        .add_mapping("0:a?")
+        .set_output_video_settings(Some(args.video_encoder().as_str()), None, Some(args.video_crf()))
+        .set_output_file(&output_video_file)
+        .set_overwrite_output_file(true)
+        .set_atomic_output(true);
+
+    let frame_count = video_infos.iter().map(probe::Result::frame_count).max().unwrap();
+    ffmpeg_command.build().unwrap().spawn_with_progress(frame_count)?.wait().await?;
+
+    log::info!("comparison video written: {}", output_video_file.to_string_lossy());
+    Ok(())
+}
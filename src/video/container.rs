@@ -0,0 +1,80 @@
+// Container formats of the output videos
+//
+// By default the container of an output video is the one FFMpeg infers from its file extension, which is the extension of
+// the input video unless another output file is specified. An explicit container forces the muxer and sets the extension of
+// the default output file names.
+
+use std::{ffi::OsStr, path::Path};
+
+use strum::IntoEnumIterator;
+
+
+/// `-movflags` value moving the index of the video to the start of the file so that it can be played before being fully downloaded
+pub const FASTSTART_MOVFLAGS: &str = "+faststart";
+
+/// `-movflags` value writing a fragmented MP4 which can be played while it is being written
+pub const FRAGMENTED_MOVFLAGS: &str = "+frag_keyframe+empty_moov+default_base_moof";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, strum::Display, strum::EnumIter)]
+#[strum(serialize_all = "lowercase")]
+pub enum Container {
+    Mp4,
+    Mkv,
+    Mov,
+}
+
+impl Container {
+
+    /// Container matching the extension of a file, `None` for the extensions of other containers
+    pub fn from_path<P: AsRef<Path>>(file_path: P) -> Option<Self> {
+        let extension = file_path.as_ref().extension()?;
+        Self::iter().find(|container| extension.eq_ignore_ascii_case(container.extension()))
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Container::Mp4 => "mp4",
+            Container::Mkv => "mkv",
+            Container::Mov => "mov",
+        }
+    }
+
+    /// Name of the FFMpeg muxer writing this container
+    pub fn muxer(&self) -> &'static str {
+        match self {
+            Container::Mp4 => "mp4",
+            Container::Mkv => "matroska",
+            Container::Mov => "mov",
+        }
+    }
+
+    /// Whether the muxer of this container accepts the `-movflags` option, e.g. `+faststart`
+    pub fn supports_movflags(&self) -> bool {
+        matches!(self, Container::Mp4 | Container::Mov)
+    }
+
+}
+
+/// Extension of the default output file name: the one of the container if specified, else the one of the input video
+pub fn output_extension<'a>(input_video_file: &'a Path, container: Option<Container>) -> Option<&'a OsStr> {
+    match container {
+        Some(container) => Some(OsStr::new(container.extension())),
+        None => input_video_file.extension(),
+    }
+}
+
+/// Whether an output file can be written with the extension it has: without a container it must have the extension of the
+/// input video for the streams to be copied into the same container, with a container any extension is accepted
+pub fn output_extension_is_valid(input_video_file: &Path, output_video_file: &Path, container: Option<Container>) -> bool {
+    if let Some(container) = container {
+        if ! output_video_file.extension().is_some_and(|extension| extension.eq_ignore_ascii_case(container.extension())) {
+            log::warn!("the extension of {} does not match the {container} container", output_video_file.to_string_lossy());
+        }
+        return true;
+    }
+    match (input_video_file.extension(), output_video_file.extension()) {
+        (None, None) => true,
+        (Some(input_extension), Some(output_extension)) => input_extension.eq_ignore_ascii_case(output_extension),
+        _ => false,
+    }
+}
@@ -0,0 +1,233 @@
+// Recordings split in several files
+//
+// The DJI goggles and air units split the recordings when the video file reaches the 4GB limit of the FAT file systems,
+// each part having its own OSD file whose frame indices restart from zero.
+
+use std::{io::{Error as IOError, Write}, path::{Path, PathBuf}, str::FromStr};
+
+use derive_more::From;
+use lazy_static::lazy_static;
+use path_absolutize::Absolutize;
+use regex::Regex;
+use thiserror::Error;
+
+use crate::{
+    cancellation,
+    create_path::{create_path, CreatePathError},
+    osd::{
+        self,
+        concat::ConcatOSDFramesError,
+        file::{GenericReader, OpenError as OSDFileOpenError, ReadError as OSDFileReadError},
+    },
+};
+
+use super::{probe, probe::Error as VideoProbingError};
+
+
+// the last part of a recording is smaller than this, a recording split at the 4GB limit has its parts larger than this
+const MIN_SPLIT_FILE_SIZE: u64 = 3_900_000_000;
+
+/// Video file continuing a recording, with its OSD file if it is not found automatically
+///
+/// Parsed from `<video file path>[,<OSD file path>]`
+#[derive(Debug, Clone)]
+pub struct Continuation {
+    video_file: PathBuf,
+    osd_file: Option<PathBuf>,
+}
+
+impl Continuation {
+
+    pub fn new<P: AsRef<Path>>(video_file: P, osd_file: Option<PathBuf>) -> Self {
+        Self { video_file: video_file.as_ref().to_path_buf(), osd_file }
+    }
+
+    pub fn video_file(&self) -> &Path {
+        &self.video_file
+    }
+
+    fn osd_file(&self) -> Result<PathBuf, JoinRecordingError> {
+        match &self.osd_file {
+            Some(osd_file) => Ok(osd_file.clone()),
+            None => osd::file::find_associated_to_video_file(&self.video_file)
+                .ok_or_else(|| JoinRecordingError::ContinuationOSDFileNotFound(self.video_file.clone())),
+        }
+    }
+
+}
+
+impl FromStr for Continuation {
+    type Err = std::convert::Infallible;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Ok(match value.split_once(',') {
+            Some((video_file, osd_file)) => Self::new(video_file, Some(PathBuf::from(osd_file))),
+            None => Self::new(value, None),
+        })
+    }
+}
+
+#[derive(Debug, Error, From)]
+pub enum JoinRecordingError {
+    #[error("video file does not exist: {0}")]
+    #[from(ignore)]
+    VideoFileDoesNotExist(PathBuf),
+    #[error("no OSD file found for continuation video file {0}")]
+    #[from(ignore)]
+    ContinuationOSDFileNotFound(PathBuf),
+    #[error(transparent)]
+    VideoProbingError(VideoProbingError),
+    #[error(transparent)]
+    OSDFileOpenError(OSDFileOpenError),
+    #[error("OSD file read error: {0}")]
+    OSDFileReadError(OSDFileReadError),
+    #[error(transparent)]
+    ConcatOSDFramesError(ConcatOSDFramesError),
+    #[error(transparent)]
+    CreatePathError(CreatePathError),
+    #[error("failed to write video concat script: {0}")]
+    ConcatScriptWriteError(IOError),
+}
+
+/// Finds the video files continuing a DJI recording, e.g. `DJIG0002.mp4` and `DJIG0003.mp4` for `DJIG0001.mp4`
+///
+/// The next file in the numbering is a continuation as long as the previous one was split at the 4GB limit.
+pub fn find_continuations<P: AsRef<Path>>(video_file: P) -> Vec<PathBuf> {
+    lazy_static! { static ref DJI_VIDEO_FILE_RE: Regex = Regex::new(r"\A(DJI[GU])(\d{4})(.*)\z").unwrap(); }
+    let mut video_file = video_file.as_ref().to_path_buf();
+    let mut continuations = vec![];
+    loop {
+        let is_split = matches!(fs_err::metadata(&video_file), Ok(metadata) if metadata.len() >= MIN_SPLIT_FILE_SIZE);
+        if ! is_split { break }
+        let Some(file_name) = video_file.file_name().map(|file_name| file_name.to_string_lossy().to_string()) else { break };
+        let Some(captures) = DJI_VIDEO_FILE_RE.captures(&file_name) else { break };
+        let Ok(number) = captures[2].parse::<u32>() else { break };
+        let next_video_file = video_file.with_file_name(format!("{}{:04}{}", &captures[1], number + 1, &captures[3]));
+        if ! next_video_file.is_file() { break }
+        log::info!("found continuation of the recording: {}", next_video_file.to_string_lossy());
+        continuations.push(next_video_file.clone());
+        video_file = next_video_file;
+    }
+    continuations
+}
+
+/// Recording joined from its parts: an FFMpeg concat script listing the video files of the parts and an OSD file, the files
+/// are removed when the value is dropped
+pub struct JoinedRecording {
+    concat_script_file: PathBuf,
+    osd_file: PathBuf,
+    video_info: probe::Result,
+    video_size: u64,
+}
+
+impl JoinedRecording {
+
+    /// Concat demuxer script to use as the input video, see [`crate::ffmpeg::CommandBuilder::add_concat_script_input_slice`]
+    pub fn concat_script_file(&self) -> &Path {
+        &self.concat_script_file
+    }
+
+    pub fn osd_file(&self) -> &Path {
+        &self.osd_file
+    }
+
+    /// Details of the joined video
+    pub fn video_info(&self) -> &probe::Result {
+        &self.video_info
+    }
+
+    /// Total size of the video files of the parts
+    pub fn video_size(&self) -> u64 {
+        self.video_size
+    }
+
+}
+
+impl Drop for JoinedRecording {
+    fn drop(&mut self) {
+        for path in [&self.concat_script_file, &self.osd_file] {
+            cancellation::output_completed(path);
+            if let Err(error) = std::fs::remove_file(path) {
+                if error.kind() != std::io::ErrorKind::NotFound {
+                    log::warn!("failed to remove joined recording file {}: {error}", path.to_string_lossy());
+                }
+            }
+        }
+    }
+}
+
+fn write_concat_script(video_files: &[PathBuf], script_path: &Path) -> Result<(), IOError> {
+    let mut writer = std::io::BufWriter::new(fs_err::File::create(script_path)?);
+    writeln!(writer, "ffconcat version 1.0")?;
+    for video_file in video_files {
+        writeln!(writer, "{}", concat_script_file_line(&video_file.absolutize()?))?;
+    }
+    writer.flush()
+}
+
+// the path is quoted, the quotes it contains being escaped
+fn concat_script_file_line(video_file: &Path) -> String {
+    format!("file '{}'", video_file.to_string_lossy().replace('\'', r"'\''"))
+}
+
+/// Joins a recording and its continuations into an FFMpeg concat script and one OSD file written in `work_dir`
+///
+/// The videos are not copied, the concat script is meant to be read as the input of the transcode. The OSD frames of each
+/// part are placed after the frames of the previous parts according to the duration of their videos, the joined OSD file
+/// having the format of the OSD files of the parts.
+pub fn join<P: AsRef<Path>, Q: AsRef<Path>, R: AsRef<Path>>(video_file: P, osd_file: Q, continuations: &[Continuation], work_dir: R)
+        -> Result<JoinedRecording, JoinRecordingError> {
+    let (video_file, work_dir) = (video_file.as_ref(), work_dir.as_ref());
+
+    let mut video_files = vec![video_file.to_path_buf()];
+    let mut osd_files = vec![osd_file.as_ref().to_path_buf()];
+    for continuation in continuations {
+        if ! continuation.video_file().is_file() { return Err(JoinRecordingError::VideoFileDoesNotExist(continuation.video_file().to_path_buf())) }
+        video_files.push(continuation.video_file().to_path_buf());
+        osd_files.push(continuation.osd_file()?);
+    }
+
+    let mut osd_segments = vec![];
+    let mut video_infos = vec![];
+    let mut video_size = 0;
+    for (video_file, osd_file) in video_files.iter().zip(&osd_files) {
+        log::info!("joining recording part: {} + {}", video_file.to_string_lossy(), osd_file.to_string_lossy());
+        video_infos.push(probe(video_file)?);
+        video_size += fs_err::metadata(video_file).map(|metadata| metadata.len()).unwrap_or_default();
+        osd_segments.push(osd::file::open(osd_file)?.frames()?);
+    }
+    let video_durations = video_infos.iter().map(probe::Result::duration).collect::<Vec<_>>();
+    let segment_start_frames = osd::concat::segment_start_frames(&video_durations, osd_segments.len());
+    let osd_frames = osd::concat::concat_frames(&osd_segments, &segment_start_frames)?;
+    let video_info = probe::Result::concatenated(&video_infos).unwrap();
+
+    let file_stem = video_file.file_stem().unwrap_or_default().to_string_lossy();
+    let joined_file_name = format!("{}_{}_{file_stem}_joined", env!("CARGO_PKG_NAME"), std::process::id());
+    let concat_script_file = work_dir.join(&joined_file_name).with_extension("ffconcat");
+    let joined_osd_file = work_dir.join(joined_file_name).with_extension("osd");
+
+    // the files are written in dry-run mode too since the transcode reads them to build its command
+    create_path(work_dir)?;
+    // created before writing the files so that they get removed if joining fails
+    let joined_recording = JoinedRecording { concat_script_file, osd_file: joined_osd_file, video_info, video_size };
+    cancellation::track_output(joined_recording.concat_script_file());
+    cancellation::track_output(joined_recording.osd_file());
+
+    write_concat_script(&video_files, joined_recording.concat_script_file())?;
+    osd::concat::write_osd_file(joined_recording.osd_file(), &osd_frames)?;
+    log::info!("joined {} recording parts: {:.1}s of video, {} OSD frames", video_files.len(), video_durations.iter().sum::<f64>(), osd_frames.len());
+
+    Ok(joined_recording)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concat_script_file_lines_quote_the_paths() {
+        assert_eq!(concat_script_file_line(Path::new("/videos/DJIG0002.mp4")), "file '/videos/DJIG0002.mp4'");
+        assert_eq!(concat_script_file_line(Path::new("/videos/pilot's flight/DJIG0002.mp4")), r"file '/videos/pilot'\''s flight/DJIG0002.mp4'");
+    }
+
+}
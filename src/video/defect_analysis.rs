@@ -0,0 +1,261 @@
+// Detection of the dead and stuck pixels of a camera
+//
+// Frames evenly spread over a video are decoded as grayscale pictures. A pixel whose value stands out from its neighbors in
+// nearly all the sampled frames cannot be part of the filmed scene since the scene moves, it is a defect of the sensor or
+// dirt on the lens. The adjacent defective pixels are grouped into regions ready to be used with --remove-video-defects.
+
+use std::{collections::VecDeque, io::{Error as IOError, ErrorKind as IOErrorKind, Read}, path::{Path, PathBuf}};
+
+use getset::CopyGetters;
+use itertools::Itertools;
+use thiserror::Error;
+
+use crate::{config::{Config, ConfigError}, ffmpeg};
+
+use super::{probe, probe::Error as VideoProbingError, resolution::Resolution};
+
+
+// minimum difference between a pixel and the average of its 8 neighbors for it to be an outlier in a frame
+const OUTLIER_THRESHOLD: u16 = 40;
+
+// share of the sampled frames in which a pixel must be an outlier to be a defect
+const MIN_OUTLIER_FRAME_RATIO: f64 = 0.9;
+
+// regions larger than this are static parts of the picture, e.g. a burnt-in timestamp, rather than defects
+const MAX_DEFECT_SIZE: u32 = 10;
+
+/// Minimum number of frames to sample for the detection to be reliable
+pub const MIN_SAMPLE_COUNT: u32 = 5;
+
+#[derive(Debug, Error)]
+pub enum DefectAnalysisError {
+    #[error("input video file does not exist: {0}")]
+    InputVideoFileDoesNotExist(PathBuf),
+    #[error("failed to get video details: {0}")]
+    FailedToGetVideoDetails(#[from] VideoProbingError),
+    #[error(transparent)]
+    FailedSpawningFFMpegProcess(#[from] ffmpeg::SpawnError),
+    #[error(transparent)]
+    FFMpegExitedWithError(#[from] ffmpeg::ProcessError),
+    #[error("failed reading the frames decoded by ffmpeg: {0}")]
+    FrameReadError(IOError),
+    #[error("only {0} frames could be sampled, at least {MIN_SAMPLE_COUNT} are needed")]
+    NotEnoughFrames(u32),
+    #[error(transparent)]
+    ConfigError(#[from] ConfigError),
+}
+
+/// Rectangle of defective pixels with a one pixel margin for the delogo filter to interpolate from the surrounding pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CopyGetters)]
+#[getset(get_copy = "pub")]
+pub struct DefectRegion {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+impl DefectRegion {
+
+    /// Region formatted like the --remove-video-defects regions: `<x>,<y>:<width>x<height>`
+    pub fn to_region_string(&self) -> String {
+        format!("{},{}:{}x{}", self.x, self.y, self.width, self.height)
+    }
+
+}
+
+// counts for each pixel the number of frames in which it stands out from its neighbors, the border pixels are not counted
+fn count_outliers(frame: &[u8], resolution: Resolution, outlier_counts: &mut [u32]) {
+    let (width, height) = (resolution.width as usize, resolution.height as usize);
+    // a frame narrower or lower than 3 pixels, e.g. from a broken probe result, has no pixel with all its neighbors
+    if width < 3 || height < 3 { return }
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let pixel = |dx: usize, dy: usize| frame[(y + dy - 1) * width + x + dx - 1] as u16;
+            let neighbor_sum: u16 = [(0, 0), (1, 0), (2, 0), (0, 1), (2, 1), (0, 2), (1, 2), (2, 2)].iter().map(|(dx, dy)| pixel(*dx, *dy)).sum();
+            if (pixel(1, 1) * 8).abs_diff(neighbor_sum) >= OUTLIER_THRESHOLD * 8 {
+                outlier_counts[y * width + x] += 1;
+            }
+        }
+    }
+}
+
+// groups the 8-connected defective pixels into their bounding rectangles
+fn group_defects(defects: &[bool], resolution: Resolution) -> Vec<(u32, u32, u32, u32)> {
+    let (width, height) = (resolution.width as usize, resolution.height as usize);
+    let mut visited = vec![false; defects.len()];
+    let mut groups = vec![];
+    for start_index in 0..defects.len() {
+        if ! defects[start_index] || visited[start_index] { continue }
+        visited[start_index] = true;
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (usize::MAX, usize::MAX, 0, 0);
+        let mut queue = VecDeque::from([start_index]);
+        while let Some(index) = queue.pop_front() {
+            let (x, y) = (index % width, index / width);
+            (min_x, min_y, max_x, max_y) = (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y));
+            for (nx, ny) in (x.saturating_sub(1)..=(x + 1).min(width - 1)).cartesian_product(y.saturating_sub(1)..=(y + 1).min(height - 1)) {
+                let neighbor_index = ny * width + nx;
+                if defects[neighbor_index] && ! visited[neighbor_index] {
+                    visited[neighbor_index] = true;
+                    queue.push_back(neighbor_index);
+                }
+            }
+        }
+        groups.push((min_x as u32, min_y as u32, max_x as u32, max_y as u32));
+    }
+    groups
+}
+
+fn defect_regions(outlier_counts: &[u32], sample_count: u32, resolution: Resolution) -> Vec<DefectRegion> {
+    let min_outlier_count = (sample_count as f64 * MIN_OUTLIER_FRAME_RATIO).ceil() as u32;
+    let defects = outlier_counts.iter().map(|count| *count >= min_outlier_count).collect::<Vec<_>>();
+    group_defects(&defects, resolution).into_iter().filter_map(|(min_x, min_y, max_x, max_y)| {
+        if max_x - min_x + 1 > MAX_DEFECT_SIZE || max_y - min_y + 1 > MAX_DEFECT_SIZE {
+            log::warn!("ignoring static area at {min_x},{min_y}: {}x{} pixels is too large to be a defect", max_x - min_x + 1, max_y - min_y + 1);
+            return None;
+        }
+        let (x, y) = (min_x.saturating_sub(1), min_y.saturating_sub(1));
+        let width = (max_x + 2).min(resolution.width) - x;
+        let height = (max_y + 2).min(resolution.height) - y;
+        Some(DefectRegion { x, y, width, height })
+    }).collect()
+}
+
+/// Samples `sample_count` frames evenly spread over a video and returns the regions of the dead and stuck pixels found
+///
+/// Returns `None` in dry-run mode once the decoding command printed. The video should be moving for the scene not to
+/// be mistaken for defects.
+pub async fn detect_defects<P: AsRef<Path>>(video_file: P, sample_count: u32) -> Result<Option<Vec<DefectRegion>>, DefectAnalysisError> {
+    let video_file = video_file.as_ref();
+    if ! video_file.is_file() { return Err(DefectAnalysisError::InputVideoFileDoesNotExist(video_file.to_path_buf())) }
+    let video_info = probe(video_file)?;
+    let resolution = video_info.resolution();
+    let duration = video_info.duration().max(f64::EPSILON);
+
+    let mut ffmpeg_command = ffmpeg::CommandBuilder::default();
+    ffmpeg_command
+        .add_input_file(video_file)
+        .add_video_filter(&format!("fps={sample_count}/{duration:.3},format=gray"))
+        .set_output_video_codec(Some("rawvideo"))
+        .add_args(&["-an", "-frames:v", &sample_count.to_string(), "-f", "rawvideo"])
+        .set_output_file("pipe:1");
+    let mut ffmpeg_process = ffmpeg_command.build().unwrap().spawn_piped_output()?;
+    let Some(mut frames_stream) = ffmpeg_process.take_stdout() else { return Ok(None) };
+
+    log::info!("sampling {sample_count} frames of {}", video_file.to_string_lossy());
+    // the pipe is read with blocking reads so they are run outside of the async runtime threads
+    let read_result = tokio::task::spawn_blocking(move || {
+        let mut frame = vec![0; resolution.width as usize * resolution.height as usize];
+        let mut outlier_counts = vec![0; frame.len()];
+        let mut frame_count = 0;
+        loop {
+            match frames_stream.read_exact(&mut frame) {
+                Ok(()) => {},
+                Err(error) if error.kind() == IOErrorKind::UnexpectedEof => break,
+                Err(error) => return Err(error),
+            }
+            count_outliers(&frame, resolution, &mut outlier_counts);
+            frame_count += 1;
+        }
+        Ok((outlier_counts, frame_count))
+    }).await.unwrap_or_else(|error| Err(IOError::new(IOErrorKind::Other, error)));
+    ffmpeg_process.wait().await?;
+    let (outlier_counts, frame_count) = read_result.map_err(DefectAnalysisError::FrameReadError)?;
+    if frame_count < MIN_SAMPLE_COUNT { return Err(DefectAnalysisError::NotEnoughFrames(frame_count)) }
+
+    Ok(Some(defect_regions(&outlier_counts, frame_count, resolution)))
+}
+
+/// Detects the defects of a video and prints them as a --remove-video-defects argument, with `save` the regions are written
+/// to the configuration file to be removed by `transcode-video` when --remove-video-defects is not specified
+pub async fn analyze_defects<P: AsRef<Path>>(video_file: P, sample_count: u32, save: bool) -> Result<(), DefectAnalysisError> {
+    let Some(defect_regions) = detect_defects(video_file, sample_count).await? else { return Ok(()) };
+    if defect_regions.is_empty() {
+        println!("no defect found");
+        return Ok(());
+    }
+    let regions = defect_regions.iter().map(DefectRegion::to_region_string).join(";");
+    println!("found {} defects", defect_regions.len());
+    println!("--remove-video-defects '{regions}'");
+    if save {
+        let mut config = Config::load()?;
+        config.set_remove_video_defects(&regions);
+        let config_file = config.save()?;
+        log::info!("video defects saved to {}", config_file.to_string_lossy());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESOLUTION: Resolution = Resolution { width: 6, height: 5 };
+
+    fn frame(defects: &[(usize, usize, u8)]) -> Vec<u8> {
+        let mut frame = vec![100; (RESOLUTION.width * RESOLUTION.height) as usize];
+        for (x, y, value) in defects {
+            frame[y * RESOLUTION.width as usize + x] = *value;
+        }
+        frame
+    }
+
+    fn defect_map(defect_positions: &[(usize, usize)]) -> Vec<bool> {
+        let mut defects = vec![false; (RESOLUTION.width * RESOLUTION.height) as usize];
+        for (x, y) in defect_positions {
+            defects[y * RESOLUTION.width as usize + x] = true;
+        }
+        defects
+    }
+
+    #[test]
+    fn counts_pixels_standing_out_from_their_neighbors() {
+        let mut outlier_counts = vec![0; (RESOLUTION.width * RESOLUTION.height) as usize];
+        count_outliers(&frame(&[(2, 2, 255)]), RESOLUTION, &mut outlier_counts);
+        count_outliers(&frame(&[(2, 2, 0), (4, 1, 120)]), RESOLUTION, &mut outlier_counts);
+        assert_eq!(outlier_counts[2 * RESOLUTION.width as usize + 2], 2);
+        // below the threshold
+        assert_eq!(outlier_counts[RESOLUTION.width as usize + 4], 0);
+        assert_eq!(outlier_counts.iter().sum::<u32>(), 2);
+    }
+
+    #[test]
+    fn degenerate_resolutions_count_no_outliers() {
+        for resolution in [Resolution { width: 0, height: 0 }, Resolution { width: 6, height: 2 }, Resolution { width: 1, height: 5 }] {
+            let mut outlier_counts = vec![0; (resolution.width * resolution.height) as usize];
+            count_outliers(&vec![0; outlier_counts.len()], resolution, &mut outlier_counts);
+            assert!(outlier_counts.iter().all(|count| *count == 0));
+        }
+    }
+
+    #[test]
+    fn border_pixels_are_not_counted() {
+        let mut outlier_counts = vec![0; (RESOLUTION.width * RESOLUTION.height) as usize];
+        count_outliers(&frame(&[(0, 0, 255), (5, 4, 255), (0, 2, 255)]), RESOLUTION, &mut outlier_counts);
+        assert!(outlier_counts.iter().all(|count| *count == 0));
+    }
+
+    #[test]
+    fn groups_adjacent_defects_into_bounding_rectangles() {
+        let defects = defect_map(&[(1, 1), (2, 2), (4, 1), (4, 4), (5, 4)]);
+        assert_eq!(group_defects(&defects, RESOLUTION), [(1, 1, 2, 2), (4, 1, 4, 1), (4, 4, 5, 4)]);
+    }
+
+    #[test]
+    fn no_defect_gives_no_group() {
+        assert!(group_defects(&defect_map(&[]), RESOLUTION).is_empty());
+    }
+
+    #[test]
+    fn defect_regions_have_a_margin_clamped_to_the_picture() {
+        let mut outlier_counts = vec![0; (RESOLUTION.width * RESOLUTION.height) as usize];
+        outlier_counts[0] = 10;
+        outlier_counts[2 * RESOLUTION.width as usize + 2] = 10;
+        outlier_counts[3 * RESOLUTION.width as usize + 3] = 8;
+        assert_eq!(defect_regions(&outlier_counts, 10, RESOLUTION), [
+            DefectRegion { x: 0, y: 0, width: 2, height: 2 },
+            DefectRegion { x: 1, y: 1, width: 3, height: 3 },
+        ]);
+    }
+
+}
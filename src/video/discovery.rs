@@ -0,0 +1,132 @@
+// Discovery of the recordings stored in directories
+//
+// The directories are scanned for video files which are paired with the OSD file recorded along with them, found the same
+// way as the OSD files of the commands auto-detecting them. The OSD kind is read from the header of the OSD files and the
+// duration probed from the videos. The symbolic links to directories are not followed and the videos written by the tool
+// are skipped.
+
+use std::{io::Error as IOError, path::{Path, PathBuf}};
+
+use getset::{CopyGetters, Getters};
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::osd;
+
+
+/// Extensions of the video files looked for, compared case-insensitively
+pub const VIDEO_FILE_EXTENSIONS: [&str; 3] = ["mp4", "mov", "mkv"];
+
+/// Suffixes of the default output file names of the commands writing videos, the videos named with them are not recordings
+pub const OUTPUT_SUFFIXES: [&str; 6] = ["with_osd", "transcoded", "cut", "fixed_audio", "pip", "osd"];
+
+#[derive(Debug, Error)]
+pub enum DiscoveryError {
+    #[error("directory does not exist: {0}")]
+    DirectoryDoesNotExist(PathBuf),
+    #[error("failed to read directory {}: {error}", dir.to_string_lossy())]
+    ReadDirError { dir: PathBuf, error: IOError },
+}
+
+/// Video file found in the scanned directories with its OSD file
+#[derive(Debug, Clone, Serialize, Getters, CopyGetters)]
+pub struct Recording {
+    #[getset(get = "pub")]
+    video_file: PathBuf,
+    #[getset(get = "pub")]
+    osd_file: Option<PathBuf>,
+    /// none if there is no OSD file or if it could not be opened
+    #[getset(get_copy = "pub")]
+    osd_kind: Option<osd::Kind>,
+    /// duration of the video in seconds, none if it could not be probed
+    #[getset(get_copy = "pub")]
+    duration: Option<f64>,
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension().is_some_and(|extension| extensions.iter().any(|candidate| extension.eq_ignore_ascii_case(candidate)))
+}
+
+// whether the video file is named like an output of the tool with the default output template
+fn is_tool_output(path: &Path) -> bool {
+    path.file_stem().is_some_and(|stem| {
+        let stem = stem.to_string_lossy();
+        OUTPUT_SUFFIXES.iter().any(|suffix| stem.strip_suffix(suffix).is_some_and(|stem| stem.len() > 1 && stem.ends_with('_')))
+    })
+}
+
+// lists the video files of a directory paired with their OSD file and the subdirectories to scan, hidden entries are skipped
+// and the symbolic links to directories are not returned so that the scan cannot loop
+fn scan_dir(dir: &Path) -> Result<(Vec<(PathBuf, Option<PathBuf>)>, Vec<PathBuf>), DiscoveryError> {
+    let read_dir_error = |error| DiscoveryError::ReadDirError { dir: dir.to_path_buf(), error };
+    let mut recordings = vec![];
+    let mut sub_dirs = vec![];
+    for entry in std::fs::read_dir(dir).map_err(read_dir_error)? {
+        let entry = entry.map_err(read_dir_error)?;
+        let path = entry.path();
+        if path.file_name().is_some_and(|file_name| file_name.to_string_lossy().starts_with('.')) { continue }
+        let file_type = entry.file_type().map_err(read_dir_error)?;
+        if file_type.is_dir() {
+            sub_dirs.push(path);
+        } else if file_type.is_symlink() && path.is_dir() {
+            log::debug!("not following symbolic link to directory {}", path.to_string_lossy());
+        } else if has_extension(&path, &VIDEO_FILE_EXTENSIONS) && ! is_tool_output(&path) {
+            let osd_file = osd::file::locate_associated_to_video_file(&path);
+            recordings.push((path, osd_file));
+        }
+    }
+    Ok((recordings, sub_dirs))
+}
+
+fn probe_recording(video_file: PathBuf, osd_file: Option<PathBuf>) -> Recording {
+    let osd_kind = osd_file.as_ref().and_then(|osd_file| match osd::file::open(osd_file) {
+        Ok(reader) => Some(reader.osd_kind()),
+        Err(error) => {
+            log::warn!("{error}");
+            None
+        },
+    });
+    let duration = match super::probe(&video_file) {
+        Ok(video_info) => Some(video_info.duration()),
+        Err(error) => {
+            log::warn!("failed to probe {}: {error}", video_file.to_string_lossy());
+            None
+        },
+    };
+    Recording { video_file, osd_file, osd_kind, duration }
+}
+
+/// Recordings found in the specified directories, and in their subdirectories if `recursive`, sorted by video file path
+pub fn discover<P: AsRef<Path>>(dirs: &[P], recursive: bool) -> Result<Vec<Recording>, DiscoveryError> {
+    let mut dirs_to_scan = vec![];
+    for dir in dirs {
+        let dir = dir.as_ref();
+        if ! dir.is_dir() { return Err(DiscoveryError::DirectoryDoesNotExist(dir.to_path_buf())) }
+        dirs_to_scan.push(dir.to_path_buf());
+    }
+    let mut pairs = vec![];
+    while let Some(dir) = dirs_to_scan.pop() {
+        let (dir_pairs, sub_dirs) = scan_dir(&dir)?;
+        pairs.extend(dir_pairs);
+        if recursive { dirs_to_scan.extend(sub_dirs) }
+    }
+    pairs.sort();
+    pairs.dedup();
+    Ok(pairs.into_iter().map(|(video_file, osd_file)| probe_recording(video_file, osd_file)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tool_outputs() {
+        for file_name in ["DJIG0000_with_osd.mp4", "DJIG0000_transcoded.mp4", "DJIG0000_cut.mov", "AvatarS0000_fixed_audio.mp4"] {
+            assert!(is_tool_output(Path::new(file_name)), "{file_name}");
+        }
+        for file_name in ["DJIG0000.mp4", "AvatarS0000.mp4", "cut.mp4", "_cut.mp4", "flight_transcoded_raw.mp4"] {
+            assert!(! is_tool_output(Path::new(file_name)), "{file_name}");
+        }
+    }
+
+}
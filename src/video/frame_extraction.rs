@@ -0,0 +1,233 @@
+use std::{io::{Error as IOError, Write}, path::{Path, PathBuf}};
+
+use itertools::Itertools;
+use thiserror::Error;
+
+use crate::{
+    cli::{extract_frames_args::ExtractFramesArgs, font_options::OSDFontDirError, start_end_args::StartGreaterThanEndError,
+        transcode_video_args::RequestedOSDButNoFileProvidedNorFound},
+    create_path::{create_path, CreatePathError},
+    ffmpeg,
+    osd::{
+        self,
+        file::{GenericReader, ReadError as OSDFileReadError, OpenError as OSDFileOpenError},
+        overlay::{
+            DrawFrameOverlayError, Generator as OverlayGenerator,
+            scaling::{Scaling, ScalingArgsError},
+            video_timeline::{OSDFrameShift, OSDVideoOffset, VideoTimeline},
+        },
+        tile_indices::UnknownOSDItem,
+    },
+};
+
+use super::{probe, probe::Error as VideoProbingError, FrameIndex};
+
+
+// ffmpeg numbers the frames it writes sequentially, they are renamed after their video frame index once all written
+const TEMP_FRAME_FILE_PREFIX: &str = ".extracting_";
+
+/// Image file format of the extracted frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum, strum::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum ExtractedFrameFormat {
+    /// lossless, for analysis
+    #[default]
+    Png,
+    /// smaller files, for thumbnails
+    Jpeg,
+}
+
+impl ExtractedFrameFormat {
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpg",
+        }
+    }
+
+}
+
+#[derive(Debug, Error)]
+pub enum ExtractFramesError {
+    #[error("input video file does not exist: {0}")]
+    InputVideoFileDoesNotExist(PathBuf),
+    #[error("invalid video file path: {0}")]
+    InvalidVideoFilePath(PathBuf),
+    #[error("output file exists: {0}")]
+    OutputFileExists(PathBuf),
+    #[error("frame index {frame_index} out of range, the video has {frame_count} frames")]
+    FrameIndexOutOfRange {
+        frame_index: FrameIndex,
+        frame_count: u64,
+    },
+    #[error("no frame to extract between the start and end timestamps")]
+    NoFrameToExtract,
+    #[error(transparent)]
+    StartGreaterThanEnd(#[from] StartGreaterThanEndError),
+    #[error("failed to get video details: {0}")]
+    FailedToGetVideoDetails(#[from] VideoProbingError),
+    #[error(transparent)]
+    RequestedOSDButNoFileProvidedNorFound(#[from] RequestedOSDButNoFileProvidedNorFound),
+    #[error(transparent)]
+    OSDFontDirError(#[from] OSDFontDirError),
+    #[error(transparent)]
+    OSDFileOpenError(#[from] OSDFileOpenError),
+    #[error("OSD file read error: {0}")]
+    OSDFileReadError(#[from] OSDFileReadError),
+    #[error(transparent)]
+    ScalingArgsError(#[from] ScalingArgsError),
+    #[error(transparent)]
+    DrawFrameOverlayError(#[from] DrawFrameOverlayError),
+    #[error(transparent)]
+    UnknownOSDItem(#[from] UnknownOSDItem),
+    #[error(transparent)]
+    CreatePathError(#[from] CreatePathError),
+    #[error(transparent)]
+    FailedSpawningFFMpegProcess(#[from] ffmpeg::SpawnError),
+    #[error(transparent)]
+    FFMpegExitedWithError(#[from] ffmpeg::ProcessError),
+    #[error("failed sending OSD frames to ffmpeg process: {0}")]
+    FailedSendingOSDFramesToFFMpeg(IOError),
+    #[error("failed to rename extracted frame file {file}: {error}")]
+    FailedRenamingFrameFile {
+        file: PathBuf,
+        error: IOError,
+    },
+}
+
+// indices of the video frames to extract, sorted and without duplicates
+fn frame_indices(args: &ExtractFramesArgs, video_info: &probe::Result) -> Result<Vec<FrameIndex>, ExtractFramesError> {
+    let frame_count = video_info.frame_count();
+    let frame_indices = match args.every() {
+        Some(every) => {
+            let frame_rate = video_info.frame_rate();
+            let first = args.start_end().start().map_or(0, |start| start.frame_count(frame_rate));
+            let end = args.start_end().end().map_or(frame_count, |end| end.frame_count(frame_rate).min(frame_count));
+            (first..end).step_by(every as usize).map(|frame_index| frame_index as FrameIndex).collect::<Vec<_>>()
+        },
+        None => {
+            if let Some(frame_index) = args.frames().iter().find(|frame_index| **frame_index as u64 >= frame_count) {
+                return Err(ExtractFramesError::FrameIndexOutOfRange { frame_index: *frame_index, frame_count })
+            }
+            args.frames().iter().copied().sorted_unstable().dedup().collect()
+        },
+    };
+    if frame_indices.is_empty() { return Err(ExtractFramesError::NoFrameToExtract) }
+    Ok(frame_indices)
+}
+
+fn frame_file_path(output_dir: &Path, frame_index: FrameIndex, format: ExtractedFrameFormat) -> PathBuf {
+    output_dir.join(format!("{frame_index:010}.{}", format.extension()))
+}
+
+// returns the OSD overlay frames generator along with the OSD frame shift to apply
+fn osd_frames_generator<P: AsRef<Path>>(args: &ExtractFramesArgs, osd_file_path: P, video_info: &probe::Result)
+        -> Result<(OverlayGenerator<'static>, OSDFrameShift), ExtractFramesError> {
+    let osd_scaling = Scaling::try_from_osd_args(args.osd_scaling_args(), video_info.resolution())?;
+    let mut osd_file = osd::file::open(osd_file_path)?;
+    let osd_frame_shift = super::osd_frame_shift_for_video(args.osd_frame_shift(), video_info, &mut osd_file)?;
+    let osd_font_dir = args.osd_font_options().osd_font_dir_with_fallback()?;
+    let osd_frames_generator = OverlayGenerator::new(
+        osd_file.frames()?,
+        args.osd_font_options().osd_font_variant(osd_file.font_variant()),
+        &osd_font_dir,
+        &args.osd_font_options().osd_font_ident(),
+        osd_scaling,
+        &[],
+        false,
+        &[]
+    )?;
+    Ok((osd_frames_generator, osd_frame_shift))
+}
+
+// sends to ffmpeg the OSD overlay frames displayed on the extracted video frames, in the same order
+fn send_osd_frames<W: Write>(osd_frames_generator: &OverlayGenerator, osd_frame_shift: OSDFrameShift, video_info: &probe::Result,
+                                frame_indices: &[FrameIndex], ffmpeg_stdin: &mut W) -> Result<(), ExtractFramesError> {
+    let frame_rate = video_info.frame_rate();
+    let timeline = VideoTimeline::new(frame_rate.numerator(), frame_rate.denominator(), OSDVideoOffset::from_osd_frame_shift(osd_frame_shift));
+    let last_frame_index = frame_indices.last().copied().unwrap_or_default();
+    let mut frames_iter = osd_frames_generator.iter_for_video_timeline(0, last_frame_index, timeline);
+    let mut frame_indices = frame_indices.iter().peekable();
+    for video_frame_index in 0..=last_frame_index {
+        let Some(frame) = frames_iter.next_shared() else { break };
+        let frame = frame?;
+        if frame_indices.next_if_eq(&&video_frame_index).is_some() {
+            ffmpeg_stdin.write_all(frame.as_raw()).map_err(ExtractFramesError::FailedSendingOSDFramesToFFMpeg)?;
+        }
+    }
+    Ok(())
+}
+
+/// Extracts frames of a video as image files named after their video frame index, optionally with the OSD composited onto them
+///
+/// The OSD frame composited onto each video frame is the one displayed on it when transcoding the video with the OSD burned in.
+pub async fn extract_frames(args: &ExtractFramesArgs) -> Result<(), ExtractFramesError> {
+    let video_file = args.video_file();
+    if ! video_file.exists() { return Err(ExtractFramesError::InputVideoFileDoesNotExist(video_file.clone())) }
+    args.start_end().check_valid()?;
+    let osd_file_path = args.osd_file_path()?;
+
+    let video_info = probe(video_file)?;
+    let frame_indices = frame_indices(args, &video_info)?;
+    let output_dir = args.output_dir().ok_or_else(|| ExtractFramesError::InvalidVideoFilePath(video_file.clone()))?;
+    let frame_files = frame_indices.iter().map(|frame_index| frame_file_path(&output_dir, *frame_index, args.format())).collect::<Vec<_>>();
+    if ! args.overwrite() {
+        if let Some(frame_file) = frame_files.iter().find(|frame_file| frame_file.exists()) {
+            return Err(ExtractFramesError::OutputFileExists(frame_file.clone()))
+        }
+    }
+
+    log::info!("extracting {} frames: {} -> {}", frame_indices.len(), video_file.to_string_lossy(), output_dir.to_string_lossy());
+
+    let select_filter = match args.every() {
+        Some(every) => format!("select='between(n,{},{})*not(mod(n-{0},{every}))'", frame_indices[0], frame_indices[frame_indices.len() - 1]),
+        None => format!("select='{}'", frame_indices.iter().map(|index| format!("eq(n,{index})")).join("+")),
+    };
+
+    let osd_frames_generator = match osd_file_path {
+        Some(osd_file_path) => Some(osd_frames_generator(args, osd_file_path, &video_info)?),
+        None => None,
+    };
+
+    let mut ffmpeg_command = ffmpeg::CommandBuilder::default();
+    ffmpeg_command.add_input_file(video_file);
+    match &osd_frames_generator {
+        Some((osd_frames_generator, _)) => {
+            ffmpeg_command.add_stdin_input(osd_frames_generator.frame_dimensions(), 1).unwrap();
+            // both streams are renumbered so that each selected video frame is paired with its OSD frame
+            ffmpeg_command.add_complex_filter(&format!(
+                "[0:v]{select_filter},setpts=N/TB[video];[1:v]setpts=N/TB[osd];[video][osd]overlay=(W-w)/2:(H-h)/2[vo]"
+            ));
+        },
+        None => { ffmpeg_command.add_complex_filter(&format!("[0:v]{select_filter}[vo]")); },
+    }
+
+    let temp_file_pattern = output_dir.join(format!("{TEMP_FRAME_FILE_PREFIX}%010d.{}", args.format().extension()));
+    ffmpeg_command
+        .add_mapping("[vo]")
+        .add_args(&["-fps_mode", "passthrough", "-start_number", "0"])
+        .set_output_file(&temp_file_pattern)
+        .set_overwrite_output_file(true);
+    if args.format() == ExtractedFrameFormat::Jpeg { ffmpeg_command.add_args(&["-q:v", "2"]); }
+
+    if ! crate::process::is_dry_run() { create_path(&output_dir)?; }
+    let mut ffmpeg_process = ffmpeg_command.build().unwrap().spawn_no_output()?;
+    let send_result = match (ffmpeg_process.take_stdin(), &osd_frames_generator) {
+        (Some(mut ffmpeg_stdin), Some((osd_frames_generator, osd_frame_shift))) =>
+            send_osd_frames(osd_frames_generator, *osd_frame_shift, &video_info, &frame_indices, &mut ffmpeg_stdin),
+        _ => Ok(()),
+    };
+    ffmpeg_process.wait().await?;
+    send_result?;
+    if crate::process::is_dry_run() { return Ok(()) }
+
+    for (sequence_number, frame_file) in frame_files.iter().enumerate() {
+        let temp_file = output_dir.join(format!("{TEMP_FRAME_FILE_PREFIX}{sequence_number:010}.{}", args.format().extension()));
+        fs_err::rename(&temp_file, frame_file)
+            .map_err(|error| ExtractFramesError::FailedRenamingFrameFile { file: temp_file.clone(), error })?;
+    }
+
+    log::info!("{} frames written to {}", frame_files.len(), output_dir.to_string_lossy());
+    Ok(())
+}
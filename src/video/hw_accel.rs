@@ -0,0 +1,179 @@
+use std::{io::Error as IOError, path::Path, process::{Command, Stdio}};
+
+use thiserror::Error;
+
+use crate::ffmpeg;
+
+use super::Resolution;
+
+
+/// Render node used when no VAAPI device is specified
+pub const DEFAULT_VAAPI_DEVICE: &str = "/dev/dri/renderD128";
+
+/// Hardware encoder families which can be used to encode the output video
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, clap::ValueEnum, strum::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum EncoderBackend {
+    /// Video Acceleration API (Linux, Intel/AMD GPUs)
+    Vaapi,
+    /// NVIDIA NVENC
+    Nvenc,
+    /// Intel Quick Sync Video
+    Qsv,
+}
+
+/// Codec families supported by the hardware encoders
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, strum::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum Codec {
+    H264,
+    Hevc,
+    Av1,
+}
+
+impl Codec {
+
+    /// Whether the hardware encoders of this codec can encode 10-bit videos
+    pub fn hw_supports_10_bit(&self) -> bool {
+        ! matches!(self, Self::H264)
+    }
+
+    /// Returns the codec family encoded by the specified FFMpeg encoder if known
+    pub fn from_encoder_name(encoder_name: &str) -> Option<Self> {
+        Some(match encoder_name {
+            "libx264" | "libopenh264" => Self::H264,
+            "libx265" | "libkvazaar" => Self::Hevc,
+            "libsvtav1" | "libaom-av1" | "librav1e" => Self::Av1,
+            _ => {
+                let (codec, _backend) = encoder_name.split_once('_')?;
+                match codec {
+                    "h264" => Self::H264,
+                    "hevc" => Self::Hevc,
+                    "av1" => Self::Av1,
+                    _ => return None,
+                }
+            },
+        })
+    }
+
+}
+
+impl EncoderBackend {
+
+    /// Backends in the order in which they are tried when automatically detecting the backend to use
+    pub const DETECTION_ORDER: [Self; 3] = [Self::Nvenc, Self::Qsv, Self::Vaapi];
+
+    /// Name of the FFMpeg encoder for the specified codec with this backend
+    pub fn encoder_name(&self, codec: Codec) -> String {
+        format!("{codec}_{self}")
+    }
+
+    /// Arguments to pass to FFMpeg before the inputs to initialize the hardware device
+    pub fn global_args(&self) -> Vec<String> {
+        let args: &[&str] = match self {
+            Self::Vaapi => &["-vaapi_device", DEFAULT_VAAPI_DEVICE],
+            Self::Qsv => &["-init_hw_device", "qsv=hw", "-filter_hw_device", "hw"],
+            // NVENC accepts frames from system memory
+            Self::Nvenc => &[],
+        };
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    /// Arguments setting the constant quality mode of the encoder since the hardware encoders do not support `-crf`
+    pub fn quality_args(&self, quality: u8) -> Vec<String> {
+        let option = match self {
+            Self::Vaapi => "-qp",
+            Self::Nvenc => "-cq",
+            Self::Qsv => "-global_quality",
+        };
+        vec![option.to_owned(), quality.to_string()]
+    }
+
+}
+
+/// Returns the filters to append to the video filter chain to feed frames to the hardware encoder
+///
+/// Filters running on the CPU (overlay, delogo) must come before these since the frames are uploaded to the GPU by them.
+/// With `ten_bit` the frames are uploaded as 10-bit frames, which the H.264 hardware encoders do not support.
+pub fn transcode_video_filter_parts(backend: EncoderBackend, output_resolution: Option<Resolution>, ten_bit: bool) -> Vec<String> {
+    let mut parts = vec![];
+    let upload_format = if ten_bit { "format=p010" } else { "format=nv12" };
+    match backend {
+        EncoderBackend::Vaapi => {
+            parts.push(upload_format.to_owned());
+            parts.push("hwupload".to_owned());
+            if let Some(resolution) = output_resolution {
+                parts.push(format!("scale_vaapi=w={}:h={}", resolution.width, resolution.height));
+            }
+        },
+        EncoderBackend::Qsv => {
+            parts.push(upload_format.to_owned());
+            parts.push("hwupload=extra_hw_frames=64".to_owned());
+            if let Some(resolution) = output_resolution {
+                parts.push(format!("scale_qsv=w={}:h={}", resolution.width, resolution.height));
+            }
+        },
+        EncoderBackend::Nvenc => {
+            if let Some(resolution) = output_resolution {
+                parts.push(format!("scale={}:{}", resolution.width, resolution.height));
+            }
+            parts.push(if ten_bit { "format=p010le" } else { "format=yuv420p" }.to_owned());
+        },
+    }
+    parts
+}
+
+#[derive(Debug, Error)]
+pub enum DetectionError {
+    #[error("failed running {ffmpeg_path}: {error}")]
+    FailedRunningFFMpeg {
+        ffmpeg_path: String,
+        error: IOError,
+    },
+    #[error("no hardware encoder available for the {0} codec")]
+    NoEncoderAvailable(Codec),
+    #[error("{0} encoder is not available with this FFMpeg build")]
+    EncoderNotAvailable(String),
+    #[error("{0} encoder failed to encode a test frame, check that the GPU and its drivers support it")]
+    EncoderNotWorking(String),
+    #[error("cannot determine the codec encoded by {0}, use a codec specific software encoder (libx264, libx265, libsvtav1) with --hw-encoder")]
+    UnknownCodec(String),
+}
+
+/// Whether a hardware encoder can actually encode with this machine by encoding a test frame
+///
+/// FFMpeg lists the hardware encoders it was built with even when the GPU or its drivers are missing.
+pub fn encoder_works<P: AsRef<Path>>(ffmpeg_path: P, backend: EncoderBackend, encoder_name: &str) -> bool {
+    let video_filter = transcode_video_filter_parts(backend, None, false).join(",");
+    let status = Command::new(ffmpeg_path.as_ref())
+        .args(["-hide_banner", "-loglevel", "error"])
+        .args(backend.global_args())
+        .args(["-f", "lavfi", "-i", "nullsrc=s=256x256", "-frames:v", "1", "-vf", &video_filter, "-c:v", encoder_name, "-f", "null", "-"])
+        .stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null())
+        .status();
+    let works = status.is_ok_and(|status| status.success());
+    if ! works { log::debug!("{encoder_name} encoder failed to encode a test frame") }
+    works
+}
+
+/// Returns the backend and encoder name to use for the specified codec
+///
+/// When `backend` is not specified the first backend in [`EncoderBackend::DETECTION_ORDER`] whose encoder is available and
+/// encodes a test frame is used.
+pub fn select_encoder<P: AsRef<Path>>(ffmpeg_path: P, codec: Codec, backend: Option<EncoderBackend>) -> Result<(EncoderBackend, String), DetectionError> {
+    let ffmpeg_path = ffmpeg_path.as_ref();
+    let encoders = ffmpeg::available_encoders(ffmpeg_path)
+        .map_err(|error| DetectionError::FailedRunningFFMpeg { ffmpeg_path: ffmpeg_path.to_string_lossy().to_string(), error })?;
+    match backend {
+        Some(backend) => {
+            let encoder_name = backend.encoder_name(codec);
+            if ! encoders.contains(&encoder_name) { return Err(DetectionError::EncoderNotAvailable(encoder_name)) }
+            if ! encoder_works(ffmpeg_path, backend, &encoder_name) { return Err(DetectionError::EncoderNotWorking(encoder_name)) }
+            Ok((backend, encoder_name))
+        },
+        None => EncoderBackend::DETECTION_ORDER.iter()
+            .map(|backend| (*backend, backend.encoder_name(codec)))
+            .find(|(backend, encoder_name)| encoders.contains(encoder_name) && encoder_works(ffmpeg_path, *backend, encoder_name))
+            .ok_or(DetectionError::NoEncoderAvailable(codec)),
+    }
+}
@@ -0,0 +1,97 @@
+use std::{io::Error as IOError, path::PathBuf};
+
+use crate::ffmpeg;
+
+
+/// Chapter mark of the output video
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chapter {
+    /// start and end times in seconds from the start of the output video
+    pub start_secs: f64,
+    pub end_secs: f64,
+    pub title: String,
+}
+
+/// Metadata written into the output container
+#[derive(Debug, Clone, Default)]
+pub struct OutputMetadata {
+    title: Option<String>,
+    /// ISO 8601 formatted
+    creation_time: Option<String>,
+    /// latitude and longitude in degrees
+    location: Option<(f64, f64)>,
+    chapters: Vec<Chapter>,
+}
+
+// escapes the special characters of the FFMpeg metadata file format
+fn escape_ffmetadata_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') { escaped.push('\\') }
+        escaped.push(c);
+    }
+    escaped
+}
+
+impl OutputMetadata {
+
+    pub fn set_title(&mut self, title: Option<String>) -> &mut Self {
+        self.title = title;
+        self
+    }
+
+    pub fn set_creation_time(&mut self, creation_time: Option<String>) -> &mut Self {
+        self.creation_time = creation_time;
+        self
+    }
+
+    pub fn set_location(&mut self, location: Option<(f64, f64)>) -> &mut Self {
+        self.location = location;
+        self
+    }
+
+    pub fn set_chapters(&mut self, chapters: Vec<Chapter>) -> &mut Self {
+        self.chapters = chapters;
+        self
+    }
+
+    // ISO 6709 location string written by the MP4/MOV muxer in the `©xyz` atom
+    fn iso6709_location(latitude: f64, longitude: f64) -> String {
+        format!("{latitude:+08.4}{longitude:+09.4}/")
+    }
+
+    // chapters in the FFMpeg metadata file format, the times are in milliseconds
+    fn ffmetadata_chapters(&self) -> String {
+        let mut ffmetadata = ";FFMETADATA1\n".to_owned();
+        for chapter in &self.chapters {
+            ffmetadata.push_str(&format!("[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle={}\n",
+                (chapter.start_secs * 1000.0).round() as u64, (chapter.end_secs * 1000.0).round() as u64, escape_ffmetadata_value(&chapter.title)));
+        }
+        ffmetadata
+    }
+
+    /// Adds the metadata options to an FFMpeg command, must be called after all the other inputs have been added
+    ///
+    /// The chapters are read by FFMpeg from a metadata file written in the temporary directory, its path is returned
+    /// so that it can be removed once the command has run.
+    pub fn add_to_ffmpeg_command(&self, ffmpeg_command: &mut ffmpeg::CommandBuilder) -> Result<Option<PathBuf>, IOError> {
+        if let Some(title) = &self.title {
+            ffmpeg_command.add_args(&["-metadata", &format!("title={title}")]);
+        }
+        if let Some(creation_time) = &self.creation_time {
+            ffmpeg_command.add_args(&["-metadata", &format!("creation_time={creation_time}")]);
+        }
+        if let Some((latitude, longitude)) = self.location {
+            ffmpeg_command.add_args(&["-metadata", &format!("location={}", Self::iso6709_location(latitude, longitude))]);
+        }
+        if self.chapters.is_empty() { return Ok(None) }
+        let chapters_file = std::env::temp_dir().join(format!("{}_{}_chapters.ffmetadata", env!("CARGO_PKG_NAME"), std::process::id()));
+        fs_err::write(&chapters_file, self.ffmetadata_chapters())?;
+        let chapters_input_index = ffmpeg_command.input_count();
+        ffmpeg_command
+            .add_input_file(&chapters_file)
+            .add_args(&["-map_chapters", &chapters_input_index.to_string()]);
+        Ok(Some(chapters_file))
+    }
+
+}
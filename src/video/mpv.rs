@@ -0,0 +1,108 @@
+use std::{io::Write, path::{Path, PathBuf}, sync::OnceLock};
+
+use fs_err::File;
+
+
+pub const DEFAULT_BINARY_PATH: &str = "mpv";
+
+static BINARY_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// Sets the path of the MPV binary to run instead of the one found in the PATH, only the first path set is used
+pub fn set_binary_path<P: AsRef<Path>>(binary_path: P) {
+    let _ = BINARY_PATH.set(binary_path.as_ref().to_path_buf());
+}
+
+/// Path of the MPV binary to run
+pub fn binary_path() -> &'static Path {
+    BINARY_PATH.get().map(PathBuf::as_path).unwrap_or(Path::new(DEFAULT_BINARY_PATH))
+}
+
+const OVERLAY_FILTER: &str = "overlay=(main_w-overlay_w)/2:(main_h-overlay_h)/2";
+
+const OPACITY_STEP_PERCENT: u8 = 10;
+
+/// Returns the `--lavfi-complex` filter graph overlaying the OSD track (`vid2`) over the video track (`vid1`)
+///
+/// `osd_video_filter` is applied to the OSD track before overlaying it, e.g. to restore its transparency.
+pub fn osd_overlay_filter_graph(osd_video_filter: Option<&str>, opacity_percent: u8) -> String {
+    let mut graph = osd_track_input(osd_video_filter);
+    if opacity_percent < 100 {
+        graph.push_str(&format!("format=rgba,colorchannelmixer=aa={:.2},", opacity_percent as f64 / 100.0));
+    }
+    graph.push_str(&format!("null[osd];[vid1][osd]{OVERLAY_FILTER}[vo]"));
+    graph
+}
+
+fn osd_track_input(osd_video_filter: Option<&str>) -> String {
+    match osd_video_filter {
+        Some(osd_video_filter) => format!("[vid2]{osd_video_filter},"),
+        None => "[vid2]".to_owned(),
+    }
+}
+
+/// Lua script controlling the OSD overlay from MPV
+///
+/// Key bindings: `Alt+o` toggles the OSD, `Alt+-` and `Alt+=` decrease and increase its opacity.
+/// The same actions can be triggered through the IPC server with the `osd-toggle` and `osd-opacity <+/-percent>` script messages.
+/// The filter graph is rebuilt on each change, MPV keeps the external OSD track in sync with the video when seeking.
+pub fn osd_control_script(osd_video_filter: Option<&str>, opacity_percent: u8) -> String {
+    let osd_track_input = osd_track_input(osd_video_filter);
+    format!(r#"local visible = true
+local opacity = {opacity_percent}
+
+local function filter_graph()
+    if not visible then return "[vid1]null[vo]" end
+    local graph = [=[{osd_track_input}]=]
+    if opacity < 100 then
+        graph = graph .. string.format("format=rgba,colorchannelmixer=aa=%.2f,", opacity / 100)
+    end
+    return graph .. "null[osd];[vid1][osd]{OVERLAY_FILTER}[vo]"
+end
+
+local function apply()
+    mp.set_property("lavfi-complex", filter_graph())
+end
+
+local function toggle()
+    visible = not visible
+    apply()
+    mp.osd_message(visible and "OSD shown" or "OSD hidden")
+end
+
+local function change_opacity(delta)
+    opacity = math.max(0, math.min(100, opacity + delta))
+    visible = true
+    apply()
+    mp.osd_message(string.format("OSD opacity: %d%%", opacity))
+end
+
+mp.add_key_binding("Alt+o", "osd-toggle-key", toggle)
+mp.add_key_binding("Alt+-", "osd-opacity-down", function() change_opacity(-{OPACITY_STEP_PERCENT}) end, {{ repeatable = true }})
+mp.add_key_binding("Alt+=", "osd-opacity-up", function() change_opacity({OPACITY_STEP_PERCENT}) end, {{ repeatable = true }})
+mp.register_script_message("osd-toggle", toggle)
+mp.register_script_message("osd-opacity", function(delta) change_opacity(tonumber(delta) or 0) end)
+"#)
+}
+
+/// Script file removed when dropped
+pub struct TempScriptFile(PathBuf);
+
+impl TempScriptFile {
+
+    pub fn create(content: &str) -> std::io::Result<Self> {
+        let path = std::env::temp_dir().join(format!("hd_fpv_video_tool_osd_control_{}.lua", std::process::id()));
+        File::create(&path)?.write_all(content.as_bytes())?;
+        Ok(Self(path))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+}
+
+impl Drop for TempScriptFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
@@ -0,0 +1,79 @@
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::{ffmpeg, osd};
+
+use super::{probe, probe::Error as VideoProbingError, Timestamp};
+
+
+#[derive(Debug, Error)]
+pub enum OSDVideoError {
+    #[error("invalid video file path: {0}")]
+    InvalidVideoFilePath(PathBuf),
+    #[error("OSD video file does not exist: {0}")]
+    OSDVideoFileDoesNotExist(PathBuf),
+    #[error("unsupported OSD video codec, the OSD video must be generated with the `generate-overlay-video` command: {0}")]
+    UnsupportedOSDVideoCodec(PathBuf),
+    #[error("failed to get OSD video details: {0}")]
+    FailedToGetVideoDetails(#[from] VideoProbingError),
+}
+
+/// How an OSD overlay video generated with the `generate-overlay-video` command is decoded with its transparency
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decoding {
+    /// decoded with the specified decoder, the libvpx decoders are needed to decode the VP8/VP9 alpha channel
+    Decoder(&'static str),
+    /// AV1 overlays have their alpha channel stacked under the color picture, see [`osd::overlay::AV1_STACKED_ALPHA_MERGE_FILTER`]
+    StackedAlpha,
+}
+
+impl Decoding {
+
+    /// Returns the decoding of an OSD overlay video encoded with the specified codec, `None` if it is not an overlay video codec
+    pub fn from_codec(codec: &str) -> Option<Self> {
+        match codec {
+            "vp8" => Some(Self::Decoder("libvpx")),
+            "vp9" => Some(Self::Decoder("libvpx-vp9")),
+            "av1" => Some(Self::StackedAlpha),
+            _ => None,
+        }
+    }
+
+}
+
+/// Returns the OSD overlay video overlaid with the `--osd-video` option or, with `use_default`, the default OSD video of
+/// the video file, see [`super::default_osd_video_file_path`]
+pub fn resolve_file(video_file: &Path, osd_video_file: Option<&PathBuf>, use_default: bool) -> Result<Option<PathBuf>, OSDVideoError> {
+    let osd_video_file = match (osd_video_file, use_default) {
+        (Some(osd_video_file), _) => osd_video_file.clone(),
+        (None, true) => super::default_osd_video_file_path(video_file)
+            .ok_or_else(|| OSDVideoError::InvalidVideoFilePath(video_file.to_path_buf()))?,
+        (None, false) => return Ok(None),
+    };
+    if ! osd_video_file.exists() { return Err(OSDVideoError::OSDVideoFileDoesNotExist(osd_video_file)) }
+    Ok(Some(osd_video_file))
+}
+
+/// Adds the OSD overlay video input cut like the video, returns the filter decoding its transparency into the `[<label>]` stream
+///
+/// The labels used by the filter are derived from `label` so that several OSD videos can be overlaid by the same command.
+pub fn add_input(ffmpeg_command: &mut ffmpeg::CommandBuilder, osd_video_file: &Path, start: Option<Timestamp>, end: Option<Timestamp>, label: &str)
+        -> Result<String, OSDVideoError> {
+    let osd_video_info = probe(osd_video_file)?;
+    let decoding = osd_video_info.video_codec().as_deref().and_then(Decoding::from_codec)
+        .ok_or_else(|| OSDVideoError::UnsupportedOSDVideoCodec(osd_video_file.to_path_buf()))?;
+    let input_index = ffmpeg_command.input_count();
+    Ok(match decoding {
+        Decoding::Decoder(decoder) => {
+            ffmpeg_command.add_input_file_slice_with_decoder(osd_video_file, start, end, decoder);
+            format!("[{input_index}:v]null[{label}]")
+        },
+        // same as osd::overlay::AV1_STACKED_ALPHA_MERGE_FILTER with labels unique to this input
+        Decoding::StackedAlpha => {
+            ffmpeg_command.add_input_file_slice(osd_video_file, start, end);
+            format!("[{input_index}:v]split[{label}_color][{label}_alpha];[{label}_color]crop=iw:ih/2:0:0[{label}_color];\
+                [{label}_alpha]crop=iw:ih/2:0:ih/2[{label}_alpha];[{label}_color][{label}_alpha]alphamerge[{label}]")
+        },
+    })
+}
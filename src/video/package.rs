@@ -0,0 +1,179 @@
+use std::{collections::HashMap, path::{Path, PathBuf}, io::{Error as IOError, Write}};
+
+use fs_err::File;
+use thiserror::Error;
+
+use crate::{
+    ffmpeg,
+    create_path::{create_path, CreatePathError},
+    osd::{self, file::{GenericReader, OpenError as OSDFileOpenError, ReadError as OSDFileReadError}, overlay::video_timeline::{OSDVideoOffset, VideoTimeline}},
+};
+
+use super::{probe, probe::Error as VideoProbingError, metadata::{Chapter, OutputMetadata}};
+
+
+const PLAYLIST_FILE_NAME: &str = "session.m3u";
+
+#[derive(Debug, Error)]
+pub enum PackageSessionError {
+    #[error("no video files provided")]
+    NoVideoFiles,
+    #[error("input video file does not exist: {0}")]
+    InputVideoFileDoesNotExist(PathBuf),
+    #[error("invalid video file path: {0}")]
+    InvalidVideoFilePath(PathBuf),
+    #[error("OSD overlay video file not found: {0}")]
+    OSDVideoFileNotFound(PathBuf),
+    #[error("output file exists: {0}")]
+    OutputFileExists(PathBuf),
+    #[error(transparent)]
+    CreatePathError(#[from] CreatePathError),
+    #[error("failed to get video details: {0}")]
+    FailedToGetVideoDetails(#[from] VideoProbingError),
+    #[error(transparent)]
+    FailedSpawningFFMpegProcess(#[from] ffmpeg::SpawnError),
+    #[error(transparent)]
+    FFMpegExitedWithError(#[from] ffmpeg::ProcessError),
+    #[error(transparent)]
+    OSDFileOpenError(#[from] OSDFileOpenError),
+    #[error("OSD file read error: {0}")]
+    OSDFileReadError(#[from] OSDFileReadError),
+    #[error("failed to write chapters file: {0}")]
+    FailedWritingChapters(IOError),
+    #[error("failed to write playlist: {0}")]
+    FailedWritingPlaylist(IOError),
+}
+
+struct PackagedFlight {
+    file_name: String,
+    title: String,
+    duration_secs: f64,
+}
+
+// output file stems, the inputs sharing the same file stem are prefixed with the name of their directory
+// and numbered if it is not enough to tell them apart
+fn output_file_stems(video_files: &[&Path]) -> Vec<String> {
+    let file_stem = |video_file: &Path| video_file.file_stem().unwrap().to_string_lossy().to_string();
+    let mut stem_counts = HashMap::new();
+    for video_file in video_files { *stem_counts.entry(file_stem(video_file)).or_insert(0) += 1 }
+    let mut used_stems = HashMap::new();
+    video_files.iter().map(|video_file| {
+        let mut output_stem = file_stem(video_file);
+        if stem_counts[&output_stem] > 1 {
+            if let Some(dir_name) = video_file.parent().and_then(Path::file_name) {
+                output_stem = format!("{}_{output_stem}", dir_name.to_string_lossy());
+            }
+        }
+        let use_count = used_stems.entry(output_stem.clone()).or_insert(0);
+        *use_count += 1;
+        if *use_count > 1 { output_stem = format!("{output_stem}_{use_count}") }
+        output_stem
+    }).collect()
+}
+
+// chapter marks of the flights detected on the OSD file associated to the video or a single chapter spanning the whole video
+// when there is no OSD file or no flight could be detected
+fn flight_chapters(video_file: &Path, video_info: &probe::Result, duration_secs: f64, title: &str) -> Result<Vec<Chapter>, PackageSessionError> {
+    let whole_video_chapter = vec![Chapter { start_secs: 0.0, end_secs: duration_secs, title: title.to_owned() }];
+    let Some(osd_file_path) = osd::file::find_associated_to_video_file(video_file) else { return Ok(whole_video_chapter) };
+    let mut osd_file = osd::file::open(osd_file_path)?;
+    let osd_frame_shift = super::osd_frame_shift_for_video(None, video_info, &mut osd_file)?;
+    let flights = osd::flights::detect_flights(&osd_file.frames()?, osd_file.font_variant(), super::processing::MIN_FLIGHT_CHAPTER_DURATION_SECS);
+    let frame_rate = video_info.frame_rate();
+    let video_timeline = VideoTimeline::new(frame_rate.numerator(), frame_rate.denominator(), OSDVideoOffset::from_osd_frame_shift(osd_frame_shift));
+    let video_time = |osd_time: f64| video_timeline.video_time(osd_time).clamp(0.0, duration_secs);
+    let chapters = flights.iter().enumerate().filter_map(|(flight_index, flight)| {
+        let (start_secs, end_secs) = (video_time(flight.start_secs()), video_time(flight.end_secs()));
+        (end_secs > start_secs).then(|| Chapter { start_secs, end_secs, title: format!("{title} - part {}", flight_index + 1) })
+    }).collect::<Vec<_>>();
+    Ok(if chapters.is_empty() { whole_video_chapter } else { chapters })
+}
+
+fn mux_flight(video_file: &Path, osd_video_file: &Path, output_file: &Path) -> ffmpeg::CommandBuilder {
+    let mut ffmpeg_command = ffmpeg::CommandBuilder::default();
+    ffmpeg_command
+        .add_input_file(video_file)
+        .add_input_file(osd_video_file)
+        .add_mappings(&["0:v", "0:a?", "1:v"])
+        .set_output_video_codec(Some("copy"))
+        .set_output_audio_codec(Some("copy"))
+        .add_args(&["-metadata:s:v:0", "title=Video", "-metadata:s:v:1", "title=OSD"])
+        // the OSD track is not meant to be displayed on its own
+        .add_args(&["-disposition:v:0", "default", "-disposition:v:1", "0"])
+        .set_output_file(output_file)
+        .set_overwrite_output_file(true)
+        .set_atomic_output(true);
+    ffmpeg_command
+}
+
+/// Muxes each video with its OSD overlay video into an MKV file with the overlay as a secondary video track
+/// and writes an M3U playlist listing all the flights of the session
+///
+/// The OSD overlay video of each video is expected to be named like the ones generated by the `generate-overlay-video` command.
+/// Each MKV file gets a chapter per flight detected on the OSD file associated to its video, or a single chapter when there is none.
+/// Inputs sharing the same file name are told apart in the output file names by the name of their directory.
+pub async fn package_session<P: AsRef<Path>, Q: AsRef<Path>>(video_files: &[P], output_dir: Q, overwrite: bool) -> Result<(), PackageSessionError> {
+    if video_files.is_empty() { return Err(PackageSessionError::NoVideoFiles) }
+    let output_dir = output_dir.as_ref();
+
+    let video_files = video_files.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+    for video_file in &video_files {
+        if ! video_file.exists() { return Err(PackageSessionError::InputVideoFileDoesNotExist(video_file.to_path_buf())) }
+        if video_file.file_stem().is_none() { return Err(PackageSessionError::InvalidVideoFilePath(video_file.to_path_buf())) }
+    }
+
+    let mut pairs = vec![];
+    for (video_file, file_stem) in video_files.iter().zip(output_file_stems(&video_files)) {
+        let osd_video_file = super::default_osd_video_file_path(video_file)
+            .ok_or_else(|| PackageSessionError::InvalidVideoFilePath(video_file.to_path_buf()))?;
+        if ! osd_video_file.exists() { return Err(PackageSessionError::OSDVideoFileNotFound(osd_video_file)) }
+        let output_file = output_dir.join(format!("{file_stem}.mkv"));
+        if ! overwrite && output_file.exists() { return Err(PackageSessionError::OutputFileExists(output_file)) }
+        pairs.push((*video_file, osd_video_file, output_file, file_stem));
+    }
+    let playlist_path = output_dir.join(PLAYLIST_FILE_NAME);
+    if ! overwrite && playlist_path.exists() { return Err(PackageSessionError::OutputFileExists(playlist_path)) }
+
+    let dry_run = crate::process::is_dry_run();
+    if ! dry_run { create_path(output_dir)?; }
+
+    let mut flights = vec![];
+    for (index, (video_file, osd_video_file, output_file, file_stem)) in pairs.into_iter().enumerate() {
+        let title = format!("Flight {} - {file_stem}", index + 1);
+        log::info!("packaging {} + {} -> {}", video_file.to_string_lossy(), osd_video_file.to_string_lossy(), output_file.to_string_lossy());
+        let video_info = probe(video_file)?;
+        let frame_rate = video_info.frame_rate();
+        let duration_secs = video_info.frame_count() as f64 * frame_rate.denominator() as f64 / frame_rate.numerator() as f64;
+        let mut metadata = OutputMetadata::default();
+        metadata
+            .set_title(Some(title.clone()))
+            .set_chapters(flight_chapters(video_file, &video_info, duration_secs, &title)?);
+        let mut ffmpeg_command = mux_flight(video_file, &osd_video_file, &output_file);
+        let chapters_file = metadata.add_to_ffmpeg_command(&mut ffmpeg_command).map_err(PackageSessionError::FailedWritingChapters)?;
+        let mux_result = async {
+            ffmpeg_command.build().unwrap().spawn_no_output()?.wait().await?;
+            Ok::<(), PackageSessionError>(())
+        }.await;
+        if let Some(chapters_file) = &chapters_file {
+            let _ = fs_err::remove_file(chapters_file);
+        }
+        mux_result?;
+        flights.push(PackagedFlight { file_name: output_file.file_name().unwrap().to_string_lossy().to_string(), title, duration_secs });
+    }
+
+    if dry_run { return Ok(()) }
+    write_playlist(&playlist_path, &flights).map_err(PackageSessionError::FailedWritingPlaylist)?;
+    log::info!("{} flights packaged, playlist written to {}", flights.len(), playlist_path.to_string_lossy());
+
+    Ok(())
+}
+
+fn write_playlist(path: &Path, flights: &[PackagedFlight]) -> std::io::Result<()> {
+    let mut playlist_file = File::create(path)?;
+    writeln!(playlist_file, "#EXTM3U")?;
+    for flight in flights {
+        writeln!(playlist_file, "#EXTINF:{},{}", flight.duration_secs.round() as u64, flight.title)?;
+        writeln!(playlist_file, "{}", flight.file_name)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+use crate::{cli::pip_args::PipArgs, ffmpeg, file::{self, TouchError}};
+
+use super::{probe, probe::Error as VideoProbingError};
+
+
+/// Corner of the main video in which the picture-in-picture window is placed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum, strum::Display)]
+#[strum(serialize_all = "kebab-case")]
+pub enum PipCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+impl PipCorner {
+
+    /// Position of the window as overlay filter expressions, `margin` being the distance to the edges of the main video
+    fn overlay_position(&self, margin: u32) -> (String, String) {
+        let (left, right) = (margin.to_string(), format!("W-w-{margin}"));
+        let (top, bottom) = (margin.to_string(), format!("H-h-{margin}"));
+        match self {
+            Self::TopLeft => (left, top),
+            Self::TopRight => (right, top),
+            Self::BottomLeft => (left, bottom),
+            Self::BottomRight => (right, bottom),
+        }
+    }
+
+}
+
+#[derive(Debug, Error)]
+pub enum PipError {
+    #[error("input video file does not exist: {0}")]
+    InputVideoFileDoesNotExist(PathBuf),
+    #[error("invalid video file path: {0}")]
+    InvalidVideoFilePath(PathBuf),
+    #[error("output video file exists: {0}")]
+    OutputVideoFileExists(PathBuf),
+    #[error("failed to get video details: {0}")]
+    FailedToGetVideoDetails(#[from] VideoProbingError),
+    #[error(transparent)]
+    TouchError(#[from] TouchError),
+    #[error(transparent)]
+    FailedSpawningFFMpegProcess(#[from] ffmpeg::SpawnError),
+    #[error(transparent)]
+    FFMpegExitedWithError(#[from] ffmpeg::ProcessError),
+}
+
+/// Overlays a second video as a picture-in-picture window over the main video, e.g. HD footage over the DJI goggles recording
+///
+/// The window is scaled to a percentage of the main video width and placed in a corner, the audio of the main video is kept.
+/// Once the second video ends the main video keeps playing without the window.
+pub async fn pip(args: &PipArgs) -> Result<(), PipError> {
+    let (main_video_file, pip_video_file) = (args.main_video_file(), args.pip_video_file());
+    for video_file in [main_video_file, pip_video_file] {
+        if ! video_file.exists() { return Err(PipError::InputVideoFileDoesNotExist(video_file.clone())) }
+    }
+    let output_video_file = args.output_video_file().ok_or_else(|| PipError::InvalidVideoFilePath(main_video_file.clone()))?;
+    if ! args.overwrite() && output_video_file.exists() { return Err(PipError::OutputVideoFileExists(output_video_file)) }
+
+    let main_video_info = probe(main_video_file)?;
+    // the width is kept even for the chroma subsampling of the encoders
+    let pip_width = (main_video_info.resolution().width * args.pip_size() as u32 / 100) & !1;
+
+    log::info!("overlaying picture-in-picture video: {} + {} -> {}", main_video_file.to_string_lossy(), pip_video_file.to_string_lossy(),
+        output_video_file.to_string_lossy());
+
+    file::touch(&output_video_file)?;
+
+    let mut pip_filter_parts = vec![format!("scale={pip_width}:-2"), "setsar=1".to_owned()];
+    if args.border() > 0 {
+        let border = args.border();
+        pip_filter_parts.push(format!("pad=iw+{}:ih+{}:{border}:{border}:color={}", 2 * border, 2 * border, ffmpeg::escape_filter_option_value(args.border_color())));
+    }
+    let (x, y) = args.corner().overlay_position(args.margin());
+
+    let mut ffmpeg_command = ffmpeg::CommandBuilder::default();
+    ffmpeg_command
+        .add_input_file(main_video_file)
+        .add_input_file(pip_video_file)
+        .add_complex_filter(&format!("[1:v]{}[pip];[0:v][pip]overlay=x={x}:y={y}:eof_action=pass[vo]", pip_filter_parts.join(",")))
+        .add_mapping("[vo]")
+        .add_mapping("0:a?")
+        .set_output_video_settings(Some(args.video_encoder().as_str()), None, Some(args.video_crf()))
+        .set_output_file(&output_video_file)
+        .set_overwrite_output_file(true)
+        .set_atomic_output(true);
+
+    ffmpeg_command.build().unwrap().spawn_with_progress(main_video_info.frame_count())?.wait().await?;
+
+    log::info!("picture-in-picture video written: {}", output_video_file.to_string_lossy());
+    Ok(())
+}
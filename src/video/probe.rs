@@ -1,4 +1,4 @@
-use std::{path::{PathBuf, Path}, ffi::CStr};
+use std::{path::{PathBuf, Path}, ffi::{CStr, c_char}};
 
 use ffmpeg_next as ffmpeg;
 
@@ -9,6 +9,11 @@ use thiserror::Error;
 use super::resolution::Resolution;
 
 
+// relative difference between the nominal and the average frame rates above which a video is considered VFR
+const VFR_TOLERANCE: f64 = 0.01;
+// nominal frame rates above this are time bases rather than real frame rates, the average frame rate is used instead
+const MAX_NOMINAL_FRAME_RATE: f64 = 240.0;
+
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("failed opening video file {file_path}: {error}")]
@@ -30,12 +35,91 @@ impl Error {
 #[getset(get_copy = "pub")]
 pub struct Result {
     frame_count: u64,
+    /// nominal frame rate, every frame timestamp is a multiple of its period
     frame_rate: Rational,
+    /// total frame count divided by the duration
+    average_frame_rate: Rational,
+    /// duration of the video stream in seconds
+    duration: f64,
+    /// whether the frames are not evenly spaced in time, e.g. some phone or DJI recordings
+    variable_frame_rate: bool,
     has_audio: bool,
+    /// number of audio streams, e.g. 2 for a video with the audio of an external microphone muxed along with the camera audio
+    audio_track_count: usize,
+    /// duration of the best audio stream in seconds
+    audio_duration: Option<f64>,
+    /// `encoder` tag of the container, identifies the recording device firmware
+    #[getset(skip)]
+    #[getset(get = "pub")]
+    encoder: Option<String>,
+    /// `creation_time` tag of the container, ISO 8601 formatted
+    #[getset(skip)]
+    #[getset(get = "pub")]
+    creation_time: Option<String>,
     resolution: Resolution,
 
     #[getset(skip)] #[getset(get = "pub")]
     video_codec: Option<String>,
+
+    /// FFMpeg name of the pixel format of the video stream, e.g. `yuv420p10le`
+    #[getset(skip)] #[getset(get = "pub")]
+    pixel_format: Option<String>,
+    /// bits per color component, 8 if the pixel format is unknown
+    bit_depth: u8,
+    #[getset(skip)] #[getset(get = "pub")]
+    color_metadata: ColorMetadata,
+}
+
+/// Color properties of a video stream, as FFMpeg names, the unspecified properties are `None`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Getters)]
+#[getset(get = "pub")]
+pub struct ColorMetadata {
+    primaries: Option<String>,
+    transfer: Option<String>,
+    space: Option<String>,
+    range: Option<String>,
+}
+
+impl ColorMetadata {
+
+    /// Whether the transfer characteristic is one of the HDR ones: PQ (HDR10, Dolby Vision) or HLG
+    pub fn is_hdr(&self) -> bool {
+        matches!(self.transfer.as_deref(), Some("smpte2084" | "arib-std-b67"))
+    }
+
+    /// Same color range with the BT.709 primaries, transfer characteristic and color space of SDR videos
+    pub fn with_bt709(&self) -> Self {
+        let bt709 = || Some("bt709".to_owned());
+        Self { primaries: bt709(), transfer: bt709(), space: bt709(), range: self.range.clone() }
+    }
+
+    /// FFMpeg output arguments tagging the output video stream with the same color properties
+    pub fn ffmpeg_output_args(&self) -> Vec<String> {
+        [("-color_primaries", &self.primaries), ("-color_trc", &self.transfer), ("-colorspace", &self.space), ("-color_range", &self.range)]
+            .into_iter()
+            .filter_map(|(option, value)| Some([option.to_owned(), value.clone()?]))
+            .flatten()
+            .collect()
+    }
+
+}
+
+// converts the name of an FFMpeg enum value, the unknown and unspecified values are discarded
+unsafe fn ffmpeg_value_name(name_ptr: *const c_char) -> Option<String> {
+    if name_ptr.is_null() { return None }
+    let name = String::from_utf8_lossy(CStr::from_ptr(name_ptr).to_bytes()).to_string();
+    (! matches!(name.as_str(), "unknown" | "unspecified" | "reserved")).then_some(name)
+}
+
+// descriptor of the pixel format of codec parameters, the format is stored as an integer which is looked up among the
+// pixel formats known to FFMpeg since converting an unknown value to an AVPixelFormat would be undefined behavior
+unsafe fn pixel_format_descriptor(format: i32) -> Option<&'static ffmpeg::ffi::AVPixFmtDescriptor> {
+    let mut descriptor = std::ptr::null();
+    loop {
+        descriptor = ffmpeg::ffi::av_pix_fmt_desc_next(descriptor);
+        if descriptor.is_null() { return None }
+        if ffmpeg::ffi::av_pix_fmt_desc_get_id(descriptor) as i32 == format { return Some(&*descriptor) }
+    }
 }
 
 pub fn probe<P: AsRef<Path>>(video_file: P) -> std::result::Result<Result, Error> {
@@ -45,7 +129,13 @@ pub fn probe<P: AsRef<Path>>(video_file: P) -> std::result::Result<Result, Error
     let input = ffmpeg::format::input(&video_file)
         .map_err(|error| Error::ffmpeg(&video_file, error))?;
 
-    let has_audio = input.streams().best(ffmpeg::media::Type::Audio).is_some();
+    let audio_stream = input.streams().best(ffmpeg::media::Type::Audio);
+    let has_audio = audio_stream.is_some();
+    let audio_track_count = input.streams().filter(|stream| stream.parameters().medium() == ffmpeg::media::Type::Audio).count();
+    let audio_duration = audio_stream.filter(|audio_stream| audio_stream.duration() > 0)
+        .map(|audio_stream| audio_stream.duration() as f64 * f64::from(audio_stream.time_base()));
+    let encoder = input.metadata().get("encoder").map(str::to_owned);
+    let creation_time = input.metadata().get("creation_time").map(str::to_owned);
 
     let video_stream = input.streams().best(ffmpeg::media::Type::Video)
         .ok_or_else(|| Error::CannotFindVideoStream(video_file.as_ref().to_path_buf()))?;
@@ -66,9 +156,69 @@ pub fn probe<P: AsRef<Path>>(video_file: P) -> std::result::Result<Result, Error
         }
     };
 
+    let (pixel_format, bit_depth, color_metadata) = unsafe {
+        let parameters = &*video_stream_parameters.as_ptr();
+        let pixel_format_descriptor = pixel_format_descriptor(parameters.format);
+        let bit_depth = pixel_format_descriptor.map_or(8, |descriptor| descriptor.comp[0].depth as u8);
+        let color_metadata = ColorMetadata {
+            primaries: ffmpeg_value_name(ffmpeg::ffi::av_color_primaries_name(parameters.color_primaries)),
+            transfer: ffmpeg_value_name(ffmpeg::ffi::av_color_transfer_name(parameters.color_trc)),
+            space: ffmpeg_value_name(ffmpeg::ffi::av_color_space_name(parameters.color_space)),
+            range: ffmpeg_value_name(ffmpeg::ffi::av_color_range_name(parameters.color_range)),
+        };
+        let pixel_format = pixel_format_descriptor.and_then(|descriptor| ffmpeg_value_name(descriptor.name));
+        (pixel_format, bit_depth, color_metadata)
+    };
+
     let frame_rate = video_stream.rate();
 
-    let frame_count = u64::try_from(video_stream.frames()).unwrap();
+    let average_frame_rate = video_stream.avg_frame_rate();
+    let duration = match video_stream.duration() {
+        duration if duration > 0 => duration as f64 * f64::from(video_stream.time_base()),
+        _ => (input.duration().max(0) as f64) / ffmpeg::ffi::AV_TIME_BASE as f64,
+    };
+
+    // some containers do not store the frame count, it is then estimated from the duration
+    let frame_count = match u64::try_from(video_stream.frames()).unwrap() {
+        0 if average_frame_rate.numerator() > 0 => (duration * f64::from(average_frame_rate)).round() as u64,
+        frame_count => frame_count,
+    };
+
+    let variable_frame_rate = average_frame_rate.numerator() > 0 && frame_rate.numerator() > 0 &&
+        (f64::from(frame_rate) - f64::from(average_frame_rate)).abs() / f64::from(frame_rate) > VFR_TOLERANCE;
+
+    Ok(Result { frame_count, frame_rate, average_frame_rate, duration, variable_frame_rate, has_audio, audio_track_count, audio_duration, encoder, creation_time, resolution, video_codec,
+        pixel_format, bit_depth, color_metadata })
+}
+
+impl Result {
+
+    /// Frame rate to which a VFR video is converted to make it CFR: the nominal frame rate when it is a real frame rate
+    pub fn constant_frame_rate(&self) -> Rational {
+        match f64::from(self.frame_rate) {
+            frame_rate if frame_rate > 0.0 && frame_rate <= MAX_NOMINAL_FRAME_RATE => self.frame_rate,
+            _ => self.average_frame_rate,
+        }
+    }
+
+    /// Details of the concatenation of videos recorded with the same settings, e.g. the parts of a split recording, the stream
+    /// properties are the ones of the first video
+    pub fn concatenated(parts: &[Self]) -> Option<Self> {
+        let first_part = parts.first()?;
+        Some(Self {
+            frame_count: parts.iter().map(|part| part.frame_count).sum(),
+            duration: parts.iter().map(|part| part.duration).sum(),
+            variable_frame_rate: parts.iter().any(|part| part.variable_frame_rate),
+            audio_duration: parts.iter().map(|part| part.audio_duration).sum(),
+            ..first_part.clone()
+        })
+    }
+
+    /// Details of the video once converted to a constant frame rate with the FFMpeg fps filter
+    pub fn to_constant_frame_rate(&self) -> Self {
+        let frame_rate = self.constant_frame_rate();
+        let frame_count = (self.duration * f64::from(frame_rate)).round() as u64;
+        Self { frame_count, frame_rate, average_frame_rate: frame_rate, variable_frame_rate: false, ..self.clone() }
+    }
 
-    Ok(Result { frame_count, frame_rate, has_audio, resolution, video_codec })
 }
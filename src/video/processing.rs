@@ -0,0 +1,1576 @@
+// video processing commands: cutting, transcoding, burning the OSD and playing videos with their OSD
+
+use std::ffi::{OsStr, OsString};
+use std::path::PathBuf;
+use std::process::ExitStatus;
+use std::path::Path;
+
+use derive_more::From;
+use itertools::Itertools;
+use thiserror::Error;
+use std::io::Error as IOError;
+use ffmpeg_next::Rational;
+
+use crate::cli::font_options::OSDFontDirError;
+use crate::cli::start_end_args::{StartEndArgs, StartGreaterThanEndError};
+use crate::cli::transcode_video_args::OutputVideoFileError;
+use crate::file::TouchError;
+use crate::osd::overlay::SendFramesToFFMpegError;
+use crate::osd::overlay::video_timeline::{OSDFrameShift, OSDVideoOffset, OSDTimebaseCorrection, OSDSpeed, VideoTimeline};
+use crate::osd::overlay::frames_manifest::{FramesManifest, ManifestReadError};
+use crate::osd::tile_indices::UnknownOSDItem;
+use crate::osd::overlay::placement::{OSDMarginArgs, Placement as OverlayPlacement};
+use super::annotations::Annotations;
+use super::audio_sync_model::SyncFactorSource;
+use super::container::Container;
+use super::osd_video;
+use super::metadata::{Chapter, OutputMetadata};
+use crate::{prelude::*, osd::overlay::scaling::ScalingArgsError};
+use crate::{prelude::{TranscodeVideoArgs, Scaling}, cli::transcode_video_args::{TranscodeVideoOSDArgs, RequestedOSDButNoFileProvidedNorFound}};
+use crate::cli::play_with_osd_args::PlayWithOSDArgs;
+use crate::osd::file::{ReadError as OSDFileReadError, GenericReader, OpenError as OSDFileOpenError};
+use crate::ffmpeg::{self, bitrate::Bitrate};
+use crate::process::Command as ProcessCommand;
+use crate::removable_media::{self, StageError, StagedFile};
+use crate::disk_space::{self, InsufficientDiskSpaceError};
+use crate::output_path;
+use crate::remote::{RemoteEncode, RemoteError};
+use super::*;
+
+#[derive(Debug, Error, From)]
+pub enum CutVideoError {
+    #[error("failed to get input video details")]
+    FailedToGetInputVideoDetails(VideoProbingError),
+    #[error("input video file does not exist")]
+    InputVideoFileDoesNotExist,
+    #[error("output video file exists")]
+    OutputVideoFileExists,
+    #[error("input file and output file are the same file")]
+    InputAndOutputFileIsTheSame,
+    #[error("input has no file name")]
+    InputHasNoFileName,
+    #[error("input has no extension")]
+    InputHasNoExtension,
+    #[error("output file has a different extension than input")]
+    OutputHasADifferentExtensionThanInput,
+    #[error(transparent)]
+    FailedSpawningFFMpegProcess(ffmpeg::SpawnError),
+    #[error(transparent)]
+    FFMpegExitedWithError(ffmpeg::ProcessError),
+    #[error(transparent)]
+    WriteToFileError(TouchError),
+    #[error(transparent)]
+    InsufficientDiskSpace(InsufficientDiskSpaceError),
+}
+
+// `-map_metadata` value copying the metadata of the first input or removing it
+fn map_metadata(preserve_metadata: bool) -> i32 {
+    if preserve_metadata { 0 } else { -1 }
+}
+
+// keeps the modification time of the input video so that the outputs sort chronologically in editors and file managers
+fn preserve_modification_time(input_video_file: &Path, output_video_file: &Path) {
+    if let Err(error) = file::copy_modification_time(input_video_file, output_video_file) {
+        log::warn!("failed to set the modification time of {}: {error}", output_video_file.to_string_lossy());
+    }
+}
+
+fn file_size(path: &Path) -> Option<u64> {
+    fs_err::metadata(path).ok().map(|metadata| metadata.len())
+}
+
+// estimated size of an output copying the streams of the input video for `frame_count` of its frames
+fn stream_copy_size_estimate(input_video_file: &Path, video_info: &probe::Result, frame_count: u64) -> Option<u64> {
+    let input_size = file_size(input_video_file)?;
+    Some((input_size as f64 * frame_count as f64 / video_info.frame_count().max(1) as f64) as u64)
+}
+
+/// Cuts a video without transcoding, with `preserve_metadata` the metadata and modification time of the input video are kept
+pub async fn cut<P: AsRef<Path>, Q: AsRef<Path>>(input_video_file: P, output_video_file: &Option<Q>,
+        overwrite: bool, start_end: &StartEndArgs, preserve_metadata: bool, container: Option<Container>) -> Result<(), CutVideoError> {
+
+    let input_video_file = input_video_file.as_ref();
+
+    if ! input_video_file.exists() { return Err(CutVideoError::InputVideoFileDoesNotExist); }
+
+    let output_video_file = match output_video_file {
+        Some(output_video_file) => {
+            let output_video_file = output_video_file.as_ref();
+            if input_video_file == output_video_file { return Err(CutVideoError::InputAndOutputFileIsTheSame) }
+            if ! container::output_extension_is_valid(input_video_file, output_video_file, container) {
+                return Err(CutVideoError::OutputHasADifferentExtensionThanInput)
+            }
+            output_video_file.to_path_buf()
+        },
+        None => {
+            let input_file_extension = container::output_extension(input_video_file, container).ok_or(CutVideoError::InputHasNoExtension)?;
+            output_path::default_output_path(input_video_file, "cut", Some(input_file_extension)).ok_or(CutVideoError::InputHasNoFileName)?
+        },
+    };
+
+    if ! overwrite && output_video_file.exists() { return Err(CutVideoError::OutputVideoFileExists); }
+
+    file::check_output_path(&output_video_file)?;
+
+    log::info!("cutting video: {} -> {}", input_video_file.to_string_lossy(), output_video_file.to_string_lossy());
+
+    let video_info = probe(input_video_file)?;
+    let frame_count = frame_count_for_interval(video_info.frame_count(), video_info.frame_rate(), &start_end.start(), &start_end.end());
+    if let Some(size_estimate) = stream_copy_size_estimate(input_video_file, &video_info, frame_count) {
+        disk_space::check(&output_video_file, size_estimate)?;
+    }
+
+    let mut ffmpeg_command = ffmpeg::CommandBuilder::default();
+
+    ffmpeg_command
+        .add_input_file_slice(input_video_file, start_end.start(), start_end.end())
+        .set_map_metadata(Some(map_metadata(preserve_metadata)))
+        .set_output_video_codec(Some("copy"))
+        .set_output_format(container.map(|container| container.muxer()))
+        .set_output_file(&output_video_file)
+        .set_overwrite_output_file(true)
+        .set_atomic_output(true);
+
+    if video_info.has_audio() {
+        ffmpeg_command.set_output_audio_codec(Some("copy"));
+    }
+
+    ffmpeg_command.build().unwrap().spawn_with_progress(frame_count)?.wait().await?;
+    if preserve_metadata { preserve_modification_time(input_video_file, &output_video_file) }
+
+    log::info!("video file cut successfully");
+    Ok(())
+}
+
+#[derive(Debug, Error, From)]
+pub enum FixVideoFileAudioError {
+    #[error("failed to get input video details")]
+    FailedToGetInputVideoDetails(VideoProbingError),
+    #[error("input video file does not exist")]
+    InputVideoFileDoesNotExist,
+    #[error("output video file exists")]
+    OutputVideoFileExists,
+    #[error("input file and output file are the same file")]
+    InputAndOutputFileIsTheSame,
+    #[error("input has no file name")]
+    InputHasNoFileName,
+    #[error("input has no extension")]
+    InputHasNoExtension,
+    #[error("output file has a different extension than input")]
+    OutputHasADifferentExtensionThanInput,
+    #[error(transparent)]
+    FailedSpawningFFMpegProcess(ffmpeg::SpawnError),
+    #[error(transparent)]
+    FFMpegExitedWithError(ffmpeg::ProcessError),
+    #[error("the input video file does not have an audio stream")]
+    InputVideoDoesNotHaveAnAudioStream,
+    #[error(transparent)]
+    WriteToFileError(TouchError),
+    #[error(transparent)]
+    AudioAnalysisError(audio_analysis::AnalyzeAudioError),
+    #[error(transparent)]
+    InsufficientDiskSpace(InsufficientDiskSpaceError),
+}
+
+/// Gain applied to the audio of the DJI Air Unit videos when the gain is not computed from an analysis of the audio
+const DEFAULT_AUDIO_FIX_VOLUME_GAIN: f64 = 20.0;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AudioFixType {
+    Sync,
+    Volume,
+    SyncAndVolume,
+}
+
+impl AudioFixType {
+
+    pub fn sync(&self) -> bool {
+        use AudioFixType::*;
+        matches!(self, Sync | SyncAndVolume)
+    }
+
+    pub fn volume(&self) -> bool {
+        use AudioFixType::*;
+        matches!(self, Volume | SyncAndVolume)
+    }
+
+    fn ffmpeg_audio_filter_string_with(&self, sync_factor: f64, volume_gain: f64) -> String {
+        use AudioFixType::*;
+        match self {
+            Sync => format!("atempo={sync_factor:.6}"),
+            Volume => format!("volume={volume_gain}"),
+            SyncAndVolume => [Sync.ffmpeg_audio_filter_string_with(sync_factor, volume_gain), Volume.ffmpeg_audio_filter_string_with(sync_factor, volume_gain)].join(","),
+        }
+    }
+
+}
+
+/// Fixes the audio of a DJI Air Unit video, with `auto_gain` the volume gain is computed from an analysis of the audio
+/// instead of using the fixed 20x gain, `sync_factor_source` selects the tempo factor of the sync fix, with `preserve_metadata`
+/// the metadata and modification time of the input video are kept
+pub async fn fix_dji_air_unit_audio<P: AsRef<Path>, Q: AsRef<Path>>(input_video_file: P, output_video_file: &Option<Q>,
+        overwrite: bool, fix_type: AudioFixType, auto_gain: bool, sync_factor_source: SyncFactorSource, preserve_metadata: bool,
+        container: Option<Container>) -> Result<(), FixVideoFileAudioError> {
+
+    let input_video_file = input_video_file.as_ref();
+
+    if ! input_video_file.exists() { return Err(FixVideoFileAudioError::InputVideoFileDoesNotExist); }
+
+    let output_video_file = match output_video_file {
+        Some(output_video_file) => {
+            let output_video_file = output_video_file.as_ref();
+            if input_video_file == output_video_file { return Err(FixVideoFileAudioError::InputAndOutputFileIsTheSame) }
+            if ! container::output_extension_is_valid(input_video_file, output_video_file, container) {
+                return Err(FixVideoFileAudioError::OutputHasADifferentExtensionThanInput);
+            }
+            output_video_file.to_path_buf()
+        },
+        None => {
+            let input_file_extension = container::output_extension(input_video_file, container).ok_or(FixVideoFileAudioError::InputHasNoExtension)?;
+            output_path::default_output_path(input_video_file, "fixed_audio", Some(input_file_extension)).ok_or(FixVideoFileAudioError::InputHasNoFileName)?
+        },
+    };
+
+    if ! overwrite && output_video_file.exists() { return Err(FixVideoFileAudioError::OutputVideoFileExists); }
+
+    file::check_output_path(&output_video_file)?;
+
+    log::info!("fixing video file audio: {} -> {}", input_video_file.to_string_lossy(), output_video_file.to_string_lossy());
+
+    let video_info = probe(input_video_file)?;
+
+    if ! video_info.has_audio() {
+        return Err(FixVideoFileAudioError::InputVideoDoesNotHaveAnAudioStream);
+    }
+    if let Some(size_estimate) = stream_copy_size_estimate(input_video_file, &video_info, video_info.frame_count()) {
+        disk_space::check(&output_video_file, size_estimate)?;
+    }
+
+    let volume_gain = match auto_gain && fix_type.volume() {
+        true => {
+            let analysis = audio_analysis::analyze(input_video_file).await?;
+            match analysis.normalization_gain() {
+                Some(volume_gain) => {
+                    log::info!("integrated loudness {:.1} LUFS, applying a {volume_gain:.2}x volume gain", analysis.integrated_loudness().unwrap_or_default());
+                    volume_gain
+                },
+                None => {
+                    log::warn!("the audio is silent, applying the default {DEFAULT_AUDIO_FIX_VOLUME_GAIN}x volume gain");
+                    DEFAULT_AUDIO_FIX_VOLUME_GAIN
+                },
+            }
+        },
+        false => DEFAULT_AUDIO_FIX_VOLUME_GAIN,
+    };
+
+    let sync_factor = match fix_type.sync() {
+        true => audio_sync_model::sync_factor(sync_factor_source, input_video_file, &video_info),
+        false => audio_sync_model::DEFAULT_SYNC_FACTOR,
+    };
+
+    let mut ffmpeg_command = ffmpeg::CommandBuilder::default();
+
+    ffmpeg_command
+        .add_input_file(input_video_file)
+        .add_audio_filter(&fix_type.ffmpeg_audio_filter_string_with(sync_factor, volume_gain))
+        .set_map_metadata(Some(map_metadata(preserve_metadata)))
+        .set_output_video_codec(Some("copy"))
+        .set_output_audio_settings(Some("aac"), Some(Bitrate::from_kbps(93)))
+        .set_output_format(container.map(|container| container.muxer()))
+        .set_output_file(&output_video_file)
+        .set_overwrite_output_file(true)
+        .set_atomic_output(true);
+
+    ffmpeg_command.build().unwrap().spawn_with_progress(video_info.frame_count())?.wait().await?;
+    if preserve_metadata { preserve_modification_time(input_video_file, &output_video_file) }
+
+    log::info!("video file's audio stream fixed successfully");
+    Ok(())
+}
+
+fn frame_count_for_interval(total_frames: u64, frame_rate: Rational, start: &Option<Timestamp>, end: &Option<Timestamp>) -> u64 {
+    match (start, end) {
+        (None, None) => total_frames,
+        (None, Some(end)) => Timestamp::interval_frames(&Timestamp::default(), end, frame_rate),
+        (Some(start), None) => total_frames - Timestamp::interval_frames(&Timestamp::default(), start, frame_rate),
+        (Some(start), Some(end)) => Timestamp::interval_frames(start, end, frame_rate),
+    }
+}
+
+/// Returns the OSD frame shift to apply: the requested one or the one detected from the video and its OSD file
+pub fn osd_frame_shift_for_video(requested_frame_shift: Option<OSDFrameShift>, video_info: &probe::Result, osd_file: &mut osd::file::Reader)
+        -> Result<OSDFrameShift, OSDFileReadError> {
+    if let Some(frame_shift) = requested_frame_shift { return Ok(frame_shift) }
+    let (frame_shift, reason) = detect_osd_frame_shift(video_info, osd_file)?;
+    log::info!("{reason}: applying {frame_shift} OSD frames shift");
+    Ok(OSDFrameShift::from_frames(frame_shift))
+}
+
+// Only the DJI Air Unit recordings, the ones with audio, may need the OSD to be shifted: their OSD recording sometimes starts
+// before the video recording. It shows in the OSD recording lasting longer than the video.
+fn detect_osd_frame_shift(video_info: &probe::Result, osd_file: &mut osd::file::Reader) -> Result<(i32, String), OSDFileReadError> {
+    let au_frame_shift = crate::osd::dji::AU_OSD_FRAME_SHIFT;
+    if ! matches!(osd_file, osd::file::Reader::DJI(_)) { return Ok((0, "not a DJI OSD file".to_owned())) }
+    if ! video_info.has_audio() { return Ok((0, "input video file without audio, assuming DJI goggles origin".to_owned())) }
+    let osd_file_frames = osd_file.frames()?;
+    let (Some(first_frame), Some(last_frame)) = (osd_file_frames.first(), osd_file_frames.last()) else {
+        return Ok((0, "empty OSD file".to_owned()))
+    };
+    // the duration of an OSD recording whose first frames are missing, e.g. a repaired one, cannot be compared with the video duration
+    if first_frame.index() > au_frame_shift.unsigned_abs() {
+        return Ok((au_frame_shift, format!("input video file contains audio and the OSD file starts at frame {}, assuming DJI AU origin", first_frame.index())))
+    }
+    let osd_lead_frames = (last_frame.index() + 1) as f64 - video_info.duration() * osd::OSD_TIME_BASE_HZ;
+    Ok(if osd_lead_frames >= au_frame_shift.unsigned_abs() as f64 / 2.0 {
+        (au_frame_shift, format!("input video file contains audio and the OSD recording lasts {osd_lead_frames:.0} frames longer than the video, \
+            assuming DJI AU origin with the OSD recording started first"))
+    } else {
+        (0, "input video file contains audio and the OSD recording does not last longer than the video, assuming both recordings started together".to_owned())
+    })
+}
+
+/// Returns the frame count, first and last video frame indices used when burning the OSD onto a video
+fn burn_osd_frame_range(video_info: &probe::Result, start_end: &StartEndArgs) -> (u64, FrameIndex, FrameIndex) {
+    let frame_count = frame_count_for_interval(video_info.frame_count(), video_info.frame_rate(), &start_end.start(), &start_end.end());
+    let first_frame_index = start_end.start().map(|tstamp| tstamp.frame_count(video_info.frame_rate()) as u32).unwrap_or(0);
+    let last_frame_index = start_end.end().map(|end| end.frame_count(video_info.frame_rate()) as u32).unwrap_or(frame_count as u32);
+    (frame_count, first_frame_index, last_frame_index)
+}
+
+/// Returns the first and last OSD frame indices (60Hz time base) covering the part of a VFR video the OSD is burnt onto
+fn burn_osd_timestamp_range(video_info: &probe::Result, start_end: &StartEndArgs) -> (FrameIndex, FrameIndex) {
+    let osd_frame_rate = Rational::new(osd::OSD_FRAME_RATE.into(), 1);
+    let first_frame_index = start_end.start().map(|start| start.frame_count(osd_frame_rate) as FrameIndex).unwrap_or(0);
+    let last_frame_index = start_end.end().map(|end| end.frame_count(osd_frame_rate) as FrameIndex)
+        .unwrap_or((video_info.duration() * osd::OSD_TIME_BASE_HZ).ceil() as FrameIndex);
+    (first_frame_index, last_frame_index)
+}
+
+#[derive(Debug, Error, From)]
+pub enum ExportOSDFrameMappingError {
+    #[error("video file does not exist")]
+    VideoFileDoesNotExist,
+    #[error("no OSD file provided nor found")]
+    NoOSDFileProvidedNorFound,
+    #[error("failed to get video details")]
+    FailedToGetVideoDetails(VideoProbingError),
+    #[error(transparent)]
+    OSDFileOpenError(OSDFileOpenError),
+    #[error("OSD file read error: {0}")]
+    OSDFileReadError(OSDFileReadError),
+    #[error("failed to write frame mapping: {0}")]
+    WriteError(IOError),
+}
+
+/// Writes which OSD frame is displayed on each video frame using the same frame shift decision as when burning the OSD
+///
+/// The OSD frames are mapped onto the video frame rate, placed with the OSD video offset if specified.
+/// The mapping is written to stdout if no output file is specified.
+pub fn export_osd_frame_mapping<P: AsRef<Path>>(video_file: P, osd_file: &Option<PathBuf>, osd_frame_shift: Option<OSDFrameShift>,
+        osd_video_offset: Option<OSDVideoOffset>, start_end: &StartEndArgs, format: osd::frame_mapping::ExportFormat, output_file: &Option<PathBuf>)
+        -> Result<(), ExportOSDFrameMappingError> {
+
+    let video_file = video_file.as_ref();
+    if ! video_file.exists() { return Err(ExportOSDFrameMappingError::VideoFileDoesNotExist) }
+    let osd_file = match osd_file {
+        Some(osd_file) => osd_file.clone(),
+        None => osd::file::find_associated_to_video_file(video_file).ok_or(ExportOSDFrameMappingError::NoOSDFileProvidedNorFound)?,
+    };
+
+    let video_info = probe(video_file)?;
+    let mut osd_file = osd::file::open(osd_file)?;
+    let osd_video_offset = match osd_video_offset {
+        Some(osd_video_offset) => osd_video_offset,
+        None => OSDVideoOffset::from_osd_frame_shift(osd_frame_shift_for_video(osd_frame_shift, &video_info, &mut osd_file)?),
+    };
+    let frame_rate = video_info.frame_rate();
+    let video_timeline = VideoTimeline::new(frame_rate.numerator(), frame_rate.denominator(), osd_video_offset);
+    let (_, first_frame_index, last_frame_index) = burn_osd_frame_range(&video_info, start_end);
+    let osd_file_frames = osd_file.frames()?;
+
+    let entries = osd::frame_mapping::frame_mapping(&osd_file_frames, first_frame_index..last_frame_index, f64::from(frame_rate),
+        |video_frame_index| video_timeline.osd_frame_index(video_frame_index));
+    match output_file {
+        Some(output_file) if crate::process::is_dry_run() => println!("OSD frame mapping file: {}", output_file.to_string_lossy()),
+        Some(output_file) => {
+            let mut writer = std::io::BufWriter::new(fs_err::File::create(output_file)?);
+            osd::frame_mapping::write_frame_mapping(&mut writer, &entries, format)?;
+        },
+        None => osd::frame_mapping::write_frame_mapping(&mut std::io::stdout().lock(), &entries, format)?,
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Error, From)]
+pub enum TranscodeVideoError {
+    #[error(transparent)]
+    OSDFontDirError(OSDFontDirError),
+    #[error(transparent)]
+    OutputVideoFileError(OutputVideoFileError),
+    #[error(transparent)]
+    OSDFileOpenError(OSDFileOpenError),
+    #[error(transparent)]
+    ScalingArgsError(ScalingArgsError),
+    #[error(transparent)]
+    DrawFrameOverlayError(DrawFrameOverlayError),
+    #[error("failed to get input video details")]
+    FailedToGetInputVideoDetails(VideoProbingError),
+    #[error(transparent)]
+    AudioSyncError(audio_sync::AudioSyncError),
+    #[error("it is only possible to burn the OSD on 60FPS videos, given video is {0:.1}FPS")]
+    CanOnlyBurnOSDOn60FPSVideo(f64),
+    #[error("requested to fix audio but input has no audio stream")]
+    RequestedAudioFixingButInputHasNoAudio,
+    #[error("audio track {track} does not exist, the input video has {track_count} audio tracks")]
+    AudioTrackDoesNotExist {
+        track: usize,
+        track_count: usize,
+    },
+    #[error("input video file does not exist")]
+    InputVideoFileDoesNotExist,
+    #[error("output video file exists")]
+    OutputVideoFileExists,
+    #[error("input file and output file are the same file")]
+    InputAndOutputFileIsTheSame,
+    #[error("incompatible arguments: {0}")]
+    IncompatibleArguments(String),
+    #[error("OSD file read error: {0}")]
+    OSDFileReadError(OSDFileReadError),
+    #[error(transparent)]
+    FailedSpawningFFMpegProcess(ffmpeg::SpawnError),
+    #[error("failed sending OSD frames to ffmpeg process: {0}")]
+    FailedSendingOSDFramesToFFMpeg(IOError),
+    #[error(transparent)]
+    FFMpegExitedWithError(ffmpeg::ProcessError),
+    #[error(transparent)]
+    UnknownOSDItem(UnknownOSDItem),
+    #[error(transparent)]
+    WriteToFileError(TouchError),
+    #[error(transparent)]
+    InsufficientDiskSpace(InsufficientDiskSpaceError),
+    #[error(transparent)]
+    StageInputError(StageError),
+    #[error("failed to join the recording parts: {0}")]
+    JoinRecordingError(continuation::JoinRecordingError),
+    #[cfg(feature = "hwaccel")]
+    #[error(transparent)]
+    HwEncoderError(hw_accel::DetectionError),
+    #[error("OSD frames directory does not exist: {0}")]
+    OSDFramesDirDoesNotExist(PathBuf),
+    #[error("OSD frames directory has no frame for the first transcoded video frame ({0})")]
+    OSDFramesDirHasNoFrameAtStart(FrameIndex),
+    #[error(transparent)]
+    OSDFramesManifestReadError(ManifestReadError),
+    #[error("failed to write OSD frames concat script: {0}")] #[from(ignore)]
+    OSDFramesManifestWriteError(IOError),
+    #[error(transparent)]
+    CropError(transform::CropError),
+    #[error("LUT file does not exist: {0}")]
+    LUTFileDoesNotExist(PathBuf),
+    #[error("music file does not exist: {0}")]
+    MusicFileDoesNotExist(PathBuf),
+    #[error("remote encoding error: {0}")]
+    RemoteEncodingError(RemoteError),
+    #[error("failed to write chapters metadata file: {0}")] #[from(ignore)]
+    ChaptersWriteError(IOError),
+    #[error("proxy output file exists: {0}")]
+    ProxyOutputFileExists(PathBuf),
+    #[error(transparent)]
+    AnnotationsReadError(annotations::ReadError),
+}
+
+impl From<SendFramesToFFMpegError> for TranscodeVideoError {
+    fn from(error: SendFramesToFFMpegError) -> Self {
+        use SendFramesToFFMpegError::*;
+        match error {
+            PipeError(error) => Self::FailedSendingOSDFramesToFFMpeg(error),
+            UnknownOSDItem(error) => Self::UnknownOSDItem(error),
+            FFMpegExitedWithError(error) => Self::FFMpegExitedWithError(error),
+        }
+    }
+}
+
+pub async fn transcode(args: &TranscodeVideoArgs, start_end: &StartEndArgs) -> Result<(), TranscodeVideoError> {
+
+    let output_video_file = args.output_video_file(false)?;
+    if ! args.input_video_file().exists() { return Err(TranscodeVideoError::InputVideoFileDoesNotExist); }
+    if ! args.overwrite() && output_video_file.exists() { return Err(TranscodeVideoError::OutputVideoFileExists); }
+    if *args.input_video_file() == output_video_file { return Err(TranscodeVideoError::InputAndOutputFileIsTheSame) }
+    check_transcode_output_file(args, &output_video_file)?;
+    if start_end.start().is_some() && matches!(args.video_audio_fix(), Some(fix) if fix.sync()) {
+        return Err(TranscodeVideoError::IncompatibleArguments("cannot fix video audio sync while not starting at the beginning of the file".to_owned()));
+    }
+
+    check_metadata_args_without_osd(args)?;
+    let proxy_output_file = proxy_output_file(args)?;
+
+    let input_video_file = removable_media::stage_if_requested(args.input_video_file(), args.copy_local_first(), args.staging_dir())?;
+
+    log::info!("transcoding video: {} -> {}", args.input_video_file().to_string_lossy(), output_video_file.to_string_lossy());
+
+    let video_info = probe_input_video(args, input_video_file.path())?;
+    let annotations = read_annotations(args)?;
+    let mut frame_count = frame_count_for_interval(video_info.frame_count(), video_info.frame_rate(), &start_end.start(), &start_end.end());
+    if let Some(speed) = args.speed_args().speed() { frame_count = speed.output_frame_count(frame_count) }
+    frame_count += title_card_frame_count(annotations.as_ref(), &video_info);
+
+    check_transcode_output_space(args, &output_video_file, file_size(input_video_file.path()), &video_info, frame_count)?;
+    let remote_encode = prepare_remote_encode(args, input_video_file.path(), &output_video_file)?;
+    let (encode_input_file, encode_output_file) = encode_file_paths(remote_encode.as_ref(), input_video_file.path(), &output_video_file);
+    let two_pass_log_file_prefix = args.two_pass().then(|| ffmpeg::two_pass_log_file_prefix(&encode_output_file));
+
+    let mut ffmpeg_command = ffmpeg::CommandBuilder::default();
+    ffmpeg_command.set_progress_callback(args.progress_callback());
+
+    ffmpeg_command
+        .add_input_file_slice(&encode_input_file, start_end.start(), start_end.end())
+        .set_map_metadata(Some(map_metadata(args.metadata_args().preserve_metadata())))
+        .set_output_format(args.container().map(|container| container.muxer()))
+        .set_output_movflags(args.output_movflags(&output_video_file))
+        .set_output_file(&encode_output_file)
+        .set_overwrite_output_file(true)
+        .set_atomic_output(! args.fragmented_mp4())
+        .set_remote_host(remote_encode.as_ref().map(|remote_encode| remote_encode.host().clone()));
+
+    let (hw_filter, _) = configure_video_encoder(&mut ffmpeg_command, args, &video_info)?;
+
+    let input_filter_parts = input_video_filter_parts(args, start_end, &video_info)?;
+    check_audio_track(args, &video_info)?;
+    let keep_audio = keeps_audio(args, &video_info);
+    let music_file = music_file(args)?;
+    if ! input_filter_parts.is_empty() || annotations.is_some() || proxy_output_file.is_some() {
+        let input_filter = if input_filter_parts.is_empty() { "null".to_owned() } else { input_filter_parts.join(",") };
+        let complex_filter = finish_output_video_filter(format!("[0]{input_filter}"), annotations.as_ref(), hw_filter, args, &video_info);
+        ffmpeg_command.add_complex_filter(&complex_filter).add_mapping("[vo]");
+        if keep_audio && music_file.is_none() { ffmpeg_command.add_mapping(&args.audio_tracks_mapping()); }
+    } else {
+        // the streams are selected automatically unless the music is mixed in or the audio tracks are selected
+        if music_file.is_some() || args.selects_audio_tracks() { ffmpeg_command.add_mapping("0:v"); }
+        if keep_audio && music_file.is_none() && args.selects_audio_tracks() { ffmpeg_command.add_mapping(&args.audio_tracks_mapping()); }
+        if let Some(hw_filter) = hw_filter { ffmpeg_command.add_video_filter(&hw_filter); }
+    }
+
+    let audio_filter_parts = audio_filter_parts(args, &video_info, annotations.as_ref());
+    match music_file {
+        Some(music_file) => add_music_mix(&mut ffmpeg_command, args, music_file, keep_audio.then(|| audio_filter_parts.clone()),
+            output_duration_secs(frame_count, &video_info)),
+        None if keep_audio && ! audio_filter_parts.is_empty() => {
+            ffmpeg_command
+                .add_audio_filter(&audio_filter_parts.join(","))
+                .set_output_audio_settings(Some(args.audio_encoder()), Some(args.audio_bitrate()));
+        },
+        None => {},
+    }
+    if let Some(proxy_output_file) = proxy_output_file {
+        add_proxy_output(&mut ffmpeg_command, args, proxy_output_file, keep_audio, &audio_filter_parts);
+    }
+
+    output_metadata(args, &video_info, None, vec![]).add_to_ffmpeg_command(&mut ffmpeg_command).map_err(TranscodeVideoError::ChaptersWriteError)?;
+
+    let encode_result = async {
+        for pass_command in pass_commands(&ffmpeg_command, two_pass_log_file_prefix.as_deref()) {
+            pass_command.build().unwrap().spawn_with_progress(frame_count)?.wait().await?;
+        }
+        Ok::<(), TranscodeVideoError>(())
+    }.await;
+    if let (Some(two_pass_log_file_prefix), None) = (&two_pass_log_file_prefix, &remote_encode) {
+        ffmpeg::remove_two_pass_log_files(two_pass_log_file_prefix);
+    }
+    let remote_encode_result = remote_encode.map(|remote_encode| remote_encode.finish(encode_result.is_ok())).transpose();
+    encode_result?;
+    remote_encode_result?;
+
+    if args.metadata_args().preserve_metadata() {
+        preserve_modification_time(args.input_video_file(), &output_video_file);
+        if let Some(proxy_output_file) = proxy_output_file { preserve_modification_time(args.input_video_file(), proxy_output_file) }
+    }
+
+    log::info!("{frame_count} frames transcoded successfully");
+    Ok(())
+}
+
+// whether the output keeps the bit depth of a 10-bit input video, the output is 8-bit if the encoder does not support it
+fn keeps_high_bit_depth(args: &TranscodeVideoArgs, video_info: &probe::Result) -> bool {
+    if video_info.bit_depth() <= 8 { return false }
+    let supported = match args.uses_hw_encoder() {
+        #[cfg(feature = "hwaccel")]
+        true => hw_accel::Codec::from_encoder_name(args.video_encoder()).map_or(false, |codec| codec.hw_supports_10_bit()),
+        _ => matches!(args.video_encoder(), "libx265" | "libsvtav1" | "libaom-av1" | "librav1e" | "libvpx-vp9"),
+    };
+    let keeps_high_bit_depth = supported && ! args.force_8_bit();
+    if ! supported && ! args.force_8_bit() {
+        log::warn!("the input video is {}-bit but the {} encoder cannot encode 10-bit videos, the output video is 8-bit", video_info.bit_depth(), args.video_encoder());
+    }
+    if ! keeps_high_bit_depth && video_info.color_metadata().is_hdr() {
+        log::warn!("the HDR input video is encoded as an 8-bit video, color banding may appear");
+    }
+    keeps_high_bit_depth
+}
+
+// sets the video encoder settings, returns the filter uploading the frames to the GPU when a hardware encoder is used
+// and whether the video is encoded with 10 bits per component
+//
+// The output video is tagged with the color properties of the input video so that HDR videos are still displayed as HDR. A
+// LUT converts the colors, to Rec.709 for the usual log to SDR LUTs, so the output is then tagged as a BT.709 video.
+fn configure_video_encoder(ffmpeg_command: &mut ffmpeg::CommandBuilder, args: &TranscodeVideoArgs, video_info: &probe::Result)
+        -> Result<(Option<String>, bool), TranscodeVideoError> {
+    let ten_bit = keeps_high_bit_depth(args, video_info);
+    let color_metadata = match args.color_grading().lut3d() {
+        Some(_) => video_info.color_metadata().with_bt709(),
+        None => video_info.color_metadata().clone(),
+    };
+    let color_args = color_metadata.ffmpeg_output_args();
+    ffmpeg_command.add_args(&color_args.iter().map(String::as_str).collect::<Vec<_>>());
+    #[cfg(feature = "hwaccel")]
+    if let Some(hw_backend) = args.hw_encoder() {
+        return configure_hw_video_encoder(ffmpeg_command, args, hw_backend, ten_bit);
+    }
+    let encoder_name = args.video_encoder();
+    ffmpeg_command.set_output_video_settings(Some(encoder_name), Some(args.video_bitrate(encoder_name)), args.video_crf_setting(encoder_name));
+    add_preset_speed_args(ffmpeg_command, args, encoder_name);
+    if ten_bit { ffmpeg_command.add_args(&["-pix_fmt", "yuv420p10le"]); }
+    if let Some(encoder_args) = args.encoder_args() { ffmpeg_command.add_encoder_args(encoder_args); }
+    Ok((None, ten_bit))
+}
+
+// sets the settings of the hardware encoder of the codec of the video encoder, returns the filter uploading the frames to the GPU
+#[cfg(feature = "hwaccel")]
+fn configure_hw_video_encoder(ffmpeg_command: &mut ffmpeg::CommandBuilder, args: &TranscodeVideoArgs, hw_backend: Option<hw_accel::EncoderBackend>,
+                                ten_bit: bool) -> Result<(Option<String>, bool), TranscodeVideoError> {
+    let codec = hw_accel::Codec::from_encoder_name(args.video_encoder())
+        .ok_or_else(|| hw_accel::DetectionError::UnknownCodec(args.video_encoder().to_owned()))?;
+    let (hw_backend, encoder_name) = hw_accel::select_encoder(ffmpeg::binary_path(), codec, hw_backend)?;
+    log::info!("using {encoder_name} hardware encoder");
+    ffmpeg_command
+        .add_global_args(&hw_backend.global_args())
+        .set_output_video_settings(Some(&encoder_name), Some(args.video_bitrate(&encoder_name)), None);
+    if let Some(quality) = args.video_crf_setting(&encoder_name) {
+        let quality_args = hw_backend.quality_args(quality);
+        ffmpeg_command.add_args(&quality_args.iter().map(String::as_str).collect::<Vec<_>>());
+    }
+    add_preset_speed_args(ffmpeg_command, args, &encoder_name);
+    if let Some(encoder_args) = args.encoder_args() { ffmpeg_command.add_encoder_args(encoder_args); }
+    Ok((Some(hw_accel::transcode_video_filter_parts(hw_backend, None, ten_bit).join(",")), ten_bit))
+}
+
+fn add_preset_speed_args(ffmpeg_command: &mut ffmpeg::CommandBuilder, args: &TranscodeVideoArgs, encoder_name: &str) {
+    let Some(preset) = args.preset() else { return };
+    match args.preset_settings(encoder_name) {
+        Some(preset_settings) => { ffmpeg_command.add_args(preset_settings.speed_args()); },
+        None => log::warn!("the {preset} preset has no settings for the {encoder_name} encoder, using the default quality settings"),
+    }
+}
+
+// checks the options which cannot be used when encoding on a remote host and copies the input file to it
+fn prepare_remote_encode(args: &TranscodeVideoArgs, input_video_file: &Path, output_video_file: &Path) -> Result<Option<RemoteEncode>, TranscodeVideoError> {
+    let Some(remote_host) = args.remote_encoding().host() else { return Ok(None) };
+    if args.uses_hw_encoder() {
+        return Err(TranscodeVideoError::IncompatibleArguments("cannot use a hardware encoder when encoding on a remote host".to_owned()));
+    }
+    if args.color_grading().lut3d().is_some() {
+        return Err(TranscodeVideoError::IncompatibleArguments("cannot use a LUT file when encoding on a remote host".to_owned()));
+    }
+    if args.proxy_output().is_some() {
+        return Err(TranscodeVideoError::IncompatibleArguments("cannot write a proxy output when encoding on a remote host".to_owned()));
+    }
+    Ok(Some(RemoteEncode::prepare(remote_host, input_video_file, output_video_file)?))
+}
+
+// input and output file paths passed to FFMpeg, the paths of the remote host when encoding remotely
+fn encode_file_paths(remote_encode: Option<&RemoteEncode>, input_video_file: &Path, output_video_file: &Path) -> (PathBuf, PathBuf) {
+    match remote_encode {
+        Some(remote_encode) => (remote_encode.input_path().clone(), remote_encode.output_path().clone()),
+        None => (input_video_file.to_path_buf(), output_video_file.to_path_buf()),
+    }
+}
+
+// returns the commands to run: the command itself or one command per pass when two-pass encoding is requested
+fn pass_commands(ffmpeg_command: &ffmpeg::CommandBuilder, two_pass_log_file_prefix: Option<&Path>) -> Vec<ffmpeg::CommandBuilder> {
+    match two_pass_log_file_prefix {
+        Some(log_file_prefix) => (1..=ffmpeg::TWO_PASS_COUNT).map(|pass| {
+            let mut pass_command = ffmpeg_command.clone();
+            pass_command.set_two_pass(pass, log_file_prefix);
+            pass_command
+        }).collect(),
+        None => vec![ffmpeg_command.clone()],
+    }
+}
+
+// probes the input video, the details are the ones of the video converted to a constant frame rate with --cfr
+fn probe_input_video(args: &TranscodeVideoArgs, video_file: &Path) -> Result<probe::Result, TranscodeVideoError> {
+    Ok(input_video_info(args, probe(video_file)?))
+}
+
+// details of the input video as transcoded, converted to a constant frame rate with --cfr
+fn input_video_info(args: &TranscodeVideoArgs, video_info: probe::Result) -> probe::Result {
+    if video_info.variable_frame_rate() {
+        let average_frame_rate = f64::from(video_info.average_frame_rate());
+        match args.cfr() {
+            true => log::info!("converting variable frame rate video (average {average_frame_rate:.2}FPS) to {:.2}FPS", f64::from(video_info.constant_frame_rate())),
+            false => log::warn!("input video has a variable frame rate (average {average_frame_rate:.2}FPS), use --cfr to convert it to a constant frame rate"),
+        }
+    }
+    if args.cfr() { video_info.to_constant_frame_rate() } else { video_info }
+}
+
+// filters applied to the input video before the OSD is overlaid: the frame rate is made constant, the defects are removed and the
+// regions blurred at their position in the recorded picture, the colors are graded without altering the OSD colors, then the video is
+// rotated/flipped/cropped so that the OSD is burnt upright and fits and finally the speed is changed
+fn input_video_filter_parts(args: &TranscodeVideoArgs, start_end: &StartEndArgs, video_info: &probe::Result) -> Result<Vec<String>, TranscodeVideoError> {
+    if let Some(lut_file) = args.color_grading().lut3d() {
+        if ! lut_file.is_file() { return Err(TranscodeVideoError::LUTFileDoesNotExist(lut_file.clone())) }
+    }
+    let frame_rate = video_info.frame_rate();
+    // the timestamps of the input start at 0 from the start of the transcode
+    let input_start_secs = start_end.start().map_or(0, |start| start.total_seconds());
+    Ok(args.cfr().then(|| format!("fps={}/{}", frame_rate.numerator(), frame_rate.denominator())).into_iter()
+        .chain(args.remove_video_defects().iter().map(|region| format!("delogo={}", region.to_ffmpeg_filter_string(input_start_secs))))
+        .chain(args.blur_regions().iter().enumerate()
+            .map(|(index, region)| region.to_ffmpeg_blur_filter_string(&format!("blur{index}"), args.blur_strength(), input_start_secs)))
+        .chain(args.color_grading().ffmpeg_filter_parts())
+        .chain(args.video_transform().ffmpeg_filter_parts(video_info.resolution())?)
+        .chain(args.speed_args().speed().map(|speed| speed.video_filter_parts(frame_rate)).unwrap_or_default())
+        .collect())
+}
+
+// the audio is dropped when the video speed is changed too much for it to be useful
+fn keeps_audio(args: &TranscodeVideoArgs, video_info: &probe::Result) -> bool {
+    video_info.has_audio() && args.speed_args().speed().map_or(true, |speed| speed.keeps_audio())
+}
+
+fn check_audio_track(args: &TranscodeVideoArgs, video_info: &probe::Result) -> Result<(), TranscodeVideoError> {
+    match args.audio_track() {
+        Some(track) if track >= video_info.audio_track_count() =>
+            Err(TranscodeVideoError::AudioTrackDoesNotExist { track, track_count: video_info.audio_track_count() }),
+        _ => Ok(()),
+    }
+}
+
+// audio filters: the DJI AU audio fix with the factor of the sync model matching the video, the tempo change matching the video speed
+// then the delay matching the title card
+fn audio_filter_parts(args: &TranscodeVideoArgs, video_info: &probe::Result, annotations: Option<&Annotations>) -> Vec<String> {
+    let title_card_duration = annotations.map_or(0.0, Annotations::title_card_duration);
+    let audio_fix_filter = args.video_audio_fix().map(|audio_fix| {
+        let sync_factor = match audio_fix.sync() {
+            true => audio_sync_model::sync_factor(SyncFactorSource::Model, args.input_video_file(), video_info),
+            false => audio_sync_model::DEFAULT_SYNC_FACTOR,
+        };
+        audio_fix.ffmpeg_audio_filter_string_with(sync_factor, DEFAULT_AUDIO_FIX_VOLUME_GAIN)
+    });
+    audio_fix_filter
+        .into_iter()
+        .chain(args.speed_args().speed().map(|speed| speed.audio_filter_parts()).unwrap_or_default())
+        .chain((title_card_duration > 0.0).then(|| format!("adelay=delays={}:all=1", (title_card_duration * 1000.0).round() as u64)))
+        .collect()
+}
+
+// the music file is read locally so it cannot be mixed in on a remote host
+fn music_file(args: &TranscodeVideoArgs) -> Result<Option<&Path>, TranscodeVideoError> {
+    let Some(music_file) = args.music_args().music() else { return Ok(None) };
+    if ! music_file.is_file() { return Err(TranscodeVideoError::MusicFileDoesNotExist(music_file.clone())) }
+    if args.remote_encoding().host().is_some() {
+        return Err(TranscodeVideoError::IncompatibleArguments("cannot mix background music while encoding on a remote host".to_owned()));
+    }
+    Ok(Some(music_file))
+}
+
+fn output_duration_secs(frame_count: u64, video_info: &probe::Result) -> f64 {
+    frame_count as f64 * video_info.frame_rate().denominator() as f64 / video_info.frame_rate().numerator() as f64
+}
+
+// estimated size of the transcoded video: the max bitrate of the encoder capped to the bitrate of the input video, which
+// re-encoding rarely exceeds, plus the audio bitrate over the duration of the output
+fn transcode_output_size_estimate(args: &TranscodeVideoArgs, input_size: Option<u64>, video_info: &probe::Result, frame_count: u64) -> Option<u64> {
+    if video_info.duration() <= 0.0 { return None }
+    let input_bitrate = Bitrate::from_bps((input_size? as f64 * 8.0 / video_info.duration()) as u64);
+    let video_bitrate = args.video_bitrate(args.video_encoder()).min(input_bitrate);
+    let total_bitrate = Bitrate::from_bps(video_bitrate.bps() + args.audio_bitrate().bps());
+    Some(disk_space::bitrate_size_estimate(total_bitrate, output_duration_secs(frame_count, video_info)))
+}
+
+// checks the space available for the transcoded video and the mount options of its file system, `input_size` is the size of
+// the input video files
+fn check_transcode_output_space(args: &TranscodeVideoArgs, output_video_file: &Path, input_size: Option<u64>, video_info: &probe::Result,
+        frame_count: u64) -> Result<(), TranscodeVideoError> {
+    let size_estimate = transcode_output_size_estimate(args, input_size, video_info, frame_count);
+    // the size of the input is the best guess of the output size for the file system checks when it cannot be estimated
+    removable_media::check_output(output_video_file, size_estimate.or(input_size));
+    if let Some(size_estimate) = size_estimate {
+        disk_space::check(output_video_file, size_estimate)?;
+    }
+    Ok(())
+}
+
+// adds the music input and maps the [ao] output of a filtergraph mixing the music with the original audio filtered by
+// `original_audio_filter_parts`, the music alone is cut at the end of the video if the original audio is not kept
+fn add_music_mix(ffmpeg_command: &mut ffmpeg::CommandBuilder, args: &TranscodeVideoArgs, music_file: &Path,
+        original_audio_filter_parts: Option<Vec<String>>, output_duration_secs: f64) {
+    let music_input_index = ffmpeg_command.input_count();
+    let music_chain = format!("[{music_input_index}:a]volume={}", args.music_args().music_volume());
+    let filter = match original_audio_filter_parts {
+        None => {
+            if args.music_args().music_ducking() { log::warn!("the video has no audio to duck the music against, ignoring --music-ducking") }
+            format!("{music_chain},atrim=duration={output_duration_secs:.3}[ao]")
+        },
+        Some(audio_filter_parts) => {
+            let original_chain = format!("[{}]{}", args.audio_track_stream(), if audio_filter_parts.is_empty() { "anull".to_owned() } else { audio_filter_parts.join(",") });
+            let mix = "amix=inputs=2:duration=first:normalize=0[ao]";
+            match args.music_args().music_ducking() {
+                true => format!("{original_chain},asplit=2[original][sidechain];{music_chain}[music];\
+                    [music][sidechain]sidechaincompress=threshold=0.02:ratio=10:attack=50:release=500[ducked];[original][ducked]{mix}"),
+                false => format!("{original_chain}[original];{music_chain}[music];[original][music]{mix}"),
+            }
+        },
+    };
+    // the mixed audio is also needed by the proxy output
+    let filter = match args.proxy_output().is_some() {
+        true => format!("{},asplit=2[ao][ap]", filter.strip_suffix("[ao]").unwrap_or(&filter)),
+        false => filter,
+    };
+    log::info!("mixing background music: {}", music_file.to_string_lossy());
+    ffmpeg_command
+        .add_input_file(music_file)
+        .add_complex_filter(&filter)
+        .add_mapping("[ao]")
+        .set_output_audio_settings(Some(args.audio_encoder()), Some(args.audio_bitrate()));
+}
+
+/// Encoder and constant quality of the proxy videos, fast to encode and decoded by every editor
+const PROXY_VIDEO_ENCODER: &str = "libx264";
+const PROXY_VIDEO_CRF: u8 = 23;
+
+/// Flights shorter than this are not marked as chapters
+pub(super) const MIN_FLIGHT_CHAPTER_DURATION_SECS: f64 = 10.0;
+
+// the GPS location and the flight chapters are read from the OSD frames so they are only available when burning the OSD
+fn check_metadata_args_without_osd(args: &TranscodeVideoArgs) -> Result<(), TranscodeVideoError> {
+    if args.metadata_args().requires_osd() {
+        return Err(TranscodeVideoError::IncompatibleArguments("--gps-location and --flight-chapters require burning the OSD from an OSD file".to_owned()));
+    }
+    Ok(())
+}
+
+// chapter marks of the flights detected on the OSD, the flight times are mapped onto the output video timeline
+fn flight_chapters(flights: &[osd::flights::Flight], video_timeline: VideoTimeline, args: &TranscodeVideoArgs, start_end: &StartEndArgs, annotations: Option<&Annotations>,
+        output_duration_secs: f64) -> Vec<Chapter> {
+    let speed_factor = args.speed_args().speed().map_or(1.0, |speed| speed.factor());
+    let start_secs = start_end.start().map_or(0.0, |start| start.total_seconds() as f64) / speed_factor;
+    let title_card_duration = annotations.map_or(0.0, Annotations::title_card_duration);
+    let output_time = |osd_time: f64| (video_timeline.video_time(osd_time) - start_secs + title_card_duration).clamp(title_card_duration, output_duration_secs);
+    flights.iter().enumerate().filter_map(|(flight_index, flight)| {
+        let (start_secs, end_secs) = (output_time(flight.start_secs()), output_time(flight.end_secs()));
+        (end_secs > start_secs).then(|| Chapter { start_secs, end_secs, title: format!("Flight {}", flight_index + 1) })
+    }).collect()
+}
+
+fn output_metadata(args: &TranscodeVideoArgs, video_info: &probe::Result, location: Option<(f64, f64)>, chapters: Vec<Chapter>) -> OutputMetadata {
+    let metadata_args = args.metadata_args();
+    let creation_time = match metadata_args.copy_creation_time() {
+        true => {
+            if video_info.creation_time().is_none() { log::warn!("the input video has no creation time") }
+            video_info.creation_time().clone()
+        },
+        false => None,
+    };
+    if metadata_args.gps_location() && location.is_none() { log::warn!("no GPS position found in the OSD telemetry") }
+    if metadata_args.flight_chapters() && chapters.is_empty() { log::warn!("no flight detected on the OSD, no chapter added") }
+    let mut metadata = OutputMetadata::default();
+    metadata
+        .set_title(metadata_args.title().clone())
+        .set_creation_time(creation_time)
+        .set_location(location)
+        .set_chapters(chapters);
+    metadata
+}
+
+fn read_annotations(args: &TranscodeVideoArgs) -> Result<Option<Annotations>, TranscodeVideoError> {
+    Ok(args.annotations().as_ref().map(Annotations::read_from_file).transpose()?)
+}
+
+fn title_card_frame_count(annotations: Option<&Annotations>, video_info: &probe::Result) -> u64 {
+    let frame_rate = video_info.frame_rate();
+    let title_card_duration = annotations.map_or(0.0, Annotations::title_card_duration);
+    (title_card_duration * frame_rate.numerator() as f64 / frame_rate.denominator() as f64).round() as u64
+}
+
+// completes the filtergraph whose last chain is left open: the annotations are drawn on the video, the title card is
+// prepended, then the hardware upload filter is applied and the output is labelled [vo]
+fn finish_output_video_filter(mut filter: String, annotations: Option<&Annotations>, hw_filter: Option<String>,
+        args: &TranscodeVideoArgs, video_info: &probe::Result) -> String {
+    if let Some(annotations) = annotations {
+        let output_resolution = args.video_transform().transformed_resolution(video_info.resolution());
+        for filter_part in annotations.ffmpeg_annotation_filter_parts(output_resolution) {
+            filter.push(',');
+            filter.push_str(&filter_part);
+        }
+        if let Some(title_card_chain) = annotations.ffmpeg_title_card_chain(output_resolution, video_info.frame_rate(), "card") {
+            filter = format!("{filter}[main];{title_card_chain};[card][main]concat=n=2:v=1:a=0");
+        }
+    }
+    // the proxy branch is split off before the upload to the hardware encoder since it is encoded in software
+    if args.proxy_output().is_some() {
+        filter = format!("{filter},split=2[vmain][vproxy];[vproxy]scale=-2:{},format=yuv420p[vp];[vmain]null", args.proxy_height());
+    }
+    if let Some(hw_filter) = hw_filter {
+        filter.push(',');
+        filter.push_str(&hw_filter);
+    }
+    filter + "[vo]"
+}
+
+// the output is written under a temporary name and renamed once complete, except when it is downloaded from a remote host
+fn check_transcode_output_file(args: &TranscodeVideoArgs, output_video_file: &Path) -> Result<(), file::TouchError> {
+    match args.remote_encoding().host() {
+        Some(_) => file::touch(output_video_file),
+        None => file::check_output_path(output_video_file),
+    }
+}
+
+// checks the proxy output file path, returns it if a proxy is requested
+fn proxy_output_file(args: &TranscodeVideoArgs) -> Result<Option<&Path>, TranscodeVideoError> {
+    let Some(proxy_output_file) = args.proxy_output() else { return Ok(None) };
+    if proxy_output_file == args.input_video_file() { return Err(TranscodeVideoError::InputAndOutputFileIsTheSame) }
+    if ! args.overwrite() && proxy_output_file.exists() { return Err(TranscodeVideoError::ProxyOutputFileExists(proxy_output_file.clone())) }
+    file::check_output_path(proxy_output_file)?;
+    Ok(Some(proxy_output_file))
+}
+
+// adds the proxy output encoded from the [vp] output of the video filter with the audio of the main output, `audio_filter_parts`
+// are the audio filters of the main output, the mixed in music is taken from the [ap] output of the music mix
+fn add_proxy_output(ffmpeg_command: &mut ffmpeg::CommandBuilder, args: &TranscodeVideoArgs, proxy_output_file: &Path,
+        keep_audio: bool, audio_filter_parts: &[String]) {
+    let mut proxy_output = ffmpeg::AdditionalOutput::new(proxy_output_file);
+    proxy_output
+        .add_mapping(ffmpeg::Mapping::WithoutFilter("[vp]".to_owned()))
+        .set_output_video_settings(Some(PROXY_VIDEO_ENCODER), None, Some(PROXY_VIDEO_CRF))
+        .add_args(&["-preset", "veryfast", "-map_metadata", &map_metadata(args.metadata_args().preserve_metadata()).to_string()]);
+    let audio_mapping = match (args.music_args().music().is_some(), keep_audio, audio_filter_parts.is_empty()) {
+        (true, _, _) => Some(ffmpeg::Mapping::WithoutFilter("[ap]".to_owned())),
+        (false, true, true) => Some(ffmpeg::Mapping::WithoutFilter(args.audio_track_stream())),
+        (false, true, false) => Some(ffmpeg::Mapping::new_with_audio_filter(&args.audio_track_stream(), &audio_filter_parts.join(","))),
+        (false, false, _) => None,
+    };
+    if let Some(audio_mapping) = audio_mapping {
+        proxy_output
+            .add_mapping(audio_mapping)
+            .set_output_audio_settings(Some(args.audio_encoder()), Some(args.audio_bitrate()));
+    }
+    log::info!("writing proxy video: {}", proxy_output_file.to_string_lossy());
+    ffmpeg_command.add_additional_output(proxy_output);
+}
+
+// sets the video encoder settings and returns the complex filter overlaying input 1 over input 0 into [vo]
+fn burn_osd_complex_filter(ffmpeg_command: &mut ffmpeg::CommandBuilder, args: &TranscodeVideoArgs, start_end: &StartEndArgs, video_info: &probe::Result,
+        osd_placement: OverlayPlacement, annotations: Option<&Annotations>) -> Result<String, TranscodeVideoError> {
+    let (hw_filter, ten_bit) = configure_video_encoder(ffmpeg_command, args, video_info)?;
+    // the overlay filter converts its output to 8-bit by default
+    let overlay_format = if ten_bit { ":format=yuv420p10" } else { "" };
+
+    let position = osd_placement.ffmpeg_overlay_position();
+    let input_filter_parts = input_video_filter_parts(args, start_end, video_info)?;
+    let filter = if input_filter_parts.is_empty() {
+        format!("[0][1]overlay=eof_action=repeat:{position}{overlay_format}")
+    } else {
+        format!("[0]{}[s1];[s1][1]overlay=eof_action=repeat:{position}{overlay_format}", input_filter_parts.join(","))
+    };
+    Ok(finish_output_video_filter(filter, annotations, hw_filter, args, video_info))
+}
+
+// needs to be called once all the other inputs have been added since the music is added as the last input
+fn add_burn_osd_audio_settings(ffmpeg_command: &mut ffmpeg::CommandBuilder, args: &TranscodeVideoArgs, video_info: &probe::Result,
+        annotations: Option<&Annotations>, frame_count: u64) -> Result<(), TranscodeVideoError> {
+    if ! video_info.has_audio() && args.video_audio_fix().is_some() { return Err(TranscodeVideoError::RequestedAudioFixingButInputHasNoAudio) }
+    check_audio_track(args, video_info)?;
+    let keep_audio = keeps_audio(args, video_info);
+    let audio_filter_parts = audio_filter_parts(args, video_info, annotations);
+    if let Some(music_file) = music_file(args)? {
+        add_music_mix(ffmpeg_command, args, music_file, keep_audio.then_some(audio_filter_parts), output_duration_secs(frame_count, video_info));
+        return Ok(());
+    }
+    if ! keep_audio { return Ok(()) }
+    match audio_filter_parts.is_empty() {
+        true => { ffmpeg_command.add_mapping(&args.audio_tracks_mapping()); },
+        false => {
+            ffmpeg_command
+                .add_mapping_with_audio_filter(&args.audio_tracks_mapping(), &audio_filter_parts.join(","))
+                .set_output_audio_settings(Some(args.audio_encoder()), Some(args.audio_bitrate()));
+        },
+    }
+    Ok(())
+}
+
+/// Burns pre-generated OSD frames from a directory written by the `generate-overlay-frames` command onto a video
+///
+/// The frames are used as is: they must have been generated for the whole video with the frame shift already applied.
+pub async fn transcode_burn_osd_frames_dir<P: AsRef<Path>>(args: &TranscodeVideoArgs, start_end: &StartEndArgs, osd_frames_dir: P, osd_margin_args: &OSDMarginArgs) -> Result<(), TranscodeVideoError> {
+
+    let osd_frames_dir = osd_frames_dir.as_ref();
+    let output_video_file = args.output_video_file(true)?;
+
+    if ! args.input_video_file().exists() { return Err(TranscodeVideoError::InputVideoFileDoesNotExist); }
+    if ! osd_frames_dir.is_dir() { return Err(TranscodeVideoError::OSDFramesDirDoesNotExist(osd_frames_dir.to_path_buf())); }
+    if ! args.overwrite() && output_video_file.exists() { return Err(TranscodeVideoError::OutputVideoFileExists); }
+    if *args.input_video_file() == output_video_file { return Err(TranscodeVideoError::InputAndOutputFileIsTheSame) }
+    check_transcode_output_file(args, &output_video_file)?;
+    if start_end.start().is_some() && matches!(args.video_audio_fix(), Some(fix) if fix.sync()) {
+        return Err(TranscodeVideoError::IncompatibleArguments("cannot fix video audio sync while not starting at the beginning of the file".to_owned()));
+    }
+
+    if args.speed_args().speed().is_some() {
+        return Err(TranscodeVideoError::IncompatibleArguments("cannot change the video speed while burning OSD frames from a directory".to_owned()));
+    }
+    if args.remote_encoding().host().is_some() {
+        return Err(TranscodeVideoError::IncompatibleArguments("cannot encode on a remote host while burning OSD frames from a directory".to_owned()));
+    }
+    check_metadata_args_without_osd(args)?;
+    let proxy_output_file = proxy_output_file(args)?;
+
+    let input_video_file = removable_media::stage_if_requested(args.input_video_file(), args.copy_local_first(), args.staging_dir())?;
+    let video_info = probe_input_video(args, input_video_file.path())?;
+
+    log::info!("transcoding video: {} + {} -> {}", args.input_video_file().to_string_lossy(), osd_frames_dir.to_string_lossy(), output_video_file.to_string_lossy());
+
+    if video_info.frame_rate().numerator() != i32::from(osd::OSD_FRAME_RATE) || video_info.frame_rate().denominator() != 1 {
+        return Err(TranscodeVideoError::CanOnlyBurnOSDOn60FPSVideo(video_info.frame_rate().numerator() as f64 / video_info.frame_rate().denominator() as f64))
+    }
+
+    let annotations = read_annotations(args)?;
+    let (mut frame_count, first_frame_index, _) = burn_osd_frame_range(&video_info, start_end);
+    frame_count += title_card_frame_count(annotations.as_ref(), &video_info);
+    // directories written with the manifest frame link mode only contain the unique frames
+    let frames_manifest = match FramesManifest::exists_in(osd_frames_dir) {
+        true => Some(FramesManifest::read_from_dir(osd_frames_dir)?),
+        false => None,
+    };
+    let has_frame_at_start = match &frames_manifest {
+        Some(frames_manifest) => frames_manifest.file_name_at(first_frame_index).is_some(),
+        None => osd::overlay::make_overlay_frame_file_path(osd_frames_dir, first_frame_index, FrameImageFormat::Png).exists(),
+    };
+    if ! has_frame_at_start {
+        return Err(TranscodeVideoError::OSDFramesDirHasNoFrameAtStart(first_frame_index));
+    }
+    let concat_script_file = match &frames_manifest {
+        Some(frames_manifest) => {
+            let concat_script_file = std::env::temp_dir().join(format!("{}_{}_osd_frames.ffconcat", env!("CARGO_PKG_NAME"), std::process::id()));
+            frames_manifest.write_concat_script(osd_frames_dir, first_frame_index, &concat_script_file).map_err(TranscodeVideoError::OSDFramesManifestWriteError)?;
+            Some(concat_script_file)
+        },
+        None => None,
+    };
+
+    check_transcode_output_space(args, &output_video_file, file_size(input_video_file.path()), &video_info, frame_count)?;
+    let two_pass_log_file_prefix = args.two_pass().then(|| ffmpeg::two_pass_log_file_prefix(&output_video_file));
+
+    let mut ffmpeg_command = ffmpeg::CommandBuilder::default();
+    ffmpeg_command.set_progress_callback(args.progress_callback());
+    let complex_filter = burn_osd_complex_filter(&mut ffmpeg_command, args, start_end, &video_info, OverlayPlacement::default().with_margins(osd_margin_args),
+        annotations.as_ref())?;
+
+    ffmpeg_command
+        .add_input_file_slice(input_video_file.path(), start_end.start(), start_end.end())
+        .set_map_metadata(Some(map_metadata(args.metadata_args().preserve_metadata())));
+    match &concat_script_file {
+        Some(concat_script_file) => ffmpeg_command.add_concat_script_input(concat_script_file),
+        None => ffmpeg_command.add_image_sequence_input(osd_frames_dir.join(osd::overlay::OVERLAY_FRAME_FILE_NAME_PATTERN), osd::OSD_FRAME_RATE, first_frame_index),
+    };
+    ffmpeg_command
+        .add_complex_filter(&complex_filter)
+        .add_mapping("[vo]")
+        .set_output_format(args.container().map(|container| container.muxer()))
+        .set_output_movflags(args.output_movflags(&output_video_file))
+        .set_output_file(&output_video_file)
+        .set_overwrite_output_file(true)
+        .set_atomic_output(! args.fragmented_mp4());
+
+    add_burn_osd_audio_settings(&mut ffmpeg_command, args, &video_info, annotations.as_ref(), frame_count)?;
+    if let Some(proxy_output_file) = proxy_output_file {
+        add_proxy_output(&mut ffmpeg_command, args, proxy_output_file, keeps_audio(args, &video_info), &audio_filter_parts(args, &video_info, annotations.as_ref()));
+    }
+    output_metadata(args, &video_info, None, vec![]).add_to_ffmpeg_command(&mut ffmpeg_command).map_err(TranscodeVideoError::ChaptersWriteError)?;
+
+    let encode_result = async {
+        for pass_command in pass_commands(&ffmpeg_command, two_pass_log_file_prefix.as_deref()) {
+            pass_command.build().unwrap().spawn_with_progress(frame_count)?.wait().await?;
+        }
+        Ok::<(), TranscodeVideoError>(())
+    }.await;
+    if let Some(two_pass_log_file_prefix) = &two_pass_log_file_prefix {
+        ffmpeg::remove_two_pass_log_files(two_pass_log_file_prefix);
+    }
+    if let Some(concat_script_file) = &concat_script_file {
+        let _ = fs_err::remove_file(concat_script_file);
+    }
+    encode_result?;
+
+    if args.metadata_args().preserve_metadata() {
+        preserve_modification_time(args.input_video_file(), &output_video_file);
+        if let Some(proxy_output_file) = proxy_output_file { preserve_modification_time(args.input_video_file(), proxy_output_file) }
+    }
+
+    log::info!("{frame_count} frames transcoded successfully");
+    Ok(())
+}
+
+// the Walksnail OSD timestamps drift relative to the video over long recordings, the drift is estimated by comparing
+// the duration of the OSD recording with the duration of the video after the start of the OSD recording
+pub(super) fn osd_timebase_correction(osd_timebase_correction: Option<OSDTimebaseCorrection>, estimate: bool, osd_speed: OSDSpeed,
+        osd_file: &mut osd::file::Reader, video_info: &probe::Result, osd_video_offset: OSDVideoOffset) -> Result<OSDTimebaseCorrection, OSDFileReadError> {
+    if let Some(osd_timebase_correction) = osd_timebase_correction { return Ok(osd_timebase_correction) }
+    if ! estimate { return Ok(OSDTimebaseCorrection::NONE) }
+    if ! matches!(osd_file, osd::file::Reader::WSA(_)) {
+        log::warn!("the OSD timebase drift is only estimated for Walksnail OSD files, not correcting it");
+        return Ok(OSDTimebaseCorrection::NONE)
+    }
+    let osd_duration_secs = (osd_file.last_frame_frame_index()? + 1) as f64 / osd::OSD_TIME_BASE_HZ;
+    // duration of the footage in the time of the original recording
+    let video_duration_secs = video_info.duration() * osd_speed.factor() - osd_video_offset.as_secs();
+    Ok(match OSDTimebaseCorrection::estimate(osd_duration_secs, video_duration_secs) {
+        Some(osd_timebase_correction) => {
+            log::info!("OSD duration {osd_duration_secs:.2}s, video duration {video_duration_secs:.2}s: correcting the OSD timebase by a factor of {:.5}",
+                osd_timebase_correction.factor());
+            osd_timebase_correction
+        },
+        None => {
+            log::info!("OSD duration {osd_duration_secs:.2}s, video duration {video_duration_secs:.2}s: cannot estimate the OSD timebase drift, not correcting it");
+            OSDTimebaseCorrection::NONE
+        },
+    })
+}
+
+// placement of the overlay from the video offset of the DJI OSD files, the offset is relative to the recorded picture
+// so it cannot be applied once the video is rotated or cropped
+fn osd_file_placement(osd_file: &osd::file::Reader, overlay_resolution: Resolution, args: &TranscodeVideoArgs) -> OverlayPlacement {
+    let osd::file::Reader::DJI(osd_file) = osd_file else { return OverlayPlacement::default() };
+    let header = osd_file.header();
+    let offset = (*header.offset().x(), *header.offset().y());
+    if offset == (0, 0) { return OverlayPlacement::default() }
+    if ! args.video_transform().is_identity() {
+        log::warn!("ignoring the OSD file video offset ({}) since the video is transformed, the OSD is centered", header.offset());
+        return OverlayPlacement::default();
+    }
+    let native_overlay_resolution = Resolution::new(
+        header.osd_dimensions().width * header.tile_dimensions().width,
+        header.osd_dimensions().height * header.tile_dimensions().height,
+    );
+    log::info!("placing the OSD according to the OSD file video offset: {}", header.offset());
+    OverlayPlacement::from_osd_file_offset(offset, native_overlay_resolution, overlay_resolution)
+}
+
+/// Transcodes a video burning the OSD from the OSD file or OSD frames directory of the OSD arguments if any,
+/// the way the `transcode-video` command does
+#[derive(Debug, Error, From)]
+pub enum TranscodeWithOSDArgsError {
+    #[error(transparent)]
+    StartGreaterThanEnd(StartGreaterThanEndError),
+    #[error(transparent)]
+    RequestedOSDButNoFileProvidedNorFound(RequestedOSDButNoFileProvidedNorFound),
+    #[error(transparent)]
+    TrimToOSDError(trim_to_osd::TrimToOSDError),
+    #[error(transparent)]
+    TranscodeVideoError(TranscodeVideoError),
+}
+
+pub async fn transcode_with_osd_args(args: &TranscodeVideoArgs, osd_args: &TranscodeVideoOSDArgs) -> Result<(), TranscodeWithOSDArgsError> {
+    let osd_file_path = osd_args.osd_file_path(args.input_video_file())?;
+    let osd_start_end = match args.trim_to_osd() {
+        true => Some(trim_to_osd::osd_start_end(args.input_video_file(), osd_file_path.as_deref(), osd_args.osd_frame_shift(),
+            osd_args.osd_video_offset(), osd_args.osd_speed(), osd_args.osd_timebase_correction(), osd_args.osd_estimate_timebase_correction())?),
+        false => None,
+    };
+    let start_end = osd_start_end.as_ref().unwrap_or(args.start_end());
+    start_end.check_valid()?;
+    if let Some(osd_frames_dir) = osd_args.osd_frames_dir() {
+        return Ok(transcode_burn_osd_frames_dir(args, start_end, osd_frames_dir, osd_args.osd_margin_args()).await?);
+    }
+    match osd_file_path {
+        Some(osd_file_path) => transcode_burn_osd(args, start_end, osd_file_path, osd_args).await?,
+        None if ! osd_args.continuations(args.input_video_file()).is_empty() =>
+            return Err(TranscodeVideoError::IncompatibleArguments("the continuations of a recording can only be joined when burning the OSD".to_owned()).into()),
+        None => transcode(args, start_end).await?,
+    }
+    Ok(())
+}
+
+pub async fn transcode_burn_osd<P: AsRef<Path>>(args: &TranscodeVideoArgs, start_end: &StartEndArgs, osd_file_path: P, osd_args: &TranscodeVideoOSDArgs) -> Result<(), TranscodeVideoError> {
+
+    let output_video_file = args.output_video_file(true)?;
+
+    if ! args.input_video_file().exists() { return Err(TranscodeVideoError::InputVideoFileDoesNotExist); }
+    if ! args.overwrite() && output_video_file.exists() { return Err(TranscodeVideoError::OutputVideoFileExists); }
+    if *args.input_video_file() == output_video_file { return Err(TranscodeVideoError::InputAndOutputFileIsTheSame) }
+    check_transcode_output_file(args, &output_video_file)?;
+    if start_end.start().is_some() && matches!(args.video_audio_fix(), Some(fix) if fix.sync()) {
+        return Err(TranscodeVideoError::IncompatibleArguments("cannot fix video audio sync while not starting at the beginning of the file".to_owned()));
+    }
+    // the chapters metadata file is written locally
+    if args.metadata_args().flight_chapters() && args.remote_encoding().host().is_some() {
+        return Err(TranscodeVideoError::IncompatibleArguments("cannot add flight chapters while encoding on a remote host".to_owned()));
+    }
+    let proxy_output_file = proxy_output_file(args)?;
+
+    // the parts of a split recording are read through a concat script listing their video files along with an OSD file joining
+    // their OSD files, the OSD frame shift and the audio sync are detected on the first part which starts the joined video
+    let continuations = osd_args.continuations(args.input_video_file());
+    let joined_recording = match continuations.is_empty() {
+        true => None,
+        false => {
+            if args.remote_encoding().host().is_some() {
+                return Err(TranscodeVideoError::IncompatibleArguments("cannot join the continuations of a recording while encoding on a remote host".to_owned()));
+            }
+            let work_dir = args.staging_dir().clone().unwrap_or_else(removable_media::default_staging_dir);
+            Some(continuation::join(args.input_video_file(), &osd_file_path, &continuations, work_dir)?)
+        },
+    };
+    let (input_video_file, osd_file_path) = match &joined_recording {
+        Some(joined_recording) => (StagedFile::unstaged(args.input_video_file()), joined_recording.osd_file().to_path_buf()),
+        None => (removable_media::stage_if_requested(args.input_video_file(), args.copy_local_first(), args.staging_dir())?,
+            osd_file_path.as_ref().to_path_buf()),
+    };
+
+    let video_info = match &joined_recording {
+        Some(joined_recording) => input_video_info(args, joined_recording.video_info().clone()),
+        None => probe_input_video(args, input_video_file.path())?,
+    };
+
+    log::info!("transcoding video: {} -> {}", args.input_video_file().to_string_lossy(), output_video_file.to_string_lossy());
+
+    // the OSD frames are mapped onto the video timeline when they cannot be sent 1:1 with the video frames,
+    // with a VFR video they are sent with their 60Hz time base and overlaid according to the video frame timestamps
+    let timestamp_based_osd_mapping = video_info.variable_frame_rate();
+    let frame_rate = if timestamp_based_osd_mapping { Rational::new(osd::OSD_FRAME_RATE.into(), 1) } else { video_info.frame_rate() };
+    let mut osd_file = osd::file::open(osd_file_path)?;
+    let osd_video_offset = match (osd_args.osd_video_offset(), osd_args.osd_audio_sync_reference()) {
+        (Some(osd_video_offset), _) => Some(osd_video_offset),
+        (None, Some(reference_video_file)) => {
+            // the OSD is synchronized with the reference video the same way as when burning it onto the reference video
+            let reference_frame_shift = osd_frame_shift_for_video(osd_args.osd_frame_shift(), &probe(reference_video_file)?, &mut osd_file)?;
+            let audio_offset = audio_sync::find_offset(reference_video_file, input_video_file.path(), osd_args.osd_audio_sync_max_offset())?;
+            Some(OSDVideoOffset::from_secs(audio_offset.offset_secs() + OSDVideoOffset::from_osd_frame_shift(reference_frame_shift).as_secs()))
+        },
+        (None, None) => None,
+    };
+    let osd_frame_shift = match osd_video_offset {
+        Some(_) => OSDFrameShift::default(),
+        None => osd_frame_shift_for_video(osd_args.osd_frame_shift(), &video_info, &mut osd_file)?,
+    };
+    let speed = args.speed_args().speed();
+    let osd_speed = osd_args.osd_speed().unwrap_or(OSDSpeed::NORMAL);
+    let osd_timebase_correction = osd_timebase_correction(osd_args.osd_timebase_correction(), osd_args.osd_estimate_timebase_correction(), osd_speed, &mut osd_file, &video_info,
+        osd_video_offset.unwrap_or_else(|| OSDVideoOffset::from_osd_frame_shift(osd_frame_shift)))?;
+    let mut video_timeline = match osd_video_offset {
+        Some(osd_video_offset) => Some(VideoTimeline::new(frame_rate.numerator(), frame_rate.denominator(), osd_video_offset)),
+        // fractional frame shifts are placed with a sub-frame precision by the video timeline
+        None if frame_rate.numerator() != i32::from(osd::OSD_FRAME_RATE) || frame_rate.denominator() != 1 || speed.is_some() || osd_speed != OSDSpeed::NORMAL ||
+                osd_timebase_correction != OSDTimebaseCorrection::NONE || ! osd_frame_shift.is_whole() =>
+            Some(VideoTimeline::new(frame_rate.numerator(), frame_rate.denominator(), OSDVideoOffset::from_osd_frame_shift(osd_frame_shift))),
+        None => None,
+    };
+    // the OSD frames are resampled the same way as the video frames, the OSD speed stretching the timeline of the input video
+    let speed_factor = speed.map_or(1.0, |speed| speed.factor()) * osd_speed.factor();
+    if let Some(video_timeline) = &mut video_timeline { video_timeline.set_speed(speed_factor); }
+    if let Some(video_timeline) = &mut video_timeline { video_timeline.set_osd_timebase_correction(osd_timebase_correction); }
+    if let Some(video_timeline) = &video_timeline { log::debug!("mapping OSD frames onto video timeline: {video_timeline:?}") }
+
+    let output_resolution = args.video_transform().transformed_resolution(video_info.resolution());
+    let osd_scaling = Scaling::try_from_osd_args(osd_args.osd_scaling_args(), output_resolution)?;
+    let osd_font_dir = osd_args.osd_font_options().osd_font_dir_with_fallback()?;
+    let osd_font_variant = osd_args.osd_font_options().osd_font_variant(osd_file.font_variant());
+    let mut osd_file_frames = osd_file.frames()?;
+    if let Some(unit_system) = osd_args.osd_units() {
+        osd_file_frames = osd::units::convert_frames(&osd_file_frames, osd_font_variant, unit_system);
+    }
+    let flights = match args.metadata_args().flight_chapters() {
+        true => osd::flights::detect_flights(&osd_file_frames, osd_font_variant, MIN_FLIGHT_CHAPTER_DURATION_SECS),
+        false => vec![],
+    };
+    #[cfg(feature = "telemetry")]
+    let location = match args.metadata_args().gps_location() {
+        true => osd::telemetry::decode_frames(&osd_file_frames, osd_font_variant).iter().find_map(osd::telemetry::Sample::position),
+        false => None,
+    };
+    #[cfg(not(feature = "telemetry"))]
+    let location = None;
+    if osd_args.osd_interpolate_values() {
+        osd_file_frames = osd::interpolation::interpolate_frames(&osd_file_frames, osd_font_variant, osd::interpolation::DEFAULT_MAX_GAP);
+    }
+    if ! osd_args.osd_add_text().is_empty() {
+        osd_file_frames = osd::text_injection::inject_text(&osd_file_frames, osd_font_variant, osd_args.osd_add_text());
+    }
+    let mut osd_frames_generator = OverlayGenerator::new(
+        osd_file_frames,
+        osd_font_variant,
+        &osd_font_dir,
+        &osd_args.osd_font_options().osd_font_ident(),
+        osd_scaling,
+        osd_args.osd_hide_regions(),
+        osd_args.osd_strict_regions(),
+        osd_args.osd_hide_items()
+    )?;
+    osd_frames_generator.set_stick_overlay(osd_args.stick_overlay_args().stick_overlay())
+        .set_enlargement(osd_args.enlargement_args().enlargement())
+        .set_render_thread_count(osd_args.osd_render_threads() as usize);
+
+    let (mut frame_count, mut first_frame_index, mut last_frame_index) = burn_osd_frame_range(&video_info, start_end);
+    if timestamp_based_osd_mapping { (first_frame_index, last_frame_index) = burn_osd_timestamp_range(&video_info, start_end) }
+    if let Some(speed) = speed {
+        frame_count = speed.output_frame_count(frame_count);
+        first_frame_index = speed.output_frame_index(first_frame_index);
+        last_frame_index = speed.output_frame_index(last_frame_index);
+    }
+    let annotations = read_annotations(args)?;
+    frame_count += title_card_frame_count(annotations.as_ref(), &video_info);
+    log::debug!("frame count: video={}, transcode={}", video_info.frame_count(), frame_count);
+    let osd_overlay_resolution = osd_frames_generator.frame_dimensions();
+    let osd_placement = osd_file_placement(&osd_file, osd_overlay_resolution, args).with_margins(osd_args.osd_margin_args());
+
+    let mut ffmpeg_command = ffmpeg::CommandBuilder::default();
+    ffmpeg_command.set_progress_callback(args.progress_callback());
+
+    let complex_filter = burn_osd_complex_filter(&mut ffmpeg_command, args, start_end, &video_info, osd_placement, annotations.as_ref())?;
+
+    let input_size = match &joined_recording {
+        Some(joined_recording) => Some(joined_recording.video_size()),
+        None => file_size(input_video_file.path()),
+    };
+    check_transcode_output_space(args, &output_video_file, input_size, &video_info, frame_count)?;
+    let remote_encode = prepare_remote_encode(args, input_video_file.path(), &output_video_file)?;
+    let (encode_input_file, encode_output_file) = encode_file_paths(remote_encode.as_ref(), input_video_file.path(), &output_video_file);
+    let two_pass_log_file_prefix = args.two_pass().then(|| ffmpeg::two_pass_log_file_prefix(&encode_output_file));
+
+    match &joined_recording {
+        Some(joined_recording) => ffmpeg_command.add_concat_script_input_slice(joined_recording.concat_script_file(), start_end.start(), start_end.end()),
+        None => ffmpeg_command.add_input_file_slice(&encode_input_file, start_end.start(), start_end.end()),
+    };
+    ffmpeg_command
+        .set_map_metadata(Some(map_metadata(args.metadata_args().preserve_metadata())))
+        .set_remote_host(remote_encode.as_ref().map(|remote_encode| remote_encode.host().clone()));
+    match video_timeline {
+        Some(_) => ffmpeg_command.add_stdin_input_with_rational_frame_rate(osd_overlay_resolution, frame_rate.numerator(), frame_rate.denominator()).unwrap(),
+        None => ffmpeg_command.add_stdin_input(osd_overlay_resolution, osd::OSD_FRAME_RATE).unwrap(),
+    };
+    ffmpeg_command
+        .add_complex_filter(&complex_filter)
+        .add_mapping("[vo]")
+        .set_output_format(args.container().map(|container| container.muxer()))
+        .set_output_movflags(args.output_movflags(&output_video_file))
+        .set_output_file(&encode_output_file)
+        .set_overwrite_output_file(true)
+        .set_atomic_output(! args.fragmented_mp4());
+
+    add_burn_osd_audio_settings(&mut ffmpeg_command, args, &video_info, annotations.as_ref(), frame_count)?;
+    if let Some(proxy_output_file) = proxy_output_file {
+        add_proxy_output(&mut ffmpeg_command, args, proxy_output_file, keeps_audio(args, &video_info), &audio_filter_parts(args, &video_info, annotations.as_ref()));
+    }
+
+    let chapters_video_timeline = video_timeline.unwrap_or_else(||
+        VideoTimeline::new(frame_rate.numerator(), frame_rate.denominator(), OSDVideoOffset::from_osd_frame_shift(osd_frame_shift)));
+    let chapters = flight_chapters(&flights, chapters_video_timeline, args, start_end, annotations.as_ref(), output_duration_secs(frame_count, &video_info));
+    let chapters_file = output_metadata(args, &video_info, location, chapters).add_to_ffmpeg_command(&mut ffmpeg_command)
+        .map_err(TranscodeVideoError::ChaptersWriteError)?;
+
+    let encode_result = async {
+        // the OSD frames need to be sent again for each pass
+        for pass_command in pass_commands(&ffmpeg_command, two_pass_log_file_prefix.as_deref()) {
+            let ffmpeg_process = pass_command.build().unwrap().spawn_with_progress(frame_count)?;
+            match video_timeline {
+                Some(video_timeline) => {
+                    let osd_frames_iter = osd_frames_generator.iter_for_video_timeline(first_frame_index, last_frame_index, video_timeline);
+                    osd_frames_iter.send_frames_to_ffmpeg_and_wait(ffmpeg_process).await?;
+                },
+                None => {
+                    let osd_frames_iter = osd_frames_generator.iter_advanced(first_frame_index, Some(last_frame_index), osd_frame_shift.nearest_frames());
+                    osd_frames_iter.send_frames_to_ffmpeg_and_wait(ffmpeg_process).await?;
+                },
+            }
+        }
+        Ok::<(), TranscodeVideoError>(())
+    }.await;
+    if let (Some(two_pass_log_file_prefix), None) = (&two_pass_log_file_prefix, &remote_encode) {
+        ffmpeg::remove_two_pass_log_files(two_pass_log_file_prefix);
+    }
+    if let Some(chapters_file) = &chapters_file {
+        let _ = fs_err::remove_file(chapters_file);
+    }
+    let remote_encode_result = remote_encode.map(|remote_encode| remote_encode.finish(encode_result.is_ok())).transpose();
+    encode_result?;
+    remote_encode_result?;
+
+    if args.metadata_args().preserve_metadata() {
+        preserve_modification_time(args.input_video_file(), &output_video_file);
+        if let Some(proxy_output_file) = proxy_output_file { preserve_modification_time(args.input_video_file(), proxy_output_file) }
+    }
+
+    log::info!("{frame_count} frames transcoded successfully");
+    Ok(())
+}
+
+#[derive(Debug, Error)]
+pub enum PlayWithOSDError {
+    #[error("invalid video file path: {0}")]
+    InvalidVideoFilePath(PathBuf),
+    #[error("OSD file not found: {0}")]
+    OSDVideoFileNotFound(PathBuf),
+    #[error(transparent)]
+    VideoProbingError(#[from] VideoProbingError),
+    #[error("can only use OSD video files encoded with VP8, VP9 or AV1")]
+    UnsupportedOSDVideoCodec,
+    #[error("failed to start MPV: {0}, make sure MPV is installed or specify its path with --mpv-path")]
+    FailedToStartMPV(IOError),
+    #[error("failed to write MPV OSD control script: {0}")]
+    FailedWritingMPVScript(IOError),
+    #[error("MPV exited with an error: {0}")]
+    MPVExitedWithAnError(ExitStatus),
+    #[error(transparent)]
+    RequestedOSDButNoFileProvidedNorFound(#[from] RequestedOSDButNoFileProvidedNorFound),
+    #[error(transparent)]
+    OSDFontDirError(#[from] OSDFontDirError),
+    #[error(transparent)]
+    OSDFileOpenError(#[from] OSDFileOpenError),
+    #[error("OSD file read error: {0}")]
+    OSDFileReadError(#[from] OSDFileReadError),
+    #[error(transparent)]
+    ScalingArgsError(#[from] ScalingArgsError),
+    #[error(transparent)]
+    DrawFrameOverlayError(#[from] DrawFrameOverlayError),
+    #[error(transparent)]
+    FailedSpawningFFMpegProcess(#[from] ffmpeg::SpawnError),
+}
+
+/// Returns the path of the OSD overlay video associated to a video file, the default output path of `generate-overlay-video`
+/// for this video: <video file stem>_osd.webm with the default output template
+pub fn default_osd_video_file_path<P: AsRef<Path>>(video_file: P) -> Option<PathBuf> {
+    output_path::default_output_path(video_file, "osd", Some(OsStr::new("webm")))
+}
+
+// MPV command playing the video with the OSD track overlaid and the OSD control script loaded
+fn mpv_osd_command(args: &PlayWithOSDArgs, osd_track: &OsStr, osd_video_filter: Option<&str>, control_script: &mpv::TempScriptFile) -> ProcessCommand {
+    let mut external_file_arg = OsString::from("--external-file=");
+    external_file_arg.push(osd_track);
+    let mut script_arg = OsString::from("--script=");
+    script_arg.push(control_script.path());
+
+    let mut mpv_command = ProcessCommand::new(mpv::binary_path());
+    mpv_command
+        .arg(external_file_arg)
+        .arg(script_arg)
+        .arg(format!("--lavfi-complex={}", mpv::osd_overlay_filter_graph(osd_video_filter, args.osd_opacity())));
+    if let Some(ipc_server_path) = args.mpv_ipc_server() {
+        let mut ipc_server_arg = OsString::from("--input-ipc-server=");
+        ipc_server_arg.push(ipc_server_path);
+        mpv_command.arg(ipc_server_arg);
+    }
+    mpv_command.arg(args.video_file());
+    mpv_command
+}
+
+fn create_mpv_osd_control_script(osd_video_filter: Option<&str>, opacity_percent: u8) -> Result<mpv::TempScriptFile, PlayWithOSDError> {
+    mpv::TempScriptFile::create(&mpv::osd_control_script(osd_video_filter, opacity_percent)).map_err(PlayWithOSDError::FailedWritingMPVScript)
+}
+
+/// Plays a video with an OSD overlay video using MPV
+///
+/// The OSD can be toggled and its opacity changed while playing, see [`mpv::osd_control_script`].
+pub fn play_with_osd(args: &PlayWithOSDArgs) -> Result<(), PlayWithOSDError> {
+
+    let video_file = args.video_file();
+
+    let osd_video_file = match args.osd_video_file() {
+        Some(osd_video_file) => osd_video_file.clone(),
+        None => {
+            let osd_video_file = default_osd_video_file_path(video_file)
+                .ok_or_else(|| PlayWithOSDError::InvalidVideoFilePath(video_file.to_path_buf()))?;
+            if ! osd_video_file.exists() { return Err(PlayWithOSDError::OSDVideoFileNotFound(osd_video_file)); }
+            osd_video_file
+        },
+    };
+
+    let probe_result = probe(&osd_video_file)?;
+    let osd_video_decoding = probe_result.video_codec().as_deref().and_then(osd_video::Decoding::from_codec)
+        .ok_or(PlayWithOSDError::UnsupportedOSDVideoCodec)?;
+    let (decode_lib, osd_video_filter) = match osd_video_decoding {
+        osd_video::Decoding::Decoder(decoder) => (Some(decoder), None),
+        osd_video::Decoding::StackedAlpha => (None, Some(osd::overlay::AV1_STACKED_ALPHA_MERGE_FILTER)),
+    };
+
+    let control_script = create_mpv_osd_control_script(osd_video_filter, args.osd_opacity())?;
+    let mut mpv_command = mpv_osd_command(args, osd_video_file.as_os_str(), osd_video_filter, &control_script);
+
+    if let Some(decode_lib) = decode_lib {
+        mpv_command.arg(format!("--vd={decode_lib}"));
+    }
+
+    if crate::process::is_dry_run() {
+        println!("{mpv_command}");
+        return Ok(());
+    }
+
+    let mut mpv_child_proc = mpv_command.spawn().map_err(PlayWithOSDError::FailedToStartMPV)?;
+    let _mpv_child_guard = crate::cancellation::track_child(mpv_child_proc.id());
+
+    match mpv_child_proc.wait().unwrap() {
+        exit_result if ! exit_result.success() => Err(PlayWithOSDError::MPVExitedWithAnError(exit_result)),
+        _ => Ok(())
+    }
+}
+
+/// Plays a video with the OSD rendered live from the OSD file instead of an OSD overlay video
+///
+/// The OSD frames are sent to MPV as a raw video stream through FFMpeg, they are rendered at the playback pace
+/// since writing to the stream blocks until MPV needs more frames. Seeking is not supported since the stream can
+/// only be read forward.
+pub async fn play_with_live_osd<P: AsRef<Path>>(args: &PlayWithOSDArgs, osd_file_path: P) -> Result<(), PlayWithOSDError> {
+    let video_file = args.video_file();
+    let video_info = probe(video_file)?;
+    let mut osd_file = osd::file::open(osd_file_path)?;
+    let osd_frame_shift = osd_frame_shift_for_video(args.osd_frame_shift(), &video_info, &mut osd_file)?;
+
+    let osd_scaling = Scaling::try_from_osd_args(args.osd_scaling_args(), video_info.resolution())?;
+    let osd_font_dir = args.osd_font_options().osd_font_dir_with_fallback()?;
+    let osd_frames_generator = OverlayGenerator::new(
+        osd_file.frames()?,
+        args.osd_font_options().osd_font_variant(osd_file.font_variant()),
+        &osd_font_dir,
+        &args.osd_font_options().osd_font_ident(),
+        osd_scaling,
+        &[],
+        false,
+        &[]
+    )?;
+
+    // raw frames are muxed into NUT so that MPV gets the frame dimensions and timestamps with the frames
+    let mut ffmpeg_command = ffmpeg::CommandBuilder::default();
+    ffmpeg_command
+        .add_stdin_input(osd_frames_generator.frame_dimensions(), osd::OSD_FRAME_RATE).unwrap()
+        .set_output_video_codec(Some("rawvideo"))
+        .add_args(&["-f", "nut"])
+        .set_output_file("pipe:1");
+    let ffmpeg_command = ffmpeg_command.build().unwrap();
+
+    let control_script = create_mpv_osd_control_script(None, args.osd_opacity())?;
+    let mut mpv_command = mpv_osd_command(args, OsStr::new("-"), None, &control_script);
+
+    if crate::process::is_dry_run() {
+        println!("{ffmpeg_command} | {mpv_command}");
+        return Ok(());
+    }
+
+    let mut ffmpeg_process = ffmpeg_command.spawn_piped_output()?;
+    let osd_stream = ffmpeg_process.take_stdout().unwrap();
+    mpv_command.stdin(std::process::Stdio::from(osd_stream));
+    let mut mpv_child_proc = mpv_command.spawn().map_err(PlayWithOSDError::FailedToStartMPV)?;
+    let _mpv_child_guard = crate::cancellation::track_child(mpv_child_proc.id());
+
+    let frame_rate = video_info.frame_rate();
+    let last_osd_frame = (video_info.frame_count() * osd::OSD_FRAME_RATE as u64 * frame_rate.denominator() as u64 / frame_rate.numerator() as u64) as FrameIndex;
+    let mut osd_frames_iter = osd_frames_generator.iter_advanced(0, Some(last_osd_frame), osd_frame_shift.nearest_frames());
+    let send_result = osd_frames_iter.send_frames_to_ffmpeg(&mut ffmpeg_process);
+
+    let mpv_exit_status = mpv_child_proc.wait().unwrap();
+    // quitting MPV before the end of the video closes the OSD stream which makes FFMpeg and the frame sending fail
+    if let Err(error) = send_result { log::debug!("OSD stream closed: {error}") }
+    if let Err(error) = ffmpeg_process.wait().await { log::debug!("OSD stream FFMpeg process: {error}") }
+
+    if ! mpv_exit_status.success() { return Err(PlayWithOSDError::MPVExitedWithAnError(mpv_exit_status)) }
+    Ok(())
+}
\ No newline at end of file
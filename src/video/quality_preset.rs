@@ -0,0 +1,90 @@
+use getset::{CopyGetters, Getters};
+
+use crate::ffmpeg::bitrate::Bitrate;
+
+#[cfg(feature = "hwaccel")]
+use super::hw_accel::EncoderBackend;
+
+
+/// Named quality/speed trade-offs so that the quality scale and speed options of each encoder do not need to be known
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::Display)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[strum(serialize_all = "lowercase")]
+pub enum QualityPreset {
+    /// fast encoding, bigger files or lower quality
+    Fast,
+    /// balance between encoding speed, quality and file size
+    Medium,
+    /// high quality, slow encoding
+    Quality,
+}
+
+/// Encoder settings selected by a quality preset
+#[derive(Debug, Clone, CopyGetters, Getters)]
+pub struct PresetSettings {
+    /// value of the constant quality setting: CRF for the software encoders, quality level for the hardware encoders
+    #[getset(get_copy = "pub")]
+    crf: u8,
+
+    /// max bitrate
+    #[getset(get_copy = "pub")]
+    bitrate: Bitrate,
+
+    /// encoder specific arguments setting its speed, e.g. `-preset` or `-cpu-used`
+    #[getset(get = "pub")]
+    speed_args: Vec<&'static str>,
+}
+
+impl PresetSettings {
+    fn new(crf: u8, bitrate: Bitrate, speed_args: &[&'static str]) -> Self {
+        Self { crf, bitrate, speed_args: speed_args.to_vec() }
+    }
+}
+
+impl QualityPreset {
+
+    // picks the value matching the preset
+    fn pick<T>(&self, fast: T, medium: T, quality: T) -> T {
+        match self {
+            Self::Fast => fast,
+            Self::Medium => medium,
+            Self::Quality => quality,
+        }
+    }
+
+    fn bitrate(&self) -> Bitrate {
+        self.pick(Bitrate::from_mbps(15), Bitrate::from_mbps(25), Bitrate::from_mbps(40))
+    }
+
+    /// Settings for the specified FFMpeg encoder, `None` if the encoder is not known
+    pub fn encoder_settings(&self, encoder_name: &str) -> Option<PresetSettings> {
+        let settings = match encoder_name {
+            "libx264" => PresetSettings::new(self.pick(26, 22, 18), self.bitrate(), &["-preset", self.pick("veryfast", "medium", "slow")]),
+            "libx265" => PresetSettings::new(self.pick(30, 25, 21), self.bitrate(), &["-preset", self.pick("veryfast", "medium", "slow")]),
+            "libsvtav1" => PresetSettings::new(self.pick(38, 32, 26), self.bitrate(), &["-preset", self.pick("10", "8", "5")]),
+            "libaom-av1" => PresetSettings::new(self.pick(38, 32, 26), self.bitrate(), &["-cpu-used", self.pick("8", "6", "4")]),
+            "libvpx-vp9" => PresetSettings::new(self.pick(38, 32, 26), self.bitrate(), &["-deadline", "good", "-cpu-used", self.pick("5", "2", "1")]),
+            "libvpx" => PresetSettings::new(self.pick(45, 40, 30), self.bitrate(), &["-deadline", "good", "-cpu-used", self.pick("5", "2", "0")]),
+            #[cfg(feature = "hwaccel")]
+            _ => {
+                let (_codec, backend) = encoder_name.rsplit_once('_')?;
+                let backend = EncoderBackend::DETECTION_ORDER.into_iter().find(|known_backend| known_backend.to_string() == backend)?;
+                return Some(self.hardware_encoder_settings(backend));
+            },
+            #[cfg(not(feature = "hwaccel"))]
+            _ => return None,
+        };
+        Some(settings)
+    }
+
+    #[cfg(feature = "hwaccel")]
+    fn hardware_encoder_settings(&self, backend: EncoderBackend) -> PresetSettings {
+        let quality = self.pick(28, 24, 20);
+        match backend {
+            EncoderBackend::Nvenc => PresetSettings::new(quality, self.bitrate(), &["-preset", self.pick("p2", "p4", "p7")]),
+            EncoderBackend::Qsv => PresetSettings::new(quality, self.bitrate(), &["-preset", self.pick("veryfast", "medium", "veryslow")]),
+            EncoderBackend::Vaapi => PresetSettings::new(quality, self.bitrate(), &[]),
+        }
+    }
+
+}
@@ -6,6 +6,8 @@ use getset::Getters;
 use crate::prelude::*;
 use thiserror::Error;
 
+use crate::osd::region::{InvalidTimeRange, TimeRange};
+
 
 
 
@@ -14,18 +16,26 @@ use thiserror::Error;
 pub struct Region {
     top_left_corner: super::SignedCoordinates,
     dimensions: super::Dimensions,
+    /// the region is only processed during this interval when specified
+    time_range: Option<TimeRange>,
 }
 
 impl Region {
 
     pub fn new(top_left_corner: super::SignedCoordinates, dimensions: super::Dimensions) -> Self {
-        Self { top_left_corner, dimensions }
+        Self { top_left_corner, dimensions, time_range: None }
+    }
+
+    pub fn with_time_range(mut self, time_range: Option<TimeRange>) -> Self {
+        self.time_range = time_range;
+        self
     }
 
     pub fn new4(x: super::SignedCoordinate, y: super::SignedCoordinate, width: super::Dimension, height: super::Dimension) -> Self {
         Self {
             top_left_corner: super::SignedCoordinates::new(x, y),
-            dimensions: super::Dimensions::new(width, height)
+            dimensions: super::Dimensions::new(width, height),
+            time_range: None,
         }
     }
 
@@ -60,14 +70,21 @@ pub enum InvalidRegionString {
     #[error(transparent)]
     FormatError(FormatError),
     #[error("invalid dimensions: {0}: dimension component cannot be 0")]
-    InvalidDimensionValue(String)
+    InvalidDimensionValue(String),
+    #[error(transparent)]
+    InvalidTimeRange(InvalidTimeRange),
 }
 
 impl FromStr for Region {
     type Err = InvalidRegionString;
 
+    /// Parses `[<time range>@]<left_x>,<top_y>[:<width>x<height>]`
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(match s.split_once(':') {
+        let (time_range, s) = match s.split_once('@') {
+            Some((time_range_s, region_s)) => (Some(TimeRange::from_str(time_range_s)?), region_s),
+            None => (None, s),
+        };
+        let region = match s.split_once(':') {
 
             Some((origin_s, dimensions_s)) => {
                 let origin = super::Coordinates::from_str(origin_s)
@@ -77,21 +94,16 @@ impl FromStr for Region {
                 if dimensions.width == 0 || dimensions.height == 0 {
                     return Err(InvalidRegionString::InvalidDimensionValue(dimensions_s.to_owned()));
                 }
-                Region {
-                    top_left_corner: super::SignedCoordinates::from(origin),
-                    dimensions
-                }
+                Region::new(super::SignedCoordinates::from(origin), dimensions)
             },
 
             None => {
                 let origin = super::Coordinates::from_str(s)
                     .map_err(|error| FormatError::Origin { value: s.to_owned(), error })?;
-                Region {
-                    top_left_corner: super::SignedCoordinates::from(origin),
-                    dimensions: super::Dimensions::new(1, 1),
-                }
+                Region::new(super::SignedCoordinates::from(origin), super::Dimensions::new(1, 1))
             },
 
-        })
+        };
+        Ok(region.with_time_range(time_range))
     }
 }
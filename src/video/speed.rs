@@ -0,0 +1,72 @@
+use ffmpeg_next::Rational;
+
+use super::FrameIndex;
+
+
+// tempo range accepted by a single atempo filter with all FFMpeg versions
+const MIN_ATEMPO_FACTOR: f64 = 0.5;
+const MAX_ATEMPO_FACTOR: f64 = 2.0;
+
+/// Playback speed change applied to a video
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Speed {
+    /// speed multiplied by the factor, the frame rate is kept by dropping or duplicating frames
+    Factor(f64),
+    /// only every Nth frame is kept, the audio is dropped
+    Timelapse(u32),
+}
+
+impl Speed {
+
+    pub fn factor(&self) -> f64 {
+        match self {
+            Self::Factor(factor) => *factor,
+            Self::Timelapse(frame_interval) => *frame_interval as f64,
+        }
+    }
+
+    pub fn keeps_audio(&self) -> bool {
+        matches!(self, Self::Factor(_))
+    }
+
+    /// FFMpeg filters changing the speed of a video with the specified frame rate, the output keeps the same frame rate
+    pub fn video_filter_parts(&self, frame_rate: Rational) -> Vec<String> {
+        match self {
+            Self::Factor(factor) => vec![
+                format!("setpts=PTS/{factor}"),
+                format!("fps={}/{}", frame_rate.numerator(), frame_rate.denominator()),
+            ],
+            Self::Timelapse(frame_interval) => vec![
+                format!("select=not(mod(n\\,{frame_interval}))"),
+                "setpts=N/FRAME_RATE/TB".to_owned(),
+            ],
+        }
+    }
+
+    /// FFMpeg filters changing the tempo of the audio, the atempo filter is chained when the factor is out of its range
+    pub fn audio_filter_parts(&self) -> Vec<String> {
+        let Self::Factor(mut factor) = *self else { return vec![] };
+        let mut parts = vec![];
+        while factor > MAX_ATEMPO_FACTOR {
+            parts.push(format!("atempo={MAX_ATEMPO_FACTOR}"));
+            factor /= MAX_ATEMPO_FACTOR;
+        }
+        while factor < MIN_ATEMPO_FACTOR {
+            parts.push(format!("atempo={MIN_ATEMPO_FACTOR}"));
+            factor /= MIN_ATEMPO_FACTOR;
+        }
+        parts.push(format!("atempo={factor}"));
+        parts
+    }
+
+    /// Index in the output video of the frame at the specified index of the input video
+    pub fn output_frame_index(&self, frame_index: FrameIndex) -> FrameIndex {
+        (frame_index as f64 / self.factor()).round() as FrameIndex
+    }
+
+    /// Number of frames of the output video for the specified number of input video frames
+    pub fn output_frame_count(&self, frame_count: u64) -> u64 {
+        (frame_count as f64 / self.factor()).ceil() as u64
+    }
+
+}
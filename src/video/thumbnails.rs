@@ -0,0 +1,155 @@
+use std::{io::{Error as IOError, Write}, path::{Path, PathBuf}, sync::Arc};
+
+use itertools::Itertools;
+use thiserror::Error;
+
+use crate::{
+    cli::{font_options::OSDFontDirError, generate_thumbnails_args::GenerateThumbnailsArgs, transcode_video_args::RequestedOSDButNoFileProvidedNorFound},
+    ffmpeg,
+    osd::{
+        self,
+        file::{GenericReader, ReadError as OSDFileReadError, OpenError as OSDFileOpenError},
+        overlay::{
+            DrawFrameOverlayError, Frame, Generator as OverlayGenerator,
+            scaling::{Scaling, ScalingArgsError},
+            video_timeline::{OSDVideoOffset, VideoTimeline},
+        },
+        tile_indices::UnknownOSDItem,
+    },
+};
+
+use super::{probe, probe::Error as VideoProbingError, FrameIndex};
+
+
+#[derive(Debug, Error)]
+pub enum GenerateThumbnailsError {
+    #[error("input video file does not exist: {0}")]
+    InputVideoFileDoesNotExist(PathBuf),
+    #[error("invalid video file path: {0}")]
+    InvalidVideoFilePath(PathBuf),
+    #[error("output file exists: {0}")]
+    OutputFileExists(PathBuf),
+    #[error("the grid must have at least one column and one row")]
+    EmptyGrid,
+    #[error("the video has fewer frames ({frame_count}) than thumbnails to generate ({thumbnail_count})")]
+    NotEnoughFrames {
+        frame_count: u64,
+        thumbnail_count: u64,
+    },
+    #[error("failed to get video details: {0}")]
+    FailedToGetVideoDetails(#[from] VideoProbingError),
+    #[error(transparent)]
+    RequestedOSDButNoFileProvidedNorFound(#[from] RequestedOSDButNoFileProvidedNorFound),
+    #[error(transparent)]
+    OSDFontDirError(#[from] OSDFontDirError),
+    #[error(transparent)]
+    OSDFileOpenError(#[from] OSDFileOpenError),
+    #[error("OSD file read error: {0}")]
+    OSDFileReadError(#[from] OSDFileReadError),
+    #[error(transparent)]
+    ScalingArgsError(#[from] ScalingArgsError),
+    #[error(transparent)]
+    DrawFrameOverlayError(#[from] DrawFrameOverlayError),
+    #[error(transparent)]
+    UnknownOSDItem(#[from] UnknownOSDItem),
+    #[error(transparent)]
+    FailedSpawningFFMpegProcess(#[from] ffmpeg::SpawnError),
+    #[error(transparent)]
+    FFMpegExitedWithError(#[from] ffmpeg::ProcessError),
+    #[error("failed sending OSD frames to ffmpeg process: {0}")]
+    FailedSendingOSDFramesToFFMpeg(#[from] IOError),
+}
+
+// the thumbnails are taken in the middle of equal intervals so that the first and last ones are not black frames
+fn thumbnail_frame_indices(frame_count: u64, thumbnail_count: u64) -> Vec<FrameIndex> {
+    (0..thumbnail_count).map(|index| ((2 * index + 1) * frame_count / (2 * thumbnail_count)) as FrameIndex).collect()
+}
+
+// renders the OSD overlay frames displayed on the specified video frames
+fn render_osd_frames<P: AsRef<Path>>(args: &GenerateThumbnailsArgs, osd_file_path: P, video_info: &probe::Result,
+                                      frame_indices: &[FrameIndex]) -> Result<(osd::overlay::Dimensions, Vec<Arc<Frame>>), GenerateThumbnailsError> {
+    let mut osd_file = osd::file::open(osd_file_path)?;
+    let osd_frame_shift = super::osd_frame_shift_for_video(args.osd_frame_shift(), video_info, &mut osd_file)?;
+    let osd_scaling = Scaling::try_from_osd_args(args.osd_scaling_args(), video_info.resolution())?;
+    let osd_font_dir = args.osd_font_options().osd_font_dir_with_fallback()?;
+    let osd_frames_generator = OverlayGenerator::new(
+        osd_file.frames()?,
+        args.osd_font_options().osd_font_variant(osd_file.font_variant()),
+        &osd_font_dir,
+        &args.osd_font_options().osd_font_ident(),
+        osd_scaling,
+        &[],
+        false,
+        &[]
+    )?;
+
+    let frame_rate = video_info.frame_rate();
+    let timeline = VideoTimeline::new(frame_rate.numerator(), frame_rate.denominator(), OSDVideoOffset::from_osd_frame_shift(osd_frame_shift));
+    let last_frame_index = frame_indices.last().copied().unwrap_or_default();
+    let mut frames_iter = osd_frames_generator.iter_for_video_timeline(0, last_frame_index, timeline);
+    let mut frames = Vec::with_capacity(frame_indices.len());
+    for video_frame_index in 0..=last_frame_index {
+        let Some(frame) = frames_iter.next_shared() else { break };
+        let frame = frame?;
+        if frame_indices.contains(&video_frame_index) { frames.push(frame) }
+    }
+    Ok((osd_frames_generator.frame_dimensions(), frames))
+}
+
+/// Generates a contact sheet image made of thumbnails of frames evenly spread over the video, optionally with the OSD burned on them
+pub async fn generate_thumbnails(args: &GenerateThumbnailsArgs) -> Result<(), GenerateThumbnailsError> {
+    let video_file = args.video_file();
+    if ! video_file.exists() { return Err(GenerateThumbnailsError::InputVideoFileDoesNotExist(video_file.clone())) }
+    let output_file = args.output_file().ok_or_else(|| GenerateThumbnailsError::InvalidVideoFilePath(video_file.clone()))?;
+    if ! args.overwrite() && output_file.exists() { return Err(GenerateThumbnailsError::OutputFileExists(output_file)) }
+    let osd_file_path = args.osd_file_path()?;
+
+    let grid = args.grid();
+    let thumbnail_count = grid.width as u64 * grid.height as u64;
+    if thumbnail_count == 0 { return Err(GenerateThumbnailsError::EmptyGrid) }
+    let video_info = probe(video_file)?;
+    let frame_count = video_info.frame_count();
+    if frame_count < thumbnail_count { return Err(GenerateThumbnailsError::NotEnoughFrames { frame_count, thumbnail_count }) }
+    let frame_indices = thumbnail_frame_indices(frame_count, thumbnail_count);
+
+    log::info!("generating {}x{} thumbnails contact sheet: {} -> {}", grid.width, grid.height, video_file.to_string_lossy(), output_file.to_string_lossy());
+
+    let select_filter = format!("select='{}'", frame_indices.iter().map(|index| format!("eq(n,{index})")).join("+"));
+    let sheet_filters = format!("scale={}:-2,tile={}x{}", args.thumbnail_width(), grid.width, grid.height);
+
+    let mut ffmpeg_command = ffmpeg::CommandBuilder::default();
+    ffmpeg_command.add_input_file(video_file);
+
+    let osd_frames = match osd_file_path {
+        Some(osd_file_path) => {
+            let (osd_frame_dimensions, osd_frames) = render_osd_frames(args, osd_file_path, &video_info, &frame_indices)?;
+            ffmpeg_command.add_stdin_input(osd_frame_dimensions, 1).unwrap();
+            // both streams are renumbered so that each selected video frame is paired with its OSD frame
+            ffmpeg_command.add_complex_filter(&format!(
+                "[0:v]{select_filter},setpts=N/TB[video];[1:v]setpts=N/TB[osd];[video][osd]overlay=(W-w)/2:(H-h)/2,{sheet_filters}[vo]"
+            ));
+            osd_frames
+        },
+        None => {
+            ffmpeg_command.add_complex_filter(&format!("[0:v]{select_filter},{sheet_filters}[vo]"));
+            vec![]
+        },
+    };
+
+    ffmpeg_command
+        .add_mapping("[vo]")
+        .add_args(&["-frames:v", "1", "-update", "1"])
+        .set_output_file(&output_file)
+        .set_overwrite_output_file(true);
+
+    let mut ffmpeg_process = ffmpeg_command.build().unwrap().spawn_no_output()?;
+    let send_result = match ffmpeg_process.take_stdin() {
+        Some(mut ffmpeg_stdin) => osd_frames.iter().try_for_each(|osd_frame| ffmpeg_stdin.write_all(osd_frame.as_raw())),
+        None => Ok(()),
+    };
+    ffmpeg_process.wait().await?;
+    send_result?;
+
+    log::info!("contact sheet written: {}", output_file.to_string_lossy());
+    Ok(())
+}
@@ -2,6 +2,7 @@
 use std::{fmt::Display, str::FromStr};
 
 use derive_more::Constructor;
+#[cfg(not(target_arch = "wasm32"))]
 use ffmpeg_next::Rational;
 use getset::{CopyGetters, Setters};
 use regex::Regex;
@@ -19,6 +20,10 @@ pub struct Timestamp {
 
 impl Timestamp {
 
+    pub fn from_total_seconds(total_seconds: u32) -> Self {
+        Self::new((total_seconds / 3600) as u16, (total_seconds / 60 % 60) as u8, (total_seconds % 60) as u8)
+    }
+
     pub fn total_seconds(&self) -> u32 {
         self.hours as u32 * 3600 + self.minutes as u32 * 60 + self.seconds as u32
     }
@@ -27,13 +32,14 @@ impl Timestamp {
         format!("{}:{}:{}", self.hours, self.minutes, self.seconds)
     }
 
+    #[cfg(not(target_arch = "wasm32"))]
     pub fn frame_count(&self, fps: Rational) -> u64 {
         let frame_exact = fps * ffmpeg_next::Rational::new(self.total_seconds() as i32, 1);
         (frame_exact.numerator() as f64 / frame_exact.denominator() as f64).round() as u64
     }
 
     pub fn overlay_frame_count(&self) -> u32 {
-        u32::try_from(self.frame_count(Rational::from((60, 1)))).unwrap()
+        u32::try_from(self.total_seconds() as u64 * crate::osd::OSD_FRAME_RATE as u64).unwrap()
     }
 
     pub fn overlay_frame_index(&self) -> u32 {
@@ -44,6 +50,7 @@ impl Timestamp {
         frame_count - 1
     }
 
+    #[cfg(not(target_arch = "wasm32"))]
     pub fn interval_frames(start_timestamp: &Self, end_timestamp: &Self, fps: Rational) -> u64 {
         let interval_seconds = end_timestamp.total_seconds() as i32 - start_timestamp.total_seconds() as i32;
         if interval_seconds < 0 { return 0 }
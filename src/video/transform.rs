@@ -0,0 +1,152 @@
+use std::{fmt::Display, str::FromStr};
+
+use clap::ValueEnum;
+use thiserror::Error;
+
+use super::Resolution;
+
+
+/// Clockwise rotation applied to a video
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Rotation {
+    #[value(name = "90")]
+    Quarter,
+    #[value(name = "180")]
+    Half,
+    #[value(name = "270")]
+    ThreeQuarters,
+}
+
+impl Rotation {
+
+    /// FFMpeg filters rotating the video
+    pub fn ffmpeg_filter_parts(&self) -> Vec<String> {
+        let parts: &[&str] = match self {
+            Self::Quarter => &["transpose=clock"],
+            Self::Half => &["hflip", "vflip"],
+            Self::ThreeQuarters => &["transpose=cclock"],
+        };
+        parts.iter().map(|part| part.to_string()).collect()
+    }
+
+    /// Resolution of a video of the specified resolution once rotated
+    pub fn rotated_resolution(&self, resolution: Resolution) -> Resolution {
+        match self {
+            Self::Quarter | Self::ThreeQuarters => Resolution::new(resolution.height, resolution.width),
+            Self::Half => resolution,
+        }
+    }
+
+}
+
+/// Rectangular area of the video to keep, centered in the picture when its origin is not specified
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crop {
+    origin: Option<(u32, u32)>,
+    dimensions: Resolution,
+}
+
+#[derive(Debug, Error)]
+#[error("crop area {crop} does not fit in the {}x{} video", resolution.width, resolution.height)]
+pub struct CropError {
+    crop: Crop,
+    resolution: Resolution,
+}
+
+impl Crop {
+
+    pub fn centered(dimensions: Resolution) -> Self {
+        Self { origin: None, dimensions }
+    }
+
+    pub fn dimensions(&self) -> Resolution {
+        self.dimensions
+    }
+
+    /// Position of the top left corner of the cropped area in a video of the specified resolution
+    pub fn origin_in(&self, resolution: Resolution) -> Result<(u32, u32), CropError> {
+        let out_of_bounds = || CropError { crop: *self, resolution };
+        if self.dimensions.width > resolution.width || self.dimensions.height > resolution.height { return Err(out_of_bounds()) }
+        let (x, y) = self.origin.unwrap_or((
+            (resolution.width - self.dimensions.width) / 2,
+            (resolution.height - self.dimensions.height) / 2,
+        ));
+        if x + self.dimensions.width > resolution.width || y + self.dimensions.height > resolution.height { return Err(out_of_bounds()) }
+        Ok((x, y))
+    }
+
+    /// FFMpeg filter cropping a video of the specified resolution
+    pub fn ffmpeg_filter_string(&self, resolution: Resolution) -> Result<String, CropError> {
+        let (x, y) = self.origin_in(resolution)?;
+        Ok(format!("crop={}:{}:{x}:{y}", self.dimensions.width, self.dimensions.height))
+    }
+
+}
+
+impl Display for Crop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some((x, y)) = self.origin { write!(f, "{x},{y}:")?; }
+        write!(f, "{}x{}", self.dimensions.width, self.dimensions.height)
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("invalid crop area `{0}`, the format is [<left_x>,<top_y>:]<width>x<height>")]
+pub struct InvalidCrop(String);
+
+fn parse_pair(value: &str, separator: char) -> Option<(u32, u32)> {
+    let (first, second) = value.split_once(separator)?;
+    Some((first.trim().parse().ok()?, second.trim().parse().ok()?))
+}
+
+impl FromStr for Crop {
+    type Err = InvalidCrop;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidCrop(s.to_owned());
+        let (origin, dimensions) = match s.split_once(':') {
+            Some((origin, dimensions)) => (Some(parse_pair(origin, ',').ok_or_else(invalid)?), dimensions),
+            None => (None, s),
+        };
+        let (width, height) = parse_pair(dimensions, 'x').ok_or_else(invalid)?;
+        if width == 0 || height == 0 { return Err(invalid()) }
+        Ok(Self { origin, dimensions: Resolution::new(width, height) })
+    }
+}
+
+/// Aspect ratio of the output video, e.g. 16:9 or 9:16 for vertical videos
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AspectRatio {
+    width: u32,
+    height: u32,
+}
+
+impl AspectRatio {
+
+    /// Largest centered area with this aspect ratio in a video of the specified resolution
+    ///
+    /// The dimensions are rounded down to even values as required by the YUV 4:2:0 pixel formats.
+    pub fn crop(&self, resolution: Resolution) -> Crop {
+        let (width, height) = match resolution.width as u64 * self.height as u64 > resolution.height as u64 * self.width as u64 {
+            true => ((resolution.height as u64 * self.width as u64 / self.height as u64) as u32, resolution.height),
+            false => (resolution.width, (resolution.width as u64 * self.height as u64 / self.width as u64) as u32),
+        };
+        Crop::centered(Resolution::new(width & !1, height & !1))
+    }
+
+}
+
+#[derive(Debug, Error)]
+#[error("invalid aspect ratio `{0}`, the format is <width>:<height>, e.g. 16:9")]
+pub struct InvalidAspectRatio(String);
+
+impl FromStr for AspectRatio {
+    type Err = InvalidAspectRatio;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match parse_pair(s, ':') {
+            Some((width, height)) if width > 0 && height > 0 => Ok(Self { width, height }),
+            _ => Err(InvalidAspectRatio(s.to_owned())),
+        }
+    }
+}
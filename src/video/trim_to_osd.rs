@@ -0,0 +1,71 @@
+// Trimming of a video to the part covered by its OSD recording
+//
+// The recording of the video often goes on after disarming while the OSD is not updated anymore. The start and end of the
+// video are set to the first and last frames of the OSD file, placed onto the video the same way as when burning the OSD.
+
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::{
+    cli::{start_end_args::StartEndArgs, transcode_video_args::RequestedOSDButNoFileProvidedNorFound},
+    osd::{self, file::{GenericReader, ReadError as OSDFileReadError, OpenError as OSDFileOpenError}, overlay::video_timeline::{OSDFrameShift, OSDVideoOffset, OSDSpeed, OSDTimebaseCorrection, VideoTimeline}},
+};
+
+use super::{probe, probe::Error as VideoProbingError, Timestamp};
+
+
+#[derive(Debug, Error)]
+pub enum TrimToOSDError {
+    #[error("failed to get video details: {0}")]
+    FailedToGetVideoDetails(#[from] VideoProbingError),
+    #[error(transparent)]
+    RequestedOSDButNoFileProvidedNorFound(#[from] RequestedOSDButNoFileProvidedNorFound),
+    #[error(transparent)]
+    OSDFileOpenError(#[from] OSDFileOpenError),
+    #[error("OSD file read error: {0}")]
+    OSDFileReadError(#[from] OSDFileReadError),
+    #[error("the OSD recording does not overlap the video: {0}")]
+    NoOverlap(PathBuf),
+}
+
+/// Start and end of the part of a video covered by the frames of an OSD file, the timestamps are rounded outwards to the second
+///
+/// The OSD file associated with the video is used when `osd_file_path` is not specified. The OSD is placed with the OSD
+/// video offset if specified, else with the OSD frame shift, detected the same way as when burning the OSD if not specified.
+/// The OSD timeline is then mapped onto the video with the OSD speed and time base correction, the correction of the
+/// Walksnail OSD files being estimated the same way as when burning the OSD if not specified and `estimate_osd_timebase_correction` is set.
+pub fn osd_start_end<P: AsRef<Path>>(video_file: P, osd_file_path: Option<&Path>, osd_frame_shift: Option<OSDFrameShift>,
+        osd_video_offset: Option<OSDVideoOffset>, osd_speed: Option<OSDSpeed>, osd_timebase_correction: Option<OSDTimebaseCorrection>,
+        estimate_osd_timebase_correction: bool) -> Result<StartEndArgs, TrimToOSDError> {
+    let video_file = video_file.as_ref();
+    let osd_file_path = match osd_file_path {
+        Some(osd_file_path) => osd_file_path.to_path_buf(),
+        None => osd::file::find_associated_to_video_file(video_file).ok_or(RequestedOSDButNoFileProvidedNorFound)?,
+    };
+    let video_info = probe(video_file)?;
+    let mut osd_file = osd::file::open(&osd_file_path)?;
+    let osd_video_offset = match osd_video_offset {
+        Some(osd_video_offset) => osd_video_offset,
+        None => OSDVideoOffset::from_osd_frame_shift(super::osd_frame_shift_for_video(osd_frame_shift, &video_info, &mut osd_file)?),
+    };
+    let osd_speed = osd_speed.unwrap_or(OSDSpeed::NORMAL);
+    let osd_timebase_correction = super::processing::osd_timebase_correction(osd_timebase_correction, estimate_osd_timebase_correction, osd_speed, &mut osd_file, &video_info,
+        osd_video_offset)?;
+    // the frame rate is not used to map the OSD times onto the video
+    let mut video_timeline = VideoTimeline::new(1, 1, osd_video_offset);
+    video_timeline.set_speed(osd_speed.factor()).set_osd_timebase_correction(osd_timebase_correction);
+
+    let frames = osd_file.frames()?;
+    let (Some(first_frame), Some(last_frame)) = (frames.first(), frames.last()) else { return Err(OSDFileReadError::NoFrames.into()) };
+    let start_secs = video_timeline.video_time(first_frame.index() as f64 / osd::OSD_TIME_BASE_HZ);
+    let end_secs = video_timeline.video_time((last_frame.index() + 1) as f64 / osd::OSD_TIME_BASE_HZ);
+    let video_duration_secs = video_info.duration();
+    if end_secs <= 0.0 || start_secs >= video_duration_secs { return Err(TrimToOSDError::NoOverlap(osd_file_path)) }
+
+    let start = (start_secs >= 1.0).then(|| Timestamp::from_total_seconds(start_secs.floor() as u32));
+    let end = (end_secs < video_duration_secs).then(|| Timestamp::from_total_seconds(end_secs.ceil() as u32));
+    log::info!("trimming the video to the OSD recording: {} -> {}", start.unwrap_or_default(),
+        end.map(|end| end.to_string()).unwrap_or_else(|| "end".to_owned()));
+    Ok(StartEndArgs::new(start, end))
+}
@@ -0,0 +1,143 @@
+use std::{io::Error as IOError, path::PathBuf};
+
+use itertools::Itertools;
+use thiserror::Error;
+
+use crate::{cancellation, cli::export_web_args::ExportWebArgs, create_path::{create_path, CreatePathError}, ffmpeg, file};
+
+use super::{probe, probe::Error as VideoProbingError, osd_video::{self, OSDVideoError}};
+
+
+const MASTER_PLAYLIST_FILE_NAME: &str = "master.m3u8";
+
+#[derive(Debug, Error)]
+pub enum ExportWebError {
+    #[error("input video file does not exist: {0}")]
+    InputVideoFileDoesNotExist(PathBuf),
+    #[error("invalid video file path: {0}")]
+    InvalidVideoFilePath(PathBuf),
+    #[error("output playlist exists: {0}")]
+    OutputPlaylistExists(PathBuf),
+    #[error(transparent)]
+    OSDVideo(#[from] OSDVideoError),
+    #[error("failed to get video details: {0}")]
+    FailedToGetVideoDetails(#[from] VideoProbingError),
+    #[error(transparent)]
+    CreatePathError(#[from] CreatePathError),
+    #[error(transparent)]
+    FailedSpawningFFMpegProcess(#[from] ffmpeg::SpawnError),
+    #[error(transparent)]
+    FFMpegExitedWithError(#[from] ffmpeg::ProcessError),
+    #[error("failed renaming the temporary master playlist to its final path: {0}")]
+    FailedRenamingMasterPlaylist(IOError),
+}
+
+// bitrate cap of a rendition in kbit/s, in line with the usual streaming platform recommendations for H.264
+fn rendition_max_bitrate_kbps(height: u32) -> u32 {
+    match height {
+        2160.. => 20000,
+        1440.. => 12000,
+        1080.. => 6000,
+        720.. => 3000,
+        480.. => 1500,
+        _ => 800,
+    }
+}
+
+// heights of the renditions from the highest to the lowest, upscaled renditions are skipped
+fn rendition_heights(requested_heights: &[u32], video_height: u32) -> Vec<u32> {
+    let heights = requested_heights.iter().copied()
+        .filter(|height| *height <= video_height)
+        .sorted_unstable_by(|a, b| b.cmp(a))
+        .dedup()
+        .collect::<Vec<_>>();
+    if heights.is_empty() { vec![video_height] } else { heights }
+}
+
+/// Transcodes a video into an HLS ladder for self-hosted streaming: one H.264/AAC rendition per requested height
+/// plus a master playlist referencing them
+///
+/// The renditions are written in one sub-directory each named after their height, e.g. `720p`, all of them
+/// being encoded in a single FFMpeg pass. The OSD overlay video can be overlaid onto the video at the same time.
+pub async fn export_web(args: &ExportWebArgs) -> Result<(), ExportWebError> {
+    let input_video_file = args.input_video_file();
+    if ! input_video_file.exists() { return Err(ExportWebError::InputVideoFileDoesNotExist(input_video_file.clone())) }
+    let output_dir = args.output_dir().ok_or_else(|| ExportWebError::InvalidVideoFilePath(input_video_file.clone()))?;
+    let master_playlist_file = output_dir.join(MASTER_PLAYLIST_FILE_NAME);
+    if ! args.overwrite() && master_playlist_file.exists() { return Err(ExportWebError::OutputPlaylistExists(master_playlist_file)) }
+    let osd_video_file = osd_video::resolve_file(input_video_file, args.osd_video().as_ref(), args.osd())?;
+
+    let video_info = probe(input_video_file)?;
+    let heights = rendition_heights(args.renditions(), video_info.resolution().height);
+
+    log::info!("exporting video for the web: {} -> {} ({})", input_video_file.to_string_lossy(), output_dir.to_string_lossy(),
+        heights.iter().map(|height| format!("{height}p")).join(", "));
+
+    if ! crate::process::is_dry_run() { create_path(&output_dir)?; }
+
+    let mut ffmpeg_command = ffmpeg::CommandBuilder::default();
+    ffmpeg_command.add_input_file(input_video_file);
+
+    let mut filter_chains = vec![];
+    let source = match &osd_video_file {
+        Some(osd_video_file) => {
+            filter_chains.push(osd_video::add_input(&mut ffmpeg_command, osd_video_file, None, None, "osd")?);
+            "[0:v][osd]overlay=x=(W-w)/2:y=(H-h)/2:eof_action=pass,"
+        },
+        None => "[0:v]",
+    };
+    let split_labels = (0..heights.len()).map(|index| format!("[s{index}]")).join("");
+    filter_chains.push(format!("{source}split={}{split_labels}", heights.len()));
+    for (index, height) in heights.iter().enumerate() {
+        filter_chains.push(format!("[s{index}]scale=-2:{height},format=yuv420p[v{index}]"));
+    }
+    ffmpeg_command.add_complex_filter(&filter_chains.join(";"));
+
+    let mut stream_map = vec![];
+    for (index, height) in heights.iter().enumerate() {
+        let max_bitrate_kbps = rendition_max_bitrate_kbps(*height);
+        ffmpeg_command
+            .add_mapping(&format!("[v{index}]"))
+            .add_args(&[&format!("-maxrate:v:{index}"), &format!("{max_bitrate_kbps}k"), &format!("-bufsize:v:{index}"), &format!("{}k", 2 * max_bitrate_kbps)]);
+        match video_info.has_audio() {
+            true => {
+                ffmpeg_command.add_mapping("0:a:0");
+                stream_map.push(format!("v:{index},a:{index},name:{height}p"));
+            },
+            false => stream_map.push(format!("v:{index},name:{height}p")),
+        }
+    }
+
+    // the master playlist is written under a temporary name and renamed once all the renditions are complete so that a
+    // failed export does not leave a playlist referencing incomplete renditions
+    let temp_master_playlist_file = file::temp_output_path(&master_playlist_file);
+    let temp_master_playlist_file_name = temp_master_playlist_file.file_name().unwrap().to_string_lossy().to_string();
+
+    let segment_duration = args.segment_duration().to_string();
+    let segment_file_pattern = output_dir.join("%v").join("segment_%05d.ts");
+    ffmpeg_command
+        .set_output_video_settings(Some("libx264"), None, Some(args.video_crf()))
+        .set_output_audio_settings(Some("aac"), Some(args.audio_bitrate()))
+        // the segments of all the renditions need to start on a key frame at the same time for the players to switch between them
+        .add_args(&["-force_key_frames", &format!("expr:gte(t,n_forced*{segment_duration})"), "-sc_threshold", "0"])
+        .add_args(&["-f", "hls", "-hls_time", &segment_duration, "-hls_playlist_type", "vod"])
+        .add_args(&["-hls_segment_filename", &segment_file_pattern.to_string_lossy()])
+        .add_args(&["-master_pl_name", &temp_master_playlist_file_name, "-var_stream_map", &stream_map.join(" ")])
+        .set_output_file(output_dir.join("%v").join("index.m3u8"))
+        .set_overwrite_output_file(true);
+
+    let mut ffmpeg_process = ffmpeg_command.build().unwrap().spawn_with_progress(video_info.frame_count())?;
+    cancellation::track_output(&temp_master_playlist_file);
+    let wait_result = ffmpeg_process.wait().await;
+    cancellation::output_completed(&temp_master_playlist_file);
+    if let Err(error) = wait_result {
+        let _ = fs_err::remove_file(&temp_master_playlist_file);
+        return Err(error.into());
+    }
+    if ! crate::process::is_dry_run() {
+        fs_err::rename(&temp_master_playlist_file, &master_playlist_file).map_err(ExportWebError::FailedRenamingMasterPlaylist)?;
+    }
+
+    log::info!("HLS ladder written, master playlist: {}", master_playlist_file.to_string_lossy());
+    Ok(())
+}
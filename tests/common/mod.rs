@@ -0,0 +1,165 @@
+// Synthetic fixtures for the end-to-end tests: OSD files in the DJI and WSA byte formats, fonts and small test videos
+//
+// The fixtures are written into a temporary directory removed when the `Fixtures` value is dropped. The videos are
+// generated with the ffmpeg binary, the tests needing them are skipped when it is not available.
+
+#![allow(dead_code)]
+
+use std::{collections::BTreeMap, path::PathBuf, process::{Command, Stdio}, sync::atomic::{AtomicUsize, Ordering}};
+
+use hd_fpv_osd_font_tool::prelude::tile;
+use hd_fpv_video_tool::osd::{FontVariant, Kind, OSD_TIME_BASE_HZ, dji::file::font_variant_id, font_dir::PAGE_TILE_COUNT, tile_indices, wsa};
+
+
+// the WSA frame timestamps are in milliseconds
+const WSA_FRAME_DURATION_MS: f64 = 1000.0 / OSD_TIME_BASE_HZ;
+
+pub struct Fixtures {
+    dir: PathBuf,
+}
+
+impl Fixtures {
+
+    pub fn new(name: &str) -> Self {
+        let dir = temp_path(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        // the resized tiles of the synthetic fonts must not end up in the cache of the user
+        #[cfg(feature = "overlay")]
+        hd_fpv_video_tool::osd::tile_resize::set_cache_enabled(false);
+        Self { dir }
+    }
+
+    pub fn path(&self, file_name: &str) -> PathBuf {
+        self.dir.join(file_name)
+    }
+
+    /// Writes a DJI OSD file with a frame at each of the specified OSD frame indices displaying `tile_index` at the top left corner
+    pub fn dji_osd_file(&self, file_name: &str, kind: Kind, font_variant: FontVariant, frame_indices: &[u32], tile_index: u16) -> PathBuf {
+        let mut content = dji_header(kind, font_variant, 1);
+        for frame_index in frame_indices {
+            content.extend(dji_frame(*frame_index, &dji_frame_tile_indices(tile_index), &[]));
+        }
+        self.write(file_name, &content)
+    }
+
+    /// Writes a Walksnail Avatar OSD file with a frame at each of the specified OSD frame indices displaying `tile_index` at the top left corner
+    pub fn wsa_osd_file(&self, file_name: &str, font_variant_id: &[u8; 4], frame_indices: &[u32], tile_index: u16) -> PathBuf {
+        let mut tile_indices = vec![0; (wsa::DIMENSIONS.width * wsa::DIMENSIONS.height) as usize];
+        tile_indices[0] = tile_index;
+        let frames = frame_indices.iter().map(|frame_index| (*frame_index, tile_indices.clone())).collect();
+        self.write(file_name, &wsa_file_content(font_variant_id, false, 0, &frames))
+    }
+
+    /// Writes the generic SD and HD fonts with two pages, every glyph being an opaque white rectangle except the first one which is blank
+    pub fn font_dir(&self) -> PathBuf {
+        let font_dir = self.path("fonts");
+        std::fs::create_dir_all(&font_dir).unwrap();
+        for (tile_kind, tile_kind_part) in [(tile::Kind::SD, ""), (tile::Kind::HD, "_hd")] {
+            let dimensions = tile_kind.dimensions();
+            let tile_size = dimensions.width as usize * dimensions.height as usize * 4;
+            for page_part in ["", "_2"] {
+                let mut content = vec![0; tile_size];
+                content.extend(std::iter::repeat(0xff).take(tile_size * (PAGE_TILE_COUNT - 1)));
+                std::fs::write(font_dir.join(format!("font{tile_kind_part}{page_part}.bin")), content).unwrap();
+            }
+        }
+        font_dir
+    }
+
+    /// Generates a test pattern video with the ffmpeg binary, returns none if ffmpeg is not available
+    pub fn video(&self, file_name: &str, width: u32, height: u32, frame_rate: u32, duration_secs: f64) -> Option<PathBuf> {
+        let path = self.path(file_name);
+        let status = Command::new("ffmpeg")
+            .args(["-hide_banner", "-loglevel", "error", "-y", "-f", "lavfi", "-i"])
+            .arg(format!("testsrc2=size={width}x{height}:rate={frame_rate}"))
+            .args(["-t", &duration_secs.to_string(), "-c:v", "libx264", "-pix_fmt", "yuv420p"])
+            .arg(&path)
+            .stdout(Stdio::null()).stderr(Stdio::null())
+            .status();
+        match status {
+            Ok(status) if status.success() => Some(path),
+            _ => {
+                eprintln!("ffmpeg with libx264 not available, skipping");
+                None
+            },
+        }
+    }
+
+    fn write(&self, file_name: &str, content: &[u8]) -> PathBuf {
+        let path = self.path(file_name);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+}
+
+impl Drop for Fixtures {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Path in the temporary directory unique to each call, the tests of a binary run in parallel
+pub fn temp_path(name: &str) -> PathBuf {
+    static PATH_COUNT: AtomicUsize = AtomicUsize::new(0);
+    let path_index = PATH_COUNT.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir().join(format!("{}_{}_{path_index}_{name}", env!("CARGO_PKG_NAME"), std::process::id()))
+}
+
+/// Header of a DJI OSD file of the specified format version
+pub fn dji_header(kind: Kind, font_variant: FontVariant, format_version: u16) -> Vec<u8> {
+    let osd_dimensions = kind.dimensions_tiles();
+    let tile_dimensions = kind.tile_kind().dimensions();
+    let mut content = b"MSPOSD\x00".to_vec();
+    content.extend(format_version.to_le_bytes());
+    content.extend([osd_dimensions.width as u8, osd_dimensions.height as u8, tile_dimensions.width as u8, tile_dimensions.height as u8]);
+    content.extend(0u16.to_le_bytes());
+    content.extend(0u16.to_le_bytes());
+    content.push(font_variant_id(font_variant));
+    content
+}
+
+/// Frame record of a DJI OSD file, `extra_data` is stored after the tile indices and counted in the data length
+pub fn dji_frame(frame_index: u32, tile_indices: &[u16], extra_data: &[u16]) -> Vec<u8> {
+    let mut content = frame_index.to_le_bytes().to_vec();
+    content.extend(((tile_indices.len() + extra_data.len()) as u32).to_le_bytes());
+    content.extend(tile_indices.iter().chain(extra_data).flat_map(|tile_index| tile_index.to_le_bytes()));
+    content
+}
+
+/// Tile indices of a DJI OSD frame displaying `tile_index` at the top left corner
+///
+/// The payload is always the FakeHD grid stored column by column whatever the OSD kind is.
+pub fn dji_frame_tile_indices(tile_index: u16) -> Vec<u16> {
+    let mut tile_indices = vec![0; tile_indices::COUNT];
+    tile_indices[0] = tile_index;
+    tile_indices
+}
+
+/// Content of a Walksnail Avatar OSD file, the grid of each frame being stored row by row
+///
+/// The newer firmwares store 32 bit tile indices and pad the frame records with `padding` bytes.
+pub fn wsa_file_content(font_variant_id: &[u8; 4], tile_indices_32_bit: bool, padding: usize, frames: &BTreeMap<u32, Vec<u16>>) -> Vec<u8> {
+    let mut content = font_variant_id.to_vec();
+    content.extend([0; 32]);
+    content.extend((wsa::DIMENSIONS.width as u16).to_le_bytes());
+    content.extend((wsa::DIMENSIONS.height as u16).to_le_bytes());
+    for (frame_index, tile_indices) in frames {
+        let timestamp = (*frame_index as f64 * WSA_FRAME_DURATION_MS).round() as u32;
+        content.extend(timestamp.to_le_bytes());
+        for tile_index in tile_indices {
+            if tile_indices_32_bit {
+                content.extend((*tile_index as u32).to_le_bytes());
+            } else {
+                content.extend(tile_index.to_le_bytes());
+            }
+        }
+        content.extend(std::iter::repeat(0).take(padding));
+    }
+    content
+}
+
+/// Whether the pixel at the specified position of an RGBA frame is opaque
+pub fn is_opaque(frame: &[u8], frame_width: u32, x: u32, y: u32) -> bool {
+    frame[(y * frame_width + x) as usize * 4 + 3] == 0xff
+}
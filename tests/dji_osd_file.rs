@@ -0,0 +1,114 @@
+mod common;
+
+use std::path::PathBuf;
+
+use hd_fpv_video_tool::osd::{repair::repair_osd_file, dji::file::{OpenError, Reader, Writer}, file::{Frame, GenericReader}, FontVariant, Kind, TileIndices};
+
+use common::{dji_frame as frame, dji_frame_tile_indices as frame_tile_indices};
+
+
+// header of a FakeHD OSD file with the INAV font variant
+fn header(format_version: u16) -> Vec<u8> {
+    common::dji_header(Kind::DJI_FakeHD, FontVariant::INAV, format_version)
+}
+
+fn write_sample_file(name: &str, content: &[u8]) -> PathBuf {
+    let path = common::temp_path(&format!("{name}.osd"));
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+fn read_frames(name: &str, content: &[u8]) -> (u16, Vec<(u32, u16)>) {
+    let path = write_sample_file(name, content);
+    let mut reader = Reader::open(&path).unwrap();
+    let format_version = *reader.header().format_version();
+    assert!(matches!(reader.osd_kind(), Kind::DJI_FakeHD));
+    let frames = reader.frames().unwrap().iter().map(|frame| (frame.index(), frame.tile_indices()[0])).collect();
+    std::fs::remove_file(path).unwrap();
+    (format_version, frames)
+}
+
+#[test]
+fn reads_format_version_1() {
+    let mut content = header(1);
+    content.extend(frame(0, &frame_tile_indices(1), &[]));
+    content.extend(frame(3, &frame_tile_indices(2), &[]));
+    assert_eq!(read_frames("v1", &content), (1, vec![(0, 1), (3, 2)]));
+}
+
+#[test]
+fn reads_format_version_2() {
+    let mut content = header(2);
+    content.extend(frame(0, &frame_tile_indices(1), &[]));
+    content.extend(frame(3, &frame_tile_indices(2), &[]));
+    assert_eq!(read_frames("v2", &content), (2, vec![(0, 1), (3, 2)]));
+}
+
+#[test]
+fn skips_per_frame_extensions() {
+    let mut content = header(2);
+    content.extend(frame(0, &frame_tile_indices(1), &[7, 7, 7]));
+    content.extend(frame(3, &frame_tile_indices(2), &[]));
+    assert_eq!(read_frames("frame_extension", &content), (2, vec![(0, 1), (3, 2)]));
+}
+
+#[test]
+fn rejects_unknown_format_version() {
+    let path = write_sample_file("unknown_version", &header(99));
+    let result = Reader::open(&path);
+    std::fs::remove_file(path).unwrap();
+    assert!(matches!(result, Err(OpenError::UnsupportedFileFormatVersion(99))));
+}
+
+#[test]
+fn reads_back_written_file() {
+    let path = write_sample_file("written", &[]);
+    let mut writer = Writer::create(&path, Kind::DJI_FakeHD, FontVariant::INAV).unwrap();
+    for (frame_index, first_tile_index) in [(0, 1), (3, 2)] {
+        writer.write_frame(&Frame::new(frame_index, TileIndices::new(frame_tile_indices(first_tile_index)))).unwrap();
+    }
+    writer.finish().unwrap();
+    let content = std::fs::read(&path).unwrap();
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(content, [header(1), frame(0, &frame_tile_indices(1), &[]), frame(3, &frame_tile_indices(2), &[])].concat());
+}
+
+#[test]
+fn repairs_truncated_file() {
+    let mut content = header(1);
+    content.extend(frame(0, &frame_tile_indices(1), &[]));
+    content.extend(frame(3, &frame_tile_indices(0xffff), &[]));
+    content.extend(frame(3, &frame_tile_indices(2), &[]));
+    content.extend(frame(2, &frame_tile_indices(3), &[]));
+    content.extend(frame(100_000, &frame_tile_indices(4), &[]));
+    content.extend(&frame(5, &frame_tile_indices(5), &[])[..100]);
+    let path = write_sample_file("damaged", &content);
+    let repaired_path = path.with_extension("repaired.osd");
+    let summary = repair_osd_file(&path, &repaired_path, 3600, true, true).unwrap();
+    std::fs::remove_file(path).unwrap();
+    let repaired_content = std::fs::read(&repaired_path).unwrap();
+    std::fs::remove_file(repaired_path).unwrap();
+    assert!(summary.truncated());
+    assert_eq!((summary.kept_frames(), summary.invalid_tile_index_frames(), summary.frame_index_jump_frames(), summary.renumbered_duplicate_frames()), (3, 1, 1, 1));
+    assert_eq!(repaired_content, [header(1), frame(0, &frame_tile_indices(1), &[]), frame(3, &frame_tile_indices(2), &[]),
+        frame(4, &frame_tile_indices(3), &[])].concat());
+}
+
+#[test]
+fn repairs_file_with_corrupt_first_frame() {
+    let mut content = header(1);
+    content.extend(frame(4_000_000_000, &frame_tile_indices(1), &[]));
+    for frame_index in 0..4 {
+        content.extend(frame(frame_index, &frame_tile_indices(2), &[]));
+    }
+    let path = write_sample_file("corrupt_first_frame", &content);
+    let repaired_path = path.with_extension("repaired.osd");
+    let summary = repair_osd_file(&path, &repaired_path, 3600, true, true).unwrap();
+    std::fs::remove_file(path).unwrap();
+    let repaired_content = std::fs::read(&repaired_path).unwrap();
+    std::fs::remove_file(repaired_path).unwrap();
+    assert!(! summary.truncated());
+    assert_eq!((summary.kept_frames(), summary.frame_index_jump_frames(), summary.renumbered_duplicate_frames()), (4, 1, 0));
+    let expected_frames = (0..4).map(|frame_index| frame(frame_index, &frame_tile_indices(2), &[]));
+    assert_eq!(repaired_content, std::iter::once(header(1)).chain(expected_frames).collect::<Vec<_>>().concat());
+}
@@ -0,0 +1,134 @@
+mod common;
+
+use clap::Parser;
+
+use hd_fpv_video_tool::{
+    file,
+    cli::transcode_video_args::{TranscodeVideoArgs, TranscodeVideoOSDArgs},
+    osd::{
+        self,
+        file::{GenericReader, SortedUniqFrames},
+        overlay::{Generator, OverlayVideoCodec, scaling::Scaling, video_timeline::OSDSpeed},
+        tile_resize::ScalingFilter,
+        FontDir, FontVariant, Kind,
+    },
+    video::{self, resolution::TargetResolution, Resolution},
+};
+
+use common::{is_opaque, Fixtures};
+
+
+fn read_frames(path: &std::path::Path) -> SortedUniqFrames {
+    osd::file::open(path).unwrap().frames().unwrap()
+}
+
+fn generator(frames: SortedUniqFrames, fixtures: &Fixtures, scaling: Scaling) -> Generator<'static> {
+    Generator::new(frames, FontVariant::Generic, &FontDir::new(fixtures.font_dir()), &None, scaling, &[], false, &[]).unwrap()
+}
+
+fn scaling_to(width: u32, height: u32) -> Scaling {
+    Scaling::Yes {
+        target_resolution: TargetResolution::Custom(Resolution::new(width, height)),
+        min_margins: "20:20".parse().unwrap(),
+        filter: ScalingFilter::default(),
+    }
+}
+
+#[test]
+fn reads_synthetic_osd_files() {
+    let fixtures = Fixtures::new("read");
+
+    let frames = read_frames(&fixtures.dji_osd_file("DJIG0000.osd", Kind::DJI_FakeHD, FontVariant::INAV, &[0, 30, 60], 1));
+    assert!(matches!(frames.kind(), Kind::DJI_FakeHD));
+    assert_eq!(frames.font_variant(), FontVariant::INAV);
+    assert_eq!(frames.iter().map(|frame| (frame.index(), frame.tile_indices()[0])).collect::<Vec<_>>(), vec![(0, 1), (30, 1), (60, 1)]);
+
+    let frames = read_frames(&fixtures.wsa_osd_file("AvatarG0000.osd", b"ARDU", &[0, 30, 60], 2));
+    assert!(matches!(frames.kind(), Kind::WSA));
+    assert_eq!(frames.font_variant(), FontVariant::Ardupilot);
+    assert_eq!(frames.iter().map(|frame| (frame.index(), frame.tile_indices()[0])).collect::<Vec<_>>(), vec![(0, 2), (30, 2), (60, 2)]);
+}
+
+#[test]
+fn renders_overlay_frames_with_native_resolution() {
+    let fixtures = Fixtures::new("render");
+    for (file_name, kind) in [("DJIG0000.osd", Kind::DJI_SD), ("DJIG0001.osd", Kind::DJI_FakeHD), ("DJIG0002.osd", Kind::DJI_HD)] {
+        let frames = read_frames(&fixtures.dji_osd_file(file_name, kind, FontVariant::Generic, &[0, 60], 1));
+        let generator = generator(frames, &fixtures, Scaling::No { target_resolution: None });
+        let tile_dimensions = kind.tile_kind().dimensions();
+        let expected_width = kind.dimensions_tiles().width * tile_dimensions.width;
+        let expected_height = kind.dimensions_tiles().height * tile_dimensions.height;
+        let frame = generator.render_osd_frame(1).unwrap().unwrap();
+        assert_eq!((frame.width(), frame.height()), (expected_width, expected_height), "{kind}");
+        assert!(is_opaque(&frame, frame.width(), 0, 0), "{kind}");
+        assert!(! is_opaque(&frame, frame.width(), tile_dimensions.width, 0), "{kind}");
+        assert!(generator.render_osd_frame(2).is_none());
+    }
+}
+
+// Walksnail Avatar OSD files have their own grid dimensions which must be taken into account when scaling
+#[test]
+fn scales_walksnail_overlay_to_target_resolution() {
+    let fixtures = Fixtures::new("wsa_scaling");
+    let frames = read_frames(&fixtures.wsa_osd_file("AvatarG0000.osd", b"INAV", &[0], 1));
+    for (width, height) in [(1280, 720), (1920, 1080)] {
+        let generator = generator(frames.clone(), &fixtures, scaling_to(width, height));
+        let frame = generator.render_osd_frame(0).unwrap().unwrap();
+        assert!(frame.width() <= width && frame.height() <= height, "{}x{} overlay for {width}x{height}", frame.width(), frame.height());
+        let tile_width = frame.width() / Kind::WSA.dimensions_tiles().width;
+        assert_eq!(tile_width * Kind::WSA.dimensions_tiles().width, frame.width());
+        assert!(is_opaque(&frame, frame.width(), 0, 0));
+        assert!(! is_opaque(&frame, frame.width(), tile_width + 1, 0));
+    }
+}
+
+#[tokio::test]
+async fn generates_overlay_video() {
+    let fixtures = Fixtures::new("overlay_video");
+    if fixtures.video("probe.mp4", 64, 64, 60, 0.1).is_none() { return }
+    let frames = read_frames(&fixtures.dji_osd_file("DJIG0000.osd", Kind::DJI_SD, FontVariant::Generic, &[0, 30, 60], 1));
+    let mut generator = generator(frames, &fixtures, Scaling::No { target_resolution: None });
+    let output_video_file = fixtures.path("overlay.webm");
+    generator.generate_overlay_video(OverlayVideoCodec::Vp8, None, None, None, &output_video_file, 0, OSDSpeed::NORMAL, false).await.unwrap();
+    assert!(! file::temp_output_path(&output_video_file).exists());
+    let video_info = video::probe(&output_video_file).unwrap();
+    let tile_dimensions = Kind::DJI_SD.tile_kind().dimensions();
+    assert_eq!(video_info.resolution(), Resolution::new(Kind::DJI_SD.dimensions_tiles().width * tile_dimensions.width,
+        Kind::DJI_SD.dimensions_tiles().height * tile_dimensions.height));
+}
+
+#[derive(Parser)]
+#[clap(no_binary_name = true)]
+struct TranscodeVideoCommand {
+    #[clap(flatten)]
+    osd_args: TranscodeVideoOSDArgs,
+
+    #[clap(flatten)]
+    transcode_args: TranscodeVideoArgs,
+}
+
+// the overlay must be scaled or placed to match the video resolution whatever the OSD kind is
+#[tokio::test]
+async fn transcodes_video_with_osd_keeping_video_resolution() {
+    let fixtures = Fixtures::new("transcode");
+    let font_dir = fixtures.font_dir();
+    let cases = [
+        ("DJIG0000.osd", Kind::DJI_FakeHD, 1280, 720),
+        ("DJIG0001.osd", Kind::DJI_SD, 1280, 720),
+        ("DJIG0002.osd", Kind::DJI_HD, 1920, 1080),
+    ];
+    for (osd_file_name, kind, width, height) in cases {
+        let Some(input_video_file) = fixtures.video(&format!("{osd_file_name}.mp4"), width, height, 60, 0.5) else { return };
+        let osd_file = fixtures.dji_osd_file(osd_file_name, kind, FontVariant::Generic, &[0, 15], 1);
+        let output_video_file = fixtures.path(&format!("{osd_file_name}_with_osd.mp4"));
+        let command = TranscodeVideoCommand::try_parse_from([
+            "--osd-file", osd_file.to_str().unwrap(),
+            "--osd-font-dir", font_dir.to_str().unwrap(),
+            "--video-encoder", "libx264",
+            input_video_file.to_str().unwrap(), output_video_file.to_str().unwrap(),
+        ]).unwrap();
+        video::transcode_with_osd_args(&command.transcode_args, &command.osd_args).await.unwrap();
+        let video_info = video::probe(&output_video_file).unwrap();
+        assert_eq!(video_info.resolution(), Resolution::new(width, height), "{kind}");
+    }
+}
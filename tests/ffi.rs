@@ -0,0 +1,115 @@
+mod common;
+
+use std::{ffi::CStr, ptr};
+
+use hd_fpv_video_tool::{ffi::*, osd::{FontVariant, Kind}};
+
+use common::Fixtures;
+
+
+fn last_error() -> String {
+    let message = hd_fpv_last_error();
+    assert!(! message.is_null());
+    unsafe { CStr::from_ptr(message) }.to_string_lossy().into_owned()
+}
+
+fn parse(content: &[u8]) -> *mut HdFpvOSDFile {
+    unsafe { hd_fpv_osd_file_parse(content.as_ptr(), content.len()) }
+}
+
+#[test]
+fn parses_osd_files_held_in_memory() {
+    let fixtures = Fixtures::new("ffi_parse");
+    for (path, kind) in [
+        (fixtures.dji_osd_file("DJIG0000.osd", Kind::DJI_FakeHD, FontVariant::INAV, &[0, 30, 60], 1), Kind::DJI_FakeHD),
+        (fixtures.wsa_osd_file("AvatarG0000.osd", b"INAV", &[0, 30, 60], 1), Kind::WSA),
+    ] {
+        let osd_file = parse(&std::fs::read(path).unwrap());
+        assert!(! osd_file.is_null());
+        unsafe {
+            assert_eq!(hd_fpv_osd_file_frame_count(osd_file), 3);
+            let (mut width, mut height) = (0, 0);
+            assert_eq!(hd_fpv_osd_file_dimensions_tiles(osd_file, &mut width, &mut height), 0);
+            assert_eq!((width, height), (kind.dimensions_tiles().width, kind.dimensions_tiles().height));
+            assert_eq!(hd_fpv_osd_file_frame_index(osd_file, 1), 30);
+
+            // the number of tile indices of the frame is returned even when the buffer is smaller
+            let mut tile_indices = [0xffff; 2];
+            let tile_count = hd_fpv_osd_file_frame_tile_indices(osd_file, 2, tile_indices.as_mut_ptr(), tile_indices.len());
+            assert!(tile_count > tile_indices.len() as i64);
+            assert_eq!(tile_indices, [1, 0]);
+            assert_eq!(hd_fpv_osd_file_frame_tile_indices(osd_file, 0, ptr::null_mut(), 0), tile_count);
+
+            hd_fpv_osd_file_free(osd_file);
+        }
+    }
+}
+
+#[test]
+fn invalid_content_is_an_error() {
+    assert!(parse(b"not an OSD file").is_null());
+    assert!(! last_error().is_empty());
+    assert!(unsafe { hd_fpv_osd_file_parse(ptr::null(), 16) }.is_null());
+    assert_eq!(last_error(), "data is NULL");
+}
+
+#[test]
+fn null_pointers_and_out_of_range_positions_are_errors() {
+    let fixtures = Fixtures::new("ffi_errors");
+    let osd_file = parse(&std::fs::read(fixtures.dji_osd_file("DJIG0000.osd", Kind::DJI_SD, FontVariant::Generic, &[0], 1)).unwrap());
+    unsafe {
+        assert_eq!(hd_fpv_osd_file_frame_count(ptr::null()), -1);
+        assert_eq!(last_error(), "osd_file is NULL");
+
+        let mut width = 0;
+        assert_eq!(hd_fpv_osd_file_dimensions_tiles(osd_file, &mut width, ptr::null_mut()), -1);
+        assert_eq!(last_error(), "height is NULL");
+
+        assert_eq!(hd_fpv_osd_file_frame_tile_indices(osd_file, 0, ptr::null_mut(), 4), -1);
+        assert_eq!(last_error(), "tile_indices is NULL");
+
+        assert_eq!(hd_fpv_osd_file_frame_index(osd_file, 1), -1);
+        assert_eq!(last_error(), "OSD frame position out of range: 1");
+
+        assert!(hd_fpv_osd_file_open(ptr::null()).is_null());
+        assert_eq!(last_error(), "path is NULL");
+
+        hd_fpv_osd_file_free(osd_file);
+        hd_fpv_osd_file_free(ptr::null_mut());
+    }
+}
+
+#[cfg(feature = "overlay")]
+#[test]
+fn renders_overlay_frames() {
+    let fixtures = Fixtures::new("ffi_render");
+    let osd_file_path = fixtures.dji_osd_file("DJIG0000.osd", Kind::DJI_SD, FontVariant::Generic, &[0, 60], 1);
+    let osd_file_path = std::ffi::CString::new(osd_file_path.to_str().unwrap()).unwrap();
+    let font_dir = std::ffi::CString::new(fixtures.font_dir().to_str().unwrap()).unwrap();
+    unsafe {
+        let osd_file = hd_fpv_osd_file_open(osd_file_path.as_ptr());
+        assert!(! osd_file.is_null());
+        let renderer = hd_fpv_overlay_renderer_new(osd_file, font_dir.as_ptr());
+        hd_fpv_osd_file_free(osd_file);
+        assert!(! renderer.is_null());
+
+        let (mut width, mut height) = (0, 0);
+        assert_eq!(hd_fpv_overlay_renderer_frame_dimensions(renderer, &mut width, &mut height), 0);
+        let mut buffer = vec![0; (width * height * 4) as usize];
+
+        assert_eq!(hd_fpv_overlay_renderer_render(renderer, 1, buffer.as_mut_ptr(), buffer.len() - 1), -1);
+        assert!(last_error().starts_with("buffer too small"));
+        assert_eq!(hd_fpv_overlay_renderer_render(renderer, 1, ptr::null_mut(), buffer.len()), -1);
+        assert_eq!(last_error(), "buffer is NULL");
+        assert_eq!(hd_fpv_overlay_renderer_render(renderer, 2, buffer.as_mut_ptr(), buffer.len()), -1);
+
+        assert_eq!(hd_fpv_overlay_renderer_render(renderer, 1, buffer.as_mut_ptr(), buffer.len()), 0);
+        assert!(common::is_opaque(&buffer, width, 0, 0));
+        assert!(! common::is_opaque(&buffer, width, Kind::DJI_SD.tile_kind().dimensions().width, 0));
+
+        assert!(hd_fpv_overlay_renderer_new(ptr::null(), font_dir.as_ptr()).is_null());
+        assert_eq!(last_error(), "osd_file is NULL");
+
+        hd_fpv_overlay_renderer_free(renderer);
+    }
+}
@@ -0,0 +1,140 @@
+mod common;
+
+use std::collections::BTreeMap;
+
+use proptest::{prelude::*, sample::select};
+
+use hd_fpv_video_tool::osd::{dji, wsa, file::{Frame, GenericReader}, tile_indices, FontVariant, Kind, TileIndices};
+
+
+const DJI_KINDS: [Kind; 3] = [Kind::DJI_SD, Kind::DJI_FakeHD, Kind::DJI_HD];
+const FONT_VARIANTS: [FontVariant; 5] = [FontVariant::Generic, FontVariant::Betaflight, FontVariant::INAV, FontVariant::Ardupilot, FontVariant::KISSUltra];
+// the WSA frame timestamps are in milliseconds, the frame indices are only kept by the roundtrip below this value
+const MAX_WSA_FRAME_INDEX: u32 = 1_000_000;
+
+fn dji_frames() -> impl Strategy<Value = BTreeMap<u32, Vec<u16>>> {
+    prop::collection::btree_map(any::<u32>(), prop::collection::vec(0..512u16, tile_indices::COUNT), 0..8)
+}
+
+fn wsa_frames() -> impl Strategy<Value = BTreeMap<u32, Vec<u16>>> {
+    let tile_count = (wsa::DIMENSIONS.width * wsa::DIMENSIONS.height) as usize;
+    prop::collection::btree_map(0..MAX_WSA_FRAME_INDEX, prop::collection::vec(0..512u16, tile_count), 0..8)
+}
+
+// frames following each other like in actual recordings, the timestamps are higher than any value read from the tile
+// indices so that the layout of the revision files cannot be mistaken for another one, at least two frames are needed to
+// tell the layout of the revision files
+fn wsa_recording_frames() -> impl Strategy<Value = BTreeMap<u32, Vec<u16>>> {
+    let tile_count = (wsa::DIMENSIONS.width * wsa::DIMENSIONS.height) as usize;
+    let frames = prop::collection::vec((1..4u32, prop::collection::vec(0..512u16, tile_count)), 2..8);
+    (2_100_000..3_000_000u32, frames).prop_map(|(first_frame_index, frames)|
+        frames.into_iter().scan(first_frame_index, |frame_index, (interval, tile_indices)| {
+            *frame_index += interval;
+            Some((*frame_index, tile_indices))
+        }).collect()
+    )
+}
+
+fn dji_file_content(kind: Kind, font_variant: FontVariant, frames: &BTreeMap<u32, Vec<u16>>) -> Vec<u8> {
+    let path = common::temp_path("proptest.osd");
+    let mut writer = dji::file::Writer::create(&path, kind, font_variant).unwrap();
+    for (frame_index, tile_indices) in frames {
+        writer.write_frame(&Frame::new(*frame_index, TileIndices::new(tile_indices.clone()))).unwrap();
+    }
+    writer.finish().unwrap();
+    let content = std::fs::read(&path).unwrap();
+    std::fs::remove_file(path).unwrap();
+    content
+}
+
+fn wsa_file_content(font_variant_id: &[u8; 4], frames: &BTreeMap<u32, Vec<u16>>) -> Vec<u8> {
+    common::wsa_file_content(font_variant_id, false, 0, frames)
+}
+
+fn wsa_tile_index_at(tile_indices: &[u16], x: u32, y: u32) -> u16 {
+    if x < wsa::DIMENSIONS.width && y < wsa::DIMENSIONS.height {
+        tile_indices[(y * wsa::DIMENSIONS.width + x) as usize]
+    } else {
+        0
+    }
+}
+
+proptest! {
+
+    #[test]
+    fn dji_files_roundtrip(kind in select(&DJI_KINDS[..]), font_variant in select(&FONT_VARIANTS[..]), frames in dji_frames()) {
+        let parsed = dji::file::parse(&dji_file_content(kind, font_variant, &frames)).unwrap();
+        prop_assert_eq!(parsed.kind().to_string(), kind.to_string());
+        prop_assert_eq!(parsed.font_variant(), font_variant);
+        let parsed_frames = parsed.iter().map(|frame| (frame.index(), frame.tile_indices().to_vec())).collect::<BTreeMap<_, _>>();
+        prop_assert_eq!(parsed_frames, frames);
+    }
+
+    #[test]
+    fn dji_parse_matches_reader(kind in select(&DJI_KINDS[..]), frames in dji_frames()) {
+        let content = dji_file_content(kind, FontVariant::INAV, &frames);
+        let path = common::temp_path("proptest.osd");
+        std::fs::write(&path, &content).unwrap();
+        let read_frames = dji::file::Reader::open(&path).unwrap().frames().unwrap();
+        std::fs::remove_file(path).unwrap();
+        prop_assert_eq!(dji::file::parse(&content).unwrap().to_vec(), read_frames.to_vec());
+    }
+
+    #[test]
+    fn wsa_files_roundtrip(frames in wsa_frames()) {
+        let parsed = wsa::file::parse(&wsa_file_content(b"ARDU", &frames)).unwrap();
+        prop_assert_eq!(parsed.font_variant(), FontVariant::Ardupilot);
+        prop_assert_eq!(parsed.len(), frames.len());
+        for (parsed_frame, (frame_index, tile_indices)) in parsed.iter().zip(&frames) {
+            prop_assert_eq!(parsed_frame.index(), *frame_index);
+            for (coordinates, tile_index) in parsed_frame.enumerate_tile_indices() {
+                prop_assert_eq!(tile_index, wsa_tile_index_at(tile_indices, coordinates.x as u32, coordinates.y as u32));
+            }
+        }
+    }
+
+    #[test]
+    fn wsa_revision_files_roundtrip(tile_indices_32_bit in any::<bool>(), padding in (0..=32usize).prop_map(|padding| padding * 2),
+            frames in wsa_recording_frames()) {
+        let parsed = wsa::file::parse(&common::wsa_file_content(b"INAV", tile_indices_32_bit, padding, &frames)).unwrap();
+        let original_parsed = wsa::file::parse(&wsa_file_content(b"INAV", &frames)).unwrap();
+        prop_assert_eq!(parsed.to_vec(), original_parsed.to_vec());
+    }
+
+    #[test]
+    fn truncated_dji_files_are_rejected(kind in select(&DJI_KINDS[..]), frames in dji_frames(), cut in any::<prop::sample::Index>()) {
+        let content = dji_file_content(kind, FontVariant::Generic, &frames);
+        let frame_len = 8 + tile_indices::COUNT * 2;
+        let cut = cut.index(content.len());
+        let header_len = content.len() - frames.len() * frame_len;
+        let result = dji::file::parse(&content[..cut]);
+        prop_assert_eq!(result.is_ok(), cut >= header_len && (cut - header_len) % frame_len == 0);
+    }
+
+    #[test]
+    fn truncated_wsa_files_are_rejected(frames in wsa_frames(), cut in any::<prop::sample::Index>()) {
+        let content = wsa_file_content(b"INAV", &frames);
+        let cut = cut.index(content.len());
+        let frame_len = 4 + (wsa::DIMENSIONS.width * wsa::DIMENSIONS.height) as usize * 2;
+        let result = wsa::file::parse(&content[..cut]);
+        prop_assert_eq!(result.is_ok(), cut >= 40 && (cut - 40) % frame_len == 0);
+    }
+
+    #[test]
+    fn parsers_do_not_panic_on_arbitrary_data(data in prop::collection::vec(any::<u8>(), 0..8192)) {
+        let _ = dji::file::parse(&data);
+        let _ = wsa::file::parse(&data);
+    }
+
+    #[test]
+    fn dji_parser_does_not_panic_on_arbitrary_frames(kind in select(&DJI_KINDS[..]), data in prop::collection::vec(any::<u8>(), 0..8192)) {
+        let mut content = dji_file_content(kind, FontVariant::Generic, &BTreeMap::new());
+        content.extend(data);
+        if let Ok(frames) = dji::file::parse(&content) {
+            for frame in frames.iter() {
+                prop_assert_eq!(frame.tile_indices().len(), tile_indices::COUNT);
+            }
+        }
+    }
+
+}